@@ -0,0 +1,148 @@
+use serde::de::DeserializeOwned;
+use std::cell::{Cell, RefCell};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{ReadableStreamDefaultReader, Response};
+
+// Retries after the first attempt, for flaky mobile connections
+pub const DEFAULT_RETRIES: u32 = 2;
+const RETRY_DELAY_MS: i32 = 300;
+
+thread_local! {
+    // Page callback receiving (stage, loaded, total, url) for a loading screen
+    static LOADING_CALLBACK: RefCell<Option<js_sys::Function>> = RefCell::new(None);
+    // Image textures requested and finished since the last "ready"
+    static TEXTURES: Cell<(u32, u32)> = const { Cell::new((0, 0)) };
+    static AWAITING_READY: Cell<bool> = const { Cell::new(false) };
+}
+
+pub fn set_loading_callback(callback: Option<js_sys::Function>) {
+    LOADING_CALLBACK.with(|cb| *cb.borrow_mut() = callback);
+}
+
+// Total is 0 while the size isn't known yet
+pub fn report_loading(stage: &str, loaded: f64, total: f64) {
+    report(stage, loaded, total, JsValue::UNDEFINED);
+}
+
+// Downloads also pass the url being fetched
+fn report(stage: &str, loaded: f64, total: f64, url: JsValue) {
+    let callback = LOADING_CALLBACK.with(|cb| cb.borrow().clone());
+    if let Some(callback) = callback {
+        callback.call4(&JsValue::NULL, &JsValue::from_str(stage), &JsValue::from(loaded), &JsValue::from(total), &url).ok();
+    }
+}
+
+pub fn texture_requested() {
+    let (done, requested) = TEXTURES.with(|t| t.get());
+    TEXTURES.with(|t| t.set((done, requested + 1)));
+    report_loading("texture", done as f64, (requested + 1) as f64);
+}
+
+// Failed images count as finished too, a missing texture shouldn't hold the spinner forever
+pub fn texture_finished() {
+    let (done, requested) = TEXTURES.with(|t| t.get());
+    let done = (done + 1).min(requested);
+    TEXTURES.with(|t| t.set((done, requested)));
+    report_loading("texture", done as f64, requested as f64);
+    if done == requested && AWAITING_READY.with(|a| a.replace(false)) {
+        send_ready();
+    }
+}
+
+// Called once a game is running: "ready" goes out now, or when its last texture arrives
+pub fn finish_loading() {
+    let (done, requested) = TEXTURES.with(|t| t.get());
+    if done == requested {
+        send_ready();
+    } else {
+        AWAITING_READY.with(|a| a.set(true));
+    }
+}
+
+fn send_ready() {
+    TEXTURES.with(|t| t.set((0, 0)));
+    report_loading("ready", 1.0, 1.0);
+}
+
+pub async fn fetch_bytes(stage: &str, url: &str) -> Result<Vec<u8>, JsValue> {
+    fetch_bytes_with_retries(stage, url, DEFAULT_RETRIES).await
+}
+
+// Server errors and network failures are retried, a missing file isn't
+pub async fn fetch_bytes_with_retries(stage: &str, url: &str, retries: u32) -> Result<Vec<u8>, JsValue> {
+    let mut attempt = 0;
+    loop {
+        match fetch_once(stage, url).await {
+            Ok(bytes) => return Ok(bytes),
+            Err(FetchError::Fatal(e)) => return Err(e),
+            Err(FetchError::Retryable(e)) if attempt >= retries => return Err(e),
+            Err(FetchError::Retryable(_)) => {
+                attempt += 1;
+                sleep(RETRY_DELAY_MS * attempt as i32).await?;
+            }
+        }
+    }
+}
+
+pub async fn fetch_json<T: DeserializeOwned>(stage: &str, url: &str) -> Result<T, JsValue> {
+    let bytes = fetch_bytes(stage, url).await?;
+    serde_json::from_slice(&bytes).map_err(|e| JsValue::from_str(&format!("{} isn't valid: {}", url, e)))
+}
+
+enum FetchError {
+    Retryable(JsValue),
+    Fatal(JsValue),
+}
+
+async fn fetch_once(stage: &str, url: &str) -> Result<Vec<u8>, FetchError> {
+    let window = web_sys::window().ok_or_else(|| FetchError::Fatal("No window".into()))?;
+    let response: Response = JsFuture::from(window.fetch_with_str(url)).await
+        .and_then(|value| value.dyn_into())
+        .map_err(|_| FetchError::Retryable(JsValue::from_str(&format!("Couldn't reach {}", url))))?;
+    if !response.ok() {
+        let message = JsValue::from_str(&format!("Fetching {} failed: HTTP {}", url, response.status()));
+        return Err(if response.status() >= 500 { FetchError::Retryable(message) } else { FetchError::Fatal(message) });
+    }
+    read_body(stage, url, &response).await
+        .map_err(|_| FetchError::Retryable(JsValue::from_str(&format!("Download of {} was interrupted", url))))
+}
+
+// Streamed chunk by chunk when someone listens for progress, in one piece otherwise
+async fn read_body(stage: &str, url: &str, response: &Response) -> Result<Vec<u8>, JsValue> {
+    let listening = LOADING_CALLBACK.with(|cb| cb.borrow().is_some());
+    let url = JsValue::from_str(url);
+    let body = match response.body() {
+        Some(body) if listening => body,
+        _ => {
+            let buffer = JsFuture::from(response.array_buffer()?).await?;
+            let bytes = js_sys::Uint8Array::new(&buffer).to_vec();
+            report(stage, bytes.len() as f64, bytes.len() as f64, url);
+            return Ok(bytes);
+        }
+    };
+    let total = response.headers().get("Content-Length").ok().flatten()
+        .and_then(|length| length.parse::<f64>().ok());
+    let reader: ReadableStreamDefaultReader = body.get_reader().unchecked_into();
+    let mut bytes = Vec::new();
+    loop {
+        let chunk = JsFuture::from(reader.read()).await?;
+        if js_sys::Reflect::get(&chunk, &"done".into())?.as_bool().unwrap_or(true) {
+            break;
+        }
+        let value: js_sys::Uint8Array = js_sys::Reflect::get(&chunk, &"value".into())?.dyn_into()?;
+        bytes.extend(value.to_vec());
+        report(stage, bytes.len() as f64, total.unwrap_or(0.0), url.clone());
+    }
+    report(stage, bytes.len() as f64, bytes.len() as f64, url);
+    Ok(bytes)
+}
+
+async fn sleep(ms: i32) -> Result<(), JsValue> {
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        if let Some(window) = web_sys::window() {
+            window.set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, ms).ok();
+        }
+    });
+    JsFuture::from(promise).await.map(|_| ())
+}
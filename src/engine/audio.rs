@@ -0,0 +1,260 @@
+use crate::engine::assets;
+use crate::engine::settings::Settings;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{AudioBuffer, AudioContext, AudioContextState, AudioScheduledSourceNode, BiquadFilterNode, BiquadFilterType, GainNode, OscillatorType};
+
+thread_local! {
+    // Decoded samples by name, shared by every game since buffers outlive their context
+    static SAMPLES: RefCell<HashMap<String, AudioBuffer>> = RefCell::new(HashMap::new());
+    // The page's one context, made for the first game that wants sound and kept after it
+    static SHARED: RefCell<Option<Shared>> = const { RefCell::new(None) };
+    static UNLOCKED: Cell<bool> = const { Cell::new(false) };
+    static MUTED: Cell<bool> = const { Cell::new(false) };
+}
+
+// Every game's sounds meet in output, which alone carries the volume, on/off and mute
+struct Shared {
+    ctx: AudioContext,
+    output: GainNode,
+    noise: AudioBuffer,
+}
+
+impl Shared {
+    fn new() -> Result<Self, JsValue> {
+        let ctx = AudioContext::new()?;
+        let output = ctx.create_gain()?;
+        output.connect_with_audio_node(&ctx.destination())?;
+
+        // One second of white noise, reused by every noise-based sound
+        let rate = ctx.sample_rate();
+        let noise = ctx.create_buffer(1, rate as u32, rate)?;
+        let samples: Vec<f32> = (0..rate as usize).map(|_| js_sys::Math::random() as f32 * 2.0 - 1.0).collect();
+        noise.copy_to_channel(&samples, 0)?;
+
+        if UNLOCKED.with(Cell::get) {
+            ctx.resume().ok();
+        }
+        let shared = Shared { ctx, output, noise };
+        shared.apply_settings();
+        Ok(shared)
+    }
+
+    fn apply_settings(&self) {
+        let settings = Settings::load();
+        let volume = if settings.audio_enabled && !MUTED.with(Cell::get) { settings.audio_volume.clamp(0.0, 1.0) } else { 0.0 };
+        self.output.gain().set_value(volume);
+    }
+}
+
+// Called from input listeners. Browsers keep contexts suspended until a user gesture.
+pub fn unlock() {
+    UNLOCKED.with(|u| u.set(true));
+    SHARED.with(|shared| {
+        if let Some(shared) = shared.borrow().as_ref().filter(|shared| shared.ctx.state() == AudioContextState::Suspended) {
+            shared.ctx.resume().ok();
+        }
+    });
+}
+
+// Holds until unmuted, the saved settings stay as they are
+pub fn set_muted(muted: bool) {
+    MUTED.with(|m| m.set(muted));
+    apply_settings();
+}
+
+// Picks up new volume or on/off from the settings blob. Only the output gain changes,
+// playing loops carry on underneath it.
+pub fn apply_settings() {
+    SHARED.with(|shared| {
+        if let Some(shared) = shared.borrow().as_ref() {
+            shared.apply_settings();
+        }
+    });
+}
+
+// The shared context, its output and noise buffer, making them on first use
+fn shared_nodes() -> Result<(AudioContext, GainNode, AudioBuffer), JsValue> {
+    SHARED.with(|shared| {
+        let mut shared = shared.borrow_mut();
+        if shared.is_none() {
+            *shared = Some(Shared::new()?);
+        }
+        let shared = shared.as_ref().unwrap();
+        Ok((shared.ctx.clone(), shared.output.clone(), shared.noise.clone()))
+    })
+}
+
+// Fetches and decodes a sample that play(name) uses in place of the synthesized sound
+pub async fn load_sound(name: &str, url: &str) -> Result<(), JsValue> {
+    let bytes = assets::fetch_bytes("sound", url).await?;
+    let data = js_sys::Uint8Array::from(&bytes[..]).buffer();
+    // Decoding doesn't need a gesture, the shared context can do it while still suspended
+    let (ctx, _, _) = shared_nodes()?;
+    let buffer: AudioBuffer = JsFuture::from(ctx.decode_audio_data(&data)?).await?.dyn_into()?;
+    SAMPLES.with(|s| s.borrow_mut().insert(name.to_string(), buffer));
+    Ok(())
+}
+
+// Synthesized by default, no audio assets ship with the games. Sounds run through
+// master gain -> muffle filter -> the shared output so one toggle can dull this mix
+// without touching another's.
+pub struct Audio {
+    ctx: AudioContext,
+    master: GainNode,
+    muffle: BiquadFilterNode,
+    noise: AudioBuffer,
+}
+
+// A continuous source (looping noise or a held tone) whose loudness can be changed while it plays
+pub struct SoundLoop {
+    source: AudioScheduledSourceNode,
+    gain: GainNode,
+}
+
+const OPEN_CUTOFF_HZ: f32 = 20000.0;
+const MUFFLED_CUTOFF_HZ: f32 = 700.0;
+
+impl Audio {
+    // None when the browser refuses a context. Switched off or muted still gives one, the
+    // shared output is what goes quiet.
+    pub fn new() -> Option<Self> {
+        Audio::try_new().ok()
+    }
+
+    fn try_new() -> Result<Self, wasm_bindgen::JsValue> {
+        let (ctx, output, noise) = shared_nodes()?;
+        let master = ctx.create_gain()?;
+        let muffle = ctx.create_biquad_filter()?;
+        muffle.set_type(BiquadFilterType::Lowpass);
+        muffle.frequency().set_value(OPEN_CUTOFF_HZ);
+        master.connect_with_audio_node(&muffle)?;
+        muffle.connect_with_audio_node(&output)?;
+        Ok(Audio { ctx, master, muffle, noise })
+    }
+
+    pub fn set_muffled(&self, muffled: bool) {
+        let cutoff = if muffled { MUFFLED_CUTOFF_HZ } else { OPEN_CUTOFF_HZ };
+        let now = self.ctx.current_time();
+        // Short glide so the switch doesn't click
+        self.muffle.frequency().set_target_at_time(cutoff, now, 0.1).ok();
+    }
+
+    // Filtered noise burst with a fast decay
+    pub fn noise_burst(&self, filter: BiquadFilterType, cutoff: f32, duration: f64, volume: f32) {
+        let _ = self.try_noise_burst(filter, cutoff, duration, volume);
+    }
+
+    fn try_noise_burst(&self, filter: BiquadFilterType, cutoff: f32, duration: f64, volume: f32) -> Result<(), wasm_bindgen::JsValue> {
+        let now = self.ctx.current_time();
+        let source = self.ctx.create_buffer_source()?;
+        source.set_buffer(Some(&self.noise));
+        let shape = self.ctx.create_biquad_filter()?;
+        shape.set_type(filter);
+        shape.frequency().set_value(cutoff);
+        let gain = self.ctx.create_gain()?;
+        gain.gain().set_value_at_time(volume, now)?;
+        gain.gain().exponential_ramp_to_value_at_time(0.001, now + duration)?;
+
+        source.connect_with_audio_node(&shape)?;
+        shape.connect_with_audio_node(&gain)?;
+        gain.connect_with_audio_node(&self.master)?;
+        source.start_with_when_and_grain_offset(now, js_sys::Math::random() * 0.5)?;
+        web_sys::AudioScheduledSourceNode::stop_with_when(&source, now + duration)?;
+        Ok(())
+    }
+
+    // Plays a sample from load_sound, false when none goes by that name
+    pub fn play(&self, name: &str, volume: f32) -> bool {
+        match SAMPLES.with(|s| s.borrow().get(name).cloned()) {
+            Some(buffer) => {
+                let _ = self.try_play(&buffer, volume);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn try_play(&self, buffer: &AudioBuffer, volume: f32) -> Result<(), wasm_bindgen::JsValue> {
+        let source = self.ctx.create_buffer_source()?;
+        source.set_buffer(Some(buffer));
+        let gain = self.ctx.create_gain()?;
+        gain.gain().set_value(volume);
+        source.connect_with_audio_node(&gain)?;
+        gain.connect_with_audio_node(&self.master)?;
+        source.start()?;
+        Ok(())
+    }
+
+    pub fn blip(&self, wave: OscillatorType, frequency: f32, duration: f64, volume: f32) {
+        let _ = self.try_blip(wave, frequency, duration, volume);
+    }
+
+    fn try_blip(&self, wave: OscillatorType, frequency: f32, duration: f64, volume: f32) -> Result<(), wasm_bindgen::JsValue> {
+        let now = self.ctx.current_time();
+        let osc = self.ctx.create_oscillator()?;
+        osc.set_type(wave);
+        osc.frequency().set_value(frequency);
+        let gain = self.ctx.create_gain()?;
+        gain.gain().set_value_at_time(volume, now)?;
+        gain.gain().exponential_ramp_to_value_at_time(0.001, now + duration)?;
+
+        osc.connect_with_audio_node(&gain)?;
+        gain.connect_with_audio_node(&self.master)?;
+        osc.start_with_when(now)?;
+        osc.stop_with_when(now + duration)?;
+        Ok(())
+    }
+
+    // Band-passed looping noise, starts silent
+    pub fn noise_loop(&self, center: f32) -> Option<SoundLoop> {
+        let source = self.ctx.create_buffer_source().ok()?;
+        source.set_buffer(Some(&self.noise));
+        source.set_loop(true);
+        let band = self.ctx.create_biquad_filter().ok()?;
+        band.set_type(BiquadFilterType::Bandpass);
+        band.frequency().set_value(center);
+        let gain = self.ctx.create_gain().ok()?;
+        gain.gain().set_value(0.0);
+
+        source.connect_with_audio_node(&band).ok()?;
+        band.connect_with_audio_node(&gain).ok()?;
+        gain.connect_with_audio_node(&self.master).ok()?;
+        source.start().ok()?;
+        Some(SoundLoop { source: source.into(), gain })
+    }
+
+    // Held oscillator, starts silent
+    pub fn tone_loop(&self, wave: OscillatorType, frequency: f32) -> Option<SoundLoop> {
+        let osc = self.ctx.create_oscillator().ok()?;
+        osc.set_type(wave);
+        osc.frequency().set_value(frequency);
+        let gain = self.ctx.create_gain().ok()?;
+        gain.gain().set_value(0.0);
+
+        osc.connect_with_audio_node(&gain).ok()?;
+        gain.connect_with_audio_node(&self.master).ok()?;
+        osc.start().ok()?;
+        Some(SoundLoop { source: osc.into(), gain })
+    }
+
+    pub fn set_loop_volume(&self, sound_loop: &SoundLoop, volume: f32) {
+        sound_loop.gain.gain().set_target_at_time(volume, self.ctx.current_time(), 0.3).ok();
+    }
+}
+
+impl Drop for SoundLoop {
+    fn drop(&mut self) {
+        self.source.stop().ok();
+    }
+}
+
+// Leaving a game drops its Audio, which cuts its sounds off the shared output. The
+// context stays for the next game.
+impl Drop for Audio {
+    fn drop(&mut self) {
+        self.muffle.disconnect().ok();
+    }
+}
@@ -0,0 +1,214 @@
+// Raw pointer input, forwarded from the DOM listeners to the active game's controller
+pub enum InputEvent {
+    PointerDown { x: i32, y: i32, button: i32 },
+    PointerUp,
+    PointerMove { x: i32, y: i32, dx: i32, dy: i32 },
+    Wheel { delta: f32 },
+}
+
+// Drag to orbit around a target, wheel to zoom. The view eases toward the requested
+// angles and distance, `damping` is the fraction of the gap closed per update (1.0 = instant)
+pub struct OrbitController {
+    pub yaw: f32,
+    pub pitch: f32,
+    pub distance: f32,
+    target_yaw: f32,
+    target_pitch: f32,
+    target_distance: f32,
+    pub damping: f32,
+    pub rotate_speed: f32,
+    pub zoom_speed: f32,
+    pub min_pitch: f32,
+    pub max_pitch: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    dragging: bool,
+    last_pointer: (i32, i32),
+}
+
+impl OrbitController {
+    pub fn new(distance: f32, pitch: f32, yaw: f32) -> Self {
+        OrbitController {
+            yaw,
+            pitch,
+            distance,
+            target_yaw: yaw,
+            target_pitch: pitch,
+            target_distance: distance,
+            damping: 0.3,
+            rotate_speed: 0.01,
+            zoom_speed: 0.001,
+            min_pitch: -1.5,
+            max_pitch: 1.5,
+            min_distance: 0.0001,
+            max_distance: 100000000.0,
+            dragging: false,
+            last_pointer: (0, 0),
+        }
+    }
+
+    pub fn handle(&mut self, event: &InputEvent) {
+        match *event {
+            InputEvent::PointerDown { x, y, .. } => {
+                self.dragging = true;
+                self.last_pointer = (x, y);
+            }
+            InputEvent::PointerUp => self.dragging = false,
+            InputEvent::PointerMove { x, y, .. } => {
+                if self.dragging {
+                    let dx = x - self.last_pointer.0;
+                    let dy = y - self.last_pointer.1;
+                    self.rotate(dx as f32 * self.rotate_speed, dy as f32 * self.rotate_speed);
+                    self.last_pointer = (x, y);
+                }
+            }
+            InputEvent::Wheel { delta } => self.zoom((delta * self.zoom_speed).exp()),
+        }
+    }
+
+    pub fn rotate(&mut self, d_yaw: f32, d_pitch: f32) {
+        self.target_yaw += d_yaw;
+        self.target_pitch = (self.target_pitch + d_pitch).clamp(self.min_pitch, self.max_pitch);
+    }
+
+    pub fn zoom(&mut self, factor: f32) {
+        self.set_distance(self.target_distance * factor);
+    }
+
+    pub fn target_distance(&self) -> f32 {
+        self.target_distance
+    }
+
+    pub fn set_distance(&mut self, distance: f32) {
+        self.target_distance = distance.clamp(self.min_distance, self.max_distance);
+    }
+
+    // Jump straight to the requested view, skipping the easing
+    #[cfg(test)]
+    pub fn snap(&mut self) {
+        self.yaw = self.target_yaw;
+        self.pitch = self.target_pitch;
+        self.distance = self.target_distance;
+    }
+
+    pub fn update(&mut self) {
+        let k = self.damping.clamp(0.0, 1.0);
+        self.yaw += (self.target_yaw - self.yaw) * k;
+        self.pitch += (self.target_pitch - self.pitch) * k;
+        // Zoom eases in log space so huge distance jumps feel the same as small ones
+        self.distance *= (self.target_distance / self.distance).powf(k);
+    }
+}
+
+// Pointer-lock mouse look: relative movement turns the view directly
+pub struct FpsController {
+    pub yaw: f32,
+    pub pitch: f32,
+    pub sensitivity: f32,
+    pub max_pitch: f32,
+}
+
+impl FpsController {
+    pub fn new(yaw: f32, pitch: f32) -> Self {
+        FpsController { yaw, pitch, sensitivity: 0.005, max_pitch: 1.5 }
+    }
+
+    pub fn handle(&mut self, event: &InputEvent) {
+        if let InputEvent::PointerMove { dx, dy, .. } = *event {
+            self.look(dx as f32 * self.sensitivity, -dy as f32 * self.sensitivity);
+        }
+    }
+
+    pub fn look(&mut self, d_yaw: f32, d_pitch: f32) {
+        self.yaw += d_yaw;
+        self.pitch = (self.pitch + d_pitch).clamp(-self.max_pitch, self.max_pitch);
+    }
+
+    pub fn rotation(&self) -> (f32, f32) {
+        (self.yaw, self.pitch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orbit_damping_converges_on_the_target() {
+        let mut orbit = OrbitController::new(10.0, 0.0, 0.0);
+        orbit.rotate(1.0, 0.5);
+        orbit.set_distance(1000.0);
+        orbit.update();
+        // One step closes `damping` of the gap
+        assert!((orbit.yaw - 0.3).abs() < 1e-6);
+        for _ in 0..100 {
+            orbit.update();
+        }
+        assert!((orbit.yaw - 1.0).abs() < 1e-4);
+        assert!((orbit.pitch - 0.5).abs() < 1e-4);
+        assert!((orbit.distance - 1000.0).abs() / 1000.0 < 1e-4);
+    }
+
+    #[test]
+    fn orbit_zoom_eases_in_log_space() {
+        let mut orbit = OrbitController::new(1.0, 0.0, 0.0);
+        orbit.damping = 0.5;
+        orbit.set_distance(100.0);
+        orbit.update();
+        // Half way in log space is the geometric mean
+        assert!((orbit.distance - 10.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn orbit_clamps_pitch_and_distance() {
+        let mut orbit = OrbitController::new(10.0, 0.0, 0.0);
+        orbit.rotate(0.0, 10.0);
+        orbit.set_distance(1e12);
+        orbit.snap();
+        assert_eq!(orbit.pitch, orbit.max_pitch);
+        assert_eq!(orbit.distance, orbit.max_distance);
+        orbit.rotate(0.0, -20.0);
+        orbit.zoom(0.0);
+        orbit.snap();
+        assert_eq!(orbit.pitch, orbit.min_pitch);
+        assert_eq!(orbit.distance, orbit.min_distance);
+    }
+
+    #[test]
+    fn orbit_only_turns_while_dragging() {
+        let mut orbit = OrbitController::new(10.0, 0.0, 0.0);
+        orbit.damping = 1.0;
+        orbit.handle(&InputEvent::PointerMove { x: 50, y: 0, dx: 50, dy: 0 });
+        orbit.update();
+        assert_eq!(orbit.yaw, 0.0);
+        orbit.handle(&InputEvent::PointerDown { x: 0, y: 0, button: 0 });
+        orbit.handle(&InputEvent::PointerMove { x: 100, y: 0, dx: 100, dy: 0 });
+        orbit.handle(&InputEvent::PointerUp);
+        orbit.handle(&InputEvent::PointerMove { x: 200, y: 0, dx: 100, dy: 0 });
+        orbit.update();
+        assert!((orbit.yaw - 100.0 * orbit.rotate_speed).abs() < 1e-6);
+    }
+
+    #[test]
+    fn fps_sensitivity_scales_mouse_movement() {
+        let mut slow = FpsController::new(0.0, 0.0);
+        let mut fast = FpsController::new(0.0, 0.0);
+        fast.sensitivity = slow.sensitivity * 2.0;
+        let event = InputEvent::PointerMove { x: 0, y: 0, dx: 40, dy: -20 };
+        slow.handle(&event);
+        fast.handle(&event);
+        assert!((fast.yaw - 2.0 * slow.yaw).abs() < 1e-6);
+        assert!((fast.pitch - 2.0 * slow.pitch).abs() < 1e-6);
+        // Moving the mouse up looks up
+        assert!(slow.pitch > 0.0);
+    }
+
+    #[test]
+    fn fps_pitch_stops_short_of_straight_up_and_down() {
+        let mut fps = FpsController::new(0.0, 0.0);
+        fps.look(0.0, 10.0);
+        assert_eq!(fps.pitch, fps.max_pitch);
+        fps.look(0.0, -20.0);
+        assert_eq!(fps.pitch, -fps.max_pitch);
+    }
+}
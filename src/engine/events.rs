@@ -0,0 +1,49 @@
+use std::cell::RefCell;
+use wasm_bindgen::JsValue;
+
+thread_local! {
+    static EVENT_CALLBACK: RefCell<Option<js_sys::Function>> = const { RefCell::new(None) };
+    static PENDING_EVENTS: RefCell<Vec<serde_json::Value>> = const { RefCell::new(Vec::new()) };
+    static ERROR_CALLBACK: RefCell<Option<js_sys::Function>> = const { RefCell::new(None) };
+}
+
+pub fn set_callback(callback: Option<js_sys::Function>) {
+    EVENT_CALLBACK.with(|cb| *cb.borrow_mut() = callback);
+}
+
+// Events are queued and delivered by flush() once the game is no longer borrowed,
+// so the page callback can safely call back into the exported functions.
+pub fn emit(event_type: &str, value: serde_json::Value) {
+    PENDING_EVENTS.with(|events| {
+        events.borrow_mut().push(serde_json::json!({ "type": event_type, "value": value }));
+    });
+}
+
+pub fn set_error_callback(callback: Option<js_sys::Function>) {
+    ERROR_CALLBACK.with(|cb| *cb.borrow_mut() = callback);
+}
+
+// Called straight away rather than queued, a failed start or a panic has no next frame
+// to flush from
+pub fn report_error(message: &str) {
+    let callback = ERROR_CALLBACK.with(|cb| cb.try_borrow().ok().and_then(|cb| cb.clone()));
+    if let Some(callback) = callback {
+        callback.call1(&JsValue::NULL, &JsValue::from_str(message)).ok();
+    }
+}
+
+pub fn flush() {
+    let events = PENDING_EVENTS.with(|events| std::mem::take(&mut *events.borrow_mut()));
+    if events.is_empty() {
+        return;
+    }
+
+    let callback = EVENT_CALLBACK.with(|cb| cb.borrow().clone());
+    if let Some(callback) = callback {
+        for event in events {
+            if let Ok(event) = js_sys::JSON::parse(&event.to_string()) {
+                callback.call1(&JsValue::NULL, &event).ok();
+            }
+        }
+    }
+}
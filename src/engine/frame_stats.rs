@@ -0,0 +1,59 @@
+use serde::Serialize;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+
+// Frames averaged over, about a second at 60 Hz
+const WINDOW: usize = 60;
+
+struct Sample {
+    // None for the first frame and the first after a pause, which have no interval
+    frame_ms: Option<f64>,
+    update_ms: f64,
+    render_ms: f64,
+}
+
+#[derive(Serialize, Default, Clone, Copy)]
+pub struct FrameStats {
+    pub fps: f64,
+    pub frame_ms: f64,
+    pub update_ms: f64,
+    pub render_ms: f64,
+}
+
+thread_local! {
+    static SAMPLES: RefCell<VecDeque<Sample>> = RefCell::new(VecDeque::with_capacity(WINDOW));
+}
+
+pub fn record(frame_ms: Option<f64>, update_ms: f64, render_ms: f64) {
+    SAMPLES.with(|s| {
+        let mut samples = s.borrow_mut();
+        if samples.len() == WINDOW {
+            samples.pop_front();
+        }
+        samples.push_back(Sample { frame_ms, update_ms, render_ms });
+    });
+}
+
+pub fn clear() {
+    SAMPLES.with(|s| s.borrow_mut().clear());
+}
+
+pub fn current() -> FrameStats {
+    SAMPLES.with(|s| {
+        let samples = s.borrow();
+        if samples.is_empty() {
+            return FrameStats::default();
+        }
+        let count = samples.len() as f64;
+        let (interval_sum, interval_count) = samples.iter()
+            .filter_map(|sample| sample.frame_ms)
+            .fold((0.0, 0), |(sum, n), ms| (sum + ms, n + 1));
+        let frame_ms = if interval_count == 0 { 0.0 } else { interval_sum / interval_count as f64 };
+        FrameStats {
+            fps: if frame_ms > 0.0 { 1000.0 / frame_ms } else { 0.0 },
+            frame_ms,
+            update_ms: samples.iter().map(|sample| sample.update_ms).sum::<f64>() / count,
+            render_ms: samples.iter().map(|sample| sample.render_ms).sum::<f64>() / count,
+        }
+    })
+}
@@ -0,0 +1,122 @@
+use std::cell::RefCell;
+use wasm_bindgen::JsCast;
+use web_sys::{Gamepad, GamepadButton};
+
+// Stick travel ignored around the center, worn sticks rest a little off zero
+const STICK_DEAD_ZONE: f32 = 0.2;
+// Stick deflection that counts as a direction press
+const STICK_PRESS: f32 = 0.5;
+const TRIGGER_DEAD_ZONE: f32 = 0.1;
+
+// Indices of the browser's "standard" gamepad mapping
+const BUTTON_A: usize = 0;
+const BUTTON_LB: usize = 4;
+const BUTTON_RB: usize = 5;
+const BUTTON_LT: usize = 6;
+const BUTTON_RT: usize = 7;
+const BUTTON_START: usize = 9;
+const DPAD_UP: usize = 12;
+const DPAD_DOWN: usize = 13;
+const DPAD_LEFT: usize = 14;
+const DPAD_RIGHT: usize = 15;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameAction {
+    Left,
+    Right,
+    Forward,
+    Back,
+    Jump,
+    // Break and place in Minecraft, the two mouse buttons
+    Primary,
+    Secondary,
+    Restart,
+}
+
+const ACTIONS: [GameAction; 8] = [
+    GameAction::Left,
+    GameAction::Right,
+    GameAction::Forward,
+    GameAction::Back,
+    GameAction::Jump,
+    GameAction::Primary,
+    GameAction::Secondary,
+    GameAction::Restart,
+];
+
+// One poll of the first connected pad, in game terms
+pub struct GamepadFrame {
+    // Went down or up since the previous poll
+    pub pressed: Vec<GameAction>,
+    pub released: Vec<GameAction>,
+    // Right stick after the dead zone, -1 to 1 with y down
+    pub look: (f32, f32),
+    // Right trigger minus left trigger, positive zooms in
+    pub zoom: f32,
+}
+
+thread_local! {
+    static HELD: RefCell<Vec<GameAction>> = const { RefCell::new(Vec::new()) };
+}
+
+// Radial dead zone, the remaining travel rescaled so the edge of the zone reads as zero
+fn dead_zone(x: f32, y: f32) -> (f32, f32) {
+    let magnitude = (x * x + y * y).sqrt();
+    if magnitude <= STICK_DEAD_ZONE {
+        return (0.0, 0.0);
+    }
+    let scale = ((magnitude - STICK_DEAD_ZONE) / (1.0 - STICK_DEAD_ZONE)).min(1.0) / magnitude;
+    (x * scale, y * scale)
+}
+
+fn first_gamepad() -> Option<Gamepad> {
+    let pads = web_sys::window()?.navigator().get_gamepads().ok()?;
+    pads.iter()
+        .filter_map(|pad| pad.dyn_into::<Gamepad>().ok())
+        .find(|pad| pad.connected() && pad.mapping() == web_sys::GamepadMappingType::Standard)
+}
+
+fn button(pad: &Gamepad, index: usize) -> f32 {
+    pad.buttons().get(index as u32).dyn_into::<GamepadButton>().map(|b| b.value() as f32).unwrap_or(0.0)
+}
+
+fn axis(pad: &Gamepad, index: usize) -> f32 {
+    pad.axes().get(index as u32).as_f64().unwrap_or(0.0) as f32
+}
+
+// None without a pad, so keyboard play isn't interrupted by releases nobody made
+pub fn poll() -> Option<GamepadFrame> {
+    let pad = match first_gamepad() {
+        Some(pad) => pad,
+        None => {
+            HELD.with(|h| h.borrow_mut().clear());
+            return None;
+        }
+    };
+    let (move_x, move_y) = dead_zone(axis(&pad, 0), axis(&pad, 1));
+    let look = dead_zone(axis(&pad, 2), axis(&pad, 3));
+    let pressed = |index: usize| button(&pad, index) > 0.5;
+    let held: Vec<GameAction> = ACTIONS.into_iter()
+        .filter(|action| match action {
+            GameAction::Left => pressed(DPAD_LEFT) || move_x < -STICK_PRESS,
+            GameAction::Right => pressed(DPAD_RIGHT) || move_x > STICK_PRESS,
+            GameAction::Forward => pressed(DPAD_UP) || move_y < -STICK_PRESS,
+            GameAction::Back => pressed(DPAD_DOWN) || move_y > STICK_PRESS,
+            GameAction::Jump => pressed(BUTTON_A),
+            GameAction::Primary => pressed(BUTTON_RB),
+            GameAction::Secondary => pressed(BUTTON_LB),
+            GameAction::Restart => pressed(BUTTON_START),
+        })
+        .collect();
+    let trigger = |index: usize| {
+        let value = button(&pad, index);
+        if value > TRIGGER_DEAD_ZONE { value } else { 0.0 }
+    };
+    let previous = HELD.with(|h| h.replace(held.clone()));
+    Some(GamepadFrame {
+        pressed: held.iter().copied().filter(|a| !previous.contains(a)).collect(),
+        released: previous.iter().copied().filter(|a| !held.contains(a)).collect(),
+        look,
+        zoom: trigger(BUTTON_RT) - trigger(BUTTON_LT),
+    })
+}
@@ -0,0 +1,192 @@
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyAction {
+    Forward,
+    Back,
+    Left,
+    Right,
+    Jump,
+    Sprint,
+    Restart,
+}
+
+// Action to KeyboardEvent.key or .code values, e.g. {"left": ["ArrowLeft", "q", "KeyA"]}.
+// An action listed here replaces that game's default keys for it, unlisted actions keep theirs.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(transparent)]
+pub struct KeyBindings(HashMap<KeyAction, Vec<String>>);
+
+// One section per game, since the same key means different things in each, e.g.
+// {"crossy": {"forward": [" ", "ArrowUp"]}, "minecraft": {"jump": ["j"]}}. A flat
+// {"forward": [" ", "ArrowUp"]} without sections applies to both games.
+#[derive(Serialize, Clone, Default)]
+pub struct GameBindings {
+    pub crossy: KeyBindings,
+    pub minecraft: KeyBindings,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct PerGame {
+    crossy: KeyBindings,
+    minecraft: KeyBindings,
+}
+
+// Either schema gets serde's own error, e.g. an unknown action or a section mixed with flat keys
+impl<'de> Deserialize<'de> for GameBindings {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+        let value = serde_json::Value::deserialize(deserializer)?;
+        let sectioned = value.as_object().is_some_and(|fields| fields.contains_key("crossy") || fields.contains_key("minecraft"));
+        if sectioned {
+            let PerGame { crossy, minecraft } = PerGame::deserialize(value).map_err(D::Error::custom)?;
+            Ok(GameBindings { crossy, minecraft })
+        } else {
+            let shared = KeyBindings::deserialize(value).map_err(D::Error::custom)?;
+            Ok(GameBindings { crossy: shared.clone(), minecraft: shared })
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BindingSet {
+    Crossy,
+    Minecraft,
+}
+
+impl GameBindings {
+    fn get(&self, set: BindingSet) -> &KeyBindings {
+        match set {
+            BindingSet::Crossy => &self.crossy,
+            BindingSet::Minecraft => &self.minecraft,
+        }
+    }
+}
+
+thread_local! {
+    static BINDINGS: RefCell<GameBindings> = RefCell::new(GameBindings::default());
+}
+
+pub fn set(bindings: GameBindings) {
+    BINDINGS.with(|b| *b.borrow_mut() = bindings);
+}
+
+// Letters match either case, so Shift or Caps Lock doesn't stop movement. Codes name the
+// physical key, so a binding like "KeyW" stays in place on any keyboard layout.
+fn key_matches(bound: &str, key: &str, code: &str) -> bool {
+    if bound.chars().count() == 1 {
+        bound.eq_ignore_ascii_case(key)
+    } else {
+        bound == key || bound == code
+    }
+}
+
+// None for keys bound to nothing, which the game handles itself. Code may be empty
+// when there's no physical key behind the input.
+pub fn action(set: BindingSet, key: &str, code: &str, defaults: &[(KeyAction, &[&str])]) -> Option<KeyAction> {
+    BINDINGS.with(|b| resolve(b.borrow().get(set), key, code, defaults))
+}
+
+fn resolve(bindings: &KeyBindings, key: &str, code: &str, defaults: &[(KeyAction, &[&str])]) -> Option<KeyAction> {
+    defaults.iter()
+        .find(|(action, default_keys)| match bindings.0.get(action) {
+            Some(keys) => keys.iter().any(|bound| key_matches(bound, key, code)),
+            None => default_keys.iter().any(|bound| key_matches(bound, key, code)),
+        })
+        .map(|(action, _)| *action)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::minecraft::MINECRAFT_KEYS;
+    use crate::game::CROSSY_KEYS;
+
+    fn bindings(json: &str) -> KeyBindings {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn crossy_arrows_and_space_move() {
+        let none = KeyBindings::default();
+        assert_eq!(resolve(&none, " ", "Space", CROSSY_KEYS), Some(KeyAction::Forward));
+        assert_eq!(resolve(&none, "ArrowDown", "ArrowDown", CROSSY_KEYS), Some(KeyAction::Back));
+        assert_eq!(resolve(&none, "ArrowLeft", "ArrowLeft", CROSSY_KEYS), Some(KeyAction::Left));
+        assert_eq!(resolve(&none, "ArrowRight", "ArrowRight", CROSSY_KEYS), Some(KeyAction::Right));
+        assert_eq!(resolve(&none, "r", "KeyR", CROSSY_KEYS), Some(KeyAction::Restart));
+    }
+
+    // The old handler had these two the wrong way round
+    #[test]
+    fn crossy_a_is_left_and_d_is_right() {
+        let none = KeyBindings::default();
+        assert_eq!(resolve(&none, "a", "KeyA", CROSSY_KEYS), Some(KeyAction::Left));
+        assert_eq!(resolve(&none, "d", "KeyD", CROSSY_KEYS), Some(KeyAction::Right));
+        assert_eq!(resolve(&none, "A", "", CROSSY_KEYS), Some(KeyAction::Left));
+        assert_eq!(resolve(&none, "D", "", CROSSY_KEYS), Some(KeyAction::Right));
+    }
+
+    #[test]
+    fn minecraft_wasd_moves_by_letter_or_physical_key() {
+        let none = KeyBindings::default();
+        assert_eq!(resolve(&none, "w", "KeyW", MINECRAFT_KEYS), Some(KeyAction::Forward));
+        assert_eq!(resolve(&none, "a", "", MINECRAFT_KEYS), Some(KeyAction::Left));
+        assert_eq!(resolve(&none, "d", "KeyD", MINECRAFT_KEYS), Some(KeyAction::Right));
+        // "z" where an AZERTY layout has its W key
+        assert_eq!(resolve(&none, "z", "KeyW", MINECRAFT_KEYS), Some(KeyAction::Forward));
+        assert_eq!(resolve(&none, " ", "Space", MINECRAFT_KEYS), Some(KeyAction::Jump));
+        assert_eq!(resolve(&none, "Shift", "ShiftLeft", MINECRAFT_KEYS), Some(KeyAction::Sprint));
+        assert_eq!(resolve(&none, "x", "KeyX", MINECRAFT_KEYS), None);
+    }
+
+    #[test]
+    fn letters_match_either_case() {
+        let none = KeyBindings::default();
+        assert_eq!(resolve(&none, "S", "", MINECRAFT_KEYS), Some(KeyAction::Back));
+        assert_eq!(resolve(&none, "D", "", MINECRAFT_KEYS), Some(KeyAction::Right));
+        // Names longer than a letter are exact
+        assert_eq!(resolve(&none, "arrowleft", "", CROSSY_KEYS), None);
+    }
+
+    #[test]
+    fn binding_replaces_only_its_action() {
+        let custom = bindings(r#"{"forward": ["i"]}"#);
+        assert_eq!(resolve(&custom, "I", "KeyI", MINECRAFT_KEYS), Some(KeyAction::Forward));
+        assert_eq!(resolve(&custom, "w", "KeyW", MINECRAFT_KEYS), None);
+        assert_eq!(resolve(&custom, "a", "KeyA", MINECRAFT_KEYS), Some(KeyAction::Left));
+    }
+
+    #[test]
+    fn action_reads_the_set_for_its_game() {
+        set(serde_json::from_str(r#"{"minecraft": {"left": ["j"]}}"#).unwrap());
+        assert_eq!(action(BindingSet::Minecraft, "j", "KeyJ", MINECRAFT_KEYS), Some(KeyAction::Left));
+        assert_eq!(action(BindingSet::Crossy, "j", "KeyJ", CROSSY_KEYS), None);
+        assert_eq!(action(BindingSet::Crossy, "ArrowLeft", "ArrowLeft", CROSSY_KEYS), Some(KeyAction::Left));
+        set(GameBindings::default());
+    }
+
+    #[test]
+    fn flat_bindings_apply_to_both_games() {
+        let flat: GameBindings = serde_json::from_str(r#"{"forward": ["i"], "left": ["j"]}"#).unwrap();
+        for (bindings, defaults) in [(&flat.crossy, CROSSY_KEYS), (&flat.minecraft, MINECRAFT_KEYS)] {
+            assert_eq!(resolve(bindings, "i", "KeyI", defaults), Some(KeyAction::Forward));
+            assert_eq!(resolve(bindings, "j", "KeyJ", defaults), Some(KeyAction::Left));
+            assert_eq!(resolve(bindings, "d", "KeyD", defaults), Some(KeyAction::Right));
+        }
+
+        let sectioned: GameBindings = serde_json::from_str(r#"{"minecraft": {"forward": ["i"]}}"#).unwrap();
+        assert_eq!(resolve(&sectioned.minecraft, "i", "KeyI", MINECRAFT_KEYS), Some(KeyAction::Forward));
+        assert_eq!(resolve(&sectioned.crossy, "i", "KeyI", CROSSY_KEYS), None);
+    }
+
+    #[test]
+    fn unknown_actions_and_mixed_schemas_are_rejected() {
+        assert!(serde_json::from_str::<GameBindings>(r#"{"fly": ["f"]}"#).is_err());
+        assert!(serde_json::from_str::<GameBindings>(r#"{"crossy": {}, "forward": ["i"]}"#).is_err());
+        assert!(serde_json::from_str::<GameBindings>(r#"{"forward": "i"}"#).is_err());
+    }
+}
@@ -60,6 +60,47 @@ impl Mesh {
         Mesh { vertices, indices }
     }
 
+    // White boxes inside a unit cell (coordinates in -0.5..0.5). UVs come from the
+    // positions so a partial block shows the matching crop of its texture instead of a squashed copy.
+    pub fn cuboids(boxes: &[([f32; 3], [f32; 3])]) -> Self {
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+
+        for &([x0, y0, z0], [x1, y1, z1]) in boxes {
+            // Corners counter-clockwise from outside, same winding and shading as cube()
+            let faces = [
+                ([[x0, y0, z1], [x1, y0, z1], [x1, y1, z1], [x0, y1, z1]], [0.0, 0.0, 1.0], 0.9),
+                ([[x1, y0, z0], [x0, y0, z0], [x0, y1, z0], [x1, y1, z0]], [0.0, 0.0, -1.0], 0.7),
+                ([[x0, y1, z1], [x1, y1, z1], [x1, y1, z0], [x0, y1, z0]], [0.0, 1.0, 0.0], 1.1),
+                ([[x0, y0, z0], [x1, y0, z0], [x1, y0, z1], [x0, y0, z1]], [0.0, -1.0, 0.0], 0.4),
+                ([[x1, y0, z1], [x1, y0, z0], [x1, y1, z0], [x1, y1, z1]], [1.0, 0.0, 0.0], 0.8),
+                ([[x0, y0, z0], [x0, y0, z1], [x0, y1, z1], [x0, y1, z0]], [-1.0, 0.0, 0.0], 0.6),
+            ];
+
+            for (corners, normal, brightness) in faces {
+                let base = (vertices.len() / 11) as u16;
+                for [x, y, z] in corners {
+                    let (u, v) = if normal[2] != 0.0 {
+                        (x * normal[2], y)
+                    } else if normal[1] != 0.0 {
+                        (x, -z * normal[1])
+                    } else {
+                        (-z * normal[0], y)
+                    };
+                    vertices.extend_from_slice(&[
+                        x, y, z, brightness, brightness, brightness, u + 0.5, v + 0.5, normal[0], normal[1], normal[2],
+                    ]);
+                }
+                indices.extend_from_slice(&[
+                    base, base + 1, base + 2,
+                    base, base + 2, base + 3,
+                ]);
+            }
+        }
+
+        Mesh { vertices, indices }
+    }
+
     pub fn face_top(size: f32) -> Self {
         let s = size / 2.0;
         let mut vertices = Vec::new();
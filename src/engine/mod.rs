@@ -1,2 +1,12 @@
+pub mod assets;
+pub mod audio;
+pub mod camera;
+pub mod events;
+pub mod frame_stats;
+pub mod input;
+pub mod key_bindings;
 pub mod mesh;
+pub mod overlay;
+pub mod particles;
 pub mod renderer;
+pub mod settings;
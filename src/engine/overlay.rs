@@ -0,0 +1,170 @@
+use crate::engine::renderer::Renderer;
+use std::cell::RefCell;
+
+type Color = (f32, f32, f32);
+
+pub const DAMAGE_COLOR: Color = (0.8, 0.05, 0.05);
+pub const UNDERWATER_COLOR: Color = (0.1, 0.3, 0.8);
+
+// Full-screen feedback shared by the games: a flash that fades out on its own and a
+// tint that stays until it's changed. Games set it from anywhere through the functions
+// below, lib.rs draws it once per frame on top of whichever game rendered.
+#[derive(Default)]
+pub struct ScreenOverlay {
+    // Color, current opacity and opacity lost per second
+    flash: Option<(Color, f32, f32)>,
+    tint: Option<(Color, f32)>,
+    last_frame_ms: Option<f64>,
+}
+
+thread_local! {
+    static OVERLAY: RefCell<ScreenOverlay> = RefCell::new(ScreenOverlay::default());
+}
+
+impl ScreenOverlay {
+    // Opacity falls linearly to zero over the decay time it was set with
+    pub fn update(&mut self, dt: f32) {
+        if let Some((_, intensity, rate)) = self.flash.as_mut() {
+            *intensity -= *rate * dt;
+            if *intensity <= 0.0 {
+                self.flash = None;
+            }
+        }
+    }
+
+    // One quad: the flash sits on the tint, their colors mixed by opacity
+    pub fn quad(&self) -> Option<(Color, f32)> {
+        match (self.tint, self.flash) {
+            (None, None) => None,
+            (Some(tint), None) => Some(tint),
+            (None, Some((color, intensity, _))) => Some((color, intensity)),
+            (Some((tint, tint_alpha)), Some((flash, intensity, _))) => {
+                let alpha = intensity + tint_alpha * (1.0 - intensity);
+                let mix = |f: f32, t: f32| (f * intensity + t * tint_alpha * (1.0 - intensity)) / alpha;
+                Some(((mix(flash.0, tint.0), mix(flash.1, tint.1), mix(flash.2, tint.2)), alpha))
+            }
+        }
+    }
+}
+
+// A newer flash replaces the current one, a weaker one never dims a stronger flash
+pub fn set_flash(color: Color, intensity: f32, decay_seconds: f32) {
+    let intensity = intensity.clamp(0.0, 1.0);
+    if intensity <= 0.0 {
+        return;
+    }
+    OVERLAY.with(|o| {
+        let mut overlay = o.borrow_mut();
+        if overlay.flash.is_some_and(|(_, current, _)| current > intensity) {
+            return;
+        }
+        overlay.flash = Some((color, intensity, intensity / decay_seconds.max(0.01)));
+    });
+}
+
+// Zero alpha clears the tint
+pub fn set_persistent_tint(color: Color, alpha: f32) {
+    let alpha = alpha.clamp(0.0, 1.0);
+    OVERLAY.with(|o| o.borrow_mut().tint = (alpha > 0.0).then_some((color, alpha)));
+}
+
+// Called when the active game changes so nothing carries over
+pub fn clear() {
+    OVERLAY.with(|o| {
+        let mut overlay = o.borrow_mut();
+        overlay.flash = None;
+        overlay.tint = None;
+    });
+}
+
+// Advances the flash by the real time since the last frame, then draws
+pub fn update_and_draw(renderer: &Renderer) {
+    let now = web_sys::window().and_then(|w| w.performance()).map(|p| p.now()).unwrap_or(0.0);
+    OVERLAY.with(|o| {
+        let mut overlay = o.borrow_mut();
+        if let Some(last) = overlay.last_frame_ms {
+            // A backgrounded tab shouldn't eat a whole flash in one frame
+            overlay.update((((now - last) / 1000.0) as f32).min(0.1));
+        }
+        overlay.last_frame_ms = Some(now);
+    });
+    draw(renderer);
+}
+
+pub fn draw(renderer: &Renderer) {
+    let quad = OVERLAY.with(|o| o.borrow().quad());
+    let (color, alpha) = match quad {
+        Some(quad) => quad,
+        None => return,
+    };
+    let (width, height) = match renderer.canvas() {
+        Some(canvas) => (canvas.width() as f32, canvas.height() as f32),
+        None => return,
+    };
+    renderer.begin_screen_overlay();
+    renderer.enable_blend();
+    renderer.set_alpha(alpha);
+    renderer.draw_screen_rect(0.0, 0.0, width, height, color, (width, height));
+    renderer.set_alpha(1.0);
+    renderer.disable_blend();
+    renderer.end_screen_overlay();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flashing(intensity: f32, decay_seconds: f32) -> ScreenOverlay {
+        clear();
+        set_flash((1.0, 1.0, 1.0), intensity, decay_seconds);
+        OVERLAY.with(|o| std::mem::take(&mut *o.borrow_mut()))
+    }
+
+    fn opacity(overlay: &ScreenOverlay) -> f32 {
+        overlay.quad().map_or(0.0, |(_, alpha)| alpha)
+    }
+
+    #[test]
+    fn flash_fades_linearly_over_its_decay_time() {
+        let mut overlay = flashing(0.8, 0.4);
+        overlay.update(0.1);
+        assert!((opacity(&overlay) - 0.6).abs() < 1e-6);
+        overlay.update(0.2);
+        assert!((opacity(&overlay) - 0.2).abs() < 1e-6);
+        overlay.update(0.15);
+        assert!(overlay.quad().is_none());
+    }
+
+    // However the frames fall, the flash is gone once its decay time has passed
+    #[test]
+    fn flash_is_gone_after_its_decay_time_at_any_frame_rate() {
+        for dt in [1.0f32 / 144.0, 1.0 / 60.0, 1.0 / 24.0] {
+            let mut overlay = flashing(1.0, 0.5);
+            // One frame spare for rounding in the running total
+            let frames = (0.5 / dt).ceil() as usize + 1;
+            for _ in 0..frames {
+                overlay.update(dt);
+            }
+            assert!(overlay.quad().is_none(), "still showing at dt {}", dt);
+        }
+    }
+
+    #[test]
+    fn tint_stays_under_a_fading_flash() {
+        let mut overlay = flashing(0.5, 1.0);
+        overlay.tint = Some((UNDERWATER_COLOR, 0.3));
+        overlay.update(0.5);
+        assert!((opacity(&overlay) - (0.25 + 0.3 * 0.75)).abs() < 1e-6);
+        overlay.update(1.0);
+        assert_eq!(overlay.quad(), Some((UNDERWATER_COLOR, 0.3)));
+    }
+
+    #[test]
+    fn weaker_flash_leaves_a_stronger_one() {
+        clear();
+        set_flash(DAMAGE_COLOR, 0.9, 1.0);
+        set_flash((1.0, 1.0, 1.0), 0.2, 1.0);
+        assert_eq!(OVERLAY.with(|o| o.borrow().quad()), Some((DAMAGE_COLOR, 0.9)));
+        clear();
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::VecDeque;
+
+pub const MAX_PARTICLES: usize = 512;
+
+type Color = (f32, f32, f32);
+
+// Shape of a burst, the caller picks the color
+pub struct Burst {
+    pub count: usize,
+    // Horizontal speed range in units per second, scaled by a random factor per particle
+    pub speed: f32,
+    pub lift: f32,
+    pub gravity: f32,
+    pub lifetime: f32,
+    pub size: f32,
+}
+
+pub const SPLASH: Burst = Burst { count: 24, speed: 2.5, lift: 5.0, gravity: 14.0, lifetime: 0.7, size: 0.18 };
+pub const DEBRIS: Burst = Burst { count: 20, speed: 4.0, lift: 4.0, gravity: 12.0, lifetime: 0.9, size: 0.2 };
+pub const SPARKLE: Burst = Burst { count: 12, speed: 2.5, lift: 5.0, gravity: 8.0, lifetime: 0.6, size: 0.25 };
+pub const DUST: Burst = Burst { count: 6, speed: 1.2, lift: 0.6, gravity: 0.0, lifetime: 0.35, size: 0.15 };
+
+struct Particle {
+    position: (f32, f32, f32),
+    velocity: (f32, f32, f32),
+    gravity: f32,
+    life: f32,
+    lifetime: f32,
+    size: f32,
+    color: Color,
+}
+
+// Purely cosmetic. Randomness comes from a private generator, never the world seed, so
+// effects can't shift anything the simulation draws.
+pub struct Particles {
+    pool: VecDeque<Particle>,
+    rng: u32,
+}
+
+impl Particles {
+    pub fn new() -> Self {
+        Particles { pool: VecDeque::with_capacity(MAX_PARTICLES), rng: 0x9e37_79b9 }
+    }
+
+    // xorshift32, in [0, 1)
+    fn next_rand(&mut self) -> f32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        (self.rng >> 8) as f32 / (1u32 << 24) as f32
+    }
+
+    // Once full the oldest particles make room
+    pub fn burst(&mut self, origin: (f32, f32, f32), color: Color, burst: &Burst) {
+        for i in 0..burst.count {
+            let angle = (i as f32 + self.next_rand()) / burst.count as f32 * std::f32::consts::TAU;
+            let speed = burst.speed * (0.5 + self.next_rand() * 0.5);
+            let lift = burst.lift * (0.6 + self.next_rand() * 0.4);
+            if self.pool.len() == MAX_PARTICLES {
+                self.pool.pop_front();
+            }
+            self.pool.push_back(Particle {
+                position: origin,
+                velocity: (angle.cos() * speed, lift, angle.sin() * speed),
+                gravity: burst.gravity,
+                life: burst.lifetime,
+                lifetime: burst.lifetime,
+                size: burst.size,
+                color,
+            });
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        for p in &mut self.pool {
+            p.position.0 += p.velocity.0 * dt;
+            p.position.1 += p.velocity.1 * dt;
+            p.position.2 += p.velocity.2 * dt;
+            p.velocity.1 -= p.gravity * dt;
+            p.life -= dt;
+        }
+        self.pool.retain(|p| p.life > 0.0);
+    }
+
+    pub fn clear(&mut self) {
+        self.pool.clear();
+    }
+
+    // Fills data with the layout draw_instanced_mesh expects: position, size, color, light.
+    // Particles shrink as they age.
+    pub fn instance_data(&self, light: f32, data: &mut Vec<f32>) {
+        data.clear();
+        for p in &self.pool {
+            let size = p.size * p.life / p.lifetime;
+            data.extend_from_slice(&[p.position.0, p.position.1, p.position.2, size, p.color.0, p.color.1, p.color.2, light]);
+        }
+    }
+}
+
+impl Default for Particles {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLOCK: Burst = Burst { count: 256, speed: 1.0, lift: 1.0, gravity: 0.0, lifetime: 0.5, size: 0.2 };
+
+    fn colors(particles: &Particles) -> Vec<f32> {
+        let mut data = Vec::new();
+        particles.instance_data(1.0, &mut data);
+        data.chunks(8).map(|p| p[4]).collect()
+    }
+
+    #[test]
+    fn pool_never_grows_past_the_cap() {
+        let mut particles = Particles::new();
+        for _ in 0..40 {
+            particles.burst((0.0, 0.0, 0.0), (1.0, 1.0, 1.0), &SPLASH);
+        }
+        assert_eq!(colors(&particles).len(), MAX_PARTICLES);
+    }
+
+    #[test]
+    fn full_pool_recycles_the_oldest_first() {
+        let mut particles = Particles::new();
+        particles.burst((0.0, 0.0, 0.0), (0.1, 0.0, 0.0), &BLOCK);
+        particles.burst((0.0, 0.0, 0.0), (0.2, 0.0, 0.0), &BLOCK);
+        particles.burst((0.0, 0.0, 0.0), (0.3, 0.0, 0.0), &Burst { count: 100, ..BLOCK });
+        let colors = colors(&particles);
+        assert_eq!(colors.len(), MAX_PARTICLES);
+        assert_eq!(colors.iter().filter(|&&c| c == 0.1).count(), 156);
+        assert_eq!(colors.iter().filter(|&&c| c == 0.2).count(), 256);
+        assert!(colors[MAX_PARTICLES - 100..].iter().all(|&c| c == 0.3));
+    }
+
+    #[test]
+    fn particles_shrink_and_expire_with_their_lifetime() {
+        let mut particles = Particles::new();
+        particles.burst((0.0, 0.0, 0.0), (1.0, 1.0, 1.0), &Burst { count: 4, ..BLOCK });
+        particles.update(0.25);
+        let mut data = Vec::new();
+        particles.instance_data(1.0, &mut data);
+        assert_eq!(data.len(), 4 * 8);
+        assert!(data.chunks(8).all(|p| (p[3] - 0.1).abs() < 1e-6));
+
+        particles.update(0.3);
+        particles.instance_data(1.0, &mut data);
+        assert!(data.is_empty());
+    }
+}
@@ -1,8 +1,14 @@
 use wasm_bindgen::prelude::*;
-use web_sys::{WebGlRenderingContext, WebGlProgram, WebGlBuffer, WebGlUniformLocation, HtmlCanvasElement, WebGlTexture, HtmlImageElement, AngleInstancedArrays};
+use web_sys::{WebGlRenderingContext, WebGlProgram, WebGlBuffer, WebGlUniformLocation, HtmlCanvasElement, WebGlTexture, HtmlImageElement, AngleInstancedArrays, WebGl2RenderingContext, WebGlFramebuffer, WebGlRenderbuffer};
 use nalgebra::{Matrix4, Vector3};
+use crate::engine::assets;
 use crate::engine::mesh::Mesh;
 use wasm_bindgen::JsCast;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+// Texels along each side of the sun's shadow map
+const SHADOW_MAP_SIZE: i32 = 2048;
 
 const VERTEX_SHADER: &str = r#"
     attribute vec3 aPosition;
@@ -80,13 +86,26 @@ const FRAGMENT_SHADER: &str = r#"
     uniform vec3 uUniformColor;
     uniform bool uUseUniformColor;
     uniform vec3 uTimeColor;
+    uniform float uAlpha;
     uniform bool uIsRing;
     uniform float uRingInnerRadius;
     
     uniform vec3 uLightPos;
+    uniform vec3 uLightColor;
+    // Optional second star, its intensity is the brightness at unit distance
+    // relative to the primary and falls off with the square of the distance
+    uniform vec3 uLight2Pos;
+    uniform vec3 uLight2Color;
+    uniform float uLight2Intensity;
     const vec3 lightColor = vec3(1.0, 1.0, 1.0);
     const float ambientStrength = 0.15;
 
+    // Sun shadow map for instanced blocks, depth packed into the RGBA channels
+    uniform bool uUseShadowMap;
+    uniform sampler2D uShadowMap;
+    uniform mat4 uLightViewProjection;
+    uniform float uShadowTexel;
+
     uniform bool uUseLighting;
     uniform bool uIsBlackHole;
     uniform bool uIsFrozen;
@@ -99,6 +118,24 @@ const FRAGMENT_SHADER: &str = r#"
         return vec2(u, v);
     }
 
+    float unpackDepth(vec4 rgba) {
+        return dot(rgba, vec4(1.0, 1.0 / 255.0, 1.0 / 65025.0, 1.0 / 16581375.0));
+    }
+
+    // Share of a 2x2 texel patch the sun reaches, anything outside the map is lit
+    float sunVisibility(float bias) {
+        vec4 lightSpace = uLightViewProjection * vec4(vFragPos, 1.0);
+        vec3 coord = lightSpace.xyz / lightSpace.w * 0.5 + 0.5;
+        if (coord.x <= 0.0 || coord.x >= 1.0 || coord.y <= 0.0 || coord.y >= 1.0 || coord.z >= 1.0) {
+            return 1.0;
+        }
+        float lit = 0.0;
+        for (int i = 0; i < 4; i++) {
+            vec2 offset = (vec2(mod(float(i), 2.0), floor(float(i) / 2.0)) - 0.5) * uShadowTexel;
+            lit += step(coord.z - bias, unpackDepth(texture2D(uShadowMap, coord.xy + offset)));
+        }
+        return lit * 0.25;
+    }
 
     void main() {
         vec3 color;
@@ -186,14 +223,28 @@ const FRAGMENT_SHADER: &str = r#"
             vec3 lightDir = normalize(uLightPos - vFragPos);
             
             float diff = max(dot(norm, lightDir), 0.0);
+            // Faces turned away are dark already, grazing ones need the larger bias
+            if (uUseShadowMap && diff > 0.0) {
+                diff *= sunVisibility(max(0.004 * (1.0 - diff), 0.001));
+            }
+
+            float falloff2 = 0.0;
+            float diff2 = 0.0;
+            if (uLight2Intensity > 0.0) {
+                float dist2 = length(vFragPos - uLight2Pos);
+                falloff2 = min(uLight2Intensity / max(dist2 * dist2, 0.000001), 1.0);
+                diff2 = max(dot(norm, normalize(uLight2Pos - vFragPos)), 0.0) * falloff2;
+            }
 
             if (uIsRing) {
                 diff = 0.8;
+                diff2 = 0.8 * falloff2;
                 ambient = vec3(0.4);
             }
 
             if (uIsFrozen) {
                 diff = 0.0;
+                diff2 = 0.0;
                 ambient *= 0.5;
             }
             
@@ -203,13 +254,14 @@ const FRAGMENT_SHADER: &str = r#"
                 ambient = vec3(1.0);
             }
             
-            vec3 diffuse = diff * lightColor;
+            // With uLight2Intensity at 0 the second term is exactly zero
+            vec3 diffuse = diff * uLightColor + diff2 * uLight2Color;
             
             vec3 dayColor = (ambient + diffuse) * color;
             
             if (uUseNightTexture == 1) {
                 vec3 nightColor = texture2D(uNightTexture, texCoord).rgb;
-                float mixFactor = smoothstep(0.0, 0.2, diff);
+                float mixFactor = smoothstep(0.0, 0.2, max(diff, diff2));
                 result = mix(nightColor, dayColor, mixFactor);
             } else {
                 result = dayColor;
@@ -232,7 +284,7 @@ const FRAGMENT_SHADER: &str = r#"
         
         result = pow(result, vec3(1.1));
 
-        gl_FragColor = vec4(result, alpha);
+        gl_FragColor = vec4(result, alpha * uAlpha);
     }
 "#;
 
@@ -269,6 +321,133 @@ const SKYBOX_FRAGMENT_SHADER: &str = r#"
     }
 "#;
 
+// Instanced blocks as seen from the sun, only depth comes out
+const SHADOW_VERTEX_SHADER: &str = r#"
+    attribute vec3 aPosition;
+    attribute vec3 aInstancePosition;
+    attribute float aInstanceScale;
+
+    uniform mat4 uLightViewProjection;
+
+    void main() {
+        gl_Position = uLightViewProjection * vec4(aPosition * aInstanceScale + aInstancePosition, 1.0);
+    }
+"#;
+
+// WebGL1 only has depth textures behind an extension, so depth goes into the color
+// channels at 8 bits each and unpackDepth puts it back together
+const SHADOW_FRAGMENT_SHADER: &str = r#"
+    precision highp float;
+
+    void main() {
+        vec4 depth = fract(gl_FragCoord.z * vec4(1.0, 255.0, 65025.0, 16581375.0));
+        depth -= depth.yzww * vec4(1.0 / 255.0, 1.0 / 255.0, 1.0 / 255.0, 0.0);
+        gl_FragColor = depth;
+    }
+"#;
+
+// Render target and program for the sun's depth pass
+struct ShadowMap {
+    framebuffer: WebGlFramebuffer,
+    texture: WebGlTexture,
+    depth: WebGlRenderbuffer,
+    program: WebGlProgram,
+    u_light_view_projection_loc: WebGlUniformLocation,
+}
+
+impl ShadowMap {
+    fn new(gl: &WebGlRenderingContext) -> Result<Self, JsValue> {
+        let program = create_shadow_program(gl)?;
+        let u_light_view_projection_loc = gl.get_uniform_location(&program, "uLightViewProjection")
+            .ok_or("Failed to get uLightViewProjection shadow")?;
+
+        let texture = gl.create_texture().ok_or("Failed to create shadow map texture")?;
+        gl.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&texture));
+        gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+            WebGlRenderingContext::TEXTURE_2D, 0, WebGlRenderingContext::RGBA as i32, SHADOW_MAP_SIZE, SHADOW_MAP_SIZE, 0,
+            WebGlRenderingContext::RGBA, WebGlRenderingContext::UNSIGNED_BYTE, None
+        )?;
+        for (param, value) in [
+            (WebGlRenderingContext::TEXTURE_MIN_FILTER, WebGlRenderingContext::NEAREST),
+            (WebGlRenderingContext::TEXTURE_MAG_FILTER, WebGlRenderingContext::NEAREST),
+            (WebGlRenderingContext::TEXTURE_WRAP_S, WebGlRenderingContext::CLAMP_TO_EDGE),
+            (WebGlRenderingContext::TEXTURE_WRAP_T, WebGlRenderingContext::CLAMP_TO_EDGE),
+        ] {
+            gl.tex_parameteri(WebGlRenderingContext::TEXTURE_2D, param, value as i32);
+        }
+
+        let depth = gl.create_renderbuffer().ok_or("Failed to create shadow map depth buffer")?;
+        gl.bind_renderbuffer(WebGlRenderingContext::RENDERBUFFER, Some(&depth));
+        gl.renderbuffer_storage(WebGlRenderingContext::RENDERBUFFER, WebGlRenderingContext::DEPTH_COMPONENT16, SHADOW_MAP_SIZE, SHADOW_MAP_SIZE);
+
+        let framebuffer = gl.create_framebuffer().ok_or("Failed to create shadow map framebuffer")?;
+        gl.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, Some(&framebuffer));
+        gl.framebuffer_texture_2d(WebGlRenderingContext::FRAMEBUFFER, WebGlRenderingContext::COLOR_ATTACHMENT0, WebGlRenderingContext::TEXTURE_2D, Some(&texture), 0);
+        gl.framebuffer_renderbuffer(WebGlRenderingContext::FRAMEBUFFER, WebGlRenderingContext::DEPTH_ATTACHMENT, WebGlRenderingContext::RENDERBUFFER, Some(&depth));
+        let complete = gl.check_framebuffer_status(WebGlRenderingContext::FRAMEBUFFER) == WebGlRenderingContext::FRAMEBUFFER_COMPLETE;
+        gl.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, None);
+
+        let map = ShadowMap { framebuffer, texture, depth, program, u_light_view_projection_loc };
+        if !complete {
+            map.delete(gl);
+            return Err(JsValue::from_str("Shadow map framebuffer is incomplete"));
+        }
+        Ok(map)
+    }
+
+    fn delete(&self, gl: &WebGlRenderingContext) {
+        gl.delete_framebuffer(Some(&self.framebuffer));
+        gl.delete_texture(Some(&self.texture));
+        gl.delete_renderbuffer(Some(&self.depth));
+        gl.delete_program(Some(&self.program));
+    }
+}
+
+// WebGL2 instances natively, WebGL1 needs ANGLE_instanced_arrays. The shaders stay
+// GLSL ES 1.00, which both accept.
+enum Instancing {
+    Native(WebGl2RenderingContext),
+    Angle(AngleInstancedArrays),
+}
+
+impl Instancing {
+    fn detect(gl: &WebGlRenderingContext) -> Result<Option<Self>, JsValue> {
+        if let Ok(gl2) = gl.clone().dyn_into::<WebGl2RenderingContext>() {
+            return Ok(Some(Instancing::Native(gl2)));
+        }
+        Ok(gl.get_extension("ANGLE_instanced_arrays")?.map(|e| Instancing::Angle(e.unchecked_into())))
+    }
+
+    fn vertex_attrib_divisor(&self, index: u32, divisor: u32) {
+        match self {
+            Instancing::Native(gl) => gl.vertex_attrib_divisor(index, divisor),
+            Instancing::Angle(ext) => ext.vertex_attrib_divisor_angle(index, divisor),
+        }
+    }
+
+    fn draw_elements_instanced(&self, mode: u32, count: i32, type_: u32, offset: i32, instances: i32) {
+        match self {
+            Instancing::Native(gl) => gl.draw_elements_instanced_with_i32(mode, count, type_, offset, instances),
+            Instancing::Angle(ext) => ext.draw_elements_instanced_angle_with_i32(mode, count, type_, offset, instances),
+        }
+    }
+}
+
+// A point light added to the primary one, as a binary's companion star. intensity is its
+// strength at distance 1 relative to the primary, 0 turns it off.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SecondLight {
+    pub position: Vector3<f32>,
+    pub color: (f32, f32, f32),
+    pub intensity: f32,
+}
+
+impl SecondLight {
+    pub fn off() -> Self {
+        SecondLight { position: Vector3::zeros(), color: (1.0, 1.0, 1.0), intensity: 0.0 }
+    }
+}
+
 pub struct Renderer {
     pub gl: WebGlRenderingContext,
     program: WebGlProgram,
@@ -278,12 +457,17 @@ pub struct Renderer {
     u_uniform_color_location: WebGlUniformLocation,
     u_use_uniform_color_location: WebGlUniformLocation,
     u_time_color_location: WebGlUniformLocation,
+    u_alpha_location: WebGlUniformLocation,
     u_use_texture_location: WebGlUniformLocation,
     u_texture_location: WebGlUniformLocation,
     u_use_night_texture_location: WebGlUniformLocation,
     u_night_texture_location: WebGlUniformLocation,
     pub u_use_lighting_location: WebGlUniformLocation,
     pub u_light_pos_location: WebGlUniformLocation,
+    u_light_color_location: WebGlUniformLocation,
+    u_light2_pos_location: WebGlUniformLocation,
+    u_light2_color_location: WebGlUniformLocation,
+    u_light2_intensity_location: WebGlUniformLocation,
     pub u_is_ring_location: WebGlUniformLocation,
     pub u_ring_inner_radius_location: WebGlUniformLocation,
     pub u_is_black_hole_location: WebGlUniformLocation,
@@ -297,7 +481,7 @@ pub struct Renderer {
     dynamic_index_buffer: WebGlBuffer,
     
     // Instancing
-    instanced_ext: Option<AngleInstancedArrays>,
+    instancing: Option<Instancing>,
     instanced_program: WebGlProgram,
     u_instanced_view_loc: WebGlUniformLocation,
     u_instanced_proj_loc: WebGlUniformLocation,
@@ -306,13 +490,33 @@ pub struct Renderer {
     u_instanced_time_color_loc: WebGlUniformLocation,
     u_instanced_use_texture_loc: WebGlUniformLocation,
     u_instanced_texture_loc: WebGlUniformLocation,
+    u_instanced_use_shadow_map_loc: WebGlUniformLocation,
+    u_instanced_shadow_map_loc: WebGlUniformLocation,
+    u_instanced_light_view_projection_loc: WebGlUniformLocation,
+    u_instanced_shadow_texel_loc: WebGlUniformLocation,
     instance_data_buffer: WebGlBuffer,
 
+    // Built on demand, and the sun's view-projection while the last pass is still valid
+    shadow_map: Option<ShadowMap>,
+    shadow_view_projection: Cell<Option<Matrix4<f32>>>,
+
     // Skybox
     skybox_program: WebGlProgram,
     u_skybox_view_loc: WebGlUniformLocation,
     u_skybox_proj_loc: WebGlUniformLocation,
     u_skybox_texture_loc: WebGlUniformLocation,
+
+    // Every texture create_texture handed out, so a lost context can load them again
+    texture_urls: RefCell<Vec<(WebGlTexture, String)>>,
+    // Handles from before the last reinit and what replaced them
+    restored_textures: Vec<(WebGlTexture, WebGlTexture)>,
+    // Textures built from pixels, only kept so drop can delete them
+    data_textures: RefCell<Vec<WebGlTexture>>,
+    // Filter chosen per texture, shared with the image onload so a choice made before the
+    // image arrives is put back over what the upload sets
+    texture_filters: Rc<RefCell<Vec<(WebGlTexture, bool)>>>,
+    // Loaded textures without mipmaps, which a mipmap filter would leave incomplete
+    unmipmapped_textures: Rc<RefCell<Vec<WebGlTexture>>>,
 }
 
 impl Renderer {
@@ -335,6 +539,8 @@ impl Renderer {
             .ok_or("Failed to get uUseUniformColor location")?;
         let u_time_color_location = gl.get_uniform_location(&program, "uTimeColor")
             .ok_or("Failed to get uTimeColor location")?;
+        let u_alpha_location = gl.get_uniform_location(&program, "uAlpha")
+            .ok_or("Failed to get uAlpha location")?;
         let u_use_texture_location = gl.get_uniform_location(&program, "uUseTexture")
             .ok_or("Failed to get uUseTexture location")?;
         let u_texture_location = gl.get_uniform_location(&program, "uTexture")
@@ -347,6 +553,14 @@ impl Renderer {
             .ok_or("Failed to get uUseLighting location")?;
         let u_light_pos_location = gl.get_uniform_location(&program, "uLightPos")
             .ok_or("Failed to get uLightPos location")?;
+        let u_light_color_location = gl.get_uniform_location(&program, "uLightColor")
+            .ok_or("Failed to get uLightColor location")?;
+        let u_light2_pos_location = gl.get_uniform_location(&program, "uLight2Pos")
+            .ok_or("Failed to get uLight2Pos location")?;
+        let u_light2_color_location = gl.get_uniform_location(&program, "uLight2Color")
+            .ok_or("Failed to get uLight2Color location")?;
+        let u_light2_intensity_location = gl.get_uniform_location(&program, "uLight2Intensity")
+            .ok_or("Failed to get uLight2Intensity location")?;
         let u_is_ring_location = gl.get_uniform_location(&program, "uIsRing")
             .ok_or("Failed to get uIsRing location")?;
         let u_ring_inner_radius_location = gl.get_uniform_location(&program, "uRingInnerRadius")
@@ -361,7 +575,7 @@ impl Renderer {
             .ok_or("Failed to get uBackgroundTexture location")?;
 
         // Instancing setup
-        let instanced_ext = gl.get_extension("ANGLE_instanced_arrays")?.map(|e| e.unchecked_into::<AngleInstancedArrays>());
+        let instancing = Instancing::detect(&gl)?;
         let instanced_program = create_instanced_program(&gl)?;
         let u_instanced_view_loc = gl.get_uniform_location(&instanced_program, "uView").ok_or("Failed to get uView")?;
         let u_instanced_proj_loc = gl.get_uniform_location(&instanced_program, "uProjection").ok_or("Failed to get uProjection")?;
@@ -370,6 +584,10 @@ impl Renderer {
         let u_instanced_time_color_loc = gl.get_uniform_location(&instanced_program, "uTimeColor").ok_or("Failed to get uTimeColor")?;
         let u_instanced_use_texture_loc = gl.get_uniform_location(&instanced_program, "uUseTexture").ok_or("Failed to get uUseTexture instanced")?;
         let u_instanced_texture_loc = gl.get_uniform_location(&instanced_program, "uTexture").ok_or("Failed to get uTexture instanced")?;
+        let u_instanced_use_shadow_map_loc = gl.get_uniform_location(&instanced_program, "uUseShadowMap").ok_or("Failed to get uUseShadowMap instanced")?;
+        let u_instanced_shadow_map_loc = gl.get_uniform_location(&instanced_program, "uShadowMap").ok_or("Failed to get uShadowMap instanced")?;
+        let u_instanced_light_view_projection_loc = gl.get_uniform_location(&instanced_program, "uLightViewProjection").ok_or("Failed to get uLightViewProjection instanced")?;
+        let u_instanced_shadow_texel_loc = gl.get_uniform_location(&instanced_program, "uShadowTexel").ok_or("Failed to get uShadowTexel instanced")?;
         let instance_data_buffer = gl.create_buffer().ok_or("Failed to create instance buffer")?;
 
         // Skybox setup
@@ -407,8 +625,12 @@ impl Renderer {
 
         // Initialize time color to white (no filter)
         gl.uniform3f(Some(&u_time_color_location), 1.0, 1.0, 1.0);
+        gl.uniform1f(Some(&u_alpha_location), 1.0);
         // Initialize light pos to 0,0,0
         gl.uniform3f(Some(&u_light_pos_location), 0.0, 0.0, 0.0);
+        // White single light until a scene says otherwise
+        gl.uniform3f(Some(&u_light_color_location), 1.0, 1.0, 1.0);
+        gl.uniform1f(Some(&u_light2_intensity_location), 0.0);
 
         Ok(Renderer {
             gl,
@@ -419,6 +641,7 @@ impl Renderer {
             u_uniform_color_location,
             u_use_uniform_color_location,
             u_time_color_location,
+            u_alpha_location,
             u_use_texture_location,
             u_texture_location,
             u_use_night_texture_location,
@@ -430,13 +653,17 @@ impl Renderer {
             dynamic_index_buffer,
             u_use_lighting_location,
             u_light_pos_location,
+            u_light_color_location,
+            u_light2_pos_location,
+            u_light2_color_location,
+            u_light2_intensity_location,
             u_is_ring_location,
             u_ring_inner_radius_location,
             u_is_black_hole_location,
             u_is_frozen_location,
             u_camera_pos_location,
             u_background_texture_location,
-            instanced_ext,
+            instancing,
             instanced_program,
             u_instanced_view_loc,
             u_instanced_proj_loc,
@@ -445,18 +672,61 @@ impl Renderer {
             u_instanced_time_color_loc,
             u_instanced_use_texture_loc,
             u_instanced_texture_loc,
+            u_instanced_use_shadow_map_loc,
+            u_instanced_shadow_map_loc,
+            u_instanced_light_view_projection_loc,
+            u_instanced_shadow_texel_loc,
             instance_data_buffer,
+            shadow_map: None,
+            shadow_view_projection: Cell::new(None),
             skybox_program,
             u_skybox_view_loc,
             u_skybox_proj_loc,
             u_skybox_texture_loc,
+            texture_urls: RefCell::new(Vec::new()),
+            restored_textures: Vec::new(),
+            data_textures: RefCell::new(Vec::new()),
+            texture_filters: Rc::new(RefCell::new(Vec::new())),
+            unmipmapped_textures: Rc::new(RefCell::new(Vec::new())),
         })
     }
 
+    // After the context comes back every program, buffer and texture is gone. Rebuilds
+    // them on the same context and reloads the textures; owners swap their handles
+    // with restore_texture.
+    pub fn reinit(&mut self) -> Result<(), JsValue> {
+        // A failed rebuild leaves the URLs where they were for the next attempt
+        let fresh = Renderer::new(self.gl.clone())?;
+        let lost = self.texture_urls.take();
+        *self = fresh;
+        self.restored_textures = lost.into_iter()
+            .filter_map(|(old, url)| self.create_texture(&url).ok().map(|new| (old, new)))
+            .collect();
+        Ok(())
+    }
+
+    // Textures from create_texture_from_rgba aren't remembered, their owners rebuild them
+    pub fn restore_texture(&self, texture: &mut WebGlTexture) {
+        if let Some((_, new)) = self.restored_textures.iter().find(|(old, _)| old == texture) {
+            *texture = new.clone();
+        }
+    }
+
     pub fn set_light_position(&self, x: f32, y: f32, z: f32) {
         self.gl.uniform3f(Some(&self.u_light_pos_location), x, y, z);
     }
 
+    pub fn set_light_color(&self, r: f32, g: f32, b: f32) {
+        self.gl.uniform3f(Some(&self.u_light_color_location), r, g, b);
+    }
+
+    pub fn set_second_light(&self, light: SecondLight) {
+        let (r, g, b) = light.color;
+        self.gl.uniform3f(Some(&self.u_light2_pos_location), light.position.x, light.position.y, light.position.z);
+        self.gl.uniform3f(Some(&self.u_light2_color_location), r, g, b);
+        self.gl.uniform1f(Some(&self.u_light2_intensity_location), light.intensity);
+    }
+
     pub fn clear(&self, r: f32, g: f32, b: f32) {
         self.gl.clear_color(r, g, b, 1.0);
         self.gl.clear(WebGlRenderingContext::COLOR_BUFFER_BIT | WebGlRenderingContext::DEPTH_BUFFER_BIT);
@@ -466,6 +736,11 @@ impl Renderer {
         self.gl.uniform3f(Some(&self.u_time_color_location), r, g, b);
     }
 
+    // Global opacity for main-program draws, only visible while blending is enabled
+    pub fn set_alpha(&self, alpha: f32) {
+        self.gl.uniform1f(Some(&self.u_alpha_location), alpha);
+    }
+
     pub fn enable_depth_test(&self) {
         self.gl.enable(WebGlRenderingContext::DEPTH_TEST);
     }
@@ -488,6 +763,23 @@ impl Renderer {
         self.gl.viewport(0, 0, width, height);
     }
 
+    // Canvas backing pixels, which differ from its CSS size on high density screens
+    pub fn drawing_buffer_size(&self) -> (i32, i32) {
+        (self.gl.drawing_buffer_width(), self.gl.drawing_buffer_height())
+    }
+
+    // Restricts drawing (including clears) to a rectangle in GL window coordinates
+    pub fn set_sub_viewport(&self, x: i32, y: i32, width: i32, height: i32) {
+        self.gl.enable(WebGlRenderingContext::SCISSOR_TEST);
+        self.gl.scissor(x, y, width, height);
+        self.gl.viewport(x, y, width, height);
+    }
+
+    pub fn reset_viewport(&self, width: i32, height: i32) {
+        self.gl.disable(WebGlRenderingContext::SCISSOR_TEST);
+        self.gl.viewport(0, 0, width, height);
+    }
+
     pub fn clear_screen(&self, r: f32, g: f32, b: f32) {
         self.gl.clear_color(r, g, b, 1.0);
         self.gl.clear(WebGlRenderingContext::COLOR_BUFFER_BIT | WebGlRenderingContext::DEPTH_BUFFER_BIT);
@@ -498,6 +790,8 @@ impl Renderer {
     }
 
     pub fn draw_cube(&self, x: f32, y: f32, z: f32, w: f32, h: f32, d: f32, r: f32, g: f32, b: f32, projection: &Matrix4<f32>, view: &Matrix4<f32>) {
+        // Callers may have just drawn with the instanced program
+        self.gl.use_program(Some(&self.program));
         self.gl.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&self.unit_cube_vertex_buffer));
         self.gl.bind_buffer(WebGlRenderingContext::ELEMENT_ARRAY_BUFFER, Some(&self.unit_cube_index_buffer));
 
@@ -540,7 +834,160 @@ impl Renderer {
         );
     }
 
+    // Screen-space drawing uses CSS-like pixel coordinates with the origin at the top-left
+    pub fn begin_screen_overlay(&self) {
+        self.gl.use_program(Some(&self.program));
+        self.gl.disable(WebGlRenderingContext::DEPTH_TEST);
+        self.gl.disable(WebGlRenderingContext::CULL_FACE);
+        self.set_time_color(1.0, 1.0, 1.0);
+    }
+
+    pub fn end_screen_overlay(&self) {
+        self.gl.enable(WebGlRenderingContext::DEPTH_TEST);
+    }
+
+    // color is (r, g, b) and screen the canvas's (width, height) in pixels, as for the other screen draws
+    pub fn draw_screen_rect(&self, x: f32, y: f32, w: f32, h: f32, (r, g, b): (f32, f32, f32), (screen_width, screen_height): (f32, f32)) {
+        let projection = Matrix4::new_orthographic(0.0, screen_width, screen_height, 0.0, -1.0, 1.0);
+        self.draw_cube(x + w / 2.0, y + h / 2.0, 0.0, w, h, 1.0, r, g, b, &projection, &Matrix4::identity());
+    }
+
+    // Seven-segment style text: digits plus the handful of letters the segments can show
+    pub fn draw_screen_text(&self, text: &str, x: f32, y: f32, size: f32, color: (f32, f32, f32), screen: (f32, f32)) {
+        let char_width = size * 0.6;
+        let t = size * 0.12;
+        let half = size / 2.0;
+        let mut cx = x;
+
+        for ch in text.chars() {
+            match ch {
+                '.' => {
+                    self.draw_screen_rect(cx, y + size - t, t, t, color, screen);
+                    cx += t * 2.5;
+                    continue;
+                }
+                ':' => {
+                    self.draw_screen_rect(cx, y + size * 0.25, t, t, color, screen);
+                    self.draw_screen_rect(cx, y + size * 0.7, t, t, color, screen);
+                    cx += t * 2.5;
+                    continue;
+                }
+                _ => {}
+            }
+
+            let mask = seven_segment_mask(ch);
+            let segment = |bit: u8, sx: f32, sy: f32, sw: f32, sh: f32| {
+                if mask & bit != 0 {
+                    self.draw_screen_rect(sx, sy, sw, sh, color, screen);
+                }
+            };
+            segment(SEG_A, cx, y, char_width, t);
+            segment(SEG_B, cx + char_width - t, y, t, half);
+            segment(SEG_C, cx + char_width - t, y + half, t, half);
+            segment(SEG_D, cx, y + size - t, char_width, t);
+            segment(SEG_E, cx, y + half, t, half);
+            segment(SEG_F, cx, y, t, half);
+            segment(SEG_G, cx, y + half - t / 2.0, char_width, t);
+
+            cx += char_width + size * 0.25;
+        }
+    }
+
+    // Outline as x, y pairs in screen pixels, filled as a fan around the first point
+    pub fn draw_screen_polygon(&self, points: &[f32], (r, g, b): (f32, f32, f32), (screen_width, screen_height): (f32, f32)) {
+        let vertices: Vec<f32> = points.chunks(2).flat_map(|p| [p[0], p[1], 0.0]).collect();
+        self.gl.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&self.dynamic_vertex_buffer));
+        unsafe {
+            let vert_array = js_sys::Float32Array::view(&vertices);
+            self.gl.buffer_data_with_array_buffer_view(
+                WebGlRenderingContext::ARRAY_BUFFER,
+                &vert_array,
+                WebGlRenderingContext::DYNAMIC_DRAW
+            );
+        }
+
+        let pos_loc = self.gl.get_attrib_location(&self.program, "aPosition") as u32;
+        let col_loc = self.gl.get_attrib_location(&self.program, "aColor") as u32;
+        let tex_loc = self.gl.get_attrib_location(&self.program, "aTexCoord") as u32;
+        let norm_loc = self.gl.get_attrib_location(&self.program, "aNormal") as u32;
+
+        self.gl.vertex_attrib_pointer_with_i32(pos_loc, 3, WebGlRenderingContext::FLOAT, false, 0, 0);
+        self.gl.enable_vertex_attrib_array(pos_loc);
+        self.gl.disable_vertex_attrib_array(col_loc);
+        self.gl.disable_vertex_attrib_array(tex_loc);
+        self.gl.disable_vertex_attrib_array(norm_loc);
+
+        self.gl.uniform1i(Some(&self.u_use_uniform_color_location), 1);
+        self.gl.uniform1i(Some(&self.u_use_texture_location), 0);
+        self.gl.uniform1i(Some(&self.u_use_lighting_location), 0);
+        self.gl.uniform1i(Some(&self.u_is_black_hole_location), 0);
+        self.gl.uniform3f(Some(&self.u_uniform_color_location), r, g, b);
+
+        let projection = Matrix4::new_orthographic(0.0, screen_width, screen_height, 0.0, -1.0, 1.0);
+        let mvp_array: [f32; 16] = projection.as_slice().try_into().unwrap();
+        self.gl.uniform_matrix4fv_with_f32_array(Some(&self.mvp_location), false, &mvp_array);
+
+        self.gl.draw_arrays(WebGlRenderingContext::TRIANGLE_FAN, 0, (vertices.len() / 3) as i32);
+    }
+
+    // Texture row 0 lands at the top edge of the rect
+    pub fn draw_screen_texture(&self, texture: &WebGlTexture, x: f32, y: f32, w: f32, h: f32, (screen_width, screen_height): (f32, f32)) {
+        // Position, white vertex color, uv
+        let vertices: [f32; 32] = [
+            x, y, 0.0, 1.0, 1.0, 1.0, 0.0, 0.0,
+            x + w, y, 0.0, 1.0, 1.0, 1.0, 1.0, 0.0,
+            x + w, y + h, 0.0, 1.0, 1.0, 1.0, 1.0, 1.0,
+            x, y + h, 0.0, 1.0, 1.0, 1.0, 0.0, 1.0,
+        ];
+        self.gl.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&self.dynamic_vertex_buffer));
+        unsafe {
+            let vert_array = js_sys::Float32Array::view(&vertices);
+            self.gl.buffer_data_with_array_buffer_view(
+                WebGlRenderingContext::ARRAY_BUFFER,
+                &vert_array,
+                WebGlRenderingContext::DYNAMIC_DRAW
+            );
+        }
+
+        let pos_loc = self.gl.get_attrib_location(&self.program, "aPosition") as u32;
+        let col_loc = self.gl.get_attrib_location(&self.program, "aColor") as u32;
+        let tex_loc = self.gl.get_attrib_location(&self.program, "aTexCoord") as u32;
+        let norm_loc = self.gl.get_attrib_location(&self.program, "aNormal") as u32;
+
+        self.gl.vertex_attrib_pointer_with_i32(pos_loc, 3, WebGlRenderingContext::FLOAT, false, 32, 0);
+        self.gl.enable_vertex_attrib_array(pos_loc);
+        self.gl.vertex_attrib_pointer_with_i32(col_loc, 3, WebGlRenderingContext::FLOAT, false, 32, 12);
+        self.gl.enable_vertex_attrib_array(col_loc);
+        self.gl.vertex_attrib_pointer_with_i32(tex_loc, 2, WebGlRenderingContext::FLOAT, false, 32, 24);
+        self.gl.enable_vertex_attrib_array(tex_loc);
+        self.gl.disable_vertex_attrib_array(norm_loc);
+
+        self.gl.uniform1i(Some(&self.u_use_uniform_color_location), 0);
+        self.gl.uniform1i(Some(&self.u_use_lighting_location), 0);
+        self.gl.uniform1i(Some(&self.u_is_black_hole_location), 0);
+        self.gl.active_texture(WebGlRenderingContext::TEXTURE0);
+        self.gl.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(texture));
+        self.gl.uniform1i(Some(&self.u_use_texture_location), 1);
+        self.gl.uniform1i(Some(&self.u_texture_location), 0);
+
+        let projection = Matrix4::new_orthographic(0.0, screen_width, screen_height, 0.0, -1.0, 1.0);
+        let mvp_array: [f32; 16] = projection.as_slice().try_into().unwrap();
+        self.gl.uniform_matrix4fv_with_f32_array(Some(&self.mvp_location), false, &mvp_array);
+
+        self.gl.draw_arrays(WebGlRenderingContext::TRIANGLE_FAN, 0, 4);
+    }
+
+    pub fn screen_text_width(text: &str, size: f32) -> f32 {
+        let t = size * 0.12;
+        text.chars().map(|ch| match ch {
+            '.' | ':' => t * 2.5,
+            _ => size * 0.6 + size * 0.25,
+        }).sum()
+    }
+
     pub fn draw_textured_cube(&self, x: f32, y: f32, z: f32, w: f32, h: f32, d: f32, texture: Option<&WebGlTexture>, projection: &Matrix4<f32>, view: &Matrix4<f32>) {
+        // Callers may come straight from the skybox or instanced passes
+        self.gl.use_program(Some(&self.program));
         self.gl.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&self.unit_cube_vertex_buffer));
         self.gl.bind_buffer(WebGlRenderingContext::ELEMENT_ARRAY_BUFFER, Some(&self.unit_cube_index_buffer));
 
@@ -651,6 +1098,10 @@ impl Renderer {
         self.gl.depth_mask(true);
     }
 
+    pub fn supports_instancing(&self) -> bool {
+        self.instancing.is_some()
+    }
+
     pub fn draw_instanced_mesh(
         &self,
         mesh: &Mesh,
@@ -661,12 +1112,10 @@ impl Renderer {
         light_pos: &Vector3<f32>,
         texture: Option<&WebGlTexture>,
     ) {
-        let ext = match &self.instanced_ext {
+        // Runs every frame, so callers that must draw anyway check supports_instancing first
+        let ext = match &self.instancing {
             Some(e) => e,
-            None => {
-                web_sys::console::log_1(&"Instanced extension not found".into());
-                return;
-            },
+            None => return,
         };
 
         self.gl.use_program(Some(&self.instanced_program));
@@ -688,6 +1137,24 @@ impl Renderer {
             self.gl.uniform1i(Some(&self.u_instanced_use_texture_loc), 0);
         }
 
+        match (&self.shadow_map, self.shadow_view_projection.get()) {
+            (Some(map), Some(light_view_projection)) => {
+                self.gl.active_texture(WebGlRenderingContext::TEXTURE1);
+                self.gl.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&map.texture));
+                self.gl.uniform1i(Some(&self.u_instanced_shadow_map_loc), 1);
+                self.gl.uniform1i(Some(&self.u_instanced_use_shadow_map_loc), 1);
+                self.gl.uniform_matrix4fv_with_f32_array(Some(&self.u_instanced_light_view_projection_loc), false, light_view_projection.as_slice());
+                self.gl.uniform1f(Some(&self.u_instanced_shadow_texel_loc), 1.0 / SHADOW_MAP_SIZE as f32);
+                self.gl.active_texture(WebGlRenderingContext::TEXTURE0);
+            }
+            _ => self.gl.uniform1i(Some(&self.u_instanced_use_shadow_map_loc), 0),
+        }
+
+        self.draw_instances(ext, &self.instanced_program, mesh, instance_data, count);
+    }
+
+    // Uploads the mesh and instance data and draws with whichever instanced program is bound
+    fn draw_instances(&self, ext: &Instancing, program: &WebGlProgram, mesh: &Mesh, instance_data: &[f32], count: i32) {
         self.gl.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&self.dynamic_vertex_buffer));
         unsafe {
             let vert_array = js_sys::Float32Array::view(&mesh.vertices);
@@ -708,9 +1175,9 @@ impl Renderer {
             );
         }
 
-        let pos_loc = self.gl.get_attrib_location(&self.instanced_program, "aPosition");
-        let norm_loc = self.gl.get_attrib_location(&self.instanced_program, "aNormal");
-        let tex_loc = self.gl.get_attrib_location(&self.instanced_program, "aTexCoord");
+        let pos_loc = self.gl.get_attrib_location(program, "aPosition");
+        let norm_loc = self.gl.get_attrib_location(program, "aNormal");
+        let tex_loc = self.gl.get_attrib_location(program, "aTexCoord");
 
         if pos_loc != -1 {
             self.gl.vertex_attrib_pointer_with_i32(pos_loc as u32, 3, WebGlRenderingContext::FLOAT, false, 44, 0);
@@ -737,38 +1204,38 @@ impl Renderer {
             );
         }
 
-        let i_pos_loc = self.gl.get_attrib_location(&self.instanced_program, "aInstancePosition");
-        let i_scale_loc = self.gl.get_attrib_location(&self.instanced_program, "aInstanceScale");
-        let i_col_loc = self.gl.get_attrib_location(&self.instanced_program, "aInstanceColor");
-        let i_light_loc = self.gl.get_attrib_location(&self.instanced_program, "aInstanceLight");
+        let i_pos_loc = self.gl.get_attrib_location(program, "aInstancePosition");
+        let i_scale_loc = self.gl.get_attrib_location(program, "aInstanceScale");
+        let i_col_loc = self.gl.get_attrib_location(program, "aInstanceColor");
+        let i_light_loc = self.gl.get_attrib_location(program, "aInstanceLight");
 
         let stride = 32; // 3+1+3+1 = 8 floats * 4 bytes = 32 bytes
 
         if i_pos_loc != -1 {
             self.gl.vertex_attrib_pointer_with_i32(i_pos_loc as u32, 3, WebGlRenderingContext::FLOAT, false, stride, 0);
             self.gl.enable_vertex_attrib_array(i_pos_loc as u32);
-            ext.vertex_attrib_divisor_angle(i_pos_loc as u32, 1);
+            ext.vertex_attrib_divisor(i_pos_loc as u32, 1);
         }
 
         if i_scale_loc != -1 {
             self.gl.vertex_attrib_pointer_with_i32(i_scale_loc as u32, 1, WebGlRenderingContext::FLOAT, false, stride, 12);
             self.gl.enable_vertex_attrib_array(i_scale_loc as u32);
-            ext.vertex_attrib_divisor_angle(i_scale_loc as u32, 1);
+            ext.vertex_attrib_divisor(i_scale_loc as u32, 1);
         }
 
         if i_col_loc != -1 {
             self.gl.vertex_attrib_pointer_with_i32(i_col_loc as u32, 3, WebGlRenderingContext::FLOAT, false, stride, 16);
             self.gl.enable_vertex_attrib_array(i_col_loc as u32);
-            ext.vertex_attrib_divisor_angle(i_col_loc as u32, 1);
+            ext.vertex_attrib_divisor(i_col_loc as u32, 1);
         }
 
         if i_light_loc != -1 {
             self.gl.vertex_attrib_pointer_with_i32(i_light_loc as u32, 1, WebGlRenderingContext::FLOAT, false, stride, 28);
             self.gl.enable_vertex_attrib_array(i_light_loc as u32);
-            ext.vertex_attrib_divisor_angle(i_light_loc as u32, 1);
+            ext.vertex_attrib_divisor(i_light_loc as u32, 1);
         }
 
-        ext.draw_elements_instanced_angle_with_i32(
+        ext.draw_elements_instanced(
             WebGlRenderingContext::TRIANGLES,
             mesh.indices.len() as i32,
             WebGlRenderingContext::UNSIGNED_SHORT,
@@ -777,23 +1244,83 @@ impl Renderer {
         );
 
         if i_pos_loc != -1 {
-            ext.vertex_attrib_divisor_angle(i_pos_loc as u32, 0);
+            ext.vertex_attrib_divisor(i_pos_loc as u32, 0);
             self.gl.disable_vertex_attrib_array(i_pos_loc as u32);
         }
         if i_scale_loc != -1 {
-            ext.vertex_attrib_divisor_angle(i_scale_loc as u32, 0);
+            ext.vertex_attrib_divisor(i_scale_loc as u32, 0);
             self.gl.disable_vertex_attrib_array(i_scale_loc as u32);
         }
         if i_col_loc != -1 {
-            ext.vertex_attrib_divisor_angle(i_col_loc as u32, 0);
+            ext.vertex_attrib_divisor(i_col_loc as u32, 0);
             self.gl.disable_vertex_attrib_array(i_col_loc as u32);
         }
         if i_light_loc != -1 {
-            ext.vertex_attrib_divisor_angle(i_light_loc as u32, 0);
+            ext.vertex_attrib_divisor(i_light_loc as u32, 0);
             self.gl.disable_vertex_attrib_array(i_light_loc as u32);
         }
     }
 
+    // Creates the sun's shadow map or frees it. Without it the instanced shader skips the
+    // shadow test and only baked lighting shows.
+    pub fn set_shadow_map(&mut self, enabled: bool) -> Result<(), JsValue> {
+        self.shadow_view_projection.set(None);
+        match (enabled, &self.shadow_map) {
+            (true, None) => self.shadow_map = Some(ShadowMap::new(&self.gl)?),
+            (false, Some(map)) => {
+                map.delete(&self.gl);
+                self.shadow_map = None;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // Starts a depth pass from the sun, false when there's no shadow map to draw into.
+    // Casters go in with draw_shadow_caster and end_shadow_pass puts the screen back.
+    pub fn begin_shadow_pass(&self, light_view_projection: &Matrix4<f32>) -> bool {
+        let map = match &self.shadow_map {
+            Some(map) if self.instancing.is_some() => map,
+            _ => return false,
+        };
+        self.gl.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, Some(&map.framebuffer));
+        self.gl.viewport(0, 0, SHADOW_MAP_SIZE, SHADOW_MAP_SIZE);
+        // Cleared to the far plane, so empty texels never shadow anything
+        self.gl.clear_color(1.0, 1.0, 1.0, 1.0);
+        self.gl.clear(WebGlRenderingContext::COLOR_BUFFER_BIT | WebGlRenderingContext::DEPTH_BUFFER_BIT);
+        self.gl.use_program(Some(&map.program));
+        self.gl.uniform_matrix4fv_with_f32_array(Some(&map.u_light_view_projection_loc), false, light_view_projection.as_slice());
+        self.shadow_view_projection.set(Some(*light_view_projection));
+        true
+    }
+
+    pub fn draw_shadow_caster(&self, mesh: &Mesh, instance_data: &[f32], count: i32) {
+        if let (Some(ext), Some(map)) = (&self.instancing, &self.shadow_map) {
+            self.draw_instances(ext, &map.program, mesh, instance_data, count);
+        }
+    }
+
+    pub fn end_shadow_pass(&self) {
+        self.gl.bind_framebuffer(WebGlRenderingContext::FRAMEBUFFER, None);
+        let (width, height) = self.drawing_buffer_size();
+        self.gl.viewport(0, 0, width, height);
+    }
+
+    // Later frames draw without shadows until another pass runs, as while the sun is down
+    pub fn clear_shadow_pass(&self) {
+        self.shadow_view_projection.set(None);
+    }
+
+    // Same transform draw_mesh uploads, so callers can place things on a mesh's surface on the CPU.
+    // rotation holds the angles about x, y and z.
+    pub fn model_matrix(position: Vector3<f32>, size: Vector3<f32>, rotation: Vector3<f32>) -> Matrix4<f32> {
+        Matrix4::new_translation(&position) *
+            Matrix4::from_axis_angle(&Vector3::z_axis(), rotation.z) *
+            Matrix4::from_axis_angle(&Vector3::x_axis(), rotation.x) *
+            Matrix4::from_axis_angle(&Vector3::y_axis(), rotation.y) *
+            Matrix4::new_nonuniform_scaling(&size)
+    }
+
     pub fn draw_mesh(&self, mesh: &Mesh, x: f32, y: f32, z: f32, w: f32, h: f32, d: f32, rotation_x: f32, rotation_y: f32, rotation_z: f32, projection: &Matrix4<f32>, view: &Matrix4<f32>, texture: Option<&WebGlTexture>, night_texture: Option<&WebGlTexture>, color_override: Option<(f32, f32, f32)>, is_ring: bool, ring_inner_radius: Option<f32>, use_lighting: bool, is_black_hole: bool, is_frozen: bool, camera_pos: Option<(f32, f32, f32)>, background_texture: Option<&WebGlTexture>) {
         self.gl.use_program(Some(&self.program));
         
@@ -890,11 +1417,7 @@ impl Renderer {
         self.gl.vertex_attrib_pointer_with_i32(norm_loc, 3, WebGlRenderingContext::FLOAT, false, 44, 32);
         self.gl.enable_vertex_attrib_array(norm_loc);
 
-        let model = Matrix4::new_translation(&Vector3::new(x, y, z)) *
-                    Matrix4::from_axis_angle(&Vector3::z_axis(), rotation_z) *
-                    Matrix4::from_axis_angle(&Vector3::x_axis(), rotation_x) *
-                    Matrix4::from_axis_angle(&Vector3::y_axis(), rotation_y) *
-                    Matrix4::new_nonuniform_scaling(&Vector3::new(w, h, d));
+        let model = Renderer::model_matrix(Vector3::new(x, y, z), Vector3::new(w, h, d), Vector3::new(rotation_x, rotation_y, rotation_z));
         let mvp = projection * view * model;
 
         let mvp_array: [f32; 16] = mvp.as_slice().try_into().unwrap();
@@ -964,6 +1487,86 @@ impl Renderer {
             (vertices.len() / 3) as i32
         );
     }
+    // Interleaved position + color (6 floats per vertex) line strip
+    pub fn draw_colored_lines(&self, vertices: &[f32], projection: &Matrix4<f32>, view: &Matrix4<f32>) {
+        self.gl.bind_buffer(WebGlRenderingContext::ARRAY_BUFFER, Some(&self.dynamic_vertex_buffer));
+        unsafe {
+            let vert_array = js_sys::Float32Array::view(vertices);
+            self.gl.buffer_data_with_array_buffer_view(
+                WebGlRenderingContext::ARRAY_BUFFER,
+                &vert_array,
+                WebGlRenderingContext::DYNAMIC_DRAW
+            );
+        }
+
+        let pos_loc = self.gl.get_attrib_location(&self.program, "aPosition") as u32;
+        let col_loc = self.gl.get_attrib_location(&self.program, "aColor") as u32;
+        let tex_loc = self.gl.get_attrib_location(&self.program, "aTexCoord") as u32;
+        let norm_loc = self.gl.get_attrib_location(&self.program, "aNormal") as u32;
+
+        self.gl.vertex_attrib_pointer_with_i32(pos_loc, 3, WebGlRenderingContext::FLOAT, false, 24, 0);
+        self.gl.enable_vertex_attrib_array(pos_loc);
+        self.gl.vertex_attrib_pointer_with_i32(col_loc, 3, WebGlRenderingContext::FLOAT, false, 24, 12);
+        self.gl.enable_vertex_attrib_array(col_loc);
+
+        self.gl.disable_vertex_attrib_array(tex_loc);
+        self.gl.disable_vertex_attrib_array(norm_loc);
+
+        self.gl.uniform1i(Some(&self.u_use_uniform_color_location), 0);
+        self.gl.uniform1i(Some(&self.u_use_texture_location), 0);
+        self.gl.uniform1i(Some(&self.u_use_lighting_location), 0);
+        self.gl.uniform1i(Some(&self.u_is_black_hole_location), 0);
+        self.gl.uniform1i(Some(&self.u_is_frozen_location), 0);
+
+        let mvp = projection * view;
+        let mvp_array: [f32; 16] = mvp.as_slice().try_into().unwrap();
+        self.gl.uniform_matrix4fv_with_f32_array(Some(&self.mvp_location), false, &mvp_array);
+
+        self.gl.draw_arrays(
+            WebGlRenderingContext::LINE_STRIP,
+            0,
+            (vertices.len() / 6) as i32
+        );
+    }
+
+    // Pixel-art texture filled from CPU data, tightly packed RGBA rows
+    pub fn create_texture_from_rgba(&self, width: i32, height: i32, pixels: &[u8]) -> Result<WebGlTexture, JsValue> {
+        let texture = self.gl.create_texture().ok_or("Failed to create texture")?;
+        self.gl.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&texture));
+        self.gl.pixel_storei(WebGlRenderingContext::UNPACK_FLIP_Y_WEBGL, 0);
+        self.gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+            WebGlRenderingContext::TEXTURE_2D, 0, WebGlRenderingContext::RGBA as i32, width, height, 0,
+            WebGlRenderingContext::RGBA, WebGlRenderingContext::UNSIGNED_BYTE, Some(pixels)
+        )?;
+        self.gl.tex_parameteri(WebGlRenderingContext::TEXTURE_2D, WebGlRenderingContext::TEXTURE_WRAP_S, WebGlRenderingContext::CLAMP_TO_EDGE as i32);
+        self.gl.tex_parameteri(WebGlRenderingContext::TEXTURE_2D, WebGlRenderingContext::TEXTURE_WRAP_T, WebGlRenderingContext::CLAMP_TO_EDGE as i32);
+        self.gl.tex_parameteri(WebGlRenderingContext::TEXTURE_2D, WebGlRenderingContext::TEXTURE_MIN_FILTER, WebGlRenderingContext::NEAREST as i32);
+        self.gl.tex_parameteri(WebGlRenderingContext::TEXTURE_2D, WebGlRenderingContext::TEXTURE_MAG_FILTER, WebGlRenderingContext::NEAREST as i32);
+        self.data_textures.borrow_mut().push(texture.clone());
+        Ok(texture)
+    }
+
+    // Remembered, so a texture still loading gets it again once its image is uploaded
+    pub fn set_texture_filter(&self, texture: &WebGlTexture, nearest: bool) {
+        {
+            let mut filters = self.texture_filters.borrow_mut();
+            filters.retain(|(t, _)| t != texture);
+            filters.push((texture.clone(), nearest));
+        }
+        let mipmapped = !self.unmipmapped_textures.borrow().contains(texture);
+        apply_texture_filter(&self.gl, texture, nearest, mipmapped);
+    }
+
+    // Replaces a sub-rectangle without re-uploading the whole image
+    pub fn update_texture_region(&self, texture: &WebGlTexture, x: i32, y: i32, width: i32, height: i32, pixels: &[u8]) -> Result<(), JsValue> {
+        self.gl.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(texture));
+        self.gl.pixel_storei(WebGlRenderingContext::UNPACK_FLIP_Y_WEBGL, 0);
+        self.gl.tex_sub_image_2d_with_i32_and_i32_and_u32_and_type_and_opt_u8_array(
+            WebGlRenderingContext::TEXTURE_2D, 0, x, y, width, height,
+            WebGlRenderingContext::RGBA, WebGlRenderingContext::UNSIGNED_BYTE, Some(pixels)
+        )
+    }
+
     pub fn create_texture(&self, url: &str) -> Result<WebGlTexture, JsValue> {
         let texture = self.gl.create_texture().ok_or("Failed to create texture")?;
         self.gl.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&texture));
@@ -990,9 +1593,12 @@ impl Renderer {
         let texture_clone = texture.clone();
         let img_clone = img.clone();
         let url_string = url.to_string();
+        let filters = self.texture_filters.clone();
+        let unmipmapped = self.unmipmapped_textures.clone();
         
         let onload = Closure::wrap(Box::new(move || {
             web_sys::console::log_1(&format!("Texture loaded: {}", url_string).into());
+            assets::texture_finished();
             gl.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(&texture_clone));
             
             // Flip Y for textures to match standard UV coordinates
@@ -1003,17 +1609,24 @@ impl Renderer {
             ).unwrap();
             
             // Check if power of 2
-            if is_power_of_2(img_clone.width()) && is_power_of_2(img_clone.height()) {
+            let mipmapped = is_power_of_2(img_clone.width()) && is_power_of_2(img_clone.height());
+            if mipmapped {
                 gl.generate_mipmap(WebGlRenderingContext::TEXTURE_2D);
             } else {
                 gl.tex_parameteri(WebGlRenderingContext::TEXTURE_2D, WebGlRenderingContext::TEXTURE_WRAP_S, WebGlRenderingContext::CLAMP_TO_EDGE as i32);
                 gl.tex_parameteri(WebGlRenderingContext::TEXTURE_2D, WebGlRenderingContext::TEXTURE_WRAP_T, WebGlRenderingContext::CLAMP_TO_EDGE as i32);
                 gl.tex_parameteri(WebGlRenderingContext::TEXTURE_2D, WebGlRenderingContext::TEXTURE_MIN_FILTER, WebGlRenderingContext::LINEAR as i32);
+                unmipmapped.borrow_mut().push(texture_clone.clone());
+            }
+            // The upload reset whatever filter was picked while the image was loading
+            if let Some(&(_, nearest)) = filters.borrow().iter().find(|(t, _)| *t == texture_clone) {
+                apply_texture_filter(&gl, &texture_clone, nearest, mipmapped);
             }
         }) as Box<dyn FnMut()>);
 
         let onerror = Closure::wrap(Box::new(move || {
             web_sys::console::error_1(&"Failed to load texture".into());
+            assets::texture_finished();
         }) as Box<dyn FnMut()>);
 
         img.set_onload(Some(onload.as_ref().unchecked_ref()));
@@ -1021,12 +1634,83 @@ impl Renderer {
         onload.forget();
         onerror.forget();
         
+        assets::texture_requested();
         img.set_src(url);
 
+        self.texture_urls.borrow_mut().push((texture.clone(), url.to_string()));
         Ok(texture)
     }
 }
 
+// Games share one context, so whatever a game's renderer made goes with the game
+impl Drop for Renderer {
+    fn drop(&mut self) {
+        let gl = &self.gl;
+        for (texture, _) in self.texture_urls.borrow().iter() {
+            gl.delete_texture(Some(texture));
+        }
+        for texture in self.data_textures.borrow().iter() {
+            gl.delete_texture(Some(texture));
+        }
+        for buffer in [
+            &self.unit_cube_vertex_buffer,
+            &self.unit_cube_index_buffer,
+            &self.dynamic_vertex_buffer,
+            &self.dynamic_index_buffer,
+            &self.instance_data_buffer,
+        ] {
+            gl.delete_buffer(Some(buffer));
+        }
+        for program in [&self.program, &self.instanced_program, &self.skybox_program] {
+            gl.delete_program(Some(program));
+        }
+        if let Some(map) = &self.shadow_map {
+            map.delete(gl);
+        }
+    }
+}
+
+const SEG_A: u8 = 1;
+const SEG_B: u8 = 2;
+const SEG_C: u8 = 4;
+const SEG_D: u8 = 8;
+const SEG_E: u8 = 16;
+const SEG_F: u8 = 32;
+const SEG_G: u8 = 64;
+
+fn seven_segment_mask(ch: char) -> u8 {
+    match ch.to_ascii_uppercase() {
+        '0' | 'O' => SEG_A | SEG_B | SEG_C | SEG_D | SEG_E | SEG_F,
+        '1' | 'I' => SEG_B | SEG_C,
+        '2' | 'Z' => SEG_A | SEG_B | SEG_G | SEG_E | SEG_D,
+        '3' => SEG_A | SEG_B | SEG_G | SEG_C | SEG_D,
+        '4' => SEG_F | SEG_G | SEG_B | SEG_C,
+        '5' | 'S' => SEG_A | SEG_F | SEG_G | SEG_C | SEG_D,
+        '6' => SEG_A | SEG_F | SEG_G | SEG_E | SEG_D | SEG_C,
+        '7' => SEG_A | SEG_B | SEG_C,
+        '8' => SEG_A | SEG_B | SEG_C | SEG_D | SEG_E | SEG_F | SEG_G,
+        '9' => SEG_A | SEG_B | SEG_C | SEG_D | SEG_F | SEG_G,
+        'A' => SEG_A | SEG_B | SEG_C | SEG_E | SEG_F | SEG_G,
+        'B' => SEG_C | SEG_D | SEG_E | SEG_F | SEG_G,
+        'C' => SEG_A | SEG_D | SEG_E | SEG_F,
+        'D' => SEG_B | SEG_C | SEG_D | SEG_E | SEG_G,
+        'E' => SEG_A | SEG_D | SEG_E | SEG_F | SEG_G,
+        'F' => SEG_A | SEG_E | SEG_F | SEG_G,
+        'G' => SEG_A | SEG_C | SEG_D | SEG_E | SEG_F,
+        'H' => SEG_B | SEG_C | SEG_E | SEG_F | SEG_G,
+        'L' => SEG_D | SEG_E | SEG_F,
+        'N' => SEG_C | SEG_E | SEG_G,
+        'P' => SEG_A | SEG_B | SEG_E | SEG_F | SEG_G,
+        'R' => SEG_E | SEG_G,
+        'T' => SEG_D | SEG_E | SEG_F | SEG_G,
+        'U' | 'V' => SEG_B | SEG_C | SEG_D | SEG_E | SEG_F,
+        'Y' => SEG_B | SEG_C | SEG_D | SEG_F | SEG_G,
+        '-' => SEG_G,
+        '_' => SEG_D,
+        _ => 0,
+    }
+}
+
 fn is_power_of_2(value: u32) -> bool {
     (value & (value - 1)) == 0
 }
@@ -1063,6 +1747,22 @@ fn create_instanced_program(gl: &WebGlRenderingContext) -> Result<WebGlProgram,
     }
 }
 
+fn create_shadow_program(gl: &WebGlRenderingContext) -> Result<WebGlProgram, JsValue> {
+    let vert_shader = compile_shader(gl, WebGlRenderingContext::VERTEX_SHADER, SHADOW_VERTEX_SHADER)?;
+    let frag_shader = compile_shader(gl, WebGlRenderingContext::FRAGMENT_SHADER, SHADOW_FRAGMENT_SHADER)?;
+
+    let program = gl.create_program().ok_or("Unable to create program")?;
+    gl.attach_shader(&program, &vert_shader);
+    gl.attach_shader(&program, &frag_shader);
+    gl.link_program(&program);
+
+    if gl.get_program_parameter(&program, WebGlRenderingContext::LINK_STATUS).as_bool().unwrap_or(false) {
+        Ok(program)
+    } else {
+        Err(JsValue::from_str(&gl.get_program_info_log(&program).unwrap_or_default()))
+    }
+}
+
 fn create_skybox_program(gl: &WebGlRenderingContext) -> Result<WebGlProgram, JsValue> {
     let vert_shader = compile_shader(gl, WebGlRenderingContext::VERTEX_SHADER, SKYBOX_VERTEX_SHADER)?;
     let frag_shader = compile_shader(gl, WebGlRenderingContext::FRAGMENT_SHADER, SKYBOX_FRAGMENT_SHADER)?;
@@ -1079,6 +1779,19 @@ fn create_skybox_program(gl: &WebGlRenderingContext) -> Result<WebGlProgram, JsV
     }
 }
 
+// Nearest keeps texels crisp and skips mipmap sampling. Otherwise the WebGL defaults
+// return, or plain linear for a texture without mipmaps to sample.
+fn apply_texture_filter(gl: &WebGlRenderingContext, texture: &WebGlTexture, nearest: bool, mipmapped: bool) {
+    let (min, mag) = match (nearest, mipmapped) {
+        (true, _) => (WebGlRenderingContext::NEAREST, WebGlRenderingContext::NEAREST),
+        (false, true) => (WebGlRenderingContext::NEAREST_MIPMAP_LINEAR, WebGlRenderingContext::LINEAR),
+        (false, false) => (WebGlRenderingContext::LINEAR, WebGlRenderingContext::LINEAR),
+    };
+    gl.bind_texture(WebGlRenderingContext::TEXTURE_2D, Some(texture));
+    gl.tex_parameteri(WebGlRenderingContext::TEXTURE_2D, WebGlRenderingContext::TEXTURE_MIN_FILTER, min as i32);
+    gl.tex_parameteri(WebGlRenderingContext::TEXTURE_2D, WebGlRenderingContext::TEXTURE_MAG_FILTER, mag as i32);
+}
+
 fn compile_shader(gl: &WebGlRenderingContext, shader_type: u32, source: &str) -> Result<web_sys::WebGlShader, JsValue> {
     let shader = gl.create_shader(shader_type).ok_or("Unable to create shader")?;
     gl.shader_source(&shader, source);
@@ -0,0 +1,88 @@
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+
+const SETTINGS_KEY: &str = "minigame_settings";
+
+// Settings shared by every game, stored as a single JSON blob in localStorage.
+// Missing fields fall back to their defaults so older blobs keep loading.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Settings {
+    pub crossy_palette: String,
+    pub audio_enabled: bool,
+    pub audio_volume: f32,
+    // Parsed by the game itself so a damaged entry can't reset the other settings
+    pub crossy_stats: serde_json::Value,
+    pub minecraft_physics: serde_json::Value,
+    pub minecraft_graphics: serde_json::Value,
+    // Anaglyph eye separation as a fraction of the Solar camera distance
+    pub solar_eye_separation: f32,
+    pub solar_time_bookmarks: serde_json::Value,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            crossy_palette: String::new(),
+            audio_enabled: true,
+            audio_volume: 0.6,
+            crossy_stats: serde_json::Value::Null,
+            minecraft_physics: serde_json::Value::Null,
+            minecraft_graphics: serde_json::Value::Null,
+            solar_eye_separation: 0.03,
+            solar_time_bookmarks: serde_json::Value::Null,
+        }
+    }
+}
+
+thread_local! {
+    // Last saved settings, read back when localStorage is unavailable or refuses writes
+    static IN_MEMORY: RefCell<Option<Settings>> = const { RefCell::new(None) };
+}
+
+impl Settings {
+    // Whatever this page last saved wins, storage may still hold an older blob if it
+    // refused the write
+    pub fn load() -> Self {
+        IN_MEMORY.with(|m| m.borrow().clone())
+            .or_else(|| {
+                local_storage()
+                    .and_then(|storage| storage.get_item(SETTINGS_KEY).ok().flatten())
+                    .and_then(|json| serde_json::from_str(&json).ok())
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        IN_MEMORY.with(|m| *m.borrow_mut() = Some(self.clone()));
+        if let (Some(storage), Ok(json)) = (local_storage(), serde_json::to_string(self)) {
+            // Private browsing can refuse writes, the in-memory copy still applies
+            storage.set_item(SETTINGS_KEY, &json).ok();
+        }
+    }
+
+    pub fn update(f: impl FnOnce(&mut Settings)) {
+        let mut settings = Settings::load();
+        f(&mut settings);
+        settings.save();
+    }
+}
+
+pub fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok().flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Storage isn't reached at all while the page has saved something this session
+    #[test]
+    fn load_prefers_the_in_memory_copy() {
+        let saved = Settings { crossy_palette: "night".to_string(), audio_volume: 0.25, ..Settings::default() };
+        IN_MEMORY.with(|m| *m.borrow_mut() = Some(saved));
+        let loaded = Settings::load();
+        assert_eq!(loaded.crossy_palette, "night");
+        assert_eq!(loaded.audio_volume, 0.25);
+    }
+}
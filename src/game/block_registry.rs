@@ -0,0 +1,234 @@
+use crate::game::minecraft::BlockType;
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+// The built-in blocks, in the order of the BlockType constants. Their ids are their
+// positions here, blocks registered later are numbered after them.
+const DEFAULT_BLOCKS: &str = include_str!("../../assets/blocks.json");
+const MAX_LIGHT: u8 = 15;
+
+// Footstep family
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum StepSound {
+    Plant,
+    Soil,
+    Stone,
+    Wood,
+    Glass,
+    Fluid,
+}
+
+fn yes() -> bool {
+    true
+}
+
+fn default_shadow() -> f32 {
+    0.3
+}
+
+fn default_hardness() -> f32 {
+    1.0
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct BlockDef {
+    pub name: String,
+    // Tiles under assets/textures/TinyCraft/tiles, without the extension. Top and bottom
+    // fall back to the side tile.
+    pub side: String,
+    #[serde(default)]
+    pub top: Option<String>,
+    #[serde(default)]
+    pub bottom: Option<String>,
+    // Map pixels and break particles
+    pub color: (f32, f32, f32),
+    // Stops the player, mobs and block picking. Fluids aren't solid.
+    #[serde(default = "yes")]
+    pub solid: bool,
+    // Faces behind it still get drawn
+    #[serde(default)]
+    pub transparent: bool,
+    // 0-15, baked in as full light when meshing
+    #[serde(default)]
+    pub emits_light: u8,
+    // Sunlight let through when it stands between a face and the sun
+    #[serde(default = "default_shadow")]
+    pub shadow: f32,
+    // Negative can't be broken. Breaking is instant for now, so only the sign is read.
+    #[serde(default = "default_hardness")]
+    pub hardness: f32,
+    // Drops a cell at a time while nothing is under it
+    #[serde(default)]
+    pub gravity_affected: bool,
+    // Name of the item breaking it gives back, the block itself when absent
+    #[serde(default)]
+    pub drops: Option<String>,
+    pub sound: StepSound,
+}
+
+impl BlockDef {
+    pub fn top_tile(&self) -> &str {
+        self.top.as_deref().unwrap_or(&self.side)
+    }
+
+    pub fn bottom_tile(&self) -> &str {
+        self.bottom.as_deref().unwrap_or(&self.side)
+    }
+
+    // Blocks showing the same tile on every face draw as one cube instead of three meshes
+    pub fn single_tile(&self) -> bool {
+        self.top_tile() == self.side && self.bottom_tile() == self.side
+    }
+}
+
+pub struct BlockRegistry {
+    defs: Vec<BlockDef>,
+    ids: HashMap<String, BlockType>,
+    // Resolved drops, indexed like defs
+    drops: Vec<BlockType>,
+}
+
+thread_local! {
+    static REGISTRY: RefCell<BlockRegistry> = RefCell::new(BlockRegistry::builtin());
+}
+
+impl BlockRegistry {
+    fn builtin() -> Self {
+        let defs: Vec<BlockDef> = serde_json::from_str(DEFAULT_BLOCKS).expect("assets/blocks.json is valid");
+        let mut registry = BlockRegistry { defs: Vec::new(), ids: HashMap::new(), drops: Vec::new() };
+        registry.merge(defs).expect("assets/blocks.json is consistent");
+        for (block, name) in BlockType::BUILTIN.iter().zip(registry.defs.iter().map(|def| &def.name)) {
+            assert_eq!(registry.ids.get(name), Some(block), "assets/blocks.json order");
+        }
+        registry
+    }
+
+    // A def with a known name replaces that block's properties and keeps its id, so
+    // saved worlds and the built-in constants still point at it
+    fn merge(&mut self, defs: Vec<BlockDef>) -> Result<(), String> {
+        let mut staged = self.defs.clone();
+        let mut ids = self.ids.clone();
+        for def in defs {
+            if def.name.is_empty() {
+                return Err("Block name can't be empty".to_string());
+            }
+            if def.emits_light > MAX_LIGHT {
+                return Err(format!("Block \"{}\": emits_light goes up to {}", def.name, MAX_LIGHT));
+            }
+            match ids.get(&def.name) {
+                Some(block) => staged[block.0 as usize] = def,
+                None => {
+                    let id = u16::try_from(staged.len()).map_err(|_| "Too many block types".to_string())?;
+                    ids.insert(def.name.clone(), BlockType(id));
+                    staged.push(def);
+                }
+            }
+        }
+        let drops = staged.iter().enumerate()
+            .map(|(id, def)| match &def.drops {
+                Some(name) => ids.get(name).copied()
+                    .ok_or_else(|| format!("Block \"{}\" drops unknown block \"{}\"", def.name, name)),
+                None => Ok(BlockType(id as u16)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.defs = staged;
+        self.ids = ids;
+        self.drops = drops;
+        Ok(())
+    }
+
+    fn def(&self, block: BlockType) -> &BlockDef {
+        // Ids only come from the registry, the fallback keeps a stale one drawable
+        self.defs.get(block.0 as usize).unwrap_or(&self.defs[0])
+    }
+}
+
+// Adds or overrides blocks from a JSON array of defs. Nothing changes when any entry is bad.
+pub fn register(json: &str) -> Result<(), String> {
+    let defs: Vec<BlockDef> = serde_json::from_str(json).map_err(|e| format!("Invalid block list: {}", e))?;
+    REGISTRY.with(|r| r.borrow_mut().merge(defs))
+}
+
+pub fn with_def<T>(block: BlockType, f: impl FnOnce(&BlockDef) -> T) -> T {
+    REGISTRY.with(|r| f(r.borrow().def(block)))
+}
+
+pub fn by_name(name: &str) -> Option<BlockType> {
+    REGISTRY.with(|r| r.borrow().ids.get(name).copied())
+}
+
+pub fn drops(block: BlockType) -> BlockType {
+    REGISTRY.with(|r| r.borrow().drops.get(block.0 as usize).copied().unwrap_or(block))
+}
+
+// Every tile some block uses, for loading textures
+pub fn tiles() -> Vec<String> {
+    REGISTRY.with(|r| {
+        let mut tiles: Vec<String> = r.borrow().defs.iter()
+            .flat_map(|def| [def.side.clone(), def.top_tile().to_string(), def.bottom_tile().to_string()])
+            .collect();
+        tiles.sort();
+        tiles.dedup();
+        tiles
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Edits = Vec<((i32, i32, i32), Option<BlockType>)>;
+
+    fn def(name: &str) -> String {
+        format!(r#"{{"name": "{}", "side": "stone", "color": [0.9, 0.9, 0.9], "sound": "stone"}}"#, name)
+    }
+
+    #[test]
+    fn added_block_saves_by_name_and_loads_back() {
+        register(&format!("[{}]", def("marble"))).unwrap();
+        let marble = by_name("marble").unwrap();
+        assert!(marble.0 as usize >= BlockType::BUILTIN.len());
+
+        let edits: Edits = vec![((1, 2, 3), Some(marble)), ((4, 5, 6), Some(BlockType::Brick)), ((7, 8, 9), None)];
+        let saved = serde_json::to_string(&edits).unwrap();
+        assert!(saved.contains("\"marble\""));
+        let loaded: Edits = serde_json::from_str(&saved).unwrap();
+        assert_eq!(loaded, edits);
+    }
+
+    // Another session registering blocks in another order gives them other ids, the
+    // names in the save still find the right ones
+    #[test]
+    fn save_survives_a_reordered_registry() {
+        register(&format!("[{}, {}]", def("marble"), def("jade"))).unwrap();
+        let edits: Edits = vec![((0, 0, 0), by_name("marble")), ((1, 0, 0), by_name("jade"))];
+        let saved = serde_json::to_string(&edits).unwrap();
+
+        let loaded = std::thread::spawn(move || {
+            register(&format!("[{}, {}]", def("jade"), def("marble"))).unwrap();
+            let loaded: Edits = serde_json::from_str(&saved).unwrap();
+            assert_eq!(loaded, vec![((0, 0, 0), by_name("marble")), ((1, 0, 0), by_name("jade"))]);
+            loaded.iter().map(|(_, block)| block.unwrap().name()).collect::<Vec<_>>()
+        }).join().unwrap();
+        assert_eq!(loaded, ["marble", "jade"]);
+        assert_ne!(edits[0].1, by_name("jade"));
+    }
+
+    #[test]
+    fn save_with_an_unregistered_block_is_an_error() {
+        let error = serde_json::from_str::<Edits>(r#"[[[0, 0, 0], "obsidian_glass"]]"#).unwrap_err();
+        assert!(error.to_string().contains("unknown block \"obsidian_glass\""));
+    }
+
+    #[test]
+    fn bad_entry_leaves_the_registry_as_it_was() {
+        let before = tiles();
+        let bad = format!(r#"[{}, {{"name": "lamp", "side": "stone", "color": [1, 1, 1], "sound": "stone", "emits_light": 99}}]"#, def("marble"));
+        assert!(register(&bad).is_err());
+        assert!(by_name("marble").is_none());
+        assert_eq!(tiles(), before);
+    }
+}
@@ -0,0 +1,214 @@
+use crate::game::block_updates::{BlockMeta, BlockPos};
+use crate::game::fluids;
+use crate::game::minecraft::BlockType;
+use nalgebra::Vector3;
+use std::collections::HashMap;
+
+// (min, max) relative to the cell center, a full cell spans -0.5..0.5
+pub type Aabb = ([f32; 3], [f32; 3]);
+
+pub const FULL_CUBE: Aabb = ([-0.5, -0.5, -0.5], [0.5, 0.5, 0.5]);
+const POST_HALF_WIDTH: f32 = 0.125;
+const RAIL_HALF_WIDTH: f32 = 0.0625;
+
+// Arm directions in the order of FenceArm's index: +x, -x, +z, -z
+const FENCE_DIRECTIONS: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+// One meshable, collidable piece of a partial block. Fences are a post plus
+// one arm per connected neighbor, so their parts depend on the surroundings.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ShapePart {
+    SlabBottom,
+    SlabTop,
+    FencePost,
+    FenceArm(usize),
+    // Fluid surface at the given flow level, see fluids::surface_height
+    Fluid(u8),
+}
+
+impl ShapePart {
+    pub const ALL: [ShapePart; 15] = [
+        ShapePart::SlabBottom,
+        ShapePart::SlabTop,
+        ShapePart::FencePost,
+        ShapePart::FenceArm(0),
+        ShapePart::FenceArm(1),
+        ShapePart::FenceArm(2),
+        ShapePart::FenceArm(3),
+        ShapePart::Fluid(0),
+        ShapePart::Fluid(1),
+        ShapePart::Fluid(2),
+        ShapePart::Fluid(3),
+        ShapePart::Fluid(4),
+        ShapePart::Fluid(5),
+        ShapePart::Fluid(6),
+        ShapePart::Fluid(7),
+    ];
+
+    // What gets drawn; arms are two rails
+    pub fn render_boxes(&self) -> Vec<Aabb> {
+        match self {
+            ShapePart::FenceArm(dir) => vec![
+                arm_box(*dir, RAIL_HALF_WIDTH, 0.1, 0.35),
+                arm_box(*dir, RAIL_HALF_WIDTH, -0.25, 0.0),
+            ],
+            _ => vec![self.collision_box()],
+        }
+    }
+
+    // What the player bumps into; arms are solid so nobody slips between the rails
+    pub fn collision_box(&self) -> Aabb {
+        match self {
+            ShapePart::SlabBottom => ([-0.5, -0.5, -0.5], [0.5, 0.0, 0.5]),
+            ShapePart::SlabTop => ([-0.5, 0.0, -0.5], [0.5, 0.5, 0.5]),
+            ShapePart::FencePost => ([-POST_HALF_WIDTH, -0.5, -POST_HALF_WIDTH], [POST_HALF_WIDTH, 0.5, POST_HALF_WIDTH]),
+            ShapePart::FenceArm(dir) => arm_box(*dir, POST_HALF_WIDTH, -0.5, 0.5),
+            ShapePart::Fluid(level) => ([-0.5, -0.5, -0.5], [0.5, fluids::surface_height(*level) - 0.5, 0.5]),
+        }
+    }
+}
+
+// From the post's edge out to the cell boundary in one direction
+fn arm_box(dir: usize, half_width: f32, min_y: f32, max_y: f32) -> Aabb {
+    let (dx, dz) = FENCE_DIRECTIONS[dir];
+    let along = |d: i32| match d {
+        1 => (POST_HALF_WIDTH, 0.5),
+        -1 => (-0.5, -POST_HALF_WIDTH),
+        _ => (-half_width, half_width),
+    };
+    let (min_x, max_x) = along(dx);
+    let (min_z, max_z) = along(dz);
+    ([min_x, min_y, min_z], [max_x, max_y, max_z])
+}
+
+pub fn is_full_cube(block: BlockType) -> bool {
+    !matches!(block, BlockType::Slab | BlockType::SlabTop | BlockType::Fence) && !fluids::is_fluid(block)
+}
+
+// Fences only join other fences
+pub fn fence_arms(blocks: &HashMap<BlockPos, BlockType>, (x, y, z): BlockPos) -> [bool; 4] {
+    FENCE_DIRECTIONS.map(|(dx, dz)| blocks.get(&(x + dx, y, z + dz)) == Some(&BlockType::Fence))
+}
+
+// Empty for full cubes, they're drawn with the shared cube meshes
+pub fn shape_parts(blocks: &HashMap<BlockPos, BlockType>, meta: &BlockMeta, pos: BlockPos, block: BlockType) -> Vec<ShapePart> {
+    if fluids::is_fluid(block) {
+        let level = meta.get(&pos).copied().unwrap_or(0);
+        return vec![ShapePart::Fluid(level.min(7))];
+    }
+    solid_parts(blocks, pos, block)
+}
+
+fn solid_parts(blocks: &HashMap<BlockPos, BlockType>, pos: BlockPos, block: BlockType) -> Vec<ShapePart> {
+    match block {
+        BlockType::Slab => vec![ShapePart::SlabBottom],
+        BlockType::SlabTop => vec![ShapePart::SlabTop],
+        BlockType::Fence => {
+            let arms = fence_arms(blocks, pos);
+            let mut parts = vec![ShapePart::FencePost];
+            parts.extend((0..4).filter(|dir| arms[*dir]).map(ShapePart::FenceArm));
+            parts
+        }
+        _ => Vec::new(),
+    }
+}
+
+// World-space boxes for the block at pos, fluids have none
+pub fn collision_boxes(blocks: &HashMap<BlockPos, BlockType>, pos: BlockPos, block: BlockType) -> Vec<(Vector3<f32>, Vector3<f32>)> {
+    if !block.is_solid() {
+        return Vec::new();
+    }
+    let center = Vector3::new(pos.0 as f32, pos.1 as f32, pos.2 as f32);
+    let local = if is_full_cube(block) {
+        vec![FULL_CUBE]
+    } else {
+        solid_parts(blocks, pos, block).iter().map(|part| part.collision_box()).collect()
+    };
+    local.into_iter()
+        .map(|(min, max)| (center + Vector3::from(min), center + Vector3::from(max)))
+        .collect()
+}
+
+// Slab test, returns the entry distance and the face id (same numbering as
+// RaycastHit) or None when the ray misses or starts inside
+pub fn ray_box(origin: Vector3<f32>, dir: Vector3<f32>, min: Vector3<f32>, max: Vector3<f32>) -> Option<(f32, usize)> {
+    let mut t_enter = f32::NEG_INFINITY;
+    let mut t_exit = f32::INFINITY;
+    let mut face = 6;
+    for axis in 0..3 {
+        if dir[axis] == 0.0 {
+            if origin[axis] < min[axis] || origin[axis] > max[axis] {
+                return None;
+            }
+            continue;
+        }
+        let t1 = (min[axis] - origin[axis]) / dir[axis];
+        let t2 = (max[axis] - origin[axis]) / dir[axis];
+        let (near, far) = if t1 < t2 { (t1, t2) } else { (t2, t1) };
+        if near > t_enter {
+            t_enter = near;
+            // Entering through the min side means the hit face points negative
+            face = axis * 2 + if dir[axis] > 0.0 { 1 } else { 0 };
+        }
+        t_exit = t_exit.min(far);
+    }
+    if t_enter > t_exit || t_enter < 0.0 {
+        return None;
+    }
+    Some((t_enter, face))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn world(cells: &[(BlockPos, BlockType)]) -> HashMap<BlockPos, BlockType> {
+        cells.iter().copied().collect()
+    }
+
+    #[test]
+    fn lone_fence_is_just_a_post() {
+        let blocks = world(&[((0, 0, 0), BlockType::Fence)]);
+        assert_eq!(fence_arms(&blocks, (0, 0, 0)), [false; 4]);
+        assert_eq!(shape_parts(&blocks, &BlockMeta::new(), (0, 0, 0), BlockType::Fence), vec![ShapePart::FencePost]);
+    }
+
+    #[test]
+    fn arms_reach_toward_neighboring_fences_only() {
+        let blocks = world(&[
+            ((0, 0, 0), BlockType::Fence),
+            ((1, 0, 0), BlockType::Fence),
+            ((0, 0, -1), BlockType::Fence),
+            // Other blocks, diagonal fences and fences a level up don't connect
+            ((-1, 0, 0), BlockType::Stone),
+            ((0, 0, 1), BlockType::Slab),
+            ((1, 0, 1), BlockType::Fence),
+            ((0, 1, 0), BlockType::Fence),
+        ]);
+        assert_eq!(fence_arms(&blocks, (0, 0, 0)), [true, false, false, true]);
+        assert_eq!(
+            shape_parts(&blocks, &BlockMeta::new(), (0, 0, 0), BlockType::Fence),
+            vec![ShapePart::FencePost, ShapePart::FenceArm(0), ShapePart::FenceArm(3)]
+        );
+    }
+
+    #[test]
+    fn connections_are_mutual_and_follow_edits() {
+        let mut blocks = world(&[((0, 0, 0), BlockType::Fence), ((0, 0, 1), BlockType::Fence)]);
+        assert!(fence_arms(&blocks, (0, 0, 0))[2]);
+        assert!(fence_arms(&blocks, (0, 0, 1))[3]);
+        blocks.remove(&(0, 0, 1));
+        assert_eq!(fence_arms(&blocks, (0, 0, 0)), [false; 4]);
+    }
+
+    #[test]
+    fn arm_collision_runs_from_the_post_to_the_cell_edge() {
+        let blocks = world(&[((0, 0, 0), BlockType::Fence), ((1, 0, 0), BlockType::Fence)]);
+        let boxes = collision_boxes(&blocks, (0, 0, 0), BlockType::Fence);
+        assert_eq!(boxes.len(), 2);
+        let (min, max) = boxes[1];
+        assert_eq!((min.x, max.x), (POST_HALF_WIDTH, 0.5));
+        assert_eq!((min.z, max.z), (-POST_HALF_WIDTH, POST_HALF_WIDTH));
+        assert_eq!((min.y, max.y), (-0.5, 0.5));
+    }
+}
@@ -0,0 +1,321 @@
+use crate::game::block_registry;
+use crate::game::fluids;
+use crate::game::minecraft::BlockType;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+pub type BlockPos = (i32, i32, i32);
+// One extra byte per position for blocks that need state, e.g. a fluid's level. Absent means 0.
+pub type BlockMeta = HashMap<BlockPos, u8>;
+// A handler's requested change as (position, block, meta), None removes the block
+pub type BlockEdit = (BlockPos, Option<BlockType>, u8);
+type Handler = fn(&HashMap<BlockPos, BlockType>, &BlockMeta, BlockPos) -> Vec<BlockEdit>;
+
+// Positions processed per frame, the rest wait for the next one
+pub const DEFAULT_UPDATE_BUDGET: usize = 64;
+// Leaves hold on while this many leaf steps lead to something that isn't a leaf
+const LEAF_REACH: usize = 4;
+
+const NEIGHBORS: [BlockPos; 6] = [(1, 0, 0), (-1, 0, 0), (0, 1, 0), (0, -1, 0), (0, 0, 1), (0, 0, -1)];
+
+// (block type, frames to wait before reacting, handler). Slow ticks let a fluid
+// spread visibly instead of filling its whole reach in one frame.
+const HANDLERS: &[(BlockType, u32, Handler)] = &[
+    (BlockType::Leaves, 0, leaves_update),
+    (BlockType::Water, fluids::WATER_TICK_FRAMES, fluids::water_update),
+    (BlockType::Lava, fluids::LAVA_TICK_FRAMES, fluids::lava_update),
+];
+
+// Changed positions waiting to tell their block type's handler. A position
+// is queued at most once until it's processed.
+pub struct BlockUpdates {
+    queue: VecDeque<BlockPos>,
+    queued: HashSet<BlockPos>,
+    // Positions whose handler has a tick delay, as (due frame, position)
+    delayed: Vec<(u32, BlockPos)>,
+    scheduled: HashSet<BlockPos>,
+    frame: u32,
+    pub budget: usize,
+    pub fluids_enabled: bool,
+}
+
+impl Default for BlockUpdates {
+    fn default() -> Self {
+        BlockUpdates {
+            queue: VecDeque::new(),
+            queued: HashSet::new(),
+            delayed: Vec::new(),
+            scheduled: HashSet::new(),
+            frame: 0,
+            budget: DEFAULT_UPDATE_BUDGET,
+            fluids_enabled: false,
+        }
+    }
+}
+
+impl BlockUpdates {
+    // The changed position plus its six neighbors
+    pub fn notify(&mut self, (x, y, z): BlockPos) {
+        self.push((x, y, z));
+        for (dx, dy, dz) in NEIGHBORS {
+            self.push((x + dx, y + dy, z + dz));
+        }
+    }
+
+    fn push(&mut self, pos: BlockPos) {
+        if self.queued.insert(pos) {
+            self.queue.push_back(pos);
+        }
+    }
+
+    // Called once per frame before popping
+    pub fn tick(&mut self) {
+        self.frame = self.frame.wrapping_add(1);
+    }
+
+    // Next position ready to react: due delayed ones first, then the queue.
+    // Queued positions with a delayed handler are parked until their frame comes.
+    pub fn pop(&mut self, blocks: &HashMap<BlockPos, BlockType>) -> Option<BlockPos> {
+        if let Some(index) = self.delayed.iter().position(|(due, _)| *due <= self.frame) {
+            let (_, pos) = self.delayed.swap_remove(index);
+            self.scheduled.remove(&pos);
+            return Some(pos);
+        }
+        loop {
+            let pos = self.queue.pop_front()?;
+            self.queued.remove(&pos);
+            let delay = blocks.get(&pos)
+                .and_then(|block| handler(*block))
+                .map(|(delay, _)| delay)
+                .unwrap_or(0);
+            if delay == 0 {
+                return Some(pos);
+            }
+            if self.scheduled.insert(pos) {
+                self.delayed.push((self.frame.wrapping_add(delay), pos));
+            }
+        }
+    }
+
+    // Runs the handler for whatever block sits at pos, air and unhandled types do nothing
+    pub fn react(&self, blocks: &HashMap<BlockPos, BlockType>, meta: &BlockMeta, pos: BlockPos) -> Vec<BlockEdit> {
+        let block = match blocks.get(&pos) {
+            Some(block) => *block,
+            None => return Vec::new(),
+        };
+        if fluids::is_fluid(block) && !self.fluids_enabled {
+            return Vec::new();
+        }
+        handler(block)
+            .map(|(_, handler)| handler(blocks, meta, pos))
+            .unwrap_or_default()
+    }
+}
+
+// Blocks the registry marks as falling share one handler, the rest are looked up by type
+fn handler(block: BlockType) -> Option<(u32, Handler)> {
+    if block_registry::with_def(block, |def| def.gravity_affected) {
+        return Some((0, falling_update));
+    }
+    HANDLERS.iter()
+        .find(|(block_type, _, _)| *block_type == block)
+        .map(|(_, delay, handler)| (*delay, *handler))
+}
+
+// An unsupported falling block drops one cell, the move itself queues the next step
+fn falling_update(blocks: &HashMap<BlockPos, BlockType>, _meta: &BlockMeta, (x, y, z): BlockPos) -> Vec<BlockEdit> {
+    if y <= 0 || blocks.contains_key(&(x, y - 1, z)) {
+        return Vec::new();
+    }
+    let block = blocks.get(&(x, y, z)).copied();
+    vec![((x, y, z), None, 0), ((x, y - 1, z), block, 0)]
+}
+
+// Player-placed leaves always touch the block they were placed against,
+// so only canopies cut off from their tree decay
+fn leaves_update(blocks: &HashMap<BlockPos, BlockType>, _meta: &BlockMeta, pos: BlockPos) -> Vec<BlockEdit> {
+    let mut seen = HashSet::from([pos]);
+    let mut frontier = vec![pos];
+    for _ in 0..LEAF_REACH {
+        let mut next = Vec::new();
+        for (x, y, z) in frontier {
+            for (dx, dy, dz) in NEIGHBORS {
+                let neighbor = (x + dx, y + dy, z + dz);
+                match blocks.get(&neighbor) {
+                    Some(&BlockType::Leaves) if seen.insert(neighbor) => next.push(neighbor),
+                    Some(&BlockType::Leaves) => {}
+                    Some(_) => return Vec::new(),
+                    None => {}
+                }
+            }
+        }
+        frontier = next;
+    }
+    vec![(pos, None, 0)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // One frame of Minecraft::process_block_updates, writing edits back the way world_write
+    // does. Returns how many positions reacted.
+    fn run_frame(updates: &mut BlockUpdates, blocks: &mut HashMap<BlockPos, BlockType>, meta: &mut BlockMeta) -> usize {
+        updates.tick();
+        let mut reacted = 0;
+        for _ in 0..updates.budget {
+            let pos = match updates.pop(blocks) {
+                Some(pos) => pos,
+                None => break,
+            };
+            reacted += 1;
+            for (edit_pos, block, level) in updates.react(blocks, meta, pos) {
+                match block {
+                    Some(block) => { blocks.insert(edit_pos, block); }
+                    None => { blocks.remove(&edit_pos); }
+                }
+                if level > 0 { meta.insert(edit_pos, level); } else { meta.remove(&edit_pos); }
+                updates.notify(edit_pos);
+            }
+        }
+        reacted
+    }
+
+    // Frames until nothing is queued or waiting on a tick
+    fn settle(updates: &mut BlockUpdates, blocks: &mut HashMap<BlockPos, BlockType>, meta: &mut BlockMeta) -> usize {
+        let mut frames = 0;
+        while !updates.queue.is_empty() || !updates.delayed.is_empty() {
+            run_frame(updates, blocks, meta);
+            frames += 1;
+            assert!(frames < 10000, "the world never came to rest");
+        }
+        frames
+    }
+
+    // A stone floor at y 0 with a water source on it at the origin, flow switched on
+    fn pond() -> (BlockUpdates, HashMap<BlockPos, BlockType>, BlockMeta) {
+        let mut blocks = HashMap::new();
+        for x in -10..=10 {
+            for z in -10..=10 {
+                blocks.insert((x, 0, z), BlockType::Stone);
+            }
+        }
+        blocks.insert((0, 1, 0), BlockType::Water);
+        let mut updates = BlockUpdates { fluids_enabled: true, ..Default::default() };
+        updates.notify((0, 1, 0));
+        (updates, blocks, BlockMeta::new())
+    }
+
+    #[test]
+    fn water_spreads_its_reach_and_thins_with_distance() {
+        let (mut updates, mut blocks, mut meta) = pond();
+        settle(&mut updates, &mut blocks, &mut meta);
+        for (pos, level) in [((0, 1, 0), 0), ((1, 1, 0), 1), ((0, 1, -3), 3), ((2, 1, 2), 4), ((-7, 1, 0), 7), ((3, 1, 4), 7)] {
+            assert_eq!(blocks.get(&pos), Some(&BlockType::Water), "{:?}", pos);
+            assert_eq!(meta.get(&pos).copied().unwrap_or(0), level, "{:?}", pos);
+        }
+        // Nothing past the reach, nothing diagonal beyond it either
+        assert!(!blocks.contains_key(&(8, 1, 0)));
+        assert!(!blocks.contains_key(&(4, 1, 4)));
+        let wet = blocks.values().filter(|b| **b == BlockType::Water).count();
+        assert_eq!(wet, 1 + 2 * 7 * 8, "a diamond of radius 7");
+    }
+
+    #[test]
+    fn water_dries_up_once_its_source_is_removed() {
+        let (mut updates, mut blocks, mut meta) = pond();
+        settle(&mut updates, &mut blocks, &mut meta);
+        blocks.remove(&(0, 1, 0));
+        updates.notify((0, 1, 0));
+        settle(&mut updates, &mut blocks, &mut meta);
+        assert!(blocks.values().all(|b| *b == BlockType::Stone), "flowing water left behind");
+        assert!(meta.is_empty());
+    }
+
+    #[test]
+    fn water_pours_into_a_hole_and_stays_put_without_fluids() {
+        let (mut updates, mut blocks, mut meta) = pond();
+        blocks.remove(&(2, 0, 0));
+        settle(&mut updates, &mut blocks, &mut meta);
+        // Falling water restarts at level 1 whatever fed it from above
+        assert_eq!(blocks.get(&(2, 0, 0)), Some(&BlockType::Water));
+        assert_eq!(meta.get(&(2, 0, 0)), Some(&1));
+        assert_eq!(meta.get(&(2, 1, 0)), Some(&2));
+
+        let (mut updates, mut blocks, mut meta) = pond();
+        updates.fluids_enabled = false;
+        settle(&mut updates, &mut blocks, &mut meta);
+        assert_eq!(blocks.len(), 21 * 21 + 1);
+    }
+
+    #[test]
+    fn lava_reaches_less_far_and_hardens_where_water_meets_it() {
+        let (mut updates, mut blocks, mut meta) = pond();
+        blocks.insert((0, 1, 0), BlockType::Lava);
+        settle(&mut updates, &mut blocks, &mut meta);
+        assert_eq!(meta.get(&(3, 1, 0)), Some(&3));
+        assert!(!blocks.contains_key(&(4, 1, 0)));
+
+        // Water poured beside the source turns it to obsidian
+        blocks.insert((-1, 1, 0), BlockType::Water);
+        meta.remove(&(-1, 1, 0));
+        updates.notify((-1, 1, 0));
+        settle(&mut updates, &mut blocks, &mut meta);
+        assert_eq!(blocks.get(&(0, 1, 0)), Some(&BlockType::Obsidian));
+        assert!(!blocks.values().any(|b| *b == BlockType::Lava), "flowing lava outlived its source");
+    }
+
+    #[test]
+    fn sand_column_settles_over_several_frames_within_the_budget() {
+        let mut blocks = HashMap::new();
+        let mut meta = BlockMeta::new();
+        let mut updates = BlockUpdates::default();
+        blocks.insert((0, 0, 0), BlockType::Stone);
+        // Twenty sand blocks hanging over a ten block gap, the support under them just removed
+        for y in 11..31 {
+            blocks.insert((0, y, 0), BlockType::Sand);
+        }
+        updates.notify((0, 10, 0));
+
+        let mut frames = 0;
+        while !updates.queue.is_empty() || !updates.delayed.is_empty() {
+            assert!(run_frame(&mut updates, &mut blocks, &mut meta) <= DEFAULT_UPDATE_BUDGET);
+            frames += 1;
+            assert!(frames < 1000, "the column never came to rest");
+        }
+        assert!(frames > 1, "a budget of {} can't drop 20 blocks 10 cells in one frame", DEFAULT_UPDATE_BUDGET);
+        for y in 1..21 {
+            assert_eq!(blocks.get(&(0, y, 0)), Some(&BlockType::Sand), "y {}", y);
+        }
+        assert_eq!(blocks.len(), 21);
+    }
+
+    #[test]
+    fn leaves_cut_off_from_the_trunk_decay() {
+        let mut blocks = HashMap::new();
+        let mut meta = BlockMeta::new();
+        let mut updates = BlockUpdates::default();
+        for y in 0..4 {
+            blocks.insert((0, y, 0), BlockType::Wood);
+        }
+        let canopy: Vec<BlockPos> = (-1..=1).flat_map(|x| (-1..=1).map(move |z| (x, 4, z))).collect();
+        for &pos in &canopy {
+            blocks.insert(pos, BlockType::Leaves);
+        }
+        for &pos in &canopy {
+            updates.notify(pos);
+        }
+        for _ in 0..10 {
+            run_frame(&mut updates, &mut blocks, &mut meta);
+        }
+        assert!(canopy.iter().all(|pos| blocks.get(pos) == Some(&BlockType::Leaves)), "leaves on a trunk hold");
+
+        // Chopping the trunk's top leaves the canopy floating
+        blocks.remove(&(0, 3, 0));
+        updates.notify((0, 3, 0));
+        for _ in 0..10 {
+            run_frame(&mut updates, &mut blocks, &mut meta);
+        }
+        assert!(canopy.iter().all(|pos| !blocks.contains_key(pos)), "floating leaves decay");
+        assert_eq!(blocks.get(&(0, 2, 0)), Some(&BlockType::Wood));
+    }
+}
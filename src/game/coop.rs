@@ -0,0 +1,241 @@
+use crate::game::block_updates::BlockPos;
+use crate::game::minecraft::BlockType;
+use crate::game::terrain::ChunkQueue;
+use nalgebra::{Matrix4, Vector4};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use wasm_bindgen::JsCast;
+use web_sys::HtmlElement;
+
+const LABEL_ID: &str = "remote-player-label";
+// Name label height above the remote player's feet
+const LABEL_HEIGHT: f32 = 2.1;
+
+// One block change as it travels between peers, {"p":[x,y,z],"b":"stone","t":ms} with
+// b null for a break. The host page moves these over its own channel.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct BlockEdit {
+    #[serde(rename = "p")]
+    pub pos: BlockPos,
+    #[serde(rename = "b")]
+    pub block: Option<BlockType>,
+    #[serde(rename = "t")]
+    pub time: f64,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum EditBatch {
+    One(BlockEdit),
+    Many(Vec<BlockEdit>),
+}
+
+// Feet position and look yaw as last reported by the peer
+#[derive(Deserialize, Clone)]
+pub struct RemotePlayer {
+    #[serde(default)]
+    pub name: String,
+    pub pos: [f32; 3],
+    #[serde(default)]
+    pub yaw: f32,
+}
+
+pub struct Coop {
+    outbox: Vec<BlockEdit>,
+    // The edit currently owning each position, local or remote, as (time, block)
+    stamps: HashMap<BlockPos, (f64, Option<BlockType>)>,
+    pub remote: Option<RemotePlayer>,
+    label: Option<HtmlElement>,
+}
+
+// Equal timestamps still need a winner both peers agree on, so order by block name
+fn tie_rank(block: Option<BlockType>) -> String {
+    serde_json::to_string(&block).unwrap_or_default()
+}
+
+impl Coop {
+    pub fn new() -> Self {
+        let label = remote_label();
+        if let Some(label) = &label {
+            label.style().set_property("display", "none").ok();
+        }
+        Coop { outbox: Vec::new(), stamps: HashMap::new(), remote: None, label }
+    }
+
+    // A later local edit always beats whatever it replaces, even with the peer's clock ahead
+    pub fn record_local(&mut self, pos: BlockPos, block: Option<BlockType>) {
+        self.record_local_at(pos, block, js_sys::Date::now());
+    }
+
+    fn record_local_at(&mut self, pos: BlockPos, block: Option<BlockType>, now: f64) {
+        let time = self.stamps.get(&pos).map_or(now, |(stamp, _)| now.max(stamp + 1.0));
+        self.stamps.insert(pos, (time, block));
+        self.outbox.push(BlockEdit { pos, block, time });
+    }
+
+    pub fn drain_local_edits(&mut self) -> String {
+        serde_json::to_string(&std::mem::take(&mut self.outbox)).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    // A single edit or an array of them
+    pub fn parse_edits(json: &str) -> Result<Vec<BlockEdit>, String> {
+        match serde_json::from_str(json).map_err(|e| format!("Invalid edit: {}", e))? {
+            EditBatch::One(edit) => Ok(vec![edit]),
+            EditBatch::Many(edits) => Ok(edits),
+        }
+    }
+
+    // The accepted edits whose chunks exist, to be written now. The rest are handed to
+    // the chunk queue for when their chunk generates.
+    pub fn receive(&mut self, json: &str, chunks: &mut ChunkQueue) -> Result<Vec<BlockEdit>, String> {
+        let mut ready = Vec::new();
+        for edit in Self::parse_edits(json)? {
+            if !self.accept(&edit) {
+                continue;
+            }
+            let (x, _, z) = edit.pos;
+            if chunks.is_generated(ChunkQueue::chunk_of(x, z)) {
+                ready.push(edit);
+            } else {
+                chunks.record_edit(edit.pos, edit.block);
+            }
+        }
+        Ok(ready)
+    }
+
+    // Last write wins: true if the remote edit is newer than what owns its position
+    pub fn accept(&mut self, edit: &BlockEdit) -> bool {
+        if !edit.time.is_finite() {
+            return false;
+        }
+        if let Some(&(time, block)) = self.stamps.get(&edit.pos) {
+            if edit.time < time || (edit.time == time && tie_rank(edit.block) <= tie_rank(block)) {
+                return false;
+            }
+        }
+        self.stamps.insert(edit.pos, (edit.time, edit.block));
+        true
+    }
+
+    // null clears the remote player
+    pub fn set_remote_player(&mut self, json: &str) -> Result<(), String> {
+        let remote: Option<RemotePlayer> = serde_json::from_str(json).map_err(|e| format!("Invalid player state: {}", e))?;
+        if remote.as_ref().is_some_and(|r| !r.pos.iter().chain([&r.yaw]).all(|v| v.is_finite())) {
+            return Err("Invalid player state: position and yaw must be finite".to_string());
+        }
+        if let (Some(label), Some(remote)) = (&self.label, &remote) {
+            if label.text_content().as_deref() != Some(remote.name.as_str()) {
+                label.set_text_content(Some(&remote.name));
+            }
+        }
+        self.remote = remote;
+        Ok(())
+    }
+
+    pub fn update_label(&self, projection: &Matrix4<f32>, view: &Matrix4<f32>, width: i32, height: i32) {
+        let label = match &self.label {
+            Some(label) => label,
+            None => return,
+        };
+        let screen = self.remote.as_ref().filter(|r| !r.name.is_empty()).and_then(|r| {
+            let clip = projection * view * Vector4::new(r.pos[0], r.pos[1] + LABEL_HEIGHT, r.pos[2], 1.0);
+            if clip.w <= 0.0 {
+                return None;
+            }
+            let x = (clip.x / clip.w + 1.0) / 2.0 * width as f32;
+            let y = (1.0 - clip.y / clip.w) / 2.0 * height as f32;
+            (x >= 0.0 && x <= width as f32 && y >= 0.0 && y <= height as f32).then_some((x, y))
+        });
+        let style = label.style();
+        match screen {
+            Some((x, y)) => {
+                style.set_property("display", "block").ok();
+                style.set_property("left", &format!("{}px", x.round())).ok();
+                style.set_property("top", &format!("{}px", y.round())).ok();
+            }
+            None => {
+                style.set_property("display", "none").ok();
+            }
+        }
+    }
+}
+
+// The label is shared with whichever game comes next, so it's only hidden
+impl Drop for Coop {
+    fn drop(&mut self) {
+        if let Some(label) = &self.label {
+            label.style().set_property("display", "none").ok();
+        }
+    }
+}
+
+// Reused across games so restarting Minecraft doesn't stack up labels
+fn remote_label() -> Option<HtmlElement> {
+    let document = web_sys::window()?.document()?;
+    if let Some(el) = document.get_element_by_id(LABEL_ID) {
+        return el.dyn_into::<HtmlElement>().ok();
+    }
+    let el = document.create_element("div").ok()?;
+    el.set_id(LABEL_ID);
+    el.set_class_name("player-name-label");
+    el.set_attribute("style", "position: absolute; transform: translateX(-50%); pointer-events: none; display: none;").ok();
+    document.body()?.append_child(&el).ok()?;
+    el.dyn_into::<HtmlElement>().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::Vector3;
+
+    fn peer() -> Coop {
+        Coop { outbox: Vec::new(), stamps: HashMap::new(), remote: None, label: None }
+    }
+
+    fn generate_around(chunks: &mut ChunkQueue, blocks: &mut HashMap<BlockPos, BlockType>, x: f32, z: f32) {
+        let pos = Vector3::new(x, 20.0, z);
+        chunks.request_around(pos, 0.0);
+        chunks.run_all(blocks, &HashMap::new(), pos, 0.0);
+    }
+
+    // One peer's edit log replayed on another: an edit to a chunk the second peer has, and
+    // two to a chunk it only generates afterwards, which must survive generation
+    #[test]
+    fn edit_log_replays_on_another_peer() {
+        let mut a = peer();
+        a.record_local_at((3, 40, 3), Some(BlockType::Brick), 1000.0);
+        a.record_local_at((200, 50, 200), Some(BlockType::Glass), 1001.0);
+        a.record_local_at((200, 0, 200), None, 1002.0);
+        let log = a.drain_local_edits();
+
+        let mut b = peer();
+        let mut chunks = ChunkQueue::new(7);
+        let mut blocks = HashMap::new();
+        generate_around(&mut chunks, &mut blocks, 0.0, 0.0);
+
+        for edit in b.receive(&log, &mut chunks).unwrap() {
+            match edit.block {
+                Some(block) => { blocks.insert(edit.pos, block); }
+                None => { blocks.remove(&edit.pos); }
+            }
+        }
+        assert_eq!(blocks.get(&(3, 40, 3)), Some(&BlockType::Brick));
+        assert!(!chunks.is_generated(ChunkQueue::chunk_of(200, 200)));
+        assert!(!blocks.contains_key(&(200, 50, 200)));
+
+        generate_around(&mut chunks, &mut blocks, 200.0, 200.0);
+        assert_eq!(blocks.get(&(200, 50, 200)), Some(&BlockType::Glass));
+        // Generation always puts bedrock here, the peer's break has to win
+        assert!(!blocks.contains_key(&(200, 0, 200)));
+    }
+
+    #[test]
+    fn older_remote_edit_loses_to_local() {
+        let mut local = peer();
+        local.record_local_at((1, 1, 1), Some(BlockType::Stone), 2000.0);
+        let stale = BlockEdit { pos: (1, 1, 1), block: None, time: 1500.0 };
+        assert!(!local.accept(&stale));
+        let newer = BlockEdit { pos: (1, 1, 1), block: None, time: 2500.0 };
+        assert!(local.accept(&newer));
+    }
+}
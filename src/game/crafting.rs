@@ -0,0 +1,129 @@
+use crate::game::minecraft::BlockType;
+use serde::Serialize;
+use std::collections::HashMap;
+
+#[derive(Serialize)]
+pub struct Ingredient {
+    pub block: BlockType,
+    pub count: u32,
+}
+
+#[derive(Serialize)]
+pub struct Recipe {
+    pub id: &'static str,
+    pub inputs: &'static [Ingredient],
+    pub output: Ingredient,
+}
+
+pub const RECIPES: &[Recipe] = &[
+    Recipe {
+        id: "planks",
+        inputs: &[Ingredient { block: BlockType::Wood, count: 1 }],
+        output: Ingredient { block: BlockType::Planks, count: 4 },
+    },
+    Recipe {
+        id: "brick",
+        inputs: &[Ingredient { block: BlockType::Stone, count: 4 }],
+        output: Ingredient { block: BlockType::Brick, count: 1 },
+    },
+    // Planks are the fuel for smelting
+    Recipe {
+        id: "glass",
+        inputs: &[
+            Ingredient { block: BlockType::Sand, count: 1 },
+            Ingredient { block: BlockType::Planks, count: 1 },
+        ],
+        output: Ingredient { block: BlockType::Glass, count: 1 },
+    },
+    Recipe {
+        id: "slab",
+        inputs: &[Ingredient { block: BlockType::Planks, count: 3 }],
+        output: Ingredient { block: BlockType::Slab, count: 6 },
+    },
+    Recipe {
+        id: "fence",
+        inputs: &[Ingredient { block: BlockType::Planks, count: 4 }],
+        output: Ingredient { block: BlockType::Fence, count: 3 },
+    },
+];
+
+pub type Inventory = HashMap<BlockType, u32>;
+
+pub fn find_recipe(id: &str) -> Option<&'static Recipe> {
+    RECIPES.iter().find(|r| r.id == id)
+}
+
+// All-or-nothing: the inventory is only touched once every ingredient is available
+pub fn craft(inventory: &mut Inventory, recipe_id: &str) -> Result<&'static Recipe, String> {
+    let recipe = find_recipe(recipe_id).ok_or_else(|| format!("Unknown recipe: {}", recipe_id))?;
+
+    for input in recipe.inputs {
+        let have = inventory.get(&input.block).copied().unwrap_or(0);
+        if have < input.count {
+            return Err(format!("Not enough {:?}: need {}, have {}", input.block, input.count, have));
+        }
+    }
+
+    for input in recipe.inputs {
+        if let Some(count) = inventory.get_mut(&input.block) {
+            *count -= input.count;
+            if *count == 0 {
+                inventory.remove(&input.block);
+            }
+        }
+    }
+    *inventory.entry(recipe.output.block).or_insert(0) += recipe.output.count;
+    Ok(recipe)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inventory(items: &[(BlockType, u32)]) -> Inventory {
+        items.iter().copied().collect()
+    }
+
+    #[test]
+    fn insufficient_ingredients_leave_the_inventory_alone() {
+        // Enough sand but no fuel
+        let mut inv = inventory(&[(BlockType::Sand, 5)]);
+        assert!(craft(&mut inv, "glass").is_err());
+        assert_eq!(inv, inventory(&[(BlockType::Sand, 5)]));
+
+        let mut inv = inventory(&[(BlockType::Stone, 3)]);
+        assert!(craft(&mut inv, "brick").is_err());
+        assert_eq!(inv, inventory(&[(BlockType::Stone, 3)]));
+    }
+
+    #[test]
+    fn crafting_consumes_exactly_the_inputs() {
+        let mut inv = inventory(&[(BlockType::Stone, 9), (BlockType::Wood, 1)]);
+        craft(&mut inv, "brick").unwrap();
+        assert_eq!(inv, inventory(&[(BlockType::Stone, 5), (BlockType::Wood, 1), (BlockType::Brick, 1)]));
+
+        // Using up the last of a block drops its entry rather than leaving a zero
+        craft(&mut inv, "planks").unwrap();
+        assert_eq!(inv.get(&BlockType::Wood), None);
+        assert_eq!(inv.get(&BlockType::Planks), Some(&4));
+
+        let mut inv = inventory(&[(BlockType::Sand, 1), (BlockType::Planks, 1)]);
+        craft(&mut inv, "glass").unwrap();
+        assert_eq!(inv, inventory(&[(BlockType::Glass, 1)]));
+    }
+
+    #[test]
+    fn unknown_recipe_is_an_error() {
+        let mut inv = inventory(&[(BlockType::Wood, 10)]);
+        let Err(err) = craft(&mut inv, "diamond_sword") else { panic!("crafted an unknown recipe") };
+        assert!(err.contains("diamond_sword"));
+        assert_eq!(inv, inventory(&[(BlockType::Wood, 10)]));
+    }
+
+    #[test]
+    fn recipe_ids_are_unique() {
+        for (i, recipe) in RECIPES.iter().enumerate() {
+            assert!(RECIPES[..i].iter().all(|other| other.id != recipe.id), "{} is listed twice", recipe.id);
+        }
+    }
+}
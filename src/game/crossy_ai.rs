@@ -0,0 +1,227 @@
+use crate::game::{reaches_column, GameObject, Lane, LaneType, HOP_SPEED, SIM_DT};
+
+pub enum AiMove {
+    Forward,
+    Left,
+    Right,
+}
+
+const HOP_SECONDS: f32 = 2.0 / HOP_SPEED * SIM_DT;
+// How long a landing spot has to stay clear after touching down
+const SAFETY_SECONDS: f32 = 0.4;
+// How far ahead a ride is checked for carrying us into the edge
+const EDGE_LOOKAHEAD_SECONDS: f32 = 1.5;
+const PLAYER_REACH: f32 = 0.4;
+// Landing this far past a log's end still counts, the player box overhangs it
+const LOG_SLACK: f32 = 0.2;
+
+// Reads only simulation state. Called once the player is standing still;
+// None means waiting where we are is the best option.
+pub fn choose_move(player: &GameObject, lanes: &[Lane], half_width: f32) -> Option<AiMove> {
+    let lane_idx = (player.z / 2.0).round() as i32;
+    let here = lane_at(lanes, lane_idx)?;
+    let ahead = lane_at(lanes, lane_idx + 1);
+    let x = player.x;
+
+    if ahead.is_some_and(|ahead| spot_safe(ahead, x, HOP_SECONDS, SAFETY_SECONDS, half_width)) {
+        return Some(AiMove::Forward);
+    }
+
+    // Lily pads never come to us, so head for the nearest one. Otherwise prefer the center.
+    let pad = ahead.and_then(|ahead| nearest_pad(ahead, x));
+    let toward_right = pad.map_or(x < 0.0, |pad| pad > x);
+    let mut sides = [(AiMove::Left, x - 2.0), (AiMove::Right, x + 2.0)];
+    if toward_right {
+        sides.reverse();
+    }
+    let sides = sides.into_iter().filter(|(_, side_x)| side_x.abs() <= half_width);
+
+    // A ride is checked further out so we get off before it reaches the edge
+    let stay = if here.lane_type == LaneType::Water { EDGE_LOOKAHEAD_SECONDS.max(HOP_SECONDS + SAFETY_SECONDS) } else { HOP_SECONDS + SAFETY_SECONDS };
+    if spot_safe(here, x, 0.0, stay, half_width) {
+        // Waiting is fine, but a step sideways that lines up a way forward is better
+        for (step, side_x) in sides {
+            let lines_up = ahead.is_some_and(|ahead| spot_safe(ahead, side_x, 2.0 * HOP_SECONDS, SAFETY_SECONDS, half_width));
+            let closer = pad.is_some_and(|pad| (pad - side_x).abs() < (pad - x).abs());
+            if (lines_up || closer) && spot_safe(here, side_x, HOP_SECONDS, SAFETY_SECONDS, half_width) {
+                return Some(step);
+            }
+        }
+        return None;
+    }
+
+    // Staying is fatal, sidestep
+    for (step, side_x) in sides {
+        if spot_safe(here, side_x, HOP_SECONDS, SAFETY_SECONDS, half_width) {
+            return Some(step);
+        }
+    }
+
+    // Nothing is safe, at least make progress
+    Some(AiMove::Forward)
+}
+
+fn lane_at(lanes: &[Lane], index: i32) -> Option<&Lane> {
+    lanes.iter().find(|lane| (lane.z / 2.0).round() as i32 == index)
+}
+
+// The closest stepping stone in a lane of lily pads
+fn nearest_pad(lane: &Lane, x: f32) -> Option<f32> {
+    if lane.lane_type != LaneType::Water || lane.obstacles.iter().any(|pad| pad.velocity_x != 0.0) {
+        return None;
+    }
+    lane.obstacles.iter()
+        .map(|pad| pad.x)
+        .min_by(|a, b| (a - x).abs().total_cmp(&(b - x).abs()))
+}
+
+// Whether standing at x in this lane, from `arrive` seconds from now for `stay` seconds, survives
+fn spot_safe(lane: &Lane, x: f32, arrive: f32, stay: f32, half_width: f32) -> bool {
+    let ticks = arrive / SIM_DT;
+    match lane.lane_type {
+        LaneType::Grass | LaneType::Finish => true,
+        // A train's velocity is set from its signal on, only an idle track can be crossed
+        LaneType::Rail if lane.obstacles.iter().any(|train| train.velocity_x != 0.0) => false,
+        LaneType::Road | LaneType::Rail => !lane.obstacles.iter().any(|car| {
+            let arrival_x = car.x + car.velocity_x * ticks;
+            reaches_column(arrival_x, car.velocity_x, PLAYER_REACH + car.width / 2.0, x, stay)
+        }),
+        LaneType::Water => lane.obstacles.iter().any(|log| {
+            let arrival_x = log.x + log.velocity_x * ticks;
+            // Land on the log, and don't ride it into the edge clamp
+            let drifted_x = x + log.velocity_x * stay / SIM_DT;
+            (arrival_x - x).abs() < log.width / 2.0 + LOG_SLACK && drifted_x.abs() <= half_width
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{
+        clamp_to_field, create_lane_procedural, landing_x, log_footing, step_hop, step_traffic, touch_lane, wrap_bound,
+        CollisionMargins, DifficultyConfig, LaneDetails, ObjectKind, TrafficMode, DEFAULT_HALF_WIDTH, DEFAULT_IDLE_SECONDS,
+        EAGLE_SPEED, EAGLE_START_DISTANCE, LANES_KEPT_BEHIND,
+    };
+
+    // Game::tick without the renderer: the same hop, traffic and lane steps, with the eagle
+    // reduced to its idle deadline. Returns the lane the run ended on, or max_lanes once it
+    // gets that far.
+    fn headless_run(seed: u32, max_lanes: i32) -> i32 {
+        let half_width = DEFAULT_HALF_WIDTH;
+        let params = DifficultyConfig::default();
+        let margins = CollisionMargins::default();
+        let wrap = wrap_bound(half_width);
+        let idle_limit = DEFAULT_IDLE_SECONDS + EAGLE_START_DISTANCE / EAGLE_SPEED;
+
+        let mut lanes: Vec<Lane> = (-5..=24).map(|i| create_lane_procedural(i as f32 * 2.0, i, seed, half_width, &params)).collect();
+        let mut furthest = 24;
+        let mut player = GameObject { kind: ObjectKind::Player, ..GameObject::new(0.0, 0.5, 0.0, 0.8, 1.0, 0.8, (1.0, 1.0, 1.0)) };
+        // Direction and target as Game keeps them
+        let mut hop: Option<(i32, f32, f32)> = None;
+        let (mut clock, mut idle, mut camera_min_z) = (0.0, 0.0, 0.0f32);
+
+        loop {
+            clock += SIM_DT;
+            if hop.is_none() {
+                let (x, z) = (player.x, player.z);
+                hop = match choose_move(&player, &lanes, half_width) {
+                    Some(AiMove::Forward) => Some((0, x, z + 2.0)),
+                    Some(AiMove::Left) if x - 2.0 >= -half_width => Some((2, x - 2.0, z)),
+                    Some(AiMove::Right) if x + 2.0 <= half_width => Some((3, x + 2.0, z)),
+                    _ => None,
+                };
+            }
+
+            let landed = hop.is_some_and(|(direction, target_x, target_z)| step_hop(&mut player, direction, target_x, target_z));
+            if landed {
+                hop = None;
+            }
+            let moving = hop.is_some();
+            let lane_idx = (player.z / 2.0).round() as i32;
+
+            if landed {
+                let footing = lanes.iter()
+                    .find(|lane| (lane.z / 2.0).round() as i32 == lane_idx && lane.lane_type == LaneType::Water)
+                    .and_then(|lane| lane.obstacles.iter().find(|o| o.kind == ObjectKind::Log && player.collides_horizontal(o, &margins)))
+                    .map(|log| log_footing(log, &player));
+                if let Some(footing) = footing {
+                    player.x = landing_x(player.x, footing);
+                }
+            }
+
+            while furthest < lane_idx + 20 {
+                furthest += 1;
+                lanes.push(create_lane_procedural(furthest as f32 * 2.0, furthest, seed, half_width, &params));
+            }
+            if player.z > camera_min_z {
+                idle = 0.0;
+            } else {
+                idle += SIM_DT;
+            }
+            camera_min_z = camera_min_z.max(player.z);
+            let camera_lane_idx = (camera_min_z / 2.0).round() as i32;
+            lanes.retain(|lane| (lane.z / 2.0).round() as i32 > camera_lane_idx - LANES_KEPT_BEHIND);
+
+            step_traffic(&mut lanes, TrafficMode::Normal, seed, wrap, clock);
+
+            let dead = lanes.iter()
+                .find(|lane| (lane.z / 2.0).round() as i32 == lane_idx)
+                .is_some_and(|lane| touch_lane(lane, &mut player, moving, half_width, &margins).hazard.is_some());
+            player.x = clamp_to_field(player.x, half_width);
+
+            if dead || idle >= idle_limit || lane_idx >= max_lanes {
+                return lane_idx.min(max_lanes);
+            }
+        }
+    }
+
+    #[test]
+    fn ai_survives_thirty_lanes_on_average() {
+        let runs = 100;
+        let total: i32 = (0..runs).map(|seed| headless_run(seed, 100)).sum();
+        let average = total as f32 / runs as f32;
+        assert!(average >= 30.0, "the AI averaged {:.1} lanes", average);
+    }
+
+    fn lane(lane_type: LaneType, obstacles: Vec<GameObject>) -> Lane {
+        Lane { z: 2.0, lane_type, obstacles, coins: Vec::new(), puddles: Vec::new(), decorations: Vec::new(), power_ups: Vec::new(), details: LaneDetails::None }
+    }
+
+    fn moving(x: f32, width: f32, velocity_x: f32) -> GameObject {
+        GameObject { velocity_x, ..GameObject::new(x, 0.5, 2.0, width, 1.0, 1.5, (1.0, 1.0, 1.0)) }
+    }
+
+    #[test]
+    fn car_about_to_cross_the_column_is_unsafe() {
+        let road = lane(LaneType::Road, vec![moving(-3.0, 2.0, 0.1)]);
+        assert!(!spot_safe(&road, 0.0, HOP_SECONDS, SAFETY_SECONDS, DEFAULT_HALF_WIDTH));
+        // The same car heading away
+        let road = lane(LaneType::Road, vec![moving(-3.0, 2.0, -0.1)]);
+        assert!(spot_safe(&road, 0.0, HOP_SECONDS, SAFETY_SECONDS, DEFAULT_HALF_WIDTH));
+    }
+
+    #[test]
+    fn signalled_train_closes_the_track() {
+        let idle = lane(LaneType::Rail, vec![moving(-35.0, 20.0, 0.0)]);
+        assert!(spot_safe(&idle, 0.0, HOP_SECONDS, SAFETY_SECONDS, DEFAULT_HALF_WIDTH));
+        let signalled = lane(LaneType::Rail, vec![moving(-35.0, 20.0, 0.8)]);
+        assert!(!spot_safe(&signalled, 0.0, HOP_SECONDS, SAFETY_SECONDS, DEFAULT_HALF_WIDTH));
+    }
+
+    #[test]
+    fn log_heading_into_the_edge_is_left_early() {
+        let river = lane(LaneType::Water, vec![moving(9.0, 4.0, 0.015)]);
+        assert!(spot_safe(&river, 9.0, 0.0, HOP_SECONDS + SAFETY_SECONDS, DEFAULT_HALF_WIDTH));
+        assert!(!spot_safe(&river, 9.0, 0.0, EDGE_LOOKAHEAD_SECONDS, DEFAULT_HALF_WIDTH));
+    }
+
+    #[test]
+    fn waits_on_grass_steps_toward_the_nearest_pad() {
+        let mut grass = lane(LaneType::Grass, Vec::new());
+        grass.z = 0.0;
+        let pads = lane(LaneType::Water, vec![moving(-6.0, 1.0, 0.0), moving(6.0, 1.0, 0.0)]);
+        let player = GameObject::new(3.0, 0.5, 0.0, 0.8, 1.0, 0.8, (1.0, 1.0, 1.0));
+        assert!(matches!(choose_move(&player, &[grass, pads], DEFAULT_HALF_WIDTH), Some(AiMove::Right)));
+    }
+}
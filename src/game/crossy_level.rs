@@ -0,0 +1,296 @@
+use crate::game::{create_details, create_finish_lane, create_puddles, create_train, GameObject, Lane, LaneType, ObjectKind, DEFAULT_HALF_WIDTH};
+use serde::{Deserialize, Serialize};
+
+// Obstacles may start anywhere they could wrap to, coins only where the player can reach
+const MAX_OBSTACLE_X: f32 = DEFAULT_HALF_WIDTH + 5.0;
+const MAX_COIN_X: f32 = DEFAULT_HALF_WIDTH;
+
+// A hand-made run of lanes starting at lane 1. Positions are in default-width units
+// like the procedural layout, so a level stretches with the playfield.
+#[derive(Serialize, Deserialize)]
+pub struct CustomLevel {
+    #[serde(default, rename = "loop")]
+    pub looping: bool,
+    lanes: Vec<ScriptedLane>,
+}
+
+#[derive(Serialize)]
+struct ScriptedLane {
+    #[serde(rename = "type")]
+    lane_type: LaneType,
+    obstacles: Vec<ScriptedObstacle>,
+    coins: Vec<ScriptedCoin>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawLane {
+    #[serde(rename = "type")]
+    lane_type: LaneType,
+    #[serde(default)]
+    obstacles: Vec<ScriptedObstacle>,
+    #[serde(default)]
+    coins: Vec<ScriptedCoin>,
+}
+
+// Sizes left out fall back to what the generator uses for the lane type.
+// On grass an obstacle is a tree unless it is marked as a rock.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+struct ScriptedObstacle {
+    x: f32,
+    #[serde(default)]
+    velocity: f32,
+    width: Option<f32>,
+    height: Option<f32>,
+    depth: Option<f32>,
+    #[serde(default)]
+    color: usize,
+    #[serde(default)]
+    rock: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+struct ScriptedCoin {
+    x: f32,
+    #[serde(default)]
+    velocity: f32,
+}
+
+impl ScriptedObstacle {
+    fn size(&self, lane_type: LaneType) -> (f32, f32, f32) {
+        let default = match lane_type {
+            LaneType::Road => (2.0, 1.0, 1.5),
+            LaneType::Water => (4.0, 0.6, 1.5),
+            _ if self.rock => (0.8, 0.5, 0.6),
+            _ => (0.8, 2.0, 0.8),
+        };
+        (self.width.unwrap_or(default.0), self.height.unwrap_or(default.1), self.depth.unwrap_or(default.2))
+    }
+}
+
+// Validating inside the lane's own map lets serde_json tag errors with the line and column
+// where the lane closes; a try_from on the seq element would only get the end of the array
+impl<'de> Deserialize<'de> for ScriptedLane {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct LaneVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for LaneVisitor {
+            type Value = ScriptedLane;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a lane")
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(self, map: A) -> Result<ScriptedLane, A::Error> {
+                let raw = RawLane::deserialize(serde::de::value::MapAccessDeserializer::new(map))?;
+                ScriptedLane::try_from(raw).map_err(serde::de::Error::custom)
+            }
+        }
+
+        deserializer.deserialize_map(LaneVisitor)
+    }
+}
+
+impl TryFrom<RawLane> for ScriptedLane {
+    type Error = String;
+
+    fn try_from(raw: RawLane) -> Result<Self, String> {
+        if raw.lane_type == LaneType::Finish && !(raw.obstacles.is_empty() && raw.coins.is_empty()) {
+            return Err("finish lanes can't hold obstacles or coins".to_string());
+        }
+        if raw.lane_type == LaneType::Rail && !raw.obstacles.is_empty() {
+            return Err("rail lanes run their own train and can't hold obstacles".to_string());
+        }
+        for (i, obstacle) in raw.obstacles.iter().enumerate() {
+            let (w, h, d) = obstacle.size(raw.lane_type);
+            if !obstacle.x.is_finite() || obstacle.x.abs() > MAX_OBSTACLE_X {
+                return Err(format!("obstacle {} x {} is outside -{max}..{max}", i, obstacle.x, max = MAX_OBSTACLE_X));
+            }
+            if !obstacle.velocity.is_finite() || ![w, h, d].iter().all(|v| v.is_finite() && *v > 0.0) {
+                return Err(format!("obstacle {} needs a finite velocity and positive sizes", i));
+            }
+        }
+        for (i, a) in raw.obstacles.iter().enumerate() {
+            for (j, b) in raw.obstacles.iter().enumerate().skip(i + 1) {
+                let reach = (a.size(raw.lane_type).0 + b.size(raw.lane_type).0) / 2.0;
+                if (a.x - b.x).abs() < reach {
+                    return Err(format!("obstacles {} and {} overlap", i, j));
+                }
+            }
+        }
+        for (i, coin) in raw.coins.iter().enumerate() {
+            if !coin.x.is_finite() || coin.x.abs() > MAX_COIN_X {
+                return Err(format!("coin {} x {} is outside -{max}..{max}", i, coin.x, max = MAX_COIN_X));
+            }
+            if !coin.velocity.is_finite() {
+                return Err(format!("coin {} needs a finite velocity", i));
+            }
+        }
+        Ok(ScriptedLane { lane_type: raw.lane_type, obstacles: raw.obstacles, coins: raw.coins })
+    }
+}
+
+impl CustomLevel {
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let level: CustomLevel = serde_json::from_str(json).map_err(|e| format!("Invalid level: {}", e))?;
+        if level.lanes.is_empty() {
+            return Err("Invalid level: no lanes".to_string());
+        }
+        Ok(level)
+    }
+
+    // Generated lanes in the level schema, a start for hand editing. The schema has no
+    // lily pads, trains come with their rail lane and scenery isn't scripted, so those drop out.
+    // Generated traffic can start overlapping, which a level can't, so the later of two
+    // overlapping obstacles drops out too and every lane written here parses.
+    #[cfg(test)]
+    pub fn from_lanes(lanes: &[Lane], half_width: f32, looping: bool) -> Self {
+        let scale = half_width / DEFAULT_HALF_WIDTH;
+        let lanes = lanes.iter().map(|lane| ScriptedLane::from_lane(lane, scale)).collect();
+        CustomLevel { looping, lanes }
+    }
+
+    // None past the scripted lanes of a non-looping level, the caller generates those
+    pub fn lane(&self, index: i32, world_seed: u32, half_width: f32) -> Option<Lane> {
+        if index < 1 {
+            return None;
+        }
+        let offset = (index - 1) as usize;
+        let scripted = if self.looping {
+            &self.lanes[offset % self.lanes.len()]
+        } else {
+            self.lanes.get(offset)?
+        };
+        Some(scripted.build(index as f32 * 2.0, index, world_seed, half_width))
+    }
+}
+
+impl ScriptedLane {
+    #[cfg(test)]
+    fn from_lane(lane: &Lane, scale: f32) -> Self {
+        let mut obstacles: Vec<ScriptedObstacle> = Vec::new();
+        for o in lane.obstacles.iter().filter(|o| is_scripted(o.kind)) {
+            let x = (o.x / scale).clamp(-MAX_OBSTACLE_X, MAX_OBSTACLE_X);
+            if obstacles.iter().any(|kept| (kept.x - x).abs() < (kept.width.unwrap_or(0.0) + o.width) / 2.0) {
+                continue;
+            }
+            obstacles.push(ScriptedObstacle {
+                x,
+                velocity: o.velocity_x,
+                width: Some(o.width),
+                height: Some(o.height),
+                depth: Some(o.depth),
+                color: o.color_idx,
+                rock: o.kind == ObjectKind::Rock,
+            });
+        }
+        let coins = lane.coins.iter().map(|c| ScriptedCoin { x: c.x / scale, velocity: c.velocity_x }).collect();
+        ScriptedLane { lane_type: lane.lane_type, obstacles, coins }
+    }
+
+    fn build(&self, z: f32, index: i32, world_seed: u32, half_width: f32) -> Lane {
+        if self.lane_type == LaneType::Finish {
+            return create_finish_lane(z);
+        }
+        let scale = half_width / DEFAULT_HALF_WIDTH;
+        let mut obstacles: Vec<GameObject> = self.obstacles.iter().map(|o| {
+            let (w, h, d) = o.size(self.lane_type);
+            let mut obstacle = match self.lane_type {
+                LaneType::Road => GameObject::new_car(o.x * scale, 0.5, z, w, h, d, o.color),
+                LaneType::Water => GameObject::new(o.x * scale, 0.3, z, w, h, d, (0.5, 0.3, 0.1)),
+                _ if o.rock => GameObject::new(o.x * scale, 0.3, z, w, h, d, (0.5, 0.5, 0.5)),
+                _ => GameObject::new(o.x * scale, h / 2.0, z, w, h, d, (0.2, 0.5, 0.15)),
+            };
+            obstacle.velocity_x = o.velocity;
+            match self.lane_type {
+                LaneType::Water => obstacle.kind = ObjectKind::Log,
+                LaneType::Grass if o.rock => obstacle.kind = ObjectKind::Rock,
+                LaneType::Grass => obstacle.kind = ObjectKind::Tree,
+                _ => {}
+            }
+            obstacle
+        }).collect();
+        if self.lane_type == LaneType::Rail {
+            obstacles.push(create_train(z, half_width));
+        }
+        // Coins over water ride on logs, so they sit higher
+        let coin_y = if self.lane_type == LaneType::Water { 0.8 } else { 0.5 };
+        let coins = self.coins.iter().map(|c| {
+            let mut coin = GameObject::new_coin(c.x * scale, coin_y, z);
+            coin.velocity_x = c.velocity;
+            coin
+        }).collect();
+        let puddles = if self.lane_type == LaneType::Road { create_puddles(index, world_seed, half_width) } else { Vec::new() };
+        let details = create_details(self.lane_type, z, half_width);
+        Lane { z, lane_type: self.lane_type, obstacles, coins, puddles, decorations: Vec::new(), power_ups: Vec::new(), details }
+    }
+}
+
+#[cfg(test)]
+fn is_scripted(kind: ObjectKind) -> bool {
+    matches!(kind, ObjectKind::Car | ObjectKind::Truck | ObjectKind::Log | ObjectKind::Tree | ObjectKind::Rock)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{create_lane_procedural, DifficultyConfig};
+
+    // Kind, x, velocity and size of each obstacle, then x and velocity of each coin
+    type Layout = (Vec<(ObjectKind, f32, f32, f32, f32, f32)>, Vec<(f32, f32)>);
+
+    // What the schema keeps of a lane, in a form that compares
+    fn layout(lane: &Lane) -> Layout {
+        let obstacles = lane.obstacles.iter()
+            .filter(|o| is_scripted(o.kind))
+            .map(|o| (o.kind, o.x, o.velocity_x, o.width, o.height, o.depth))
+            .collect();
+        let coins = lane.coins.iter().map(|c| (c.x, c.velocity_x)).collect();
+        (obstacles, coins)
+    }
+
+    fn to_json(lanes: &[Lane]) -> String {
+        serde_json::to_string(&CustomLevel::from_lanes(lanes, DEFAULT_HALF_WIDTH, false)).unwrap()
+    }
+
+    #[test]
+    fn generated_lanes_round_trip_through_the_schema() {
+        let params = DifficultyConfig::default();
+        let generated: Vec<Lane> = (1..60)
+            .map(|i| create_lane_procedural(i as f32 * 2.0, i, 1234, DEFAULT_HALF_WIDTH, &params))
+            .collect();
+
+        let level = CustomLevel::parse(&to_json(&generated)).unwrap();
+        let mut dropped = 0;
+        for (i, lane) in generated.iter().enumerate() {
+            let loaded = level.lane(i as i32 + 1, 1234, DEFAULT_HALF_WIDTH).unwrap();
+            assert!(loaded.lane_type == lane.lane_type);
+            let (kept, coins) = layout(&loaded);
+            let (original, original_coins) = layout(lane);
+            assert_eq!(coins, original_coins, "lane {}", i + 1);
+            // Every obstacle comes back as generated, except ones the export dropped for
+            // overlapping an obstacle it kept
+            assert!(kept.iter().all(|o| original.contains(o)), "lane {}", i + 1);
+            for o in original.iter().filter(|o| !kept.contains(o)) {
+                assert!(kept.iter().any(|k| (k.1 - o.1).abs() < (k.3 + o.3) / 2.0), "lane {} lost {:?}", i + 1, o);
+                dropped += 1;
+            }
+        }
+        assert!(dropped < generated.len());
+    }
+
+    #[test]
+    fn overlapping_obstacles_are_rejected_with_their_line() {
+        let json = "{\n  \"lanes\": [\n    {\"type\": \"road\", \"obstacles\": [{\"x\": 0}, {\"x\": 1}]}\n  ]\n}";
+        let error = CustomLevel::parse(json).err().unwrap();
+        assert!(error.contains("overlap") && error.contains("line 3"), "{}", error);
+    }
+
+    #[test]
+    fn out_of_range_coin_is_rejected() {
+        let json = r#"{"lanes": [{"type": "grass", "coins": [{"x": 40}]}]}"#;
+        assert!(CustomLevel::parse(json).err().unwrap().contains("coin 0"));
+    }
+}
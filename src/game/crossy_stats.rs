@@ -0,0 +1,171 @@
+use crate::engine::settings::{self, Settings};
+use crate::game::RunSummary;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// Bumped whenever a field changes meaning, older blobs go through migrate()
+pub const STATS_VERSION: u32 = 1;
+
+// Plain copies of longest_run and total_coins for pages that read localStorage directly
+const HIGH_SCORE_KEY: &str = "crossy_high_score";
+const TOTAL_COINS_KEY: &str = "crossy_total_coins";
+
+// Lifetime Crossy numbers, kept in the settings blob under crossy_stats so the in-memory
+// fallback covers them with everything else. Fields missing from an older blob read as zero.
+// The best score and coin total are also written to crossy_high_score and crossy_total_coins.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct CrossyStats {
+    pub version: u32,
+    pub total_runs: u32,
+    pub total_lanes: u64,
+    pub total_coins: u64,
+    pub total_gems: u64,
+    pub deaths_by_cause: BTreeMap<String, u32>,
+    pub longest_run: i32,
+    pub total_play_seconds: f64,
+}
+
+impl CrossyStats {
+    pub fn load() -> Self {
+        let value = Settings::load().crossy_stats;
+        if value.is_null() {
+            // No stats blob yet, the plain keys may still hold a best score and coin total
+            let read = |key: &str| settings::local_storage().and_then(|storage| storage.get_item(key).ok().flatten());
+            return Self::from_keys(read(HIGH_SCORE_KEY), read(TOTAL_COINS_KEY));
+        }
+        Self::from_value(value)
+    }
+
+    fn from_keys(high_score: Option<String>, total_coins: Option<String>) -> Self {
+        CrossyStats {
+            version: STATS_VERSION,
+            longest_run: high_score.and_then(|s| s.parse::<i32>().ok()).unwrap_or(0).max(0),
+            total_coins: total_coins.and_then(|s| s.parse().ok()).unwrap_or(0),
+            ..Default::default()
+        }
+    }
+
+    // Anything unreadable starts over from zero rather than stopping the game
+    pub fn from_value(value: serde_json::Value) -> Self {
+        if value.is_null() {
+            return CrossyStats { version: STATS_VERSION, ..Default::default() };
+        }
+        let mut stats: CrossyStats = serde_json::from_value(value).unwrap_or_default();
+        stats.migrate();
+        stats
+    }
+
+    // Version 0 (no version field) held only total_runs and longest_run, serde's
+    // defaults fill in the rest. Out-of-range values are clamped rather than trusted.
+    fn migrate(&mut self) {
+        if !self.total_play_seconds.is_finite() || self.total_play_seconds < 0.0 {
+            self.total_play_seconds = 0.0;
+        }
+        self.longest_run = self.longest_run.max(0);
+        self.version = STATS_VERSION;
+    }
+
+    pub fn record_run(&mut self, summary: &RunSummary) {
+        self.total_runs += 1;
+        self.total_lanes += summary.score.max(0) as u64;
+        self.total_coins += summary.coins.max(0) as u64;
+        self.total_gems += summary.gems.max(0) as u64;
+        if let Some(cause) = summary.cause {
+            *self.deaths_by_cause.entry(cause.to_string()).or_insert(0) += 1;
+        }
+        self.longest_run = self.longest_run.max(summary.score);
+        self.total_play_seconds += summary.play_seconds as f64;
+    }
+
+    // Total coins and the other totals stay
+    pub fn reset_high_score() {
+        let mut stats = Self::load();
+        stats.longest_run = 0;
+        stats.save();
+    }
+
+    pub fn average_run_length(&self) -> f64 {
+        if self.total_runs == 0 {
+            0.0
+        } else {
+            self.total_lanes as f64 / self.total_runs as f64
+        }
+    }
+
+    pub fn save(&self) {
+        let value = serde_json::to_value(self).unwrap_or_default();
+        Settings::update(|s| s.crossy_stats = value);
+        // Refused writes are fine here too, the settings copy is the one read back
+        if let Some(storage) = settings::local_storage() {
+            storage.set_item(HIGH_SCORE_KEY, &self.longest_run.to_string()).ok();
+            storage.set_item(TOTAL_COINS_KEY, &self.total_coins.to_string()).ok();
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        if let Some(fields) = value.as_object_mut() {
+            fields.insert("average_run_length".to_string(), serde_json::json!(self.average_run_length()));
+        }
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_zero_blob_keeps_its_totals() {
+        let stats = CrossyStats::from_value(serde_json::json!({ "total_runs": 12, "longest_run": 40 }));
+        assert_eq!(stats.version, STATS_VERSION);
+        assert_eq!(stats.total_runs, 12);
+        assert_eq!(stats.longest_run, 40);
+        assert_eq!((stats.total_lanes, stats.total_coins, stats.total_gems), (0, 0, 0));
+        assert!(stats.deaths_by_cause.is_empty());
+        assert_eq!(stats.total_play_seconds, 0.0);
+    }
+
+    #[test]
+    fn out_of_range_values_are_clamped() {
+        let stats = CrossyStats::from_value(serde_json::json!({ "total_runs": 3, "longest_run": -7, "total_play_seconds": -1.5 }));
+        assert_eq!(stats.longest_run, 0);
+        assert_eq!(stats.total_play_seconds, 0.0);
+        assert_eq!(stats.total_runs, 3);
+    }
+
+    #[test]
+    fn unreadable_blob_starts_over() {
+        let stats = CrossyStats::from_value(serde_json::json!({ "total_runs": "lots" }));
+        assert_eq!(stats.total_runs, 0);
+        assert_eq!(stats.version, STATS_VERSION);
+        assert_eq!(CrossyStats::from_value(serde_json::Value::Null).version, STATS_VERSION);
+    }
+
+    fn run(score: i32, coins: i32, gems: i32) -> RunSummary {
+        RunSummary { seed: 1, score, coins, gems, deaths: 1, finish_time_ms: None, cause: Some("car"), play_seconds: 10.0, theme: None }
+    }
+
+    #[test]
+    fn gems_add_up_across_runs() {
+        let mut stats = CrossyStats::default();
+        stats.record_run(&run(12, 30, 2));
+        stats.record_run(&run(4, 5, 0));
+        stats.record_run(&run(20, 41, 3));
+        assert_eq!((stats.total_gems, stats.total_coins, stats.total_runs), (5, 76, 3));
+        // A bad summary doesn't take gems away
+        stats.record_run(&run(0, 0, -4));
+        assert_eq!(stats.total_gems, 5);
+    }
+
+    #[test]
+    fn plain_keys_seed_the_best_score_and_coin_total() {
+        let stats = CrossyStats::from_keys(Some("42".to_string()), Some("310".to_string()));
+        assert_eq!((stats.longest_run, stats.total_coins, stats.total_runs), (42, 310, 0));
+        assert_eq!(stats.version, STATS_VERSION);
+        let damaged = CrossyStats::from_keys(Some("-3".to_string()), Some("lots".to_string()));
+        assert_eq!((damaged.longest_run, damaged.total_coins), (0, 0));
+        assert_eq!(CrossyStats::from_keys(None, None).longest_run, 0);
+    }
+}
@@ -0,0 +1,74 @@
+use crate::game::{Lane, LaneType, ObjectKind, Palette};
+
+type Color = (f32, f32, f32);
+
+// An event look and its gameplay twists, layered over normal play. Colors left as None
+// keep whatever the player's palette has, so accessibility palettes still apply underneath.
+pub struct ThemeDef {
+    pub key: &'static str,
+    pub background: Option<Color>,
+    pub grass: Option<Color>,
+    pub grass_tuft: Option<Color>,
+    pub grass_blade: Option<Color>,
+    pub dirt_patch: Option<Color>,
+    pub water: Option<Color>,
+    pub water_highlight: Option<Color>,
+    pub water_deep: Option<Color>,
+    pub coin: Option<Color>,
+    pub log: Option<Color>,
+    // Ribbon drawn across each coin, turning it into a gift box
+    pub coin_ribbon: Option<Color>,
+    pub coin_value: i32,
+    // Units the player keeps sliding after landing on a log
+    pub log_slide: f32,
+    pub snowfall: bool,
+}
+
+const WINTER: ThemeDef = ThemeDef {
+    key: "winter",
+    background: Some((0.75, 0.82, 0.92)),
+    grass: Some((0.9, 0.93, 0.97)),
+    grass_tuft: Some((0.8, 0.85, 0.9)),
+    grass_blade: Some((0.55, 0.65, 0.6)),
+    dirt_patch: Some((0.7, 0.75, 0.82)),
+    water: Some((0.55, 0.75, 0.9)),
+    water_highlight: Some((0.8, 0.92, 1.0)),
+    water_deep: Some((0.35, 0.55, 0.75)),
+    coin: Some((0.85, 0.15, 0.2)),
+    log: Some((0.82, 0.92, 0.98)),
+    coin_ribbon: Some((1.0, 0.85, 0.2)),
+    coin_value: 2,
+    log_slide: 0.3,
+    snowfall: true,
+};
+
+pub const THEMES: [ThemeDef; 1] = [WINTER];
+
+pub fn theme(key: &str) -> Option<&'static ThemeDef> {
+    THEMES.iter().find(|theme| theme.key == key)
+}
+
+impl ThemeDef {
+    pub fn apply(&self, palette: Palette) -> Palette {
+        Palette {
+            grass: self.grass.unwrap_or(palette.grass),
+            grass_tuft: self.grass_tuft.unwrap_or(palette.grass_tuft),
+            grass_blade: self.grass_blade.unwrap_or(palette.grass_blade),
+            dirt_patch: self.dirt_patch.unwrap_or(palette.dirt_patch),
+            water: self.water.unwrap_or(palette.water),
+            water_highlight: self.water_highlight.unwrap_or(palette.water_highlight),
+            water_deep: self.water_deep.unwrap_or(palette.water_deep),
+            coin: self.coin.unwrap_or(palette.coin),
+            ..palette
+        }
+    }
+
+    // Generated lanes pass through here, so layouts stay the seed's and only looks change
+    pub fn dress_lane(&self, lane: &mut Lane) {
+        if let (LaneType::Water, Some(color)) = (lane.lane_type, self.log) {
+            for log in lane.obstacles.iter_mut().filter(|o| o.kind == ObjectKind::Log) {
+                log.color = color;
+            }
+        }
+    }
+}
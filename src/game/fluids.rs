@@ -0,0 +1,90 @@
+use crate::game::block_updates::{BlockEdit, BlockMeta, BlockPos};
+use crate::game::minecraft::BlockType;
+use std::collections::HashMap;
+
+// Frames between a fluid cell noticing a change and reacting to it
+pub const WATER_TICK_FRAMES: u32 = 5;
+pub const LAVA_TICK_FRAMES: u32 = 30;
+// How many cells a source reaches sideways on flat ground
+const WATER_SPREAD: u8 = 7;
+const LAVA_SPREAD: u8 = 3;
+
+const SIDES: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+pub fn is_fluid(block: BlockType) -> bool {
+    matches!(block, BlockType::Water | BlockType::Lava)
+}
+
+fn spread(kind: BlockType) -> u8 {
+    if kind == BlockType::Lava { LAVA_SPREAD } else { WATER_SPREAD }
+}
+
+// Level 0 is a source, each step away lowers the surface. Returned as a
+// fraction of the cell height so flowing fluid visibly thins out.
+pub fn surface_height(level: u8) -> f32 {
+    (8 - level.min(7)) as f32 / 9.0
+}
+
+pub fn water_update(blocks: &HashMap<BlockPos, BlockType>, meta: &BlockMeta, pos: BlockPos) -> Vec<BlockEdit> {
+    flow(BlockType::Water, blocks, meta, pos)
+}
+
+// Lava touching water hardens: sources into obsidian, flowing lava into stone
+pub fn lava_update(blocks: &HashMap<BlockPos, BlockType>, meta: &BlockMeta, pos: BlockPos) -> Vec<BlockEdit> {
+    let (x, y, z) = pos;
+    let neighbours = [(x + 1, y, z), (x - 1, y, z), (x, y + 1, z), (x, y - 1, z), (x, y, z + 1), (x, y, z - 1)];
+    if neighbours.iter().any(|n| blocks.get(n) == Some(&BlockType::Water)) {
+        let hardened = if level(meta, pos) == 0 { BlockType::Obsidian } else { BlockType::Stone };
+        return vec![(pos, Some(hardened), 0)];
+    }
+    flow(BlockType::Lava, blocks, meta, pos)
+}
+
+fn level(meta: &BlockMeta, pos: BlockPos) -> u8 {
+    meta.get(&pos).copied().unwrap_or(0)
+}
+
+// The level a flowing cell should have given its neighbours, None once nothing feeds it
+fn fed_level(kind: BlockType, blocks: &HashMap<BlockPos, BlockType>, meta: &BlockMeta, (x, y, z): BlockPos) -> Option<u8> {
+    if blocks.get(&(x, y + 1, z)) == Some(&kind) {
+        return Some(1);
+    }
+    SIDES.iter()
+        .map(|(dx, dz)| (x + dx, y, z + dz))
+        .filter(|n| blocks.get(n) == Some(&kind))
+        .map(|n| level(meta, n) + 1)
+        .min()
+        .filter(|l| *l <= spread(kind))
+}
+
+fn flow(kind: BlockType, blocks: &HashMap<BlockPos, BlockType>, meta: &BlockMeta, pos: BlockPos) -> Vec<BlockEdit> {
+    let current = level(meta, pos);
+    // Sources never change, flowing cells follow whatever feeds them and dry up without it
+    if current > 0 {
+        match fed_level(kind, blocks, meta, pos) {
+            None => return vec![(pos, None, 0)],
+            Some(fed) if fed != current => return vec![(pos, Some(kind), fed)],
+            _ => {}
+        }
+    }
+
+    // Falling beats spreading
+    let (x, y, z) = pos;
+    let below = (x, y - 1, z);
+    if y > 0 && !blocks.contains_key(&below) {
+        return vec![(below, Some(kind), 1)];
+    }
+    if current >= spread(kind) {
+        return Vec::new();
+    }
+
+    let next = current + 1;
+    SIDES.iter()
+        .map(|(dx, dz)| (x + dx, y, z + dz))
+        .filter(|n| match blocks.get(n) {
+            None => true,
+            Some(block) => *block == kind && level(meta, *n) > next,
+        })
+        .map(|n| (n, Some(kind), next))
+        .collect()
+}
@@ -1,42 +1,226 @@
+use crate::engine::audio::{Audio, SoundLoop};
+use crate::engine::camera::{FpsController, InputEvent};
 use crate::engine::renderer::Renderer;
 use crate::engine::mesh::Mesh;
-use nalgebra::{Matrix4, Vector3, Point3};
+use crate::engine::events;
+use crate::engine::key_bindings::{self, BindingSet, KeyAction};
+use crate::engine::overlay;
+use crate::game::block_registry::{self, StepSound};
+use crate::game::block_shapes::{self, ShapePart};
+use crate::game::block_updates::{BlockMeta, BlockPos, BlockUpdates};
+use crate::game::coop::Coop;
+use crate::game::crafting::{self, Inventory};
+use crate::game::fluids;
+use crate::game::minecraft_graphics::{GraphicsProfile, ShadowMode};
+use crate::game::minecraft_physics::PhysicsConfig;
+use crate::game::mobs::Mobs;
+use crate::game::terrain::{self, ChunkQueue};
+use crate::game::world_map::WorldMap;
+use crate::game::world_save::{self, WorldMeta, WorldOptions};
+use nalgebra::{Matrix4, Orthographic3, Vector3, Point3};
 use std::collections::HashMap;
-use web_sys::WebGlTexture;
+use web_sys::{BiquadFilterType, OscillatorType, WebGlTexture};
 use wasm_bindgen::JsCast;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
-pub enum BlockType {
-    Grass,
-    Dirt,
-    Stone,
-    Wood,
-    Leaves,
-    Bedrock,
+const REACH: f32 = 5.0;
+const PLAYER_WIDTH: f32 = 0.6;
+const STAND_HEIGHT: f32 = 1.8;
+const STAND_EYE_HEIGHT: f32 = 1.62;
+// Horizontal distance walked per footstep sound
+const STEP_LENGTH: f32 = 1.6;
+const MAX_HEALTH: f32 = 20.0;
+// Falls shorter than this many blocks are free, each block past it costs one point
+const SAFE_FALL_BLOCKS: f32 = 3.0;
+const VOID_Y: f32 = -16.0;
+// Fade to black, respawn at the darkest point, fade back in
+const RESPAWN_FADE_FRAMES: u32 = 60;
+// Dropped items last 60 seconds at 60 fps
+const PICKUP_LIFETIME_FRAMES: u32 = 60 * 60;
+const PICKUP_RADIUS: f32 = 1.0;
+// Named worlds save themselves this often while played
+const AUTOSAVE_SECONDS: f64 = 60.0;
+// Pickups this close drift toward the player before being collected
+const PICKUP_MAGNET_RADIUS: f32 = 2.5;
+const PICKUP_MAGNET_PULL: f32 = 0.15;
+// Drops of one type this close share a stack, and the oldest go past the cap
+const PICKUP_MERGE_RADIUS: f32 = 0.5;
+const MAX_PICKUPS: usize = 64;
+// The crosshair also looks past reach so it can tell "too far" apart from "nothing"
+const CROSSHAIR_RANGE: f32 = 20.0;
+// Physics runs at a fixed 60 Hz, rendering interpolates between the last two steps
+const PHYSICS_DT: f64 = 1.0 / 60.0;
+// Longer gaps (tab in the background) are dropped instead of replayed
+const MAX_FRAME_SECONDS: f64 = 0.25;
+// Eye dip after a landing: a damped spring per physics step
+const LANDING_SPRING: f32 = 0.12;
+const LANDING_DAMPING: f32 = 0.35;
+const LANDING_KICK_PER_BLOCK: f32 = 0.025;
+const MAX_LANDING_KICK: f32 = 0.15;
+const LAVA_DAMAGE: f32 = 4.0;
+const LAVA_DAMAGE_INTERVAL_FRAMES: u32 = 30;
+// Radians turned per second with the right stick fully over
+const GAMEPAD_LOOK_SPEED: f32 = 3.0;
+
+// Index into the block registry. The built-in blocks keep their old names as constants;
+// saves and messages carry the registry name, so reordering ids never breaks a world.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct BlockType(pub u16);
+
+#[allow(non_upper_case_globals)]
+impl BlockType {
+    pub const Grass: BlockType = BlockType(0);
+    pub const Dirt: BlockType = BlockType(1);
+    pub const Stone: BlockType = BlockType(2);
+    pub const Wood: BlockType = BlockType(3);
+    pub const Leaves: BlockType = BlockType(4);
+    pub const Bedrock: BlockType = BlockType(5);
+    pub const Sand: BlockType = BlockType(6);
+    pub const Planks: BlockType = BlockType(7);
+    pub const Brick: BlockType = BlockType(8);
+    pub const Glass: BlockType = BlockType(9);
+    // Half-height planks, the top half variant is what a slab placed high in a cell becomes
+    pub const Slab: BlockType = BlockType(10);
+    pub const SlabTop: BlockType = BlockType(11);
+    pub const Fence: BlockType = BlockType(12);
+    // Fluids, their flow level lives in the world's meta map
+    pub const Water: BlockType = BlockType(13);
+    pub const Lava: BlockType = BlockType(14);
+    pub const Obsidian: BlockType = BlockType(15);
+
+    pub const BUILTIN: [BlockType; 16] = [
+        BlockType::Grass, BlockType::Dirt, BlockType::Stone, BlockType::Wood, BlockType::Leaves, BlockType::Bedrock,
+        BlockType::Sand, BlockType::Planks, BlockType::Brick, BlockType::Glass, BlockType::Slab, BlockType::SlabTop,
+        BlockType::Fence, BlockType::Water, BlockType::Lava, BlockType::Obsidian,
+    ];
 }
 
 impl BlockType {
+    pub fn name(&self) -> String {
+        block_registry::with_def(*self, |def| def.name.clone())
+    }
+
     pub fn color(&self) -> (f32, f32, f32) {
-        match self {
-            BlockType::Grass => (0.2, 0.8, 0.2),
-            BlockType::Dirt => (0.5, 0.3, 0.1),
-            BlockType::Stone => (0.5, 0.5, 0.5),
-            BlockType::Wood => (0.4, 0.2, 0.0),
-            BlockType::Leaves => (0.1, 0.6, 0.1),
-            BlockType::Bedrock => (0.1, 0.1, 0.1),
+        block_registry::with_def(*self, |def| def.color)
+    }
+
+    // 0-15, baked in as full light when meshing
+    pub fn light_emission(&self) -> u8 {
+        block_registry::with_def(*self, |def| def.emits_light)
+    }
+
+    // What breaking the block gives back
+    pub fn item(&self) -> BlockType {
+        block_registry::drops(*self)
+    }
+
+    pub fn is_breakable(&self) -> bool {
+        block_registry::with_def(*self, |def| def.hardness >= 0.0)
+    }
+
+    pub fn is_solid(&self) -> bool {
+        block_registry::with_def(*self, |def| def.solid)
+    }
+
+    pub fn is_transparent(&self) -> bool {
+        block_registry::with_def(*self, |def| def.transparent)
+    }
+}
+
+impl Serialize for BlockType {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for BlockType {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        block_registry::by_name(&name).ok_or_else(|| serde::de::Error::custom(format!("unknown block \"{}\"", name)))
+    }
+}
+
+pub struct RaycastHit {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+    pub face: usize,
+    pub distance: f32,
+    pub block: BlockType,
+    pub point: Vector3<f32>,
+}
+
+#[derive(Deserialize, Clone)]
+#[serde(default)]
+pub struct CrosshairStyle {
+    pub size: f32,
+    pub thickness: f32,
+    pub color_target: (f32, f32, f32),
+    pub color_empty: (f32, f32, f32),
+    pub color_blocked: (f32, f32, f32),
+}
+
+impl Default for CrosshairStyle {
+    fn default() -> Self {
+        CrosshairStyle {
+            size: 16.0,
+            thickness: 2.0,
+            color_target: (1.0, 1.0, 1.0),
+            color_empty: (0.55, 0.55, 0.55),
+            color_blocked: (0.9, 0.15, 0.15),
         }
     }
 }
 
+// Inventory stack dropped on death or by a broken block, walked over to collect
+#[derive(Serialize, Deserialize)]
+struct Pickup {
+    pos: Vector3<f32>,
+    block: BlockType,
+    count: u32,
+    frames_left: u32,
+}
+
+// What a named world slot stores. Terrain comes back from the seed, so only blocks changed
+// since generation are kept, listed rather than keyed by position since JSON maps only take
+// string keys.
+#[derive(Serialize, Deserialize)]
+struct WorldState {
+    edits: Vec<(BlockPos, Option<BlockType>)>,
+    meta: Vec<(BlockPos, u8)>,
+    player_pos: Vector3<f32>,
+    yaw: f32,
+    pitch: f32,
+    spawn_point: Vector3<f32>,
+    health: f32,
+    inventory: Inventory,
+    time_of_day: f32,
+    pickups: Vec<Pickup>,
+    mobs: Mobs,
+    map: WorldMap,
+}
+
+// What get_game_state reports for Minecraft
+#[derive(Serialize)]
+pub struct MinecraftSnapshot {
+    pub player_pos: [f32; 3],
+    pub selected_block: BlockType,
+    pub block_count: usize,
+}
+
 pub struct Minecraft {
     renderer: Renderer,
     blocks: HashMap<(i32, i32, i32), BlockType>,
-    player_pos: Vector3<f32>,
-    player_rot: (f32, f32), // yaw, pitch
+    meta: BlockMeta,
+    player_pos: Vector3<f32>, // feet, centered horizontally
+    player_height: f32,
+    eye_height: f32,
+    look: FpsController,
     cube_mesh: Mesh,
     top_mesh: Mesh,
     bottom_mesh: Mesh,
     side_mesh: Mesh,
+    shape_meshes: HashMap<ShapePart, Mesh>,
     is_locked: bool,
     velocity: Vector3<f32>,
     on_ground: bool,
@@ -44,114 +228,406 @@ pub struct Minecraft {
     input_state: InputState,
     
     // Textures
-    grass_top_texture: Option<WebGlTexture>,
-    grass_side_texture: Option<WebGlTexture>,
-    dirt_texture: Option<WebGlTexture>,
-    leaves_texture: Option<WebGlTexture>,
-    stone_texture: Option<WebGlTexture>,
-    wood_side_texture: Option<WebGlTexture>,
-    wood_top_texture: Option<WebGlTexture>,
-    bedrock_texture: Option<WebGlTexture>,
+    // Block tiles by name, loaded for every tile the registry uses
+    tiles: HashMap<String, WebGlTexture>,
     skybox_texture: Option<WebGlTexture>,
     sun_texture: Option<WebGlTexture>,
     moon_texture: Option<WebGlTexture>,
     time_of_day: f32,
+    chunks: ChunkQueue,
+    crosshair_style: CrosshairStyle,
+    debug_overlay: bool,
+    placement_flash: u32, // frames left of the red "can't place" crosshair
+    inventory: Inventory,
+    audio: Option<Audio>,
+    wind: Option<SoundLoop>,
+    step_distance: f32,
+    // Last upward sky scan as (eye cell, underground), redone when the cell or the column changes
+    sky_scan: Option<((i32, i32, i32), bool)>,
+    map: WorldMap,
+    block_updates: BlockUpdates,
+    health: f32,
+    fall_peak: Option<f32>, // highest feet height since leaving the ground
+    spawn_point: Vector3<f32>,
+    keep_inventory: bool,
+    respawn_frame: Option<u32>,
+    pickups: Vec<Pickup>,
+    frame: u32,
+    mobs: Mobs,
+    lava_cooldown: u32,
+    // Broken blocks go straight into the inventory instead of dropping
+    creative: bool,
+    last_frame_ms: Option<f64>,
+    accumulator: f64,
+    prev_player_pos: Vector3<f32>,
+    // How far render() is between the previous and current physics step, 0..1
+    render_alpha: f32,
+    landing_sway: bool,
+    landing_dip: f32,
+    landing_dip_velocity: f32,
+    physics: PhysicsConfig,
+    graphics: GraphicsProfile,
+    // The named slot being played, None for a throwaway world
+    world: Option<WorldMeta>,
+    world_border: Option<i32>,
+    autosave_timer: f64,
+    // Edit log and peer state for a host page syncing two players
+    coop: Coop,
 }
 
+#[derive(Default)]
 struct InputState {
     forward: bool,
     backward: bool,
     left: bool,
     right: bool,
+    sprint: bool,
 }
 
 impl Minecraft {
     pub fn new(renderer: Renderer) -> Self {
+        Self::with_seed(renderer, (js_sys::Math::random() * 1000000.0) as u32)
+    }
+
+    fn with_seed(renderer: Renderer, seed: u32) -> Self {
         let mut blocks = HashMap::new();
         let cube_mesh = Mesh::cube(1.0, 1.0, 1.0, 1.0);
         let top_mesh = Mesh::face_top(1.0);
         let bottom_mesh = Mesh::face_bottom(1.0);
         let side_mesh = Mesh::face_sides(1.0);
+        let shape_meshes = ShapePart::ALL.iter()
+            .map(|part| (*part, Mesh::cuboids(&part.render_boxes())))
+            .collect();
 
         // Load textures
-        let grass_top_texture = renderer.create_texture("assets/textures/TinyCraft/tiles/grass_top.png").ok();
-        let grass_side_texture = renderer.create_texture("assets/textures/TinyCraft/tiles/dirt_grass.png").ok();
-        let dirt_texture = renderer.create_texture("assets/textures/TinyCraft/tiles/dirt.png").ok();
-        let leaves_texture = renderer.create_texture("assets/textures/TinyCraft/tiles/leaves_transparent.png").ok();
-        let stone_texture = renderer.create_texture("assets/textures/TinyCraft/tiles/stone.png").ok();
-        let wood_side_texture = renderer.create_texture("assets/textures/TinyCraft/tiles/trunk_side.png").ok();
-        let wood_top_texture = renderer.create_texture("assets/textures/TinyCraft/tiles/trunk_top.png").ok();
-        let bedrock_texture = renderer.create_texture("assets/textures/TinyCraft/tiles/greystone.png").ok();
+        let tiles = load_tiles(&renderer, HashMap::new());
         
         // Converted from EXR to JPG for browser compatibility
         let skybox_texture = renderer.create_texture("assets/textures/cloudy_bright_day.jpg").ok();
         let sun_texture = renderer.create_texture("assets/textures/2k_sun.jpg").ok();
         let moon_texture = renderer.create_texture("assets/textures/2k_moon.jpg").ok();
 
-        // Generate simple terrain
-        for x in -10..10 {
-            for z in -10..10 {
-                blocks.insert((x, 0, z), BlockType::Bedrock);
-                blocks.insert((x, 1, z), BlockType::Dirt);
-                blocks.insert((x, 2, z), BlockType::Grass);
-            }
-        }
-
-        // Some trees
-        let trees = [(2, 2), (-5, -5), (7, -3)];
-        for (tx, tz) in trees {
-            for y in 3..6 {
-                blocks.insert((tx, y, tz), BlockType::Wood);
-            }
-            for x in -1..=1 {
-                for z in -1..=1 {
-                    for y in 5..7 {
-                        if x == 0 && z == 0 && y < 6 { continue; }
-                        blocks.insert((tx + x, y, tz + z), BlockType::Leaves);
-                    }
-                }
-            }
-        }
+        // Generate the spawn area up front, the rest streams in from update()
+        let time_of_day = 0.3;
+        let spawn = Vector3::new(0.0, 0.0, 0.0);
+        let graphics = GraphicsProfile::load();
+        let mut chunks = ChunkQueue::new(seed);
+        chunks.apply_graphics(&graphics);
+        chunks.request_around(spawn, sun_angle(time_of_day));
+        let meta = BlockMeta::new();
+        chunks.run_all(&mut blocks, &meta, spawn, sun_angle(time_of_day));
+        let spawn_height = chunks.surface_height(0, 0) as f32 + 1.0;
 
-        Minecraft {
+        let mut game = Minecraft {
             renderer,
             blocks,
-            player_pos: Vector3::new(0.0, 5.0, 0.0),
-            player_rot: (0.0, 0.0),
+            meta,
+            player_pos: Vector3::new(0.0, spawn_height, 0.0),
+            player_height: STAND_HEIGHT,
+            eye_height: STAND_EYE_HEIGHT,
+            look: FpsController::new(0.0, 0.0),
             cube_mesh,
             top_mesh,
             bottom_mesh,
             side_mesh,
+            shape_meshes,
             is_locked: false,
             velocity: Vector3::new(0.0, 0.0, 0.0),
             on_ground: false,
             selected_block_type: BlockType::Grass,
-            input_state: InputState {
-                forward: false,
-                backward: false,
-                left: false,
-                right: false,
-            },
-            grass_top_texture,
-            grass_side_texture,
-            dirt_texture,
-            leaves_texture,
-            stone_texture,
-            wood_side_texture,
-            wood_top_texture,
-            bedrock_texture,
+            input_state: InputState::default(),
+            tiles,
             skybox_texture,
             sun_texture,
             moon_texture,
-            time_of_day: 0.3,
+            time_of_day,
+            chunks,
+            crosshair_style: CrosshairStyle::default(),
+            physics: PhysicsConfig::load(),
+            graphics,
+            debug_overlay: false,
+            placement_flash: 0,
+            inventory: starting_inventory(),
+            audio: Audio::new(),
+            wind: None,
+            step_distance: 0.0,
+            sky_scan: None,
+            map: WorldMap::default(),
+            block_updates: BlockUpdates::default(),
+            health: MAX_HEALTH,
+            fall_peak: None,
+            spawn_point: Vector3::new(0.0, spawn_height, 0.0),
+            keep_inventory: true,
+            respawn_frame: None,
+            pickups: Vec::new(),
+            frame: 0,
+            mobs: Mobs::default(),
+            lava_cooldown: 0,
+            creative: false,
+            last_frame_ms: None,
+            accumulator: 0.0,
+            prev_player_pos: Vector3::new(0.0, spawn_height, 0.0),
+            render_alpha: 1.0,
+            landing_sway: true,
+            landing_dip: 0.0,
+            landing_dip_velocity: 0.0,
+            world: None,
+            world_border: None,
+            autosave_timer: 0.0,
+            coop: Coop::new(),
+        };
+        game.wind = game.audio.as_ref().and_then(|audio| audio.noise_loop(400.0));
+        game.apply_texture_filter();
+        game.apply_shadow_map();
+        show_click_to_play(true);
+        game
+    }
+
+    pub fn create_world(renderer: Renderer, name: &str, seed: u32, options_json: &str) -> Result<Self, String> {
+        world_save::validate_name(name)?;
+        if world_save::exists(name) {
+            return Err(format!("A world named \"{}\" already exists", name));
+        }
+        let options = WorldOptions::parse(options_json)?;
+        let mut game = Self::with_seed(renderer, seed);
+        game.apply_world_options(&options);
+        game.world = Some(WorldMeta::new(name, seed, options));
+        game.save_world()?;
+        Ok(game)
+    }
+
+    pub fn load_world(renderer: Renderer, name: &str) -> Result<Self, String> {
+        let (meta, bytes) = world_save::read(name)?;
+        let state: WorldState = serde_json::from_slice(&bytes)
+            .map_err(|e| format!("World \"{}\" is damaged: {}", name, e))?;
+        let mut game = Self::with_seed(renderer, meta.seed);
+        game.restore(state);
+        game.apply_world_options(&meta.options);
+        game.world = Some(meta);
+        Ok(game)
+    }
+
+    fn restore(&mut self, state: WorldState) {
+        self.blocks.clear();
+        self.meta = state.meta.into_iter().collect();
+        self.player_pos = state.player_pos;
+        self.prev_player_pos = state.player_pos;
+        self.look = FpsController::new(state.yaw, state.pitch);
+        self.spawn_point = state.spawn_point;
+        self.health = state.health;
+        self.inventory = state.inventory;
+        self.time_of_day = state.time_of_day;
+        self.pickups = state.pickups;
+        self.mobs = state.mobs;
+        self.map = state.map;
+        // Regenerate around the player before the first frame, like a new spawn
+        let sun = sun_angle(self.time_of_day);
+        self.chunks.restore(state.edits);
+        self.chunks.request_around(self.player_pos, sun);
+        self.chunks.run_all(&mut self.blocks, &self.meta, self.player_pos, sun);
+    }
+
+    fn apply_world_options(&mut self, options: &WorldOptions) {
+        self.creative = options.creative;
+        self.keep_inventory = options.keep_inventory;
+        self.block_updates.fluids_enabled = options.fluids_enabled;
+        self.world_border = options.world_border;
+    }
+
+    pub fn save_world(&mut self) -> Result<(), String> {
+        let meta = self.world.as_mut().ok_or("No named world is loaded")?;
+        meta.last_played = js_sys::Date::now();
+        let (yaw, pitch) = self.look.rotation();
+        let state = WorldState {
+            edits: self.chunks.edits(),
+            meta: self.meta.iter().map(|(pos, meta)| (*pos, *meta)).collect(),
+            player_pos: self.player_pos,
+            yaw,
+            pitch,
+            spawn_point: self.spawn_point,
+            health: self.health,
+            inventory: self.inventory.clone(),
+            time_of_day: self.time_of_day,
+            pickups: std::mem::take(&mut self.pickups),
+            mobs: std::mem::take(&mut self.mobs),
+            map: std::mem::take(&mut self.map),
+        };
+        let bytes = serde_json::to_vec(&state).map_err(|e| e.to_string());
+        self.pickups = state.pickups;
+        self.mobs = state.mobs;
+        self.map = state.map;
+        self.autosave_timer = 0.0;
+        world_save::write(meta, &bytes?)
+    }
+
+    // Stops a deleted world being written back by the next autosave
+    pub fn forget_world(&mut self, name: &str) {
+        if self.world.as_ref().is_some_and(|meta| meta.name == name) {
+            self.world = None;
+        }
+    }
+
+    // Autosave has no caller to hand an error to, so a refused write goes to the error callback
+    fn update_autosave(&mut self, elapsed: f64) {
+        let meta = match self.world.as_mut() {
+            Some(meta) => meta,
+            None => return,
+        };
+        meta.play_seconds += elapsed;
+        self.autosave_timer += elapsed;
+        if self.autosave_timer >= AUTOSAVE_SECONDS {
+            if let Err(message) = self.save_world() {
+                events::report_error(&message);
+            }
+        }
+    }
+
+    // Keeps the feet inside the border square, killing the velocity that pushed outward
+    fn clamp_to_border(&mut self) {
+        let limit = match self.world_border {
+            Some(border) => border as f32,
+            None => return,
+        };
+        if self.player_pos.x.abs() > limit {
+            self.player_pos.x = self.player_pos.x.clamp(-limit, limit);
+            self.velocity.x = 0.0;
+        }
+        if self.player_pos.z.abs() > limit {
+            self.player_pos.z = self.player_pos.z.clamp(-limit, limit);
+            self.velocity.z = 0.0;
+        }
+    }
+
+    fn is_underground(&mut self) -> bool {
+        let eye = self.eye_pos();
+        let cell = (eye.x.round() as i32, eye.y.round() as i32, eye.z.round() as i32);
+        if let Some((cached_cell, underground)) = self.sky_scan {
+            if cached_cell == cell {
+                return underground;
+            }
+        }
+        let underground = (cell.1 + 1..=terrain::CHUNK_HEIGHT).any(|y| {
+            matches!(self.blocks.get(&(cell.0, y, cell.2)), Some(block) if !block.is_transparent())
+        });
+        self.sky_scan = Some((cell, underground));
+        underground
+    }
+
+    // Edits above the cached cell can open or close the sky
+    fn invalidate_sky_scan(&mut self, x: i32, y: i32, z: i32) {
+        if let Some(((cx, cy, cz), _)) = self.sky_scan {
+            if cx == x && cz == z && y > cy {
+                self.sky_scan = None;
+            }
+        }
+    }
+
+    fn update_audio(&mut self, walked: f32) {
+        if self.audio.is_none() {
+            return;
+        }
+
+        let mut step_on = None;
+        if self.on_ground && walked > 0.0 {
+            self.step_distance += walked;
+            if self.step_distance >= STEP_LENGTH {
+                self.step_distance -= STEP_LENGTH;
+                let below = (self.player_pos.x.round() as i32, (self.player_pos.y - 0.5).round() as i32, self.player_pos.z.round() as i32);
+                step_on = self.blocks.get(&below).copied();
+            }
         }
+
+        let underground = self.is_underground();
+        let altitude = self.eye_pos().y;
+        let audio = match &self.audio {
+            Some(audio) => audio,
+            None => return,
+        };
+        if let Some(block) = step_on {
+            play_footstep(audio, block);
+        }
+        audio.set_muffled(underground);
+        if let Some(wind) = &self.wind {
+            let volume = if underground { 0.0 } else { ((altitude - 12.0) / 30.0).clamp(0.0, 1.0) * 0.35 };
+            audio.set_loop_volume(wind, volume);
+        }
+    }
+
+    pub fn snapshot(&self) -> MinecraftSnapshot {
+        MinecraftSnapshot {
+            player_pos: [self.player_pos.x, self.player_pos.y, self.player_pos.z],
+            selected_block: self.selected_block_type,
+            block_count: self.blocks.len(),
+        }
+    }
+
+    // After a pause the next update takes one step instead of replaying the gap
+    pub fn resume(&mut self) {
+        self.last_frame_ms = None;
     }
 
+    pub fn restore_context(&mut self) -> Result<(), wasm_bindgen::JsValue> {
+        self.renderer.reinit()?;
+        let renderer = &self.renderer;
+        for texture in self.tiles.values_mut() {
+            renderer.restore_texture(texture);
+        }
+        for texture in [&mut self.skybox_texture, &mut self.sun_texture, &mut self.moon_texture].into_iter().flatten() {
+            renderer.restore_texture(texture);
+        }
+        self.map.forget_texture();
+        self.apply_texture_filter();
+        self.apply_shadow_map();
+        Ok(())
+    }
+
+    // Runs as many fixed physics steps as the elapsed time covers. Mouse look isn't part of
+    // the step, render() reads the latest rotation directly.
     pub fn update(&mut self) {
-        let speed = 0.02;
-        let max_speed = 0.15;
-        
-        let (yaw, _) = self.player_rot;
+        let now = web_sys::window().and_then(|w| w.performance()).map(|p| p.now()).unwrap_or(0.0);
+        let elapsed = match self.last_frame_ms.replace(now) {
+            Some(last) => ((now - last) / 1000.0).clamp(0.0, MAX_FRAME_SECONDS),
+            None => PHYSICS_DT,
+        };
+        self.update_autosave(elapsed);
+        let (steps, accumulator) = physics_steps(self.accumulator, elapsed);
+        self.accumulator = accumulator;
+        for _ in 0..steps {
+            self.prev_player_pos = self.player_pos;
+            self.tick();
+        }
+        self.render_alpha = (self.accumulator / PHYSICS_DT) as f32;
+
+        // World upkeep runs once a frame however many steps caught up, so a stall doesn't
+        // multiply the chunk and block-update budgets
+        self.update_time_ui();
+        self.process_block_updates();
+        self.update_underwater_tint();
+
+        let (yaw, _) = self.look.rotation();
+        let front = Vector3::new(yaw.cos(), 0.0, yaw.sin());
+        let sun = sun_angle(self.time_of_day);
+        let now = || web_sys::window().and_then(|w| w.performance()).map(|p| p.now()).unwrap_or(0.0);
+        self.chunks.advance_fades(elapsed as f32);
+        self.chunks.request_around(self.player_pos, sun);
+        self.chunks.process(&mut self.blocks, &self.meta, self.player_pos, front, sun, &now);
+        self.map.update(&self.renderer, &self.blocks, &self.chunks, self.player_pos);
+    }
+
+    fn tick(&mut self) {
+        self.placement_flash = self.placement_flash.saturating_sub(1);
+        self.frame = self.frame.wrapping_add(1);
+        self.update_respawn();
+        let dying = self.respawn_frame.is_some();
+
+        let physics = &self.physics;
+        let sprinting = self.input_state.sprint && self.input_state.forward;
+        let sprint = if sprinting { physics.sprint_multiplier } else { 1.0 };
+        let control = if self.on_ground { 1.0 } else { physics.air_control };
+        let speed = physics.acceleration * sprint * control;
+        let max_speed = physics.max_speed * sprint;
+
+        let (yaw, _) = self.look.rotation();
         let forward = Vector3::new(yaw.cos(), 0.0, yaw.sin()).normalize();
         let right = Vector3::new(-yaw.sin(), 0.0, yaw.cos()).normalize();
         
@@ -161,35 +637,268 @@ impl Minecraft {
         if self.input_state.right { move_dir += right; }
         if self.input_state.left { move_dir -= right; }
 
-        if move_dir.norm() > 0.0 {
+        // Input can't push past the speed cap, but momentum already above it (a sprint
+        // jump's boost, knockback) is left for friction to bleed off
+        let speed_before = Vector3::new(self.velocity.x, 0.0, self.velocity.z).norm();
+        if move_dir.norm() > 0.0 && !dying {
             move_dir = move_dir.normalize();
             self.velocity.x += move_dir.x * speed;
             self.velocity.z += move_dir.z * speed;
         }
 
+        let cap = max_speed.max(speed_before);
         let h_vel = Vector3::new(self.velocity.x, 0.0, self.velocity.z);
-        if h_vel.norm() > max_speed {
-            let clamped = h_vel.normalize() * max_speed;
+        if h_vel.norm() > cap {
+            let clamped = h_vel.normalize() * cap;
             self.velocity.x = clamped.x;
             self.velocity.z = clamped.z;
         }
 
-        self.velocity.y -= 0.02;
+        self.velocity.y = physics.fall_step(self.velocity.y);
+        let friction = physics.friction;
 
+        let start = self.player_pos;
         self.player_pos.x += self.velocity.x;
         self.resolve_collisions(0); 
         
         self.player_pos.z += self.velocity.z;
         self.resolve_collisions(2); 
+        self.clamp_to_border();
 
         self.player_pos.y += self.velocity.y;
         self.on_ground = false;
         self.resolve_collisions(1); 
 
-        self.velocity.x *= 0.8;
-        self.velocity.z *= 0.8;
+        self.velocity.x *= friction;
+        self.velocity.z *= friction;
 
-        self.update_time_ui();
+        let walked = Vector3::new(self.player_pos.x - start.x, 0.0, self.player_pos.z - start.z).norm();
+        self.update_audio(walked);
+        self.check_fall();
+        self.update_landing_dip();
+        // A corpse would vacuum its own drops back up, they wait for the respawn
+        if self.respawn_frame.is_none() {
+            self.update_pickups();
+        }
+        self.update_mobs();
+        self.check_lava();
+    }
+
+    pub fn renderer(&self) -> &Renderer {
+        &self.renderer
+    }
+
+    fn update_underwater_tint(&self) {
+        let eye = self.eye_pos();
+        let cell = (eye.x.round() as i32, eye.y.round() as i32, eye.z.round() as i32);
+        let submerged = self.blocks.get(&cell) == Some(&BlockType::Water);
+        overlay::set_persistent_tint(overlay::UNDERWATER_COLOR, if submerged { 0.35 } else { 0.0 });
+    }
+
+    fn update_mobs(&mut self) {
+        let sun_elevation = sun_angle(self.time_of_day).sin();
+        let (player_min, player_max) = self.player_bounds();
+        let hits = self.mobs.update(&self.blocks, &self.chunks, player_min, player_max, sun_elevation);
+        if let Some(cap) = self.graphics.particle_cap {
+            self.mobs.cap_particles(cap);
+        }
+        for hit in hits {
+            if self.respawn_frame.is_some() {
+                break;
+            }
+            self.velocity += hit.knockback;
+            self.set_health(self.health - hit.damage);
+            if self.health <= 0.0 {
+                self.die();
+            }
+        }
+    }
+
+    // Standing in lava burns on a cooldown, like zombie hits
+    fn check_lava(&mut self) {
+        self.lava_cooldown = self.lava_cooldown.saturating_sub(1);
+        if self.respawn_frame.is_some() || self.lava_cooldown > 0 {
+            return;
+        }
+        let (min, max) = self.player_bounds();
+        let touching = (min.y.round() as i32..=max.y.round() as i32).any(|y| {
+            (min.x.round() as i32..=max.x.round() as i32).any(|x| {
+                (min.z.round() as i32..=max.z.round() as i32).any(|z| self.blocks.get(&(x, y, z)) == Some(&BlockType::Lava))
+            })
+        });
+        if touching {
+            self.lava_cooldown = LAVA_DAMAGE_INTERVAL_FRAMES;
+            self.set_health(self.health - LAVA_DAMAGE);
+            if self.health <= 0.0 {
+                self.die();
+            }
+        }
+    }
+
+    pub fn set_fluids_enabled(&mut self, enabled: bool) {
+        self.block_updates.fluids_enabled = enabled;
+    }
+
+    pub fn toggle_map(&mut self) {
+        self.map.toggle();
+    }
+
+    fn check_fall(&mut self) {
+        if self.respawn_frame.is_some() {
+            return;
+        }
+        if self.player_pos.y < VOID_Y {
+            self.die();
+            return;
+        }
+        if !self.on_ground {
+            let peak = self.fall_peak.get_or_insert(self.player_pos.y);
+            *peak = peak.max(self.player_pos.y);
+            return;
+        }
+        if let Some(peak) = self.fall_peak.take() {
+            if self.landing_sway {
+                let drop = peak - self.player_pos.y;
+                self.landing_dip_velocity -= (drop * LANDING_KICK_PER_BLOCK).min(MAX_LANDING_KICK);
+            }
+            let damage = (peak - self.player_pos.y - SAFE_FALL_BLOCKS).floor();
+            if damage > 0.0 {
+                self.set_health(self.health - damage);
+                if self.health <= 0.0 {
+                    self.die();
+                }
+            }
+        }
+    }
+
+    fn update_landing_dip(&mut self) {
+        self.landing_dip_velocity -= LANDING_SPRING * self.landing_dip + LANDING_DAMPING * self.landing_dip_velocity;
+        self.landing_dip += self.landing_dip_velocity;
+    }
+
+    pub fn set_view_smoothing(&mut self, enabled: bool) {
+        self.landing_sway = enabled;
+        if !enabled {
+            self.landing_dip = 0.0;
+            self.landing_dip_velocity = 0.0;
+        }
+    }
+
+    fn set_health(&mut self, health: f32) {
+        let health = health.clamp(0.0, MAX_HEALTH);
+        if health < self.health {
+            // Stronger the bigger the hit, a full heart bar's worth is a solid red
+            overlay::set_flash(overlay::DAMAGE_COLOR, 0.25 + 0.5 * (self.health - health) / MAX_HEALTH, 0.5);
+        }
+        self.health = health;
+        events::emit("health_changed", serde_json::json!({ "health": self.health, "max": MAX_HEALTH }));
+    }
+
+    fn die(&mut self) {
+        self.respawn_frame = Some(0);
+        events::emit("player_died", serde_json::json!({ "keep_inventory": self.keep_inventory }));
+        if self.keep_inventory {
+            return;
+        }
+
+        // Scatter each stack around the death spot, clamped above the void
+        let mut death_pos = self.player_pos;
+        death_pos.y = death_pos.y.max(1.0);
+        let stacks: Vec<(BlockType, u32)> = self.inventory.drain().collect();
+        for (block, count) in stacks {
+            let angle = js_sys::Math::random() as f32 * std::f32::consts::TAU;
+            let offset = Vector3::new(angle.cos(), 0.5, angle.sin()) * 0.8;
+            self.drop_pickup(death_pos + offset, block, count);
+        }
+        self.emit_inventory();
+    }
+
+    fn update_respawn(&mut self) {
+        let frame = match self.respawn_frame.as_mut() {
+            Some(frame) => frame,
+            None => return,
+        };
+        *frame += 1;
+        let frame = *frame;
+        if frame == RESPAWN_FADE_FRAMES / 2 {
+            self.player_pos = self.spawn_point;
+            self.prev_player_pos = self.spawn_point;
+            self.velocity = Vector3::new(0.0, 0.0, 0.0);
+            self.fall_peak = None;
+            self.set_health(MAX_HEALTH);
+        }
+        if frame >= RESPAWN_FADE_FRAMES {
+            self.respawn_frame = None;
+        }
+    }
+
+    // 0 clear .. 1 black, peaking when the teleport happens
+    fn respawn_fade(&self) -> f32 {
+        match self.respawn_frame {
+            Some(frame) => {
+                let half = RESPAWN_FADE_FRAMES as f32 / 2.0;
+                1.0 - (frame as f32 / half - 1.0).abs()
+            }
+            None => 0.0,
+        }
+    }
+
+    // Joins a nearby stack of the same block when there is one
+    fn drop_pickup(&mut self, pos: Vector3<f32>, block: BlockType, count: u32) {
+        let nearby = self.pickups.iter_mut()
+            .find(|pickup| pickup.block == block && (pickup.pos - pos).norm() <= PICKUP_MERGE_RADIUS);
+        if let Some(pickup) = nearby {
+            pickup.count += count;
+            pickup.frames_left = PICKUP_LIFETIME_FRAMES;
+            return;
+        }
+        if self.pickups.len() >= MAX_PICKUPS {
+            if let Some(oldest) = (0..self.pickups.len()).min_by_key(|i| self.pickups[*i].frames_left) {
+                self.pickups.swap_remove(oldest);
+            }
+        }
+        self.pickups.push(Pickup { pos, block, count, frames_left: PICKUP_LIFETIME_FRAMES });
+    }
+
+    fn update_pickups(&mut self) {
+        let center = self.player_pos + Vector3::new(0.0, self.player_height / 2.0, 0.0);
+        let mut collected = false;
+        let inventory = &mut self.inventory;
+        self.pickups.retain_mut(|pickup| {
+            let distance = (pickup.pos - center).norm();
+            if distance <= PICKUP_MAGNET_RADIUS {
+                pickup.pos += (center - pickup.pos) * PICKUP_MAGNET_PULL;
+            }
+            if (pickup.pos - center).norm() <= PICKUP_RADIUS {
+                *inventory.entry(pickup.block).or_insert(0) += pickup.count;
+                collected = true;
+                return false;
+            }
+            pickup.frames_left = pickup.frames_left.saturating_sub(1);
+            pickup.frames_left > 0
+        });
+        if collected {
+            self.emit_inventory();
+        }
+    }
+
+    pub fn set_spawn_here(&mut self) {
+        if self.on_ground {
+            self.spawn_point = self.player_pos;
+            events::emit("spawn_set", serde_json::json!([self.spawn_point.x, self.spawn_point.y, self.spawn_point.z]));
+        }
+    }
+
+    pub fn set_keep_inventory(&mut self, keep: bool) {
+        self.keep_inventory = keep;
+    }
+
+    pub fn set_creative(&mut self, creative: bool) {
+        self.creative = creative;
+    }
+
+    pub fn set_chunk_budget(&mut self, budget_ms: f64) {
+        self.chunks.budget_ms = budget_ms.max(0.1);
     }
 
     fn update_time_ui(&mut self) {
@@ -224,140 +933,73 @@ impl Minecraft {
         }
     }
 
-    fn resolve_collisions(&mut self, axis: usize) {
-        let px = self.player_pos.x.round() as i32;
-        let py = self.player_pos.y.round() as i32;
-        let pz = self.player_pos.z.round() as i32;
-
-        for y in (py - 2)..=(py + 2) {
-            for x in (px - 1)..=(px + 1) {
-                for z in (pz - 1)..=(pz + 1) {
-                    if self.blocks.contains_key(&(x, y, z)) {
-                        let block_min = Vector3::new(x as f32 - 0.5, y as f32 - 0.5, z as f32 - 0.5);
-                        let block_max = Vector3::new(x as f32 + 0.5, y as f32 + 0.5, z as f32 + 0.5);
-
-                        let player_width = 0.6;
-                        let _player_height = 1.8;
-                        let player_min = Vector3::new(
-                            self.player_pos.x - player_width / 2.0,
-                            self.player_pos.y - 1.5,
-                            self.player_pos.z - player_width / 2.0
-                        );
-                        let player_max = Vector3::new(
-                            self.player_pos.x + player_width / 2.0,
-                            self.player_pos.y + 0.3,
-                            self.player_pos.z + player_width / 2.0
-                        );
-
-                        if self.aabb_intersect(player_min, player_max, block_min, block_max) {
-                            match axis {
-                                0 => { 
-                                    if self.velocity.x > 0.0 {
-                                        self.player_pos.x = block_min.x - player_width / 2.0 - 0.001;
-                                    } else if self.velocity.x < 0.0 {
-                                        self.player_pos.x = block_max.x + player_width / 2.0 + 0.001;
-                                    }
-                                    self.velocity.x = 0.0;
-                                },
-                                1 => { 
-                                    if self.velocity.y > 0.0 {
-                                        self.player_pos.y = block_min.y - 0.3 - 0.001;
-                                        self.velocity.y = 0.0;
-                                    } else if self.velocity.y < 0.0 {
-                                        self.player_pos.y = block_max.y + 1.5; 
-                                        self.velocity.y = 0.0;
-                                        self.on_ground = true;
-                                    }
-                                },
-                                2 => { 
-                                    if self.velocity.z > 0.0 {
-                                        self.player_pos.z = block_min.z - player_width / 2.0 - 0.001;
-                                    } else if self.velocity.z < 0.0 {
-                                        self.player_pos.z = block_max.z + player_width / 2.0 + 0.001;
-                                    }
-                                    self.velocity.z = 0.0;
-                                },
-                                _ => {}
-                            }
-                        }
-                    }
-                }
-            }
-        }
+    fn eye_pos(&self) -> Vector3<f32> {
+        self.player_pos + Vector3::new(0.0, self.eye_height, 0.0)
     }
 
-    fn aabb_intersect(&self, min1: Vector3<f32>, max1: Vector3<f32>, min2: Vector3<f32>, max2: Vector3<f32>) -> bool {
-        min1.x < max2.x && max1.x > min2.x &&
-        min1.y < max2.y && max1.y > min2.y &&
-        min1.z < max2.z && max1.z > min2.z
+    // Where the camera sits this frame: between physics steps, plus the landing dip
+    fn render_eye_pos(&self) -> Vector3<f32> {
+        interpolate_position(self.prev_player_pos, self.player_pos, self.render_alpha)
+            + Vector3::new(0.0, self.eye_height + self.landing_dip, 0.0)
     }
 
-    fn calculate_shadow(&self, x: i32, y: i32, z: i32, light_dir: Vector3<f32>) -> f32 {
-        // Start slightly above the top face center to avoid self-shadowing from the block itself
-        // and to avoid shadowing from neighbor ground blocks when sun is low.
-        let origin = Vector3::new(x as f32, y as f32 + 0.6, z as f32);
-        let mut ray_pos = origin;
-        
-        let max_steps = 100;
-        let step_size = 0.2;
-        
-        for _ in 0..max_steps {
-            // Step first
-            ray_pos += light_dir * step_size;
-            
-            let check_x = ray_pos.x.round() as i32;
-            let check_y = ray_pos.y.round() as i32;
-            let check_z = ray_pos.z.round() as i32;
-            
-            // Ignore blocks in the same vertical column to prevent ugly self-shadowing on trees/walls
-            if check_x == x && check_z == z {
-                continue;
-            }
+    fn player_bounds(&self) -> (Vector3<f32>, Vector3<f32>) {
+        let half = PLAYER_WIDTH / 2.0;
+        (
+            Vector3::new(self.player_pos.x - half, self.player_pos.y, self.player_pos.z - half),
+            Vector3::new(self.player_pos.x + half, self.player_pos.y + self.player_height, self.player_pos.z + half),
+        )
+    }
 
-            if let Some(block) = self.blocks.get(&(check_x, check_y, check_z)) {
-                if matches!(block, BlockType::Leaves) {
-                    return 0.6; 
-                } else {
-                    return 0.3; 
-                }
-            }
-            
-            if ray_pos.y > 20.0 { break; } 
+    fn resolve_collisions(&mut self, axis: usize) {
+        let falling = self.velocity.y < 0.0;
+        let hit = resolve_collisions(&self.blocks, &mut self.player_pos, &mut self.velocity, PLAYER_WIDTH, self.player_height, axis);
+        if hit && axis == 1 && falling {
+            self.on_ground = true;
         }
-        
-        1.0 
     }
 
-    pub fn render(&mut self, width: i32, height: i32) {
+    // Everything a placed block must not overlap
+    fn entity_bounds(&self) -> Vec<(Vector3<f32>, Vector3<f32>)> {
+        let mut bounds = vec![self.player_bounds()];
+        bounds.extend(self.mobs.zombies.iter().map(|zombie| zombie.bounds()));
+        bounds
+    }
+
+    fn can_place_at(&self, x: i32, y: i32, z: i32, block: BlockType) -> bool {
+        can_place(&self.blocks, (x, y, z), block, self.eye_pos(), &self.entity_bounds())
+    }
+
+    // Drawn at the canvas's backing size, the CSS size is for DOM placed over it
+    pub fn render(&mut self, css_width: i32, css_height: i32) {
+        let (width, height) = self.renderer.drawing_buffer_size();
         self.renderer.resize(width, height);
-        self.renderer.clear_screen(0.5, 0.7, 1.0); // Sky blue
         self.renderer.enable_depth_test();
         self.renderer.enable_face_culling();
+        self.render_shadow_map();
+        self.renderer.clear_screen(0.5, 0.7, 1.0); // Sky blue
 
         let aspect = width as f32 / height as f32;
         let projection = Matrix4::new_perspective(aspect, 45.0f32.to_radians(), 0.1, 100.0);
         
-        // Camera view matrix
-        let (yaw, pitch) = self.player_rot;
-        let front = Vector3::new(
-            yaw.cos() * pitch.cos(),
-            pitch.sin(),
-            yaw.sin() * pitch.cos()
-        ).normalize();
-        
-        let target = self.player_pos + front;
+        // Camera view matrix. Only the position is interpolated; the look direction is always
+        // the latest one so the mouse never lags behind
+        let eye = self.render_eye_pos();
+        let target = eye + look_front(&self.look);
         let view = Matrix4::look_at_rh(
-            &Point3::from(self.player_pos),
+            &Point3::from(eye),
             &Point3::from(target),
             &Vector3::y(),
         );
 
         // Draw Skybox
-        self.renderer.draw_skybox(&self.cube_mesh, &projection, &view, self.skybox_texture.as_ref());
-        self.renderer.gl.depth_mask(true); // Re-enable depth writing
+        if self.graphics.sky {
+            self.renderer.draw_skybox(&self.cube_mesh, &projection, &view, self.skybox_texture.as_ref());
+            self.renderer.gl.depth_mask(true); // Re-enable depth writing
+        }
 
         // Calculate Sun Position again for shadows
-        let sun_angle = (self.time_of_day - 0.25) * std::f32::consts::PI * 2.0;
+        let sun_angle = sun_angle(self.time_of_day);
         let sun_dist = 100.0;
         
         // Sun position for rendering (relative to player so it's always visible)
@@ -365,7 +1007,7 @@ impl Minecraft {
         let moon_pos = self.player_pos + Vector3::new(-sun_angle.cos() * sun_dist, -sun_angle.sin() * sun_dist, 0.0);
         
         // Light direction for shadows (Global, independent of player)
-        let light_dir = Vector3::new(sun_angle.cos(), sun_angle.sin(), 0.0).normalize();
+        let light_dir = terrain::sun_direction(sun_angle);
         
         // Light position for shader (Far away to simulate directional light)
         let light_pos_uniform = light_dir * 10000.0; 
@@ -380,101 +1022,278 @@ impl Minecraft {
             self.renderer.draw_textured_cube(moon_pos.x, moon_pos.y, moon_pos.z, 6.0, 6.0, 6.0, self.moon_texture.as_ref(), &projection, &view);
         }
 
-        // Collect instance data grouped by block type
-        let mut instance_data_map: HashMap<BlockType, Vec<f32>> = HashMap::new();
-        let mut count_map: HashMap<BlockType, i32> = HashMap::new();
-
-        for ((x, y, z), block_type) in &self.blocks {
-            let (r, g, b) = (1.0, 1.0, 1.0); // Use white for all blocks as they are all textured now
-            
-            // Shadow logic: Raycast to sun
-            let light_level = self.calculate_shadow(*x, *y, *z, light_dir);
-
-            let data = instance_data_map.entry(*block_type).or_insert(Vec::new());
-            data.extend_from_slice(&[
-                *x as f32, *y as f32, *z as f32, // Position
-                1.0, // Scale
-                r, g, b, // Color
-                light_level // Light level
-            ]);
-            *count_map.entry(*block_type).or_insert(0) += 1;
-        }
-
-        // Draw each group
-        for (block_type, data) in instance_data_map {
-            let count = count_map[&block_type];
-            
-            match block_type {
-                BlockType::Grass => {
-                    // Top
-                    self.renderer.draw_instanced_mesh(
-                        &self.top_mesh, &data, count, &projection, &view, &light_pos_uniform, self.grass_top_texture.as_ref()
-                    );
-                    // Bottom
-                    self.renderer.draw_instanced_mesh(
-                        &self.bottom_mesh, &data, count, &projection, &view, &light_pos_uniform, self.dirt_texture.as_ref()
-                    );
-                    // Sides
-                    self.renderer.draw_instanced_mesh(
-                        &self.side_mesh, &data, count, &projection, &view, &light_pos_uniform, self.grass_side_texture.as_ref()
-                    );
-                },
-                BlockType::Wood => {
-                    // Top & Bottom
-                    self.renderer.draw_instanced_mesh(
-                        &self.top_mesh, &data, count, &projection, &view, &light_pos_uniform, self.wood_top_texture.as_ref()
-                    );
-                    self.renderer.draw_instanced_mesh(
-                        &self.bottom_mesh, &data, count, &projection, &view, &light_pos_uniform, self.wood_top_texture.as_ref()
-                    );
-                    // Sides
-                    self.renderer.draw_instanced_mesh(
-                        &self.side_mesh, &data, count, &projection, &view, &light_pos_uniform, self.wood_side_texture.as_ref()
-                    );
-                },
-                _ => {
-                    let texture = match block_type {
-                        BlockType::Dirt => self.dirt_texture.as_ref(),
-                        BlockType::Leaves => self.leaves_texture.as_ref(),
-                        BlockType::Stone => self.stone_texture.as_ref(),
-                        BlockType::Bedrock => self.bedrock_texture.as_ref(),
-                        _ => None,
-                    };
-                    self.renderer.draw_instanced_mesh(
-                        &self.cube_mesh, &data, count, &projection, &view, &light_pos_uniform, texture
-                    );
-                }
+
+        // Draw each chunk's prebuilt groups, chunks still generating draw nothing
+        for (block_type, data) in self.chunks.meshes.values().flat_map(|mesh| mesh.instances.iter()) {
+            let block_type = *block_type;
+            let count = (data.len() / 8) as i32;
+
+            let (side, top, bottom, single) = block_registry::with_def(block_type, |def| {
+                (def.side.clone(), def.top_tile().to_string(), def.bottom_tile().to_string(), def.single_tile())
+            });
+            if single {
+                self.renderer.draw_instanced_mesh(
+                    &self.cube_mesh, data, count, &projection, &view, &light_pos_uniform, self.tiles.get(&side)
+                );
+                continue;
             }
+            self.renderer.draw_instanced_mesh(
+                &self.top_mesh, data, count, &projection, &view, &light_pos_uniform, self.tiles.get(&top)
+            );
+            self.renderer.draw_instanced_mesh(
+                &self.bottom_mesh, data, count, &projection, &view, &light_pos_uniform, self.tiles.get(&bottom)
+            );
+            self.renderer.draw_instanced_mesh(
+                &self.side_mesh, data, count, &projection, &view, &light_pos_uniform, self.tiles.get(&side)
+            );
+        }
+
+        for ((block_type, part), data) in self.chunks.meshes.values().flat_map(|mesh| mesh.parts.iter()) {
+            if let Some(mesh) = self.shape_meshes.get(part) {
+                let count = (data.len() / 8) as i32;
+                self.renderer.draw_instanced_mesh(
+                    mesh, data, count, &projection, &view, &light_pos_uniform, self.block_texture(*block_type)
+                );
+            }
+        }
+
+        // Zombies are a green body on blue legs, tinted orange while burning
+        for zombie in &self.mobs.zombies {
+            let (skin, legs) = if zombie.is_burning() {
+                ((0.9, 0.45, 0.1), (0.6, 0.3, 0.1))
+            } else {
+                ((0.3, 0.6, 0.3), (0.2, 0.25, 0.6))
+            };
+            let p = zombie.pos;
+            self.renderer.draw_cube(p.x, p.y + 0.45, p.z, 0.5, 0.9, 0.3, legs.0, legs.1, legs.2, &projection, &view);
+            self.renderer.draw_cube(p.x, p.y + 1.2, p.z, 0.6, 0.6, 0.35, skin.0, skin.1, skin.2, &projection, &view);
+            self.renderer.draw_cube(p.x, p.y + 1.6, p.z, 0.45, 0.4, 0.45, skin.0, skin.1, skin.2, &projection, &view);
+        }
+        // The peer is drawn like a zombie in player colors, turned to face where they look
+        if let Some(remote) = &self.coop.remote {
+            let [x, y, z] = remote.pos;
+            let parts = [
+                (0.45, 0.5, 0.9, 0.3, (0.25, 0.3, 0.7)),
+                (1.2, 0.6, 0.6, 0.35, (0.2, 0.7, 0.8)),
+                (1.6, 0.45, 0.4, 0.45, (0.9, 0.75, 0.6)),
+            ];
+            for (dy, w, h, d, color) in parts {
+                self.renderer.draw_mesh(
+                    &self.cube_mesh, x, y + dy, z, w, h, d, 0.0, -remote.yaw, 0.0,
+                    &projection, &view, None, None, Some(color), false, None, true, false, false, None, None
+                );
+            }
+        }
+        self.coop.update_label(&projection, &view, css_width, css_height);
+        for particle in &self.mobs.particles {
+            let (r, g, b) = particle.color;
+            self.renderer.draw_cube(particle.pos.x, particle.pos.y, particle.pos.z, 0.1, 0.1, 0.1, r, g, b, &projection, &view);
+        }
+
+        // Dropped stacks spin and bob as small blocks
+        for pickup in &self.pickups {
+            let bob = ((self.frame as f32 * 0.05) + pickup.pos.x).sin() * 0.1;
+            let spin = self.frame as f32 * 0.04 + pickup.pos.z;
+            self.renderer.draw_mesh(
+                &self.cube_mesh, pickup.pos.x, pickup.pos.y + bob, pickup.pos.z, 0.3, 0.3, 0.3, 0.0, spin, 0.0,
+                &projection, &view, self.block_texture(pickup.block), None, None, false, None, true, false, false, None, None
+            );
         }
         
-        // Render selection highlight (raycast)
-        if let Some((_bx, _by, _bz, _face)) = self.raycast() {
-             // Draw a wireframe or slightly larger transparent cube
-             // For now, just draw a marker
+        self.map.draw(&self.renderer, self.player_pos, self.look.rotation().0, width as f32, height as f32);
+
+        // Crosshair goes last, on top of everything
+        let hit = self.raycast(CROSSHAIR_RANGE);
+        self.draw_crosshair(width as f32, height as f32, hit.as_ref());
+
+        let fade = self.respawn_fade();
+        if fade > 0.0 {
+            self.renderer.begin_screen_overlay();
+            self.renderer.enable_blend();
+            self.renderer.set_alpha(fade);
+            self.renderer.draw_screen_rect(0.0, 0.0, width as f32, height as f32, (0.0, 0.0, 0.0), (width as f32, height as f32));
+            self.renderer.set_alpha(1.0);
+            self.renderer.disable_blend();
+            self.renderer.end_screen_overlay();
+        }
+    }
+
+    // Side texture for multi-texture blocks, used for everything drawn as a single cube
+    fn block_texture(&self, block: BlockType) -> Option<&WebGlTexture> {
+        self.tiles.get(&block_registry::with_def(block, |def| def.side.clone()))
+    }
+
+    fn draw_crosshair(&self, width: f32, height: f32, hit: Option<&RaycastHit>) {
+        let style = &self.crosshair_style;
+        let color = match hit {
+            _ if self.placement_flash > 0 => style.color_blocked,
+            Some(hit) if hit.distance <= REACH && hit.block.is_breakable() => style.color_target,
+            Some(_) => style.color_blocked,
+            None => style.color_empty,
+        };
+
+        let dpr = web_sys::window().map(|w| w.device_pixel_ratio()).unwrap_or(1.0) as f32;
+        let size = style.size * dpr;
+        let thickness = (style.thickness * dpr).max(1.0);
+        let cx = width / 2.0;
+        let cy = height / 2.0;
+
+        self.renderer.begin_screen_overlay();
+        self.renderer.draw_screen_rect(cx - size / 2.0, cy - thickness / 2.0, size, thickness, color, (width, height));
+        self.renderer.draw_screen_rect(cx - thickness / 2.0, cy - size / 2.0, thickness, size, color, (width, height));
+
+        if self.debug_overlay {
+            if let Some(hit) = hit {
+                let text = format!("{:.1}", hit.distance);
+                self.renderer.draw_screen_text(&text, cx + size, cy + size / 2.0, 10.0 * dpr, color, (width, height));
+            }
+        }
+        self.renderer.end_screen_overlay();
+    }
+
+    pub fn craft(&mut self, recipe_id: &str) -> Result<(), String> {
+        crafting::craft(&mut self.inventory, recipe_id)?;
+        self.emit_inventory();
+        Ok(())
+    }
+
+    pub fn inventory_json(&self) -> String {
+        serde_json::to_string(&self.inventory).unwrap_or_default()
+    }
+
+    fn emit_inventory(&self) {
+        events::emit("inventory_changed", serde_json::to_value(&self.inventory).unwrap_or_default());
+    }
+
+    fn jump(&mut self) {
+        if !self.on_ground {
+            return;
+        }
+        self.velocity.y = self.physics.jump_velocity;
+        if self.input_state.sprint && self.input_state.forward {
+            let (yaw, _) = self.look.rotation();
+            let h_vel = Vector3::new(self.velocity.x, 0.0, self.velocity.z);
+            let direction = if h_vel.norm() > 0.0 { h_vel.normalize() } else { Vector3::new(yaw.cos(), 0.0, yaw.sin()) };
+            self.velocity += direction * self.physics.sprint_jump_boost;
+        }
+    }
+
+    pub fn physics_json(&self) -> String {
+        serde_json::to_string(&self.physics).unwrap_or_default()
+    }
+
+    // Only the fields named change. Out-of-range values are clamped rather than rejected,
+    // the result is saved as the override
+    pub fn set_physics(&mut self, json: &str) -> Result<(), String> {
+        self.physics = self.physics.merged(json)?.clamped();
+        self.physics.save();
+        Ok(())
+    }
+
+    pub fn reset_physics(&mut self) {
+        self.physics = PhysicsConfig::reset();
+    }
+
+    pub fn graphics_tier(&self) -> String {
+        self.graphics.name()
+    }
+
+    // Takes effect on the running world: lighting changes relight chunks as the queue gets
+    // to them and a shorter view distance drops the far meshes on the next update
+    pub fn set_graphics(&mut self, tier: &str) -> Result<(), String> {
+        self.graphics = GraphicsProfile::parse(tier)?;
+        self.graphics.save();
+        self.chunks.apply_graphics(&self.graphics);
+        self.apply_texture_filter();
+        self.apply_shadow_map();
+        Ok(())
+    }
+
+    // A shadow map the context can't make leaves the high tier with flat lighting, which
+    // is worth a console warning but not worth refusing the tier over
+    fn apply_shadow_map(&mut self) {
+        if let Err(e) = self.renderer.set_shadow_map(self.graphics.shadows == ShadowMode::Mapped) {
+            web_sys::console::warn_1(&format!("Shadow map unavailable: {}", e.as_string().unwrap_or_else(|| format!("{:?}", e))).into());
+        }
+    }
+
+    // Depth of every opaque chunk block as the sun sees it, for the instanced shader to
+    // test against. Skipped while the sun is down, it lights nothing then.
+    fn render_shadow_map(&self) {
+        let to_sun = terrain::sun_direction(sun_angle(self.time_of_day));
+        if self.graphics.shadows != ShadowMode::Mapped || to_sun.y <= 0.0 {
+            self.renderer.clear_shadow_pass();
+            return;
+        }
+        let light_view_projection = sun_view_projection(self.player_pos, to_sun, self.graphics.view_radius);
+        if !self.renderer.begin_shadow_pass(&light_view_projection) {
+            return;
+        }
+        for (block_type, data) in self.chunks.meshes.values().flat_map(|mesh| mesh.instances.iter()) {
+            if !block_type.is_transparent() {
+                self.renderer.draw_shadow_caster(&self.cube_mesh, data, (data.len() / 8) as i32);
+            }
+        }
+        for ((block_type, part), data) in self.chunks.meshes.values().flat_map(|mesh| mesh.parts.iter()) {
+            if block_type.is_transparent() {
+                continue;
+            }
+            if let Some(mesh) = self.shape_meshes.get(part) {
+                self.renderer.draw_shadow_caster(mesh, data, (data.len() / 8) as i32);
+            }
+        }
+        self.renderer.end_shadow_pass();
+    }
+
+    fn apply_texture_filter(&self) {
+        for texture in self.tiles.values() {
+            self.renderer.set_texture_filter(texture, self.graphics.nearest_filter);
         }
     }
 
-    pub fn handle_input(&mut self, key: &str) {
+    // Blocks registered mid-game need their tiles, placed blocks pick up changed properties
+    // the next time their chunk is rebuilt
+    pub fn register_blocks(&mut self, json: &str) -> Result<(), String> {
+        block_registry::register(json)?;
+        self.tiles = load_tiles(&self.renderer, std::mem::take(&mut self.tiles));
+        self.apply_texture_filter();
+        Ok(())
+    }
+
+    pub fn set_crosshair_style(&mut self, json: &str) -> Result<(), String> {
+        let style: CrosshairStyle = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        self.crosshair_style = style;
+        Ok(())
+    }
+
+    pub fn handle_input(&mut self, key: &str, code: &str) {
+        if let Some(action) = key_bindings::action(BindingSet::Minecraft, key, code, MINECRAFT_KEYS) {
+            self.apply_action(action, true);
+            return;
+        }
         match key {
-            "w" | "W" => self.input_state.forward = true,
-            "s" | "S" => self.input_state.backward = true,
-            "a" | "A" => self.input_state.left = true,
-            "d" | "D" => self.input_state.right = true,
-            " " => {
-                if self.on_ground {
-                    self.velocity.y = 0.4;
-                }
-            },
             "1" => { self.selected_block_type = BlockType::Grass; self.update_block_ui(); },
             "2" => { self.selected_block_type = BlockType::Dirt; self.update_block_ui(); },
             "3" => { self.selected_block_type = BlockType::Stone; self.update_block_ui(); },
             "4" => { self.selected_block_type = BlockType::Wood; self.update_block_ui(); },
             "5" => { self.selected_block_type = BlockType::Leaves; self.update_block_ui(); },
+            "6" => { self.selected_block_type = BlockType::Sand; self.update_block_ui(); },
+            "7" => { self.selected_block_type = BlockType::Planks; self.update_block_ui(); },
+            "8" => { self.selected_block_type = BlockType::Brick; self.update_block_ui(); },
+            "9" => { self.selected_block_type = BlockType::Glass; self.update_block_ui(); },
+            "0" => { self.selected_block_type = BlockType::Slab; self.update_block_ui(); },
+            "-" => { self.selected_block_type = BlockType::Fence; self.update_block_ui(); },
+            "[" if self.block_updates.fluids_enabled => { self.selected_block_type = BlockType::Water; self.update_block_ui(); },
+            "]" if self.block_updates.fluids_enabled => { self.selected_block_type = BlockType::Lava; self.update_block_ui(); },
+            "F3" => self.debug_overlay = !self.debug_overlay,
+            "m" | "M" => self.toggle_map(),
+            "p" | "P" => self.set_spawn_here(),
             _ => {}
         }
     }
 
     fn update_block_ui(&self) {
+        events::emit("block_selected", serde_json::json!(self.selected_block_type));
         if let Some(window) = web_sys::window() {
             if let Some(document) = window.document() {
                 let selected_index = match self.selected_block_type {
@@ -483,10 +1302,18 @@ impl Minecraft {
                     BlockType::Stone => 3,
                     BlockType::Wood => 4,
                     BlockType::Leaves => 5,
+                    BlockType::Sand => 6,
+                    BlockType::Planks => 7,
+                    BlockType::Brick => 8,
+                    BlockType::Glass => 9,
+                    BlockType::Slab => 10,
+                    BlockType::Fence => 11,
+                    BlockType::Water => 12,
+                    BlockType::Lava => 13,
                     _ => 1,
                 };
 
-                for i in 1..=5 {
+                for i in 1..=13 {
                     if let Some(element) = document.get_element_by_id(&format!("slot-{}", i)) {
                         let class_name = if i == selected_index {
                             "hotbar-slot selected"
@@ -500,41 +1327,181 @@ impl Minecraft {
         }
     }
 
-    pub fn handle_keyup(&mut self, key: &str) {
-        match key {
-            "w" | "W" => self.input_state.forward = false,
-            "s" | "S" => self.input_state.backward = false,
-            "a" | "A" => self.input_state.left = false,
-            "d" | "D" => self.input_state.right = false,
-            _ => {}
+    pub fn handle_keyup(&mut self, key: &str, code: &str) {
+        if let Some(action) = key_bindings::action(BindingSet::Minecraft, key, code, MINECRAFT_KEYS) {
+            self.apply_action(action, false);
+        }
+    }
+
+    // Held is whether the key went down or came up, jumping only happens on the way down
+    pub fn apply_action(&mut self, action: KeyAction, held: bool) {
+        match action {
+            KeyAction::Jump if held => self.jump(),
+            _ => self.set_movement(action, held),
+        }
+    }
+
+    // A sample from load_sound when there is one, otherwise the block's footstep
+    fn play_block_sound(&self, name: &str, block: BlockType) {
+        if let Some(audio) = &self.audio {
+            if !audio.play(name, 0.5) {
+                play_footstep(audio, block);
+            }
+        }
+    }
+
+    fn set_movement(&mut self, action: KeyAction, held: bool) {
+        match action {
+            KeyAction::Forward => self.input_state.forward = held,
+            KeyAction::Back => self.input_state.backward = held,
+            KeyAction::Left => self.input_state.left = held,
+            KeyAction::Right => self.input_state.right = held,
+            KeyAction::Sprint => self.input_state.sprint = held,
+            KeyAction::Jump | KeyAction::Restart => {}
         }
     }
 
+    // Right stick, already past its dead zone. Pads don't need pointer lock to look around.
+    pub fn gamepad_look(&mut self, x: f32, y: f32, dt: f32) {
+        if x != 0.0 || y != 0.0 {
+            let step = GAMEPAD_LOOK_SPEED * dt;
+            self.look.look(x * step, -y * step);
+        }
+    }
+
+    // A pad press isn't a user gesture, the browser would refuse the pointer lock, so pads
+    // break and place without one
+    pub fn gamepad_use(&mut self, button: i32) {
+        self.use_tool(button);
+    }
+
+    // A finger dragged by (dx, dy) CSS pixels turns the view like the mouse would
+    pub fn touch_look(&mut self, dx: i32, dy: i32) {
+        self.look.handle(&InputEvent::PointerMove { x: 0, y: 0, dx, dy });
+    }
+
+    // Driven by pointerlockchange only. Escape or a lost lock also lets go of held
+    // movement, since the keyups may go to the browser instead.
     pub fn set_locked(&mut self, locked: bool) {
         self.is_locked = locked;
+        if !locked {
+            self.input_state = InputState::default();
+        }
+        show_click_to_play(!locked);
+    }
+
+    fn request_pointer_lock(&self) {
+        if let Some(canvas) = self.renderer.canvas() {
+            canvas.request_pointer_lock();
+        }
+    }
+
+    pub fn handle_pointer_event(&mut self, event: &InputEvent) {
+        match *event {
+            InputEvent::PointerDown { button, .. } => self.handle_mouse_down(button),
+            InputEvent::PointerMove { .. } if self.is_locked => self.look.handle(event),
+            _ => {}
+        }
+    }
+
+    fn world_set(&mut self, pos: BlockPos, block: Option<BlockType>) {
+        self.world_set_meta(pos, block, 0);
+    }
+
+    // Every gameplay edit goes through here so the peer gets it, clicks and fluids alike
+    fn world_set_meta(&mut self, pos: BlockPos, block: Option<BlockType>, meta: u8) {
+        self.world_write(pos, block, meta);
+        self.coop.record_local(pos, block);
     }
 
-    pub fn handle_mouse_move(&mut self, dx: i32, dy: i32) {
-        if self.is_locked {
-            let sensitivity = 0.005;
-            self.player_rot.0 += dx as f32 * sensitivity; // Yaw (Inverted from -= to +=)
-            self.player_rot.1 -= dy as f32 * sensitivity; // Pitch
-            
-            // Clamp pitch
-            self.player_rot.1 = self.player_rot.1.max(-1.5).min(1.5);
+    // Meshes, the map and block handlers hear about every change, the peer's included
+    fn world_write(&mut self, (x, y, z): BlockPos, block: Option<BlockType>, meta: u8) {
+        match block {
+            Some(block) => { self.blocks.insert((x, y, z), block); }
+            None => { self.blocks.remove(&(x, y, z)); }
         }
+        if block.is_some() && meta > 0 {
+            self.meta.insert((x, y, z), meta);
+        } else {
+            self.meta.remove(&(x, y, z));
+        }
+        self.chunks.record_edit((x, y, z), block);
+        self.chunks.mark_dirty(x, z);
+        self.map.mark_edited(x, z);
+        self.invalidate_sky_scan(x, y, z);
+        self.block_updates.notify((x, y, z));
+    }
+
+    // Peer edits go through world_write like local ones so meshes, lighting and fluids react.
+    // Edits to chunks not generated yet wait in the chunk queue, generation would overwrite them.
+    pub fn apply_remote_edits(&mut self, json: &str) -> Result<(), String> {
+        for edit in self.coop.receive(json, &mut self.chunks)? {
+            if self.blocks.get(&edit.pos).copied() != edit.block {
+                self.world_write(edit.pos, edit.block, 0);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn drain_local_edits(&mut self) -> String {
+        self.coop.drain_local_edits()
     }
 
-    pub fn handle_mouse_down(&mut self, _x: i32, _y: i32, button: i32) {
+    pub fn set_remote_player(&mut self, json: &str) -> Result<(), String> {
+        self.coop.set_remote_player(json)
+    }
+
+    fn process_block_updates(&mut self) {
+        self.block_updates.tick();
+        for _ in 0..self.block_updates.budget {
+            let pos = match self.block_updates.pop(&self.blocks) {
+                Some(pos) => pos,
+                None => break,
+            };
+            for (edit_pos, block, meta) in self.block_updates.react(&self.blocks, &self.meta, pos) {
+                self.world_set_meta(edit_pos, block, meta);
+            }
+        }
+    }
+
+    fn handle_mouse_down(&mut self, button: i32) {
+        // The first click only captures the mouse, the game starts once the browser confirms
         if !self.is_locked {
-            self.is_locked = true;
-            // Request pointer lock in JS side ideally
+            self.request_pointer_lock();
             return;
         }
+        self.use_tool(button);
+    }
+
+    // 0 punches or breaks, 2 places
+    fn use_tool(&mut self, button: i32) {
+        let hit = self.raycast(REACH);
+        if button == 0 {
+            // Punching: a zombie in front of the targeted block takes the hit
+            let reach = hit.as_ref().map(|hit| hit.distance).unwrap_or(REACH);
+            let dir = self.look_dir();
+            if let Some((index, _)) = self.mobs.raycast(self.eye_pos(), dir, reach) {
+                if self.mobs.punch(index, dir) {
+                    events::emit("zombie_killed", serde_json::json!(null));
+                }
+                return;
+            }
+        }
 
-        if let Some((bx, by, bz, face)) = self.raycast() {
+        if let Some(RaycastHit { x: bx, y: by, z: bz, face, block, point, .. }) = hit {
             if button == 0 { // Left click: Break
-                self.blocks.remove(&(bx, by, bz));
+                if block.is_breakable() {
+                    self.play_block_sound("break", block);
+                    self.world_set((bx, by, bz), None);
+                    let center = Vector3::new(bx as f32, by as f32, bz as f32);
+                    self.mobs.shatter(center, block.color());
+                    if self.creative {
+                        *self.inventory.entry(block.item()).or_insert(0) += 1;
+                        self.emit_inventory();
+                    } else {
+                        self.drop_pickup(center - Vector3::new(0.0, 0.2, 0.0), block.item(), 1);
+                    }
+                }
             } else if button == 2 { // Right click: Place
                 let (nx, ny, nz) = match face {
                     0 => (bx + 1, by, bz),
@@ -545,51 +1512,477 @@ impl Minecraft {
                     5 => (bx, by, bz - 1),
                     _ => (bx, by, bz),
                 };
-                // Don't place inside player
-                let block_center = Vector3::new(nx as f32, ny as f32, nz as f32);
-                if (self.player_pos - block_center).norm() > 1.5 {
-                    self.blocks.insert((nx, ny, nz), self.selected_block_type);
+                let item = self.selected_block_type;
+                let block = match item {
+                    BlockType::Slab => slab_half(face, point.y - by as f32),
+                    block => block,
+                };
+                // Fluids are free to pour while they're enabled
+                if fluids::is_fluid(item) {
+                    if self.block_updates.fluids_enabled {
+                        self.world_set((nx, ny, nz), Some(item));
+                    } else {
+                        self.placement_flash = 12;
+                    }
+                    return;
+                }
+                let in_stock = self.inventory.get(&item).copied().unwrap_or(0) > 0;
+                if in_stock && self.can_place_at(nx, ny, nz, block) {
+                    self.play_block_sound("place", block);
+                    self.world_set((nx, ny, nz), Some(block));
+                    if let Some(count) = self.inventory.get_mut(&item) {
+                        *count -= 1;
+                        if *count == 0 {
+                            self.inventory.remove(&item);
+                        }
+                    }
+                    self.emit_inventory();
+                } else {
+                    self.placement_flash = 12;
                 }
             }
         }
     }
     
-    fn raycast(&self) -> Option<(i32, i32, i32, usize)> {
-        let (yaw, pitch) = self.player_rot;
-        let dir = Vector3::new(
-            yaw.cos() * pitch.cos(),
-            pitch.sin(),
-            yaw.sin() * pitch.cos()
-        ).normalize();
-        
+    fn look_dir(&self) -> Vector3<f32> {
+        look_front(&self.look)
+    }
+
+    // Voxel DDA: visits every cell the ray passes through, in order
+    fn raycast(&self, max_dist: f32) -> Option<RaycastHit> {
+        let dir = self.look_dir();
+
+        // Blocks are centered on integer coordinates, shift so each cell spans [n, n + 1)
+        let origin = self.eye_pos() + Vector3::new(0.5, 0.5, 0.5);
+        let mut cell = [origin.x.floor() as i32, origin.y.floor() as i32, origin.z.floor() as i32];
+        let mut step = [0i32; 3];
+        let mut t_max = [f32::INFINITY; 3];
+        let mut t_delta = [f32::INFINITY; 3];
+
+        for axis in 0..3 {
+            if dir[axis] > 0.0 {
+                step[axis] = 1;
+                t_delta[axis] = 1.0 / dir[axis];
+                t_max[axis] = (cell[axis] as f32 + 1.0 - origin[axis]) / dir[axis];
+            } else if dir[axis] < 0.0 {
+                step[axis] = -1;
+                t_delta[axis] = -1.0 / dir[axis];
+                t_max[axis] = (origin[axis] - cell[axis] as f32) / -dir[axis];
+            }
+        }
+
+        // Face ids match the neighbour offsets used for placement, 6 means the ray started inside
+        let mut face = 6;
         let mut t = 0.0;
-        let step = 0.1;
-        let max_dist = 5.0;
-        
-        while t < max_dist {
-            let pos = self.player_pos + dir * t;
-            let bx = pos.x.round() as i32;
-            let by = pos.y.round() as i32;
-            let bz = pos.z.round() as i32;
-            
-            if self.blocks.contains_key(&(bx, by, bz)) {
-                // Determine face (very simple approximation)
-                // A better way is to use a proper DDA algorithm for voxel raycasting
-                // But for now, let's just return the block.
-                // To get the face, we can check the previous position
-                let prev_pos = self.player_pos + dir * (t - step);
-                let pbx = prev_pos.x.round() as i32;
-                let pby = prev_pos.y.round() as i32;
-                let pbz = prev_pos.z.round() as i32;
-                
-                let face = if pbx > bx { 0 } else if pbx < bx { 1 }
-                           else if pby > by { 2 } else if pby < by { 3 }
-                           else if pbz > bz { 4 } else { 5 };
-                           
-                return Some((bx, by, bz, face));
-            }
-            t += step;
-        }
-        None
+
+        loop {
+            if let Some(block) = self.blocks.get(&(cell[0], cell[1], cell[2])) {
+                let pos = (cell[0], cell[1], cell[2]);
+                if block_shapes::is_full_cube(*block) {
+                    let point = self.eye_pos() + dir * t;
+                    return Some(RaycastHit { x: pos.0, y: pos.1, z: pos.2, face, distance: t, block: *block, point });
+                }
+                // Partial blocks are only hit where their boxes are, otherwise the ray carries on
+                let eye = self.eye_pos();
+                let nearest = block_shapes::collision_boxes(&self.blocks, pos, *block).into_iter()
+                    .filter_map(|(min, max)| block_shapes::ray_box(eye, dir, min, max))
+                    .min_by(|a, b| a.0.total_cmp(&b.0));
+                if let Some((box_t, box_face)) = nearest {
+                    if box_t <= max_dist {
+                        return Some(RaycastHit { x: pos.0, y: pos.1, z: pos.2, face: box_face, distance: box_t, block: *block, point: eye + dir * box_t });
+                    }
+                }
+            }
+
+            let axis = if t_max[0] < t_max[1] {
+                if t_max[0] < t_max[2] { 0 } else { 2 }
+            } else if t_max[1] < t_max[2] { 1 } else { 2 };
+
+            t = t_max[axis];
+            if t > max_dist {
+                return None;
+            }
+            cell[axis] += step[axis];
+            t_max[axis] += t_delta[axis];
+            face = axis * 2 + if step[axis] < 0 { 0 } else { 1 };
+        }
+    }
+}
+
+// Physics steps a frame of `elapsed` seconds runs, and the time left over for the next frame
+fn physics_steps(accumulator: f64, elapsed: f64) -> (u32, f64) {
+    let mut accumulator = accumulator + elapsed.max(0.0);
+    let mut steps = 0;
+    while accumulator >= PHYSICS_DT {
+        accumulator -= PHYSICS_DT;
+        steps += 1;
+    }
+    (steps, accumulator)
+}
+
+pub fn interpolate_position(previous: Vector3<f32>, current: Vector3<f32>, alpha: f32) -> Vector3<f32> {
+    previous + (current - previous) * alpha.clamp(0.0, 1.0)
+}
+
+fn look_front(look: &FpsController) -> Vector3<f32> {
+    let (yaw, pitch) = look.rotation();
+    Vector3::new(
+        yaw.cos() * pitch.cos(),
+        pitch.sin(),
+        yaw.sin() * pitch.cos()
+    ).normalize()
+}
+
+// Pushes a feet-anchored box of the given size back out of the blocks it overlaps along one
+// axis, zeroing that velocity component. Returns whether anything was hit. Shared by the
+// player and mobs.
+pub fn resolve_collisions(blocks: &HashMap<BlockPos, BlockType>, pos: &mut Vector3<f32>, velocity: &mut Vector3<f32>, width: f32, height: f32, axis: usize) -> bool {
+    let px = pos.x.round() as i32;
+    let py = pos.y.round() as i32;
+    let pz = pos.z.round() as i32;
+    let half = width / 2.0;
+    let top = py + height.ceil() as i32 + 1;
+    let mut hit = false;
+
+    for y in (py - 1)..=top {
+        for x in (px - 1)..=(px + 1) {
+            for z in (pz - 1)..=(pz + 1) {
+                let block = match blocks.get(&(x, y, z)) {
+                    Some(block) => *block,
+                    None => continue,
+                };
+                for (block_min, block_max) in block_shapes::collision_boxes(blocks, (x, y, z), block) {
+                    let body_min = Vector3::new(pos.x - half, pos.y, pos.z - half);
+                    let body_max = Vector3::new(pos.x + half, pos.y + height, pos.z + half);
+
+                    if aabb_intersect(body_min, body_max, block_min, block_max) {
+                        hit = true;
+                        match axis {
+                            0 => { 
+                                if velocity.x > 0.0 {
+                                    pos.x = block_min.x - half - 0.001;
+                                } else if velocity.x < 0.0 {
+                                    pos.x = block_max.x + half + 0.001;
+                                }
+                                velocity.x = 0.0;
+                            },
+                            1 => { 
+                                if velocity.y > 0.0 {
+                                    pos.y = block_min.y - height - 0.001;
+                                    velocity.y = 0.0;
+                                } else if velocity.y < 0.0 {
+                                    pos.y = block_max.y;
+                                    velocity.y = 0.0;
+                                }
+                            },
+                            2 => { 
+                                if velocity.z > 0.0 {
+                                    pos.z = block_min.z - half - 0.001;
+                                } else if velocity.z < 0.0 {
+                                    pos.z = block_max.z + half + 0.001;
+                                }
+                                velocity.z = 0.0;
+                            },
+                            _ => {}
+                        }
+                    }
+                }
+            }
+        }
+    }
+    hit
+}
+
+// Whether a block fits at cell without overlapping any of the entity boxes
+pub fn can_place(blocks: &HashMap<BlockPos, BlockType>, cell: BlockPos, block: BlockType, eye: Vector3<f32>, entities: &[(Vector3<f32>, Vector3<f32>)]) -> bool {
+    // Pressed against a wall the ray can start inside the target cell
+    if (eye.x.round() as i32, eye.y.round() as i32, eye.z.round() as i32) == cell {
+        return false;
+    }
+
+    let boxes = block_shapes::collision_boxes(blocks, cell, block);
+    !entities.iter().any(|(min, max)| {
+        boxes.iter().any(|(block_min, block_max)| aabb_intersect(*min, *max, *block_min, *block_max))
+    })
+}
+
+pub fn aabb_intersect(min1: Vector3<f32>, max1: Vector3<f32>, min2: Vector3<f32>, max2: Vector3<f32>) -> bool {
+    min1.x < max2.x && max1.x > min2.x &&
+    min1.y < max2.y && max1.y > min2.y &&
+    min1.z < max2.z && max1.z > min2.z
+}
+
+// Top and bottom faces decide outright, on a side face the hit height within the cell does
+fn slab_half(face: usize, hit_height: f32) -> BlockType {
+    match face {
+        2 => BlockType::Slab,
+        3 => BlockType::SlabTop,
+        _ if hit_height > 0.0 => BlockType::SlabTop,
+        _ => BlockType::Slab,
+    }
+}
+
+// Optional #click-to-play prompt over the canvas while the mouse is free
+fn show_click_to_play(visible: bool) {
+    let element = web_sys::window()
+        .and_then(|w| w.document())
+        .and_then(|d| d.get_element_by_id("click-to-play"))
+        .and_then(|e| e.dyn_into::<web_sys::HtmlElement>().ok());
+    if let Some(element) = element {
+        element.style().set_property("display", if visible { "block" } else { "none" }).ok();
+    }
+}
+
+// A stopped game shouldn't keep the mouse captured
+impl Drop for Minecraft {
+    fn drop(&mut self) {
+        show_click_to_play(false);
+        if let Some(document) = web_sys::window().and_then(|w| w.document()) {
+            document.exit_pointer_lock();
+            for i in 1..=13 {
+                if let Some(element) = document.get_element_by_id(&format!("slot-{}", i)) {
+                    element.set_class_name("hotbar-slot");
+                }
+            }
+        }
+    }
+}
+
+// Adds whatever tiles the registry names that aren't loaded yet. A tile that fails to
+// load is left out and its faces draw untextured.
+fn load_tiles(renderer: &Renderer, mut tiles: HashMap<String, WebGlTexture>) -> HashMap<String, WebGlTexture> {
+    for tile in block_registry::tiles() {
+        if tiles.contains_key(&tile) {
+            continue;
+        }
+        if let Ok(texture) = renderer.create_texture(&format!("assets/textures/TinyCraft/tiles/{}.png", tile)) {
+            tiles.insert(tile, texture);
+        }
+    }
+    tiles
+}
+
+// Keys used for actions the key_bindings config leaves out
+pub const MINECRAFT_KEYS: &[(KeyAction, &[&str])] = &[
+    (KeyAction::Forward, &["w", "KeyW"]),
+    (KeyAction::Back, &["s", "KeyS"]),
+    (KeyAction::Left, &["a", "KeyA"]),
+    (KeyAction::Right, &["d", "KeyD"]),
+    (KeyAction::Jump, &[" "]),
+    (KeyAction::Sprint, &["Shift"]),
+];
+
+fn sun_angle(time_of_day: f32) -> f32 {
+    (time_of_day - 0.25) * std::f32::consts::PI * 2.0
+}
+
+// Orthographic view from the sun over every loaded chunk. Centred on the player's block
+// so the map moves in whole blocks and shadow edges don't crawl while walking.
+fn sun_view_projection(player_pos: Vector3<f32>, to_sun: Vector3<f32>, view_radius: i32) -> Matrix4<f32> {
+    let center = player_pos.map(f32::round);
+    let reach = (view_radius + 1) as f32 * terrain::CHUNK_SIZE as f32;
+    let eye = center + to_sun * reach * 2.0;
+    let up = if to_sun.y.abs() > 0.99 { Vector3::z() } else { Vector3::y() };
+    let view = Matrix4::look_at_rh(&Point3::from(eye), &Point3::from(center), &up);
+    Orthographic3::new(-reach, reach, -reach, reach, 0.1, reach * 4.0).to_homogeneous() * view
+}
+
+// Soft thuds on soil and plants, clicks on stone, knocks on wood
+fn play_footstep(audio: &Audio, block: BlockType) {
+    match block_registry::with_def(block, |def| def.sound) {
+        StepSound::Plant => audio.noise_burst(BiquadFilterType::Lowpass, 500.0, 0.12, 0.25),
+        StepSound::Soil => audio.noise_burst(BiquadFilterType::Lowpass, 900.0, 0.1, 0.25),
+        StepSound::Stone => {
+            audio.noise_burst(BiquadFilterType::Highpass, 2500.0, 0.04, 0.3);
+            audio.blip(OscillatorType::Triangle, 900.0, 0.03, 0.05);
+        }
+        StepSound::Wood => {
+            audio.noise_burst(BiquadFilterType::Bandpass, 1200.0, 0.06, 0.3);
+            audio.blip(OscillatorType::Sine, 180.0, 0.08, 0.15);
+        }
+        StepSound::Glass => {
+            audio.noise_burst(BiquadFilterType::Highpass, 4000.0, 0.03, 0.2);
+            audio.blip(OscillatorType::Sine, 2200.0, 0.05, 0.08);
+        }
+        StepSound::Fluid => audio.noise_burst(BiquadFilterType::Lowpass, 300.0, 0.15, 0.2),
+    }
+}
+
+// Enough of the natural blocks to build with before anything is mined
+fn starting_inventory() -> Inventory {
+    [BlockType::Grass, BlockType::Dirt, BlockType::Stone, BlockType::Wood, BlockType::Leaves]
+        .iter()
+        .map(|block| (*block, 64))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A floor at y = 0 and a ceiling whose underside leaves `headroom` blocks above it,
+    // across a 5×5 patch around the origin
+    fn room(headroom: i32) -> HashMap<BlockPos, BlockType> {
+        let mut blocks = HashMap::new();
+        for x in -2..=2 {
+            for z in -2..=2 {
+                blocks.insert((x, 0, z), BlockType::Stone);
+                blocks.insert((x, headroom + 1, z), BlockType::Stone);
+            }
+        }
+        blocks
+    }
+
+    // One tick of gravity and vertical movement, the same order as Minecraft::tick
+    fn fall_tick(blocks: &HashMap<BlockPos, BlockType>, pos: &mut Vector3<f32>, velocity: &mut Vector3<f32>) {
+        let physics = PhysicsConfig::default();
+        velocity.y = physics.fall_step(velocity.y);
+        pos.y += velocity.y;
+        resolve_collisions(blocks, pos, velocity, PLAYER_WIDTH, STAND_HEIGHT, 1);
+    }
+
+    #[test]
+    fn standing_under_a_two_block_ceiling_is_free() {
+        let blocks = room(2);
+        let mut pos = Vector3::new(0.0, 0.5, 0.0);
+        let mut velocity = Vector3::zeros();
+        for _ in 0..10 {
+            fall_tick(&blocks, &mut pos, &mut velocity);
+        }
+        assert_eq!(pos.y, 0.5);
+        // The head clears the ceiling with room to spare
+        assert!(pos.y + STAND_HEIGHT < 2.5);
+    }
+
+    #[test]
+    fn walking_through_a_one_by_two_corridor() {
+        let mut blocks = HashMap::new();
+        for z in -2..=12 {
+            blocks.insert((0, 0, z), BlockType::Stone);
+            blocks.insert((-1, 1, z), BlockType::Stone);
+            blocks.insert((1, 1, z), BlockType::Stone);
+            blocks.insert((-1, 2, z), BlockType::Stone);
+            blocks.insert((1, 2, z), BlockType::Stone);
+            blocks.insert((0, 3, z), BlockType::Stone);
+        }
+        let mut pos = Vector3::new(0.0, 0.5, 0.0);
+        let mut velocity = Vector3::new(0.0, 0.0, 0.15);
+        for _ in 0..60 {
+            velocity.z = 0.15;
+            pos.z += velocity.z;
+            assert!(!resolve_collisions(&blocks, &mut pos, &mut velocity, PLAYER_WIDTH, STAND_HEIGHT, 2));
+            fall_tick(&blocks, &mut pos, &mut velocity);
+        }
+        assert!((pos.z - 9.0).abs() < 1e-3, "walked to {}", pos.z);
+        assert_eq!(pos.y, 0.5);
+        assert_eq!(pos.x, 0.0);
+    }
+
+    #[test]
+    fn jumping_in_a_three_high_room_stops_at_the_ceiling() {
+        let blocks = room(3);
+        let mut pos = Vector3::new(0.0, 0.5, 0.0);
+        let mut velocity = Vector3::new(0.0, PhysicsConfig::default().jump_velocity, 0.0);
+        let mut peak: f32 = pos.y;
+        for _ in 0..120 {
+            fall_tick(&blocks, &mut pos, &mut velocity);
+            peak = peak.max(pos.y);
+            assert!(pos.y + STAND_HEIGHT < 3.5, "head went into the ceiling at {}", pos.y);
+        }
+        // The default jump would go higher than the room, so the head stops just under it
+        assert!(peak + STAND_HEIGHT > 3.49);
+        assert_eq!(pos.y, 0.5);
+    }
+
+    fn player_box(feet: Vector3<f32>) -> (Vector3<f32>, Vector3<f32>) {
+        let half = PLAYER_WIDTH / 2.0;
+        (feet - Vector3::new(half, 0.0, half), feet + Vector3::new(half, STAND_HEIGHT, half))
+    }
+
+    #[test]
+    fn placing_into_a_player_half_inside_the_cell_is_rejected() {
+        let blocks = room(2);
+        // Standing on the cell boundary at x = 1.5, so half the body is in the (2, 1, 0) cell
+        let feet = Vector3::new(1.5, 0.5, 0.0);
+        let eye = feet + Vector3::new(0.0, STAND_EYE_HEIGHT, 0.0);
+        let entities = [player_box(feet)];
+        assert!(!can_place(&blocks, (2, 1, 0), BlockType::Stone, eye, &entities));
+        // Head height too
+        assert!(!can_place(&blocks, (2, 2, 0), BlockType::Stone, eye, &entities));
+        // One cell further along is clear of the body
+        assert!(can_place(&blocks, (3, 1, 0), BlockType::Stone, eye, &entities));
+    }
+
+    #[test]
+    fn placing_into_the_eye_cell_is_rejected() {
+        let blocks = room(2);
+        let eye = Vector3::new(0.0, 2.12, 0.0);
+        assert!(!can_place(&blocks, (0, 2, 0), BlockType::Stone, eye, &[]));
+        assert!(can_place(&blocks, (1, 2, 0), BlockType::Stone, eye, &[]));
+    }
+
+    // Frames of dt seconds for one second of play, each drawn position recorded the way
+    // update() and render() produce it, for a body moving one unit per step
+    fn drawn_positions(dts: &[f64]) -> (u32, Vec<(f64, f32)>) {
+        let (mut accumulator, mut clock, mut total) = (0.0, 0.0, 0);
+        let (mut previous, mut current) = (Vector3::zeros(), Vector3::zeros());
+        let mut drawn = Vec::new();
+        for &dt in dts {
+            let (steps, left) = physics_steps(accumulator, dt);
+            accumulator = left;
+            for _ in 0..steps {
+                previous = current;
+                current.x += 1.0;
+            }
+            total += steps;
+            clock += dt;
+            let alpha = (accumulator / PHYSICS_DT) as f32;
+            drawn.push((clock, interpolate_position(previous, current, alpha).x));
+        }
+        (total, drawn)
+    }
+
+    #[test]
+    fn step_count_follows_real_time_at_any_frame_rate() {
+        for hz in [30.0, 60.0, 75.0, 144.0] {
+            let (steps, _) = drawn_positions(&vec![1.0 / hz; hz as usize]);
+            assert!((59..=60).contains(&steps), "{} steps at {} Hz", steps, hz);
+        }
+    }
+
+    // Once the first step has run, the drawn position trails real time by exactly one step
+    // whether frames are fast, slow or uneven
+    #[test]
+    fn interpolated_position_doesnt_depend_on_frame_rate() {
+        let jittery: Vec<f64> = (0..80).map(|i| if i % 3 == 0 { 0.021 } else { 0.0095 }).collect();
+        for dts in [vec![1.0 / 30.0; 30], vec![1.0 / 60.0; 60], vec![1.0 / 144.0; 144], jittery] {
+            for (clock, x) in drawn_positions(&dts).1 {
+                if clock < PHYSICS_DT {
+                    continue;
+                }
+                let expected = (clock / PHYSICS_DT - 1.0) as f32;
+                assert!((x - expected).abs() < 1e-3, "drew {} at {:.4}s, expected {}", x, clock, expected);
+            }
+        }
+    }
+
+    // Mouse look lands between physics steps; the drawn view must face wherever the mouse
+    // points right now, not somewhere between the last two steps
+    #[test]
+    fn look_direction_is_never_interpolated() {
+        let mut look = FpsController::new(0.0, 0.0);
+        let mut accumulator = 0.0;
+        for frame in 0..60 {
+            let (_, left) = physics_steps(accumulator, 1.0 / 144.0);
+            accumulator = left;
+            look.look(0.05, if frame % 2 == 0 { 0.02 } else { -0.01 });
+            let alpha = (accumulator / PHYSICS_DT) as f32;
+            let eye = interpolate_position(Vector3::zeros(), Vector3::x(), alpha);
+            let front = (eye + look_front(&look)) - eye;
+
+            let (yaw, pitch) = look.rotation();
+            let expected = Vector3::new(yaw.cos() * pitch.cos(), pitch.sin(), yaw.sin() * pitch.cos());
+            assert!((front - expected).norm() < 1e-5, "frame {} drew {:?}, mouse at {:?}", frame, front, expected);
+        }
     }
 }
@@ -0,0 +1,103 @@
+use crate::engine::settings::Settings;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphicsTier {
+    Low,
+    #[default]
+    Medium,
+    High,
+}
+
+// How chunk lighting is baked into the instance data
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ShadowMode {
+    // Every face at full light, no rays cast
+    Flat,
+    // Raycast toward the sun, redone when it has moved far enough
+    Cached,
+    // Baked flat, the renderer darkens whatever the sun can't see in a shadow map drawn
+    // from the sun each frame
+    Mapped,
+}
+
+impl ShadowMode {
+    // Only the cached mode bakes the sun into the chunk meshes, so only it needs relighting
+    pub fn bakes_sun(self) -> bool {
+        self == ShadowMode::Cached
+    }
+}
+
+// Everything a tier switches, read by the renderer and by the chunk mesher. The frame
+// budgets are targets for a 4x throttled CPU with the default chunk budget:
+// low ~10 ms, medium ~16 ms, high ~33 ms. Most of the difference is chunk count, low
+// draws 9 chunks, medium 25 and high 121, which high draws twice with its shadow pass.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct GraphicsProfile {
+    pub tier: GraphicsTier,
+    pub nearest_filter: bool,
+    // Chunks in each direction from the player's chunk. High's 5 is its 10-chunk view
+    // distance, low rounds its 4 down to a radius of 1 so it stays under medium's 2.
+    pub view_radius: i32,
+    pub ambient_occlusion: bool,
+    pub shadows: ShadowMode,
+    // Skybox with its clouds, the plain sky color shows without it
+    pub sky: bool,
+    pub particle_cap: Option<usize>,
+}
+
+impl GraphicsProfile {
+    pub fn for_tier(tier: GraphicsTier) -> Self {
+        match tier {
+            GraphicsTier::Low => GraphicsProfile {
+                tier,
+                nearest_filter: true,
+                view_radius: 1,
+                ambient_occlusion: false,
+                shadows: ShadowMode::Flat,
+                sky: false,
+                particle_cap: Some(64),
+            },
+            GraphicsTier::Medium => GraphicsProfile {
+                tier,
+                nearest_filter: false,
+                view_radius: 2,
+                ambient_occlusion: false,
+                shadows: ShadowMode::Cached,
+                sky: true,
+                particle_cap: None,
+            },
+            GraphicsTier::High => GraphicsProfile {
+                tier,
+                nearest_filter: false,
+                view_radius: 5,
+                ambient_occlusion: true,
+                shadows: ShadowMode::Mapped,
+                sky: true,
+                particle_cap: None,
+            },
+        }
+    }
+
+    pub fn parse(name: &str) -> Result<Self, String> {
+        let tier = serde_json::from_value(serde_json::Value::String(name.to_string()))
+            .map_err(|_| format!("Unknown graphics tier \"{}\", expected low, medium or high", name))?;
+        Ok(Self::for_tier(tier))
+    }
+
+    // A missing or unknown entry falls back to medium
+    pub fn load() -> Self {
+        let tier = serde_json::from_value(Settings::load().minecraft_graphics).unwrap_or_default();
+        Self::for_tier(tier)
+    }
+
+    pub fn save(&self) {
+        let value = serde_json::to_value(self.tier).unwrap_or_default();
+        Settings::update(|s| s.minecraft_graphics = value);
+    }
+
+    pub fn name(&self) -> String {
+        serde_json::to_value(self.tier).ok().and_then(|v| v.as_str().map(str::to_string)).unwrap_or_default()
+    }
+}
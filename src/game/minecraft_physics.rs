@@ -0,0 +1,176 @@
+use crate::engine::settings::Settings;
+use serde::{Deserialize, Serialize};
+
+// Per-tick movement tuning, in blocks and blocks per tick at the fixed physics step.
+// Overrides live in the settings blob under minecraft_physics; missing fields take
+// the defaults, so a partial blob only changes what it names.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct PhysicsConfig {
+    pub acceleration: f32,
+    pub max_speed: f32,
+    pub gravity: f32,
+    // Horizontal velocity kept each tick
+    pub friction: f32,
+    pub jump_velocity: f32,
+    pub terminal_velocity: f32,
+    // Share of the ground acceleration available while airborne
+    pub air_control: f32,
+    pub sprint_multiplier: f32,
+    // Extra horizontal speed along the movement direction when jumping while sprinting
+    pub sprint_jump_boost: f32,
+}
+
+impl Default for PhysicsConfig {
+    fn default() -> Self {
+        PhysicsConfig {
+            acceleration: 0.02,
+            max_speed: 0.15,
+            gravity: 0.02,
+            friction: 0.8,
+            jump_velocity: 0.4,
+            terminal_velocity: 0.9,
+            air_control: 1.0,
+            sprint_multiplier: 1.3,
+            sprint_jump_boost: 0.1,
+        }
+    }
+}
+
+fn clamp_or(value: f32, min: f32, max: f32, default: f32) -> f32 {
+    if value.is_finite() { value.clamp(min, max) } else { default }
+}
+
+impl PhysicsConfig {
+    pub fn load() -> Self {
+        let value = Settings::load().minecraft_physics;
+        if value.is_null() {
+            return Self::default();
+        }
+        serde_json::from_value::<PhysicsConfig>(value).unwrap_or_default().clamped()
+    }
+
+    // Terminal velocity stays under a block per tick so a fall can't skip through a floor
+    pub fn clamped(self) -> Self {
+        let d = PhysicsConfig::default();
+        PhysicsConfig {
+            acceleration: clamp_or(self.acceleration, 0.001, 0.1, d.acceleration),
+            max_speed: clamp_or(self.max_speed, 0.01, 0.5, d.max_speed),
+            gravity: clamp_or(self.gravity, 0.001, 0.1, d.gravity),
+            friction: clamp_or(self.friction, 0.0, 0.99, d.friction),
+            jump_velocity: clamp_or(self.jump_velocity, 0.0, 1.0, d.jump_velocity),
+            terminal_velocity: clamp_or(self.terminal_velocity, 0.1, 0.95, d.terminal_velocity),
+            air_control: clamp_or(self.air_control, 0.0, 1.0, d.air_control),
+            sprint_multiplier: clamp_or(self.sprint_multiplier, 1.0, 3.0, d.sprint_multiplier),
+            sprint_jump_boost: clamp_or(self.sprint_jump_boost, 0.0, 0.5, d.sprint_jump_boost),
+        }
+    }
+
+    // Fields named in the JSON object replace ours, the rest stay as they are
+    pub fn merged(&self, json: &str) -> Result<Self, String> {
+        let serde_json::Value::Object(changes) = serde_json::from_str(json).map_err(|e| e.to_string())? else {
+            return Err("physics overrides must be a JSON object".to_string());
+        };
+        let mut value = serde_json::to_value(self).map_err(|e| e.to_string())?;
+        if let serde_json::Value::Object(current) = &mut value {
+            current.extend(changes);
+        }
+        serde_json::from_value(value).map_err(|e| e.to_string())
+    }
+
+    // Vertical speed after one tick of gravity, never falling faster than terminal velocity
+    pub fn fall_step(&self, vertical_velocity: f32) -> f32 {
+        (vertical_velocity - self.gravity).max(-self.terminal_velocity)
+    }
+
+    pub fn save(&self) {
+        let value = serde_json::to_value(self).unwrap_or_default();
+        Settings::update(|s| s.minecraft_physics = value);
+    }
+
+    pub fn reset() -> Self {
+        Settings::update(|s| s.minecraft_physics = serde_json::Value::Null);
+        Self::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Ticks of a jump from the ground, gravity applied before the move as in Minecraft::tick
+    fn jump_height(physics: &PhysicsConfig) -> f32 {
+        let (mut height, mut velocity) = (0.0, physics.jump_velocity);
+        loop {
+            velocity = physics.fall_step(velocity);
+            if velocity <= 0.0 {
+                return height;
+            }
+            height += velocity;
+        }
+    }
+
+    #[test]
+    fn defaults_keep_the_old_hardcoded_feel() {
+        let d = PhysicsConfig::default();
+        assert_eq!((d.acceleration, d.max_speed, d.gravity, d.friction, d.jump_velocity), (0.02, 0.15, 0.02, 0.8, 0.4));
+        assert_eq!(d.clone().clamped(), d);
+    }
+
+    #[test]
+    fn default_jump_reaches_its_analytic_height() {
+        let d = PhysicsConfig::default();
+        // Speeds v - g, v - 2g, ... down to zero sum to v^2 / 2g - v / 2
+        let expected = d.jump_velocity * d.jump_velocity / (2.0 * d.gravity) - d.jump_velocity / 2.0;
+        assert!((jump_height(&d) - expected).abs() < 1e-4, "{} against {}", jump_height(&d), expected);
+        assert!((expected - 3.8).abs() < 1e-4);
+    }
+
+    #[test]
+    fn default_fall_speeds_up_like_before_then_holds_at_terminal() {
+        let d = PhysicsConfig::default();
+        let ticks_to_terminal = (d.terminal_velocity / d.gravity).round() as usize;
+        let mut velocity = 0.0;
+        for tick in 1..=ticks_to_terminal {
+            velocity = d.fall_step(velocity);
+            // The old code only ever took 0.02 a tick off
+            assert!((velocity + 0.02 * tick as f32).abs() < 1e-4, "tick {}", tick);
+        }
+        for _ in 0..100 {
+            velocity = d.fall_step(velocity);
+        }
+        assert_eq!(velocity, -d.terminal_velocity);
+        assert!(d.terminal_velocity < 1.0, "a tick's fall must not skip a block");
+    }
+
+    #[test]
+    fn clamped_replaces_nan_and_pulls_values_into_range() {
+        let d = PhysicsConfig::default();
+        let wild = PhysicsConfig {
+            acceleration: f32::NAN,
+            max_speed: f32::INFINITY,
+            gravity: -1.0,
+            friction: 2.0,
+            terminal_velocity: 5.0,
+            air_control: f32::NEG_INFINITY,
+            sprint_multiplier: 0.0,
+            ..d.clone()
+        }.clamped();
+        assert_eq!(wild.acceleration, d.acceleration);
+        assert_eq!(wild.max_speed, d.max_speed);
+        assert_eq!(wild.gravity, 0.001);
+        assert_eq!(wild.friction, 0.99);
+        assert_eq!(wild.terminal_velocity, 0.95);
+        assert_eq!(wild.air_control, d.air_control);
+        assert_eq!(wild.sprint_multiplier, 1.0);
+    }
+
+    #[test]
+    fn partial_overrides_keep_the_current_values() {
+        let tuned = PhysicsConfig { gravity: 0.05, max_speed: 0.3, ..PhysicsConfig::default() };
+        let merged = tuned.merged(r#"{"jump_velocity": 0.6}"#).unwrap();
+        assert_eq!(merged, PhysicsConfig { jump_velocity: 0.6, ..tuned.clone() });
+        assert!(tuned.merged("[0.6]").is_err());
+        assert!(tuned.merged(r#"{"gravity": "heavy"}"#).is_err());
+    }
+}
@@ -0,0 +1,279 @@
+use crate::game::block_shapes;
+use crate::game::block_updates::BlockPos;
+use crate::game::minecraft::{aabb_intersect, resolve_collisions, BlockType};
+use crate::game::terrain::{ChunkQueue, CHUNK_HEIGHT};
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Spawn attempts, despawn checks and AI decisions share this many slots per frame,
+// movement itself runs for every zombie every frame
+pub const ENTITY_BUDGET: usize = 8;
+const MAX_ZOMBIES: usize = 6;
+const ZOMBIE_WIDTH: f32 = 0.6;
+const ZOMBIE_HEIGHT: f32 = 1.8;
+const ZOMBIE_SPEED: f32 = 0.05;
+const ZOMBIE_JUMP: f32 = 0.25;
+const CHASE_RANGE: f32 = 16.0;
+// Anything further than this from the player is dropped
+const DESPAWN_RANGE: f32 = 64.0;
+const SPAWN_MIN_DISTANCE: f32 = 12.0;
+const SPAWN_MAX_DISTANCE: f32 = 24.0;
+// Sun elevation (sine of its angle) below which the surface counts as dark
+const DARK_ELEVATION: f32 = -0.05;
+// Sun elevation above which zombies catch fire
+const BURN_ELEVATION: f32 = 0.2;
+const BURN_FRAMES: u32 = 40;
+const HITS_TO_KILL: u32 = 3;
+const CONTACT_DAMAGE: f32 = 3.0;
+const ATTACK_COOLDOWN_FRAMES: u32 = 30;
+const KNOCKBACK: f32 = 0.4;
+const PARTICLE_FRAMES: u32 = 30;
+const DEBRIS_FRAMES: u32 = 40;
+const DEBRIS_COUNT: usize = 12;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Zombie {
+    pub pos: Vector3<f32>, // feet, centered horizontally
+    velocity: Vector3<f32>,
+    heading: Vector3<f32>,
+    on_ground: bool,
+    hits: u32,
+    attack_cooldown: u32,
+    // Frames left before a burning zombie disappears
+    burning: Option<u32>,
+}
+
+impl Zombie {
+    fn new(pos: Vector3<f32>) -> Self {
+        Zombie {
+            pos,
+            velocity: Vector3::zeros(),
+            heading: Vector3::zeros(),
+            on_ground: false,
+            hits: 0,
+            attack_cooldown: 0,
+            burning: None,
+        }
+    }
+
+    pub fn bounds(&self) -> (Vector3<f32>, Vector3<f32>) {
+        let half = ZOMBIE_WIDTH / 2.0;
+        (
+            Vector3::new(self.pos.x - half, self.pos.y, self.pos.z - half),
+            Vector3::new(self.pos.x + half, self.pos.y + ZOMBIE_HEIGHT, self.pos.z + half),
+        )
+    }
+
+    pub fn is_burning(&self) -> bool {
+        self.burning.is_some()
+    }
+}
+
+// Short-lived puffs left by burning or punched-out zombies, and debris from broken blocks
+pub struct Particle {
+    pub pos: Vector3<f32>,
+    velocity: Vector3<f32>,
+    // Added to the vertical speed each frame, puffs rise and debris falls
+    gravity: f32,
+    pub frames_left: u32,
+    pub color: (f32, f32, f32),
+}
+
+// A zombie touched the player this frame
+pub struct ContactHit {
+    pub damage: f32,
+    pub knockback: Vector3<f32>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct Mobs {
+    pub zombies: Vec<Zombie>,
+    // Round-robin position so every zombie gets an AI turn within a few frames
+    #[serde(skip)]
+    cursor: usize,
+    #[serde(skip)]
+    pub particles: Vec<Particle>,
+}
+
+impl Mobs {
+    pub fn update(&mut self, blocks: &HashMap<BlockPos, BlockType>, chunks: &ChunkQueue, player_min: Vector3<f32>, player_max: Vector3<f32>, sun_elevation: f32) -> Vec<ContactHit> {
+        let player_center = (player_min + player_max) / 2.0;
+        let mut budget = ENTITY_BUDGET;
+
+        // Thinking: chase, burn, despawn
+        let count = self.zombies.len();
+        let mut far = Vec::new();
+        for _ in 0..count.min(budget) {
+            self.cursor = (self.cursor + 1) % count;
+            budget -= 1;
+            let zombie = &mut self.zombies[self.cursor];
+            let offset = Vector3::new(player_center.x - zombie.pos.x, 0.0, player_center.z - zombie.pos.z);
+            let distance = offset.norm();
+            if distance > DESPAWN_RANGE {
+                far.push(self.cursor);
+                continue;
+            }
+            if sun_elevation > BURN_ELEVATION && zombie.burning.is_none() {
+                zombie.burning = Some(BURN_FRAMES);
+            }
+            zombie.heading = if distance <= CHASE_RANGE && distance > 0.01 {
+                offset / distance
+            } else {
+                Vector3::zeros()
+            };
+        }
+        far.sort_unstable();
+        for index in far.into_iter().rev() {
+            self.zombies.swap_remove(index);
+        }
+
+        if budget > 0 && sun_elevation < DARK_ELEVATION && self.zombies.len() < MAX_ZOMBIES {
+            self.try_spawn(blocks, chunks, player_center);
+        }
+
+        // Moving: every zombie, every frame
+        let mut hits = Vec::new();
+        for zombie in &mut self.zombies {
+            zombie.attack_cooldown = zombie.attack_cooldown.saturating_sub(1);
+            zombie.velocity.x = zombie.velocity.x * 0.8 + zombie.heading.x * ZOMBIE_SPEED * 0.2;
+            zombie.velocity.z = zombie.velocity.z * 0.8 + zombie.heading.z * ZOMBIE_SPEED * 0.2;
+            zombie.velocity.y -= 0.02;
+
+            zombie.pos.x += zombie.velocity.x;
+            let blocked_x = resolve_collisions(blocks, &mut zombie.pos, &mut zombie.velocity, ZOMBIE_WIDTH, ZOMBIE_HEIGHT, 0);
+            zombie.pos.z += zombie.velocity.z;
+            let blocked_z = resolve_collisions(blocks, &mut zombie.pos, &mut zombie.velocity, ZOMBIE_WIDTH, ZOMBIE_HEIGHT, 2);
+            let falling = zombie.velocity.y < 0.0;
+            zombie.pos.y += zombie.velocity.y;
+            let landed = resolve_collisions(blocks, &mut zombie.pos, &mut zombie.velocity, ZOMBIE_WIDTH, ZOMBIE_HEIGHT, 1) && falling;
+
+            // Walked into a wall while chasing, try to hop up one block
+            let was_on_ground = zombie.on_ground;
+            zombie.on_ground = landed;
+            if (blocked_x || blocked_z) && was_on_ground && zombie.heading.norm() > 0.0 {
+                zombie.velocity.y = ZOMBIE_JUMP;
+            }
+
+            let (min, max) = zombie.bounds();
+            if zombie.attack_cooldown == 0 && zombie.burning.is_none() && aabb_intersect(min, max, player_min, player_max) {
+                zombie.attack_cooldown = ATTACK_COOLDOWN_FRAMES;
+                let push = Vector3::new(player_center.x - zombie.pos.x, 0.0, player_center.z - zombie.pos.z);
+                let push = if push.norm() > 0.0 { push.normalize() } else { zombie.heading };
+                hits.push(ContactHit { damage: CONTACT_DAMAGE, knockback: push * KNOCKBACK + Vector3::new(0.0, 0.2, 0.0) });
+            }
+        }
+
+        let particles = &mut self.particles;
+        self.zombies.retain_mut(|zombie| match zombie.burning.as_mut() {
+            Some(frames) => {
+                *frames = frames.saturating_sub(1);
+                if *frames % 4 == 0 {
+                    puff(particles, zombie.pos + Vector3::new(0.0, ZOMBIE_HEIGHT / 2.0, 0.0), 2, (1.0, 0.5, 0.1));
+                }
+                if *frames == 0 {
+                    puff(particles, zombie.pos + Vector3::new(0.0, ZOMBIE_HEIGHT / 2.0, 0.0), 10, (0.3, 0.3, 0.3));
+                }
+                *frames > 0
+            }
+            None => true,
+        });
+
+        self.particles.retain_mut(|particle| {
+            particle.pos += particle.velocity;
+            particle.velocity.y += particle.gravity;
+            // Falling debris comes to rest on the block below it
+            let cell = (particle.pos.x.round() as i32, particle.pos.y.round() as i32, particle.pos.z.round() as i32);
+            if particle.velocity.y < 0.0 && matches!(blocks.get(&cell), Some(block) if block_shapes::is_full_cube(*block)) {
+                particle.pos.y = cell.1 as f32 + 0.55;
+                particle.velocity = Vector3::zeros();
+            }
+            particle.frames_left = particle.frames_left.saturating_sub(1);
+            particle.frames_left > 0
+        });
+
+        hits
+    }
+
+    // Oldest particles go first, they are the ones closest to fading anyway
+    pub fn cap_particles(&mut self, max: usize) {
+        let excess = self.particles.len().saturating_sub(max);
+        self.particles.drain(..excess);
+    }
+
+    // One random column per frame, only on the surface grass of generated chunks
+    fn try_spawn(&mut self, blocks: &HashMap<BlockPos, BlockType>, chunks: &ChunkQueue, player_center: Vector3<f32>) {
+        let angle = js_sys::Math::random() as f32 * std::f32::consts::TAU;
+        let distance = SPAWN_MIN_DISTANCE + js_sys::Math::random() as f32 * (SPAWN_MAX_DISTANCE - SPAWN_MIN_DISTANCE);
+        let x = (player_center.x + angle.cos() * distance).round() as i32;
+        let z = (player_center.z + angle.sin() * distance).round() as i32;
+        if !chunks.is_generated(ChunkQueue::chunk_of(x, z)) {
+            return;
+        }
+
+        let top = (0..CHUNK_HEIGHT).rev().find_map(|y| blocks.get(&(x, y, z)).map(|block| (y, *block)));
+        // The topmost block always has open sky above it, feet go on its top face
+        if let Some((y, BlockType::Grass)) = top {
+            self.zombies.push(Zombie::new(Vector3::new(x as f32, y as f32 + 0.5, z as f32)));
+        }
+    }
+
+    // Closest zombie along the ray within max_dist, as (index, distance)
+    pub fn raycast(&self, origin: Vector3<f32>, dir: Vector3<f32>, max_dist: f32) -> Option<(usize, f32)> {
+        self.zombies.iter().enumerate()
+            .filter_map(|(index, zombie)| {
+                let (min, max) = zombie.bounds();
+                block_shapes::ray_box(origin, dir, min, max).map(|(t, _)| (index, t))
+            })
+            .filter(|(_, t)| *t <= max_dist)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    // Returns whether the punch killed it
+    pub fn punch(&mut self, index: usize, dir: Vector3<f32>) -> bool {
+        let zombie = &mut self.zombies[index];
+        zombie.hits += 1;
+        let away = Vector3::new(dir.x, 0.0, dir.z);
+        if away.norm() > 0.0 {
+            zombie.velocity += away.normalize() * KNOCKBACK;
+        }
+        zombie.velocity.y += 0.2;
+        if zombie.hits < HITS_TO_KILL {
+            return false;
+        }
+        let center = zombie.pos + Vector3::new(0.0, ZOMBIE_HEIGHT / 2.0, 0.0);
+        self.zombies.swap_remove(index);
+        puff(&mut self.particles, center, 10, (0.8, 0.8, 0.8));
+        true
+    }
+
+    // Chips flying out of a broken block, shaded around its tile color
+    pub fn shatter(&mut self, center: Vector3<f32>, color: (f32, f32, f32)) {
+        for _ in 0..DEBRIS_COUNT {
+            let angle = js_sys::Math::random() as f32 * std::f32::consts::TAU;
+            let speed = 0.02 + js_sys::Math::random() as f32 * 0.04;
+            let shade = 0.8 + js_sys::Math::random() as f32 * 0.4;
+            self.particles.push(Particle {
+                pos: center,
+                velocity: Vector3::new(angle.cos() * speed, 0.06 + js_sys::Math::random() as f32 * 0.06, angle.sin() * speed),
+                gravity: -0.008,
+                frames_left: DEBRIS_FRAMES,
+                color: ((color.0 * shade).min(1.0), (color.1 * shade).min(1.0), (color.2 * shade).min(1.0)),
+            });
+        }
+    }
+}
+
+fn puff(particles: &mut Vec<Particle>, center: Vector3<f32>, count: usize, color: (f32, f32, f32)) {
+    for _ in 0..count {
+        let angle = js_sys::Math::random() as f32 * std::f32::consts::TAU;
+        let speed = 0.02 + js_sys::Math::random() as f32 * 0.03;
+        particles.push(Particle {
+            pos: center,
+            velocity: Vector3::new(angle.cos() * speed, 0.02, angle.sin() * speed),
+            gravity: 0.002,
+            frames_left: PARTICLE_FRAMES,
+            color,
+        });
+    }
+}
@@ -1,9 +1,165 @@
+pub mod block_registry;
+pub mod block_shapes;
+pub mod block_updates;
+pub mod coop;
+pub mod crafting;
+pub mod crossy_ai;
+pub mod crossy_level;
+pub mod crossy_stats;
+pub mod crossy_theme;
+pub mod fluids;
+pub mod solar_ephemeris;
+pub mod solar_slowmo;
+pub mod solar_svg;
 pub mod solar_system;
+pub mod solar_time;
+pub mod solar_tour;
+pub mod structures;
+pub mod sonification;
 pub mod minecraft;
+pub mod minecraft_graphics;
+pub mod minecraft_physics;
+pub mod mobs;
+pub mod terrain;
+pub mod touch_overlay;
+pub mod world_map;
+pub mod world_save;
 use nalgebra::{Matrix4, Vector3, Perspective3};
+use crate::engine::audio::Audio;
+use crate::engine::key_bindings::{GameBindings, KeyAction};
 use crate::engine::mesh::Mesh;
 use crate::engine::renderer::Renderer;
+use crate::engine::settings::Settings;
+use crate::engine::events;
+use crate::engine::overlay;
+use crate::engine::particles::{self, Particles};
+use crate::game::crossy_level::CustomLevel;
+use crate::game::crossy_theme::ThemeDef;
+use crate::game::touch_overlay::{Swipe, TouchButton, TouchOverlay};
 use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::Write;
+use web_sys::{BiquadFilterType, OscillatorType};
+
+// One simulation step, everything in a tick moves by this much game time
+pub const SIM_DT: f32 = 0.016;
+// Distance a hop covers per tick, 2 units in a little over 13 ticks
+const HOP_SPEED: f32 = 0.15;
+// Real seconds per tick, so a 60 Hz display runs one tick a frame as it always has and
+// faster or slower displays run more or fewer
+const TICK_SECONDS: f64 = 1.0 / 60.0;
+// Real time a single update may catch up on, a stalled frame drops the rest
+const MAX_CATCH_UP_SECONDS: f64 = 0.25;
+// Further than anything moves in one tick: a wrap, a respawn or a restart, drawn where it
+// landed rather than swept across the screen
+const MAX_BLEND_DISTANCE: f32 = 1.0;
+pub const DEFAULT_COUNTDOWN: f32 = 3.0;
+const WETNESS_FADE_SECONDS: f32 = 5.0;
+pub const DAY_SECONDS: f32 = 60.0;
+// Tint keyframes through the day: full daylight until 25s, dusk at 35s, night at 50s,
+// back to daylight by the end of the cycle
+const DAY_TINT_KEYS: [(f32, (f32, f32, f32)); 5] = [
+    (0.0, (1.0, 1.0, 1.0)),
+    (25.0, (1.0, 1.0, 1.0)),
+    (35.0, (1.0, 0.7, 0.5)),
+    (50.0, (0.3, 0.2, 0.4)),
+    (60.0, (1.0, 1.0, 1.0)),
+];
+// The player moves within +-half_width, lanes are drawn two units wider on each side
+// and obstacles wrap five units past the player's limit
+pub const DEFAULT_HALF_WIDTH: f32 = 10.0;
+const MIN_HALF_WIDTH: f32 = 4.0;
+const MAX_HALF_WIDTH: f32 = 30.0;
+// Finger travel in CSS pixels before a touch counts as a swipe instead of a tap
+pub const DEFAULT_SWIPE_THRESHOLD: f32 = 30.0;
+const EDGE_WARNING_DISTANCE: f32 = 4.0;
+// How far back from the furthest point reached the player may retreat, four 2-unit hops
+const CAMERA_KILL_DISTANCE: f32 = 8.0;
+// Lanes kept behind the furthest point reached, past the kill boundary
+const LANES_KEPT_BEHIND: i32 = 10;
+// A log carried this far past the playfield edge no longer holds the player up
+const LOG_EDGE_MARGIN: f32 = 0.5;
+// Fraction of the gap to its target the camera closes each tick, 1.0 follows rigidly
+pub const DEFAULT_CAMERA_SMOOTHING: f32 = 0.15;
+// Framing shifts ahead while the player keeps making progress, and toward the last sideways hop
+const CAMERA_LOOK_AHEAD: f32 = 3.0;
+const CAMERA_LOOK_AHEAD_HOLD_SECONDS: f32 = 1.0;
+const CAMERA_LATERAL_LEAD: f32 = 0.8;
+// After a death the view turns onto the crash over this long, closing in by up to this fraction
+const DEATH_ZOOM_SECONDS: f32 = 2.5;
+const DEATH_ZOOM_AMOUNT: f32 = 0.4;
+const EDGE_WARNING_SECONDS: f32 = 1.5;
+// Seconds without a new furthest point before the eagle comes, its shadow grows over the
+// player for the last few
+pub const DEFAULT_IDLE_SECONDS: f32 = 7.0;
+const EAGLE_WARNING_SECONDS: f32 = 2.0;
+// Starts this far ahead and flies back along the camera's view at EAGLE_SPEED units a second
+const EAGLE_START_DISTANCE: f32 = 30.0;
+const EAGLE_SPEED: f32 = 40.0;
+const EAGLE_HEIGHT: f32 = 2.0;
+// Rail lanes start after RAIL_MIN_LANE and get likelier with difficulty. A train crosses
+// every TRAIN_MIN_PERIOD plus up to TRAIN_PERIOD_SPREAD seconds, the signal blinking for
+// TRAIN_WARNING_SECONDS before it.
+const RAIL_MIN_LANE: i32 = 8;
+// Chance a water lane rolls lily pads instead of logs, with 3 to 5 pads each. A lane right
+// after another pad lane can't have them, which brings the share of pad lanes down to a third.
+const LILY_PAD_LANE_CHANCE: f32 = 0.385;
+const LILY_PAD_MIN: usize = 3;
+const LILY_PAD_MAX: usize = 5;
+// Chance per grass or road lane past the first few, split evenly between the kinds
+const POWER_UP_CHANCE: f32 = 0.04;
+const MAGNET_SECONDS: f32 = 10.0;
+const MAGNET_RANGE: f32 = 3.0;
+// Fraction of the remaining distance an attracted coin covers each tick
+const MAGNET_PULL: f32 = 0.15;
+// Cars can't hit for this long after the shield takes one, the player blinks meanwhile
+const SHIELD_GRACE_SECONDS: f32 = 1.0;
+const RAIL_CHANCE: f32 = 0.02;
+const RAIL_CHANCE_AT_MAX_DIFFICULTY: f32 = 0.08;
+const TRAIN_MIN_PERIOD: f32 = 6.0;
+const TRAIN_PERIOD_SPREAD: f32 = 4.0;
+const TRAIN_WARNING_SECONDS: f32 = 1.5;
+// Units a second, and length at the default width
+const TRAIN_SPEED: f32 = 50.0;
+const TRAIN_LENGTH: f32 = 20.0;
+const ATTRACT_RESTART_SECONDS: f32 = 3.0;
+// Lanes summarized in the share text strip
+const SHARE_STRIP_LANES: usize = 20;
+// Style bonuses: one long log ride, or several water lanes in quick succession
+const PATIENT_RIDE_SECONDS: f32 = 4.0;
+const RAPID_CROSSING_LANES: usize = 3;
+const RAPID_CROSSING_SECONDS: f32 = 2.5;
+const STYLE_BONUS_COINS: i32 = 2;
+// After a death traffic keeps going in slow motion for a moment, without wrapping
+const DEATH_REVIEW_SECONDS: f32 = 1.5;
+const DEATH_REVIEW_SPEED: f32 = 0.3;
+const INTENSITY_SMOOTHING_SECONDS: f32 = 0.5;
+// Change in smoothed intensity worth an event
+const INTENSITY_EVENT_STEP: f32 = 0.05;
+// Decoration rolls per lane side
+const FENCE_CHANCE: f32 = 0.35;
+const SIGN_CHANCE: f32 = 0.25;
+const LAMP_CHANCE: f32 = 0.5;
+// Darkness (1 - average time-of-day tint) past which lamps switch on
+const LAMP_ON_DARKNESS: f32 = 0.3;
+const LAMP_RADIUS: f32 = 4.0;
+const LAMP_BOOST: f32 = 1.2;
+const LAMP_GLOW: f32 = 1.6;
+// Themed ice floes: slide speed per tick, and the box of falling snow kept around the player
+const SLIDE_SPEED: f32 = 0.05;
+const SNOW_FLAKES: i32 = 300;
+const SNOW_HEIGHT: f32 = 14.0;
+const SNOW_DEPTH: f32 = 30.0;
+
+// Keys used for actions the key_bindings config leaves out
+pub const CROSSY_KEYS: &[(KeyAction, &[&str])] = &[
+    (KeyAction::Forward, &[" "]),
+    (KeyAction::Back, &["ArrowDown", "s", "KeyS"]),
+    (KeyAction::Left, &["ArrowLeft", "a", "KeyA"]),
+    (KeyAction::Right, &["ArrowRight", "d", "KeyD"]),
+    (KeyAction::Restart, &["r"]),
+];
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct ModelConfig {
@@ -18,6 +174,239 @@ pub struct ModelConfig {
 #[derive(Serialize, Deserialize, Clone)]
 pub struct AppConfig {
     pub car_model: ModelConfig,
+    #[serde(default = "default_half_width")]
+    pub playfield_half_width: f32,
+    #[serde(default)]
+    pub collision: CollisionMargins,
+    #[serde(default)]
+    pub difficulty: DifficultyConfig,
+    #[serde(default = "default_swipe_threshold")]
+    pub swipe_threshold: f32,
+    #[serde(default)]
+    pub key_bindings: GameBindings,
+    // 0 turns the eagle off
+    #[serde(default = "default_idle_seconds")]
+    pub idle_seconds: f32,
+    #[serde(default = "default_camera_smoothing")]
+    pub camera_smoothing: f32,
+    // Player models picked with select_character, the cube is used until one is
+    #[serde(default)]
+    pub characters: Vec<ModelConfig>,
+}
+
+// Hitbox adjustment per object kind as a fraction of its width and depth, negative shrinks.
+// Car meshes are narrower than their boxes, so near misses used to kill; logs grow a little
+// so landings at the end of one still count.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct CollisionMargins {
+    // Off restores the raw boxes
+    pub enabled: bool,
+    pub player: f32,
+    pub car: f32,
+    pub truck: f32,
+    pub log: f32,
+    pub lily_pad: f32,
+    pub tree: f32,
+    pub rock: f32,
+    pub coin: f32,
+}
+
+impl Default for CollisionMargins {
+    fn default() -> Self {
+        CollisionMargins {
+            enabled: true,
+            player: 0.0,
+            car: -0.15,
+            truck: -0.15,
+            log: 0.1,
+            lily_pad: 0.1,
+            tree: 0.0,
+            rock: 0.0,
+            coin: 0.25,
+        }
+    }
+}
+
+impl CollisionMargins {
+    pub fn margin(&self, kind: ObjectKind) -> f32 {
+        if !self.enabled {
+            return 0.0;
+        }
+        let margin = match kind {
+            ObjectKind::Player => self.player,
+            ObjectKind::Car => self.car,
+            ObjectKind::Truck => self.truck,
+            ObjectKind::Log => self.log,
+            ObjectKind::LilyPad => self.lily_pad,
+            ObjectKind::Tree => self.tree,
+            ObjectKind::Rock => self.rock,
+            ObjectKind::Coin(_) | ObjectKind::PowerUp(_) => self.coin,
+            ObjectKind::Other => 0.0,
+        };
+        // Never shrink a box to nothing
+        margin.max(-0.9)
+    }
+}
+
+// Lane generation tuning. Difficulty climbs from 0 by 1 every ramp_lanes lanes up to
+// max_multiplier; speeds are per simulation tick and coin chances per lane.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct DifficultyConfig {
+    pub ramp_lanes: f32,
+    pub max_multiplier: f32,
+    pub base_car_speed: f32,
+    pub car_speed_per_difficulty: f32,
+    // Logs on a water lane past the first few, which get one more
+    pub water_log_min: usize,
+    pub coin_chance_road: f32,
+    pub coin_chance_water: f32,
+    pub coin_chance_grass: f32,
+}
+
+impl Default for DifficultyConfig {
+    fn default() -> Self {
+        DifficultyConfig {
+            ramp_lanes: 20.0,
+            max_multiplier: 1.5,
+            base_car_speed: 0.02,
+            car_speed_per_difficulty: 0.03,
+            water_log_min: 2,
+            coin_chance_road: 0.3,
+            coin_chance_water: 0.3,
+            coin_chance_grass: 0.4,
+        }
+    }
+}
+
+impl DifficultyConfig {
+    // Difficulty increases with distance
+    fn at(&self, abs_index: usize) -> f32 {
+        (abs_index as f32 / self.ramp_lanes.max(1.0)).min(self.max_multiplier)
+    }
+
+    // How far along the ramp a lane is, 0..1
+    fn progress(&self, abs_index: usize) -> f32 {
+        if self.max_multiplier <= 0.0 {
+            return 1.0;
+        }
+        self.at(abs_index) / self.max_multiplier
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ObjectKind {
+    Player,
+    Car,
+    // Road vehicles at least TRUCK_MIN_WIDTH long
+    Truck,
+    Log,
+    // Stationary stepping stone on a water lane without logs
+    LilyPad,
+    Tree,
+    Rock,
+    Coin(CoinKind),
+    PowerUp(PowerUpKind),
+    Other,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CoinKind {
+    Gold,
+    // Red gem
+    Gem,
+    Diamond,
+}
+
+impl CoinKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            CoinKind::Gold => "coin",
+            CoinKind::Gem => "gem",
+            CoinKind::Diamond => "diamond",
+        }
+    }
+
+    pub fn value(self) -> i32 {
+        match self {
+            CoinKind::Gold => 1,
+            CoinKind::Gem => 5,
+            CoinKind::Diamond => 20,
+        }
+    }
+
+    fn size(self) -> f32 {
+        match self {
+            CoinKind::Gold => COIN_SIZE,
+            CoinKind::Gem => COIN_SIZE * 1.25,
+            CoinKind::Diamond => COIN_SIZE * 1.4,
+        }
+    }
+
+    // Multiplies the pulse and bob speed
+    fn spin(self) -> f32 {
+        match self {
+            CoinKind::Gold => 1.0,
+            CoinKind::Gem => 1.8,
+            CoinKind::Diamond => 2.4,
+        }
+    }
+
+    fn color(self, palette: &Palette) -> (f32, f32, f32) {
+        match self {
+            CoinKind::Gold => palette.coin,
+            CoinKind::Gem => (0.9, 0.1, 0.2),
+            CoinKind::Diamond => (0.75, 0.95, 1.0),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PowerUpKind {
+    // Pulls nearby coins in for MAGNET_SECONDS
+    Magnet,
+    // Takes the next car hit instead of the player
+    Shield,
+}
+
+impl PowerUpKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            PowerUpKind::Magnet => "magnet",
+            PowerUpKind::Shield => "shield",
+        }
+    }
+
+    fn color(self) -> (f32, f32, f32) {
+        match self {
+            PowerUpKind::Magnet => (0.9, 0.15, 0.2),
+            PowerUpKind::Shield => (0.3, 0.8, 1.0),
+        }
+    }
+}
+
+const TRUCK_MIN_WIDTH: f32 = 3.0;
+const COIN_SIZE: f32 = 0.4;
+const COIN_COLOR: (f32, f32, f32) = (1.0, 0.8, 0.0);
+// Per coin spawned; diamonds only on roads, where picking one up is riskiest
+const GEM_CHANCE: f32 = 0.05;
+const DIAMOND_CHANCE: f32 = 0.01;
+
+fn default_half_width() -> f32 {
+    DEFAULT_HALF_WIDTH
+}
+
+fn default_swipe_threshold() -> f32 {
+    DEFAULT_SWIPE_THRESHOLD
+}
+
+fn default_camera_smoothing() -> f32 {
+    DEFAULT_CAMERA_SMOOTHING
+}
+
+fn default_idle_seconds() -> f32 {
+    DEFAULT_IDLE_SECONDS
 }
 
 pub struct GameObject {
@@ -27,9 +416,14 @@ pub struct GameObject {
     pub width: f32,
     pub height: f32,
     pub depth: f32,
+    // Footprint used for collisions, the drawn size unless set otherwise
+    pub hit_width: f32,
+    pub hit_depth: f32,
     pub velocity_x: f32,
     pub color: (f32, f32, f32),
     pub is_car: bool,
+    pub color_idx: usize,
+    pub kind: ObjectKind,
 }
 
 const CAR_COLORS: [(f32, f32, f32); 8] = [
@@ -43,21 +437,196 @@ const CAR_COLORS: [(f32, f32, f32); 8] = [
     (0.95, 0.95, 0.95),
 ];
 
+// Every color the Crossy scene uses goes through the active palette so the
+// accessibility modes can swap them without touching the generator.
+#[derive(Clone)]
+pub struct Palette {
+    pub name: &'static str,
+    pub grass: (f32, f32, f32),
+    pub road: (f32, f32, f32),
+    pub water: (f32, f32, f32),
+    pub road_line: (f32, f32, f32),
+    pub grass_tuft: (f32, f32, f32),
+    pub grass_blade: (f32, f32, f32),
+    pub dirt_patch: (f32, f32, f32),
+    pub flowers: [(f32, f32, f32); 5],
+    pub flower_stem: (f32, f32, f32),
+    pub rock: (f32, f32, f32),
+    pub mushroom_stem: (f32, f32, f32),
+    pub mushroom_cap: (f32, f32, f32),
+    pub water_highlight: (f32, f32, f32),
+    pub water_deep: (f32, f32, f32),
+    pub foam: (f32, f32, f32),
+    pub lily_pad: (f32, f32, f32),
+    pub lily_flower: (f32, f32, f32),
+    pub coin: (f32, f32, f32),
+    pub player: (f32, f32, f32),
+    pub player_dead: (f32, f32, f32),
+    pub game_over_bg: (f32, f32, f32),
+    pub warning: (f32, f32, f32),
+    pub car_colors: [(f32, f32, f32); 8],
+    pub outline: bool,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette {
+            name: "default",
+            grass: (0.22, 0.5, 0.22),
+            road: (0.3, 0.3, 0.3),
+            water: (0.2, 0.4, 0.8),
+            road_line: (0.85, 0.85, 0.5),
+            grass_tuft: (0.18, 0.45, 0.12),
+            grass_blade: (0.15, 0.4, 0.05),
+            dirt_patch: (0.25, 0.35, 0.15),
+            flowers: [
+                (0.95, 0.95, 0.3),  // Yellow
+                (0.95, 0.4, 0.4),   // Red
+                (1.0, 1.0, 1.0),    // White
+                (0.8, 0.5, 0.9),    // Purple
+                (0.95, 0.6, 0.7),   // Pink
+            ],
+            flower_stem: (0.15, 0.5, 0.1),
+            rock: (0.5, 0.5, 0.48),
+            mushroom_stem: (0.9, 0.85, 0.75),
+            mushroom_cap: (0.85, 0.2, 0.15),
+            water_highlight: (0.4, 0.6, 0.95),
+            water_deep: (0.15, 0.3, 0.6),
+            foam: (0.85, 0.9, 0.95),
+            lily_pad: (0.2, 0.55, 0.25),
+            lily_flower: (0.95, 0.7, 0.8),
+            coin: (1.0, 0.84, 0.0), // Gold
+            player: (0.2, 0.6, 1.0),
+            player_dead: (1.0, 0.2, 0.2),
+            game_over_bg: (0.8, 0.1, 0.1),
+            warning: (1.0, 0.45, 0.1),
+            car_colors: CAR_COLORS,
+            outline: false,
+        }
+    }
+}
+
+impl Palette {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "high_contrast" => Palette::high_contrast(),
+            "deuteranopia" => Palette::deuteranopia(),
+            _ => Palette::default(),
+        }
+    }
+
+    fn high_contrast() -> Self {
+        Palette {
+            name: "high_contrast",
+            grass: (0.1, 0.55, 0.1),
+            road: (0.08, 0.08, 0.08),
+            water: (0.0, 0.3, 1.0),
+            road_line: (1.0, 1.0, 1.0),
+            dirt_patch: (0.05, 0.3, 0.05),
+            coin: (1.0, 0.9, 0.0),
+            player: (0.0, 0.9, 1.0),
+            player_dead: (1.0, 0.0, 0.6),
+            game_over_bg: (0.6, 0.0, 0.4),
+            warning: (1.0, 1.0, 0.0),
+            car_colors: [
+                (1.0, 0.0, 0.0),
+                (0.0, 0.4, 1.0),
+                (1.0, 1.0, 1.0),
+                (1.0, 0.85, 0.0),
+                (1.0, 0.4, 0.0),
+                (0.8, 0.0, 1.0),
+                (0.0, 1.0, 1.0),
+                (1.0, 0.5, 0.8),
+            ],
+            outline: true,
+            ..Palette::default()
+        }
+    }
+
+    // Okabe-Ito hues: safe states stay on the blue side, danger on orange/vermillion
+    fn deuteranopia() -> Self {
+        Palette {
+            name: "deuteranopia",
+            grass: (0.35, 0.55, 0.6),
+            water: (0.1, 0.25, 0.6),
+            road_line: (0.95, 0.95, 0.95),
+            grass_tuft: (0.3, 0.5, 0.55),
+            grass_blade: (0.25, 0.45, 0.5),
+            dirt_patch: (0.3, 0.4, 0.45),
+            flower_stem: (0.25, 0.45, 0.5),
+            flowers: [
+                (0.94, 0.89, 0.26),
+                (0.8, 0.47, 0.65),
+                (1.0, 1.0, 1.0),
+                (0.34, 0.71, 0.91),
+                (0.9, 0.62, 0.0),
+            ],
+            mushroom_cap: (0.84, 0.37, 0.0),
+            water_highlight: (0.34, 0.71, 0.91),
+            water_deep: (0.0, 0.2, 0.5),
+            lily_pad: (0.0, 0.62, 0.45),
+            coin: (0.9, 0.62, 0.0),
+            player: (0.34, 0.71, 0.91),
+            player_dead: (0.84, 0.37, 0.0),
+            game_over_bg: (0.84, 0.37, 0.0),
+            warning: (0.9, 0.62, 0.0),
+            car_colors: [
+                (0.84, 0.37, 0.0),
+                (0.0, 0.45, 0.7),
+                (0.94, 0.89, 0.26),
+                (0.8, 0.47, 0.65),
+                (0.9, 0.62, 0.0),
+                (0.34, 0.71, 0.91),
+                (0.0, 0.62, 0.45),
+                (0.95, 0.95, 0.95),
+            ],
+            ..Palette::default()
+        }
+    }
+
+    pub fn car_color(&self, color_idx: usize) -> (f32, f32, f32) {
+        self.car_colors[color_idx % self.car_colors.len()]
+    }
+}
+
 impl GameObject {
     pub fn new(x: f32, y: f32, z: f32, width: f32, height: f32, depth: f32, color: (f32, f32, f32)) -> Self {
-        GameObject { x, y, z, width, height, depth, velocity_x: 0.0, color, is_car: false }
+        GameObject { x, y, z, width, height, depth, hit_width: width, hit_depth: depth, velocity_x: 0.0, color, is_car: false, color_idx: 0, kind: ObjectKind::Other }
+    }
+
+    pub fn new_coin(x: f32, y: f32, z: f32) -> Self {
+        GameObject { kind: ObjectKind::Coin(CoinKind::Gold), ..GameObject::new(x, y, z, COIN_SIZE, COIN_SIZE, COIN_SIZE, COIN_COLOR) }
+    }
+
+    pub fn coin_kind(&self) -> CoinKind {
+        match self.kind {
+            ObjectKind::Coin(kind) => kind,
+            _ => CoinKind::Gold,
+        }
+    }
+
+    fn set_coin_kind(&mut self, kind: CoinKind) {
+        self.kind = ObjectKind::Coin(kind);
+        self.width = kind.size();
+        self.height = kind.size();
+        self.depth = kind.size();
+        self.hit_width = self.width;
+        self.hit_depth = self.depth;
     }
 
     pub fn new_car(x: f32, y: f32, z: f32, width: f32, height: f32, depth: f32, color_idx: usize) -> Self {
         let color = CAR_COLORS[color_idx % CAR_COLORS.len()];
-        GameObject { x, y, z, width, height, depth, velocity_x: 0.0, color, is_car: true }
+        let kind = if width >= TRUCK_MIN_WIDTH { ObjectKind::Truck } else { ObjectKind::Car };
+        GameObject { x, y, z, width, height, depth, hit_width: width, hit_depth: depth, velocity_x: 0.0, color, is_car: true, color_idx, kind }
     }
 
-    pub fn collides_horizontal(&self, other: &GameObject) -> bool {
+    pub fn collides_horizontal(&self, other: &GameObject, margins: &CollisionMargins) -> bool {
+        let scale = 1.0 + margins.margin(self.kind);
+        let other_scale = 1.0 + margins.margin(other.kind);
         let dx = (self.x - other.x).abs();
         let dz = (self.z - other.z).abs();
-        dx < (self.width + other.width) / 2.0 &&
-        dz < (self.depth + other.depth) / 2.0
+        dx < (self.hit_width * scale + other.hit_width * other_scale) / 2.0 &&
+        dz < (self.hit_depth * scale + other.hit_depth * other_scale) / 2.0
     }
 }
 
@@ -66,12 +635,337 @@ pub struct Lane {
     pub lane_type: LaneType,
     pub obstacles: Vec<GameObject>,
     pub coins: Vec<GameObject>,
+    // Rain puddles, drawn on roads while wet
+    pub puddles: Vec<Puddle>,
+    pub decorations: Vec<Decoration>,
+    pub power_ups: Vec<GameObject>,
+    pub details: LaneDetails,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum DecorationKind {
+    Fence,
+    CrossingSign,
+    LampPost,
+}
+
+type DecorationBox = ([f32; 3], [f32; 3]);
+// The boxes drawn in one color
+type DecorationPart = (&'static [DecorationBox], (f32, f32, f32));
+
+impl DecorationKind {
+    const ALL: [DecorationKind; 3] = [DecorationKind::Fence, DecorationKind::CrossingSign, DecorationKind::LampPost];
+
+    // Boxes relative to the ground point under the decoration, one color per part.
+    // A lamp's head is its second part.
+    fn parts(&self) -> &'static [DecorationPart] {
+        match self {
+            DecorationKind::Fence => &[(&[
+                ([-0.5, 0.0, -0.05], [-0.4, 0.6, 0.05]),
+                ([-0.5, 0.2, -0.03], [0.5, 0.28, 0.03]),
+                ([-0.5, 0.42, -0.03], [0.5, 0.5, 0.03]),
+            ], (0.85, 0.8, 0.7))],
+            DecorationKind::CrossingSign => &[
+                (&[([-0.04, 0.0, -0.04], [0.04, 1.3, 0.04])], (0.6, 0.6, 0.6)),
+                (&[([-0.25, 1.1, -0.03], [0.25, 1.6, 0.03])], (0.15, 0.35, 0.85)),
+                (&[([-0.06, 1.15, -0.04], [0.06, 1.55, 0.04])], (1.0, 1.0, 1.0)),
+            ],
+            DecorationKind::LampPost => &[
+                (&[([-0.06, 0.0, -0.06], [0.06, 2.4, 0.06]), ([-0.04, 2.3, -0.04], [0.04, 2.38, 0.5])], (0.3, 0.3, 0.32)),
+                (&[([-0.12, 2.15, 0.35], [0.12, 2.3, 0.6])], (1.0, 0.9, 0.6)),
+            ],
+        }
+    }
+}
+
+// Purely visual, never collides
+pub struct Decoration {
+    pub kind: DecorationKind,
+    pub x: f32,
+    pub dz: f32,
+}
+
+// Fixed per lane from the world seed, so rolled once at lane creation
+pub struct Puddle {
+    pub x: f32,
+    pub dz: f32,
+    pub width: f32,
+    pub depth: f32,
+}
+
+// The random values behind a lane's grass or water scenery, one array per detail. They only
+// depend on the lane's z, so they're rolled once at lane creation instead of every frame.
+#[derive(Default)]
+pub enum LaneDetails {
+    #[default]
+    None,
+    Grass {
+        tufts: Vec<[f32; 3]>,
+        blades: Vec<[f32; 4]>,
+        patches: Vec<[f32; 2]>,
+        flowers: Vec<[f32; 3]>,
+        rock: Option<[f32; 2]>,
+        mushroom: Option<[f32; 2]>,
+    },
+    Water {
+        crests: Vec<[f32; 3]>,
+        depths: Vec<[f32; 2]>,
+        foam: Vec<[f32; 2]>,
+        // With whether it flowers
+        lily_pad: Option<([f32; 2], bool)>,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum LaneType {
     Grass,
     Road,
     Water,
+    Finish,
+    Rail,
+}
+
+// Normal traffic wraps at the edges. During the death review it drives off and stays off
+// so nothing teleports through the scene, and photo mode holds it still.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TrafficMode {
+    Normal,
+    NoWrap,
+    Frozen,
+}
+
+// The log is tracked by its index in the lane so a wrap at +-15 doesn't break the streak
+pub struct LogRide {
+    pub lane: i32,
+    pub log: usize,
+    pub seconds: f32,
+    pub awarded: bool,
+}
+
+// The power-up held, seconds left on it (the magnet's), and the grace period after the
+// shield takes a hit. Cleared on restart.
+#[derive(Default)]
+pub struct PowerUps {
+    pub held: Option<PowerUpKind>,
+    pub seconds: f32,
+    pub shield_grace: f32,
+}
+
+impl PowerUps {
+    // A new pickup replaces whatever was held
+    fn pick(&mut self, kind: PowerUpKind) {
+        self.held = Some(kind);
+        self.seconds = if kind == PowerUpKind::Magnet { MAGNET_SECONDS } else { 0.0 };
+    }
+
+    // The shield takes the car hit and leaves the player blinking through the grace period
+    fn use_shield(&mut self) {
+        self.held = None;
+        self.shield_grace = SHIELD_GRACE_SECONDS;
+    }
+
+    // Runs the grace period and the magnet down a tick, true while the magnet still pulls
+    fn tick(&mut self) -> bool {
+        self.shield_grace = (self.shield_grace - SIM_DT).max(0.0);
+        if self.held != Some(PowerUpKind::Magnet) {
+            return false;
+        }
+        self.seconds -= SIM_DT;
+        if self.seconds <= 0.0 {
+            self.held = None;
+            self.seconds = 0.0;
+            return false;
+        }
+        true
+    }
+
+    // What the HUD shows: the power-up and whole seconds left, 0 for the shield
+    fn hud(&self) -> Option<(PowerUpKind, i32)> {
+        self.held.map(|kind| (kind, self.seconds.ceil() as i32))
+    }
+}
+
+// Progress toward the style bonuses, cleared on death and restart
+#[derive(Default)]
+pub struct StyleStreaks {
+    pub log_ride: Option<LogRide>,
+    // Sim times of recent consecutive water landings
+    pub water_landings: Vec<f32>,
+    pub rapid_awarded: bool,
+}
+
+impl StyleStreaks {
+    // One tick on lane, riding the log at that index if any. Drift time only counts while
+    // standing still on the same log, any hop ends the ride. True on the tick a ride earns "patient".
+    fn ride(&mut self, lane: i32, riding: Option<usize>, moving: bool) -> bool {
+        let log = match riding {
+            Some(log) if !moving => log,
+            _ => {
+                self.log_ride = None;
+                return false;
+            }
+        };
+        let ride = match &mut self.log_ride {
+            Some(ride) if ride.lane == lane && ride.log == log => ride,
+            other => other.insert(LogRide { lane, log, seconds: 0.0, awarded: false }),
+        };
+        ride.seconds += SIM_DT;
+        if ride.seconds > PATIENT_RIDE_SECONDS && !ride.awarded {
+            ride.awarded = true;
+            return true;
+        }
+        false
+    }
+
+    // A landing on a new lane at sim time now. True when it earns "rapid_crossing".
+    fn land(&mut self, lane_type: LaneType, now: f32) -> bool {
+        if lane_type != LaneType::Water {
+            self.water_landings.clear();
+            self.rapid_awarded = false;
+            return false;
+        }
+        self.water_landings.push(now);
+        self.water_landings.retain(|t| now - t < RAPID_CROSSING_SECONDS);
+        if self.water_landings.len() >= RAPID_CROSSING_LANES && !self.rapid_awarded {
+            self.rapid_awarded = true;
+            return true;
+        }
+        false
+    }
+}
+
+// Tournament runs end at a fixed lane and are ranked by simulation time
+pub struct Tournament {
+    pub target_lane: i32,
+    pub elapsed_ticks: u32,
+    pub deaths: u32,
+}
+
+// What get_game_state reports for Crossy
+#[derive(Serialize)]
+pub struct CrossySnapshot {
+    pub score: i32,
+    pub coins: i32,
+    pub gems: i32,
+    pub game_over: bool,
+    pub player: PlayerPosition,
+}
+
+#[derive(Serialize)]
+pub struct PlayerPosition {
+    pub x: f32,
+    pub z: f32,
+}
+
+#[derive(Serialize, Clone)]
+pub struct RunSummary {
+    pub seed: u32,
+    pub score: i32,
+    pub coins: i32,
+    pub gems: i32,
+    pub deaths: u32,
+    pub finish_time_ms: Option<f64>,
+    // None when the run reached the finish
+    pub cause: Option<&'static str>,
+    pub play_seconds: f32,
+    // Event theme the run was played under, runs only compare within one theme
+    pub theme: Option<&'static str>,
+}
+
+// Buffers the render path fills every frame, kept between frames so drawing doesn't allocate
+#[derive(Default)]
+struct FrameScratch {
+    countdown: String,
+    lamps: Vec<(f32, f32)>,
+    instances: Vec<f32>,
+}
+
+// Where the moving things stand after a tick. The frame draws between the last two
+// ticks, so motion stays even when frames and ticks don't line up.
+#[derive(Default)]
+struct TickPositions {
+    time: f32,
+    player: (f32, f32, f32),
+    cam: (f32, f32),
+    eagle: Option<(f32, f32)>,
+    // Obstacle x per lane, with the lane's z to tell lanes apart after one scrolls away
+    obstacles: Vec<(f32, Vec<f32>)>,
+}
+
+impl TickPositions {
+    fn record(&mut self, game: &Game) {
+        self.time = game.time;
+        self.player = (game.player.x, game.player.y, game.player.z);
+        self.cam = (game.cam_x, game.cam_z);
+        self.eagle = game.eagle.as_ref().map(|eagle| (eagle.x, eagle.z));
+        self.obstacles.resize_with(game.lanes.len(), Default::default);
+        for (recorded, lane) in self.obstacles.iter_mut().zip(&game.lanes) {
+            recorded.0 = lane.z;
+            recorded.1.clear();
+            recorded.1.extend(lane.obstacles.iter().map(|o| o.x));
+        }
+    }
+
+    // Moves the game to t of the way from these positions to the ones it has now
+    fn blend_into(&self, game: &mut Game, t: f32) {
+        // Nothing recorded before the first tick
+        if self.obstacles.is_empty() {
+            return;
+        }
+        game.time = blend(self.time, game.time, t);
+        game.player.x = blend(self.player.0, game.player.x, t);
+        game.player.y = blend(self.player.1, game.player.y, t);
+        game.player.z = blend(self.player.2, game.player.z, t);
+        game.cam_x = blend(self.cam.0, game.cam_x, t);
+        game.cam_z = blend(self.cam.1, game.cam_z, t);
+        if let (Some((x, z)), Some(eagle)) = (self.eagle, game.eagle.as_mut()) {
+            eagle.x = blend(x, eagle.x, t);
+            eagle.z = blend(z, eagle.z, t);
+        }
+        for ((z, xs), lane) in self.obstacles.iter().zip(&mut game.lanes) {
+            if *z != lane.z || xs.len() != lane.obstacles.len() {
+                continue;
+            }
+            for (x, obstacle) in xs.iter().zip(&mut lane.obstacles) {
+                obstacle.x = blend(*x, obstacle.x, t);
+            }
+        }
+    }
+
+    // Puts back what record() saw, after a blended frame
+    fn restore(&self, game: &mut Game) {
+        game.time = self.time;
+        (game.player.x, game.player.y, game.player.z) = self.player;
+        (game.cam_x, game.cam_z) = self.cam;
+        if let (Some((x, z)), Some(eagle)) = (self.eagle, game.eagle.as_mut()) {
+            eagle.x = x;
+            eagle.z = z;
+        }
+        for ((_, xs), lane) in self.obstacles.iter().zip(&mut game.lanes) {
+            for (x, obstacle) in xs.iter().zip(&mut lane.obstacles) {
+                obstacle.x = *x;
+            }
+        }
+    }
+}
+
+fn blend(from: f32, to: f32, t: f32) -> f32 {
+    if (to - from).abs() > MAX_BLEND_DISTANCE {
+        return to;
+    }
+    from + (to - from) * t
+}
+
+// Ticks a frame of dt real seconds runs, and the real time left over for the next frame
+fn ticks_for(accumulator: f64, dt: f64) -> (u32, f64) {
+    let mut accumulator = (accumulator + dt.max(0.0)).min(MAX_CATCH_UP_SECONDS);
+    let mut ticks = 0;
+    while accumulator >= TICK_SECONDS {
+        accumulator -= TICK_SECONDS;
+        ticks += 1;
+    }
+    (ticks, accumulator)
 }
 
 pub struct Game {
@@ -79,7 +973,10 @@ pub struct Game {
     pub player: GameObject,
     pub lanes: Vec<Lane>,
     pub score: i32,
+    // Coins counts the value of everything picked up, gems and diamonds included;
+    // gems counts just those
     pub coins: i32,
+    pub gems: i32,
     pub game_over: bool,
     pub moving: bool,
     pub target_z: f32,
@@ -89,23 +986,104 @@ pub struct Game {
     pub base_y: f32,
     pub world_seed: u32,
     pub furthest_lane: i32,
+    // Furthest z the player has reached this run, the kill boundary trails it
+    pub camera_min_z: f32,
+    // Where the camera looks, easing after the player, the side of the last sideways hop,
+    // how far the death zoom has gone 0..1 and the crash it closes in on
+    pub cam_x: f32,
+    pub cam_z: f32,
+    cam_side: f32,
+    death_zoom: f32,
+    death_pos: Option<(f32, f32)>,
+    // Seconds since camera_min_z last grew, and the eagle once it's on its way
+    pub idle_timer: f32,
+    pub eagle: Option<GameObject>,
+    // Seconds of traffic since the run started, trains follow their schedule on it
+    pub rail_clock: f32,
     pub time: f32,
     pub car_mesh: Option<Mesh>,
+    // The selected character's mesh and its index in config.characters
+    pub player_mesh: Option<Mesh>,
+    pub character: Option<usize>,
+    // Keyed by the color's bit pattern; render() only has &self
+    pub fallback_car_meshes: RefCell<HashMap<(u32, u32, u32), Mesh>>,
+    scratch: RefCell<FrameScratch>,
+    // One mesh per (kind, part), drawn instanced
+    pub decoration_meshes: HashMap<(DecorationKind, usize), Mesh>,
     pub config: Option<AppConfig>,
+    pub half_width: f32,
     pub invincible: bool,
+    pub power_ups: PowerUps,
+    pub palette: Palette,
+    pub tournament: Option<Tournament>,
+    pub finished: bool,
+    pub last_run_summary: Option<RunSummary>,
+    pub countdown: f32,
+    pub countdown_length: f32,
+    pub countdown_allow_skip: bool,
+    pub raining: bool,
+    pub wetness: f32, // 0 dry .. 1 soaked, eases toward the current weather
+    pub attract_mode: bool,
+    pub attract_restart: f32,
+    // Longest run on record, the stats' longest_run as of the last game over
+    pub high_score: i32,
+    // Type of every lane landed on this run, indexed by lane number
+    pub lane_history: Vec<LaneType>,
+    pub death_lane: Option<usize>,
+    pub style_streaks: StyleStreaks,
+    pub particles: Particles,
+    pub death_cause: Option<&'static str>,
+    // Simulation time spent alive this run, so paused or hidden tabs don't count
+    pub run_seconds: f32,
+    // Music intensity 0..1, smoothed from crossy_intensity()
+    pub intensity: f32,
+    pub emitted_intensity: f32,
+    // Score, coins, gems, game over and power-up as last sent to the page
+    emitted_status: (i32, i32, i32, bool, Option<PowerUpKind>),
+    // Added to the sim time for the day cycle, rolled from the seed each run
+    pub day_offset: f32,
+    // Seconds of slow-motion traffic left after a death
+    pub death_review: f32,
+    pub photo_mode: bool,
+    // Real seconds not yet turned into ticks, and the positions before and after the last tick
+    tick_accumulator: f64,
+    before_tick: TickPositions,
+    after_tick: TickPositions,
+    // Photo mode: a fixed cycle position that pauses the day cycle
+    pub time_of_day_override: Option<f32>,
+    // Scripted lanes replace generation from lane 1 until they run out
+    pub custom_level: Option<CustomLevel>,
+    pub collision: CollisionMargins,
+    pub difficulty: DifficultyConfig,
+    // Event layered over normal play, None outside events
+    pub theme: Option<&'static ThemeDef>,
+    // Units of ice-floe slide left, signed along x
+    pub slide: f32,
+    pub snow_mesh: Mesh,
+    // Built-in on-screen buttons, None when the page brings its own
+    pub touch_overlay: Option<TouchOverlay>,
+    audio: Option<Audio>,
 }
 
 impl Game {
-    pub fn new(renderer: Renderer, car_mesh: Option<Mesh>, config: Option<AppConfig>) -> Self {
-        let player = GameObject::new(0.0, 0.5, 0.0, 0.8, 1.0, 0.8, (0.2, 0.6, 1.0));
+    // The same seed always lays out the same lanes, cars, logs and coins
+    pub fn new(renderer: Renderer, car_mesh: Option<Mesh>, config: Option<AppConfig>, seed: Option<u32>) -> Self {
+        let palette = Palette::from_name(&Settings::load().crossy_palette);
+        let mut player = GameObject::new(0.0, 0.5, 0.0, 0.8, 1.0, 0.8, palette.player);
+        player.kind = ObjectKind::Player;
+        let collision = config.as_ref().map(|c| c.collision.clone()).unwrap_or_default();
+        let difficulty = config.as_ref().map(|c| c.difficulty.clone()).unwrap_or_default();
 
-        // Generate random world seed
-        let world_seed = (js_sys::Math::random() * 1000000.0) as u32;
+        let world_seed = seed.unwrap_or_else(random_seed);
+        let half_width = config.as_ref()
+            .map(|c| c.playfield_half_width)
+            .unwrap_or(DEFAULT_HALF_WIDTH)
+            .clamp(MIN_HALF_WIDTH, MAX_HALF_WIDTH);
         
         // Generate initial lanes
         let mut lanes = Vec::new();
         for i in -5..25 {
-            lanes.push(create_lane_procedural(i as f32 * 2.0, i, world_seed));
+            lanes.push(create_lane_procedural(i as f32 * 2.0, i, world_seed, half_width, &difficulty));
         }
 
         Game {
@@ -114,6 +1092,7 @@ impl Game {
             lanes,
             score: 0,
             coins: 0,
+            gems: 0,
             game_over: false,
             moving: false,
             target_z: 0.0,
@@ -123,112 +1102,488 @@ impl Game {
             base_y: 0.5,
             world_seed,
             furthest_lane: 24,
+            camera_min_z: 0.0,
+            cam_x: 0.0,
+            cam_z: 0.0,
+            cam_side: 0.0,
+            death_zoom: 0.0,
+            death_pos: None,
+            idle_timer: 0.0,
+            eagle: None,
+            rail_clock: 0.0,
             time: 0.0,
             car_mesh,
+            player_mesh: None,
+            character: None,
+            fallback_car_meshes: RefCell::new(HashMap::new()),
+            scratch: RefCell::new(FrameScratch::default()),
+            decoration_meshes: create_decoration_meshes(),
             config,
+            half_width,
             invincible: false,
+            power_ups: PowerUps::default(),
+            palette,
+            tournament: None,
+            finished: false,
+            last_run_summary: None,
+            countdown: DEFAULT_COUNTDOWN,
+            countdown_length: DEFAULT_COUNTDOWN,
+            countdown_allow_skip: false,
+            raining: false,
+            wetness: 0.0,
+            attract_mode: false,
+            attract_restart: 0.0,
+            high_score: crossy_stats::CrossyStats::load().longest_run,
+            lane_history: Vec::new(),
+            death_lane: None,
+            style_streaks: StyleStreaks::default(),
+            particles: Particles::new(),
+            death_cause: None,
+            run_seconds: 0.0,
+            intensity: 0.0,
+            emitted_intensity: 0.0,
+            emitted_status: (0, 0, 0, false, None),
+            day_offset: day_phase(world_seed),
+            death_review: 0.0,
+            photo_mode: false,
+            tick_accumulator: 0.0,
+            before_tick: TickPositions::default(),
+            after_tick: TickPositions::default(),
+            time_of_day_override: None,
+            custom_level: None,
+            collision,
+            difficulty,
+            theme: None,
+            slide: 0.0,
+            snow_mesh: Mesh::cuboids(&[([-0.5, -0.5, -0.5], [0.5, 0.5, 0.5])]),
+            touch_overlay: None,
+            audio: Audio::new(),
+        }
+    }
+
+    // A sample from load_sound when there is one, a synthesized stand-in otherwise
+    fn play_coin(&self) {
+        if let Some(audio) = &self.audio {
+            if !audio.play("coin", 0.5) {
+                audio.blip(OscillatorType::Square, 1320.0, 0.12, 0.12);
+            }
+        }
+    }
+
+    fn play_death(&self) {
+        if let Some(audio) = &self.audio {
+            if !audio.play("death", 0.6) {
+                audio.noise_burst(BiquadFilterType::Lowpass, 600.0, 0.4, 0.4);
+                audio.blip(OscillatorType::Sawtooth, 110.0, 0.4, 0.15);
+            }
+        }
+    }
+
+    pub fn set_rain(&mut self, raining: bool) {
+        self.raining = raining;
+    }
+
+    pub fn set_countdown(&mut self, seconds: f32, allow_skip: bool) {
+        self.countdown_length = seconds.max(0.0);
+        self.countdown_allow_skip = allow_skip;
+        // Still in the ready phase: restart it with the new length
+        if self.countdown > 0.0 {
+            self.countdown = self.countdown_length;
+        }
+    }
+
+    fn tick_countdown(&mut self) {
+        let shown = self.countdown.ceil() as i32;
+        self.countdown = (self.countdown - SIM_DT).max(0.0);
+        let now = self.countdown.ceil() as i32;
+        if now != shown {
+            events::emit("countdown", serde_json::json!(now));
+        }
+    }
+
+    // Input during the ready phase is swallowed, the first press ends it if skipping is allowed
+    fn countdown_blocks_input(&mut self) -> bool {
+        if self.countdown <= 0.0 {
+            return false;
+        }
+        if self.countdown_allow_skip {
+            self.countdown = 0.0;
+            events::emit("countdown", serde_json::json!(0));
+        }
+        true
+    }
+
+    pub fn traffic_mode(&self) -> TrafficMode {
+        if self.photo_mode {
+            TrafficMode::Frozen
+        } else if self.game_over {
+            if self.death_review > 0.0 { TrafficMode::NoWrap } else { TrafficMode::Frozen }
+        } else {
+            TrafficMode::Normal
+        }
+    }
+
+    fn advance_traffic(&mut self) {
+        let (mode, wrap) = (self.traffic_mode(), self.wrap_x());
+        self.rail_clock += SIM_DT * traffic_speed(mode);
+        step_traffic(&mut self.lanes, mode, self.world_seed, wrap, self.rail_clock);
+    }
+
+    pub fn wrap_x(&self) -> f32 {
+        wrap_bound(self.half_width)
+    }
+
+    fn visible_half_width(&self) -> f32 {
+        self.half_width + 2.0
+    }
+
+    // Takes effect immediately with a fresh run on the same seed
+    pub fn set_half_width(&mut self, half_width: f32) {
+        self.half_width = half_width.clamp(MIN_HALF_WIDTH, MAX_HALF_WIDTH);
+        self.reset_run(self.world_seed);
+    }
+
+    pub fn start_tournament(&mut self, seed: u32, target_lane: i32) {
+        self.tournament = Some(Tournament {
+            target_lane: target_lane.max(3),
+            elapsed_ticks: 0,
+            deaths: 0,
+        });
+        self.reset_run(seed);
+    }
+
+    // The game plays itself until real input arrives
+    pub fn start_attract_mode(&mut self) {
+        self.tournament = None;
+        self.attract_mode = true;
+        events::emit("attract_mode", serde_json::json!(true));
+        self.start_new_run();
+    }
+
+    // Returns true when the input was spent leaving attract mode for a normal run
+    fn exit_attract_mode(&mut self) -> bool {
+        if !self.attract_mode {
+            return false;
+        }
+        self.attract_mode = false;
+        events::emit("attract_mode", serde_json::json!(false));
+        self.start_new_run();
+        true
+    }
+
+    fn attract_step(&mut self) {
+        if self.game_over {
+            self.attract_restart -= SIM_DT;
+            if self.attract_restart <= 0.0 {
+                self.start_new_run();
+            }
+            return;
+        }
+        if self.moving {
+            return;
+        }
+        match crossy_ai::choose_move(&self.player, &self.lanes, self.half_width) {
+            Some(crossy_ai::AiMove::Forward) => self.hop_forward(),
+            Some(crossy_ai::AiMove::Left) => self.hop_left(),
+            Some(crossy_ai::AiMove::Right) => self.hop_right(),
+            None => {}
         }
     }
 
-    pub fn update(&mut self) {
+    fn create_lane(&self, index: i32) -> Lane {
+        let mut lane = match &self.tournament {
+            Some(t) if index == t.target_lane => create_finish_lane(index as f32 * 2.0),
+            _ => self.custom_level.as_ref()
+                .and_then(|level| level.lane(index, self.world_seed, self.half_width))
+                .unwrap_or_else(|| create_lane_procedural(index as f32 * 2.0, index, self.world_seed, self.half_width, &self.difficulty)),
+        };
+        if let Some(theme) = self.theme {
+            theme.dress_lane(&mut lane);
+        }
+        lane
+    }
+
+    // Restarts under the event theme, which stays on until a new game is started
+    pub fn start_event(&mut self, theme: &'static ThemeDef) {
+        self.theme = Some(theme);
+        self.palette = theme.apply(Palette::from_name(&Settings::load().crossy_palette));
+        self.player.color = self.palette.player;
+        self.reset_run(self.world_seed);
+    }
+
+    // Restarts on the scripted lanes, later restarts replay them until a new game is started
+    pub fn load_custom_level(&mut self, level: CustomLevel) {
+        self.custom_level = Some(level);
+        self.reset_run(self.world_seed);
+    }
+
+    fn finish_time_ms(&self) -> Option<f64> {
+        self.tournament.as_ref().map(|t| t.elapsed_ticks as f64 * SIM_DT as f64 * 1000.0)
+    }
+
+    fn record_run_summary(&mut self) {
+        self.last_run_summary = Some(RunSummary {
+            seed: self.world_seed,
+            score: self.score,
+            coins: self.coins,
+            gems: self.gems,
+            deaths: self.tournament.as_ref().map(|t| t.deaths).unwrap_or(0),
+            finish_time_ms: if self.finished { self.finish_time_ms() } else { None },
+            cause: self.death_cause,
+            play_seconds: self.run_seconds,
+            theme: self.theme.map(|theme| theme.key),
+        });
+
+        // Demo runs aren't the player's
+        if !self.attract_mode {
+            if let Some(summary) = &self.last_run_summary {
+                let mut stats = crossy_stats::CrossyStats::load();
+                stats.record_run(summary);
+                stats.save();
+                self.high_score = stats.longest_run;
+            }
+        }
+    }
+
+    // Exponential smoothing so layers crossfade instead of flickering
+    fn update_intensity(&mut self) {
+        let target = crossy_intensity(&self.lanes, &self.player, &self.difficulty);
+        let blend = 1.0 - (-SIM_DT / INTENSITY_SMOOTHING_SECONDS).exp();
+        self.intensity += (target - self.intensity) * blend;
+        if (self.intensity - self.emitted_intensity).abs() >= INTENSITY_EVENT_STEP {
+            self.emitted_intensity = self.intensity;
+            events::emit("intensity", serde_json::json!(self.intensity));
+        }
+    }
+
+    fn track_log_ride(&mut self, lane: i32, riding: Option<usize>) {
+        if self.style_streaks.ride(lane, riding, self.moving) {
+            self.award_style_bonus("patient", "Patient!");
+        }
+    }
+
+    fn track_water_landing(&mut self, lane_type: LaneType) {
+        if self.style_streaks.land(lane_type, self.time) {
+            self.award_style_bonus("rapid_crossing", "Rapid crossing");
+        }
+    }
+
+    fn award_style_bonus(&mut self, id: &str, label: &str) {
+        self.coins += STYLE_BONUS_COINS;
+        events::emit("style_bonus", serde_json::json!({ "id": id, "label": label, "coins": STYLE_BONUS_COINS }));
+        self.particles.burst((self.player.x, self.player.y + 0.5, self.player.z), self.palette.coin, &particles::SPARKLE);
+    }
+
+    // Burst for whatever just killed the player
+    fn emit_death_particles(&mut self) {
+        let origin = (self.player.x, self.base_y, self.player.z);
+        match self.death_cause {
+            Some("water") => self.particles.burst(origin, self.palette.foam, &particles::SPLASH),
+            Some("car") | Some("train") => self.particles.burst(origin, self.player.color, &particles::DEBRIS),
+            _ => {}
+        }
+    }
+
+    pub fn snapshot(&self) -> CrossySnapshot {
+        CrossySnapshot {
+            score: self.score,
+            coins: self.coins,
+            gems: self.gems,
+            game_over: self.game_over,
+            player: PlayerPosition { x: self.player.x, z: self.player.z },
+        }
+    }
+
+    // None while a run is still going
+    pub fn share_text(&self) -> Option<String> {
+        if !self.game_over && !self.finished {
+            return None;
+        }
+        let now = js_sys::Date::new_0();
+        let date = format!("{}-{:02}-{:02}", now.get_full_year(), now.get_month() + 1, now.get_date());
+        let theme = self.theme.map(|theme| theme.key);
+        Some(format_share_text(&date, self.score, self.coins, &self.lane_history, self.death_lane, theme))
+    }
+
+    // Position in the day cycle, 0..DAY_SECONDS
+    pub fn time_of_day(&self) -> f32 {
+        self.time_of_day_override.unwrap_or_else(|| (self.time + self.day_offset).rem_euclid(DAY_SECONDS))
+    }
+
+    pub fn set_time_of_day(&mut self, t: f32) {
+        self.time_of_day_override = Some(t.clamp(0.0, DAY_SECONDS));
+    }
+
+    // The cycle carries on from the overridden position rather than jumping back
+    pub fn clear_time_override(&mut self) {
+        if let Some(t) = self.time_of_day_override.take() {
+            self.day_offset = t - self.time;
+        }
+    }
+
+    pub fn set_palette(&mut self, name: &str) {
+        self.palette = Palette::from_name(name);
+        if let Some(theme) = self.theme {
+            self.palette = theme.apply(self.palette.clone());
+        }
+        self.player.color = self.palette.player;
+        let palette_name = self.palette.name.to_string();
+        Settings::update(|s| s.crossy_palette = palette_name);
+    }
+
+    // dt is the real time since the last frame in seconds
+    pub fn update(&mut self, dt: f64) {
+        let (ticks, accumulator) = ticks_for(self.tick_accumulator, dt);
+        self.tick_accumulator = accumulator;
+        for _ in 0..ticks {
+            let mut before = std::mem::take(&mut self.before_tick);
+            before.record(self);
+            self.before_tick = before;
+            self.tick();
+        }
+        self.emit_status();
+    }
+
+    // Sent once per change rather than every frame. Moves happen between updates, so
+    // this runs after the ticks instead of inside them.
+    fn emit_status(&mut self) {
+        let (score, coins, gems, game_over, power_up) = self.emitted_status;
+        if self.score != score {
+            events::emit("score", serde_json::json!(self.score));
+        }
+        if self.coins != coins {
+            events::emit("coins", serde_json::json!(self.coins));
+        }
+        if self.gems != gems {
+            events::emit("gems", serde_json::json!(self.gems));
+        }
+        if self.game_over != game_over {
+            events::emit("game_over", serde_json::json!(self.game_over));
+        }
+        if self.power_ups.held != power_up {
+            events::emit("power_up", serde_json::json!(self.power_ups.held.map(PowerUpKind::name)));
+        }
+        self.emitted_status = (self.score, self.coins, self.gems, self.game_over, self.power_ups.held);
+    }
+
+    fn tick(&mut self) {
+        // Photo mode holds the whole scene, animations included
+        if self.photo_mode {
+            return;
+        }
+
         // Always update time for animations
-        self.time += 0.016; // ~60fps
-        
+        self.time += SIM_DT;
+
+        let target_wetness = if self.raining { 1.0 } else { 0.0 };
+        let step = SIM_DT / WETNESS_FADE_SECONDS;
+        self.wetness += (target_wetness - self.wetness).clamp(-step, step);
+        self.update_camera();
+
+        if self.finished {
+            return;
+        }
+
+        // Ready phase: traffic moves, the player is frozen and can't die
+        if self.countdown > 0.0 {
+            self.tick_countdown();
+            self.advance_traffic();
+            return;
+        }
+
+        if self.attract_mode {
+            self.attract_step();
+            if self.game_over {
+                return;
+            }
+        }
+
+        // The tournament clock keeps running while dead, restarts cost time
+        if let Some(t) = self.tournament.as_mut() {
+            t.elapsed_ticks += 1;
+        }
+
+        self.particles.update(SIM_DT);
         if self.game_over {
+            if self.death_review > 0.0 {
+                self.advance_traffic();
+                self.death_review -= SIM_DT;
+            }
             return;
         }
+        self.run_seconds += SIM_DT;
 
+        let was_moving = self.moving;
         if self.moving {
-            let speed = 0.15;
-            self.jump_progress += speed / 2.0;
+            self.jump_progress += HOP_SPEED / 2.0;
             
             let jump_height = 1.5;
             let jump_y = (self.jump_progress * std::f32::consts::PI).sin() * jump_height;
             self.player.y = self.base_y + jump_y;
-            
-            match self.move_direction {
-                0 => {
-                    self.player.z += speed;
-                    if self.player.z >= self.target_z {
-                        self.player.z = self.target_z;
-                        self.moving = false;
-                        self.jump_progress = 0.0;
-                        self.player.y = self.base_y;
-                    }
-                }
-                2 => {
-                    self.player.x -= speed;
-                    if self.player.x <= self.target_x {
-                        self.player.x = self.target_x;
-                        self.moving = false;
-                        self.jump_progress = 0.0;
-                        self.player.y = self.base_y;
-                    }
-                }
-                3 => {
-                    self.player.x += speed;
-                    if self.player.x >= self.target_x {
-                        self.player.x = self.target_x;
-                        self.moving = false;
-                        self.jump_progress = 0.0;
-                        self.player.y = self.base_y;
-                    }
-                }
-                _ => {}
+
+            if step_hop(&mut self.player, self.move_direction, self.target_x, self.target_z) {
+                self.moving = false;
+                self.jump_progress = 0.0;
+                self.player.y = self.base_y;
             }
         }
+        if was_moving && !self.moving {
+            self.particles.burst((self.player.x, self.base_y - 0.4, self.player.z), self.palette.dirt_patch, &particles::DUST);
+            self.snap_to_log();
+            self.start_slide();
+        }
+        self.advance_slide();
 
         // Generate new lanes ahead as player advances (infinite world)
         let player_lane_idx = (self.player.z / 2.0).round() as i32;
-        while self.furthest_lane < player_lane_idx + 20 {
+        let last_lane = self.tournament.as_ref().map(|t| t.target_lane).unwrap_or(i32::MAX);
+        while self.furthest_lane < player_lane_idx + 20 && self.furthest_lane < last_lane {
             self.furthest_lane += 1;
-            let new_lane = create_lane_procedural(
-                self.furthest_lane as f32 * 2.0,
-                self.furthest_lane,
-                self.world_seed
-            );
+            let new_lane = self.create_lane(self.furthest_lane);
             self.lanes.push(new_lane);
         }
         
-        // Remove lanes far behind the player to save memory
+        // Remove lanes far behind the furthest point reached to save memory. Lanes the
+        // player stepped back onto stay, the kill boundary is closer than this.
+        if self.player.z > self.camera_min_z {
+            self.idle_timer = 0.0;
+        } else {
+            self.idle_timer += SIM_DT;
+        }
+        self.camera_min_z = self.camera_min_z.max(self.player.z);
+        let camera_lane_idx = (self.camera_min_z / 2.0).round() as i32;
         self.lanes.retain(|lane| {
             let lane_idx = (lane.z / 2.0).round() as i32;
-            lane_idx > player_lane_idx - 10
+            lane_idx > camera_lane_idx - LANES_KEPT_BEHIND
         });
 
-        for lane in &mut self.lanes {
-            for obstacle in &mut lane.obstacles {
-                obstacle.x += obstacle.velocity_x;
-                if obstacle.x > 15.0 {
-                    obstacle.x = -15.0;
-                }
-                if obstacle.x < -15.0 {
-                    obstacle.x = 15.0;
-                }
-            }
-
-            for coin in &mut lane.coins {
-                coin.x += coin.velocity_x;
-                if coin.x > 15.0 {
-                    coin.x = -15.0;
-                }
-                if coin.x < -15.0 {
-                    coin.x = 15.0;
-                }
-            }
+        self.advance_traffic();
+        self.update_power_ups();
 
+        let coins_before = self.coins;
+        let coin_value = self.theme.map_or(1, |theme| theme.coin_value);
+        for lane in &mut self.lanes {
             // Check coin collisions
-            let mut coins_collected = 0;
             lane.coins.retain(|coin| {
-                if self.player.collides_horizontal(coin) {
-                    coins_collected += 1;
-                    false
-                } else {
-                    true
+                if !self.player.collides_horizontal(coin, &self.collision) {
+                    return true;
                 }
+                let kind = coin.coin_kind();
+                self.coins += kind.value() * coin_value;
+                if kind != CoinKind::Gold {
+                    self.gems += 1;
+                    events::emit("gem_collected", serde_json::json!({ "kind": kind.name(), "value": kind.value() * coin_value }));
+                }
+                self.particles.burst((coin.x, coin.y + 0.2, coin.z), kind.color(&self.palette), &particles::SPARKLE);
+                false
             });
-            self.coins += coins_collected;
         }
+        if self.coins > coins_before && !self.attract_mode {
+            self.play_coin();
+        }
+
+        let mut landed_new_lane = None;
+        let mut riding = None;
 
         // Find the lane at player's position
         let player_lane = self.lanes.iter().find(|lane| {
@@ -237,83 +1592,176 @@ impl Game {
         });
 
         if let Some(lane) = player_lane {
-            if !self.moving {
-                match lane.lane_type {
-                    LaneType::Road => {
-                        for obstacle in &lane.obstacles {
-                            if self.player.collides_horizontal(obstacle) && !self.invincible {
-                                self.game_over = true;
-                            }
-                        }
-                    }
-                    LaneType::Water => {
-                        let on_log = lane.obstacles.iter()
-                            .any(|o| self.player.collides_horizontal(o));
-                        if !on_log && !self.invincible {
-                            self.game_over = true;
-                        }
-                    }
-                    _ => {}
-                }
+            if !self.moving && record_lane(&mut self.lane_history, player_lane_idx, lane.lane_type) {
+                landed_new_lane = Some(lane.lane_type);
             }
-            
-            // Move player with log
-            if let LaneType::Water = lane.lane_type {
-                for obstacle in &lane.obstacles {
-                    if self.player.collides_horizontal(obstacle) {
-                        self.player.x += obstacle.velocity_x;
-                    }
+            let contact = touch_lane(lane, &mut self.player, self.moving, self.half_width, &self.collision);
+            match contact.hazard {
+                // God mode doesn't help against trains
+                Some("train") => {
+                    self.game_over = true;
+                    self.death_cause = Some("train");
+                }
+                _ if self.invincible => {}
+                Some("car") if self.power_ups.shield_grace > 0.0 => {}
+                Some("car") if self.power_ups.held == Some(PowerUpKind::Shield) => {
+                    self.power_ups.use_shield();
+                    overlay::set_flash(overlay::DAMAGE_COLOR, 0.25, 0.3);
                 }
+                Some(cause) => {
+                    self.game_over = true;
+                    self.death_cause = Some(cause);
+                }
+                None => {}
             }
+            riding = contact.riding;
+        }
+
+        if !self.moving && !self.invincible && !self.game_over && left_behind(self.camera_min_z, self.player.z) {
+            self.game_over = true;
+            self.death_cause = Some("camera");
         }
 
-        self.player.x = self.player.x.clamp(-10.0, 10.0);
+        self.update_eagle();
+
+        if let Some(lane_type) = landed_new_lane {
+            self.track_water_landing(lane_type);
+        }
+        self.track_log_ride(player_lane_idx, riding);
+        self.update_intensity();
+
+        self.player.x = clamp_to_field(self.player.x, self.half_width);
 
         let new_score = (self.player.z / 2.0) as i32;
         if new_score > self.score {
             self.score = new_score;
         }
+
+        if self.game_over {
+            if let Some(t) = self.tournament.as_mut() {
+                t.deaths += 1;
+            }
+            self.attract_restart = ATTRACT_RESTART_SECONDS;
+            self.death_review = DEATH_REVIEW_SECONDS;
+            self.death_lane = Some(player_lane_idx.max(0) as usize);
+            self.emit_death_particles();
+            if !self.attract_mode {
+                overlay::set_flash(overlay::DAMAGE_COLOR, 0.5, 0.6);
+                self.play_death();
+            }
+            self.style_streaks = StyleStreaks::default();
+            self.record_run_summary();
+            return;
+        }
+
+        let reached_finish = self.tournament.as_ref()
+            .map(|t| !self.moving && player_lane_idx >= t.target_lane)
+            .unwrap_or(false);
+        if reached_finish {
+            self.finished = true;
+            self.record_run_summary();
+            if let Some(summary) = &self.last_run_summary {
+                events::emit("tournament_finished", serde_json::to_value(summary).unwrap_or_default());
+            }
+        }
     }
 
-    pub fn render(&self) {
-        let biome_idx = (self.player.z / 100.0).floor() as i32;
-        let (mut bg_r, mut bg_g, mut bg_b) = match biome_idx % 3 {
-            0 => (0.2, 0.6, 1.0),
-            1 => (1.0, 0.6, 0.2),
-            _ => (0.1, 0.1, 0.3),
+    // Picks up power-ups the player touches and runs the active one down. A new pickup
+    // replaces whatever was held.
+    fn update_power_ups(&mut self) {
+        let mut picked = None;
+        for lane in &mut self.lanes {
+            lane.power_ups.retain(|power_up| {
+                if !self.player.collides_horizontal(power_up, &self.collision) {
+                    return true;
+                }
+                if let ObjectKind::PowerUp(kind) = power_up.kind {
+                    picked = Some(kind);
+                }
+                false
+            });
+        }
+        if let Some(kind) = picked {
+            self.power_ups.pick(kind);
+        }
+        let magnet = self.power_ups.tick();
+        attract_coins(&mut self.lanes, (self.player.x, self.player.z), magnet);
+    }
+
+    pub fn power_up_hud(&self) -> Option<(PowerUpKind, i32)> {
+        self.power_ups.hud()
+    }
+
+    fn idle_seconds(&self) -> f32 {
+        self.config.as_ref().map_or(DEFAULT_IDLE_SECONDS, |c| c.idle_seconds)
+    }
+
+    // Comes once the player stalls and flies through wherever they stand then. God mode
+    // lets it pass harmlessly.
+    fn update_eagle(&mut self) {
+        let idle_seconds = self.idle_seconds();
+        if self.eagle.is_none() && idle_seconds > 0.0 && self.idle_timer >= idle_seconds {
+            let eagle = GameObject::new(self.player.x, self.base_y + EAGLE_HEIGHT, self.player.z + EAGLE_START_DISTANCE, 1.2, 0.5, 1.0, (0.35, 0.22, 0.12));
+            self.eagle = Some(eagle);
+        }
+        let eagle = match self.eagle.as_mut() {
+            Some(eagle) => eagle,
+            None => return,
         };
+        let was_ahead = eagle.z > self.player.z;
+        eagle.z -= EAGLE_SPEED * SIM_DT;
+        eagle.y = self.base_y + EAGLE_HEIGHT * ((eagle.z - self.player.z).abs() / EAGLE_START_DISTANCE).min(1.0);
+        if was_ahead && eagle.z <= self.player.z && !self.invincible {
+            eagle.x = self.player.x;
+            eagle.z = self.player.z;
+            self.game_over = true;
+            self.death_cause = Some("eagle");
+        } else if eagle.z < self.camera_min_z - EAGLE_START_DISTANCE {
+            self.eagle = None;
+            self.idle_timer = 0.0;
+        }
+    }
 
-        // Time of day cycle (60s)
-        let cycle = self.time % 60.0;
-        let (time_r, time_g, time_b) = if cycle < 25.0 {
-            // Day
-            (1.0, 1.0, 1.0)
-        } else if cycle < 35.0 {
-            // Afternoon transition
-            let t = (cycle - 25.0) / 10.0;
-            (1.0, 1.0 - t * 0.3, 1.0 - t * 0.5)
-        } else if cycle < 50.0 {
-            // Night transition
-            let t = (cycle - 35.0) / 15.0;
-            (
-                1.0 - t * 0.7,
-                0.7 - t * 0.5,
-                0.5 - t * 0.1
-            )
-        } else {
-            // Sunrise transition
-            let t = (cycle - 50.0) / 10.0;
-            (
-                0.3 + t * 0.7,
-                0.2 + t * 0.8,
-                0.4 + t * 0.6
-            )
+    pub fn render(&mut self) {
+        self.draw_blended(true);
+    }
+
+    // Draws a frame for the caller to read back before the browser clears it. hide_ui
+    // leaves out the touch buttons and any screen flash or tint.
+    pub fn draw_capture(&mut self, hide_ui: bool) {
+        self.draw_blended(!hide_ui);
+        if !hide_ui {
+            overlay::draw(&self.renderer);
+        }
+    }
+
+    // Draws the leftover real time's share of the way from the tick before the last to the
+    // last one, then puts the ticked positions back for the next update
+    fn draw_blended(&mut self, show_ui: bool) {
+        let t = (self.tick_accumulator / TICK_SECONDS) as f32;
+        let mut after = std::mem::take(&mut self.after_tick);
+        after.record(self);
+        let before = std::mem::take(&mut self.before_tick);
+        before.blend_into(self, t);
+        self.draw_frame(show_ui);
+        after.restore(self);
+        self.before_tick = before;
+        self.after_tick = after;
+    }
+
+    fn draw_frame(&self, show_ui: bool) {
+        let biome_idx = (self.player.z / 100.0).floor() as i32;
+        let (mut bg_r, mut bg_g, mut bg_b) = match (self.theme.and_then(|theme| theme.background), biome_idx % 3) {
+            (Some(background), _) => background,
+            (None, 0) => (0.2, 0.6, 1.0),
+            (None, 1) => (1.0, 0.6, 0.2),
+            (None, _) => (0.1, 0.1, 0.3),
         };
 
+        let (time_r, time_g, time_b) = day_tint(self.time_of_day());
+
         if self.game_over {
-            bg_r = 0.8;
-            bg_g = 0.1;
-            bg_b = 0.1;
+            (bg_r, bg_g, bg_b) = self.palette.game_over_bg;
         } else {
             bg_r *= time_r;
             bg_g *= time_g;
@@ -339,46 +1787,81 @@ impl Game {
             0.0
         };
 
-        let eye = Vector3::new(self.player.x, 15.0 + zoom_offset, self.player.z - 10.0 - zoom_offset);
-        let target = Vector3::new(self.player.x, 0.0, self.player.z + 5.0);
+        // Wider fields pull the camera up and back to keep both edges in view, however wide
+        let pull_back = (self.half_width / DEFAULT_HALF_WIDTH).max(0.7);
+        let mut target = Vector3::new(self.cam_x, 0.0, self.cam_z + 5.0);
+        let mut eye = Vector3::new(self.cam_x, 15.0 * pull_back + zoom_offset, self.cam_z - 10.0 * pull_back - zoom_offset);
+        // The death zoom turns the view onto the crash and closes in on it
+        if let Some((x, z)) = self.death_pos {
+            let crash = Vector3::new(x, 0.0, z);
+            target += (crash - target) * self.death_zoom;
+            eye += (crash - eye) * self.death_zoom * DEATH_ZOOM_AMOUNT;
+        }
         let up = Vector3::new(0.0, 1.0, 0.0);
         let view = Matrix4::look_at_rh(&eye.into(), &target.into(), &up);
 
+        let palette = &self.palette;
+        let lane_width = self.visible_half_width() * 2.0;
         for lane in &self.lanes {
             match lane.lane_type {
                 LaneType::Grass => {
                     // Draw grass base
                     self.renderer.draw_cube(
                         0.0, -0.5, lane.z,
-                        24.0, 0.5, 2.0,
-                        0.22, 0.5, 0.22,
+                        lane_width, 0.5, 2.0,
+                        palette.grass.0, palette.grass.1, palette.grass.2,
                         &projection, &view
                     );
                     // Draw procedural grass details
-                    self.draw_grass_details(lane.z, &projection, &view);
+                    self.draw_grass_details(lane, &projection, &view);
                 }
                 LaneType::Road => {
+                    // Wet asphalt darkens
+                    let wet = 1.0 - self.wetness * 0.35;
                     self.renderer.draw_cube(
                         0.0, -0.5, lane.z,
-                        24.0, 0.5, 2.0,
-                        0.3, 0.3, 0.3,
+                        lane_width, 0.5, 2.0,
+                        palette.road.0 * wet, palette.road.1 * wet, palette.road.2 * wet,
                         &projection, &view
                     );
+                    if self.wetness > 0.0 {
+                        self.draw_puddles(lane, (bg_r, bg_g, bg_b), &projection, &view);
+                    }
                     self.draw_road_markings(lane.z, &projection, &view);
                 }
                 LaneType::Water => {
                     self.renderer.draw_cube(
                         0.0, -0.5, lane.z,
-                        24.0, 0.5, 2.0,
-                        0.2, 0.4, 0.8,
+                        lane_width, 0.5, 2.0,
+                        palette.water.0, palette.water.1, palette.water.2,
                         &projection, &view
                     );
                     // Add water details
-                    self.draw_water_details(lane.z, &projection, &view);
+                    self.draw_water_details(lane, &projection, &view);
+                }
+                LaneType::Finish => {
+                    self.renderer.draw_cube(
+                        0.0, -0.5, lane.z,
+                        lane_width, 0.5, 2.0,
+                        0.95, 0.95, 0.95,
+                        &projection, &view
+                    );
+                    self.draw_finish_line(lane.z, &projection, &view);
+                }
+                LaneType::Rail => {
+                    self.renderer.draw_cube(
+                        0.0, -0.5, lane.z,
+                        lane_width, 0.5, 2.0,
+                        0.45, 0.41, 0.38,
+                        &projection, &view
+                    );
+                    self.draw_rails(lane.z, &projection, &view);
                 }
             }
         }
 
+        self.draw_decorations((time_r + time_g + time_b) / 3.0, &projection, &view);
+
         for lane in &self.lanes {
             for obstacle in &lane.obstacles {
                 self.draw_shadow(
@@ -406,14 +1889,24 @@ impl Game {
             shadow_alpha.max(0.1),
             &projection, &view
         );
+        self.draw_eagle(&projection, &view);
 
         for lane in &self.lanes {
             for obstacle in &lane.obstacles {
+                if obstacle.kind == ObjectKind::LilyPad {
+                    self.draw_lily_pad(obstacle.x, obstacle.z, obstacle.width, (obstacle.x * 7.0) as i32 % 3 == 0, &projection, &view);
+                    continue;
+                }
+                let is_log = obstacle.kind == ObjectKind::Log;
+                if palette.outline && (obstacle.is_car || is_log) {
+                    self.draw_outline(obstacle, &projection, &view);
+                }
                 if obstacle.is_car {
+                    let (car_r, car_g, car_b) = palette.car_color(obstacle.color_idx);
                     self.draw_car(
                         obstacle.x, obstacle.y, obstacle.z,
                         obstacle.width, obstacle.height, obstacle.depth,
-                        obstacle.color.0, obstacle.color.1, obstacle.color.2,
+                        car_r, car_g, car_b,
                         obstacle.velocity_x,
                         &projection, &view
                     );
@@ -427,48 +1920,136 @@ impl Game {
                 }
             }
             
+            for power_up in &lane.power_ups {
+                if let ObjectKind::PowerUp(kind) = power_up.kind {
+                    let pulse = (self.time * 2.0).sin() * 0.15 + 1.0;
+                    let (r, g, b) = kind.color();
+                    self.renderer.draw_cube(
+                        power_up.x, power_up.y + 0.2 + (self.time * 1.5).sin() * 0.1, power_up.z,
+                        power_up.width * pulse, power_up.height * pulse, power_up.depth * pulse,
+                        r, g, b,
+                        &projection, &view
+                    );
+                }
+            }
+
             for coin in &lane.coins {
-                let pulse = (self.time * 5.0).sin() * 0.1 + 1.0;
+                let kind = coin.coin_kind();
+                let spin = self.time * kind.spin();
+                let pulse = (spin * 5.0).sin() * 0.1 + 1.0;
+                let (r, g, b) = kind.color(palette);
                 self.renderer.draw_cube(
-                    coin.x, coin.y + 0.2 + (self.time * 3.0).sin() * 0.1, coin.z,
+                    coin.x, coin.y + 0.2 + (spin * 3.0).sin() * 0.1, coin.z,
                     coin.width * pulse, coin.height * pulse, coin.depth * pulse,
-                    1.0, 0.84, 0.0, // Gold
+                    r, g, b,
                     &projection, &view
                 );
+                if let Some(ribbon) = self.theme.and_then(|theme| theme.coin_ribbon).filter(|_| kind == CoinKind::Gold) {
+                    let (w, h, d) = (coin.width * pulse, coin.height * pulse, coin.depth * pulse);
+                    let y = coin.y + 0.2 + (spin * 3.0).sin() * 0.1;
+                    for (rw, rd) in [(w * 1.05, d * 0.25), (w * 0.25, d * 1.05)] {
+                        self.renderer.draw_cube(coin.x, y, coin.z, rw, h * 1.05, rd, ribbon.0, ribbon.1, ribbon.2, &projection, &view);
+                    }
+                }
             }
         }
 
-        let player_color = if self.game_over { (1.0, 0.2, 0.2) } else { self.player.color };
-        self.renderer.draw_cube(
-            self.player.x, self.player.y, self.player.z,
-            self.player.width, self.player.height, self.player.depth,
-            player_color.0, player_color.1, player_color.2,
-            &projection, &view
-        );
+        if !self.game_over {
+            self.draw_edge_warnings(&projection, &view);
+        }
+
+        self.draw_particles((time_r + time_g + time_b) / 3.0, &projection, &view);
+
+        let player_color = if self.game_over { palette.player_dead } else { self.player.color };
+        let blink_hidden = self.power_ups.shield_grace > 0.0 && (self.time * 20.0).sin() < 0.0;
+        if !blink_hidden {
+            self.draw_player(player_color, &projection, &view);
+        }
+        if self.power_ups.held == Some(PowerUpKind::Shield) && !self.game_over {
+            let (r, g, b) = PowerUpKind::Shield.color();
+            self.renderer.set_alpha(0.35);
+            self.renderer.draw_cube(
+                self.player.x, self.player.y, self.player.z,
+                self.player.width * 1.4, self.player.height * 1.3, self.player.depth * 1.4,
+                r, g, b,
+                &projection, &view
+            );
+            self.renderer.set_alpha(1.0);
+        }
+
+        if self.theme.is_some_and(|theme| theme.snowfall) {
+            self.draw_snow((time_r + time_g + time_b) / 3.0, &projection, &view);
+        }
+
+        if self.countdown > 0.0 {
+            let mut scratch = self.scratch.borrow_mut();
+            scratch.countdown.clear();
+            let _ = write!(scratch.countdown, "{}", self.countdown.ceil() as i32);
+            let text = scratch.countdown.as_str();
+            let size = height as f32 * 0.25;
+            let x = (width as f32 - Renderer::screen_text_width(text, size)) / 2.0;
+            let y = (height as f32 - size) / 2.0;
+            self.renderer.begin_screen_overlay();
+            self.renderer.draw_screen_text(text, x + size * 0.04, y + size * 0.04, size, (0.0, 0.0, 0.0), (width as f32, height as f32));
+            self.renderer.draw_screen_text(text, x, y, size, (1.0, 1.0, 1.0), (width as f32, height as f32));
+            self.renderer.end_screen_overlay();
+        }
+
+        if let Some(overlay) = self.touch_overlay.as_ref().filter(|_| show_ui && !self.photo_mode) {
+            overlay.draw(&self.renderer, width as f32, height as f32, device_pixel_ratio(), js_sys::Date::now());
+        }
         
         self.renderer.disable_blend();
     }
 
-    fn draw_grass_details(&self, z: f32, projection: &Matrix4<f32>, view: &Matrix4<f32>) {
-        let biome_idx = (z / 100.0).floor() as i32;
-        let seed = (z * 100.0) as i32;
-        
-        let rand = |s: i32, offset: i32| -> f32 {
-            let n = ((s.wrapping_add(offset)).wrapping_mul(1103515245).wrapping_add(12345)) as u32;
-            (n % 10000) as f32 / 10000.0
+    // Meshes are uploaded on every draw, so the renderer is all a lost context takes
+    pub fn restore_context(&mut self) -> Result<(), wasm_bindgen::JsValue> {
+        self.renderer.reinit()
+    }
+
+    pub fn set_touch_overlay(&mut self, enabled: bool) {
+        self.touch_overlay = if enabled { Some(TouchOverlay::default()) } else { None };
+    }
+
+    // A touch in canvas pixels, true when it landed on an overlay button
+    pub fn touch_at(&mut self, x: f32, y: f32) -> bool {
+        if self.photo_mode {
+            return false;
+        }
+        let (width, height) = match self.renderer.canvas() {
+            Some(canvas) => (canvas.width() as f32, canvas.height() as f32),
+            None => return false,
+        };
+        let button = match self.touch_overlay.as_mut() {
+            Some(overlay) => overlay.hit(x, y, width, height, device_pixel_ratio(), js_sys::Date::now()),
+            None => None,
         };
+        match button {
+            Some(TouchButton::Left) => self.move_left(),
+            Some(TouchButton::Right) => self.move_right(),
+            Some(TouchButton::Forward) => self.move_forward(),
+            Some(TouchButton::Restart) => self.restart(),
+            None => return false,
+        }
+        true
+    }
+
+    fn draw_grass_details(&self, lane: &Lane, projection: &Matrix4<f32>, view: &Matrix4<f32>) {
+        let LaneDetails::Grass { tufts, blades, patches, flowers, rock, mushroom } = &lane.details else {
+            return;
+        };
+        let z = lane.z;
+        let biome_idx = (z / 100.0).floor() as i32;
         
-        for i in 0..20 {
-            let r1 = rand(seed, i * 7);
-            let r2 = rand(seed, i * 13);
-            let r3 = rand(seed, i * 23);
-            
-            let x = -11.5 + (i as f32 * 1.2) + r1 * 0.6;
+        let left = -self.visible_half_width();
+        for (i, &[r1, r2, r3]) in tufts.iter().enumerate() {
+            let x = left + 0.5 + (i as f32 * 1.2) + r1 * 0.6;
             let z_offset = (r2 - 0.5) * 1.6;
             
-            let mut base_g = 0.45 + r3 * 0.25;
-            let mut base_r = 0.18 + r1 * 0.12;
-            let mut base_b = 0.12;
+            let tuft = self.palette.grass_tuft;
+            let mut base_g = tuft.1 + r3 * 0.25;
+            let mut base_r = tuft.0 + r1 * 0.12;
+            let mut base_b = tuft.2;
 
             if biome_idx % 3 == 1 {
                 base_r += 0.4;
@@ -488,20 +2069,16 @@ impl Game {
             );
         }
         
-        for i in 0..30 {
-            let r1 = rand(seed, i * 11 + 100);
-            let r2 = rand(seed, i * 17 + 100);
-            let r3 = rand(seed, i * 29 + 100);
-            let r4 = rand(seed, i * 37 + 100);
-            
-            let x = -11.0 + (i as f32 * 0.75) + r1 * 0.5;
+        for (i, &[r1, r2, r3, r4]) in blades.iter().enumerate() {
+            let x = left + 1.0 + (i as f32 * 0.75) + r1 * 0.5;
             let z_offset = (r2 - 0.5) * 1.7;
             
             let height = 0.08 + r3 * 0.12;
             
-            let mut g = 0.4 + r4 * 0.35;
-            let mut r = 0.15 + r1 * 0.15;
-            let mut b = 0.05 + r2 * 0.1;
+            let blade = self.palette.grass_blade;
+            let mut g = blade.1 + r4 * 0.35;
+            let mut r = blade.0 + r1 * 0.15;
+            let mut b = blade.2 + r2 * 0.1;
 
             if biome_idx % 3 == 1 {
                 r += 0.4;
@@ -522,37 +2099,29 @@ impl Game {
         }
         
         // Add darker dirt/ground patches
-        for i in 0..6 {
-            let r1 = rand(seed, i * 43 + 200);
-            let r2 = rand(seed, i * 47 + 200);
-            
-            let x = -10.0 + (i as f32 * 4.0) + r1 * 2.0;
+        for (i, &[r1, r2]) in patches.iter().enumerate() {
+            let x = -self.half_width + (i as f32 * 4.0) + r1 * 2.0;
             let z_offset = (r2 - 0.5) * 1.0;
             
             // Dark ground patch
             self.renderer.draw_cube(
                 x, -0.24, z + z_offset,
                 0.3 + r1 * 0.4, 0.02, 0.25 + r2 * 0.3,
-                0.25, 0.35, 0.15,
+                self.palette.dirt_patch.0, self.palette.dirt_patch.1, self.palette.dirt_patch.2,
                 projection, view
             );
         }
         
         // Add flowers and small plants
-        let num_flowers = ((seed.abs() % 4) + 1) as i32;
-        for i in 0..num_flowers {
-            let r1 = rand(seed, i * 53 + 300);
-            let r2 = rand(seed, i * 59 + 300);
-            let r3 = rand(seed, i * 67 + 300);
-            
-            let fx = -10.0 + r1 * 20.0;
+        for &[r1, r2, r3] in flowers {
+            let fx = (r1 * 2.0 - 1.0) * self.half_width;
             let fz = z + (r2 - 0.5) * 1.5;
             
             // Flower stem
             self.renderer.draw_cube(
                 fx, -0.18, fz,
                 0.03, 0.1, 0.03,
-                0.15, 0.5, 0.1,
+                self.palette.flower_stem.0, self.palette.flower_stem.1, self.palette.flower_stem.2,
                 projection, view
             );
             
@@ -560,14 +2129,8 @@ impl Game {
             self.draw_shadow(fx, fz, 0.12, 0.12, 0.2, projection, view);
             
             // Flower head - different colors
-            let flower_type = (r3 * 5.0) as i32;
-            let (fr, fg, fb) = match flower_type {
-                0 => (0.95, 0.95, 0.3),  // Yellow
-                1 => (0.95, 0.4, 0.4),   // Red
-                2 => (1.0, 1.0, 1.0),    // White
-                3 => (0.8, 0.5, 0.9),    // Purple
-                _ => (0.95, 0.6, 0.7),   // Pink
-            };
+            let flower_type = ((r3 * 5.0) as usize).min(4);
+            let (fr, fg, fb) = self.palette.flowers[flower_type];
             
             self.renderer.draw_cube(
                 fx, -0.12, fz,
@@ -578,16 +2141,14 @@ impl Game {
         }
         
         // Add small rocks occasionally
-        if seed % 7 == 0 {
-            let r1 = rand(seed, 400);
-            let r2 = rand(seed, 401);
-            let rx = -8.0 + r1 * 16.0;
+        if let Some([r1, r2]) = *rock {
+            let rx = (r1 * 2.0 - 1.0) * self.half_width * 0.8;
             let rz = z + (r2 - 0.5) * 1.2;
             
             self.renderer.draw_cube(
                 rx, -0.2, rz,
                 0.15 + r1 * 0.1, 0.1, 0.12 + r2 * 0.08,
-                0.5, 0.5, 0.48,
+                self.palette.rock.0, self.palette.rock.1, self.palette.rock.2,
                 projection, view
             );
             
@@ -596,69 +2157,198 @@ impl Game {
         }
         
         // Add small mushrooms rarely
-        if seed % 11 == 0 {
-            let r1 = rand(seed, 500);
-            let r2 = rand(seed, 501);
-            let mx = -6.0 + r1 * 12.0;
+        if let Some([r1, r2]) = *mushroom {
+            let mx = (r1 * 2.0 - 1.0) * self.half_width * 0.6;
             let mz = z + (r2 - 0.5) * 1.0;
             
             // Stem
             self.renderer.draw_cube(
                 mx, -0.2, mz,
                 0.04, 0.08, 0.04,
-                0.9, 0.85, 0.75,
+                self.palette.mushroom_stem.0, self.palette.mushroom_stem.1, self.palette.mushroom_stem.2,
                 projection, view
             );
             // Cap
             self.renderer.draw_cube(
-                mx, -0.14, mz,
-                0.1, 0.05, 0.1,
-                0.85, 0.2, 0.15,
+                mx, -0.14, mz,
+                0.1, 0.05, 0.1,
+                self.palette.mushroom_cap.0, self.palette.mushroom_cap.1, self.palette.mushroom_cap.2,
+                projection, view
+            );
+            
+            // Shadow for mushroom
+            self.draw_shadow(mx, mz, 0.12, 0.12, 0.2, projection, view);
+        }
+    }
+
+    // Chevrons at the lane edge for cars about to come on screen and reach the player
+    fn draw_edge_warnings(&self, projection: &Matrix4<f32>, view: &Matrix4<f32>) {
+        let player_lane_idx = (self.player.z / 2.0).round() as i32;
+        let reach = self.player.width / 2.0;
+        let pulse = 1.0 + 0.25 * (self.time * 12.0).sin();
+        let (r, g, b) = self.palette.warning;
+
+        for lane in &self.lanes {
+            let lane_idx = (lane.z / 2.0).round() as i32;
+            if !matches!(lane.lane_type, LaneType::Road) || (lane_idx != player_lane_idx && lane_idx != player_lane_idx + 1) {
+                continue;
+            }
+            for car in &lane.obstacles {
+                let off_screen = car.x.abs() - self.visible_half_width();
+                let inbound = car.x * car.velocity_x < 0.0;
+                if off_screen <= 0.0 || off_screen > EDGE_WARNING_DISTANCE || !inbound {
+                    continue;
+                }
+                if !reaches_column(car.x, car.velocity_x, reach + car.width / 2.0, self.player.x, EDGE_WARNING_SECONDS) {
+                    continue;
+                }
+
+                // Arrow points inward, tip towards the screen center
+                let side = car.x.signum();
+                let tip_x = side * (self.visible_half_width() - 0.6);
+                let arm = 0.25 * pulse;
+                self.renderer.draw_cube(tip_x, -0.2, lane.z, 0.15 * pulse, 0.05, 0.15 * pulse, r, g, b, projection, view);
+                for dz in [-arm, arm] {
+                    self.renderer.draw_cube(tip_x + side * arm, -0.2, lane.z + dz, 0.15 * pulse, 0.05, 0.15 * pulse, r, g, b, projection, view);
+                }
+            }
+        }
+    }
+
+    // Fences, signs and lamp posts in one instanced batch per part. Lit lamps brighten
+    // every decoration within reach through the instance light value.
+    fn draw_decorations(&self, brightness: f32, projection: &Matrix4<f32>, view: &Matrix4<f32>) {
+        let darkness = 1.0 - brightness;
+        let lit = darkness > LAMP_ON_DARKNESS;
+        let mut scratch = self.scratch.borrow_mut();
+        let FrameScratch { lamps, instances: data, .. } = &mut *scratch;
+        lamps.clear();
+        if lit {
+            lamps.extend(self.lanes.iter()
+                .flat_map(|lane| lane.decorations.iter().map(move |d| (d, lane.z)))
+                .filter(|(d, _)| d.kind == DecorationKind::LampPost)
+                .map(|(d, z)| (d.x, z + d.dz)));
+        }
+        let pool = |x: f32, z: f32| -> f32 {
+            lamps.iter()
+                .map(|(lx, lz)| (1.0 - ((x - lx).powi(2) + (z - lz).powi(2)).sqrt() / LAMP_RADIUS).max(0.0))
+                .sum::<f32>() * darkness * LAMP_BOOST
+        };
+
+        let light_pos = Vector3::new(self.player.x + 10.0, 30.0, self.player.z - 10.0);
+        for kind in DecorationKind::ALL {
+            for (part, (_, color)) in kind.parts().iter().enumerate() {
+                let glow = lit && kind == DecorationKind::LampPost && part == 1;
+                data.clear();
+                for lane in &self.lanes {
+                    for decoration in lane.decorations.iter().filter(|d| d.kind == kind) {
+                        let z = lane.z + decoration.dz;
+                        let light = if glow { LAMP_GLOW } else { brightness + pool(decoration.x, z) };
+                        data.extend_from_slice(&[decoration.x, 0.0, z, 1.0, color.0, color.1, color.2, light]);
+                    }
+                }
+                if data.is_empty() {
+                    continue;
+                }
+                if let Some(mesh) = self.decoration_meshes.get(&(kind, part)) {
+                    let count = (data.len() / 8) as i32;
+                    self.renderer.draw_instanced_mesh(mesh, data, count, projection, view, &light_pos, None);
+                }
+            }
+        }
+    }
+
+    // One instanced draw for every live particle, on the snow cube
+    fn draw_particles(&self, brightness: f32, projection: &Matrix4<f32>, view: &Matrix4<f32>) {
+        let mut scratch = self.scratch.borrow_mut();
+        let data = &mut scratch.instances;
+        self.particles.instance_data(brightness.max(0.6), data);
+        if data.is_empty() {
+            return;
+        }
+        if !self.renderer.supports_instancing() {
+            // One cube each, light already folded into the color the way the shader would
+            for p in data.chunks(8) {
+                let (size, light) = (p[3], p[7]);
+                self.renderer.draw_cube(p[0], p[1], p[2], size, size, size, p[4] * light, p[5] * light, p[6] * light, projection, view);
+            }
+            return;
+        }
+        let light_pos = Vector3::new(self.player.x + 10.0, 30.0, self.player.z - 10.0);
+        self.renderer.draw_instanced_mesh(&self.snow_mesh, data, (data.len() / 8) as i32, projection, view, &light_pos, None);
+    }
+
+    // Flakes are a pure function of time, anchored to the world and wrapped through a box
+    // that follows the player, so nothing needs simulating
+    fn draw_snow(&self, brightness: f32, projection: &Matrix4<f32>, view: &Matrix4<f32>) {
+        let width = self.visible_half_width() * 2.0 + 4.0;
+        let mut scratch = self.scratch.borrow_mut();
+        let data = &mut scratch.instances;
+        data.clear();
+        for i in 0..SNOW_FLAKES {
+            let (r1, r2, r3) = (proc_rand(0x5e0, i, 1), proc_rand(0x5e0, i, 2), proc_rand(0x5e0, i, 3));
+            let fall = (self.time * (0.8 + r3 * 0.6) + r1 * SNOW_HEIGHT).rem_euclid(SNOW_HEIGHT);
+            let sway = (self.time * 0.7 + r2 * std::f32::consts::TAU).sin() * 0.3;
+            let x = self.player.x - width / 2.0 + (r1 * width - self.player.x).rem_euclid(width) + sway;
+            let z = self.player.z - 6.0 + (r2 * SNOW_DEPTH - self.player.z).rem_euclid(SNOW_DEPTH);
+            let size = 0.06 + r3 * 0.05;
+            data.extend_from_slice(&[x, SNOW_HEIGHT - fall, z, size, 1.0, 1.0, 1.0, brightness.max(0.6)]);
+        }
+        let light_pos = Vector3::new(self.player.x + 10.0, 30.0, self.player.z - 10.0);
+        self.renderer.draw_instanced_mesh(&self.snow_mesh, data, SNOW_FLAKES, projection, view, &light_pos, None);
+    }
+
+    // Flat puddles mirroring the sky
+    fn draw_puddles(&self, lane: &Lane, sky: (f32, f32, f32), projection: &Matrix4<f32>, view: &Matrix4<f32>) {
+        let reflect = |c: f32| (c * 1.2 + 0.15).min(1.0);
+
+        for puddle in &lane.puddles {
+            let shimmer = 0.45 + 0.15 * (self.time * 3.0 + puddle.x).sin();
+
+            self.renderer.set_alpha(shimmer * self.wetness);
+            self.renderer.draw_cube(
+                puddle.x, -0.245, lane.z + puddle.dz,
+                puddle.width, 0.01, puddle.depth,
+                reflect(sky.0), reflect(sky.1), reflect(sky.2),
                 projection, view
             );
-            
-            // Shadow for mushroom
-            self.draw_shadow(mx, mz, 0.12, 0.12, 0.2, projection, view);
         }
+        self.renderer.set_alpha(1.0);
     }
 
     fn draw_road_markings(&self, z: f32, projection: &Matrix4<f32>, view: &Matrix4<f32>) {
         // Draw edge lines only (no center dashed lines)
+        let (lr, lg, lb) = self.palette.road_line;
         self.renderer.draw_cube(
             0.0, -0.24, z + 0.9,
-            24.0, 0.02, 0.08,
-            0.85, 0.85, 0.5,
+            self.visible_half_width() * 2.0, 0.02, 0.08,
+            lr, lg, lb,
             projection, view
         );
         self.renderer.draw_cube(
             0.0, -0.24, z - 0.9,
-            24.0, 0.02, 0.08,
-            0.85, 0.85, 0.5,
+            self.visible_half_width() * 2.0, 0.02, 0.08,
+            lr, lg, lb,
             projection, view
         );
     }
 
-    fn draw_water_details(&self, z: f32, projection: &Matrix4<f32>, view: &Matrix4<f32>) {
-        let seed = (z * 100.0) as i32;
-        let time = self.time;
-        
-        let rand = |s: i32, offset: i32| -> f32 {
-            let n = ((s.wrapping_add(offset)).wrapping_mul(1103515245).wrapping_add(12345)) as u32;
-            (n % 10000) as f32 / 10000.0
+    fn draw_water_details(&self, lane: &Lane, projection: &Matrix4<f32>, view: &Matrix4<f32>) {
+        let LaneDetails::Water { crests, depths, foam, lily_pad } = &lane.details else {
+            return;
         };
+        let z = lane.z;
+        let time = self.time;
         
         // Animated water ripples/waves - move horizontally
-        for i in 0..12 {
-            let r1 = rand(seed, i * 7 + 600);
-            let r2 = rand(seed, i * 11 + 600);
-            let r3 = rand(seed, i * 17 + 600);
-            
+        let left = -self.visible_half_width();
+        for (i, &[r1, r2, r3]) in crests.iter().enumerate() {
             // Wave motion - different speeds and phases for each wave
             let phase = r1 * 6.28;
             let wave_speed = 0.8 + r2 * 0.6;
             let wave_offset = (time * wave_speed + phase).sin() * 0.3;
             
-            let base_x = -11.0 + (i as f32 * 2.0) + r1 * 1.0;
+            let base_x = left + 1.0 + (i as f32 * 2.0) + r1 * 1.0;
             let x = base_x + wave_offset;
             let z_offset = (r2 - 0.5) * 1.6 + (time * 0.5 + r3 * 6.28).sin() * 0.1;
             
@@ -669,39 +2359,33 @@ impl Game {
             self.renderer.draw_cube(
                 x, -0.23 + y_offset, z + z_offset,
                 0.8 + r3 * 0.4, 0.02, 0.15,
-                0.4, 0.6, 0.95,
+                self.palette.water_highlight.0, self.palette.water_highlight.1, self.palette.water_highlight.2,
                 projection, view
             );
         }
         
         // Animated darker water patches (depth) - subtle movement
-        for i in 0..8 {
-            let r1 = rand(seed, i * 13 + 700);
-            let r2 = rand(seed, i * 19 + 700);
-            
+        for (i, &[r1, r2]) in depths.iter().enumerate() {
             // Slow drift
             let drift = (time * 0.3 + r1 * 6.28).sin() * 0.2;
             
-            let x = -10.0 + (i as f32 * 2.8) + r1 * 1.5 + drift;
+            let x = -self.half_width + (i as f32 * 2.8) + r1 * 1.5 + drift;
             let z_offset = (r2 - 0.5) * 1.2 + (time * 0.4 + r2 * 6.28).cos() * 0.15;
             
             self.renderer.draw_cube(
                 x, -0.24, z + z_offset,
                 0.6 + r1 * 0.4, 0.01, 0.4 + r2 * 0.3,
-                0.15, 0.3, 0.6,
+                self.palette.water_deep.0, self.palette.water_deep.1, self.palette.water_deep.2,
                 projection, view
             );
         }
         
         // Animated foam/bubbles - flowing movement
-        for i in 0..6 {
-            let r1 = rand(seed, i * 23 + 800);
-            let r2 = rand(seed, i * 29 + 800);
-            
+        for (i, &[r1, r2]) in foam.iter().enumerate() {
             // Foam flows along edges
             let flow = (time * 0.6 + r1 * 6.28).sin() * 0.4;
             
-            let x = -10.0 + (i as f32 * 4.0) + r1 * 2.0 + flow;
+            let x = -self.half_width + (i as f32 * 4.0) + r1 * 2.0 + flow;
             let z_offset = if i % 2 == 0 { 0.85 } else { -0.85 };
             
             // Pulsing size
@@ -711,39 +2395,120 @@ impl Game {
             self.renderer.draw_cube(
                 x, -0.22, z + z_offset + (r2 - 0.5) * 0.1,
                 (0.3 + r1 * 0.2) * size_pulse, 0.03, 0.1,
-                0.85, 0.9, 0.95,
+                self.palette.foam.0, self.palette.foam.1, self.palette.foam.2,
                 projection, view
             );
         }
         
         // Lily pads - gentle bobbing
-        if seed % 5 == 0 {
-            let r1 = rand(seed, 900);
-            let r2 = rand(seed, 901);
-            let lx = -6.0 + r1 * 12.0;
+        if let Some(([r1, r2], flowering)) = *lily_pad {
+            let lx = (r1 * 2.0 - 1.0) * self.half_width * 0.6;
             let lz = z + (r2 - 0.5) * 1.0;
             
-            // Gentle bobbing motion
-            let bob = (time * 1.2 + r1 * 6.28).sin() * 0.02;
             let sway_x = (time * 0.8 + r2 * 6.28).sin() * 0.05;
-            
-            // Lily pad
+            self.draw_lily_pad(lx + sway_x, lz, 0.35, flowering, projection, view);
+        }
+    }
+
+    // Bobs gently in place. Decorative pads are small, the ones the player stands on fill a cell.
+    fn draw_lily_pad(&self, x: f32, z: f32, size: f32, flower: bool, projection: &Matrix4<f32>, view: &Matrix4<f32>) {
+        let bob = (self.time * 1.2 + x).sin() * 0.02;
+        self.renderer.draw_cube(
+            x, -0.21 + bob, z,
+            size, 0.03, size,
+            self.palette.lily_pad.0, self.palette.lily_pad.1, self.palette.lily_pad.2,
+            projection, view
+        );
+        // Two crossed slabs round the square off a little
+        self.renderer.draw_cube(
+            x, -0.21 + bob, z,
+            size * 1.15, 0.03, size * 0.7,
+            self.palette.lily_pad.0, self.palette.lily_pad.1, self.palette.lily_pad.2,
+            projection, view
+        );
+        self.renderer.draw_cube(
+            x, -0.21 + bob, z,
+            size * 0.7, 0.03, size * 1.15,
+            self.palette.lily_pad.0, self.palette.lily_pad.1, self.palette.lily_pad.2,
+            projection, view
+        );
+        if flower {
+            let petal = size * 0.23;
             self.renderer.draw_cube(
-                lx + sway_x, -0.21 + bob, lz,
-                0.35, 0.03, 0.35,
-                0.2, 0.55, 0.25,
+                x + size * 0.15, -0.15 + bob, z,
+                petal, petal, petal,
+                self.palette.lily_flower.0, self.palette.lily_flower.1, self.palette.lily_flower.2,
                 projection, view
             );
-            
-            // Small flower on lily pad
-            if seed % 10 == 0 {
+        }
+    }
+
+    // Sleepers across the lane, two rails along it and a signal at the right edge
+    fn draw_rails(&self, z: f32, projection: &Matrix4<f32>, view: &Matrix4<f32>) {
+        let half = self.visible_half_width();
+        let sleepers = (half * 2.0).round() as i32;
+        for i in 0..sleepers {
+            self.renderer.draw_cube(-half + 0.5 + i as f32, -0.22, z, 0.3, 0.06, 1.7, 0.35, 0.24, 0.15, projection, view);
+        }
+        for dz in [-0.5, 0.5] {
+            self.renderer.draw_cube(0.0, -0.15, z + dz, half * 2.0, 0.1, 0.12, 0.6, 0.6, 0.62, projection, view);
+        }
+
+        let index = (z / 2.0).round() as i32;
+        let length = TRAIN_LENGTH * self.half_width / DEFAULT_HALF_WIDTH;
+        let (_, _, signal) = train_state(self.world_seed, index, self.rail_clock, self.wrap_x(), length);
+        let lit = signal && (self.time * 8.0).sin() > 0.0;
+        let x = self.half_width + 1.0;
+        self.renderer.draw_cube(x, 0.5, z - 0.9, 0.15, 1.5, 0.15, 0.2, 0.2, 0.2, projection, view);
+        let (r, g, b) = if lit { (1.0, 0.1, 0.1) } else { (0.3, 0.05, 0.05) };
+        self.renderer.draw_cube(x, 1.3, z - 0.9, 0.35, 0.35, 0.2, r, g, b, projection, view);
+    }
+
+    fn draw_finish_line(&self, z: f32, projection: &Matrix4<f32>, view: &Matrix4<f32>) {
+        // Checkered band, two rows of one-unit squares
+        let columns = (self.visible_half_width() * 2.0).round() as i32;
+        for row in 0..2 {
+            for col in 0..columns {
+                if (row + col) % 2 == 0 {
+                    continue;
+                }
+                self.renderer.draw_cube(
+                    -self.visible_half_width() + 0.5 + col as f32, -0.24, z - 0.5 + row as f32,
+                    1.0, 0.02, 1.0,
+                    0.05, 0.05, 0.05,
+                    projection, view
+                );
+            }
+        }
+    }
+
+    // The warning shadow grows under the player as the eagle gets close, then the bird itself
+    fn draw_eagle(&self, projection: &Matrix4<f32>, view: &Matrix4<f32>) {
+        let idle_seconds = self.idle_seconds();
+        if idle_seconds <= 0.0 {
+            return;
+        }
+        let warning = match &self.eagle {
+            Some(_) => 1.0,
+            None => (self.idle_timer - (idle_seconds - EAGLE_WARNING_SECONDS)) / EAGLE_WARNING_SECONDS,
+        };
+        if warning > 0.0 && !self.game_over {
+            let size = 3.0 * warning.min(1.0);
+            self.draw_shadow(self.player.x, self.player.z, size, size * 0.6, 0.6, projection, view);
+        }
+        if let Some(eagle) = &self.eagle {
+            let (r, g, b) = eagle.color;
+            let flap = (self.time * 12.0).sin() * 0.15;
+            self.renderer.draw_cube(eagle.x, eagle.y, eagle.z, eagle.width, eagle.height, eagle.depth, r, g, b, projection, view);
+            for side in [-1.0, 1.0] {
                 self.renderer.draw_cube(
-                    lx + sway_x + 0.05, -0.15 + bob, lz,
-                    0.08, 0.08, 0.08,
-                    0.95, 0.7, 0.8,
+                    eagle.x + side * eagle.width * 1.1, eagle.y + flap, eagle.z,
+                    eagle.width, 0.1, eagle.depth * 0.8,
+                    r * 0.8, g * 0.8, b * 0.8,
                     projection, view
                 );
             }
+            self.renderer.draw_cube(eagle.x, eagle.y + 0.25, eagle.z - eagle.depth * 0.55, 0.4, 0.4, 0.4, 0.95, 0.95, 0.9, projection, view);
         }
     }
 
@@ -757,6 +2522,61 @@ impl Game {
         );
     }
 
+    // Black shell drawn without depth writes so the object itself paints over
+    // it, leaving only the enlarged silhouette visible as an outline
+    fn draw_outline(&self, obstacle: &GameObject, projection: &Matrix4<f32>, view: &Matrix4<f32>) {
+        let scale = 1.12;
+        self.renderer.gl.depth_mask(false);
+        self.renderer.draw_cube(
+            obstacle.x, obstacle.y, obstacle.z,
+            obstacle.width * scale, obstacle.height * scale, obstacle.depth * scale,
+            0.0, 0.0, 0.0,
+            projection, view
+        );
+        self.renderer.gl.depth_mask(true);
+    }
+
+    fn draw_player(&self, color: (f32, f32, f32), projection: &Matrix4<f32>, view: &Matrix4<f32>) {
+        let model = self.character.and_then(|i| self.config.as_ref()?.characters.get(i));
+        let (Some(mesh), Some(model)) = (&self.player_mesh, model) else {
+            self.renderer.draw_cube(
+                self.player.x, self.player.y, self.player.z,
+                self.player.width, self.player.height, self.player.depth,
+                color.0, color.1, color.2,
+                projection, view
+            );
+            return;
+        };
+        // Faces the last hop, forward is +z
+        let facing = match self.move_direction {
+            1 => std::f32::consts::PI,
+            2 => -std::f32::consts::FRAC_PI_2,
+            3 => std::f32::consts::FRAC_PI_2,
+            _ => 0.0,
+        };
+        let dead_color = if self.game_over { Some(color) } else { None };
+        self.renderer.draw_mesh(
+            mesh,
+            self.player.x, self.player.y + model.position_offset_y, self.player.z,
+            model.scale, model.scale, model.scale,
+            model.rotation_offset_x,
+            facing + model.rotation_offset_y,
+            model.rotation_offset_z,
+            projection, view,
+            None, None, dead_color, false, None, true, false, false, None, None
+        );
+    }
+
+    // Swapping mid-run keeps the score, only the look changes
+    pub fn set_character(&mut self, index: usize, mesh: Mesh) {
+        self.character = Some(index);
+        self.player_mesh = Some(mesh);
+    }
+
+    pub fn character_model(&self, index: usize) -> Option<ModelConfig> {
+        self.config.as_ref()?.characters.get(index).cloned()
+    }
+
     fn draw_car(&self, x: f32, y: f32, z: f32, w: f32, h: f32, d: f32, r: f32, g: f32, b: f32, velocity_x: f32, projection: &Matrix4<f32>, view: &Matrix4<f32>) {
         let rotation = if velocity_x >= 0.0 {
             std::f32::consts::FRAC_PI_2
@@ -792,24 +2612,98 @@ impl Game {
                 None
             );
         } else {
-            // Fallback to procedural car
-            let mesh = create_car_mesh(r, g, b);
-            self.renderer.draw_mesh(&mesh, x, y, z, w, h, d, 0.0, rotation, 0.0, projection, view, None, None, None, false, None, true, false, false, None, None);
+            // Fallback to procedural car, one mesh per body color built on first use
+            let mut meshes = self.fallback_car_meshes.borrow_mut();
+            let mesh = meshes.entry((r.to_bits(), g.to_bits(), b.to_bits()))
+                .or_insert_with(|| create_car_mesh(r, g, b));
+            self.renderer.draw_mesh(mesh, x, y, z, w, h, d, 0.0, rotation, 0.0, projection, view, None, None, None, false, None, true, false, false, None, None);
         }
     }
 
+    pub fn set_photo_mode(&mut self, enabled: bool) {
+        self.photo_mode = enabled;
+    }
+
     pub fn move_forward(&mut self) {
-        if !self.moving && !self.game_over {
+        if self.photo_mode {
+            return;
+        }
+        if !self.exit_attract_mode() {
+            self.hop_forward();
+        }
+    }
+
+    pub fn move_left(&mut self) {
+        if self.photo_mode {
+            return;
+        }
+        if !self.exit_attract_mode() {
+            self.hop_left();
+        }
+    }
+
+    pub fn move_right(&mut self) {
+        if self.photo_mode {
+            return;
+        }
+        if !self.exit_attract_mode() {
+            self.hop_right();
+        }
+    }
+
+    pub fn move_backward(&mut self) {
+        if self.photo_mode {
+            return;
+        }
+        if !self.exit_attract_mode() {
+            self.hop_backward();
+        }
+    }
+
+    // A finger lifted after moving (dx, dy) CSS pixels. Short moves are taps and hop forward.
+    pub fn swipe(&mut self, dx: f32, dy: f32) {
+        let threshold = self.config.as_ref().map_or(DEFAULT_SWIPE_THRESHOLD, |c| c.swipe_threshold);
+        match touch_overlay::classify_swipe(dx, dy, threshold) {
+            Swipe::Forward => self.move_forward(),
+            Swipe::Back => self.move_backward(),
+            Swipe::Left => self.move_left(),
+            Swipe::Right => self.move_right(),
+        }
+    }
+
+    fn hop_forward(&mut self) {
+        if self.countdown_blocks_input() {
+            return;
+        }
+        if !self.moving && !self.game_over && !self.finished {
             self.moving = true;
             self.target_z = self.player.z + 2.0;
             self.move_direction = 0;
         }
     }
 
-    pub fn move_left(&mut self) {
-        if !self.moving && !self.game_over {
+    // Never back past the starting lane
+    fn hop_backward(&mut self) {
+        if self.countdown_blocks_input() {
+            return;
+        }
+        if !self.moving && !self.game_over && !self.finished {
+            let new_target = self.player.z - 2.0;
+            if new_target >= 0.0 {
+                self.moving = true;
+                self.target_z = new_target;
+                self.move_direction = 1;
+            }
+        }
+    }
+
+    fn hop_left(&mut self) {
+        if self.countdown_blocks_input() {
+            return;
+        }
+        if !self.moving && !self.game_over && !self.finished {
             let new_target = self.player.x - 2.0;
-            if new_target >= -10.0 {
+            if new_target >= -self.half_width {
                 self.moving = true;
                 self.target_x = new_target;
                 self.move_direction = 2;
@@ -817,10 +2711,13 @@ impl Game {
         }
     }
 
-    pub fn move_right(&mut self) {
-        if !self.moving && !self.game_over {
+    fn hop_right(&mut self) {
+        if self.countdown_blocks_input() {
+            return;
+        }
+        if !self.moving && !self.game_over && !self.finished {
             let new_target = self.player.x + 2.0;
-            if new_target <= 10.0 {
+            if new_target <= self.half_width {
                 self.moving = true;
                 self.target_x = new_target;
                 self.move_direction = 3;
@@ -833,47 +2730,538 @@ impl Game {
         self.move_forward();
     }
 
+    // Keyboard, touch and pad input all land here. False for actions Crossy has no use for.
+    pub fn apply_action(&mut self, action: KeyAction) -> bool {
+        match action {
+            KeyAction::Forward | KeyAction::Jump => self.move_forward(),
+            KeyAction::Back => self.move_backward(),
+            KeyAction::Left => self.move_left(),
+            KeyAction::Right => self.move_right(),
+            KeyAction::Restart => self.restart(),
+            KeyAction::Sprint => return false,
+        }
+        true
+    }
+
     pub fn restart(&mut self) {
+        if !self.exit_attract_mode() {
+            self.start_new_run();
+        }
+    }
+
+    // Replays the current world from the start
+    pub fn restart_same_world(&mut self) {
+        if !self.exit_attract_mode() {
+            self.reset_run(self.world_seed);
+        }
+    }
+
+    fn start_new_run(&mut self) {
+        // Tournament retries keep the seed, a finished tournament starts a fresh attempt
+        let seed = if self.tournament.is_some() {
+            self.world_seed
+        } else {
+            random_seed()
+        };
+        if self.finished {
+            if let Some(t) = self.tournament.as_mut() {
+                t.elapsed_ticks = 0;
+                t.deaths = 0;
+            }
+        }
+        self.reset_run(seed);
+    }
+
+    // Landing on a log puts the player on the nearest grid column the log covers, or as
+    // close as it gets on a log shorter than a column
+    fn snap_to_log(&mut self) {
+        let lane_idx = (self.player.z / 2.0).round() as i32;
+        let footing = self.lanes.iter()
+            .find(|lane| (lane.z / 2.0).round() as i32 == lane_idx && lane.lane_type == LaneType::Water)
+            .and_then(|lane| lane.obstacles.iter().find(|o| o.kind == ObjectKind::Log && self.player.collides_horizontal(o, &self.collision)))
+            .map(|log| log_footing(log, &self.player));
+        if let Some(footing) = footing {
+            self.player.x = landing_x(self.player.x, footing);
+        }
+    }
+
+    // Ice floes keep the player going a little after landing, the way they hopped or else
+    // the way the floe drifts
+    fn start_slide(&mut self) {
+        let distance = match self.theme {
+            Some(theme) if theme.log_slide > 0.0 => theme.log_slide,
+            _ => return,
+        };
+        let lane_idx = (self.player.z / 2.0).round() as i32;
+        let floe = self.lanes.iter()
+            .find(|lane| (lane.z / 2.0).round() as i32 == lane_idx && lane.lane_type == LaneType::Water)
+            .and_then(|lane| lane.obstacles.iter().find(|o| o.kind == ObjectKind::Log && self.player.collides_horizontal(o, &self.collision)));
+        if let Some(floe) = floe {
+            let direction = match self.move_direction {
+                2 => -1.0,
+                3 => 1.0,
+                _ => floe.velocity_x.signum(),
+            };
+            self.slide = distance * direction;
+        }
+    }
+
+    fn advance_slide(&mut self) {
+        if self.moving {
+            self.slide = 0.0;
+            return;
+        }
+        let step = self.slide.clamp(-SLIDE_SPEED, SLIDE_SPEED);
+        self.player.x = clamp_to_field(self.player.x + step, self.half_width);
+        self.slide -= step;
+    }
+
+    fn update_camera(&mut self) {
+        if self.game_over {
+            // The camera stops dead, only the zoom toward where the run ended moves on
+            self.death_pos.get_or_insert((self.player.x, self.player.z));
+            self.death_zoom = (self.death_zoom + SIM_DT / DEATH_ZOOM_SECONDS).min(1.0);
+            return;
+        }
+        self.death_zoom = 0.0;
+        self.death_pos = None;
+        match self.move_direction {
+            2 => self.cam_side = -1.0,
+            3 => self.cam_side = 1.0,
+            _ => {}
+        }
+        let advancing = self.move_direction == 0 && self.idle_timer < CAMERA_LOOK_AHEAD_HOLD_SECONDS;
+        let target_x = self.player.x + self.cam_side * CAMERA_LATERAL_LEAD;
+        let target_z = self.player.z + if advancing { CAMERA_LOOK_AHEAD } else { 0.0 };
+        let smoothing = self.config.as_ref()
+            .map_or(DEFAULT_CAMERA_SMOOTHING, |c| c.camera_smoothing)
+            .clamp(0.01, 1.0);
+        self.cam_x += (target_x - self.cam_x) * smoothing;
+        self.cam_z += (target_z - self.cam_z) * smoothing;
+    }
+
+    fn reset_run(&mut self, seed: u32) {
         self.player.x = 0.0;
         self.player.y = self.base_y;
         self.player.z = 0.0;
+        self.cam_x = 0.0;
+        self.cam_z = 0.0;
+        self.cam_side = 0.0;
+        self.death_zoom = 0.0;
+        self.death_pos = None;
+        self.camera_min_z = 0.0;
+        self.idle_timer = 0.0;
+        self.eagle = None;
+        self.rail_clock = 0.0;
+        self.power_ups = PowerUps::default();
         self.score = 0;
         self.coins = 0;
+        self.gems = 0;
         self.game_over = false;
         self.moving = false;
         self.jump_progress = 0.0;
         self.invincible = false;
-        
-        // New random seed for new world
-        self.world_seed = (js_sys::Math::random() * 1000000.0) as u32;
+        self.finished = false;
+        self.lane_history.clear();
+        self.death_lane = None;
+        self.style_streaks = StyleStreaks::default();
+        self.particles.clear();
+        self.death_cause = None;
+        self.run_seconds = 0.0;
+        self.death_review = 0.0;
+        self.slide = 0.0;
+        self.intensity = 0.0;
+        self.emitted_intensity = 0.0;
+        self.countdown = self.countdown_length;
+        events::emit("restart", serde_json::json!(seed));
+        if self.countdown > 0.0 {
+            events::emit("countdown", serde_json::json!(self.countdown.ceil() as i32));
+        }
+
+        self.world_seed = seed;
+        self.day_offset = day_phase(seed) - self.time;
         self.furthest_lane = 24;
-        
+        if let Some(t) = &self.tournament {
+            self.furthest_lane = self.furthest_lane.min(t.target_lane);
+        }
+
         self.lanes.clear();
-        for i in -5..25 {
-            self.lanes.push(create_lane_procedural(i as f32 * 2.0, i, self.world_seed));
+        for i in -5..=self.furthest_lane {
+            let lane = self.create_lane(i);
+            self.lanes.push(lane);
+        }
+    }
+}
+
+// Whether something at x moving velocity_x per tick gets within reach of column_x in the
+// next `seconds`. Objects already past the column never count.
+pub fn reaches_column(x: f32, velocity_x: f32, reach: f32, column_x: f32, seconds: f32) -> bool {
+    let offset = column_x - x;
+    if velocity_x == 0.0 {
+        return offset.abs() <= reach;
+    }
+    let ahead = offset * velocity_x.signum();
+    if ahead < -reach {
+        return false;
+    }
+    let gap = (ahead - reach).max(0.0);
+    gap <= velocity_x.abs() * seconds / SIM_DT
+}
+
+// Raw music intensity for the current situation: ~0.1 idle on grass, ~0.9 with a car about to hit
+pub fn crossy_intensity(lanes: &[Lane], player: &GameObject, difficulty: &DifficultyConfig) -> f32 {
+    let player_lane = (player.z / 2.0).round() as i32;
+    let mut nearby_cars = 0;
+    let mut nearest_car = f32::MAX;
+    let mut on_water = false;
+
+    for lane in lanes {
+        let index = (lane.z / 2.0).round() as i32;
+        if (index - player_lane).abs() > 1 {
+            continue;
+        }
+        match lane.lane_type {
+            LaneType::Road => {
+                for car in &lane.obstacles {
+                    nearby_cars += 1;
+                    let gap = ((car.x - player.x).abs() - (car.width + player.width) / 2.0).max(0.0);
+                    if index == player_lane || index == player_lane + 1 {
+                        nearest_car = nearest_car.min(gap);
+                    }
+                }
+            }
+            LaneType::Water if index == player_lane => on_water = true,
+            _ => {}
+        }
+    }
+
+    let crowd = (nearby_cars as f32 / 6.0).min(1.0) * 0.1;
+    let speed = difficulty.progress(player_lane.unsigned_abs() as usize) * 0.1;
+    let danger = (1.0 - nearest_car / 8.0).clamp(0.0, 1.0) * 0.75;
+    let water = if on_water { 0.2 } else { 0.0 };
+    (0.1 + crowd + speed + danger + water).min(1.0)
+}
+
+// Adds the lane to the crossing history if the player hasn't been this far yet.
+// Returns whether it was new.
+fn record_lane(history: &mut Vec<LaneType>, lane_idx: i32, lane_type: LaneType) -> bool {
+    if lane_idx < 0 || history.len() > lane_idx as usize {
+        return false;
+    }
+    while history.len() <= lane_idx as usize {
+        history.push(lane_type);
+    }
+    true
+}
+
+// Stable share format: a header line, then one emoji per lane for the last lanes landed on
+// Event runs name their theme after the date so they only get compared with each other.
+pub fn format_share_text(date: &str, score: i32, coins: i32, history: &[LaneType], death_lane: Option<usize>, theme: Option<&str>) -> String {
+    let start = history.len().saturating_sub(SHARE_STRIP_LANES);
+    let strip: String = history[start..].iter().enumerate().map(|(offset, lane_type)| {
+        if death_lane == Some(start + offset) {
+            return "💀";
+        }
+        match lane_type {
+            LaneType::Grass => "🟩",
+            LaneType::Road => "⬛",
+            LaneType::Water => "🟦",
+            LaneType::Finish => "🏁",
+            LaneType::Rail => "🟫",
+        }
+    }).collect();
+    let tag = theme.map(|key| format!(" ({})", key)).unwrap_or_default();
+    format!("Crossy {}{} — {} lanes, {} coins\n{}", date, tag, score, coins, strip)
+}
+
+fn random_seed() -> u32 {
+    (js_sys::Math::random() * 1000000.0) as u32
+}
+
+// Procedural pseudo-random number generator
+fn proc_rand(seed: u32, x: i32, y: i32) -> f32 {
+    let n = seed.wrapping_add((x as u32).wrapping_mul(374761393))
+        .wrapping_add((y as u32).wrapping_mul(668265263));
+    let n = n ^ (n >> 13);
+    let n = n.wrapping_mul(1274126177);
+    let n = n ^ (n >> 16);
+    (n % 10000) as f32 / 10000.0
+}
+
+// Starting cycle position for a seed, so a shared daily seed also shares its lighting
+fn day_phase(world_seed: u32) -> f32 {
+    proc_rand(world_seed, 0, 990) * DAY_SECONDS
+}
+
+// Day-night tint multipliers for a cycle position. Eased between keyframes so the color
+// has no kinks where one stretch of the day hands over to the next.
+pub fn day_tint(cycle: f32) -> (f32, f32, f32) {
+    let cycle = cycle.rem_euclid(DAY_SECONDS);
+    for pair in DAY_TINT_KEYS.windows(2) {
+        let ((t0, a), (t1, b)) = (pair[0], pair[1]);
+        if cycle <= t1 {
+            let t = ((cycle - t0) / (t1 - t0)).clamp(0.0, 1.0);
+            let t = t * t * (3.0 - 2.0 * t);
+            return (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t, a.2 + (b.2 - a.2) * t);
+        }
+    }
+    DAY_TINT_KEYS[0].1
+}
+
+// Pads don't move, so two pad lanes in a row could share no column and wall the run off.
+// A lane only gets pads when the one before it didn't roll for them.
+fn is_lily_pad_lane(index: i32, world_seed: u32, difficulty: &DifficultyConfig) -> bool {
+    let rolls_pads = |index| lane_type_at(index, world_seed, difficulty) == LaneType::Water
+        && proc_rand(world_seed, index, 706) < LILY_PAD_LANE_CHANCE;
+    rolls_pads(index) && !rolls_pads(index - 1)
+}
+
+// Pads sit on the even columns the player hops along, each in its own stretch of the lane
+// so no two share a column. Some carry a coin.
+fn create_lily_pads(z: f32, index: i32, world_seed: u32, half_width: f32) -> (Vec<GameObject>, Vec<GameObject>) {
+    let max_col = ((half_width - 1.0) / 2.0).floor() as i32;
+    let columns = (2 * max_col + 1) as usize;
+    let count = (LILY_PAD_MIN + (proc_rand(world_seed, index, 707) * (LILY_PAD_MAX - LILY_PAD_MIN + 1) as f32) as usize)
+        .min(LILY_PAD_MAX)
+        .min(columns);
+    let stretch = columns as f32 / count as f32;
+    let mut pads = Vec::new();
+    let mut coins = Vec::new();
+    for i in 0..count {
+        let offset = (stretch * (i as f32 + proc_rand(world_seed, index, 710 + i as i32))).floor() as i32;
+        let x = ((-max_col + offset).min(max_col) * 2) as f32;
+        let mut pad = GameObject::new(x, -0.15, z, 1.0, 0.1, 1.0, (0.2, 0.55, 0.25));
+        pad.kind = ObjectKind::LilyPad;
+        pads.push(pad);
+        if proc_rand(world_seed, index, 720 + i as i32) > 0.75 {
+            coins.push(GameObject::new_coin(x, 0.5, z));
+        }
+    }
+    (pads, coins)
+}
+
+// Attracted coins stop riding their log and slide along their own lane to line up with the
+// player, leaving it would strand them between lanes the player never stands on. Out of
+// range or once the magnet is gone they drift with their lane's logs again.
+fn attract_coins(lanes: &mut [Lane], (px, pz): (f32, f32), magnet: bool) {
+    for lane in lanes {
+        let drift = lane.obstacles.iter().find(|o| o.kind == ObjectKind::Log).map_or(0.0, |log| log.velocity_x);
+        for coin in &mut lane.coins {
+            let (dx, dz) = (px - coin.x, pz - coin.z);
+            if magnet && dx * dx + dz * dz <= MAGNET_RANGE * MAGNET_RANGE {
+                coin.velocity_x = 0.0;
+                coin.x += dx * MAGNET_PULL;
+            } else {
+                coin.velocity_x = drift;
+            }
+        }
+    }
+}
+
+// At most one per lane, on grass or road where it can be reached without a ride. Like a
+// grass coin it's dropped rather than placed inside a tree or rock.
+fn create_power_ups(lane_type: LaneType, z: f32, index: i32, world_seed: u32, half_width: f32, obstacles: &[GameObject]) -> Vec<GameObject> {
+    if index < 5 || !matches!(lane_type, LaneType::Grass | LaneType::Road) || proc_rand(world_seed, index, 730) >= POWER_UP_CHANCE {
+        return Vec::new();
+    }
+    let kind = if proc_rand(world_seed, index, 731) < 0.5 { PowerUpKind::Magnet } else { PowerUpKind::Shield };
+    let max_col = ((half_width - 1.0) / 2.0).floor() as i32;
+    let col = (proc_rand(world_seed, index, 732) * (2 * max_col + 1) as f32) as i32 - max_col;
+    let x = (col.min(max_col) * 2) as f32;
+    if lane_type == LaneType::Grass && obstacles.iter().any(|obs| (obs.x - x).abs() < 1.0) {
+        return Vec::new();
+    }
+    let mut power_up = GameObject::new(x, 0.5, z, 0.6, 0.6, 0.6, kind.color());
+    power_up.kind = ObjectKind::PowerUp(kind);
+    vec![power_up]
+}
+
+// Parked off the edge until step_traffic runs it on schedule
+fn create_train(z: f32, half_width: f32) -> GameObject {
+    let length = TRAIN_LENGTH * half_width / DEFAULT_HALF_WIDTH;
+    GameObject::new(-(half_width + 5.0 + length), 0.8, z, length, 1.6, 1.6, (0.55, 0.12, 0.1))
+}
+
+// Past this distance from the center, traffic leaves one side and comes back on the other
+fn wrap_bound(half_width: f32) -> f32 {
+    half_width + 5.0
+}
+
+fn wrap_around(x: f32, wrap: f32) -> f32 {
+    if x > wrap {
+        -wrap
+    } else if x < -wrap {
+        wrap
+    } else {
+        x
+    }
+}
+
+// Whether the player has dropped further behind the furthest point reached than the camera allows
+fn left_behind(camera_min_z: f32, player_z: f32) -> bool {
+    camera_min_z - player_z > CAMERA_KILL_DISTANCE
+}
+
+// Where the player can stand across a field of this half width
+fn clamp_to_field(x: f32, half_width: f32) -> f32 {
+    x.clamp(-half_width, half_width)
+}
+
+// Where a rail lane's train is `clock` seconds into the run: x, velocity per tick and whether
+// the signal is on. Only the seed, the lane and the clock go in, so a seed replays its trains.
+fn train_state(world_seed: u32, index: i32, clock: f32, wrap: f32, length: f32) -> (f32, f32, bool) {
+    let period = TRAIN_MIN_PERIOD + proc_rand(world_seed, index, 701) * TRAIN_PERIOD_SPREAD;
+    let direction = if proc_rand(world_seed, index, 702) > 0.5 { 1.0 } else { -1.0 };
+    let t = (clock + proc_rand(world_seed, index, 703) * period).rem_euclid(period);
+    let start = -direction * (wrap + length / 2.0);
+    let pass_seconds = (2.0 * wrap + length) / TRAIN_SPEED;
+    let velocity = direction * TRAIN_SPEED * SIM_DT;
+    if t < pass_seconds {
+        (start + direction * TRAIN_SPEED * t, velocity, true)
+    } else if t >= period - TRAIN_WARNING_SECONDS {
+        (start, velocity, true)
+    } else {
+        (start, 0.0, false)
+    }
+}
+
+fn create_finish_lane(z: f32) -> Lane {
+    Lane { z, lane_type: LaneType::Finish, obstacles: Vec::new(), coins: Vec::new(), puddles: Vec::new(), decorations: Vec::new(), power_ups: Vec::new(), details: LaneDetails::None }
+}
+
+// Range of player x that keeps the player fully on a log
+fn log_footing(log: &GameObject, player: &GameObject) -> (f32, f32) {
+    let reach = (log.width - player.width).max(0.0) / 2.0;
+    (log.x - reach, log.x + reach)
+}
+
+// Where a landing at x ends up on a log with this footing: the nearest grid column the log
+// covers, or as close as it gets on a log shorter than a column
+fn landing_x(x: f32, (min, max): (f32, f32)) -> f32 {
+    let (first, last) = ((min / 2.0).ceil() * 2.0, (max / 2.0).floor() * 2.0);
+    if first <= last {
+        ((x / 2.0).round() * 2.0).clamp(first, last)
+    } else {
+        x.clamp(min, max)
+    }
+}
+
+// The steps of a tick a run lives or dies by, shared by Game::tick and the AI's headless
+// runs so both play by the same rules.
+
+// Moves the player one tick along a hop in direction (0 forward, 1 back, 2 left, 3 right),
+// true once it lands on the target
+fn step_hop(player: &mut GameObject, direction: i32, target_x: f32, target_z: f32) -> bool {
+    let (position, target, sign) = match direction {
+        0 => (&mut player.z, target_z, 1.0),
+        1 => (&mut player.z, target_z, -1.0),
+        2 => (&mut player.x, target_x, -1.0),
+        3 => (&mut player.x, target_x, 1.0),
+        _ => return false,
+    };
+    *position += HOP_SPEED * sign;
+    if (*position - target) * sign >= 0.0 {
+        *position = target;
+        return true;
+    }
+    false
+}
+
+// How fast traffic runs in this mode, relative to normal play
+fn traffic_speed(mode: TrafficMode) -> f32 {
+    match mode {
+        TrafficMode::Normal => 1.0,
+        TrafficMode::NoWrap => DEATH_REVIEW_SPEED,
+        TrafficMode::Frozen => 0.0,
+    }
+}
+
+// Trains follow their schedule `clock` seconds into the run rather than their velocity,
+// which is only set so anything predicting traffic sees them coming
+fn step_traffic(lanes: &mut [Lane], mode: TrafficMode, world_seed: u32, wrap: f32, clock: f32) {
+    match mode {
+        TrafficMode::Normal => {
+            advance_positions(lanes, 1.0);
+            wrap_positions(lanes, wrap);
+        }
+        TrafficMode::NoWrap => advance_positions(lanes, traffic_speed(mode)),
+        TrafficMode::Frozen => {}
+    }
+    for lane in lanes.iter_mut().filter(|lane| lane.lane_type == LaneType::Rail) {
+        let index = (lane.z / 2.0).round() as i32;
+        for train in &mut lane.obstacles {
+            let (x, velocity, _) = train_state(world_seed, index, clock, wrap, train.width);
+            train.x = x;
+            train.velocity_x = velocity;
+        }
+    }
+}
+
+fn advance_positions(lanes: &mut [Lane], speed: f32) {
+    for lane in lanes {
+        for obstacle in &mut lane.obstacles {
+            obstacle.x += obstacle.velocity_x * speed;
+        }
+        for coin in &mut lane.coins {
+            coin.x += coin.velocity_x * speed;
+        }
+    }
+}
+
+fn wrap_positions(lanes: &mut [Lane], wrap: f32) {
+    for lane in lanes {
+        for object in lane.obstacles.iter_mut().chain(&mut lane.coins) {
+            object.x = wrap_around(object.x, wrap);
         }
     }
 }
 
-// Procedural pseudo-random number generator
-fn proc_rand(seed: u32, x: i32, y: i32) -> f32 {
-    let n = seed.wrapping_add((x as u32).wrapping_mul(374761393))
-        .wrapping_add((y as u32).wrapping_mul(668265263));
-    let n = n ^ (n >> 13);
-    let n = n.wrapping_mul(1274126177);
-    let n = n ^ (n >> 16);
-    (n % 10000) as f32 / 10000.0
+// What the player's lane did this tick: what would kill them, the caller decides whether
+// god mode or a shield saves them, and the index of the log carrying them
+struct LaneContact {
+    hazard: Option<&'static str>,
+    riding: Option<usize>,
 }
 
-fn create_lane_procedural(z: f32, index: i32, world_seed: u32) -> Lane {
-    let r = proc_rand(world_seed, index, 0);
-    let abs_index = index.unsigned_abs() as usize;
+// Trains hit mid-hop too, cars and water only once landed. A log carries the player along,
+// and doesn't let them hang off its ends except mid-hop toward the next one.
+fn touch_lane(lane: &Lane, player: &mut GameObject, moving: bool, half_width: f32, margins: &CollisionMargins) -> LaneContact {
+    let touching = |player: &GameObject, o: &GameObject| player.collides_horizontal(o, margins);
+    let hazard = match lane.lane_type {
+        LaneType::Rail if lane.obstacles.iter().any(|train| touching(player, train)) => Some("train"),
+        LaneType::Road if !moving && lane.obstacles.iter().any(|car| touching(player, car)) => Some("car"),
+        LaneType::Water if !moving => {
+            let bound = half_width + LOG_EDGE_MARGIN;
+            let on_log = lane.obstacles.iter().any(|o| o.x.abs() <= bound && touching(player, o));
+            if on_log { None } else { Some("water") }
+        }
+        _ => None,
+    };
+
+    let mut riding = None;
+    if lane.lane_type == LaneType::Water {
+        for (k, log) in lane.obstacles.iter().enumerate() {
+            if log.kind == ObjectKind::Log && touching(player, log) {
+                player.x += log.velocity_x;
+                if !moving {
+                    let (min, max) = log_footing(log, player);
+                    player.x = player.x.clamp(min, max);
+                }
+                riding.get_or_insert(k);
+            }
+        }
+    }
+    LaneContact { hazard, riding }
+}
+
+// Pure function of the index so a lane can look at its neighbors' types without stored state
+fn lane_type_at(index: i32, world_seed: u32, difficulty: &DifficultyConfig) -> LaneType {
     let biome_idx = (index / 50) as i32;
-    
-    let lane_type = if index <= 0 {
-        LaneType::Grass
-    } else if index < 3 {
+    let rail_chance = RAIL_CHANCE + (RAIL_CHANCE_AT_MAX_DIFFICULTY - RAIL_CHANCE) * difficulty.progress(index.unsigned_abs() as usize);
+    if index < 3 {
         LaneType::Grass
+    } else if index >= RAIL_MIN_LANE && proc_rand(world_seed, index, 700) < rail_chance {
+        LaneType::Rail
     } else {
         let type_rand = proc_rand(world_seed, index, 1);
         match biome_idx % 3 {
@@ -893,13 +3281,21 @@ fn create_lane_procedural(z: f32, index: i32, world_seed: u32) -> Lane {
                 else { LaneType::Water }
             }
         }
-    };
+    }
+}
+
+// x positions are laid out for the default width and stretched by the playfield's,
+// so a (seed, width, difficulty config) triple always produces the same world
+fn create_lane_procedural(z: f32, index: i32, world_seed: u32, half_width: f32, params: &DifficultyConfig) -> Lane {
+    let scale = half_width / DEFAULT_HALF_WIDTH;
+    let r = proc_rand(world_seed, index, 0);
+    let abs_index = index.unsigned_abs() as usize;
+    let lane_type = lane_type_at(index, world_seed, params);
 
     let mut obstacles = Vec::new();
     let mut coins = Vec::new();
     
-    // Difficulty increases with distance
-    let difficulty = (abs_index as f32 / 20.0).min(1.5);
+    let difficulty = params.at(abs_index);
     
     match lane_type {
         LaneType::Road => {
@@ -908,7 +3304,7 @@ fn create_lane_procedural(z: f32, index: i32, world_seed: u32) -> Lane {
             let direction = if proc_rand(world_seed, index, 3) > 0.5 { 1.0 } else { -1.0 };
             
             // Speed increases with difficulty
-            let base_speed = 0.02 + difficulty * 0.03;
+            let base_speed = params.base_car_speed + difficulty * params.car_speed_per_difficulty;
             let speed_variation = proc_rand(world_seed, index, 4) * 0.02;
             let speed = base_speed + speed_variation;
             
@@ -916,7 +3312,7 @@ fn create_lane_procedural(z: f32, index: i32, world_seed: u32) -> Lane {
                 let offset = proc_rand(world_seed, index, 10 + i as i32) * 6.0;
                 let color_idx = ((proc_rand(world_seed, index, 20 + i as i32) * 8.0) as usize) % CAR_COLORS.len();
                 let mut car = GameObject::new_car(
-                    -12.0 + (i as f32 * 7.0) + offset,
+                    (-12.0 + (i as f32 * 7.0) + offset) * scale,
                     0.5,
                     z,
                     2.0, 1.0, 1.5,
@@ -927,16 +3323,21 @@ fn create_lane_procedural(z: f32, index: i32, world_seed: u32) -> Lane {
             }
 
             // Chance to spawn coin on road (risky!)
-            if proc_rand(world_seed, index, 15) > 0.7 {
-                let coin_x = -8.0 + proc_rand(world_seed, index, 16) * 16.0;
-                let coin = GameObject::new(coin_x, 0.5, z, 0.4, 0.4, 0.4, (1.0, 0.8, 0.0));
-                coins.push(coin);
+            if proc_rand(world_seed, index, 15) > 1.0 - params.coin_chance_road {
+                let coin_x = (-8.0 + proc_rand(world_seed, index, 16) * 16.0) * scale;
+                coins.push(GameObject::new_coin(coin_x, 0.5, z));
             }
         }
+        LaneType::Water if is_lily_pad_lane(index, world_seed, params) => {
+            let (pads, pad_coins) = create_lily_pads(z, index, world_seed, half_width);
+            obstacles = pads;
+            coins = pad_coins;
+        }
         LaneType::Water => {
             // More logs when easier (beginning), fewer when harder
-            let base_logs = if abs_index < 10 { 3 } else { 2 };
-            let num_logs = base_logs + (proc_rand(world_seed, index, 5) * 2.0) as usize;
+            let base_logs = if abs_index < 10 { params.water_log_min + 1 } else { params.water_log_min };
+            // Wider rivers get more logs at the same spacing, so they stay crossable
+            let num_logs = ((base_logs + (proc_rand(world_seed, index, 5) * 2.0) as usize) as f32 * scale).round().max(1.0) as usize;
             let direction = if proc_rand(world_seed, index, 6) > 0.5 { 1.0 } else { -1.0 };
             
             let base_speed = 0.015 + difficulty * 0.02;
@@ -946,42 +3347,52 @@ fn create_lane_procedural(z: f32, index: i32, world_seed: u32) -> Lane {
             let log_size = 3.0 + proc_rand(world_seed, index, 8) * 2.0;
             
             for i in 0..num_logs {
-                let offset = proc_rand(world_seed, index, 30 + i as i32) * 4.0;
+                // Logs past the fifth only come with wide fields, their rolls keep clear of the coins'
+                let salt = |base: i32| if i < 5 { base + i as i32 } else { base * 100 + i as i32 };
+                let offset = proc_rand(world_seed, index, salt(30)) * 4.0;
+                let log_x = (-10.0 + offset) * scale + i as f32 * 6.0;
                 let mut log = GameObject::new(
-                    -10.0 + (i as f32 * 6.0) + offset,
+                    log_x,
                     0.3,
                     z,
                     log_size, 0.6, 1.5,
                     (0.45 + r * 0.1, 0.25 + r * 0.1, 0.1)
                 );
                 log.velocity_x = speed * direction;
+                log.kind = ObjectKind::Log;
                 obstacles.push(log);
 
                 // Chance to spawn coin on log
-                if proc_rand(world_seed, index, 35 + i as i32) > 0.7 {
-                    let mut coin = GameObject::new(
-                        -10.0 + (i as f32 * 6.0) + offset,
+                if proc_rand(world_seed, index, salt(35)) > 1.0 - params.coin_chance_water {
+                    let mut coin = GameObject::new_coin(
+                        log_x,
                         0.8, // Higher on log
                         z,
-                        0.4, 0.4, 0.4,
-                        (1.0, 0.8, 0.0)
                     );
                     coin.velocity_x = speed * direction;
                     coins.push(coin);
                 }
             }
         }
+        LaneType::Finish => {}
+        LaneType::Rail => {
+            obstacles.push(create_train(z, half_width));
+            if proc_rand(world_seed, index, 704) > 0.8 {
+                let coin_x = (-8.0 + proc_rand(world_seed, index, 705) * 16.0) * scale;
+                coins.push(GameObject::new_coin(coin_x, 0.5, z));
+            }
+        }
         LaneType::Grass => {
             // Trees and rocks procedurally placed
             let num_obstacles = (proc_rand(world_seed, index, 9) * 3.0) as usize;
             for i in 0..num_obstacles {
-                let x_pos = -10.0 + proc_rand(world_seed, index, 40 + i as i32) * 20.0;
+                let x_pos = (-10.0 + proc_rand(world_seed, index, 40 + i as i32) * 20.0) * scale;
                 let is_tree = proc_rand(world_seed, index, 50 + i as i32) > 0.3;
                 
                 if is_tree {
                     // Tree
                     let tree_height = 1.5 + proc_rand(world_seed, index, 60 + i as i32) * 1.5;
-                    let tree = GameObject::new(
+                    let mut tree = GameObject::new(
                         x_pos,
                         tree_height / 2.0,
                         z,
@@ -990,10 +3401,11 @@ fn create_lane_procedural(z: f32, index: i32, world_seed: u32) -> Lane {
                          0.4 + proc_rand(world_seed, index, 80 + i as i32) * 0.2, 
                          0.15)
                     );
+                    tree.kind = ObjectKind::Tree;
                     obstacles.push(tree);
                 } else {
                     // Rock
-                    let rock = GameObject::new(
+                    let mut rock = GameObject::new(
                         x_pos,
                         0.3,
                         z,
@@ -1002,13 +3414,14 @@ fn create_lane_procedural(z: f32, index: i32, world_seed: u32) -> Lane {
                         0.5 + proc_rand(world_seed, index, 100 + i as i32) * 0.3,
                         (0.5, 0.5, 0.5)
                     );
+                    rock.kind = ObjectKind::Rock;
                     obstacles.push(rock);
                 }
             }
 
             // Chance to spawn coin on grass
-            if proc_rand(world_seed, index, 95) > 0.6 {
-                let coin_x = -9.0 + proc_rand(world_seed, index, 96) * 18.0;
+            if proc_rand(world_seed, index, 95) > 1.0 - params.coin_chance_grass {
+                let coin_x = (-9.0 + proc_rand(world_seed, index, 96) * 18.0) * scale;
                 // Check collision with obstacles roughly
                 let mut collides = false;
                 for obs in &obstacles {
@@ -1018,14 +3431,107 @@ fn create_lane_procedural(z: f32, index: i32, world_seed: u32) -> Lane {
                     }
                 }
                 if !collides {
-                    let coin = GameObject::new(coin_x, 0.5, z, 0.4, 0.4, 0.4, (1.0, 0.8, 0.0));
-                    coins.push(coin);
+                    coins.push(GameObject::new_coin(coin_x, 0.5, z));
+                }
+            }
+        }
+    }
+
+    let puddles = if lane_type == LaneType::Road { create_puddles(index, world_seed, half_width) } else { Vec::new() };
+    let decorations = create_decorations(lane_type, index, world_seed, half_width, params);
+    let power_ups = create_power_ups(lane_type, z, index, world_seed, half_width, &obstacles);
+    for (i, coin) in coins.iter_mut().enumerate() {
+        let salt = 740 + i as i32 * 2;
+        if lane_type == LaneType::Road && proc_rand(world_seed, index, salt) < DIAMOND_CHANCE {
+            coin.set_coin_kind(CoinKind::Diamond);
+        } else if proc_rand(world_seed, index, salt + 1) < GEM_CHANCE {
+            coin.set_coin_kind(CoinKind::Gem);
+        }
+    }
+    let details = create_details(lane_type, z, half_width);
+    Lane { z, lane_type, obstacles, coins, puddles, decorations, power_ups, details }
+}
+
+// Grass next to a road may get a fence along that edge with a gap for crossing, and a
+// crossing sign by the gap. Roads may get a pair of lamp posts at their ends.
+fn create_decorations(lane_type: LaneType, index: i32, world_seed: u32, half_width: f32, difficulty: &DifficultyConfig) -> Vec<Decoration> {
+    let fence_reach = (half_width + 1.0) as i32;
+    let mut decorations = Vec::new();
+    match lane_type {
+        LaneType::Grass => {
+            for (salt, side, neighbor) in [(0, 1.0, index + 1), (1, -1.0, index - 1)] {
+                if lane_type_at(neighbor, world_seed, difficulty) != LaneType::Road {
+                    continue;
+                }
+                let dz = side * 0.85;
+                let gap = ((proc_rand(world_seed, index, 920 + salt) * 2.0 - 1.0) * (half_width - 2.0)).round();
+                if proc_rand(world_seed, index, 922 + salt) < FENCE_CHANCE {
+                    decorations.extend((-fence_reach..=fence_reach)
+                        .map(|i| i as f32)
+                        .filter(|x| (x - gap).abs() > 1.5)
+                        .map(|x| Decoration { kind: DecorationKind::Fence, x, dz }));
+                }
+                if proc_rand(world_seed, index, 924 + salt) < SIGN_CHANCE {
+                    decorations.push(Decoration { kind: DecorationKind::CrossingSign, x: gap + 1.5, dz });
                 }
             }
         }
+        LaneType::Road if proc_rand(world_seed, index, 930) < LAMP_CHANCE => {
+            let dz = if proc_rand(world_seed, index, 931) > 0.5 { 0.9 } else { -0.9 };
+            decorations.push(Decoration { kind: DecorationKind::LampPost, x: -(half_width + 1.5), dz });
+            decorations.push(Decoration { kind: DecorationKind::LampPost, x: half_width + 1.5, dz });
+        }
+        _ => {}
     }
+    decorations
+}
 
-    Lane { z, lane_type, obstacles, coins }
+fn create_decoration_meshes() -> HashMap<(DecorationKind, usize), Mesh> {
+    DecorationKind::ALL.iter()
+        .flat_map(|kind| kind.parts().iter().enumerate().map(move |(part, (boxes, _))| ((*kind, part), Mesh::cuboids(boxes))))
+        .collect()
+}
+
+fn create_puddles(index: i32, world_seed: u32, half_width: f32) -> Vec<Puddle> {
+    let count = (proc_rand(world_seed, index, 900) * 4.0) as i32;
+    (0..count).map(|i| Puddle {
+        x: (proc_rand(world_seed, index, 901 + i * 4) * 2.0 - 1.0) * half_width,
+        dz: (proc_rand(world_seed, index, 902 + i * 4) - 0.5) * 1.2,
+        width: 1.0 + proc_rand(world_seed, index, 903 + i * 4) * 1.5,
+        depth: 0.4 + proc_rand(world_seed, index, 904 + i * 4) * 0.5,
+    }).collect()
+}
+
+// Scenery loops written for the default width run proportionally more or fewer times
+fn detail_count(default_count: i32, half_width: f32) -> i32 {
+    (default_count as f32 * half_width / DEFAULT_HALF_WIDTH).round() as i32
+}
+
+// Seeded by the lane's z alone, so a lane's scenery looks the same in every world
+fn create_details(lane_type: LaneType, z: f32, half_width: f32) -> LaneDetails {
+    let seed = (z * 100.0) as i32;
+    let rand = |offset: i32| -> f32 {
+        let n = ((seed.wrapping_add(offset)).wrapping_mul(1103515245).wrapping_add(12345)) as u32;
+        (n % 10000) as f32 / 10000.0
+    };
+    let count = |default_count: i32| 0..detail_count(default_count, half_width);
+    match lane_type {
+        LaneType::Grass => LaneDetails::Grass {
+            tufts: count(20).map(|i| [rand(i * 7), rand(i * 13), rand(i * 23)]).collect(),
+            blades: count(30).map(|i| [rand(i * 11 + 100), rand(i * 17 + 100), rand(i * 29 + 100), rand(i * 37 + 100)]).collect(),
+            patches: count(6).map(|i| [rand(i * 43 + 200), rand(i * 47 + 200)]).collect(),
+            flowers: (0..(seed.abs() % 4) + 1).map(|i| [rand(i * 53 + 300), rand(i * 59 + 300), rand(i * 67 + 300)]).collect(),
+            rock: (seed % 7 == 0).then(|| [rand(400), rand(401)]),
+            mushroom: (seed % 11 == 0).then(|| [rand(500), rand(501)]),
+        },
+        LaneType::Water => LaneDetails::Water {
+            crests: count(12).map(|i| [rand(i * 7 + 600), rand(i * 11 + 600), rand(i * 17 + 600)]).collect(),
+            depths: count(8).map(|i| [rand(i * 13 + 700), rand(i * 19 + 700)]).collect(),
+            foam: count(6).map(|i| [rand(i * 23 + 800), rand(i * 29 + 800)]).collect(),
+            lily_pad: (seed % 5 == 0).then(|| ([rand(900), rand(901)], seed % 10 == 0)),
+        },
+        _ => LaneDetails::None,
+    }
 }
 
 fn create_car_mesh(body_r: f32, body_g: f32, body_b: f32) -> Mesh {
@@ -1098,3 +3604,804 @@ fn create_car_mesh(body_r: f32, body_g: f32, body_b: f32) -> Mesh {
     
     Mesh { vertices, indices }
 }
+
+fn device_pixel_ratio() -> f32 {
+    web_sys::window().map(|w| w.device_pixel_ratio()).unwrap_or(1.0) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn player(x: f32) -> GameObject {
+        GameObject { kind: ObjectKind::Player, ..GameObject::new(x, 0.5, 0.0, 1.0, 1.0, 1.0, (1.0, 1.0, 1.0)) }
+    }
+
+    fn log() -> GameObject {
+        GameObject { kind: ObjectKind::Log, ..GameObject::new(0.0, 0.3, 0.0, 3.0, 0.6, 1.5, (0.5, 0.3, 0.1)) }
+    }
+
+    fn raw() -> CollisionMargins {
+        CollisionMargins { enabled: false, ..CollisionMargins::default() }
+    }
+
+    #[derive(PartialEq, Debug)]
+    enum Placed {
+        Object(ObjectKind),
+        Puddle,
+        Decoration(DecorationKind),
+    }
+
+    type Layout = Vec<(Placed, f32, f32, f32)>;
+
+    // Everything a lane places, in a form that compares
+    fn layout(lane: &Lane) -> Layout {
+        lane.obstacles.iter().chain(&lane.coins).chain(&lane.power_ups)
+            .map(|o| (Placed::Object(o.kind), o.x, o.velocity_x, o.width))
+            .chain(lane.puddles.iter().map(|p| (Placed::Puddle, p.x, p.dz, p.width)))
+            .chain(lane.decorations.iter().map(|d| (Placed::Decoration(d.kind), d.x, d.dz, 0.0)))
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_lays_out_the_same_lanes() {
+        let params = DifficultyConfig::default();
+        for seed in [1, 42, 0xdead_beef] {
+            for index in -5..80 {
+                let z = index as f32 * 2.0;
+                let first = create_lane_procedural(z, index, seed, DEFAULT_HALF_WIDTH, &params);
+                let second = create_lane_procedural(z, index, seed, DEFAULT_HALF_WIDTH, &params);
+                assert!(first.lane_type == second.lane_type);
+                assert_eq!(layout(&first), layout(&second));
+            }
+        }
+    }
+
+    // restart_same_world rebuilds the run from nothing but the seed, so a seed has to lay out
+    // the same lanes in any session, not just twice in a row
+    #[test]
+    fn seed_keeps_its_fixed_layout() {
+        let params = DifficultyConfig::default();
+        let lanes: Vec<Lane> = (1..=16).map(|index| create_lane_procedural(index as f32 * 2.0, index, 42, DEFAULT_HALF_WIDTH, &params)).collect();
+        let types: String = lanes.iter().map(|lane| match lane.lane_type {
+            LaneType::Grass => 'G',
+            LaneType::Road => 'R',
+            LaneType::Water => 'W',
+            LaneType::Rail => 'T',
+            LaneType::Finish => 'F',
+        }).collect();
+        assert_eq!(types, "GGGRRWRRWWRWGRRG");
+
+        let cars: Vec<(f32, f32)> = lanes[7].obstacles.iter().map(|car| (car.x, car.velocity_x)).collect();
+        let expected = [(-11.5638, -0.042402), (0.3658, -0.042402)];
+        assert_eq!(cars.len(), expected.len());
+        for ((x, velocity), (expected_x, expected_velocity)) in cars.into_iter().zip(expected) {
+            assert!((x - expected_x).abs() < 1e-3 && (velocity - expected_velocity).abs() < 1e-5, "car at {} moving {}", x, velocity);
+        }
+    }
+
+    #[test]
+    fn other_seeds_lay_out_other_lanes() {
+        let params = DifficultyConfig::default();
+        let lanes = |seed| -> Vec<_> {
+            (1..40).map(|index| layout(&create_lane_procedural(index as f32 * 2.0, index, seed, DEFAULT_HALF_WIDTH, &params))).collect()
+        };
+        assert_ne!(lanes(1), lanes(2));
+    }
+
+    fn config(difficulty: &str) -> AppConfig {
+        let json = format!(r#"{{
+            "car_model": {{ "path": "car.glb", "scale": 1.0, "rotation_offset_x": 0.0, "rotation_offset_y": 0.0,
+                "rotation_offset_z": 0.0, "position_offset_y": 0.0 }}
+            {}
+        }}"#, difficulty);
+        serde_json::from_str(&json).unwrap()
+    }
+
+    fn lanes_with(params: &DifficultyConfig) -> Vec<Layout> {
+        (1..80).map(|index| layout(&create_lane_procedural(index as f32 * 2.0, index, 42, DEFAULT_HALF_WIDTH, params))).collect()
+    }
+
+    // Configs from before the section existed keep the curve that used to be hard-coded
+    #[test]
+    fn config_without_difficulty_keeps_the_old_curve() {
+        let params = config("").difficulty;
+        assert_eq!((params.ramp_lanes, params.max_multiplier), (20.0, 1.5));
+        assert_eq!((params.base_car_speed, params.car_speed_per_difficulty), (0.02, 0.03));
+        assert_eq!(params.water_log_min, 2);
+        assert_eq!((params.coin_chance_road, params.coin_chance_water, params.coin_chance_grass), (0.3, 0.3, 0.4));
+        assert_eq!(lanes_with(&params), lanes_with(&DifficultyConfig::default()));
+
+        // A partial section only overrides what it names
+        let partial = config(r#", "difficulty": { "ramp_lanes": 40.0 }"#).difficulty;
+        assert_eq!((partial.ramp_lanes, partial.max_multiplier, partial.coin_chance_grass), (40.0, 1.5, 0.4));
+    }
+
+    #[test]
+    fn tuned_difficulty_changes_the_lanes() {
+        let params = config(r#", "difficulty": { "base_car_speed": 0.05, "coin_chance_grass": 0.0, "coin_chance_road": 0.0 }"#).difficulty;
+        assert_ne!(lanes_with(&params), lanes_with(&DifficultyConfig::default()));
+        for index in 1..80 {
+            let lane = create_lane_procedural(index as f32 * 2.0, index, 42, DEFAULT_HALF_WIDTH, &params);
+            let default = create_lane_procedural(index as f32 * 2.0, index, 42, DEFAULT_HALF_WIDTH, &DifficultyConfig::default());
+            if matches!(lane.lane_type, LaneType::Grass | LaneType::Road) {
+                assert!(lane.coins.is_empty());
+            }
+            if lane.lane_type == LaneType::Road {
+                let fastest = |lane: &Lane| lane.obstacles.iter().map(|car| car.velocity_x.abs()).fold(0.0, f32::max);
+                assert!(fastest(&lane) > fastest(&default));
+            }
+        }
+    }
+
+    // Ticks each frame runs, carrying the leftover from frame to frame like update() does
+    fn ticks_per_frame(dts: &[f64]) -> Vec<u32> {
+        let mut accumulator = 0.0;
+        dts.iter().map(|&dt| {
+            let (ticks, left) = ticks_for(accumulator, dt);
+            accumulator = left;
+            assert!((0.0..TICK_SECONDS).contains(&left));
+            ticks
+        }).collect()
+    }
+
+    #[test]
+    fn steady_60hz_runs_one_tick_a_frame() {
+        assert!(ticks_per_frame(&[TICK_SECONDS; 120]).iter().all(|&ticks| ticks == 1));
+    }
+
+    // Frames a little early or late still add up to the real time they cover
+    #[test]
+    fn jittery_60hz_keeps_up_with_real_time() {
+        let dts: Vec<f64> = (0..120).map(|i| if i % 2 == 0 { 0.0157 } else { 0.0176 }).collect();
+        let ticks = ticks_per_frame(&dts);
+        assert!(ticks.iter().all(|&ticks| ticks <= 2));
+        let total: u32 = ticks.iter().sum();
+        let expected = dts.iter().sum::<f64>() / TICK_SECONDS;
+        assert!((total as f64 - expected).abs() <= 1.0);
+    }
+
+    #[test]
+    fn fast_display_runs_a_tick_every_few_frames() {
+        let ticks = ticks_per_frame(&[1.0 / 144.0; 144]);
+        assert!(ticks.iter().all(|&ticks| ticks <= 1));
+        let total: u32 = ticks.iter().sum();
+        assert!((59..=60).contains(&total));
+    }
+
+    #[test]
+    fn stalled_frame_catches_up_at_most_a_quarter_second() {
+        let ticks = ticks_per_frame(&[2.0])[0];
+        assert!((14..=15).contains(&ticks));
+    }
+
+    #[test]
+    fn blend_sweeps_small_steps_and_jumps_wraps() {
+        assert_eq!(blend(1.0, 1.5, 0.5), 1.25);
+        assert_eq!(blend(-9.0, 9.0, 0.5), 9.0);
+    }
+
+    // Boxes touch at 1.5 apart, the car's 15% smaller hitbox only at 1.35
+    #[test]
+    fn shrunk_car_misses_near_its_bumper() {
+        let car = GameObject::new_car(0.0, 0.5, 0.0, 2.0, 1.0, 1.5, 0);
+        let margins = CollisionMargins::default();
+        assert!(player(1.4).collides_horizontal(&car, &raw()));
+        assert!(!player(1.4).collides_horizontal(&car, &margins));
+        assert!(player(1.3).collides_horizontal(&car, &margins));
+    }
+
+    // Boxes touch at 2.0 apart, the log's 10% larger hitbox reaches 2.15
+    #[test]
+    fn enlarged_log_catches_edge_landings() {
+        let margins = CollisionMargins::default();
+        assert!(player(2.1).collides_horizontal(&log(), &margins));
+        assert!(!player(2.1).collides_horizontal(&log(), &raw()));
+        assert!(!player(2.2).collides_horizontal(&log(), &margins));
+    }
+
+    // The drawn car reaches 1.5 from the player's center, its narrower hitbox only 1.2
+    #[test]
+    fn collisions_use_the_hitbox_not_the_drawn_size() {
+        let car = GameObject { hit_width: 1.4, hit_depth: 1.0, ..GameObject::new_car(0.0, 0.5, 0.0, 2.0, 1.0, 1.5, 0) };
+        assert!(player(1.1).collides_horizontal(&car, &raw()));
+        assert!(!player(1.3).collides_horizontal(&car, &raw()));
+        assert!(!GameObject { z: 1.1, ..player(0.0) }.collides_horizontal(&car, &raw()));
+        assert!(GameObject { z: 1.1, ..player(0.0) }.collides_horizontal(&GameObject::new_car(0.0, 0.5, 0.0, 2.0, 1.0, 1.5, 0), &raw()));
+        // Margins scale the hitbox
+        let margins = CollisionMargins { car: -0.5, ..CollisionMargins::default() };
+        assert!(player(0.8).collides_horizontal(&car, &margins));
+        assert!(!player(0.9).collides_horizontal(&car, &margins));
+
+        let mut coin = GameObject::new_coin(0.0, 0.5, 0.0);
+        coin.set_coin_kind(CoinKind::Diamond);
+        assert_eq!((coin.hit_width, coin.hit_depth), (coin.width, coin.depth));
+    }
+
+    #[test]
+    fn disabled_margins_use_the_drawn_boxes() {
+        let margins = CollisionMargins { enabled: false, car: -0.5, log: 0.5, ..CollisionMargins::default() };
+        let car = GameObject::new_car(0.0, 0.5, 0.0, 2.0, 1.0, 1.5, 0);
+        assert_eq!(margins.margin(ObjectKind::Car), 0.0);
+        assert!(player(1.45).collides_horizontal(&car, &margins));
+        assert!(!player(2.05).collides_horizontal(&log(), &margins));
+    }
+
+    #[test]
+    fn each_kind_reads_its_own_margin() {
+        let margins = CollisionMargins {
+            enabled: true, player: 0.01, car: 0.02, truck: 0.03, log: 0.04, lily_pad: 0.05, tree: 0.06, rock: 0.07, coin: 0.08,
+        };
+        let kinds = [
+            (ObjectKind::Player, 0.01), (ObjectKind::Car, 0.02), (ObjectKind::Truck, 0.03), (ObjectKind::Log, 0.04),
+            (ObjectKind::LilyPad, 0.05), (ObjectKind::Tree, 0.06), (ObjectKind::Rock, 0.07),
+            (ObjectKind::Coin(CoinKind::Gold), 0.08), (ObjectKind::Coin(CoinKind::Diamond), 0.08),
+            (ObjectKind::PowerUp(PowerUpKind::Shield), 0.08), (ObjectKind::Other, 0.0),
+        ];
+        for (kind, margin) in kinds {
+            assert_eq!(margins.margin(kind), margin, "{:?}", kind);
+            assert_eq!(CollisionMargins { enabled: false, ..margins.clone() }.margin(kind), 0.0, "{:?}", kind);
+        }
+        // A box never shrinks to nothing
+        assert_eq!(CollisionMargins { car: -3.0, ..margins }.margin(ObjectKind::Car), -0.9);
+    }
+
+    #[test]
+    fn inbound_car_reaches_the_column_in_time() {
+        // 0.1 per tick covers 9.375 units in 1.5 seconds, the reach adds one more
+        assert!(reaches_column(-10.0, 0.1, 1.0, 0.0, EDGE_WARNING_SECONDS));
+        assert!(reaches_column(10.0, -0.1, 1.0, 0.0, EDGE_WARNING_SECONDS));
+        assert!(!reaches_column(-11.0, 0.1, 1.0, 0.0, EDGE_WARNING_SECONDS));
+        assert!(!reaches_column(-10.0, 0.05, 1.0, 0.0, EDGE_WARNING_SECONDS));
+    }
+
+    #[test]
+    fn car_moving_away_or_already_past_never_reaches() {
+        assert!(!reaches_column(-5.0, -0.1, 1.0, 0.0, EDGE_WARNING_SECONDS));
+        assert!(!reaches_column(3.0, 0.1, 1.0, 0.0, EDGE_WARNING_SECONDS));
+        // Still overlapping the column on the way out counts
+        assert!(reaches_column(0.5, 0.1, 1.0, 0.0, EDGE_WARNING_SECONDS));
+    }
+
+    #[test]
+    fn stopped_car_only_counts_when_overlapping() {
+        assert!(reaches_column(0.8, 0.0, 1.0, 0.0, EDGE_WARNING_SECONDS));
+        assert!(!reaches_column(1.2, 0.0, 1.0, 0.0, EDGE_WARNING_SECONDS));
+    }
+
+    const DATE: &str = "2025-06-01";
+
+    #[test]
+    fn share_text_format_is_stable() {
+        let history = [LaneType::Grass, LaneType::Road, LaneType::Water, LaneType::Road, LaneType::Grass];
+        let text = format_share_text(DATE, 4, 12, &history, None, None);
+        assert_eq!(text, "Crossy 2025-06-01 — 4 lanes, 12 coins\n🟩⬛🟦⬛🟩");
+        let died = format_share_text(DATE, 4, 12, &history, Some(4), None);
+        assert_eq!(died, "Crossy 2025-06-01 — 4 lanes, 12 coins\n🟩⬛🟦⬛💀");
+    }
+
+    #[test]
+    fn share_text_names_the_theme() {
+        let history = [LaneType::Grass, LaneType::Road];
+        let themed = format_share_text(DATE, 1, 3, &history, Some(1), Some("winter-2025-12-24"));
+        assert_eq!(themed, "Crossy 2025-06-01 (winter-2025-12-24) — 1 lanes, 3 coins\n🟩💀");
+    }
+
+    #[test]
+    fn share_strip_keeps_the_last_lanes() {
+        let history: Vec<LaneType> = (0..30).map(|i| if i % 2 == 0 { LaneType::Grass } else { LaneType::Road }).collect();
+        let text = format_share_text(DATE, 29, 0, &history, Some(29), None);
+        let strip = text.lines().nth(1).unwrap();
+        assert_eq!(strip.chars().count(), SHARE_STRIP_LANES);
+        assert!(strip.ends_with("🟩💀"));
+        assert!(strip.starts_with("🟩⬛"));
+    }
+
+    #[test]
+    fn lanes_are_recorded_once() {
+        let mut history = Vec::new();
+        assert!(record_lane(&mut history, 0, LaneType::Grass));
+        assert!(record_lane(&mut history, 1, LaneType::Road));
+        assert!(!record_lane(&mut history, 1, LaneType::Road));
+        assert!(!record_lane(&mut history, -1, LaneType::Grass));
+        assert_eq!(history.len(), 2);
+    }
+
+    fn lane(index: i32, lane_type: LaneType, obstacles: Vec<GameObject>) -> Lane {
+        Lane { z: index as f32 * 2.0, lane_type, obstacles, coins: Vec::new(), puddles: Vec::new(), decorations: Vec::new(), power_ups: Vec::new(), details: LaneDetails::None }
+    }
+
+    fn car_at(x: f32) -> GameObject {
+        GameObject::new_car(x, 0.5, 0.0, 2.0, 1.0, 1.0, 0)
+    }
+
+    fn player_at(x: f32) -> GameObject {
+        GameObject::new(x, 0.5, 0.0, 1.0, 1.0, 1.0, (1.0, 1.0, 1.0))
+    }
+
+    #[test]
+    fn idle_on_grass_is_quiet() {
+        let lanes: Vec<Lane> = (-1..=1).map(|i| lane(i, LaneType::Grass, Vec::new())).collect();
+        let intensity = crossy_intensity(&lanes, &player_at(0.0), &DifficultyConfig::default());
+        assert!((intensity - 0.1).abs() < 0.02, "got {}", intensity);
+    }
+
+    #[test]
+    fn car_about_to_hit_is_loud() {
+        let lanes = vec![lane(0, LaneType::Road, vec![car_at(1.0)]), lane(1, LaneType::Grass, Vec::new())];
+        let intensity = crossy_intensity(&lanes, &player_at(0.0), &DifficultyConfig::default());
+        assert!((intensity - 0.9).abs() < 0.05, "got {}", intensity);
+
+        // Further down the road the danger fades back toward idle
+        let far = vec![lane(0, LaneType::Road, vec![car_at(10.0)]), lane(1, LaneType::Grass, Vec::new())];
+        let distant = crossy_intensity(&far, &player_at(0.0), &DifficultyConfig::default());
+        assert!(distant < 0.2, "got {}", distant);
+    }
+
+    #[test]
+    fn intensity_never_passes_one() {
+        let cars = || (-3..3).map(|k| car_at(k as f32 * 2.5)).collect::<Vec<_>>();
+        let lanes = vec![lane(99, LaneType::Road, cars()), lane(100, LaneType::Road, cars()), lane(101, LaneType::Road, cars())];
+        let player = GameObject { z: 200.0, ..player_at(0.0) };
+        assert_eq!(crossy_intensity(&lanes, &player, &DifficultyConfig::default()), 1.0);
+    }
+
+    // Ticks standing on the same log until just past the patient window
+    fn patient_ticks() -> usize {
+        (PATIENT_RIDE_SECONDS / SIM_DT) as usize + 1
+    }
+
+    #[test]
+    fn standing_on_one_log_earns_patient_once() {
+        let mut streaks = StyleStreaks::default();
+        let awards: Vec<usize> = (0..patient_ticks() * 3).filter(|_| streaks.ride(4, Some(1), false)).collect();
+        assert_eq!(awards, vec![patient_ticks() - 1]);
+    }
+
+    #[test]
+    fn hopping_or_changing_logs_restarts_the_ride() {
+        let mut streaks = StyleStreaks::default();
+        for _ in 0..patient_ticks() - 2 {
+            assert!(!streaks.ride(4, Some(1), false));
+        }
+        // Mid-hop, even over the same log
+        assert!(!streaks.ride(4, Some(1), true));
+        for _ in 0..patient_ticks() - 2 {
+            assert!(!streaks.ride(4, Some(1), false));
+        }
+        // Another log in the same lane, then the same index in the next lane
+        assert!(!streaks.ride(4, Some(2), false));
+        assert!(!streaks.ride(5, Some(2), false));
+        assert_eq!(streaks.log_ride.as_ref().map(|ride| ride.seconds), Some(SIM_DT));
+    }
+
+    // The ride follows the log's index, so another log in the lane wrapping around
+    // while the player drifts doesn't end it
+    #[test]
+    fn log_wrapping_mid_ride_keeps_the_streak() {
+        let wrap = wrap_bound(DEFAULT_HALF_WIDTH);
+        let log = |x: f32| GameObject { velocity_x: 0.02, ..GameObject::new(x, 0.3, 0.0, 3.0, 0.6, 1.5, (0.5, 0.3, 0.1)) };
+        let mut logs = [log(wrap - 0.5), log(0.0)];
+        let mut player = GameObject::new(0.0, 0.5, 0.0, 1.0, 1.0, 1.0, (1.0, 1.0, 1.0));
+        let mut streaks = StyleStreaks::default();
+        let (mut wrapped, mut awarded) = (false, false);
+        for _ in 0..patient_ticks() {
+            for log in logs.iter_mut() {
+                let x = wrap_around(log.x + log.velocity_x, wrap);
+                wrapped |= x < log.x;
+                log.x = x;
+            }
+            let riding = logs.iter().position(|log| player.collides_horizontal(log, &raw()));
+            assert_eq!(riding, Some(1));
+            player.x += logs[1].velocity_x;
+            awarded |= streaks.ride(4, riding, false);
+        }
+        assert!(wrapped, "the other log should cross the bound");
+        assert!(awarded);
+    }
+
+    #[test]
+    fn rapid_crossing_needs_consecutive_water_inside_the_window() {
+        let mut streaks = StyleStreaks::default();
+        let step = RAPID_CROSSING_SECONDS / RAPID_CROSSING_LANES as f32 * 0.9;
+        let landings: Vec<bool> = (0..RAPID_CROSSING_LANES).map(|k| streaks.land(LaneType::Water, k as f32 * step)).collect();
+        assert_eq!(landings.iter().filter(|&&rapid| rapid).count(), 1);
+        assert_eq!(landings.last(), Some(&true));
+        // Only once per streak
+        assert!(!streaks.land(LaneType::Water, RAPID_CROSSING_LANES as f32 * step));
+
+        // Dry land in between starts over
+        let mut streaks = StyleStreaks::default();
+        streaks.land(LaneType::Water, 0.0);
+        streaks.land(LaneType::Grass, 0.1);
+        assert!(!(1..RAPID_CROSSING_LANES).any(|k| streaks.land(LaneType::Water, 0.1 + k as f32 * 0.1)));
+
+        // Too slow: the first landing leaves the window before the last one arrives
+        let mut streaks = StyleStreaks::default();
+        let slow = RAPID_CROSSING_SECONDS / (RAPID_CROSSING_LANES - 1) as f32 + 0.01;
+        assert!(!(0..RAPID_CROSSING_LANES).any(|k| streaks.land(LaneType::Water, k as f32 * slow)));
+    }
+
+    // Steps every lane's traffic the way a tick does, trains aside
+    fn run_traffic(lanes: &mut [Lane], half_width: f32, ticks: usize) {
+        let wrap = wrap_bound(half_width);
+        for _ in 0..ticks {
+            advance_positions(lanes, 1.0);
+            wrap_positions(lanes, wrap);
+        }
+    }
+
+    #[test]
+    fn traffic_wraps_inside_the_bounds_at_any_width() {
+        let params = DifficultyConfig::default();
+        for half_width in [6.0, 16.0] {
+            let wrap = wrap_bound(half_width);
+            let mut lanes: Vec<Lane> = (1..60).map(|i| create_lane_procedural(i as f32 * 2.0, i, 7, half_width, &params)).collect();
+            run_traffic(&mut lanes, half_width, 5000);
+            // Trains wait past the bound until their schedule brings them in
+            let traffic = lanes.iter().filter(|lane| lane.lane_type != LaneType::Rail);
+            for object in traffic.flat_map(|lane| lane.obstacles.iter().chain(&lane.coins)) {
+                assert!(object.x.abs() <= wrap, "x {} outside ±{}", object.x, wrap);
+            }
+        }
+    }
+
+    #[test]
+    fn traffic_leaving_one_side_comes_back_on_the_other() {
+        for half_width in [6.0, 16.0] {
+            let wrap = wrap_bound(half_width);
+            assert_eq!(wrap_around(wrap + 0.1, wrap), -wrap);
+            assert_eq!(wrap_around(-wrap - 0.1, wrap), wrap);
+            assert_eq!(wrap_around(half_width, wrap), half_width);
+        }
+    }
+
+    // A car 0.1 short of the bound, heading out at 0.2 a tick: it wraps in play, drives on
+    // past the bound during the death review and stays put in photo mode
+    #[test]
+    fn car_at_the_wrap_bound_follows_the_traffic_mode() {
+        let wrap = wrap_bound(DEFAULT_HALF_WIDTH);
+        let road = || {
+            let mut car = GameObject::new_car(wrap - 0.1, 0.5, 2.0, 2.0, 1.0, 1.5, 0);
+            car.velocity_x = 0.2;
+            Lane {
+                z: 2.0, lane_type: LaneType::Road, obstacles: vec![car], coins: Vec::new(), puddles: Vec::new(),
+                decorations: Vec::new(), power_ups: Vec::new(), details: LaneDetails::None,
+            }
+        };
+        let car_x = |mode: TrafficMode, ticks: usize| {
+            let mut lanes = [road()];
+            for _ in 0..ticks {
+                step_traffic(&mut lanes, mode, 7, wrap, 0.0);
+            }
+            lanes[0].obstacles[0].x
+        };
+        assert_eq!(car_x(TrafficMode::Normal, 1), -wrap);
+        assert!((car_x(TrafficMode::NoWrap, 1) - (wrap - 0.1 + 0.2 * DEATH_REVIEW_SPEED)).abs() < 1e-5);
+        assert!((car_x(TrafficMode::NoWrap, 10) - (wrap - 0.1 + 2.0 * DEATH_REVIEW_SPEED)).abs() < 1e-5);
+        assert_eq!(car_x(TrafficMode::Frozen, 10), wrap - 0.1);
+
+        // The building blocks on their own: one tick's move, then the wrap
+        let mut lanes = [road()];
+        advance_positions(&mut lanes, 1.0);
+        assert!((lanes[0].obstacles[0].x - (wrap + 0.1)).abs() < 1e-5);
+        wrap_positions(&mut lanes, wrap);
+        assert_eq!(lanes[0].obstacles[0].x, -wrap);
+        // Still inside the bound, nothing to wrap
+        let mut lanes = [road()];
+        wrap_positions(&mut lanes, wrap);
+        assert_eq!(lanes[0].obstacles[0].x, wrap - 0.1);
+    }
+
+    // A seed replays its trains tick for tick, and every pass is preceded by exactly the
+    // warning's worth of blinking with the train waiting at the start
+    #[test]
+    fn trains_keep_their_schedule_and_warn_before_each_pass() {
+        let wrap = wrap_bound(DEFAULT_HALF_WIDTH);
+        let schedule = |seed: u32, index: i32| -> Vec<(f32, f32, bool)> {
+            (0..2000).map(|tick| train_state(seed, index, tick as f32 * SIM_DT, wrap, TRAIN_LENGTH)).collect()
+        };
+        assert!(schedule(7, 3) == schedule(7, 3));
+        assert!(schedule(7, 3) != schedule(8, 3));
+
+        let warning_ticks = (TRAIN_WARNING_SECONDS / SIM_DT) as usize;
+        for index in 1..6 {
+            let states = schedule(7, index);
+            let start = states.iter().find(|(_, velocity, _)| *velocity == 0.0).unwrap().0;
+            let passes: Vec<usize> = (1..states.len())
+                .filter(|&tick| states[tick - 1].0 == start && states[tick].0 != start)
+                .collect();
+            assert!(passes.len() >= 2, "lane {} only passed {} times", index, passes.len());
+            for &pass in passes.iter().filter(|&&pass| pass > warning_ticks + 1) {
+                assert!((pass - warning_ticks..pass).all(|tick| states[tick].2), "lane {} warned late", index);
+                let before = &states[pass - warning_ticks - 2];
+                assert!(!before.2 && before.1 == 0.0, "lane {} warned early", index);
+            }
+        }
+    }
+
+    // Four hops back from the furthest point is still allowed, the fifth is one too many
+    #[test]
+    fn camera_kills_past_four_hops_back() {
+        assert_eq!(CAMERA_KILL_DISTANCE, 8.0);
+        assert!(!left_behind(20.0, 20.0 - 4.0 * 2.0));
+        assert!(!left_behind(20.0, 20.0 - CAMERA_KILL_DISTANCE));
+        assert!(left_behind(20.0, 20.0 - CAMERA_KILL_DISTANCE - 0.01));
+        assert!(left_behind(20.0, 20.0 - 5.0 * 2.0));
+    }
+
+    // The player stops at the field edge, well inside where traffic turns around
+    #[test]
+    fn player_clamps_to_the_field_edge() {
+        for half_width in [6.0, 16.0] {
+            assert_eq!(clamp_to_field(half_width + 3.0, half_width), half_width);
+            assert_eq!(clamp_to_field(-half_width - 3.0, half_width), -half_width);
+            assert_eq!(clamp_to_field(half_width - 1.0, half_width), half_width - 1.0);
+            assert!(half_width < wrap_bound(half_width));
+        }
+    }
+
+    #[test]
+    fn wide_rivers_get_more_logs() {
+        let params = DifficultyConfig::default();
+        let logs = |half_width: f32| -> usize {
+            (1..200)
+                .map(|i| create_lane_procedural(i as f32 * 2.0, i, 7, half_width, &params))
+                .filter(|lane| lane.lane_type == LaneType::Water)
+                .map(|lane| lane.obstacles.iter().filter(|o| o.kind == ObjectKind::Log).count())
+                .sum()
+        };
+        assert!(logs(16.0) > logs(6.0) * 2);
+    }
+
+    #[test]
+    fn footing_keeps_the_player_inside_the_log() {
+        let long = GameObject { x: 1.0, ..log() };
+        assert_eq!(log_footing(&long, &player(0.0)), (0.0, 2.0));
+        // A log narrower than the player only holds them centered
+        let short = GameObject { x: 1.0, width: 0.8, ..log() };
+        assert_eq!(log_footing(&short, &player(0.0)), (1.0, 1.0));
+    }
+
+    #[test]
+    fn landings_snap_to_a_column_the_log_covers() {
+        assert_eq!(landing_x(0.7, (0.0, 2.0)), 0.0);
+        assert_eq!(landing_x(1.2, (0.0, 2.0)), 2.0);
+        assert_eq!(landing_x(-1.5, (0.0, 2.0)), 0.0);
+        assert_eq!(landing_x(3.4, (0.0, 2.0)), 2.0);
+        // No whole column between 0.3 and 1.7, the nearest footing will do
+        assert_eq!(landing_x(1.2, (0.3, 1.7)), 1.2);
+        assert_eq!(landing_x(5.0, (0.3, 1.7)), 1.7);
+    }
+
+    // A log carries the player off the field and lets them drown once it's LOG_EDGE_MARGIN past
+    // the edge, on either side
+    #[test]
+    fn logs_drop_the_player_past_the_edge_margin() {
+        let bound = DEFAULT_HALF_WIDTH + LOG_EDGE_MARGIN;
+        for side in [-1.0, 1.0] {
+            let hazard = |x: f32| {
+                let river = lane(0, LaneType::Water, vec![GameObject { x, ..log() }]);
+                touch_lane(&river, &mut player(x), false, DEFAULT_HALF_WIDTH, &raw()).hazard
+            };
+            assert_eq!(hazard(side * (bound - 0.1)), None);
+            assert_eq!(hazard(side * bound), None);
+            assert_eq!(hazard(side * (bound + 0.1)), Some("water"));
+        }
+        // Mid-hop nothing drowns yet
+        let river = lane(0, LaneType::Water, vec![GameObject { x: bound + 0.1, ..log() }]);
+        assert_eq!(touch_lane(&river, &mut player(bound + 0.1), true, DEFAULT_HALF_WIDTH, &raw()).hazard, None);
+    }
+
+    #[test]
+    fn power_ups_only_turn_up_where_they_can_be_reached() {
+        let (mut placed, mut magnets) = (0, 0);
+        for seed in 0..20 {
+            for index in 0..400 {
+                for lane_type in [LaneType::Grass, LaneType::Road, LaneType::Water, LaneType::Rail] {
+                    let power_ups = create_power_ups(lane_type, index as f32 * 2.0, index, seed, DEFAULT_HALF_WIDTH, &[]);
+                    if index < 5 || matches!(lane_type, LaneType::Water | LaneType::Rail) {
+                        assert!(power_ups.is_empty());
+                        continue;
+                    }
+                    assert!(power_ups.len() <= 1);
+                    for power_up in &power_ups {
+                        assert_eq!(power_up.x.rem_euclid(2.0), 0.0);
+                        assert!(power_up.x.abs() <= DEFAULT_HALF_WIDTH);
+                        placed += 1;
+                        magnets += (power_up.kind == ObjectKind::PowerUp(PowerUpKind::Magnet)) as usize;
+                    }
+
+                    // A tree in the way drops a grass power-up but not a road one
+                    if let Some(power_up) = power_ups.first() {
+                        let tree = [GameObject::new(power_up.x, 0.5, power_up.z, 1.0, 1.0, 1.0, (0.1, 0.5, 0.1))];
+                        let blocked = create_power_ups(lane_type, power_up.z, index, seed, DEFAULT_HALF_WIDTH, &tree);
+                        assert_eq!(blocked.is_empty(), lane_type == LaneType::Grass);
+                    }
+                }
+            }
+        }
+        assert!(placed > 0 && magnets > 0 && magnets < placed);
+    }
+
+    #[test]
+    fn shield_takes_one_hit_then_runs_out_its_grace() {
+        let mut power_ups = PowerUps::default();
+        power_ups.pick(PowerUpKind::Shield);
+        assert_eq!(power_ups.hud(), Some((PowerUpKind::Shield, 0)));
+        assert!(!power_ups.tick());
+        assert_eq!(power_ups.held, Some(PowerUpKind::Shield));
+
+        power_ups.use_shield();
+        assert_eq!(power_ups.held, None);
+        assert_eq!(power_ups.shield_grace, SHIELD_GRACE_SECONDS);
+        let ticks = (SHIELD_GRACE_SECONDS / SIM_DT).ceil() as usize;
+        for _ in 0..ticks - 1 {
+            power_ups.tick();
+        }
+        assert!(power_ups.shield_grace > 0.0);
+        power_ups.tick();
+        assert_eq!(power_ups.shield_grace, 0.0);
+    }
+
+    #[test]
+    fn magnet_runs_out_and_restart_clears_it() {
+        let mut power_ups = PowerUps::default();
+        power_ups.pick(PowerUpKind::Magnet);
+        assert_eq!(power_ups.hud(), Some((PowerUpKind::Magnet, MAGNET_SECONDS as i32)));
+        let pulling = (0..1000).take_while(|_| power_ups.tick()).count();
+        assert!(pulling.abs_diff((MAGNET_SECONDS / SIM_DT) as usize) <= 1, "pulled for {} ticks", pulling);
+        assert_eq!(power_ups.hud(), None);
+
+        // What reset_run leaves behind, with a magnet and a spent shield in play before it
+        power_ups.pick(PowerUpKind::Magnet);
+        power_ups.shield_grace = SHIELD_GRACE_SECONDS;
+        assert!(power_ups.tick());
+        power_ups = PowerUps::default();
+        assert_eq!(power_ups.hud(), None);
+        assert_eq!(power_ups.shield_grace, 0.0);
+        assert!(!power_ups.tick());
+    }
+
+    #[test]
+    fn released_coins_drift_with_their_logs_again() {
+        let mut river = lane(0, LaneType::Water, vec![GameObject { velocity_x: 0.03, ..log() }]);
+        river.coins.push(GameObject { velocity_x: 0.03, ..GameObject::new_coin(2.0, 0.8, 0.0) });
+        let mut lanes = [river, lane(1, LaneType::Road, Vec::new())];
+        lanes[1].coins.push(GameObject::new_coin(0.0, 0.5, 2.0));
+
+        attract_coins(&mut lanes, (0.0, 0.0), true);
+        assert_eq!(lanes[0].coins[0].velocity_x, 0.0);
+        assert!(lanes[0].coins[0].x < 2.0);
+        // Out of range of a player further along
+        attract_coins(&mut lanes, (20.0, 0.0), true);
+        assert_eq!(lanes[0].coins[0].velocity_x, 0.03);
+        attract_coins(&mut lanes, (0.0, 0.0), true);
+        assert_eq!(lanes[0].coins[0].velocity_x, 0.0);
+        // The magnet running out lets go of it in range too
+        attract_coins(&mut lanes, (0.0, 0.0), false);
+        assert_eq!(lanes[0].coins[0].velocity_x, 0.03);
+        assert_eq!(lanes[1].coins[0].velocity_x, 0.0);
+    }
+
+    #[test]
+    fn gems_are_worth_more_than_gold() {
+        assert_eq!((CoinKind::Gold.value(), CoinKind::Gem.value(), CoinKind::Diamond.value()), (1, 5, 20));
+        let mut coin = GameObject::new_coin(0.0, 0.5, 0.0);
+        assert_eq!(coin.coin_kind(), CoinKind::Gold);
+        coin.set_coin_kind(CoinKind::Gem);
+        assert_eq!(coin.coin_kind(), CoinKind::Gem);
+        assert!(coin.width > COIN_SIZE);
+    }
+
+    #[test]
+    fn diamonds_only_turn_up_on_roads() {
+        let params = DifficultyConfig::default();
+        let (mut diamonds, mut off_road_gems) = (0, 0);
+        for seed in 0..20 {
+            for index in 1..300 {
+                let lane = create_lane_procedural(index as f32 * 2.0, index, seed, DEFAULT_HALF_WIDTH, &params);
+                for coin in &lane.coins {
+                    match coin.coin_kind() {
+                        CoinKind::Diamond => {
+                            assert!(lane.lane_type == LaneType::Road);
+                            diamonds += 1;
+                        }
+                        CoinKind::Gem if lane.lane_type != LaneType::Road => off_road_gems += 1,
+                        _ => {}
+                    }
+                }
+            }
+        }
+        assert!(diamonds > 0 && off_road_gems > 0);
+    }
+
+    #[test]
+    fn about_a_third_of_rivers_have_lily_pads() {
+        let params = DifficultyConfig::default();
+        let (mut water, mut pads) = (0, 0);
+        for seed in 0..50 {
+            for index in 1..400 {
+                if lane_type_at(index, seed, &params) == LaneType::Water {
+                    water += 1;
+                    pads += is_lily_pad_lane(index, seed, &params) as usize;
+                }
+            }
+        }
+        let share = pads as f32 / water as f32;
+        assert!((share - 1.0 / 3.0).abs() < 0.02, "{} of water lanes have pads", share);
+    }
+
+    #[test]
+    fn lily_pads_sit_still_on_the_columns() {
+        let params = DifficultyConfig::default();
+        let mut lanes = 0;
+        for seed in 0..20 {
+            for index in (1..200).filter(|&index| is_lily_pad_lane(index, seed, &params)) {
+                let lane = create_lane_procedural(index as f32 * 2.0, index, seed, DEFAULT_HALF_WIDTH, &params);
+                assert!((LILY_PAD_MIN..=LILY_PAD_MAX).contains(&lane.obstacles.len()));
+                for pad in &lane.obstacles {
+                    assert!(pad.kind == ObjectKind::LilyPad);
+                    assert_eq!(pad.x.rem_euclid(2.0), 0.0);
+                    assert!(pad.x.abs() <= DEFAULT_HALF_WIDTH);
+                    assert_eq!(pad.velocity_x, 0.0);
+                }
+                lanes += 1;
+            }
+        }
+        assert!(lanes > 0);
+    }
+
+    // Unlike a log a pad doesn't carry the player, even one that somehow drifts
+    #[test]
+    fn standing_on_a_lily_pad_stays_put() {
+        let mut pad = GameObject { kind: ObjectKind::LilyPad, ..GameObject::new(2.0, -0.15, 0.0, 1.0, 0.1, 1.0, (0.2, 0.55, 0.25)) };
+        pad.velocity_x = 0.05;
+        let water = lane(0, LaneType::Water, vec![pad]);
+        let mut on_pad = player(2.0);
+        for _ in 0..10 {
+            let contact = touch_lane(&water, &mut on_pad, false, DEFAULT_HALF_WIDTH, &raw());
+            assert!(contact.hazard.is_none() && contact.riding.is_none());
+        }
+        assert_eq!(on_pad.x, 2.0);
+
+        let mut beside = player(4.0);
+        assert_eq!(touch_lane(&water, &mut beside, false, DEFAULT_HALF_WIDTH, &raw()).hazard, Some("water"));
+    }
+
+    #[test]
+    fn lane_details_are_rolled_per_lane_type_and_width() {
+        match create_details(LaneType::Grass, 8.0, 20.0) {
+            LaneDetails::Grass { tufts, blades, .. } => assert_eq!((tufts.len(), blades.len()), (40, 60)),
+            _ => panic!("grass lanes get grass details"),
+        }
+        assert!(matches!(create_details(LaneType::Water, 8.0, DEFAULT_HALF_WIDTH), LaneDetails::Water { .. }));
+        assert!(matches!(create_details(LaneType::Road, 8.0, DEFAULT_HALF_WIDTH), LaneDetails::None));
+    }
+
+    fn tint_gap(a: (f32, f32, f32), b: (f32, f32, f32)) -> f32 {
+        (a.0 - b.0).abs().max((a.1 - b.1).abs()).max((a.2 - b.2).abs())
+    }
+
+    #[test]
+    fn day_tint_hits_the_old_colors_at_the_transitions() {
+        for (t, tint) in [(25.0, (1.0, 1.0, 1.0)), (35.0, (1.0, 0.7, 0.5)), (50.0, (0.3, 0.2, 0.4)), (60.0, (1.0, 1.0, 1.0))] {
+            assert!(tint_gap(day_tint(t), tint) < 1e-4, "{} s gave {:?}", t, day_tint(t));
+        }
+        assert!(tint_gap(day_tint(-10.0), day_tint(50.0)) < 1e-4);
+    }
+
+    // The old piecewise tint turned sharply at 35 s; eased keys leave both sides flat
+    #[test]
+    fn day_tint_has_no_jump_or_kink_at_the_transitions() {
+        let h = 0.05;
+        for t in [25.0, 35.0, 50.0] {
+            let (before, at, after) = (day_tint(t - h), day_tint(t), day_tint(t + h));
+            assert!(tint_gap(before, at) < 1e-3 && tint_gap(at, after) < 1e-3, "jump at {} s", t);
+        }
+        // A kink shows as a slope change across the key, eased both slopes near zero there
+        let slope = |t: f32| tint_gap(day_tint(t + h), day_tint(t)) / h;
+        assert!(slope(35.0 - h) < 0.01 && slope(35.0) < 0.01);
+        assert!(slope(30.0) > 0.03, "the tint still moves mid-stretch");
+    }
+}
@@ -0,0 +1,132 @@
+use serde::Serialize;
+
+// Heliocentric positions on the J2000 ecliptic in AU, x toward the equinox and z toward the
+// ecliptic north pole, at 12:00 UTC on each date. They're worked out from JPL's published
+// Keplerian elements with their century rates (Standish, "Approximate Positions of the
+// Planets", the 1800-2050 fit), which stay within a fraction of a degree of the full
+// Horizons ephemeris over these dates. Earth is the Earth-Moon barycenter.
+const REFERENCE_DATES: [(&str, f64); 5] = [
+    ("1990-01-01", 631195200000.0),
+    ("2000-01-01", 946728000000.0),
+    ("2020-01-01", 1577880000000.0),
+    ("2035-01-01", 2051265600000.0),
+    ("2050-01-01", 2524651200000.0),
+];
+const REFERENCE: [(&str, [[f64; 3]; 5]); 8] = [
+    ("Mercury", [[0.148740, 0.271355, 0.008507], [-0.130089, -0.447292, -0.024599], [-0.052200, -0.461875, -0.032954], [0.359568, -0.093947, -0.040649], [-0.193824, 0.260245, 0.039042]]),
+    ("Venus", [[-0.005857, 0.719530, 0.010152], [-0.718316, -0.032707, 0.041016], [0.722389, 0.062544, -0.040828], [-0.578549, 0.424339, 0.039219], [0.151622, -0.711366, -0.018566]]),
+    ("Earth", [[-0.186877, 0.965374, 0.000022], [-0.177171, 0.967214, 0.000000], [-0.174946, 0.967632, -0.000044], [-0.177578, 0.967159, -0.000077], [-0.180209, 0.966678, -0.000109]]),
+    ("Mars", [[-0.970498, -1.205308, -0.001370], [1.390668, -0.013391, -0.034461], [-1.315848, -0.891006, 0.013614], [-1.442781, -0.707665, 0.020521], [-1.540601, -0.510064, 0.027007]]),
+    ("Jupiter", [[-0.576563, 5.122402, -0.008253], [3.998321, 2.945711, -0.101718], [0.532472, -5.202765, 0.009696], [4.739864, 1.427672, -0.111998], [-2.401983, 4.662781, 0.034253]]),
+    ("Saturn", [[2.802954, -9.635415, 0.056647], [6.414784, 6.545667, -0.369147], [3.798605, -9.267246, 0.009990], [-4.626294, 7.811930, 0.048550], [4.753961, -8.786092, -0.037052]]),
+    ("Uranus", [[1.916975, -19.284645, -0.096512], [14.425466, -13.737646, -0.238033], [16.219194, 11.388895, -0.167909], [-2.656108, 18.696948, 0.103690], [-17.821996, 4.076274, 0.245809]]),
+    ("Neptune", [[6.392679, -29.520380, 0.460559], [16.804763, -24.992710, 0.127403], [29.240094, -6.357327, -0.542909], [27.851865, 10.655520, -0.861275], [17.398534, 24.194698, -0.899200]]),
+];
+
+// (body, degrees, AU) the simulation may be off by before the check fails. The current
+// element set stays under a tenth of a degree for everything but Mercury, about a quarter
+// of a degree off at worst, so these leave a little margin over that. What's left is mostly
+// the planets pulling on each other, which fixed elements leave out.
+const TOLERANCES: [(&str, f64, f64); 8] = [
+    ("Mercury", 1.0, 0.01),
+    ("Venus", 0.2, 0.01),
+    ("Earth", 0.2, 0.005),
+    ("Mars", 0.2, 0.01),
+    ("Jupiter", 0.2, 0.01),
+    ("Saturn", 0.2, 0.08),
+    ("Uranus", 0.2, 0.03),
+    ("Neptune", 0.2, 0.04),
+];
+
+#[derive(Serialize)]
+pub struct DateError {
+    pub date: &'static str,
+    pub angular_deg: f64,
+    pub radial_au: f64,
+}
+
+#[derive(Serialize)]
+pub struct BodyReport {
+    pub name: &'static str,
+    pub dates: Vec<DateError>,
+    pub max_angular_deg: f64,
+    pub max_radial_au: f64,
+    pub tolerance_deg: f64,
+    pub tolerance_au: f64,
+    pub passed: bool,
+}
+
+#[derive(Serialize)]
+pub struct AccuracyReport {
+    pub bodies: Vec<BodyReport>,
+    // Bodies the simulation doesn't have, e.g. in the Sirius or black hole systems
+    pub missing: Vec<&'static str>,
+    pub passed: bool,
+}
+
+// Angle between the two directions seen from the Sun and the difference in distance
+fn compare(simulated: [f64; 3], reference: [f64; 3]) -> (f64, f64) {
+    let length = |v: [f64; 3]| (v[0] * v[0] + v[1] * v[1] + v[2] * v[2]).sqrt();
+    let (r_sim, r_ref) = (length(simulated), length(reference));
+    if r_sim == 0.0 || r_ref == 0.0 {
+        return (180.0, (r_sim - r_ref).abs());
+    }
+    let dot = simulated.iter().zip(reference.iter()).map(|(a, b)| a * b).sum::<f64>();
+    ((dot / (r_sim * r_ref)).clamp(-1.0, 1.0).acos().to_degrees(), (r_sim - r_ref).abs())
+}
+
+// sample gives a body's simulated position in the same frame and units as the table, or
+// None when the body isn't there. It's called date by date, every body for one date before
+// moving to the next.
+pub fn run(mut sample: impl FnMut(f64, &str) -> Option<[f64; 3]>) -> AccuracyReport {
+    let mut samples: Vec<Vec<Option<[f64; 3]>>> = vec![Vec::new(); REFERENCE.len()];
+    for (_, time) in REFERENCE_DATES {
+        for (index, (name, _)) in REFERENCE.iter().enumerate() {
+            samples[index].push(sample(time, name));
+        }
+    }
+    let mut report = AccuracyReport { bodies: Vec::new(), missing: Vec::new(), passed: true };
+    for ((name, positions), ((_, tolerance_deg, tolerance_au), simulated)) in REFERENCE.iter().zip(TOLERANCES.iter().zip(samples)) {
+        if simulated.iter().any(Option::is_none) {
+            report.missing.push(*name);
+            continue;
+        }
+        let dates: Vec<DateError> = REFERENCE_DATES.iter().zip(positions.iter().zip(simulated.into_iter().flatten()))
+            .map(|((date, _), (reference, simulated))| {
+                let (angular_deg, radial_au) = compare(simulated, *reference);
+                DateError { date, angular_deg, radial_au }
+            })
+            .collect();
+        let max_angular_deg = dates.iter().map(|d| d.angular_deg).fold(0.0, f64::max);
+        let max_radial_au = dates.iter().map(|d| d.radial_au).fold(0.0, f64::max);
+        let passed = max_angular_deg <= *tolerance_deg && max_radial_au <= *tolerance_au;
+        report.passed &= passed;
+        report.bodies.push(BodyReport {
+            name,
+            dates,
+            max_angular_deg,
+            max_radial_au,
+            tolerance_deg: *tolerance_deg,
+            tolerance_au: *tolerance_au,
+            passed,
+        });
+    }
+    report
+}
+
+impl AccuracyReport {
+    // One line per body for the console
+    pub fn summary(&self) -> String {
+        let mut lines: Vec<String> = self.bodies.iter()
+            .map(|body| format!(
+                "{:<8} max {:>6.2}° {:>7.4} AU (limit {}° {} AU) {}",
+                body.name, body.max_angular_deg, body.max_radial_au, body.tolerance_deg, body.tolerance_au,
+                if body.passed { "ok" } else { "FAILED" }
+            ))
+            .collect();
+        if !self.missing.is_empty() {
+            lines.push(format!("not in this system: {}", self.missing.join(", ")));
+        }
+        lines.join("\n")
+    }
+}
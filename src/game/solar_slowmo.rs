@@ -0,0 +1,195 @@
+use crate::engine::events;
+use nalgebra::Vector3;
+use serde::Deserialize;
+
+// Which alignments slow time down and how far. Everything is worked out from the current
+// positions alone, so the slowdown is right straight after a date jump.
+#[derive(Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct SlowMoConfig {
+    // Fraction of the time scale left at exact alignment
+    pub floor: f32,
+    // Watch the focused body against its siblings, as seen from their shared parent
+    pub siblings: bool,
+    pub sibling_threshold_deg: f32,
+    pub pairs: Vec<MonitoredPair>,
+}
+
+// Bodies a and b as seen from observer. With opposite set the alignment is them sitting
+// on opposite sides of the sky, as in a lunar eclipse.
+#[derive(Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct MonitoredPair {
+    pub name: String,
+    pub observer: String,
+    pub a: String,
+    pub b: String,
+    pub threshold_deg: f32,
+    #[serde(default)]
+    pub opposite: bool,
+}
+
+fn pair(name: &str, observer: &str, a: &str, b: &str, threshold_deg: f32, opposite: bool) -> MonitoredPair {
+    MonitoredPair {
+        name: name.to_string(),
+        observer: observer.to_string(),
+        a: a.to_string(),
+        b: b.to_string(),
+        threshold_deg,
+        opposite,
+    }
+}
+
+impl Default for SlowMoConfig {
+    fn default() -> Self {
+        SlowMoConfig {
+            floor: 0.05,
+            siblings: true,
+            sibling_threshold_deg: 3.0,
+            pairs: vec![
+                pair("Solar eclipse", "Earth", "Moon", "Sun", 5.0, false),
+                pair("Lunar eclipse", "Earth", "Moon", "Sun", 5.0, true),
+            ],
+        }
+    }
+}
+
+impl SlowMoConfig {
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let config: SlowMoConfig = serde_json::from_str(json).map_err(|e| format!("Invalid slow-mo config: {}", e))?;
+        if !(config.floor > 0.0 && config.floor <= 1.0) {
+            return Err("Invalid slow-mo config: floor must be in (0, 1]".to_string());
+        }
+        let mut thresholds = config.pairs.iter().map(|p| p.threshold_deg).chain([config.sibling_threshold_deg]);
+        if thresholds.any(|t| !(t > 0.0 && t < 180.0)) {
+            return Err("Invalid slow-mo config: thresholds must be between 0 and 180 degrees".to_string());
+        }
+        Ok(config)
+    }
+}
+
+#[derive(Default)]
+pub struct SlowMo {
+    pub enabled: bool,
+    pub config: SlowMoConfig,
+    // Encounters under way, kept only so each one is announced once
+    pub active: Vec<String>,
+}
+
+impl SlowMo {
+    // Multiplier on the time scale from the tightest of the (name, [observer, a, b],
+    // threshold, opposite) alignments, announcing new ones
+    pub fn factor(&mut self, monitored: &[(String, [usize; 3], f32, bool)], positions: &[Vector3<f32>]) -> f32 {
+        // (name, separation, threshold) of every alignment inside its threshold
+        let mut encounters = Vec::new();
+        for (name, [observer, a, b], threshold, opposite) in monitored {
+            let separation = angular_separation(positions[*observer], positions[*a], positions[*b]);
+            let separation = if *opposite { 180.0 - separation } else { separation };
+            if separation < *threshold {
+                encounters.push((name.clone(), separation, *threshold));
+            }
+        }
+
+        let factor = encounters.iter()
+            .map(|(_, separation, threshold)| slowdown(*separation, *threshold, self.config.floor))
+            .fold(1.0, f32::min);
+        for (name, separation, _) in &encounters {
+            if !self.active.contains(name) {
+                events::emit("slowmo_encounter", serde_json::json!({ "name": name, "separation_deg": separation }));
+            }
+        }
+        self.active = encounters.into_iter().map(|(name, _, _)| name).collect();
+        factor
+    }
+}
+
+// Degrees between the directions from observer to a and to b
+pub fn angular_separation(observer: Vector3<f32>, a: Vector3<f32>, b: Vector3<f32>) -> f32 {
+    let (to_a, to_b) = (a - observer, b - observer);
+    if to_a.norm() == 0.0 || to_b.norm() == 0.0 {
+        return 180.0;
+    }
+    to_a.angle(&to_b).to_degrees()
+}
+
+// Whether b went past a as seen from observer between two moments. The side b is on flips,
+// with both moments on the same half of the sky, or on opposite halves for an opposition.
+pub fn passed_between(start: [Vector3<f32>; 3], end: [Vector3<f32>; 3], opposite: bool) -> bool {
+    let side = |[observer, a, b]: [Vector3<f32>; 3]| (a - observer).cross(&(b - observer));
+    let facing = |[observer, a, b]: [Vector3<f32>; 3]| (a - observer).dot(&(b - observer)) > 0.0;
+    side(start).dot(&side(end)) < 0.0 && facing(start) != opposite && facing(end) != opposite
+}
+
+// Time scale multiplier for one separation: 1 beyond the threshold, easing down to floor
+pub fn slowdown(separation_deg: f32, threshold_deg: f32, floor: f32) -> f32 {
+    let t = (separation_deg / threshold_deg).clamp(0.0, 1.0);
+    floor + (1.0 - floor) * t * t * (3.0 - 2.0 * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn on_circle(deg: f32) -> Vector3<f32> {
+        let rad = deg.to_radians();
+        Vector3::new(rad.cos(), 0.0, rad.sin())
+    }
+
+    #[test]
+    fn conjunction_inside_one_frame_is_caught() {
+        let origin = Vector3::zeros();
+        // b sweeps from 10 degrees behind a to 10 degrees ahead, both outside a 3 degree threshold
+        let start = [origin, on_circle(0.0), on_circle(-10.0)];
+        let end = [origin, on_circle(0.0), on_circle(10.0)];
+        assert!(angular_separation(start[0], start[1], start[2]) > 3.0);
+        assert!(angular_separation(end[0], end[1], end[2]) > 3.0);
+        assert!(passed_between(start, end, false));
+        assert!(!passed_between(start, end, true));
+    }
+
+    #[test]
+    fn opposition_inside_one_frame_is_caught() {
+        let origin = Vector3::zeros();
+        let start = [origin, on_circle(0.0), on_circle(170.0)];
+        let end = [origin, on_circle(0.0), on_circle(190.0)];
+        assert!(passed_between(start, end, true));
+        assert!(!passed_between(start, end, false));
+    }
+
+    #[test]
+    fn approach_without_passing_is_not_a_pass() {
+        let origin = Vector3::zeros();
+        let start = [origin, on_circle(0.0), on_circle(-20.0)];
+        let end = [origin, on_circle(0.0), on_circle(-4.0)];
+        assert!(!passed_between(start, end, false));
+    }
+
+    #[test]
+    fn slowdown_reaches_floor_at_alignment() {
+        assert_eq!(slowdown(0.0, 5.0, 0.05), 0.05);
+        assert_eq!(slowdown(5.0, 5.0, 0.05), 1.0);
+        assert_eq!(slowdown(20.0, 5.0, 0.05), 1.0);
+    }
+
+    // b sweeps past a half a degree a frame: full speed until the threshold, down to the
+    // floor at the conjunction and back up the same way after it
+    #[test]
+    fn time_scale_dips_through_a_conjunction_and_recovers() {
+        let mut slowmo = SlowMo { enabled: true, ..SlowMo::default() };
+        let monitored = [("Conjunction".to_string(), [0, 1, 2], 3.0, false)];
+        let factors: Vec<f32> = (-20..=20)
+            .map(|step| {
+                let positions = [Vector3::zeros(), on_circle(0.0), on_circle(step as f32 * 0.5)];
+                slowmo.factor(&monitored, &positions)
+            })
+            .collect();
+
+        let (before, after) = factors.split_at(20);
+        assert!(before.windows(2).all(|w| w[1] <= w[0]) && after.windows(2).all(|w| w[1] >= w[0]));
+        assert_eq!((factors[0], factors[40]), (1.0, 1.0));
+        assert!((factors[20] - slowmo.config.floor).abs() < 1e-3, "{}", factors[20]);
+        // Only the frames inside the threshold slow down, and the encounter ends with them
+        assert_eq!(factors.iter().filter(|&&f| f < 1.0).count(), 11);
+        assert!(slowmo.active.is_empty());
+    }
+}
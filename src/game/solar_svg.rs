@@ -0,0 +1,78 @@
+// Printable vector version of the Solar view: strokes and flat fills on white, no textures
+pub struct StarChart {
+    width: f32,
+    height: f32,
+    orbits: String,
+    bodies: String,
+    caption: Option<String>,
+}
+
+type Color = (f32, f32, f32);
+
+// Orbit points further than this many viewports outside the frame end a run, the clip
+// rectangle trims whatever is left
+const CLIP_MARGIN: f32 = 1.0;
+
+impl StarChart {
+    pub fn new(width: f32, height: f32) -> Self {
+        StarChart { width, height, orbits: String::new(), bodies: String::new(), caption: None }
+    }
+
+    pub fn in_bounds(&self, x: f32, y: f32) -> bool {
+        let (mx, my) = (self.width * CLIP_MARGIN, self.height * CLIP_MARGIN);
+        x >= -mx && x <= self.width + mx && y >= -my && y <= self.height + my
+    }
+
+    // One path per orbit, each run a separate subpath so gaps behind the camera stay open
+    pub fn orbit(&mut self, runs: &[Vec<(f32, f32)>]) {
+        let d: Vec<String> = runs.iter()
+            .filter(|run| run.len() > 1)
+            .map(|run| {
+                let points: Vec<String> = run.iter().map(|(x, y)| format!("{:.1} {:.1}", x, y)).collect();
+                format!("M{}", points.join(" L"))
+            })
+            .collect();
+        if !d.is_empty() {
+            self.orbits.push_str(&format!("<path d=\"{}\"/>\n", d.join(" ")));
+        }
+    }
+
+    pub fn body(&mut self, name: &str, x: f32, y: f32, radius: f32, label_y: f32, color: Color) {
+        self.bodies.push_str(&format!(
+            "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"{:.1}\" fill=\"{}\" stroke=\"black\" stroke-width=\"0.5\"/>\n<text x=\"{:.1}\" y=\"{:.1}\">{}</text>\n",
+            x, y, radius.max(1.0), hex(color), x, label_y, escape(name)
+        ));
+    }
+
+    pub fn caption(&mut self, text: &str) {
+        self.caption = Some(escape(text));
+    }
+
+    pub fn finish(self) -> String {
+        let (w, h) = (self.width, self.height);
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w:.0}\" height=\"{h:.0}\" viewBox=\"0 0 {w:.0} {h:.0}\">\n\
+             <defs><clipPath id=\"view\"><rect width=\"{w:.0}\" height=\"{h:.0}\"/></clipPath></defs>\n\
+             <rect width=\"{w:.0}\" height=\"{h:.0}\" fill=\"white\"/>\n"
+        );
+        svg.push_str(&format!("<g clip-path=\"url(#view)\" fill=\"none\" stroke=\"#888\" stroke-width=\"1\">\n{}</g>\n", self.orbits));
+        svg.push_str(&format!(
+            "<g clip-path=\"url(#view)\" font-family=\"sans-serif\" font-size=\"12\" text-anchor=\"middle\">\n{}</g>\n",
+            self.bodies
+        ));
+        if let Some(caption) = &self.caption {
+            svg.push_str(&format!("<text x=\"10\" y=\"{:.0}\" font-family=\"sans-serif\" font-size=\"14\">{}</text>\n", h - 10.0, caption));
+        }
+        svg.push_str("</svg>\n");
+        svg
+    }
+}
+
+fn hex((r, g, b): Color) -> String {
+    let byte = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+    format!("#{:02x}{:02x}{:02x}", byte(r), byte(g), byte(b))
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
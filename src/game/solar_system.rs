@@ -1,10 +1,294 @@
-use crate::engine::renderer::Renderer;
+use crate::engine::camera::{InputEvent, OrbitController};
+use crate::engine::renderer::{Renderer, SecondLight};
+use crate::engine::audio::{Audio, SoundLoop};
+use crate::engine::events;
+use crate::engine::settings::Settings;
+use crate::game::solar_ephemeris;
+use crate::game::solar_slowmo::{self, SlowMo, SlowMoConfig};
+use crate::game::solar_svg::StarChart;
+use crate::game::solar_time::{self, ScaleRamp, TimeBookmark};
+use crate::game::solar_tour::{self, Tour, TourStep, TourStop};
+use crate::game::sonification::{self, Sonification};
 use crate::engine::mesh::Mesh;
 use nalgebra::{Matrix4, Point3, Vector3, Vector4};
 use js_sys::Date;
-use web_sys::{HtmlElement, WebGlTexture};
+use web_sys::{Element, HtmlElement, WebGlTexture};
 use wasm_bindgen::JsCast;
 use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::cell::{Cell, RefCell};
+
+const UNITS_PER_AU: f32 = 100.0;
+const AMBIENT_HUM_HZ: f32 = 90.0;
+const AMBIENT_VOLUME: f32 = 0.15;
+// Where the ecliptic enters each zodiac constellation (IAU boundaries, J2000 ecliptic
+// longitude in degrees), ascending. Ophiuchus gets its short stretch between Scorpius and Sagittarius.
+const ZODIAC_BOUNDARIES: &[(f32, &str)] = &[
+    (29.09, "Aries"),
+    (53.47, "Taurus"),
+    (90.14, "Gemini"),
+    (118.26, "Cancer"),
+    (138.18, "Leo"),
+    (174.15, "Virgo"),
+    (217.81, "Libra"),
+    (241.14, "Scorpius"),
+    (247.70, "Ophiuchus"),
+    (266.30, "Sagittarius"),
+    (299.71, "Capricornus"),
+    (327.88, "Aquarius"),
+    (351.57, "Pisces"),
+];
+// Points per orbit in the exported chart, plenty for print sizes
+const SVG_ORBIT_POINTS: usize = 256;
+// Points per closed orbit polyline, sampled uniformly in true anomaly
+const ORBIT_PATH_POINTS: usize = 1000;
+const METERS_PER_AU: f64 = 1.495978707e11;
+const SOLAR_LUMINOSITY_W: f64 = 3.828e26;
+const SIRIUS_A_LUMINOSITY_SOLAR: f64 = 25.4;
+const SIRIUS_B_LUMINOSITY_SOLAR: f64 = 0.056;
+const STEFAN_BOLTZMANN: f64 = 5.670374419e-8;
+// Cosmic microwave background, the floor for anything with no heat source
+const CMB_TEMPERATURE_K: f64 = 2.725;
+const J2000_MS: f64 = 946728000000.0;
+const MS_PER_JULIAN_YEAR: f64 = 365.25 * 86400000.0;
+// The catalog holds J2000 osculating elements, trusted this far either side of the epoch
+const DEFAULT_VALIDITY_YEARS: f32 = 200.0;
+// Orbit paths are resampled once drift has turned them by more than this (radians)
+const PATH_DRIFT_TOLERANCE: f32 = 0.005;
+// (body, argument of periapsis, longitude of ascending node) drift in degrees per year
+const SECULAR_RATES: &[(&str, f32, f32)] = &[
+    ("Mercury", 0.002858, -0.001253),
+    ("Moon", 60.03, -19.341),
+];
+// Bodies below this get the icy shader treatment
+const FROZEN_THRESHOLD_K: f32 = 150.0;
+
+struct NamedAsteroid {
+    name: &'static str,
+    a: f32, // AU
+    e: f32,
+    i: f32, // degrees
+    node: f32,
+    periapsis: f32,
+    mean_anomaly: f32, // at NAMED_ASTEROID_EPOCH_JD
+    radius_km: f32,
+    rotation_hours: f32,
+    mass: &'static str,
+    description: &'static str,
+}
+
+// Rounded JPL small-body elements, good for finding them roughly where they are
+const NAMED_ASTEROID_EPOCH_JD: f64 = 2460000.5;
+// Hygiea's semi-major axis only happens to read like pi
+#[allow(clippy::approx_constant)]
+const NAMED_ASTEROIDS: &[NamedAsteroid] = &[
+    NamedAsteroid { name: "Vesta", a: 2.3617, e: 0.0885, i: 7.142, node: 103.81, periapsis: 151.05, mean_anomaly: 205.5, radius_km: 262.7, rotation_hours: 5.342, mass: "2.59 × 10^20 kg", description: "The brightest asteroid, with a huge impact basin at its south pole." },
+    NamedAsteroid { name: "Pallas", a: 2.7703, e: 0.2302, i: 34.93, node: 172.89, periapsis: 310.93, mean_anomaly: 211.5, radius_km: 256.0, rotation_hours: 7.813, mass: "2.04 × 10^20 kg", description: "A large main-belt asteroid on a steeply inclined orbit." },
+    NamedAsteroid { name: "Hygiea", a: 3.1415, e: 0.1121, i: 3.831, node: 283.18, periapsis: 312.32, mean_anomaly: 68.3, radius_km: 217.0, rotation_hours: 13.83, mass: "8.74 × 10^19 kg", description: "The fourth-largest asteroid, nearly round." },
+    NamedAsteroid { name: "Eros", a: 1.4580, e: 0.2228, i: 10.83, node: 304.28, periapsis: 178.93, mean_anomaly: 310.6, radius_km: 8.4, rotation_hours: 5.27, mass: "6.687 × 10^15 kg", description: "A near-Earth asteroid, the first one orbited and landed on by a spacecraft." },
+    NamedAsteroid { name: "Bennu", a: 1.1264, e: 0.2037, i: 6.035, node: 2.06, periapsis: 66.22, mean_anomaly: 101.7, radius_km: 0.245, rotation_hours: 4.296, mass: "7.329 × 10^10 kg", description: "An Earth-crossing rubble pile sampled by OSIRIS-REx." },
+];
+
+// Past the Oort cloud the planetary system fades out and the local-neighbourhood view fades in
+const CONTEXT_FADE_START: f32 = 2000000.0;
+const CONTEXT_FADE_END: f32 = 6000000.0;
+const AU_PER_LIGHT_YEAR: f32 = 63241.08;
+const OBLIQUITY_DEG: f32 = 23.439;
+const STAR_MARKER_PIXELS: f32 = 5.0;
+const STAR_PICK_PIXELS: f32 = 14.0;
+const GALAXY_TEXTURE_SIZE: usize = 256;
+const GALAXY_INSET_PIXELS: f32 = 220.0;
+// The Sun sits a little over half way out, in the Orion spur
+const SUN_GALACTIC_RADIUS: f32 = 0.52;
+// Logarithmic spiral winding of the stylized arms
+const GALAXY_ARM_WINDING: f32 = 4.0;
+
+struct NearbyStar {
+    name: &'static str,
+    ra: f32, // degrees, J2000
+    dec: f32, // degrees, J2000
+    distance_ly: f32,
+    color: (f32, f32, f32),
+    blurb: &'static str,
+}
+
+const NEARBY_STARS: &[NearbyStar] = &[
+    NearbyStar { name: "Alpha Centauri", ra: 219.90, dec: -60.83, distance_ly: 4.37, color: (1.0, 0.95, 0.8), blurb: "The closest star system: a Sun-like pair plus the red dwarf Proxima, which hosts at least one planet." },
+    NearbyStar { name: "Barnard's Star", ra: 269.45, dec: 4.69, distance_ly: 5.96, color: (1.0, 0.55, 0.4), blurb: "A faint red dwarf with the largest proper motion of any star, crossing a Moon's width of sky every 180 years." },
+    NearbyStar { name: "Sirius", ra: 101.29, dec: -16.72, distance_ly: 8.60, color: (0.75, 0.85, 1.0), blurb: "The brightest star in the night sky, a hot A-type star orbited by the white dwarf Sirius B." },
+];
+
+// Scene position of a star from its equatorial coordinates, rotated onto the ecliptic
+fn nearby_star_position(star: &NearbyStar) -> Vector3<f32> {
+    let (ra, dec) = (star.ra.to_radians(), star.dec.to_radians());
+    let (xq, yq, zq) = (dec.cos() * ra.cos(), dec.cos() * ra.sin(), dec.sin());
+    let e = OBLIQUITY_DEG.to_radians();
+    let ye = yq * e.cos() + zq * e.sin();
+    let ze = -yq * e.sin() + zq * e.cos();
+    let distance = star.distance_ly * AU_PER_LIGHT_YEAR * UNITS_PER_AU;
+    Vector3::new(xq, ze, ye) * distance
+}
+
+// Where the Sun's marker goes on the galaxy sprite, as a fraction of the half-size from its center
+fn sun_galactic_offset() -> (f32, f32) {
+    let angle = GALAXY_ARM_WINDING * SUN_GALACTIC_RADIUS.ln();
+    (SUN_GALACTIC_RADIUS * angle.cos(), SUN_GALACTIC_RADIUS * angle.sin())
+}
+
+// Face-on two-armed spiral with a warm bulge, alpha carries the brightness
+fn galaxy_pixels(size: usize) -> Vec<u8> {
+    let mut pixels = vec![0u8; size * size * 4];
+    for j in 0..size {
+        for i in 0..size {
+            let u = (i as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+            let v = (j as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+            let r = (u * u + v * v).sqrt();
+            if r >= 1.0 {
+                continue;
+            }
+            let phase = v.atan2(u) - GALAXY_ARM_WINDING * r.max(0.05).ln();
+            let arm = (0.5 + 0.5 * (2.0 * phase).cos()).powi(4);
+            let bulge = (-(r / 0.15).powi(2)).exp();
+            let disk = (-r / 0.35).exp() * (1.0 - r) * (0.2 + 0.8 * arm);
+            let brightness = (bulge + disk).min(1.0);
+            let warm = bulge / (bulge + disk).max(f32::EPSILON);
+            let color = (0.7 + 0.3 * warm, 0.8 + 0.1 * warm, 1.0 - 0.3 * warm);
+            let k = (j * size + i) * 4;
+            pixels[k] = (color.0 * 255.0) as u8;
+            pixels[k + 1] = (color.1 * 255.0) as u8;
+            pixels[k + 2] = (color.2 * 255.0) as u8;
+            pixels[k + 3] = (brightness * 255.0) as u8;
+        }
+    }
+    pixels
+}
+
+// The black hole's physical radius is invisible, it is drawn this big so the lensing shows
+const BLACK_HOLE_VISUAL_RADIUS: f32 = 0.3;
+
+// Standing on a body looking out. The spot is fixed in the body's own frame so it turns
+// with it, and look is an orbit controller whose yaw and pitch are azimuth and altitude.
+struct SurfaceView {
+    body: usize,
+    latitude: f32,
+    longitude: f32,
+    look: OrbitController,
+}
+
+// Eye height above the surface as a multiple of the radius, and the near plane to match
+const SURFACE_EYE_HEIGHT: f32 = 1.001;
+const SURFACE_NEAR_FRACTION: f32 = 0.0005;
+// The background sphere is drawn 5000 units out, the far plane has to reach it
+const SURFACE_MIN_FAR: f32 = 10000.0;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum StereoMode {
+    Off,
+    // Left eye in red, right eye in green and blue
+    Anaglyph,
+}
+
+fn is_small_body(body: &Body) -> bool {
+    body.name.starts_with("Asteroid") || body.name.starts_with("Kuiper") || body.name.starts_with("Oort")
+}
+
+// Radius a body is drawn at from dist away and whether it is close enough to show its
+// texture. Bodies are inflated to a minimum apparent size, small bodies less so.
+fn body_render_radius(body: &Body, dist: f32) -> (f32, bool) {
+    let scale_factor = if is_small_body(body) { 0.0005 } else { 0.002 };
+    let min_size = dist * scale_factor;
+    if min_size > body.radius { (min_size, false) } else { (body.radius, true) }
+}
+
+// Labels sit this far above the top of their body's disc
+const LABEL_GAP_PX: f32 = 20.0;
+// Pointer travel below this between press and release counts as a click, not an orbit drag
+const CLICK_SLOP_PIXELS: i32 = 4;
+// Hits whose entry points are this close (relative to the distance) count as a tie
+const PICK_TIE_FRACTION: f32 = 0.01;
+
+// What the last frame drew, so a click can be turned into a ray against it
+struct PickFrame {
+    inverse_view_projection: Matrix4<f32>,
+    width: f32,
+    height: f32,
+    // Body index, camera-target-relative center and the radius it was drawn at
+    spheres: Vec<(usize, Vector3<f32>, f32)>,
+}
+
+// Distance along the ray (direction normalized) to where it enters the sphere, 0 from inside
+pub fn ray_sphere_entry(origin: Vector3<f32>, direction: Vector3<f32>, center: Vector3<f32>, radius: f32) -> Option<f32> {
+    let to_center = center - origin;
+    let along = to_center.dot(&direction);
+    // From the closest point rather than |to_center|² - along², which cancels badly far out
+    let miss_sq = (to_center - direction * along).norm_squared();
+    let radius_sq = radius * radius;
+    if miss_sq > radius_sq {
+        return None;
+    }
+    let half_chord = (radius_sq - miss_sq).sqrt();
+    if along + half_chord < 0.0 {
+        return None;
+    }
+    Some((along - half_chord).max(0.0))
+}
+
+// The nearest sphere by entry distance. Near-ties go to the smaller sphere, the one the
+// click was aimed at when a moon sits on its planet's inflated disc.
+pub fn pick_sphere(origin: Vector3<f32>, direction: Vector3<f32>, spheres: &[(usize, Vector3<f32>, f32)]) -> Option<usize> {
+    let mut hits: Vec<(usize, f32, f32)> = spheres.iter()
+        .filter_map(|&(index, center, radius)| ray_sphere_entry(origin, direction, center, radius).map(|t| (index, t, radius)))
+        .collect();
+    hits.sort_by(|a, b| a.1.total_cmp(&b.1));
+    let nearest = hits.first()?.1;
+    let tie = nearest * PICK_TIE_FRACTION;
+    hits.iter()
+        .take_while(|hit| hit.1 - nearest <= tie)
+        .min_by(|a, b| a.2.total_cmp(&b.2))
+        .map(|hit| hit.0)
+}
+
+// A body label as written to the DOM, in whole pixels
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct LabelState {
+    pub visible: bool,
+    pub x: i32,
+    pub y: i32,
+}
+
+impl LabelState {
+    pub const HIDDEN: LabelState = LabelState { visible: false, x: 0, y: 0 };
+
+    pub fn shown(x: f32, y: f32) -> Self {
+        LabelState { visible: true, x: x.round() as i32, y: y.round() as i32 }
+    }
+}
+
+// Whether moving a label from old to new needs any style write; a hidden label's position doesn't matter
+pub fn label_needs_write(old: Option<LabelState>, new: LabelState) -> bool {
+    match old {
+        None => true,
+        Some(old) if old.visible != new.visible => true,
+        Some(old) => new.visible && (old.x != new.x || old.y != new.y),
+    }
+}
+
+// Writes only the properties that changed since the last call
+fn apply_label(element: &HtmlElement, applied: &Cell<Option<LabelState>>, state: LabelState) {
+    let old = applied.get();
+    if !label_needs_write(old, state) {
+        return;
+    }
+    let style = element.style();
+    if old.map(|old| old.visible) != Some(state.visible) {
+        style.set_property("display", if state.visible { "block" } else { "none" }).ok();
+    }
+    if state.visible {
+        style.set_property("left", &format!("{}px", state.x)).ok();
+        style.set_property("top", &format!("{}px", state.y)).ok();
+    }
+    applied.set(Some(state));
+}
 
 pub struct Body {
     pub mesh: Mesh,
@@ -15,8 +299,10 @@ pub struct Body {
     pub color: (f32, f32, f32),
     pub parent: Option<usize>,
     pub name: String,
-    pub trail: Vec<f32>,
+    pub orbit_path: Vec<f32>,
     pub label_element: Option<HtmlElement>,
+    // What the label's style was last set to, so unchanged frames skip the DOM
+    pub label_state: Cell<Option<LabelState>>,
     pub texture: Option<WebGlTexture>,
     pub night_texture: Option<WebGlTexture>,
     pub cloud_texture: Option<WebGlTexture>,
@@ -27,7 +313,6 @@ pub struct Body {
     pub orbit_inclination: f32,
     pub longitude_of_ascending_node: f32,
     pub argument_of_periapsis: f32,
-    pub last_trail_angle: f32,
     pub eccentricity: f32,
     pub mass: String,
     pub temperature: f32,
@@ -36,7 +321,38 @@ pub struct Body {
     pub ring_radius: f32,
     pub ring_inner_radius: Option<f32>,
     pub is_frozen: bool,
-    pub mean_longitude_at_epoch: f32,
+    pub mean_anomaly_at_epoch: f32,
+    pub catalog_temperature: f32,
+    // Secular drift of the slow elements, radians per Julian year from J2000
+    pub periapsis_rate: f32,
+    pub node_rate: f32,
+    pub drift_years: f32,
+    path_drift_years: f32,
+}
+
+struct BodyComparison {
+    index_a: usize,
+    index_b: usize,
+    rotation: f32,
+}
+
+// Analytic orbit of the focused body, rebuilt whenever focus or elements change
+struct FocusedOrbit {
+    index: usize,
+    path: Vec<f32>,
+    periapsis: Vector3<f32>,
+    apoapsis: Vector3<f32>,
+}
+
+// Angles in degrees, missing fields keep their current value
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct OrbitUpdate {
+    pub semi_major_axis: Option<f32>,
+    pub eccentricity: Option<f32>,
+    pub inclination: Option<f32>,
+    pub longitude_of_ascending_node: Option<f32>,
+    pub argument_of_periapsis: Option<f32>,
 }
 
 #[derive(PartialEq, Clone, Copy)]
@@ -46,16 +362,25 @@ pub enum SystemType {
     Sirius,
 }
 
+// What get_game_state reports for the solar system
+#[derive(Serialize)]
+pub struct SolarSnapshot {
+    // ms since the epoch, like Date.now()
+    pub current_time: f64,
+    pub time_scale: f32,
+    pub focused_body: Option<String>,
+    pub camera_distance: f32,
+}
+
 pub struct SolarSystem {
     renderer: Renderer,
     bodies: Vec<Body>,
-    camera_distance: f32,
-    camera_rotation: (f32, f32),
+    camera: OrbitController,
     last_time: f64,
-    is_dragging: bool,
-    last_mouse_pos: (i32, i32),
     time_scale: f32,
     current_time: f64,
+    // Set while a script drives time through step_simulation, the frame loop then leaves time alone
+    manual_time: bool,
     background_mesh: Mesh,
     background_texture: Option<WebGlTexture>,
     focused_body_index: Option<usize>,
@@ -65,6 +390,62 @@ pub struct SolarSystem {
     system_type: SystemType,
     sun_texture: Option<WebGlTexture>,
     use_celsius: bool,
+    comparison: Option<BodyComparison>,
+    focused_orbit: Option<FocusedOrbit>,
+    apsis_labels: Option<(HtmlElement, HtmlElement)>,
+    // Entries this system added to the body list, taken out again on drop
+    list_items: Vec<Element>,
+    // Luminosity of the central object in watts, 0 for a bare black hole
+    central_luminosity: f64,
+    live_temperatures: bool,
+    tour: Option<Tour>,
+    // Camera target eases from the previously focused body (None = origin)
+    focus_from: Option<usize>,
+    focus_blend: f32,
+    // Observer on Earth as (latitude, longitude) in degrees, east positive
+    observer: Option<(f32, f32)>,
+    earth_markers: bool,
+    validity_years: f32,
+    elements_stale: bool,
+    sonification: Option<Sonification>,
+    // Low hum under the scene, the loop drops before its Audio
+    ambient: Option<(SoundLoop, Audio)>,
+    // Orbit trails smaller than this on screen are skipped
+    trail_min_pixels: f32,
+    galaxy_texture: Option<WebGlTexture>,
+    // One per NEARBY_STARS entry, with the state last written to it
+    star_labels: Vec<Option<(HtmlElement, Cell<Option<LabelState>>)>>,
+    // Screen positions of the nearby stars from the last frame, for picking
+    star_screen: RefCell<Vec<(usize, f32, f32)>>,
+    pick_frame: RefCell<Option<PickFrame>>,
+    stereo_mode: StereoMode,
+    // Distance between the eyes as a fraction of the camera distance
+    eye_separation: f32,
+    // Where the pointer went down and how far it has moved since
+    press: Option<(i32, i32, i32)>,
+    // Orbit camera is left as it was while this is set, so leaving picks it back up
+    surface_view: Option<SurfaceView>,
+    slowmo: SlowMo,
+    scale_ramp: Option<ScaleRamp>,
+    time_bookmarks: Vec<Option<TimeBookmark>>,
+}
+
+const FOCUS_TRANSITION_SECONDS: f32 = 1.5;
+// Ramp length when a bookmark brings its time scale back
+const BOOKMARK_RAMP_SECONDS: f64 = 1.5;
+const DEFAULT_TRAIL_MIN_PIXELS: f32 = 20.0;
+// A few times the render near plane
+const MIN_FOCUS_DISTANCE: f32 = 0.003;
+
+// The camera a frame is drawn with, one per eye in stereo. Positions are drawn relative to
+// target and eye is the camera in those coordinates. width and height are the canvas's CSS size.
+struct SceneView {
+    target: Vector3<f32>,
+    eye: Vector3<f32>,
+    projection: Matrix4<f32>,
+    view: Matrix4<f32>,
+    width: i32,
+    height: i32,
 }
 
 impl SolarSystem {
@@ -97,16 +478,16 @@ impl SolarSystem {
              list.set_inner_html("");
         }
 
-        let create_body = |name: &str, radius: f32, orbit_radius: f32, orbit_speed: f32, mean_longitude: f32, color: (f32, f32, f32), parent: Option<usize>, mesh_fn: fn(f32, u16, u16, f32, f32, f32) -> Mesh, texture_url: Option<&str>, night_texture_url: Option<&str>, cloud_texture_url: Option<&str>, ring_texture_url: Option<&str>, ring_radius: f32, rotation_period: f32, axial_tilt: f32, orbit_inclination: f32, longitude_of_ascending_node: f32, argument_of_periapsis: f32, eccentricity: f32, mass: &str, temperature: f32, description: &str, ring_inner_radius: Option<f32>| {
+        let create_body = |name: &str, radius: f32, orbit_radius: f32, orbit_speed: f32, mean_anomaly: f32, color: (f32, f32, f32), parent: Option<usize>, mesh_fn: fn(f32, u16, u16, f32, f32, f32) -> Mesh, texture_url: Option<&str>, night_texture_url: Option<&str>, cloud_texture_url: Option<&str>, ring_texture_url: Option<&str>, ring_radius: f32, rotation_period: f32, axial_tilt: f32, orbit_inclination: f32, longitude_of_ascending_node: f32, argument_of_periapsis: f32, eccentricity: f32, mass: &str, temperature: f32, description: &str, ring_inner_radius: Option<f32>| {
             let mut label_element = None;
             if let Some(container) = &labels_container {
                 if !name.starts_with("Asteroid") && !name.starts_with("Kuiper") && !name.starts_with("Oort") {
-                    let el = document.create_element("div").unwrap();
-                    el.set_class_name("solar-label");
-                    el.set_text_content(Some(name));
-                    container.append_child(&el).unwrap();
-                    if let Ok(html_el) = el.dyn_into::<HtmlElement>() {
-                        label_element = Some(html_el);
+                    if let Ok(el) = document.create_element("div") {
+                        el.set_class_name("solar-label");
+                        el.set_text_content(Some(name));
+                        if container.append_child(&el).is_ok() {
+                            label_element = el.dyn_into::<HtmlElement>().ok();
+                        }
                     }
                 }
             }
@@ -171,19 +552,7 @@ impl SolarSystem {
                 (40, 40)
             };
 
-            let (final_temp, is_frozen) = if system_type == SystemType::BlackHole && name != "Black Hole" {
-                (30.0, true)
-            } else {
-                (temperature, false)
-            };
-
-            let orbit_angle = if orbit_speed.abs() > 0.0 {
-                 let n_rad_per_day = orbit_speed * 86400.0;
-                 let angle_rad = mean_longitude.to_radians() + n_rad_per_day * days_since_j2000 as f32;
-                 angle_rad % (2.0 * std::f32::consts::PI)
-            } else {
-                 mean_longitude.to_radians()
-            };
+            let orbit_angle = orbit_angle_at(mean_anomaly, orbit_speed, days_since_j2000);
 
             Body {
                 mesh: mesh_fn(1.0, slices, stacks, mesh_r, mesh_g, mesh_b),
@@ -191,12 +560,13 @@ impl SolarSystem {
                 orbit_radius,
                 orbit_speed,
                 orbit_angle,
-                mean_longitude_at_epoch: mean_longitude,
+                mean_anomaly_at_epoch: mean_anomaly,
                 color,
                 parent,
                 name: name.to_string(),
-                trail: Vec::new(),
+                orbit_path: Vec::new(),
                 label_element,
+                label_state: Cell::new(None),
                 texture,
                 night_texture,
                 cloud_texture,
@@ -207,15 +577,19 @@ impl SolarSystem {
                 orbit_inclination: orbit_inclination.to_radians(),
                 longitude_of_ascending_node: longitude_of_ascending_node.to_radians(),
                 argument_of_periapsis: argument_of_periapsis.to_radians(),
-                last_trail_angle: orbit_angle,
                 eccentricity,
                 mass: mass.to_string(),
-                temperature: final_temp,
+                temperature,
+                catalog_temperature: temperature,
                 description: description.to_string(),
                 ring_texture,
                 ring_radius,
                 ring_inner_radius,
-                is_frozen,
+                is_frozen: false,
+                periapsis_rate: 0.0,
+                node_rate: 0.0,
+                drift_years: 0.0,
+                path_drift_years: 0.0,
             }
         };
 
@@ -235,20 +609,21 @@ impl SolarSystem {
                 bodies.push(create_body("Sun", 0.465, 0.0, 0.0, 0.0, (1.0, 1.0, 0.0), None, Mesh::sphere, Some("assets/textures/2k_sun.jpg"), None, None, None, 0.0, 25.0, 7.25, 0.0, 0.0, 0.0, 0.0, "1.989 × 10^30 kg", 5778.0, "The star at the center of our Solar System.", None));
             }
 
+        // Planets take their J2000 mean anomaly, node and argument of perihelion
         let p_mercury = 87.969;
 
-        bodies.push(create_body("Mercury", 0.0016, 39.0, get_orbit_speed(p_mercury), 252.25, (0.5, 0.5, 0.5), Some(0), Mesh::sphere, Some("assets/textures/2k_mercury.jpg"), None, None, None, 0.0, 58.6, 0.03, 7.0, 0.0, 0.0, 0.205, "3.285 × 10^23 kg", 440.0, "The smallest planet in the Solar System and the closest to the Sun.", None));
+        bodies.push(create_body("Mercury", 0.0016, 39.0, get_orbit_speed(p_mercury), 174.79, (0.5, 0.5, 0.5), Some(0), Mesh::sphere, Some("assets/textures/2k_mercury.jpg"), None, None, None, 0.0, 58.6, 0.03, 7.0, 48.33, 29.13, 0.205, "3.285 × 10^23 kg", 440.0, "The smallest planet in the Solar System and the closest to the Sun.", None));
 
         let p_venus = 224.701;
 
-        bodies.push(create_body("Venus", 0.004, 72.0, get_orbit_speed(p_venus), 181.98, (0.9, 0.7, 0.2), Some(0), Mesh::sphere, Some("assets/textures/2k_venus_surface.jpg"), None, Some("assets/textures/2k_venus_atmosphere.jpg"), None, 0.0, -243.0, 177.3, 3.4, 0.0, 0.0, 0.007, "4.867 × 10^24 kg", 737.0, "The second planet from the Sun. It has a dense atmosphere.", None));
+        bodies.push(create_body("Venus", 0.004, 72.0, get_orbit_speed(p_venus), 50.38, (0.9, 0.7, 0.2), Some(0), Mesh::sphere, Some("assets/textures/2k_venus_surface.jpg"), None, Some("assets/textures/2k_venus_atmosphere.jpg"), None, 0.0, -243.0, 177.3, 3.4, 76.68, 54.92, 0.007, "4.867 × 10^24 kg", 737.0, "The second planet from the Sun. It has a dense atmosphere.", None));
 
         let p_earth = 365.256;
 
         if system_type == SystemType::BlackHole {
-            bodies.push(create_body("Earth", 0.0042, 100.0, get_orbit_speed(p_earth), 100.46, (0.8, 0.9, 1.0), Some(0), Mesh::sphere, Some("assets/textures/2k_earth_daymap.jpg"), None, None, None, 0.0, 1.0, 23.4, 0.0, 0.0, 0.0, 0.017, "5.972 × 10^24 kg", 30.0, "A frozen wasteland orbiting a black hole.", None));
+            bodies.push(create_body("Earth", 0.0042, 100.0, get_orbit_speed(p_earth), 357.52, (0.8, 0.9, 1.0), Some(0), Mesh::sphere, Some("assets/textures/2k_earth_daymap.jpg"), None, None, None, 0.0, 1.0, 23.4, 0.0, 0.0, 102.94, 0.017, "5.972 × 10^24 kg", 30.0, "Our home planet, orbiting a black hole. Its temperature depends on the light it receives.", None));
         } else {
-            bodies.push(create_body("Earth", 0.0042, 100.0, get_orbit_speed(p_earth), 100.46, (0.0, 0.0, 1.0), Some(0), Mesh::sphere, Some("assets/textures/2k_earth_daymap.jpg"), Some("assets/textures/2k_earth_nightmap.jpg"), Some("assets/textures/2k_earth_clouds.jpg"), None, 0.0, 1.0, 23.4, 0.0, 0.0, 0.0, 0.017, "5.972 × 10^24 kg", 288.0, "Our home planet, the third from the Sun.", None));
+            bodies.push(create_body("Earth", 0.0042, 100.0, get_orbit_speed(p_earth), 357.52, (0.0, 0.0, 1.0), Some(0), Mesh::sphere, Some("assets/textures/2k_earth_daymap.jpg"), Some("assets/textures/2k_earth_nightmap.jpg"), Some("assets/textures/2k_earth_clouds.jpg"), None, 0.0, 1.0, 23.4, 0.0, 0.0, 102.94, 0.017, "5.972 × 10^24 kg", 288.0, "Our home planet, the third from the Sun.", None));
         }
 
         let p_moon = 27.322;
@@ -257,7 +632,7 @@ impl SolarSystem {
 
         let p_mars = 686.980;
 
-        bodies.push(create_body("Mars", 0.0022, 152.0, get_orbit_speed(p_mars), 355.45, (1.0, 0.0, 0.0), Some(0), Mesh::sphere, Some("assets/textures/2k_mars.jpg"), None, None, None, 0.0, 1.03, 25.2, 1.85, 0.0, 0.0, 0.094, "6.39 × 10^23 kg", 210.0, "The fourth planet from the Sun, known as the Red Planet.", None));
+        bodies.push(create_body("Mars", 0.0022, 152.0, get_orbit_speed(p_mars), 19.39, (1.0, 0.0, 0.0), Some(0), Mesh::sphere, Some("assets/textures/2k_mars.jpg"), None, None, None, 0.0, 1.03, 25.2, 1.85, 49.56, 286.50, 0.094, "6.39 × 10^23 kg", 210.0, "The fourth planet from the Sun, known as the Red Planet.", None));
         let mars_idx = bodies.len() - 1;
 
         // Mars Moons
@@ -268,6 +643,14 @@ impl SolarSystem {
         let p_ceres = 1681.6;
         bodies.push(create_body("Ceres", 0.00029, 277.0, get_orbit_speed(p_ceres), 0.0, (0.4, 0.4, 0.4), Some(0), Mesh::sphere, Some("assets/textures/2k_ceres_fictional.jpg"), None, None, None, 0.0, 0.375, 4.0, 10.6, 0.0, 0.0, 0.076, "9.393 × 10^20 kg", 168.0, "The largest object in the asteroid belt.", None));
 
+        // Named asteroids with real osculating elements, mean anomaly taken back to J2000
+        let asteroid_epoch_days = NAMED_ASTEROID_EPOCH_JD - 2451545.0;
+        for asteroid in NAMED_ASTEROIDS {
+            let period = asteroid.a.powf(1.5) * 365.256;
+            let mean_anomaly_j2000 = (asteroid.mean_anomaly - 360.0 / period * asteroid_epoch_days as f32).rem_euclid(360.0);
+            bodies.push(create_body(asteroid.name, asteroid.radius_km * 0.0042 / 6371.0, asteroid.a * UNITS_PER_AU, get_orbit_speed(period), mean_anomaly_j2000, (0.55, 0.5, 0.45), Some(0), Mesh::sphere, Some("assets/textures/phobos.webp"), None, None, None, 0.0, asteroid.rotation_hours / 24.0, 0.0, asteroid.i, asteroid.node, asteroid.periapsis, asteroid.e, asteroid.mass, 200.0, asteroid.description, None));
+        }
+
         let mut rng = rand::thread_rng();
         for i in 0..1500 {
             let angle: f32 = rng.gen_range(0.0..360.0);
@@ -302,9 +685,9 @@ impl SolarSystem {
             ));
         }
 
-        let p_jupiter = 4332.589;
+        let p_jupiter = 4332.82;
 
-        bodies.push(create_body("Jupiter", 0.047, 520.0, get_orbit_speed(p_jupiter), 34.40, (0.8, 0.6, 0.4), Some(0), Mesh::sphere, Some("assets/textures/2k_jupiter.jpg"), None, None, None, 0.0, 0.41, 3.1, 1.3, 0.0, 0.0, 0.049, "1.898 × 10^27 kg", 165.0, "The largest planet in the Solar System.", None));
+        bodies.push(create_body("Jupiter", 0.047, 520.0, get_orbit_speed(p_jupiter), 19.67, (0.8, 0.6, 0.4), Some(0), Mesh::sphere, Some("assets/textures/2k_jupiter.jpg"), None, None, None, 0.0, 0.41, 3.1, 1.3, 100.46, 274.27, 0.0484, "1.898 × 10^27 kg", 165.0, "The largest planet in the Solar System.", None));
         let jupiter_idx = bodies.len() - 1;
 
         // Jupiter Moons
@@ -313,9 +696,9 @@ impl SolarSystem {
         bodies.push(create_body("Ganymede", 0.0017, 0.71, get_orbit_speed(7.15), 0.0, (0.6, 0.6, 0.6), Some(jupiter_idx), Mesh::sphere, Some("assets/textures/Ganymede.webp"), None, None, None, 0.0, 7.15, 0.2, 0.2, 0.0, 0.0, 0.001, "1.48 × 10^23 kg", 110.0, "The largest moon in the Solar System.", None));
         bodies.push(create_body("Callisto", 0.0016, 1.25, get_orbit_speed(16.69), 0.0, (0.4, 0.4, 0.4), Some(jupiter_idx), Mesh::sphere, Some("assets/textures/Callisto.webp"), None, None, None, 0.0, 16.69, 0.0, 0.2, 0.0, 0.0, 0.007, "1.08 × 10^23 kg", 134.0, "Jupiter's heavily cratered moon.", None));
 
-        let p_saturn = 10759.22;
+        let p_saturn = 10755.88;
 
-        bodies.push(create_body("Saturn", 0.039, 958.0, get_orbit_speed(p_saturn), 49.94, (0.9, 0.8, 0.5), Some(0), Mesh::sphere, Some("assets/textures/2k_saturn.jpg"), None, None, Some("assets/textures/2k_saturn_ring_alpha.png"), 0.09, 0.45, 26.7, 2.48, 0.0, 0.0, 0.057, "5.683 × 10^26 kg", 134.0, "The sixth planet from the Sun, famous for its rings.", Some(0.15)));
+        bodies.push(create_body("Saturn", 0.039, 958.0, get_orbit_speed(p_saturn), 317.34, (0.9, 0.8, 0.5), Some(0), Mesh::sphere, Some("assets/textures/2k_saturn.jpg"), None, None, Some("assets/textures/2k_saturn_ring_alpha.png"), 0.09, 0.45, 26.7, 2.48, 113.67, 338.93, 0.0539, "5.683 × 10^26 kg", 134.0, "The sixth planet from the Sun, famous for its rings.", Some(0.15)));
         let saturn_idx = bodies.len() - 1;
 
         // Saturn Moon
@@ -327,11 +710,11 @@ impl SolarSystem {
 
         let p_uranus = 30685.4;
 
-        bodies.push(create_body("Uranus", 0.017, 1920.0, get_orbit_speed(p_uranus), 313.23, (0.0, 0.8, 0.8), Some(0), Mesh::sphere, Some("assets/textures/2k_uranus.jpg"), None, None, None, 0.0, -0.72, 97.8, 0.77, 0.0, 0.0, 0.046, "8.681 × 10^25 kg", 76.0, "The seventh planet from the Sun.", None));
+        bodies.push(create_body("Uranus", 0.017, 1920.0, get_orbit_speed(p_uranus), 142.28, (0.0, 0.8, 0.8), Some(0), Mesh::sphere, Some("assets/textures/2k_uranus.jpg"), None, None, None, 0.0, -0.72, 97.8, 0.77, 74.02, 96.93, 0.0473, "8.681 × 10^25 kg", 76.0, "The seventh planet from the Sun.", None));
 
         let p_neptune = 60189.0;
 
-        bodies.push(create_body("Neptune", 0.016, 3005.0, get_orbit_speed(p_neptune), 304.88, (0.0, 0.0, 0.8), Some(0), Mesh::sphere, Some("assets/textures/2k_neptune.jpg"), None, None, None, 0.0, 0.67, 28.3, 1.77, 0.0, 0.0, 0.011, "1.024 × 10^26 kg", 72.0, "The eighth and farthest-known Solar planet from the Sun.", None));
+        bodies.push(create_body("Neptune", 0.016, 3005.0, get_orbit_speed(p_neptune), 259.92, (0.0, 0.0, 0.8), Some(0), Mesh::sphere, Some("assets/textures/2k_neptune.jpg"), None, None, None, 0.0, 0.67, 28.3, 1.77, 131.78, 273.18, 0.0086, "1.024 × 10^26 kg", 72.0, "The eighth and farthest-known Solar planet from the Sun.", None));
 
 
         let p_pluto = 90560.0;
@@ -437,64 +820,29 @@ impl SolarSystem {
         let background_mesh = Mesh::sphere(1.0, 40, 40, 1.0, 1.0, 1.0);
 
 
-        let trail_points = 1000;
-        for i in 0..bodies.len() {
-            let body = &mut bodies[i];
-            if body.name.starts_with("Asteroid") || body.name.starts_with("Kuiper") || body.name.starts_with("Oort") { continue; }
-            if body.orbit_radius > 0.0 && body.orbit_speed != 0.0 {
-                let full_circle = 2.0 * std::f32::consts::PI;
-                let angle_step = full_circle / trail_points as f32;
-                
-
-
-
-                
-                for j in 0..trail_points {
-                    let angle_offset = -full_circle + (j as f32 * angle_step);
-                    let angle = body.orbit_angle + angle_offset;
-                    
-                    // Kepler for initial trail
-                    let m = angle;
-                    let e = body.eccentricity;
-                    let big_e = m + e * m.sin();
-                    
-                    let x_orb_raw = body.orbit_radius * (big_e.cos() - e);
-                    let z_orb_raw = body.orbit_radius * (1.0 - e*e).sqrt() * big_e.sin();
-                    
-                    // Apply Argument of Periapsis
-                    let w = body.argument_of_periapsis;
-                    let (sin_w, cos_w) = w.sin_cos();
-                    let x_orb = x_orb_raw * cos_w + z_orb_raw * sin_w;
-                    let z_orb = -x_orb_raw * sin_w + z_orb_raw * cos_w;
-                    
-                    let y_incl = z_orb * body.orbit_inclination.sin();
-                    let z_incl = z_orb * body.orbit_inclination.cos();
-                    
-                    // Apply Longitude of Ascending Node
-                    let omega = body.longitude_of_ascending_node;
-                    let (sin_o, cos_o) = omega.sin_cos();
-                    
-                    let x_final = x_orb * cos_o + z_incl * sin_o;
-                    let y_final = y_incl;
-                    let z_final = -x_orb * sin_o + z_incl * cos_o;
-                    
-                    let pos = Vector3::new(x_final, y_final, z_final);
-                    
-                    body.trail.push(pos.x);
-                    body.trail.push(pos.y);
-                    body.trail.push(pos.z);
-                }
+        let years_since_j2000 = ((now_ms - J2000_MS) / MS_PER_JULIAN_YEAR) as f32;
+        for body in bodies.iter_mut() {
+            if let Some((periapsis_rate, node_rate)) = secular_rates(body.name.trim()) {
+                body.periapsis_rate = periapsis_rate;
+                body.node_rate = node_rate;
+                body.drift_years = years_since_j2000;
+                body.path_drift_years = years_since_j2000;
             }
+            body.orbit_path = build_orbit_path(body);
         }
 
 
+        let mut list_items = Vec::new();
         if let Ok(Some(list)) = document.query_selector(".body-list") {
             list.set_inner_html(""); // Clear existing
             
             for (i, body) in bodies.iter().enumerate() {
                 if body.name.starts_with("Asteroid") || body.name.starts_with("Kuiper") || body.name.starts_with("Oort") { continue; }
                 
-                let li = document.create_element("li").unwrap();
+                let li = match document.create_element("li") {
+                    Ok(li) => li,
+                    Err(_) => continue,
+                };
                 let category = if body.name == "Sun" || body.name == "Black Hole" || body.name.starts_with("Sirius") {
                     "star"
                 } else if let Some(parent_idx) = body.parent {
@@ -512,31 +860,60 @@ impl SolarSystem {
                 
                 li.set_inner_html(&format!("{}<span>{}</span>", icon_svg, body.name));
                 
-                li.set_attribute("data-category", category).unwrap();
-                li.set_attribute("onclick", &format!("selectSolarBody({})", i)).unwrap();
+                li.set_attribute("data-category", category).ok();
+                li.set_attribute("onclick", &format!("selectSolarBody({})", i)).ok();
                 
-                list.append_child(&li).unwrap();
+                if list.append_child(&li).is_ok() {
+                    list_items.push(li);
+                }
             }
         }
 
         let sun_texture = if system_type == SystemType::Solar { bodies[0].texture.clone() } else { None };
 
+        let apsis_labels = labels_container.as_ref().and_then(|container| {
+            let create_label = |text: &str| {
+                let el = document.create_element("div").ok()?;
+                el.set_class_name("solar-label apsis-label");
+                el.set_text_content(Some(text));
+                el.set_attribute("style", "display: none;").ok();
+                container.append_child(&el).ok()?;
+                el.dyn_into::<HtmlElement>().ok()
+            };
+            Some((create_label("Periapsis")?, create_label("Apoapsis")?))
+        });
+
+        let star_labels = NEARBY_STARS.iter().map(|star| {
+            let container = labels_container.as_ref()?;
+            let el = document.create_element("div").ok()?;
+            el.set_class_name("solar-label nearby-star-label");
+            el.set_text_content(Some(star.name));
+            el.set_attribute("style", "display: none;").ok();
+            container.append_child(&el).ok()?;
+            Some((el.dyn_into::<HtmlElement>().ok()?, Cell::new(None)))
+        }).collect();
+        let galaxy_texture = renderer.create_texture_from_rgba(GALAXY_TEXTURE_SIZE as i32, GALAXY_TEXTURE_SIZE as i32, &galaxy_pixels(GALAXY_TEXTURE_SIZE)).ok();
+
         let focused_body_index = match system_type {
             SystemType::Solar => Some(3),
             SystemType::BlackHole => Some(1),
             SystemType::Sirius => Some(0),
         };
 
-        SolarSystem {
+        let central_luminosity = match system_type {
+            SystemType::Solar => SOLAR_LUMINOSITY_W,
+            SystemType::BlackHole => 0.0,
+            SystemType::Sirius => SIRIUS_A_LUMINOSITY_SOLAR * SOLAR_LUMINOSITY_W,
+        };
+
+        let mut system = SolarSystem {
             renderer,
             bodies,
-            camera_distance: 60.0,
-            camera_rotation: (0.5, 0.0),
+            camera: OrbitController::new(60.0, 0.5, 0.0),
             last_time: now_ms,
-            is_dragging: false,
-            last_mouse_pos: (0, 0),
             time_scale: 1.0,
             current_time: now_ms,
+            manual_time: false,
             background_mesh,
             background_texture,
             focused_body_index,
@@ -546,38 +923,75 @@ impl SolarSystem {
             system_type,
             sun_texture,
             use_celsius: true,
+            comparison: None,
+            focused_orbit: None,
+            apsis_labels,
+            list_items,
+            central_luminosity,
+            live_temperatures: system_type == SystemType::BlackHole,
+            tour: None,
+            focus_from: None,
+            focus_blend: 1.0,
+            observer: None,
+            earth_markers: false,
+            validity_years: DEFAULT_VALIDITY_YEARS,
+            elements_stale: false,
+            sonification: None,
+            ambient: None,
+            trail_min_pixels: DEFAULT_TRAIL_MIN_PIXELS,
+            galaxy_texture,
+            star_labels,
+            star_screen: RefCell::new(Vec::new()),
+            pick_frame: RefCell::new(None),
+            stereo_mode: StereoMode::Off,
+            eye_separation: Settings::load().solar_eye_separation,
+            press: None,
+            surface_view: None,
+            slowmo: SlowMo::default(),
+            scale_ramp: None,
+            time_bookmarks: solar_time::load_bookmarks(),
+        };
+        system.rebuild_focused_orbit();
+        if system.live_temperatures {
+            let positions = system.world_positions();
+            system.update_temperatures(&positions);
         }
+        system
     }
 
     pub fn select_body(&mut self, index: usize) {
+        self.cancel_tour();
+        self.surface_view = None;
+        self.focus_body(index);
+    }
+
+    fn focus_body(&mut self, index: usize) {
+        self.focus_from = self.focused_body_index;
+        self.focus_blend = 0.0;
         if index < self.bodies.len() {
             self.focused_body_index = Some(index);
             let body = &self.bodies[index];
+            events::emit("body_selected", serde_json::json!(body.name));
 
             let window = web_sys::window().unwrap();
             let document = window.document().unwrap();
             
             if let Some(panel) = document.get_element_by_id("solar-info-panel") {
-                panel.set_attribute("style", "position: absolute; top: 20px; right: 20px; width: 280px; display: block; pointer-events: auto; padding: 20px;").unwrap();
+                panel.set_attribute("style", "position: absolute; top: 20px; right: 20px; width: 280px; display: block; pointer-events: auto; padding: 20px;").ok();
                 panel.set_class_name("panel-glass");
                 
                 if let Some(el) = document.get_element_by_id("info-name") { el.set_text_content(Some(&body.name)); }
                 if let Some(el) = document.get_element_by_id("info-mass") { el.set_text_content(Some(&body.mass)); }
                 if let Some(el) = document.get_element_by_id("info-radius") { el.set_text_content(Some(&format!("{:.1} km", body.radius * 6371.0 / 0.0042))); } // Approx scale based on Earth
                 if let Some(el) = document.get_element_by_id("info-temp") {
-                    let temp_str = if self.use_celsius {
-                        format!("{:.0} °C", body.temperature - 273.15)
-                    } else {
-                        format!("{:.0} K", body.temperature)
-                    };
-                    el.set_text_content(Some(&temp_str));
+                    el.set_text_content(Some(&self.format_temperature(body.temperature)));
                 }
                 if let Some(el) = document.get_element_by_id("info-speed") {
                     if body.name.trim() == "Sun" || body.name.trim() == "Black Hole" {
                          el.set_text_content(Some("230 km/s (Galactic)"));
                     } else {
                         let speed_km_s = body.orbit_speed.abs() * body.orbit_radius * 1496000.0;
-                        el.set_text_content(Some(&format!("{:.2} km/s", speed_km_s)));
+                        el.set_text_content(Some(&self.format_measure(speed_km_s, 2, "km/s")));
                     }
                 }
                 if let Some(el) = document.get_element_by_id("info-period") { 
@@ -589,327 +1003,1181 @@ impl SolarSystem {
                         } else {
                             0.0
                         };
-                        el.set_text_content(Some(&format!("{:.2} days", period))); 
+                        el.set_text_content(Some(&self.format_measure(period, 2, "days")));
                     }
                 }
                 if let Some(el) = document.get_element_by_id("info-eccentricity") { el.set_text_content(Some(&format!("{:.4}", body.eccentricity))); }
                 if let Some(el) = document.get_element_by_id("info-desc") { el.set_text_content(Some(&body.description)); }
             }
 
+            self.update_apsis_info(index);
+
             let radius = self.bodies[index].radius;
-            self.camera_distance = radius * 5.0;
-            self.camera_distance = self.camera_distance.max(radius * 1.5);
+            // Bodies as small as Bennu would otherwise put the camera inside the near plane
+            self.camera.set_distance((radius * 5.0).max(MIN_FOCUS_DISTANCE));
         } else {
             self.focused_body_index = None;
             let window = web_sys::window().unwrap();
             let document = window.document().unwrap();
             if let Some(panel) = document.get_element_by_id("solar-info-panel") {
-                panel.set_attribute("style", "display: none;").unwrap();
+                panel.set_attribute("style", "display: none;").ok();
             }
         }
+        self.rebuild_focused_orbit();
+        self.retune_sonification();
     }
 
-    pub fn toggle_temperature_unit(&mut self) {
-        self.use_celsius = !self.use_celsius;
-        if let Some(index) = self.focused_body_index {
-            self.select_body(index);
-        }
+    pub fn set_auto_slowmo(&mut self, enabled: bool) {
+        self.slowmo.enabled = enabled;
+        self.slowmo.active.clear();
     }
 
-    pub fn set_date_from_timestamp(&mut self, timestamp: f64) {
-        self.current_time = timestamp;
-        let j2000_ms = 946728000000.0;
-        let days_since_j2000 = (timestamp - j2000_ms) / (1000.0 * 60.0 * 60.0 * 24.0);
-        
-        for body in &mut self.bodies {
-            if body.orbit_speed.abs() > 0.0 {
-                 let n_rad_per_day = body.orbit_speed * 86400.0;
-                 let angle_rad = body.mean_longitude_at_epoch.to_radians() + n_rad_per_day * days_since_j2000 as f32;
-                 body.orbit_angle = angle_rad % (2.0 * std::f32::consts::PI);
+    pub fn set_slowmo_config(&mut self, json: &str) -> Result<(), String> {
+        self.slowmo.config = SlowMoConfig::parse(json)?;
+        self.slowmo.active.clear();
+        Ok(())
+    }
+
+    // (name, [observer, a, b], threshold, opposite) of every alignment being watched
+    fn monitored_alignments(&self) -> Vec<(String, [usize; 3], f32, bool)> {
+        let config = &self.slowmo.config;
+        let mut monitored = Vec::new();
+        if config.siblings {
+            if let Some((index, parent)) = self.focused_body_index.and_then(|i| Some((i, self.bodies[i].parent?))) {
+                for (j, other) in self.bodies.iter().enumerate() {
+                    if j == index || other.parent != Some(parent) || is_small_body(other) {
+                        continue;
+                    }
+                    let name = format!("{} and {}", self.bodies[index].name.trim(), other.name.trim());
+                    monitored.push((name, [parent, index, j], config.sibling_threshold_deg, false));
+                }
             }
-            
-            if body.rotation_period != 0.0 {
-                let period_seconds = body.rotation_period.abs() * 24.0 * 3600.0;
-                let rotation_speed = (2.0 * std::f32::consts::PI) / period_seconds;
-                let total_seconds = days_since_j2000 * 24.0 * 3600.0;
-                body.current_rotation = (rotation_speed * total_seconds as f32) % (2.0 * std::f32::consts::PI);
+        }
+        for pair in &config.pairs {
+            if let (Some(observer), Some(a), Some(b)) = (self.find_body(&pair.observer), self.find_body(&pair.a), self.find_body(&pair.b)) {
+                monitored.push((pair.name.clone(), [observer, a, b], pair.threshold_deg, pair.opposite));
             }
         }
+        monitored
     }
 
-    pub fn set_time_scale(&mut self, scale: f32) {
-        self.time_scale = scale;
+    fn slowmo_factor(&mut self, positions: &[Vector3<f32>]) -> f32 {
+        if !self.slowmo.enabled {
+            return 1.0;
+        }
+        let monitored = self.monitored_alignments();
+        self.slowmo.factor(&monitored, positions)
     }
 
-    pub fn update(&mut self) {
-        let now = Date::now();
-        let dt = (now - self.last_time) / 1000.0;
-        self.last_time = now;
-        
-        // Prevent huge time jumps if dt is too large (e.g. tab inactive)
-        let safe_dt = if dt > 0.1 { 0.1 } else { dt };
-        
-        self.current_time += safe_dt * 1000.0 * self.time_scale as f64;
-        
-        let date = Date::new(&wasm_bindgen::JsValue::from_f64(self.current_time));
-        let window = web_sys::window().unwrap();
-        let document = window.document().unwrap();
-            if let Some(element) = document.get_element_by_id("solar-date") {
-                let year = date.get_full_year();
-                let month = date.get_month() + 1;
-                let day = date.get_date();
-                let hours = date.get_hours();
-                let minutes = date.get_minutes();
-                let seconds = date.get_seconds();
-                let date_str = format!("{:02}/{:02}/{} {:02}:{:02}:{:02}", month, day, year, hours, minutes, seconds);
-                element.set_text_content(Some(&date_str));
+    // Fraction of the frame's step to take. At high time scales an alignment can go from one
+    // side of its threshold to the other between frames and never slow anything down, so a
+    // step that passes one is cut short to land just past it and the next frames ease through.
+    fn slowmo_landing(&self, positions: &[Vector3<f32>], seconds: f32) -> f32 {
+        let monitored = self.monitored_alignments();
+        let passes = |end: &[Vector3<f32>]| {
+            monitored.iter().any(|(_, [observer, a, b], _, opposite)| {
+                let start = [positions[*observer], positions[*a], positions[*b]];
+                solar_slowmo::passed_between(start, [end[*observer], end[*a], end[*b]], *opposite)
+            })
+        };
+        if seconds == 0.0 || !passes(&self.world_positions_after(seconds)) {
+            return 1.0;
+        }
+        let (mut before, mut after) = (0.0, 1.0);
+        for _ in 0..16 {
+            let middle = (before + after) * 0.5;
+            if passes(&self.world_positions_after(seconds * middle)) {
+                after = middle;
+            } else {
+                before = middle;
             }
+        }
+        after
+    }
 
-        // Update speed info if a body is selected
-        if let Some(idx) = self.focused_body_index {
-            if idx < self.bodies.len() {
-                let body = &self.bodies[idx];
-                if let Some(el) = document.get_element_by_id("info-speed") {
-                    let speed_kmh = if body.orbit_radius > 0.0 {
-                        // Calculate current distance r
-                        let m = body.orbit_angle;
-                        let e = body.eccentricity;
-                        let big_e = m + e * m.sin();
-                        let x_orb = body.orbit_radius * (big_e.cos() - e);
-                        let z_orb = body.orbit_radius * (1.0 - e*e).sqrt() * big_e.sin();
-                        let r = (x_orb*x_orb + z_orb*z_orb).sqrt();
-
-                        // Vis-viva equation: v = sqrt(mu * (2/r - 1/a))
-                        // mu = n^2 * a^3
-                        // v = n * a * sqrt(2a/r - 1)
-                        let n = body.orbit_speed.abs();
-                        let a = body.orbit_radius;
-                        
-                        if r > 0.0 {
-                            let v_sim = n * a * ((2.0 * a / r) - 1.0).abs().sqrt();
-                            // Convert to km/h
-                            // Scale: 1 unit = 6371.0 / 0.0042 km
-                            let scale = 6371.0 / 0.0042;
-                            v_sim * scale * 3600.0
-                        } else {
-                            0.0
-                        }
-                    } else {
-                        0.0
-                    };
-                    el.set_text_content(Some(&format!("{:.0} km/h", speed_kmh)));
-                }
-            }
+    pub fn set_ambient(&mut self, enabled: bool) {
+        self.ambient = None;
+        if !enabled {
+            return;
         }
+        self.ambient = Audio::new().and_then(|audio| {
+            let hum = audio.noise_loop(AMBIENT_HUM_HZ)?;
+            audio.set_loop_volume(&hum, AMBIENT_VOLUME);
+            Some((hum, audio))
+        });
+    }
 
-        let mut positions = vec![Vector3::new(0.0, 0.0, 0.0); self.bodies.len()];        for i in 0..self.bodies.len() {
+    pub fn set_sonification(&mut self, enabled: bool) {
+        self.sonification = if enabled { Sonification::new() } else { None };
+        self.retune_sonification();
+    }
 
-            let body = &mut self.bodies[i];
-            if body.parent.is_some() {
-                body.orbit_angle += body.orbit_speed * safe_dt as f32 * self.time_scale;
-                body.orbit_angle %= 2.0 * std::f32::consts::PI;
+    // Only the focused body and its moons are voiced, never a whole system at once
+    fn retune_sonification(&mut self) {
+        let sonification = match self.sonification.as_mut() {
+            Some(sonification) => sonification,
+            None => return,
+        };
+        let mut voiced = Vec::new();
+        if let Some(index) = self.focused_body_index {
+            let focused = &self.bodies[index];
+            if focused.parent.is_some() {
+                voiced.extend(orbital_period_days(focused).map(|period| (index, period)));
+                for (i, body) in self.bodies.iter().enumerate() {
+                    if body.parent == Some(index) {
+                        voiced.extend(orbital_period_days(body).map(|period| (i, period)));
+                    }
+                }
             }
-            
-
-            if body.rotation_period != 0.0 {
-
+        }
+        sonification.retune(&voiced);
+    }
 
+    pub fn start_tour(&mut self, stops_json: Option<&str>) -> Result<(), String> {
+        let stops: Vec<TourStop> = match stops_json {
+            Some(json) => serde_json::from_str(json).map_err(|e| e.to_string())?,
+            None => solar_tour::default_stops(),
+        };
+        let resolved: Vec<(usize, TourStop)> = stops.into_iter()
+            .filter_map(|stop| {
+                self.bodies.iter()
+                    .position(|b| b.name.trim() == stop.body.trim())
+                    .map(|index| (index, stop))
+            })
+            .collect();
+        if resolved.is_empty() {
+            return Err("No tour stops match a body in this system".to_string());
+        }
 
-                let period_seconds = body.rotation_period.abs() * 24.0 * 3600.0;
-                let rotation_speed = (2.0 * std::f32::consts::PI) / period_seconds;
-                
+        self.cancel_tour();
+        self.scale_ramp = None;
+        self.tour = Some(Tour::new(resolved, self.time_scale));
+        self.arrive_at_tour_stop(0);
+        Ok(())
+    }
 
-                body.current_rotation += rotation_speed * safe_dt as f32 * self.time_scale;
-                body.current_rotation %= 2.0 * std::f32::consts::PI;
+    pub fn cancel_tour(&mut self) {
+        if let Some(tour) = self.tour.take() {
+            self.time_scale = tour.end();
+            events::emit("tour_ended", serde_json::json!({ "completed": false }));
+        }
+    }
 
-                if body.cloud_texture.is_some() {
+    fn arrive_at_tour_stop(&mut self, stop_index: usize) {
+        let (index, stop) = match self.tour.as_ref().and_then(|t| t.stops.get(stop_index)) {
+            Some((index, stop)) => (*index, stop.clone()),
+            None => return,
+        };
+        self.focus_body(index);
+        if let Some(distance) = stop.distance {
+            self.camera.set_distance(distance);
+        }
+        self.time_scale = stop.time_scale;
+        events::emit("tour_stop", serde_json::json!({
+            "index": stop_index,
+            "body": self.bodies[index].name.trim(),
+        }));
+    }
 
-                    body.cloud_rotation += rotation_speed * 0.2 * safe_dt as f32 * self.time_scale;
-                    body.cloud_rotation %= 2.0 * std::f32::consts::PI;
+    fn advance_tour(&mut self, dt: f32) {
+        let step = match self.tour.as_mut() {
+            Some(tour) => tour.advance(dt),
+            None => return,
+        };
+        match step {
+            TourStep::Stay => {}
+            TourStep::Arrive(stop_index) => self.arrive_at_tour_stop(stop_index),
+            TourStep::Finished => {
+                if let Some(tour) = self.tour.take() {
+                    self.time_scale = tour.end();
                 }
+                events::emit("tour_ended", serde_json::json!({ "completed": true }));
             }
+        }
+    }
 
-            // Calculate position using Kepler's equation approximation
-            // M = orbit_angle (Mean Anomaly)
-            // E approx M + e*sin(M) (Eccentric Anomaly)
-            // x = a * (cos(E) - e)
-            // z = a * sqrt(1 - e^2) * sin(E)
-            
-            let m = body.orbit_angle;
-            let e = body.eccentricity;
-            // Simple approximation for E (Eccentric Anomaly)
-            let big_e = m + e * m.sin(); 
-            
-            let x_orb_raw = body.orbit_radius * (big_e.cos() - e);
-            let z_orb_raw = body.orbit_radius * (1.0 - e*e).sqrt() * big_e.sin();
-            
-            // Apply Argument of Periapsis
-            let w = body.argument_of_periapsis;
-            let (sin_w, cos_w) = w.sin_cos();
-            let x_orb = x_orb_raw * cos_w + z_orb_raw * sin_w;
-            let z_orb = -x_orb_raw * sin_w + z_orb_raw * cos_w;
-            
-            // Apply inclination
-            // Rotate around X axis by inclination
-            let y_incl = z_orb * body.orbit_inclination.sin();
-            let z_incl = z_orb * body.orbit_inclination.cos();
-            
-            // Apply Longitude of Ascending Node (Rotation around Y axis)
-            let omega = body.longitude_of_ascending_node;
-            let (sin_o, cos_o) = omega.sin_cos();
-            
-            let x_final = x_orb * cos_o + z_incl * sin_o;
-            let y_final = y_incl;
-            let z_final = -x_orb * sin_o + z_incl * cos_o;
-            
-            let mut pos = Vector3::new(x_final, y_final, z_final);
-            
-            if let Some(parent_idx) = body.parent {
-                pos += positions[parent_idx];
-            }
-            
-            positions[i] = pos;
-            
-            if body.orbit_radius > 0.0 {
-                if body.name.starts_with("Asteroid") || body.name.starts_with("Kuiper") || body.name.starts_with("Oort") { continue; }
-
-                let two_pi = 2.0 * std::f32::consts::PI;
-                let angle_step = two_pi / 1000.0; // 1000 points per orbit
-                
+    pub fn set_observer_location(&mut self, latitude: f32, longitude: f32) {
+        self.observer = Some((latitude.clamp(-90.0, 90.0), wrap_degrees(longitude)));
+        self.earth_markers = true;
+    }
 
-                let current_angle = body.orbit_angle % two_pi;
-                let last_angle = body.last_trail_angle % two_pi;
-                
-                let mut diff = current_angle - last_angle;
-                if diff < 0.0 {
-                    diff += two_pi;
-                }
-                
+    pub fn set_earth_markers(&mut self, enabled: bool) {
+        self.earth_markers = enabled;
+    }
 
-                if diff >= angle_step {
-                    let steps = (diff / angle_step).floor() as usize;
-                    
+    fn find_body(&self, name: &str) -> Option<usize> {
+        self.bodies.iter().position(|b| b.name.trim() == name)
+    }
 
+    // A body's rotation-only frame, matching the model matrix it is drawn with
+    fn body_frame(&self, index: usize) -> Matrix4<f32> {
+        let body = &self.bodies[index];
+        spin_frame(body.axial_tilt, body.current_rotation)
+    }
 
-                    let steps_to_add = steps.min(1000);
-                    
-                    for k in 1..=steps_to_add {
-                        let a_angle = body.last_trail_angle + (k as f32 * angle_step);
-                        
-                        // Same Kepler calculation for trail
-                        let m_t = a_angle;
-                        let big_e_t = m_t + e * m_t.sin();
-                        
-                        let x_t_raw = body.orbit_radius * (big_e_t.cos() - e);
-                        let z_t_raw = body.orbit_radius * (1.0 - e*e).sqrt() * big_e_t.sin();
-                        
-                        // Apply Argument of Periapsis
-                        let w = body.argument_of_periapsis;
-                        let (sin_w, cos_w) = w.sin_cos();
-                        let x_t = x_t_raw * cos_w + z_t_raw * sin_w;
-                        let z_t = -x_t_raw * sin_w + z_t_raw * cos_w;
-                        
-                        let y_incl = z_t * body.orbit_inclination.sin();
-                        let z_incl = z_t * body.orbit_inclination.cos();
-                        
-                        // Apply Longitude of Ascending Node
-                        let omega = body.longitude_of_ascending_node;
-                        let (sin_o, cos_o) = omega.sin_cos();
-                        
-                        let x_final = x_t * cos_o + z_incl * sin_o;
-                        let y_final = y_incl;
-                        let z_final = -x_t * sin_o + z_incl * cos_o;
-                        
-                        let p = Vector3::new(x_final, y_final, z_final);
-                        
-                        body.trail.push(p.x);
-                        body.trail.push(p.y);
-                        body.trail.push(p.z);
-                    }
-                    
-                    body.last_trail_angle += steps as f32 * angle_step;
-                    body.last_trail_angle %= two_pi;
-                    
+    // Latitude and longitude (degrees) where the Sun is straight overhead
+    fn subsolar_point(&self, positions: &[Vector3<f32>]) -> Option<(f32, f32)> {
+        self.subsolar_point_on(positions, self.find_body("Earth")?)
+    }
 
-                    while body.trail.len() > 3000 {
-                        body.trail.drain(0..3);
-                    }
-                }
-            }
+    // Same for any body, lit by the root of its parent chain. None for the root itself.
+    fn subsolar_point_on(&self, positions: &[Vector3<f32>], index: usize) -> Option<(f32, f32)> {
+        let mut star = self.bodies[index].parent?;
+        while let Some(parent) = self.bodies[star].parent {
+            star = parent;
         }
+        let to_star = (positions[star] - positions[index]).normalize();
+        let body = &self.bodies[index];
+        Some(subsolar_coordinates(body.axial_tilt, body.current_rotation, to_star))
     }
 
-    pub fn render(&self, width: i32, height: i32) {
-        self.renderer.clear(0.0, 0.0, 0.0);
-        self.renderer.resize(width, height);
-        self.renderer.enable_depth_test();
-
+    // Stands the camera on the focused body, at the observer's spot if one is set,
+    // otherwise where its star is overhead
+    pub fn view_from_surface(&mut self, enabled: bool) -> Result<(), String> {
+        if !enabled {
+            self.surface_view = None;
+            return Ok(());
+        }
+        let body = self.focused_body_index.ok_or("Select a body to stand on first")?;
+        self.cancel_tour();
+        let positions = self.world_positions();
+        let (latitude, longitude) = self.observer
+            .or_else(|| self.subsolar_point_on(&positions, body))
+            .unwrap_or((0.0, 0.0));
+        let mut look = OrbitController::new(1.0, 0.2, 0.0);
+        look.min_pitch = -0.5;
+        self.surface_view = Some(SurfaceView { body, latitude, longitude, look });
+        Ok(())
+    }
 
-        let mut positions = vec![Vector3::new(0.0, 0.0, 0.0); self.bodies.len()];
-        for i in 0..self.bodies.len() {
-            let body = &self.bodies[i];
-            
-            let m = body.orbit_angle;
-            let e = body.eccentricity;
-            let big_e = m + e * m.sin();
-            
-            let x_orb_raw = body.orbit_radius * (big_e.cos() - e);
-            let z_orb_raw = body.orbit_radius * (1.0 - e*e).sqrt() * big_e.sin();
-            
-            // Apply Argument of Periapsis
-            let w = body.argument_of_periapsis;
-            let (sin_w, cos_w) = w.sin_cos();
-            let x_orb = x_orb_raw * cos_w + z_orb_raw * sin_w;
-            let z_orb = -x_orb_raw * sin_w + z_orb_raw * cos_w;
-            
-            let y_incl = z_orb * body.orbit_inclination.sin();
-            let z_incl = z_orb * body.orbit_inclination.cos();
+    // The view and the local up for a camera standing on the surface
+    fn surface_camera(&self, surface: &SurfaceView, positions: &[Vector3<f32>], width: i32, height: i32) -> (SceneView, Vector3<f32>) {
+        let body = &self.bodies[surface.body];
+        let target = positions[surface.body];
+        let frame = self.body_frame(surface.body);
+        let up = frame.transform_vector(&surface_point(surface.latitude, surface.longitude)).normalize();
+        let eye = up * body.radius * SURFACE_EYE_HEIGHT;
+
+        // Azimuth counts from the body's north toward east, at a pole any tangent will do
+        let pole = frame.transform_vector(&Vector3::y());
+        let north = pole - up * pole.dot(&up);
+        let north = if north.norm() > 1e-4 { north.normalize() } else { frame.transform_vector(&Vector3::x()).normalize() };
+        let east = north.cross(&up);
+        let (azimuth, altitude) = (surface.look.yaw, surface.look.pitch);
+        let direction = (north * azimuth.cos() + east * azimuth.sin()) * altitude.cos() + up * altitude.sin();
+        let view = Matrix4::look_at_rh(&Point3::from(eye), &Point3::from(eye + direction), &up);
+
+        let farthest = positions.iter().map(|p| (p - target).norm()).fold(0.0, f32::max);
+        let projection = Matrix4::new_perspective(
+            width as f32 / height as f32,
+            45.0 * std::f32::consts::PI / 180.0,
+            body.radius * SURFACE_NEAR_FRACTION,
+            (farthest * 2.0).max(SURFACE_MIN_FAR),
+        );
+        (SceneView { target, eye, projection, view, width, height }, up)
+    }
 
-            // Apply Longitude of Ascending Node
-            let omega = body.longitude_of_ascending_node;
-            let (sin_o, cos_o) = omega.sin_cos();
-            
-            let x_final = x_orb * cos_o + z_incl * sin_o;
-            let y_final = y_incl;
-            let z_final = -x_orb * sin_o + z_incl * cos_o;
+    // Geocentric ecliptic longitude (degrees) and the zodiac constellation it falls in.
+    // Only meaningful in the real Solar System and for anything other than Earth itself.
+    fn sky_position(&self, positions: &[Vector3<f32>], index: usize) -> Option<(f32, &'static str)> {
+        if self.system_type != SystemType::Solar {
+            return None;
+        }
+        let earth = self.find_body("Earth")?;
+        if index == earth {
+            return None;
+        }
+        let longitude = ecliptic_longitude(positions[index] - positions[earth]);
+        Some((longitude, zodiac_constellation(longitude)))
+    }
 
-            let mut pos = Vector3::new(x_final, y_final, z_final);
-            if let Some(parent_idx) = body.parent {
-                pos += positions[parent_idx];
+    fn update_sky_position(&self, positions: &[Vector3<f32>]) {
+        let text = match self.focused_body_index.and_then(|index| self.sky_position(positions, index)) {
+            Some((longitude, constellation)) => format!("{} ({:.1}°)", constellation, longitude),
+            None => "-".to_string(),
+        };
+        let document = web_sys::window().unwrap().document().unwrap();
+        if let Some(el) = document.get_element_by_id("info-constellation") {
+            // Changes slowly, skip the DOM write when nothing moved
+            if el.text_content().as_deref() != Some(text.as_str()) {
+                el.set_text_content(Some(&text));
             }
-            positions[i] = pos;
         }
+    }
 
-        let target = if let Some(idx) = self.focused_body_index {
-            positions[idx]
-        } else {
-            Vector3::new(0.0, 0.0, 0.0)
+    pub fn body_info_json(&self, index: usize) -> Option<String> {
+        let body = self.bodies.get(index)?;
+        let positions = self.world_positions();
+        let sky = self.sky_position(&positions, index);
+        let info = serde_json::json!({
+            "name": body.name.trim(),
+            "temperature_k": body.temperature,
+            "ecliptic_longitude": sky.map(|(longitude, _)| longitude),
+            "constellation": sky.map(|(_, constellation)| constellation),
+            "summary": sky.map(|(_, constellation)| format!("{} in {}", body.name.trim(), constellation)),
+        });
+        Some(info.to_string())
+    }
+
+    fn update_solar_time(&self, positions: &[Vector3<f32>]) {
+        let earth = self.find_body("Earth");
+        if earth.is_none() || self.focused_body_index != earth {
+            return;
+        }
+        let text = match (self.observer, self.subsolar_point(positions)) {
+            (Some((_, lon)), Some((_, subsolar_lon))) => {
+                let hours = (12.0 + wrap_degrees(lon - subsolar_lon) / 15.0).rem_euclid(24.0);
+                format!("{:02}:{:02}", hours as u32, (hours.fract() * 60.0) as u32)
+            }
+            _ => "-".to_string(),
         };
+        let document = web_sys::window().unwrap().document().unwrap();
+        if let Some(el) = document.get_element_by_id("info-solar-time") { el.set_text_content(Some(&text)); }
+    }
 
-        let aspect = width as f32 / height as f32;
-        let projection = Matrix4::new_perspective(aspect, 45.0 * std::f32::consts::PI / 180.0, 0.001, 200000000.0); // Increased far plane significantly
-        
+    fn render_earth_markers(&self, positions: &[Vector3<f32>], target: Vector3<f32>, camera_pos: Vector3<f32>, projection: &Matrix4<f32>, view: &Matrix4<f32>) {
+        let earth = match self.find_body("Earth") {
+            Some(index) => index,
+            None => return,
+        };
+        let body = &self.bodies[earth];
+        let pos = positions[earth] - target;
+        // Only when the globe itself is drawn, not the enlarged far-away dot
+        if (camera_pos - pos).norm() * 0.002 > body.radius {
+            return;
+        }
+
+        let mut markers = Vec::new();
+        if let Some((lat, lon)) = self.subsolar_point(positions) {
+            markers.push((lat, lon, (1.0, 0.85, 0.1)));
+        }
+        if let Some((lat, lon)) = self.observer {
+            markers.push((lat, lon, (0.2, 1.0, 0.4)));
+        }
+
+        let frame = self.body_frame(earth);
+        let size = body.radius * 0.04;
+        for (lat, lon, color) in markers {
+            let p = pos + frame.transform_vector(&surface_point(lat, lon)) * body.radius * 1.01;
+            self.renderer.draw_mesh(
+                &self.sphere_mesh,
+                p.x, p.y, p.z,
+                size, size, size,
+                0.0, 0.0, 0.0,
+                projection,
+                view,
+                None,
+                None,
+                Some(color),
+                false,
+                None,
+                false,
+                false,
+                false,
+                None,
+                None
+            );
+        }
+    }
 
+    pub fn set_body_orbit(&mut self, index: usize, json: &str) -> Result<(), String> {
+        let update: OrbitUpdate = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        let body = self.bodies.get_mut(index).ok_or("No such body")?;
+        if body.parent.is_none() {
+            return Err(format!("{} does not orbit anything", body.name));
+        }
 
+        if let Some(a) = update.semi_major_axis { body.orbit_radius = a.max(0.0); }
+        if let Some(e) = update.eccentricity { body.eccentricity = e.clamp(0.0, 0.99); }
+        if let Some(i) = update.inclination { body.orbit_inclination = i.to_radians(); }
+        if let Some(o) = update.longitude_of_ascending_node { body.longitude_of_ascending_node = o.to_radians(); }
+        if let Some(w) = update.argument_of_periapsis { body.argument_of_periapsis = w.to_radians(); }
 
+        self.regenerate_orbit_path(index);
 
-        
-        let rel_cam_x = self.camera_distance * self.camera_rotation.0.cos() * self.camera_rotation.1.sin();
-        let rel_cam_y = self.camera_distance * self.camera_rotation.0.sin();
-        let rel_cam_z = self.camera_distance * self.camera_rotation.0.cos() * self.camera_rotation.1.cos();
+        if self.focused_body_index == Some(index) {
+            self.update_apsis_info(index);
+            self.rebuild_focused_orbit();
+        }
+        Ok(())
+    }
+
+    // Orbit polylines are derived from the elements, call this whenever they change
+    pub fn regenerate_orbit_path(&mut self, index: usize) {
+        if let Some(body) = self.bodies.get_mut(index) {
+            body.orbit_path = build_orbit_path(body);
+            body.path_drift_years = body.drift_years;
+        }
+    }
+
+    pub fn set_validity_window(&mut self, years: f32) {
+        self.validity_years = years.abs();
+    }
+
+    pub fn elements_stale(&self) -> bool {
+        self.elements_stale
+    }
+
+    // Moves the drifting elements to the current date and flags dates the catalog can't be trusted for
+    fn update_epoch(&mut self) {
+        let years = ((self.current_time - J2000_MS) / MS_PER_JULIAN_YEAR) as f32;
+        let mut resampled_focus = false;
+        for i in 0..self.bodies.len() {
+            let body = &mut self.bodies[i];
+            if body.periapsis_rate == 0.0 && body.node_rate == 0.0 {
+                continue;
+            }
+            body.drift_years = years;
+            let turned = (body.drift_years - body.path_drift_years).abs() * body.periapsis_rate.abs().max(body.node_rate.abs());
+            if turned > PATH_DRIFT_TOLERANCE {
+                self.regenerate_orbit_path(i);
+                resampled_focus |= self.focused_body_index == Some(i);
+            }
+        }
+        if resampled_focus {
+            self.rebuild_focused_orbit();
+        }
+
+        let stale = years.abs() > self.validity_years;
+        if stale != self.elements_stale {
+            self.elements_stale = stale;
+            events::emit("elements_validity", serde_json::json!({ "stale": stale, "years_from_j2000": years }));
+            let document = web_sys::window().unwrap().document().unwrap();
+            if let Some(banner) = document.get_element_by_id("solar-epoch-warning") {
+                banner.set_attribute("style", if stale { "display: block;" } else { "display: none;" }).ok();
+            }
+            if let Some(index) = self.focused_body_index {
+                self.update_apsis_info(index);
+            }
+        }
+    }
+
+    // Out of the validity window, readouts drop to a rough figure instead of claiming precision
+    fn format_measure(&self, value: f32, decimals: usize, unit: &str) -> String {
+        if self.elements_stale {
+            format!("~{:.*} {}", decimals.min(1), value, unit)
+        } else {
+            format!("{:.*} {}", decimals, value, unit)
+        }
+    }
+
+    fn update_apsis_info(&self, index: usize) {
+        let body = &self.bodies[index];
+        let document = web_sys::window().unwrap().document().unwrap();
+        let (periapsis, apoapsis) = if body.orbit_radius > 0.0 {
+            let a_au = body.orbit_radius / UNITS_PER_AU;
+            (self.format_measure(a_au * (1.0 - body.eccentricity), 4, "AU"), self.format_measure(a_au * (1.0 + body.eccentricity), 4, "AU"))
+        } else {
+            ("-".to_string(), "-".to_string())
+        };
+        if let Some(el) = document.get_element_by_id("info-periapsis") { el.set_text_content(Some(&periapsis)); }
+        if let Some(el) = document.get_element_by_id("info-apoapsis") { el.set_text_content(Some(&apoapsis)); }
+    }
+
+    pub fn set_stereo_mode(&mut self, mode: &str) -> Result<(), String> {
+        self.stereo_mode = match mode {
+            "off" => StereoMode::Off,
+            "anaglyph" => StereoMode::Anaglyph,
+            _ => return Err(format!("Unknown stereo mode: {}", mode)),
+        };
+        Ok(())
+    }
+
+    pub fn set_eye_separation(&mut self, fraction: f32) {
+        // clamp passes NaN straight through, and it would be saved to settings
+        if !fraction.is_finite() {
+            return;
+        }
+        self.eye_separation = fraction.clamp(0.0, 0.2);
+        let separation = self.eye_separation;
+        Settings::update(|s| s.solar_eye_separation = separation);
+    }
+
+    pub fn set_trail_min_pixels(&mut self, pixels: f32) {
+        self.trail_min_pixels = pixels.max(0.0);
+    }
 
+    // Trail level of detail: moons only around the focused parent, everything else
+    // once its orbit is big enough on screen to read as more than noise
+    fn trail_visible(&self, index: usize, positions: &[Vector3<f32>], scene: &SceneView) -> bool {
+        let body = &self.bodies[index];
+        let parent = match body.parent {
+            Some(parent) => parent,
+            None => return false,
+        };
+        let is_moon = self.bodies[parent].parent.is_some();
+        if is_moon && self.focused_body_index != Some(parent) {
+            return false;
+        }
+        match project_sphere(positions[parent] - scene.target, body.orbit_radius, &scene.projection, &scene.view, scene.width, scene.height) {
+            Some((_, _, radius_px)) => radius_px * 2.0 >= self.trail_min_pixels,
+            // Orbit center is behind the camera, the orbit itself may still surround it
+            None => true,
+        }
+    }
+
+    fn rebuild_focused_orbit(&mut self) {
+        self.focused_orbit = self.focused_body_index.and_then(|index| {
+            let body = self.bodies.get(index)?;
+            let is_small_body = body.name.starts_with("Asteroid") || body.name.starts_with("Kuiper") || body.name.starts_with("Oort");
+            if body.orbit_radius <= 0.0 || body.parent.is_none() || is_small_body {
+                return None;
+            }
+
+            // Close the loop for LINE_STRIP
+            let mut path = body.orbit_path.clone();
+            path.extend_from_slice(&body.orbit_path[0..3]);
+
+            Some(FocusedOrbit {
+                index,
+                path,
+                periapsis: position_at_true_anomaly(body, 0.0),
+                apoapsis: position_at_true_anomaly(body, std::f32::consts::PI),
+            })
+        });
+
+        if self.focused_orbit.is_none() {
+            if let Some((peri_label, apo_label)) = &self.apsis_labels {
+                peri_label.style().set_property("display", "none").ok();
+                apo_label.style().set_property("display", "none").ok();
+            }
+        }
+    }
+
+    fn render_focused_orbit(&self, orbit: &FocusedOrbit, positions: &[Vector3<f32>], target: Vector3<f32>, camera_pos: Vector3<f32>, projection: &Matrix4<f32>, view: &Matrix4<f32>) {
+        let body = &self.bodies[orbit.index];
+        let parent_pos = body.parent.map(|p| positions[p]).unwrap_or_else(Vector3::zeros);
+        let offset = parent_pos - target;
+
+        let (r, g, b) = body.color;
+        let bright = ((r * 1.5 + 0.2).min(1.0), (g * 1.5 + 0.2).min(1.0), (b * 1.5 + 0.2).min(1.0));
+
+        // WebGL line width is stuck at 1px on most platforms, so thicken by redrawing
+        // with small offsets proportional to the camera distance
+        let nudge = camera_pos.norm() * 0.0015;
+        let nudges = [(0.0, 0.0), (nudge, 0.0), (-nudge, 0.0), (0.0, nudge), (0.0, -nudge)];
+        for (nx, ny) in nudges {
+            let shifted: Vec<f32> = orbit.path.chunks(3).flat_map(|p| {
+                [p[0] + offset.x + nx, p[1] + offset.y + ny, p[2] + offset.z]
+            }).collect();
+            self.renderer.draw_lines(&shifted, bright.0, bright.1, bright.2, projection, view);
+        }
+
+        let markers = [(orbit.periapsis, (1.0, 0.45, 0.2)), (orbit.apoapsis, (0.3, 0.6, 1.0))];
+        for (apsis, color) in markers.iter() {
+            let pos = apsis + offset;
+            let size = (camera_pos - pos).norm() * 0.004;
+            self.renderer.draw_mesh(
+                &self.sphere_mesh,
+                pos.x, pos.y, pos.z,
+                size, size, size,
+                0.0, 0.0, 0.0,
+                projection,
+                view,
+                None,
+                None,
+                Some(*color),
+                false,
+                None,
+                false,
+                false,
+                false,
+                None,
+                None
+            );
+        }
+    }
+
+    fn update_apsis_labels(&self, orbit: &FocusedOrbit, positions: &[Vector3<f32>], scene: &SceneView) {
+        let body = &self.bodies[orbit.index];
+        let offset = body.parent.map(|p| positions[p]).unwrap_or_else(Vector3::zeros) - scene.target;
+        for (k, apsis) in [orbit.periapsis, orbit.apoapsis].iter().enumerate() {
+            let pos = apsis + offset;
+            if let Some(labels) = &self.apsis_labels {
+                let label = if k == 0 { &labels.0 } else { &labels.1 };
+                let clip = scene.projection * scene.view * Vector4::new(pos.x, pos.y, pos.z, 1.0);
+                let style = label.style();
+                if clip.w > 0.0 && clip.x.abs() <= clip.w && clip.y.abs() <= clip.w {
+                    let screen_x = (clip.x / clip.w + 1.0) * scene.width as f32 / 2.0;
+                    let screen_y = (1.0 - clip.y / clip.w) * scene.height as f32 / 2.0;
+                    style.set_property("display", "block").ok();
+                    style.set_property("left", &format!("{}px", screen_x)).ok();
+                    style.set_property("top", &format!("{}px", screen_y - 20.0)).ok();
+                } else {
+                    style.set_property("display", "none").ok();
+                }
+            }
+        }
+    }
+
+    pub fn compare_bodies(&mut self, index_a: usize, index_b: usize) -> Option<String> {
+        if index_a >= self.bodies.len() || index_b >= self.bodies.len() {
+            return None;
+        }
+        self.comparison = Some(BodyComparison { index_a, index_b, rotation: 0.0 });
+
+        let a = &self.bodies[index_a];
+        let b = &self.bodies[index_b];
+        let ratio = |x: f64, y: f64| if y != 0.0 { Some(x / y) } else { None };
+
+        let radius_a = body_radius_km(a);
+        let radius_b = body_radius_km(b);
+        let mass_a = parse_mass_kg(&a.mass);
+        let mass_b = parse_mass_kg(&b.mass);
+        let gravity_a = mass_a.map(|m| surface_gravity(m, radius_a));
+        let gravity_b = mass_b.map(|m| surface_gravity(m, radius_b));
+        let period_a = orbital_period_days(a);
+        let period_b = orbital_period_days(b);
+
+        let report = serde_json::json!({
+            "a": { "name": a.name, "radius_km": radius_a, "mass_kg": mass_a, "temperature_k": a.temperature, "orbital_period_days": period_a, "surface_gravity": gravity_a },
+            "b": { "name": b.name, "radius_km": radius_b, "mass_kg": mass_b, "temperature_k": b.temperature, "orbital_period_days": period_b, "surface_gravity": gravity_b },
+            "ratios": {
+                "radius": ratio(radius_a, radius_b),
+                "mass": mass_a.zip(mass_b).and_then(|(ma, mb)| ratio(ma, mb)),
+                "temperature": ratio(a.temperature as f64, b.temperature as f64),
+                "orbital_period": period_a.zip(period_b).and_then(|(pa, pb)| ratio(pa, pb)),
+                "surface_gravity": gravity_a.zip(gravity_b).and_then(|(ga, gb)| ratio(ga, gb)),
+            }
+        });
+        Some(report.to_string())
+    }
+
+    pub fn exit_comparison(&mut self) {
+        self.comparison = None;
+    }
+
+    // Bottom-right corner of the screen, in GL window coordinates (origin bottom-left)
+    fn comparison_rect(width: i32, height: i32) -> (i32, i32, i32, i32) {
+        let w = (width as f32 * 0.4) as i32;
+        let h = (height as f32 * 0.4) as i32;
+        let margin = 20;
+        (width - w - margin, margin, w, h)
+    }
+
+    fn is_in_comparison_overlay(&self, x: i32, y: i32) -> bool {
+        if self.comparison.is_none() {
+            return false;
+        }
+        let window = web_sys::window().unwrap();
+        let width = window.inner_width().unwrap().as_f64().unwrap() as i32;
+        let height = window.inner_height().unwrap().as_f64().unwrap() as i32;
+        let (rx, ry, rw, rh) = Self::comparison_rect(width, height);
+        // Mouse coordinates grow downwards
+        let gl_y = height - y;
+        x >= rx && x <= rx + rw && gl_y >= ry && gl_y <= ry + rh
+    }
+
+    fn render_comparison(&self, comparison: &BodyComparison, width: i32, height: i32) {
+        let (rx, ry, rw, rh) = Self::comparison_rect(width, height);
+        // The rect is in CSS pixels like the mouse, the viewport wants backing pixels
+        let (buffer_width, buffer_height) = self.renderer.drawing_buffer_size();
+        let scale = buffer_width as f32 / width.max(1) as f32;
+        let to_buffer = |v: i32| (v as f32 * scale).round() as i32;
+        self.renderer.set_sub_viewport(to_buffer(rx), to_buffer(ry), to_buffer(rw), to_buffer(rh));
+        self.renderer.clear(0.02, 0.02, 0.05);
+        self.renderer.enable_depth_test();
+
+        let a = &self.bodies[comparison.index_a];
+        let b = &self.bodies[comparison.index_b];
+
+        // Shared kilometre scale: the larger body is 1 unit in radius
+        let radius_a = body_radius_km(a) as f32;
+        let radius_b = body_radius_km(b) as f32;
+        let largest = radius_a.max(radius_b).max(f32::EPSILON);
+        let ra = radius_a / largest;
+        let rb = radius_b / largest;
+
+        let gap = 0.3;
+        let total_width = 2.0 * ra + 2.0 * rb + gap;
+        let center_a = -total_width / 2.0 + ra;
+        let center_b = total_width / 2.0 - rb;
+
+        let fov = 45.0f32.to_radians();
+        let aspect = rw as f32 / rh.max(1) as f32;
+        let half_fov_tan = (fov / 2.0).tan();
+        let distance = (total_width / 2.0 * 1.15 / (half_fov_tan * aspect)).max(1.3 / half_fov_tan);
+
+        let projection = Matrix4::new_perspective(aspect, fov, 0.01, distance * 10.0);
         let view = Matrix4::look_at_rh(
-            &Point3::new(rel_cam_x, rel_cam_y, rel_cam_z),
+            &Point3::new(0.0, 0.0, distance),
             &Point3::new(0.0, 0.0, 0.0),
             &Vector3::y(),
         );
+        self.renderer.set_light_position(-distance, distance, distance);
+        self.renderer.set_light_color(1.0, 1.0, 1.0);
+        self.renderer.set_second_light(SecondLight::off());
+
+        for (body, center, radius) in [(a, center_a, ra), (b, center_b, rb)] {
+            let emits_light = body.name == "Sun" || body.name == "Black Hole" || body.name.starts_with("Sirius");
+            self.renderer.draw_mesh(
+                &body.mesh,
+                center, 0.0, 0.0,
+                radius, radius, radius,
+                body.axial_tilt, comparison.rotation, 0.0,
+                &projection,
+                &view,
+                body.texture.as_ref(),
+                None,
+                if body.texture.is_none() { Some(body.color) } else { None },
+                false,
+                None,
+                !emits_light,
+                false,
+                false,
+                None,
+                None
+            );
+        }
+
+        self.renderer.reset_viewport(buffer_width, buffer_height);
+    }
+
+    fn format_temperature(&self, kelvin: f32) -> String {
+        if self.use_celsius {
+            format!("{:.0} °C", kelvin - 273.15)
+        } else {
+            format!("{:.0} K", kelvin)
+        }
+    }
+
+    // Companion star as (body index, luminosity in watts) for binary systems
+    fn companion_star(&self) -> Option<(usize, f64)> {
+        match self.system_type {
+            SystemType::Sirius => {
+                let index = self.bodies.iter().position(|body| body.name == "Sirius B")?;
+                Some((index, SIRIUS_B_LUMINOSITY_SOLAR * SOLAR_LUMINOSITY_W))
+            }
+            _ => None,
+        }
+    }
+
+    fn set_star_lights(&self, positions: &[Vector3<f32>], target: Vector3<f32>) {
+        let companion = self.companion_star()
+            .map(|(index, luminosity)| (positions[index] - target, self.bodies[index].color, luminosity));
+        let ((r, g, b), second) = star_lights(self.bodies[0].color, companion, self.central_luminosity);
+        self.renderer.set_light_color(r, g, b);
+        self.renderer.set_second_light(second);
+    }
+
+    // Luminosity in units of the Sun's, e.g. an accretion disk around the black hole
+    pub fn set_central_luminosity(&mut self, solar_units: f64) {
+        self.central_luminosity = solar_units.max(0.0) * SOLAR_LUMINOSITY_W;
+    }
+
+    // Normal solar mode shows catalog values unless the live model is switched on
+    pub fn set_live_temperatures(&mut self, enabled: bool) {
+        self.live_temperatures = enabled || self.system_type == SystemType::BlackHole;
+        if !self.live_temperatures {
+            for body in &mut self.bodies {
+                body.temperature = body.catalog_temperature;
+                body.is_frozen = false;
+            }
+        }
+    }
+
+    fn world_positions(&self) -> Vec<Vector3<f32>> {
+        self.world_positions_after(0.0)
+    }
+
+    // Positions once the orbits have run on for the given simulated seconds, leaving the bodies alone
+    fn world_positions_after(&self, seconds: f32) -> Vec<Vector3<f32>> {
+        positions_after(&self.bodies, seconds)
+    }
+
+    // Equilibrium temperature from the central object's light at the current orbital distance
+    fn update_temperatures(&mut self, positions: &[Vector3<f32>]) {
+        for i in 0..self.bodies.len() {
+            let mut root = i;
+            while let Some(parent) = self.bodies[root].parent {
+                root = parent;
+            }
+
+            let body = &mut self.bodies[i];
+            // Stars (Sirius B) shine on their own
+            if body.parent.is_none() || body.catalog_temperature >= 2000.0 {
+                continue;
+            }
+
+            let distance_m = (positions[i] - positions[root]).norm() as f64 / UNITS_PER_AU as f64 * METERS_PER_AU;
+            body.temperature = equilibrium_temperature(self.central_luminosity, distance_m) as f32;
+            body.is_frozen = body.temperature < FROZEN_THRESHOLD_K;
+        }
+    }
+
+    pub fn toggle_temperature_unit(&mut self) {
+        self.use_celsius = !self.use_celsius;
+        if let Some(index) = self.focused_body_index {
+            self.select_body(index);
+        }
+    }
+
+    pub fn set_date_from_timestamp(&mut self, timestamp: f64) {
+        self.current_time = timestamp;
+        let j2000_ms = 946728000000.0;
+        let days_since_j2000 = (timestamp - j2000_ms) / (1000.0 * 60.0 * 60.0 * 24.0);
+        
+        for body in &mut self.bodies {
+            body.orbit_angle = orbit_angle_at(body.mean_anomaly_at_epoch, body.orbit_speed, days_since_j2000);
+
+            if body.rotation_period != 0.0 {
+                let total_seconds = days_since_j2000 * 24.0 * 3600.0;
+                body.current_rotation = (rotation_speed(body.rotation_period) * total_seconds as f32) % (2.0 * std::f32::consts::PI);
+            }
+        }
+    }
+
+    pub fn set_time_scale(&mut self, scale: f32) {
+        self.cancel_tour();
+        self.scale_ramp = None;
+        self.time_scale = scale;
+        self.manual_time = false;
+    }
+
+    // Eases from the current scale to target, a zero duration switches at once
+    pub fn set_time_scale_smooth(&mut self, target: f32, duration_seconds: f64) {
+        if !duration_seconds.is_finite() || duration_seconds <= 0.0 {
+            self.set_time_scale(target);
+            return;
+        }
+        self.cancel_tour();
+        self.manual_time = false;
+        self.scale_ramp = Some(ScaleRamp::new(self.time_scale, target, duration_seconds));
+    }
+
+    // A ramp under way is saved with the scale it's heading for
+    pub fn save_time_bookmark(&mut self, slot: usize) -> Result<(), String> {
+        solar_time::check_slot(slot)?;
+        let scale = self.scale_ramp.as_ref().map_or(self.time_scale, |ramp| ramp.target());
+        self.time_bookmarks[slot] = Some(TimeBookmark { scale, time: self.current_time });
+        solar_time::save_bookmarks(&self.time_bookmarks);
+        Ok(())
+    }
+
+    pub fn restore_time_bookmark(&mut self, slot: usize) -> Result<(), String> {
+        solar_time::check_slot(slot)?;
+        let bookmark = self.time_bookmarks[slot].ok_or_else(|| format!("Bookmark slot {} is empty", slot))?;
+        self.set_date_from_timestamp(bookmark.time);
+        self.update_epoch();
+        self.set_time_scale_smooth(bookmark.scale, BOOKMARK_RAMP_SECONDS);
+        Ok(())
+    }
+
+    pub fn time_bookmarks_json(&self) -> String {
+        serde_json::to_string(&self.time_bookmarks).unwrap_or_default()
+    }
+
+    // Jumps exactly `seconds` ahead through the same closed-form math as set_date_from_timestamp,
+    // so repeated steps never accumulate frame-rate dependent drift
+    pub fn step_simulation(&mut self, seconds: f64) {
+        self.manual_time = true;
+        self.set_date_from_timestamp(self.current_time + seconds * 1000.0);
+        self.update_epoch();
+    }
+
+    // Scene-space position plus the same point in AU, relative to the system's center
+    pub fn body_position_json(&self, index: usize) -> Option<String> {
+        if index >= self.bodies.len() {
+            return None;
+        }
+        let pos = self.world_positions()[index];
+        let au = pos / UNITS_PER_AU;
+        Some(serde_json::json!({
+            "name": self.bodies[index].name,
+            "time": self.current_time,
+            "scene": [pos.x, pos.y, pos.z],
+            "au": [au.x, au.y, au.z],
+        }).to_string())
+    }
+
+    // Jumps to each reference date, measures every planet against the embedded table and
+    // comes back to the date it started on. The report goes to the console as well.
+    pub fn run_accuracy_check(&mut self) -> Result<String, String> {
+        let sun = self.find_body("Sun").ok_or_else(|| "The accuracy check needs the Solar System".to_string())?;
+        let start_time = self.current_time;
+        let report = solar_ephemeris::run(|time, name| {
+            if self.current_time != time {
+                self.set_date_from_timestamp(time);
+                self.update_epoch();
+            }
+            let index = self.find_body(name)?;
+            let positions = self.world_positions();
+            // Scene y is the ecliptic's north, the table has it as z
+            let offset = (positions[index] - positions[sun]) / UNITS_PER_AU;
+            Some([offset.x as f64, offset.z as f64, offset.y as f64])
+        });
+        self.set_date_from_timestamp(start_time);
+        self.update_epoch();
+        web_sys::console::log_1(&format!("Ephemeris check {}\n{}", if report.passed { "passed" } else { "FAILED" }, report.summary()).into());
+        serde_json::to_string(&report).map_err(|e| e.to_string())
+    }
+
+    pub fn snapshot(&self) -> SolarSnapshot {
+        SolarSnapshot {
+            current_time: self.current_time,
+            time_scale: self.time_scale,
+            focused_body: self.focused_body_index.and_then(|i| self.bodies.get(i)).map(|body| body.name.clone()),
+            camera_distance: self.camera.distance,
+        }
+    }
+
+    // After a pause the next update starts from now instead of catching up
+    pub fn resume(&mut self) {
+        self.last_time = Date::now();
+    }
 
+    pub fn restore_context(&mut self) -> Result<(), wasm_bindgen::JsValue> {
+        self.renderer.reinit()?;
+        let renderer = &self.renderer;
+        for body in &mut self.bodies {
+            let textures = [&mut body.texture, &mut body.night_texture, &mut body.cloud_texture, &mut body.ring_texture];
+            for texture in textures.into_iter().flatten() {
+                renderer.restore_texture(texture);
+            }
+        }
+        for texture in [&mut self.background_texture, &mut self.sun_texture].into_iter().flatten() {
+            renderer.restore_texture(texture);
+        }
+        self.galaxy_texture = renderer.create_texture_from_rgba(GALAXY_TEXTURE_SIZE as i32, GALAXY_TEXTURE_SIZE as i32, &galaxy_pixels(GALAXY_TEXTURE_SIZE)).ok();
+        Ok(())
+    }
+
+    pub fn update(&mut self) {
+        let now = Date::now();
+        let dt = (now - self.last_time) / 1000.0;
+        self.last_time = now;
+        self.camera.update();
+        if let Some(surface) = self.surface_view.as_mut() {
+            surface.look.update();
+        }
+        
+        // Prevent huge time jumps if dt is too large (e.g. tab inactive)
+        let safe_dt = if dt > 0.1 { 0.1 } else { dt };
+        self.focus_blend = (self.focus_blend + safe_dt as f32 / FOCUS_TRANSITION_SECONDS).min(1.0);
+        self.advance_tour(safe_dt as f32);
+        
+        // A ramp gives the frame's average scale, so the clock moves by its exact integral
+        let base_scale = match self.scale_ramp.as_mut() {
+            Some(ramp) if safe_dt > 0.0 => {
+                let average = (ramp.advance(safe_dt) / safe_dt) as f32;
+                self.time_scale = ramp.current();
+                if ramp.finished() {
+                    self.time_scale = ramp.target();
+                    self.scale_ramp = None;
+                }
+                average
+            }
+            _ => self.time_scale,
+        };
 
+        // Scripted stepping owns the clock, everything below then advances by zero
+        let time_scale = if self.manual_time {
+            0.0
+        } else if self.slowmo.enabled {
+            let positions = self.world_positions();
+            let scale = base_scale * self.slowmo_factor(&positions);
+            scale * self.slowmo_landing(&positions, safe_dt as f32 * scale)
+        } else {
+            base_scale
+        };
+        self.current_time += safe_dt * 1000.0 * time_scale as f64;
+        self.update_epoch();
+        
+        let window = web_sys::window().unwrap();
+        let document = window.document().unwrap();
+            if let Some(element) = document.get_element_by_id("solar-date") {
+                element.set_text_content(Some(&format_date(self.current_time)));
+            }
 
+        // Update speed info if a body is selected
+        if let Some(idx) = self.focused_body_index {
+            if idx < self.bodies.len() {
+                let body = &self.bodies[idx];
+                if let Some(el) = document.get_element_by_id("info-speed") {
+                    let speed_kmh = if body.orbit_radius > 0.0 {
+                        // Calculate current distance r
+                        let m = body.orbit_angle;
+                        let e = body.eccentricity;
+                        let big_e = m + e * m.sin();
+                        let x_orb = body.orbit_radius * (big_e.cos() - e);
+                        let z_orb = body.orbit_radius * (1.0 - e*e).sqrt() * big_e.sin();
+                        let r = (x_orb*x_orb + z_orb*z_orb).sqrt();
+
+                        // Vis-viva equation: v = sqrt(mu * (2/r - 1/a))
+                        // mu = n^2 * a^3
+                        // v = n * a * sqrt(2a/r - 1)
+                        let n = body.orbit_speed.abs();
+                        let a = body.orbit_radius;
+                        
+                        if r > 0.0 {
+                            let v_sim = n * a * ((2.0 * a / r) - 1.0).abs().sqrt();
+                            // Convert to km/h
+                            // Scale: 1 unit = 6371.0 / 0.0042 km
+                            let scale = 6371.0 / 0.0042;
+                            v_sim * scale * 3600.0
+                        } else {
+                            0.0
+                        }
+                    } else {
+                        0.0
+                    };
+                    el.set_text_content(Some(&self.format_measure(speed_kmh, 0, "km/h")));
+                }
+            }
+        }
+
+        if let Some(comparison) = &mut self.comparison {
+            comparison.rotation += safe_dt as f32 * 0.3;
+            comparison.rotation %= 2.0 * std::f32::consts::PI;
+        }
+
+        let mut completed_orbits = vec![0u32; self.bodies.len()];
+        let mut positions = vec![Vector3::new(0.0, 0.0, 0.0); self.bodies.len()];        for i in 0..self.bodies.len() {
+
+            let body = &mut self.bodies[i];
+            if body.parent.is_some() {
+                let delta = body.orbit_speed * safe_dt as f32 * time_scale;
+                completed_orbits[i] = sonification::completed_cycles(body.orbit_angle, delta);
+                body.orbit_angle += delta;
+                body.orbit_angle %= 2.0 * std::f32::consts::PI;
+            }
+            
+
+            if body.rotation_period != 0.0 {
+
+
+
+                let spin = rotation_speed(body.rotation_period);
+                body.current_rotation += spin * safe_dt as f32 * time_scale;
+                body.current_rotation %= 2.0 * std::f32::consts::PI;
+
+                if body.cloud_texture.is_some() {
+
+                    body.cloud_rotation += spin * 0.2 * safe_dt as f32 * time_scale;
+                    body.cloud_rotation %= 2.0 * std::f32::consts::PI;
+                }
+            }
+
+            let mut pos = orbit_position(body, body.orbit_angle);
+            
+            if let Some(parent_idx) = body.parent {
+                pos += positions[parent_idx];
+            }
+            
+            positions[i] = pos;
+        }
+
+        if self.live_temperatures {
+            self.update_temperatures(&positions);
+            if let Some(idx) = self.focused_body_index {
+                if let Some(el) = document.get_element_by_id("info-temp") {
+                    el.set_text_content(Some(&self.format_temperature(self.bodies[idx].temperature)));
+                }
+            }
+        }
+
+        self.update_solar_time(&positions);
+        self.update_sky_position(&positions);
+        if let Some(sonification) = &self.sonification {
+            sonification.pulse(&completed_orbits);
+        }
+    }
+
+    // Camera for the current frame and the horizon, which is the body stood on and its local up
+    fn view_camera(&self, positions: &[Vector3<f32>], width: i32, height: i32) -> (SceneView, Option<(usize, Vector3<f32>)>) {
+        match &self.surface_view {
+            Some(surface) => {
+                let (scene, up) = self.surface_camera(surface, positions, width, height);
+                (scene, Some((surface.body, up)))
+            }
+            None => {
+                let focus_position = |index: Option<usize>| index.map(|i| positions[i]).unwrap_or_else(Vector3::zeros);
+                let blend = self.focus_blend * self.focus_blend * (3.0 - 2.0 * self.focus_blend);
+                let target = focus_position(self.focus_from).lerp(&focus_position(self.focused_body_index), blend);
+
+                let projection = Matrix4::new_perspective(width as f32 / height as f32, 45.0 * std::f32::consts::PI / 180.0, 0.001, 200000000.0); // Increased far plane significantly
+
+                let camera = &self.camera;
+                let rel_cam_x = camera.distance * camera.pitch.cos() * camera.yaw.sin();
+                let rel_cam_y = camera.distance * camera.pitch.sin();
+                let rel_cam_z = camera.distance * camera.pitch.cos() * camera.yaw.cos();
+
+                let view = Matrix4::look_at_rh(
+                    &Point3::new(rel_cam_x, rel_cam_y, rel_cam_z),
+                    &Point3::new(0.0, 0.0, 0.0),
+                    &Vector3::y(),
+                );
+                (SceneView { target, eye: Vector3::new(rel_cam_x, rel_cam_y, rel_cam_z), projection, view, width, height }, None)
+            }
+        }
+    }
+
+    // The last rendered view as a star chart: orbits, bodies with their names and the date.
+    // Small-body populations are left out, like their labels on screen. None before the
+    // first frame, which is where the viewport size comes from.
+    pub fn export_svg(&self) -> Option<String> {
+        let (width, height) = self.pick_frame.borrow().as_ref().map(|frame| (frame.width, frame.height))?;
+        let positions = self.world_positions();
+        let (scene, horizon) = self.view_camera(&positions, width as i32, height as i32);
+        let caption = format!("{}  time scale {}x", format_date(self.current_time), self.time_scale);
+        Some(star_chart(&self.bodies, &positions, &scene, horizon, &caption))
+    }
+
+    pub fn renderer(&self) -> &Renderer {
+        &self.renderer
+    }
+
+    // width and height are the canvas's CSS size, which labels and picking are laid out in
+    pub fn render(&self, width: i32, height: i32) {
+        self.renderer.clear(0.0, 0.0, 0.0);
+        let (buffer_width, buffer_height) = self.renderer.drawing_buffer_size();
+        self.renderer.resize(buffer_width, buffer_height);
+        self.renderer.enable_depth_test();
+
+        let positions = self.world_positions();
+        let context_blend = self.context_blend();
+        let (scene, horizon) = self.view_camera(&positions, width, height);
+        let (target, eye, view) = (scene.target, scene.eye, scene.view);
 
         let rel_light_pos = Vector3::new(0.0, 0.0, 0.0) - target;
         self.renderer.set_light_position(rel_light_pos.x, rel_light_pos.y, rel_light_pos.z);
+        self.set_star_lights(&positions, target);
+
+        // Stereo pivots around the focused body, which has no meaning standing on it
+        match self.stereo_mode {
+            StereoMode::Anaglyph if horizon.is_none() => {
+                // Toed-in eyes converge on the focused body, so it sits at screen depth
+                let right = Vector3::new(view[(0, 0)], view[(0, 1)], view[(0, 2)]);
+                let half_separation = self.camera.distance * self.eye_separation / 2.0;
+                let gl = &self.renderer.gl;
+                for (side, red, cyan) in [(-1.0, true, false), (1.0, false, true)] {
+                    let eye_pos = eye + right * side * half_separation;
+                    let eye_view = Matrix4::look_at_rh(&Point3::from(eye_pos), &Point3::origin(), &Vector3::y());
+                    gl.color_mask(red, cyan, cyan, true);
+                    gl.clear(web_sys::WebGlRenderingContext::DEPTH_BUFFER_BIT);
+                    self.render_scene(&positions, &SceneView { eye: eye_pos, view: eye_view, ..scene });
+                }
+                gl.color_mask(true, true, true, true);
+            }
+            _ => self.render_scene(&positions, &scene),
+        }
 
+        self.update_labels(&positions, &scene, context_blend, horizon);
+
+        if context_blend > 0.0 {
+            self.render_context(context_blend, target, width, height);
+        } else {
+            self.hide_star_labels();
+        }
+
+        if let Some(comparison) = &self.comparison {
+            self.render_comparison(comparison, width, height);
+        }
+    }
+
+    // Everything drawn in the 3D scene, once per eye in stereo; labels are left to update_labels
+    fn render_scene(&self, positions: &[Vector3<f32>], scene: &SceneView) {
+        let (rel_cam_x, rel_cam_y, rel_cam_z) = (scene.eye.x, scene.eye.y, scene.eye.z);
         self.renderer.gl.disable(web_sys::WebGlRenderingContext::DEPTH_TEST);
         
 
@@ -921,8 +2189,8 @@ impl SolarSystem {
                 rel_cam_x, rel_cam_y, rel_cam_z,
                 5000.0, 5000.0, 5000.0,
                 0.0, 0.0, 0.0,
-                &projection,
-                &view,
+                &scene.projection,
+                &scene.view,
                 self.background_texture.as_ref(),
                 None,
                 None,
@@ -940,54 +2208,34 @@ impl SolarSystem {
 
         let mut instance_data = Vec::with_capacity(self.bodies.len() * 7);
         let mut asteroid_count = 0;
-        
-        struct BodyScreenData {
-            index: usize,
-            screen_x: f32,
-            screen_y: f32,
-            label_y: f32,
-            radius_px: f32,
-            depth: f32,
-        }
-        let mut screen_data = Vec::new();
 
         for (i, body) in self.bodies.iter().enumerate() {
             let abs_pos = positions[i];
-            let pos = abs_pos - target;
+            let pos = abs_pos - scene.target;
             
-            if !body.trail.is_empty() && !body.name.starts_with("Asteroid") && !body.name.starts_with("Kuiper") && !body.name.starts_with("Oort") {
+            let is_highlighted = self.focused_orbit.as_ref().map(|o| o.index == i).unwrap_or(false);
+            if !is_highlighted && !body.orbit_path.is_empty() && self.trail_visible(i, positions, scene) {
                 let parent_pos = if let Some(pidx) = body.parent {
                     positions[pidx]
                 } else {
                     Vector3::new(0.0, 0.0, 0.0)
                 };
+                let trail = trail_vertices(body, parent_pos - scene.target);
 
-                let relative_trail: Vec<f32> = body.trail.chunks(3).flat_map(|p| {
-                    vec![p[0] + parent_pos.x - target.x, p[1] + parent_pos.y - target.y, p[2] + parent_pos.z - target.z]
-                }).collect();
-
-                self.renderer.draw_lines(
-                    &relative_trail,
-                    body.color.0 * 0.5,
-                    body.color.1 * 0.5,
-                    body.color.2 * 0.5,
-                    &projection,
-                    &view
-                );
+                // Additive blending so the faded tail disappears into the background
+                self.renderer.gl.enable(web_sys::WebGlRenderingContext::BLEND);
+                self.renderer.gl.blend_func(web_sys::WebGlRenderingContext::ONE, web_sys::WebGlRenderingContext::ONE);
+                self.renderer.draw_colored_lines(&trail, &scene.projection, &scene.view);
+                self.renderer.gl.disable(web_sys::WebGlRenderingContext::BLEND);
             }
 
             let dx = rel_cam_x - pos.x;
             let dy = rel_cam_y - pos.y;
             let dz = rel_cam_z - pos.z;
-            let dist = (dx*dx + dy*dy + dz*dz).sqrt();
-            
-            let is_small_body = body.name.starts_with("Asteroid") || body.name.starts_with("Kuiper") || body.name.starts_with("Oort");
+            let dist = (dx*dx + dy*dy + dz*dz).sqrt();
             
-            if is_small_body {
-                let scale_factor = 0.0005;
-                let min_size = dist * scale_factor; 
-                let render_radius = if min_size > body.radius { min_size } else { body.radius };
-                
+            let (render_radius, use_texture) = body_render_radius(body, dist);
+            if is_small_body(body) {
                 instance_data.push(pos.x);
                 instance_data.push(pos.y);
                 instance_data.push(pos.z);
@@ -1000,15 +2248,6 @@ impl SolarSystem {
                 continue;
             }
 
-            let scale_factor = 0.002;
-            let min_size = dist * scale_factor; 
-            
-            let (render_radius, use_texture) = if min_size > body.radius {
-                (min_size, false)
-            } else {
-                (body.radius, true)
-            };
-            
             let texture_to_use = if use_texture {
                 body.texture.as_ref()
             } else {
@@ -1039,15 +2278,15 @@ impl SolarSystem {
             // If black hole, we want it to be visible despite its tiny physical radius.
             // We scale it up for rendering so the lensing effect is visible.
             // 3km is invisible. Let's make the visual effect roughly Sun-sized (0.5) or slightly smaller.
-            let final_render_radius = if is_black_hole { 0.3 } else { render_radius };
+            let final_render_radius = if is_black_hole { BLACK_HOLE_VISUAL_RADIUS } else { render_radius };
 
             self.renderer.draw_mesh(
                 mesh_to_use,
                 pos.x, pos.y, pos.z,
                 final_render_radius, final_render_radius, final_render_radius,
                 body.axial_tilt, body.current_rotation, 0.0,
-                &projection,
-                &view,
+                &scene.projection,
+                &scene.view,
                 texture_to_use,
                 night_texture_to_use,
                 color_override,
@@ -1075,8 +2314,8 @@ impl SolarSystem {
                         pos.x, pos.y, pos.z,
                         body.ring_radius, body.ring_radius, body.ring_radius,
                         body.axial_tilt + std::f32::consts::FRAC_PI_2, 0.0, 0.0,
-                        &projection,
-                        &view,
+                        &scene.projection,
+                        &scene.view,
                         Some(ring_tex),
                         None,
                         None,
@@ -1102,8 +2341,8 @@ impl SolarSystem {
                         pos.x, pos.y, pos.z,
                         render_radius * 1.02, render_radius * 1.02, render_radius * 1.02,
                         body.axial_tilt, body.current_rotation + body.cloud_rotation, 0.0,
-                        &projection,
-                        &view,
+                        &scene.projection,
+                        &scene.view,
                         Some(cloud_tex),
                         None,
                         None,
@@ -1120,27 +2359,64 @@ impl SolarSystem {
                 }
             }
             
-            if let Some(element) = &body.label_element {
-                let center_world = Vector4::new(pos.x, pos.y, pos.z, 1.0);
-                let view_pos = view * center_world;
+        }
 
-                let top_view = view_pos + Vector4::new(0.0, render_radius, 0.0, 0.0);
-                
-                let clip_center = projection * view_pos;
-                let clip_top = projection * top_view;
-                
-                if clip_center.w > 0.0 {
-                    let ndc_center_x = clip_center.x / clip_center.w;
-                    let ndc_center_y = clip_center.y / clip_center.w;
-                    let ndc_top_y = clip_top.y / clip_top.w;
-                    
-                    if ndc_center_x >= -1.0 && ndc_center_x <= 1.0 && ndc_center_y >= -1.0 && ndc_center_y <= 1.0 {
-                        let screen_x = (ndc_center_x + 1.0) * width as f32 / 2.0;
-                        let screen_cy = (1.0 - ndc_center_y) * height as f32 / 2.0;
-                        let screen_ty = (1.0 - ndc_top_y) * height as f32 / 2.0;
-                        
-                        let radius_px = (screen_cy - screen_ty).abs();
-                        let label_y = screen_cy - radius_px - 20.0;
+        if asteroid_count > 0 {
+             self.renderer.draw_instanced_mesh(
+                &self.asteroid_mesh,
+                &instance_data,
+                asteroid_count,
+                &scene.projection,
+                &scene.view,
+                &Vector3::new(0.0, 0.0, 0.0),
+                None
+            );
+        }
+
+        if let Some(orbit) = &self.focused_orbit {
+            let camera_pos = Vector3::new(rel_cam_x, rel_cam_y, rel_cam_z);
+            self.render_focused_orbit(orbit, positions, scene.target, camera_pos, &scene.projection, &scene.view);
+        }
+
+        if self.earth_markers {
+            let camera_pos = Vector3::new(rel_cam_x, rel_cam_y, rel_cam_z);
+            self.render_earth_markers(positions, scene.target, camera_pos, &scene.projection, &scene.view);
+        }
+    }
+
+    // Labels and picking follow the unshifted camera even in stereo. From a surface, horizon
+    // is the body stood on and the local up; it and whatever is below the horizon get no label.
+    fn update_labels(&self, positions: &[Vector3<f32>], scene: &SceneView, context_blend: f32, horizon: Option<(usize, Vector3<f32>)>) {
+        struct BodyScreenData {
+            index: usize,
+            screen_x: f32,
+            screen_y: f32,
+            label_y: f32,
+            radius_px: f32,
+            depth: f32,
+        }
+        let mut screen_data = Vec::new();
+        let mut pick_spheres = Vec::new();
+
+        for (i, body) in self.bodies.iter().enumerate() {
+            if is_small_body(body) {
+                continue;
+            }
+            let pos = positions[i] - scene.target;
+            let dist = (scene.eye - pos).norm();
+            let (render_radius, _) = body_render_radius(body, dist);
+            let pick_radius = if body.name == "Black Hole" { BLACK_HOLE_VISUAL_RADIUS } else { render_radius };
+            let below_horizon = horizon.is_some_and(|(ground, up)| i == ground || (pos - scene.eye).dot(&up) < 0.0);
+            if !below_horizon {
+                pick_spheres.push((i, pos, pick_radius));
+            }
+
+            if let Some(element) = &body.label_element {
+                if below_horizon {
+                    apply_label(element, &body.label_state, LabelState::HIDDEN);
+                } else if let Some((screen_x, screen_cy, radius_px)) = project_sphere(pos, render_radius, &scene.projection, &scene.view, scene.width, scene.height) {
+                    if screen_x >= 0.0 && screen_x <= scene.width as f32 && screen_cy >= 0.0 && screen_cy <= scene.height as f32 {
+                        let label_y = screen_cy - radius_px - LABEL_GAP_PX;
                         
                         // Store for second pass
                         screen_data.push(BodyScreenData {
@@ -1152,10 +2428,10 @@ impl SolarSystem {
                             depth: dist, // dist calculated earlier
                         });
                     } else {
-                        element.style().set_property("display", "none").unwrap();
+                        apply_label(element, &body.label_state, LabelState::HIDDEN);
                     }
                 } else {
-                    element.style().set_property("display", "none").unwrap();
+                    apply_label(element, &body.label_state, LabelState::HIDDEN);
                 }
             }
         }
@@ -1185,68 +2461,862 @@ impl SolarSystem {
                 }
             }
             
-            if let Some(element) = &self.bodies[data.index].label_element {
-                if is_occluded {
-                    element.style().set_property("display", "none").unwrap();
-                } else {
-                    let style = element.style();
-                    style.set_property("display", "block").unwrap();
-                    style.set_property("left", &format!("{}px", data.screen_x)).unwrap();
-                    style.set_property("top", &format!("{}px", data.label_y)).unwrap();
-                }
+            let body = &self.bodies[data.index];
+            if let Some(element) = &body.label_element {
+                let state = if is_occluded || context_blend > 0.5 { LabelState::HIDDEN } else { LabelState::shown(data.screen_x, data.label_y) };
+                apply_label(element, &body.label_state, state);
             }
         }
 
-        if asteroid_count > 0 {
-             self.renderer.draw_instanced_mesh(
-                &self.asteroid_mesh,
-                &instance_data,
-                asteroid_count,
-                &projection,
-                &view,
-                &Vector3::new(0.0, 0.0, 0.0),
-                None
-            );
+        *self.pick_frame.borrow_mut() = (scene.projection * scene.view).try_inverse().map(|inverse_view_projection| PickFrame {
+            inverse_view_projection,
+            width: scene.width as f32,
+            height: scene.height as f32,
+            spheres: pick_spheres,
+        });
+
+        if let Some(orbit) = &self.focused_orbit {
+            self.update_apsis_labels(orbit, positions, scene);
+        }
+    }
+
+    // 0 inside the Oort cloud, 1 once the local-neighbourhood view has fully taken over
+    fn context_blend(&self) -> f32 {
+        if self.system_type != SystemType::Solar || self.surface_view.is_some() {
+            return 0.0;
+        }
+        let t = ((self.camera.distance - CONTEXT_FADE_START) / (CONTEXT_FADE_END - CONTEXT_FADE_START)).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    }
+
+    fn hide_star_labels(&self) {
+        for (element, applied) in self.star_labels.iter().flatten() {
+            apply_label(element, applied, LabelState::HIDDEN);
+        }
+        self.star_screen.borrow_mut().clear();
+    }
+
+    fn render_context(&self, blend: f32, target: Vector3<f32>, width: i32, height: i32) {
+        let (w, h) = (width as f32, height as f32);
+
+        // Dim the planetary system under the context layer
+        self.renderer.begin_screen_overlay();
+        self.renderer.enable_blend();
+        self.renderer.set_alpha(blend);
+        self.renderer.draw_screen_rect(0.0, 0.0, w, h, (0.0, 0.0, 0.0), (w, h));
+        self.renderer.end_screen_overlay();
+        self.renderer.gl.clear(web_sys::WebGlRenderingContext::DEPTH_BUFFER_BIT);
+
+        // Light-year distances would be lost in the main projection's depth range, so this
+        // layer gets its own with the near plane pulled out in proportion to the camera distance
+        let camera = &self.camera;
+        let camera_pos = Vector3::new(
+            camera.distance * camera.pitch.cos() * camera.yaw.sin(),
+            camera.distance * camera.pitch.sin(),
+            camera.distance * camera.pitch.cos() * camera.yaw.cos(),
+        );
+        let farthest = NEARBY_STARS.iter().map(|s| nearby_star_position(s).norm()).fold(0.0, f32::max);
+        let fov = 45.0f32.to_radians();
+        let projection = Matrix4::new_perspective(w / h.max(1.0), fov, camera.distance * 0.01, (camera.distance + farthest) * 2.0);
+        let view = Matrix4::look_at_rh(&Point3::from(camera_pos), &Point3::origin(), &Vector3::y());
+        let pixel_size = |pos: Vector3<f32>| STAR_MARKER_PIXELS * 2.0 * (fov / 2.0).tan() * (camera_pos - pos).norm() / h.max(1.0);
+
+        self.renderer.gl.uniform1i(Some(&self.renderer.u_use_lighting_location), 0);
+        let sun = -target;
+        let size = pixel_size(sun);
+        self.renderer.draw_cube(sun.x, sun.y, sun.z, size, size, size, 1.0, 0.9, 0.5, &projection, &view);
+
+        let mut screen = self.star_screen.borrow_mut();
+        screen.clear();
+        for (i, star) in NEARBY_STARS.iter().enumerate() {
+            let pos = nearby_star_position(star) - target;
+            let size = pixel_size(pos);
+            self.renderer.draw_cube(pos.x, pos.y, pos.z, size, size, size, star.color.0, star.color.1, star.color.2, &projection, &view);
+
+            let on_screen = project_sphere(pos, 0.0, &projection, &view, width, height)
+                .filter(|(x, y, _)| *x >= 0.0 && *x <= w && *y >= 0.0 && *y <= h);
+            if let Some((x, y, _)) = on_screen {
+                screen.push((i, x, y));
+            }
+            if let Some((element, applied)) = &self.star_labels[i] {
+                let state = match on_screen {
+                    Some((x, y, _)) if blend > 0.5 => LabelState::shown(x, y - 20.0),
+                    _ => LabelState::HIDDEN,
+                };
+                apply_label(element, applied, state);
+            }
+        }
+
+        // Galaxy sprite in the corner with the Sun's place marked
+        if let Some(texture) = &self.galaxy_texture {
+            let size = GALAXY_INSET_PIXELS.min(w * 0.35);
+            let (x, y) = (w - size - 20.0, 20.0);
+            self.renderer.begin_screen_overlay();
+            self.renderer.draw_screen_texture(texture, x, y, size, size, (w, h));
+            let (sx, sy) = sun_galactic_offset();
+            let (cx, cy) = (x + size / 2.0 + sx * size / 2.0, y + size / 2.0 + sy * size / 2.0);
+            self.renderer.draw_screen_rect(cx - 3.0, cy - 3.0, 6.0, 6.0, (1.0, 0.9, 0.2), (w, h));
+            self.renderer.end_screen_overlay();
+        }
+
+        self.renderer.set_alpha(1.0);
+        self.renderer.disable_blend();
+    }
+
+    fn pick_body(&mut self, x: i32, y: i32) {
+        let picked = self.pick_frame.borrow().as_ref().and_then(|frame| {
+            let ndc_x = 2.0 * x as f32 / frame.width - 1.0;
+            let ndc_y = 1.0 - 2.0 * y as f32 / frame.height;
+            let near = frame.inverse_view_projection * Vector4::new(ndc_x, ndc_y, -1.0, 1.0);
+            let far = frame.inverse_view_projection * Vector4::new(ndc_x, ndc_y, 1.0, 1.0);
+            let near = near.xyz() / near.w;
+            let far = far.xyz() / far.w;
+            pick_sphere(near, (far - near).normalize(), &frame.spheres)
+        });
+        if let Some(index) = picked {
+            self.select_body(index);
+        }
+    }
+
+    // Nearby stars can be read about but not flown to
+    fn pick_nearby_star(&self, x: i32, y: i32) {
+        let picked = self.star_screen.borrow().iter()
+            .map(|&(i, sx, sy)| (i, (sx - x as f32).hypot(sy - y as f32)))
+            .filter(|(_, d)| *d <= STAR_PICK_PIXELS)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i);
+        if let Some(i) = picked {
+            let star = &NEARBY_STARS[i];
+            events::emit("nearby_star_selected", serde_json::json!({
+                "name": star.name,
+                "distance_ly": star.distance_ly,
+                "blurb": star.blurb,
+            }));
         }
     }
 
     pub fn handle_input(&mut self, key: &str) {
+        self.cancel_tour();
+        if let Some(surface) = self.surface_view.as_mut() {
+            match key {
+                "ArrowUp" => surface.look.rotate(0.0, 0.1),
+                "ArrowDown" => surface.look.rotate(0.0, -0.1),
+                "ArrowLeft" => surface.look.rotate(-0.1, 0.0),
+                "ArrowRight" => surface.look.rotate(0.1, 0.0),
+                _ => {}
+            }
+            return;
+        }
         match key {
-            "ArrowUp" => self.camera_distance -= 1.0,
-            "ArrowDown" => self.camera_distance += 1.0,
-            "ArrowLeft" => self.camera_rotation.1 -= 0.1,
-            "ArrowRight" => self.camera_rotation.1 += 0.1,
+            "ArrowUp" => self.camera.set_distance(self.camera.target_distance() - 1.0),
+            "ArrowDown" => self.camera.set_distance(self.camera.target_distance() + 1.0),
+            "ArrowLeft" => self.camera.rotate(-0.1, 0.0),
+            "ArrowRight" => self.camera.rotate(0.1, 0.0),
+            _ => {}
+        }
+    }
+
+    pub fn handle_pointer_event(&mut self, event: &InputEvent) {
+        if matches!(event, InputEvent::PointerDown { .. } | InputEvent::Wheel { .. }) {
+            self.cancel_tour();
+        }
+        if let InputEvent::PointerDown { x, y, .. } = *event {
+            if self.is_in_comparison_overlay(x, y) {
+                return;
+            }
+            if self.context_blend() > 0.5 {
+                self.pick_nearby_star(x, y);
+            } else {
+                self.press = Some((x, y, 0));
+            }
+        }
+        match *event {
+            InputEvent::PointerMove { dx, dy, .. } => {
+                if let Some(press) = self.press.as_mut() {
+                    press.2 += dx.abs() + dy.abs();
+                }
+            }
+            InputEvent::PointerUp => {
+                if let Some((x, y, moved)) = self.press.take() {
+                    if moved < CLICK_SLOP_PIXELS {
+                        self.pick_body(x, y);
+                    }
+                }
+            }
             _ => {}
         }
+        match self.surface_view.as_mut() {
+            // Dragging looks around, there is nothing to zoom
+            Some(surface) => {
+                if !matches!(event, InputEvent::Wheel { .. }) {
+                    surface.look.handle(event);
+                }
+            }
+            None => self.camera.handle(event),
+        }
     }
+}
 
-    pub fn handle_mouse_down(&mut self, x: i32, y: i32) {
-        self.is_dragging = true;
-        self.last_mouse_pos = (x, y);
+// Everything new() put on the page goes with the system. Only its own nodes are removed, a
+// system replacing this one has already added its own by the time this runs.
+impl Drop for SolarSystem {
+    fn drop(&mut self) {
+        for element in self.bodies.iter().filter_map(|body| body.label_element.as_ref()) {
+            element.remove();
+        }
+        if let Some((peri_label, apo_label)) = &self.apsis_labels {
+            peri_label.remove();
+            apo_label.remove();
+        }
+        for (element, _) in self.star_labels.iter().flatten() {
+            element.remove();
+        }
+        for item in &self.list_items {
+            item.remove();
+        }
     }
+}
+
+// Unit vector in the sphere mesh's frame for a latitude/longitude, following its texture mapping
+fn surface_point(latitude: f32, longitude: f32) -> Vector3<f32> {
+    let (lat, lon) = (latitude.to_radians(), longitude.to_radians());
+    Vector3::new(-lon.cos() * lat.cos(), lat.sin(), lon.sin() * lat.cos())
+}
+
+// Radians per simulated second for a rotation period in days, retrograde spins included
+fn rotation_speed(rotation_period_days: f32) -> f32 {
+    (2.0 * std::f32::consts::PI) / (rotation_period_days.abs() * 24.0 * 3600.0)
+}
 
-    pub fn handle_mouse_up(&mut self) {
-        self.is_dragging = false;
+// A companion star's offset from the camera target, color and luminosity in watts
+type CompanionLight = (Vector3<f32>, (f32, f32, f32), f64);
+
+// Primary light color and the companion's light. The primary sits at the origin and keeps today's
+// unattenuated light. The companion's light is scaled by the luminosity ratio so both match at
+// 1 AU, then falls off with distance. A single star lights white with the second light off.
+fn star_lights(primary_color: (f32, f32, f32), companion: Option<CompanionLight>, central_luminosity: f64) -> ((f32, f32, f32), SecondLight) {
+    match companion {
+        Some((position, color, luminosity)) if central_luminosity > 0.0 => {
+            let intensity = (luminosity / central_luminosity) as f32 * UNITS_PER_AU * UNITS_PER_AU;
+            (primary_color, SecondLight { position, color, intensity })
+        }
+        _ => ((1.0, 1.0, 1.0), SecondLight::off()),
     }
+}
 
-    pub fn handle_mouse_move(&mut self, x: i32, y: i32) {
-        if self.is_dragging {
-            let dx = x - self.last_mouse_pos.0;
-            let dy = y - self.last_mouse_pos.1;
-            
-            self.camera_rotation.1 += dx as f32 * 0.01;
-            self.camera_rotation.0 += dy as f32 * 0.01;
-            
-            self.camera_rotation.0 = self.camera_rotation.0.max(-1.5).min(1.5);
-            
-            self.last_mouse_pos = (x, y);
+fn spin_frame(axial_tilt: f32, rotation: f32) -> Matrix4<f32> {
+    Renderer::model_matrix(Vector3::zeros(), Vector3::repeat(1.0), Vector3::new(axial_tilt, rotation, 0.0))
+}
+
+// Latitude and longitude under a star in the given world direction
+fn subsolar_coordinates(axial_tilt: f32, rotation: f32, to_star: Vector3<f32>) -> (f32, f32) {
+    let local = spin_frame(axial_tilt, rotation).transpose().transform_vector(&to_star);
+    surface_coordinates(&local)
+}
+
+fn surface_coordinates(local: &Vector3<f32>) -> (f32, f32) {
+    let latitude = local.y.clamp(-1.0, 1.0).asin().to_degrees();
+    let longitude = local.z.atan2(-local.x).to_degrees();
+    (latitude, longitude)
+}
+
+fn wrap_degrees(angle: f32) -> f32 {
+    (angle + 180.0).rem_euclid(360.0) - 180.0
+}
+
+// Scene positions lie in the ecliptic's x-z plane with longitude measured from +x toward +z
+pub fn ecliptic_longitude(offset: Vector3<f32>) -> f32 {
+    offset.z.atan2(offset.x).to_degrees().rem_euclid(360.0)
+}
+
+pub fn zodiac_constellation(longitude: f32) -> &'static str {
+    let longitude = longitude.rem_euclid(360.0);
+    ZODIAC_BOUNDARIES.iter()
+        .rev()
+        .find(|(start, _)| longitude >= *start)
+        .map(|(_, name)| *name)
+        // Below Aries' start the ecliptic is still in Pisces from the previous lap
+        .unwrap_or("Pisces")
+}
+
+// Mean anomaly in radians days after J2000, from the one at J2000 in degrees and the angular
+// speed in radians per second. Bodies that don't orbit keep the one they have.
+fn orbit_angle_at(epoch_mean_anomaly: f32, orbit_speed: f32, days_since_j2000: f64) -> f32 {
+    if orbit_speed.abs() > 0.0 {
+        let n_rad_per_day = orbit_speed * 86400.0;
+        (epoch_mean_anomaly.to_radians() + n_rad_per_day * days_since_j2000 as f32) % (2.0 * std::f32::consts::PI)
+    } else {
+        epoch_mean_anomaly.to_radians()
+    }
+}
+
+// Every body's position once the orbits have run on for seconds, parents before their moons
+fn positions_after(bodies: &[Body], seconds: f32) -> Vec<Vector3<f32>> {
+    let mut positions = vec![Vector3::new(0.0, 0.0, 0.0); bodies.len()];
+    for (i, body) in bodies.iter().enumerate() {
+        let angle = if body.parent.is_some() { body.orbit_angle + body.orbit_speed * seconds } else { body.orbit_angle };
+        let mut pos = orbit_position(body, angle);
+        if let Some(parent_idx) = body.parent {
+            pos += positions[parent_idx];
+        }
+        positions[i] = pos;
+    }
+    positions
+}
+
+fn format_date(time_ms: f64) -> String {
+    let date = Date::new(&wasm_bindgen::JsValue::from_f64(time_ms));
+    format!(
+        "{:02}/{:02}/{} {:02}:{:02}:{:02}",
+        date.get_month() + 1, date.get_date(), date.get_full_year(), date.get_hours(), date.get_minutes(), date.get_seconds()
+    )
+}
+
+// Position relative to the parent for a given mean anomaly, from the body's orbital elements
+fn orbit_position(body: &Body, mean_anomaly: f32) -> Vector3<f32> {
+    position_at_true_anomaly(body, true_anomaly(body, mean_anomaly))
+}
+
+// The orbit speed is sidereal, so a drifting perihelion is taken back out of the mean anomaly
+// and the body still comes round to the same longitude once a period
+fn true_anomaly(body: &Body, mean_anomaly: f32) -> f32 {
+    let mean_anomaly = mean_anomaly - (body.periapsis_rate + body.node_rate) * body.drift_years;
+    let e = body.eccentricity;
+    let big_e = eccentric_anomaly(mean_anomaly, e);
+    2.0 * ((1.0 + e).sqrt() * (big_e / 2.0).sin()).atan2((1.0 - e).sqrt() * (big_e / 2.0).cos())
+}
+
+// Newton's method on Kepler's equation M = E - e sin E, a few steps converge for e < 0.5
+fn eccentric_anomaly(mean_anomaly: f32, e: f32) -> f32 {
+    let mut big_e = mean_anomaly + e * mean_anomaly.sin();
+    for _ in 0..4 {
+        big_e -= (big_e - e * big_e.sin() - mean_anomaly) / (1.0 - e * big_e.cos());
+    }
+    big_e
+}
+
+fn position_at_true_anomaly(body: &Body, nu: f32) -> Vector3<f32> {
+    let e = body.eccentricity;
+    let r = body.orbit_radius * (1.0 - e * e) / (1.0 + e * nu.cos());
+    let periapsis = body.argument_of_periapsis + body.periapsis_rate * body.drift_years;
+    let node = body.longitude_of_ascending_node + body.node_rate * body.drift_years;
+    orient_orbit_point(r * nu.cos(), r * nu.sin(), periapsis, body.orbit_inclination, node)
+}
+
+// Periapsis and node drift in radians per Julian year, for the bodies that have one
+fn secular_rates(name: &str) -> Option<(f32, f32)> {
+    SECULAR_RATES.iter()
+        .find(|(body, _, _)| *body == name)
+        .map(|(_, periapsis_rate, node_rate)| (periapsis_rate.to_radians(), node_rate.to_radians()))
+}
+
+// From the orbit's own plane, periapsis along +x, into the scene. Periapsis and node turn the
+// same way the bodies move, from +x toward +z, like ecliptic longitude.
+fn orient_orbit_point(x_orb_raw: f32, z_orb_raw: f32, periapsis: f32, inclination: f32, node: f32) -> Vector3<f32> {
+    // Apply Argument of Periapsis
+    let (sin_w, cos_w) = periapsis.sin_cos();
+    let x_orb = x_orb_raw * cos_w - z_orb_raw * sin_w;
+    let z_orb = x_orb_raw * sin_w + z_orb_raw * cos_w;
+
+    // Apply inclination (rotation around X)
+    let y_incl = z_orb * inclination.sin();
+    let z_incl = z_orb * inclination.cos();
+
+    // Apply Longitude of Ascending Node (rotation around Y)
+    let (sin_o, cos_o) = node.sin_cos();
+    Vector3::new(
+        x_orb * cos_o - z_incl * sin_o,
+        y_incl,
+        x_orb * sin_o + z_incl * cos_o,
+    )
+}
+
+fn build_orbit_path(body: &Body) -> Vec<f32> {
+    let is_small_body = body.name.starts_with("Asteroid") || body.name.starts_with("Kuiper") || body.name.starts_with("Oort");
+    if is_small_body || body.orbit_radius <= 0.0 || body.orbit_speed == 0.0 {
+        return Vec::new();
+    }
+
+    let step = 2.0 * std::f32::consts::PI / ORBIT_PATH_POINTS as f32;
+    (0..ORBIT_PATH_POINTS).flat_map(|k| {
+        let p = position_at_true_anomaly(body, k as f32 * step);
+        [p.x, p.y, p.z]
+    }).collect()
+}
+
+// Screen center and pixel radius of a sphere given relative to the camera target, None when behind the camera
+fn project_sphere(center: Vector3<f32>, radius: f32, projection: &Matrix4<f32>, view: &Matrix4<f32>, width: i32, height: i32) -> Option<(f32, f32, f32)> {
+    let view_pos = view * Vector4::new(center.x, center.y, center.z, 1.0);
+    let top_view = view_pos + Vector4::new(0.0, radius, 0.0, 0.0);
+
+    let clip_center = projection * view_pos;
+    let clip_top = projection * top_view;
+    if clip_center.w <= 0.0 {
+        return None;
+    }
+
+    let ndc_center_x = clip_center.x / clip_center.w;
+    let ndc_center_y = clip_center.y / clip_center.w;
+    let ndc_top_y = clip_top.y / clip_top.w;
+    let screen_x = (ndc_center_x + 1.0) * width as f32 / 2.0;
+    let screen_cy = (1.0 - ndc_center_y) * height as f32 / 2.0;
+    let screen_ty = (1.0 - ndc_top_y) * height as f32 / 2.0;
+    Some((screen_x, screen_cy, (screen_cy - screen_ty).abs()))
+}
+
+// Orbits and discs of the major bodies as seen from scene, each disc under its label's anchor
+fn star_chart(bodies: &[Body], positions: &[Vector3<f32>], scene: &SceneView, horizon: Option<(usize, Vector3<f32>)>, caption: &str) -> String {
+    let mut chart = StarChart::new(scene.width as f32, scene.height as f32);
+
+    let step = 2.0 * std::f32::consts::PI / SVG_ORBIT_POINTS as f32;
+    for body in bodies.iter().filter(|body| !is_small_body(body) && !body.orbit_path.is_empty()) {
+        let parent_pos = body.parent.map_or_else(Vector3::zeros, |p| positions[p]);
+        let mut runs = vec![Vec::new()];
+        for k in 0..=SVG_ORBIT_POINTS {
+            let point = parent_pos + position_at_true_anomaly(body, k as f32 * step) - scene.target;
+            match project_sphere(point, 0.0, &scene.projection, &scene.view, scene.width, scene.height) {
+                Some((x, y, _)) if chart.in_bounds(x, y) => runs.last_mut().unwrap().push((x, y)),
+                _ => {
+                    if runs.last().is_some_and(|run| !run.is_empty()) {
+                        runs.push(Vec::new());
+                    }
+                }
+            }
+        }
+        chart.orbit(&runs);
+    }
+
+    // Far bodies first so nearer discs cover them, as on screen
+    let mut discs = Vec::new();
+    for (i, body) in bodies.iter().enumerate() {
+        if is_small_body(body) {
+            continue;
+        }
+        let pos = positions[i] - scene.target;
+        let dist = (scene.eye - pos).norm();
+        if horizon.is_some_and(|(ground, up)| i == ground || (pos - scene.eye).dot(&up) < 0.0) {
+            continue;
+        }
+        let (render_radius, _) = body_render_radius(body, dist);
+        if let Some((x, y, radius)) = project_sphere(pos, render_radius, &scene.projection, &scene.view, scene.width, scene.height) {
+            if chart.in_bounds(x, y) {
+                discs.push((dist, i, x, y, radius));
+            }
+        }
+    }
+    discs.sort_by(|a, b| b.0.total_cmp(&a.0));
+    for (_, i, x, y, radius) in discs {
+        let body = &bodies[i];
+        chart.body(&body.name, x, y, radius, y - radius - LABEL_GAP_PX, body.color);
+    }
+
+    chart.caption(caption);
+    chart.finish()
+}
+
+// The stored orbit walked from just ahead of the body around to the body itself,
+// fading in so the oldest part of the "trail" is dark. Interleaved pos + color.
+fn trail_vertices(body: &Body, offset: Vector3<f32>) -> Vec<f32> {
+    let two_pi = 2.0 * std::f32::consts::PI;
+    let n = body.orbit_path.len() / 3;
+    let nu = true_anomaly(body, body.orbit_angle).rem_euclid(two_pi);
+    let current = ((nu / two_pi * n as f32) as usize).min(n - 1);
+    let prograde = body.orbit_speed >= 0.0;
+
+    let mut vertices = Vec::with_capacity((n + 1) * 6);
+    let mut push = |p: Vector3<f32>, fade: f32| {
+        let p = p + offset;
+        vertices.extend_from_slice(&[
+            p.x, p.y, p.z,
+            body.color.0 * 0.5 * fade, body.color.1 * 0.5 * fade, body.color.2 * 0.5 * fade,
+        ]);
+    };
+
+    for j in 0..n {
+        let k = if prograde { (current + 1 + j) % n } else { (current + n - j) % n };
+        let p = Vector3::new(body.orbit_path[k * 3], body.orbit_path[k * 3 + 1], body.orbit_path[k * 3 + 2]);
+        push(p, j as f32 / n as f32);
+    }
+    push(position_at_true_anomaly(body, nu), 1.0);
+    vertices
+}
+
+// Zero-albedo blackbody: T = (L / (16 pi sigma d^2))^(1/4), about 279 K at 1 AU from the Sun
+fn equilibrium_temperature(luminosity_w: f64, distance_m: f64) -> f64 {
+    if distance_m <= 0.0 || luminosity_w <= 0.0 {
+        return CMB_TEMPERATURE_K;
+    }
+    let t = (luminosity_w / (16.0 * std::f64::consts::PI * STEFAN_BOLTZMANN * distance_m * distance_m)).powf(0.25);
+    t.max(CMB_TEMPERATURE_K)
+}
+
+fn body_radius_km(body: &Body) -> f64 {
+    // Scene units are scaled so that Earth's radius (6371 km) is 0.0042
+    body.radius as f64 * 6371.0 / 0.0042
+}
+
+fn orbital_period_days(body: &Body) -> Option<f64> {
+    if body.orbit_speed.abs() > 0.0 {
+        Some((2.0 * std::f64::consts::PI / body.orbit_speed.abs() as f64) / 86400.0)
+    } else {
+        None
+    }
+}
+
+// Catalog masses are strings like "5.972 × 10^24 kg"
+fn parse_mass_kg(mass: &str) -> Option<f64> {
+    let value = mass.trim().trim_end_matches("kg").trim();
+    let (mantissa, exponent) = value.split_once("× 10^")?;
+    let mantissa: f64 = mantissa.trim().parse().ok()?;
+    let exponent: i32 = exponent.trim().parse().ok()?;
+    Some(mantissa * 10f64.powi(exponent))
+}
+
+// Surface gravity in m/s^2 from mass (kg) and radius (km)
+fn surface_gravity(mass_kg: f64, radius_km: f64) -> f64 {
+    let g = 6.674e-11;
+    let radius_m = radius_km * 1000.0;
+    if radius_m > 0.0 { g * mass_kg / (radius_m * radius_m) } else { 0.0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at_au(au: f64) -> f64 {
+        equilibrium_temperature(SOLAR_LUMINOSITY_W, au * METERS_PER_AU)
+    }
+
+    #[test]
+    fn one_au_from_the_sun_is_about_279_kelvin() {
+        assert!((at_au(1.0) - 278.3).abs() < 0.5, "got {}", at_au(1.0));
+    }
+
+    #[test]
+    fn temperature_falls_with_the_square_root_of_distance() {
+        assert!((at_au(4.0) - at_au(1.0) / 2.0).abs() < 1e-6);
+        let brighter = equilibrium_temperature(16.0 * SOLAR_LUMINOSITY_W, METERS_PER_AU);
+        assert!((brighter - 2.0 * at_au(1.0)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn bare_black_hole_leaves_the_background() {
+        assert_eq!(equilibrium_temperature(0.0, METERS_PER_AU), CMB_TEMPERATURE_K);
+        assert_eq!(equilibrium_temperature(SOLAR_LUMINOSITY_W, 0.0), CMB_TEMPERATURE_K);
+        assert!(equilibrium_temperature(SOLAR_LUMINOSITY_W, 1e6 * METERS_PER_AU) >= CMB_TEMPERATURE_K);
+    }
+
+    #[test]
+    fn live_model_matches_the_catalog_for_airless_and_thin_air_bodies() {
+        // Venus (greenhouse) and the giants (internal heat) are expected to differ
+        for &(name, au, catalog) in &[("Mercury", 0.39, 440.0), ("Earth", 1.0, 288.0), ("Mars", 1.52, 210.0), ("Ceres", 2.77, 168.0)] {
+            let live = at_au(au);
+            assert!((live - catalog).abs() / catalog < 0.1, "{} is {:.0} K against a catalog {:.0} K", name, live, catalog);
+        }
+    }
+
+    #[test]
+    fn subsolar_longitude_drifts_fifteen_degrees_an_hour() {
+        let tilt = 23.4f32.to_radians();
+        let to_sun = Vector3::new(-1.0, 0.0, 0.3).normalize();
+        let hourly = rotation_speed(1.0) * 3600.0;
+        let mut rotation = 0.0;
+        let (lat, mut lon) = subsolar_coordinates(tilt, rotation, to_sun);
+        for _ in 0..30 {
+            rotation += hourly;
+            let (next_lat, next_lon) = subsolar_coordinates(tilt, rotation, to_sun);
+            // The Sun moves west, toward lower longitudes
+            assert!((wrap_degrees(next_lon - lon) + 15.0).abs() < 0.01, "moved {} degrees", wrap_degrees(next_lon - lon));
+            // Without orbital motion the declination holds still
+            assert!((next_lat - lat).abs() < 0.01);
+            lon = next_lon;
+        }
+    }
+
+    #[test]
+    fn subsolar_latitude_is_the_declination() {
+        let tilt = 23.4f32.to_radians();
+        // Summer solstice for this tilt: the Sun lies along the tilted pole's lean
+        let pole = spin_frame(tilt, 0.0).transform_vector(&Vector3::y());
+        let to_sun = Vector3::new(pole.x, 0.0, pole.z).normalize();
+        let (lat, _) = subsolar_coordinates(tilt, 1.234, to_sun);
+        assert!((lat - 23.4).abs() < 0.01, "got {}", lat);
+    }
+
+    #[test]
+    fn surface_point_round_trips_through_coordinates() {
+        for &(lat, lon) in &[(0.0, 0.0), (51.5, -0.1), (-33.9, 151.2), (10.0, 179.0)] {
+            let (back_lat, back_lon) = surface_coordinates(&surface_point(lat, lon));
+            assert!((back_lat - lat).abs() < 1e-3 && wrap_degrees(back_lon - lon).abs() < 1e-3);
+        }
+    }
+
+    // Where the orbit crosses the reference plane, for a periapsis and node in radians
+    fn node_direction(periapsis: f32, inclination: f32, node: f32) -> Vector3<f32> {
+        orient_orbit_point(periapsis.cos(), -periapsis.sin(), periapsis, inclination, node)
+    }
+
+    #[test]
+    fn moon_node_regresses_about_nineteen_degrees_a_year() {
+        let (periapsis_rate, node_rate) = secular_rates("Moon").unwrap();
+        assert!((node_rate.to_degrees() + 19.3).abs() < 0.1);
+
+        let inclination = 5.1f32.to_radians();
+        let node_at = |years: f32| node_direction(periapsis_rate * years, inclination, node_rate * years);
+        for years in [1.0f32, 2.0, 5.0] {
+            let (start, later) = (node_at(0.0), node_at(years));
+            // The node stays on the reference plane while the line through it turns
+            assert!(later.y.abs() < 1e-5);
+            let turned = start.dot(&later).clamp(-1.0, 1.0).acos().to_degrees();
+            let expected = (19.341 * years + 180.0).rem_euclid(360.0) - 180.0;
+            assert!((turned - expected.abs()).abs() < 0.05, "{} years turned {} degrees", years, turned);
+        }
+    }
+
+    #[test]
+    fn bodies_without_rates_hold_their_elements() {
+        assert!(secular_rates("Earth").is_none());
+        assert!(secular_rates("Mercury").is_some());
+    }
+
+    #[test]
+    fn single_star_lights_white_with_the_second_light_off() {
+        let sun_color = (1.0, 0.95, 0.8);
+        let (primary, second) = star_lights(sun_color, None, SOLAR_LUMINOSITY_W);
+        assert_eq!(primary, (1.0, 1.0, 1.0));
+        assert_eq!(second.intensity, 0.0);
+
+        let companion = Some((Vector3::new(3.0, 0.0, 0.0), (0.8, 0.9, 1.0), SOLAR_LUMINOSITY_W / 40.0));
+        let (primary, second) = star_lights(sun_color, companion, SOLAR_LUMINOSITY_W);
+        assert_eq!(primary, sun_color);
+        assert!((second.intensity - UNITS_PER_AU * UNITS_PER_AU / 40.0).abs() < 1e-3);
+    }
+
+    // A bare body on the catalog's orbit. Angles in degrees, as SolarSystem::new takes them.
+    fn orbiting(name: &str, parent: Option<usize>, orbit_radius: f32, period_days: f32, mean_anomaly: f32, inclination: f32, node: f32, periapsis: f32, eccentricity: f32) -> Body {
+        Body {
+            mesh: Mesh { vertices: Vec::new(), indices: Vec::new() },
+            radius: 0.01,
+            orbit_radius,
+            orbit_speed: if period_days > 0.0 { 2.0 * std::f32::consts::PI / (period_days * 86400.0) } else { 0.0 },
+            orbit_angle: 0.0,
+            color: (1.0, 1.0, 1.0),
+            parent,
+            name: name.to_string(),
+            orbit_path: Vec::new(),
+            label_element: None,
+            label_state: Cell::new(None),
+            texture: None,
+            night_texture: None,
+            cloud_texture: None,
+            cloud_rotation: 0.0,
+            rotation_period: 0.0,
+            axial_tilt: 0.0,
+            current_rotation: 0.0,
+            orbit_inclination: inclination.to_radians(),
+            longitude_of_ascending_node: node.to_radians(),
+            argument_of_periapsis: periapsis.to_radians(),
+            eccentricity,
+            mass: String::new(),
+            temperature: 0.0,
+            description: String::new(),
+            ring_texture: None,
+            ring_radius: 0.0,
+            ring_inner_radius: None,
+            is_frozen: false,
+            mean_anomaly_at_epoch: mean_anomaly,
+            catalog_temperature: 0.0,
+            periapsis_rate: 0.0,
+            node_rate: 0.0,
+            drift_years: 0.0,
+            path_drift_years: 0.0,
+        }
+    }
+
+    // Sun, Earth, Moon, Jupiter and Saturn with the catalog's elements, in that order
+    fn inner_and_giants() -> Vec<Body> {
+        vec![
+            orbiting("Sun", None, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0),
+            orbiting("Earth", Some(0), 100.0, 365.256, 357.52, 0.0, 0.0, 102.94, 0.017),
+            orbiting("Moon", Some(1), 0.257, 27.322, 0.0, 5.1, 0.0, 0.0, 0.055),
+            orbiting("Jupiter", Some(0), 520.0, 4332.82, 19.67, 1.3, 100.46, 274.27, 0.0484),
+            orbiting("Saturn", Some(0), 958.0, 10755.88, 317.34, 2.48, 113.67, 338.93, 0.0539),
+        ]
+    }
+
+    // What set_date_from_timestamp and update_epoch leave the bodies at, so also what
+    // step_simulation lands on
+    fn positions_on(bodies: &mut [Body], timestamp_ms: f64) -> Vec<Vector3<f32>> {
+        let days = (timestamp_ms - J2000_MS) / 86400000.0;
+        for body in bodies.iter_mut() {
+            body.orbit_angle = orbit_angle_at(body.mean_anomaly_at_epoch, body.orbit_speed, days);
+            body.drift_years = ((timestamp_ms - J2000_MS) / MS_PER_JULIAN_YEAR) as f32;
+        }
+        positions_after(bodies, 0.0)
+    }
+
+    // How far a body's longitude around its parent moves over one step, from a spread of start dates
+    fn step_returns(bodies: &mut [Body], index: usize, step_days: f64) -> f32 {
+        let parent = bodies[index].parent.unwrap();
+        let mut worst = 0.0f32;
+        for k in 0..40 {
+            let start = J2000_MS + k as f64 * 97.0 * 86400000.0;
+            let before = positions_on(bodies, start);
+            let after = positions_on(bodies, start + step_days * 86400000.0);
+            let turned = ecliptic_longitude(after[index] - after[parent]) - ecliptic_longitude(before[index] - before[parent]);
+            worst = worst.max(wrap_degrees(turned).abs());
         }
+        worst
+    }
+
+    #[test]
+    fn earth_comes_round_in_a_sidereal_year() {
+        let mut bodies = inner_and_giants();
+        let off = step_returns(&mut bodies, 1, 365.256);
+        assert!(off < 0.1, "off by {} degrees", off);
+    }
+
+    #[test]
+    fn moon_comes_round_in_a_sidereal_month() {
+        let mut bodies = inner_and_giants();
+        let off = step_returns(&mut bodies, 2, 27.322);
+        assert!(off < 0.1, "off by {} degrees", off);
+
+        // Its perigee turns about 3 degrees a month, which moves it along the ellipse by up to
+        // twice the eccentricity times that
+        let (periapsis_rate, node_rate) = secular_rates("Moon").unwrap();
+        bodies[2].periapsis_rate = periapsis_rate;
+        bodies[2].node_rate = node_rate;
+        let perigee_turn = ((periapsis_rate + node_rate) * (27.322 / 365.25)).to_degrees();
+        let off = step_returns(&mut bodies, 2, 27.322);
+        assert!(off < 0.1 + 2.0 * 0.055 * perigee_turn, "off by {} degrees", off);
+    }
+
+    #[test]
+    fn great_conjunction_of_2020_shares_a_constellation() {
+        let mut bodies = inner_and_giants();
+        // 2020-12-21 18:00 UTC
+        let positions = positions_on(&mut bodies, 1608573600000.0);
+        let jupiter = ecliptic_longitude(positions[3] - positions[1]);
+        let saturn = ecliptic_longitude(positions[4] - positions[1]);
+        assert!(wrap_degrees(jupiter - saturn).abs() < 1.0, "Jupiter at {}, Saturn at {}", jupiter, saturn);
+        assert_eq!(zodiac_constellation(jupiter), zodiac_constellation(saturn));
+        assert!((wrap_degrees(jupiter - 300.0)).abs() < 5.0, "Jupiter at {}", jupiter);
+    }
+
+    #[test]
+    fn frozen_threshold_sits_just_past_three_and_a_half_au() {
+        assert!((at_au(3.4) as f32) > FROZEN_THRESHOLD_K);
+        assert!((at_au(3.6) as f32) < FROZEN_THRESHOLD_K);
+    }
+    #[test]
+    fn labels_write_only_when_they_move_or_flip() {
+        let at = LabelState::shown(120.2, 48.0);
+        // The first frame always writes, a label that stays put never does
+        assert!(label_needs_write(None, LabelState::HIDDEN));
+        assert!(!label_needs_write(Some(at), at));
+        // Sub-pixel jitter rounds to the same pixel
+        assert!(!label_needs_write(Some(at), LabelState::shown(119.6, 48.4)));
+        assert!(label_needs_write(Some(at), LabelState::shown(121.0, 48.0)));
+        assert!(label_needs_write(Some(at), LabelState::shown(120.0, 47.0)));
+    }
+
+    #[test]
+    fn labels_write_when_shown_again_but_not_while_hidden() {
+        let hidden = LabelState::HIDDEN;
+        assert!(label_needs_write(Some(hidden), LabelState::shown(0.0, 0.0)));
+        assert!(label_needs_write(Some(LabelState::shown(10.0, 10.0)), hidden));
+        assert!(!label_needs_write(Some(hidden), LabelState { x: 300, ..hidden }));
+    }
+
+    // A pick sphere as the frame records it: a planet's render radius from a camera at the
+    // origin, inflated to its minimum apparent size
+    fn sphere_seen_from_origin(index: usize, radius: f32, center: Vector3<f32>) -> (usize, Vector3<f32>, f32) {
+        let mut body = orbiting("Planet", Some(0), 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+        body.radius = radius;
+        (index, center, body_render_radius(&body, center.norm()).0)
+    }
+
+    fn pick_toward(target: Vector3<f32>, spheres: &[(usize, Vector3<f32>, f32)]) -> Option<usize> {
+        pick_sphere(Vector3::zeros(), target.normalize(), spheres)
+    }
+
+    #[test]
+    fn moon_in_front_of_its_planet_wins_the_click() {
+        // Both inflated to the same 0.2 disc a hundred units out, the moon just nearer
+        let planet = sphere_seen_from_origin(1, 0.0042, Vector3::new(0.0, 0.0, 100.0));
+        let moon = sphere_seen_from_origin(2, 0.0011, Vector3::new(0.15, 0.0, 99.8));
+        assert!((planet.2 - moon.2).abs() < 1e-3);
+        assert_eq!(pick_toward(moon.1, &[planet, moon]), Some(2));
+        assert_eq!(pick_toward(Vector3::new(-0.1, 0.0, 100.0), &[planet, moon]), Some(1));
+
+        // A small moon a little behind the planet's front surface is still within the tie
+        let small_moon = (3, Vector3::new(0.0, 0.0, 99.9), 0.05);
+        assert_eq!(pick_toward(small_moon.1, &[planet, small_moon]), Some(3));
+    }
+
+    #[test]
+    fn sun_covers_a_planet_behind_it() {
+        let sun = sphere_seen_from_origin(0, 0.465, Vector3::new(0.0, 0.0, 50.0));
+        let planet = sphere_seen_from_origin(1, 0.0042, Vector3::new(1.2, 0.0, 150.0));
+        assert_eq!(pick_toward(planet.1, &[planet, sun]), Some(0));
+        // The edge of the planet's inflated disc that clears the Sun's limb
+        let edge = Vector3::new(1.2 + 0.25, 0.0, 150.0);
+        assert_eq!(pick_toward(edge, &[planet, sun]), Some(1));
+    }
+
+    #[test]
+    fn grazing_clicks_pick_only_inside_the_render_radius() {
+        let planet = sphere_seen_from_origin(1, 0.0042, Vector3::new(0.0, 0.0, 100.0));
+        let radius = planet.2;
+        assert_eq!(pick_toward(Vector3::new(radius * 0.99, 0.0, 100.0), &[planet]), Some(1));
+        assert_eq!(pick_toward(Vector3::new(radius * 1.01, 0.0, 100.0), &[planet]), None);
+        // Behind the camera is never a hit, from inside the entry is right here
+        assert_eq!(ray_sphere_entry(Vector3::zeros(), -Vector3::z(), planet.1, radius), None);
+        assert_eq!(ray_sphere_entry(planet.1, Vector3::x(), planet.1, radius), Some(0.0));
+    }
+
+    // Looking down on the ecliptic from far enough out to fit Saturn's orbit
+    fn overhead_view() -> SceneView {
+        let eye = Vector3::new(0.0, 3000.0, 1.0);
+        let projection = Matrix4::new_perspective(800.0 / 600.0, 45.0f32.to_radians(), 0.001, 200000000.0);
+        let view = Matrix4::look_at_rh(&Point3::from(eye), &Point3::origin(), &Vector3::y());
+        SceneView { target: Vector3::zeros(), eye, projection, view, width: 800, height: 600 }
+    }
+
+    // The number in attribute="..." after the first occurrence of after
+    fn svg_number(svg: &str, after: &str, attribute: &str) -> f32 {
+        let rest = &svg[svg.find(after).unwrap()..];
+        let start = rest.find(&format!("{}=\"", attribute)).unwrap() + attribute.len() + 2;
+        rest[start..start + rest[start..].find('"').unwrap()].parse().unwrap()
     }
 
-    pub fn handle_wheel(&mut self, delta: f32) {
-        let zoom_sensitivity = 0.001;
-        let factor = (delta * zoom_sensitivity).exp();
-        self.camera_distance *= factor;
-        self.camera_distance = self.camera_distance.max(0.0001).min(100000000.0);
+    #[test]
+    fn star_chart_draws_each_orbit_once_and_puts_earth_under_its_label() {
+        let mut bodies = inner_and_giants();
+        for body in bodies.iter_mut() {
+            body.orbit_path = build_orbit_path(body);
+        }
+        let positions = positions_on(&mut bodies, J2000_MS);
+        let scene = overhead_view();
+        let svg = star_chart(&bodies, &positions, &scene, None, "2000-01-01");
+
+        let orbiting = bodies.iter().filter(|body| !body.orbit_path.is_empty()).count();
+        assert_eq!(orbiting, 4);
+        assert_eq!(svg.matches("<path ").count(), orbiting);
+
+        // Where update_labels would put Earth's label
+        let earth = positions[1] - scene.target;
+        let (radius, _) = body_render_radius(&bodies[1], (scene.eye - earth).norm());
+        let (x, y, radius_px) = project_sphere(earth, radius, &scene.projection, &scene.view, scene.width, scene.height).unwrap();
+        let label = svg.find(">Earth<").unwrap();
+        let circle = &svg[svg[..label].rfind("<circle").unwrap()..];
+        assert!((svg_number(circle, "<circle", "cx") - x).abs() < 1.0);
+        assert!((svg_number(circle, "<circle", "cy") - y).abs() < 1.0);
+        assert!((svg_number(circle, "<text", "x") - x).abs() < 1.0);
+        assert!((svg_number(circle, "<text", "y") - (y - radius_px - LABEL_GAP_PX)).abs() < 1.0);
+        assert!(svg.contains("2000-01-01"));
     }
+
+
 }
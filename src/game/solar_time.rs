@@ -0,0 +1,149 @@
+use crate::engine::settings::Settings;
+use serde::{Deserialize, Serialize};
+
+pub const BOOKMARK_SLOTS: usize = 5;
+
+// Time scale easing from one value to another over a fixed number of real seconds.
+// Same-sign scales move exponentially, so 1x to 86400x spends as long in each decade;
+// a ramp through zero or from a stop has no exponential and goes linearly instead.
+pub struct ScaleRamp {
+    from: f64,
+    to: f64,
+    duration: f64,
+    elapsed: f64,
+}
+
+impl ScaleRamp {
+    pub fn new(from: f32, to: f32, duration: f64) -> Self {
+        ScaleRamp { from: from as f64, to: to as f64, duration: duration.max(1e-6), elapsed: 0.0 }
+    }
+
+    fn exponential(&self) -> bool {
+        self.from * self.to > 0.0 && self.from != self.to
+    }
+
+    pub fn scale_at(&self, t: f64) -> f64 {
+        let u = (t / self.duration).clamp(0.0, 1.0);
+        if self.exponential() {
+            self.from * (self.to / self.from).powf(u)
+        } else {
+            self.from + (self.to - self.from) * u
+        }
+    }
+
+    // Closed-form integral of the scale from 0 to t, flat at the target past the end
+    pub fn integral_to(&self, t: f64) -> f64 {
+        let ramp_t = t.clamp(0.0, self.duration);
+        let u = ramp_t / self.duration;
+        let ramp = if self.exponential() {
+            let k = (self.to / self.from).ln();
+            self.from * self.duration / k * ((k * u).exp() - 1.0)
+        } else {
+            ramp_t * (self.from + self.scale_at(ramp_t)) / 2.0
+        };
+        ramp + (t - ramp_t).max(0.0) * self.to
+    }
+
+    // Simulated seconds per real second over the next dt, the integral so the clock lands
+    // where the changing scale really takes it
+    pub fn advance(&mut self, dt: f64) -> f64 {
+        let covered = self.integral_to(self.elapsed + dt) - self.integral_to(self.elapsed);
+        self.elapsed += dt;
+        covered
+    }
+
+    pub fn current(&self) -> f32 {
+        self.scale_at(self.elapsed) as f32
+    }
+
+    pub fn target(&self) -> f32 {
+        self.to as f32
+    }
+
+    pub fn finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub struct TimeBookmark {
+    pub scale: f32,
+    // Simulation date, milliseconds since the epoch
+    pub time: f64,
+}
+
+pub fn load_bookmarks() -> Vec<Option<TimeBookmark>> {
+    let mut slots: Vec<Option<TimeBookmark>> = serde_json::from_value(Settings::load().solar_time_bookmarks).unwrap_or_default();
+    slots.resize(BOOKMARK_SLOTS, None);
+    slots
+}
+
+pub fn save_bookmarks(slots: &[Option<TimeBookmark>]) {
+    let value = serde_json::to_value(slots).unwrap_or_default();
+    Settings::update(|s| s.solar_time_bookmarks = value);
+}
+
+pub fn check_slot(slot: usize) -> Result<(), String> {
+    if slot >= BOOKMARK_SLOTS {
+        return Err(format!("Bookmark slot must be below {}", BOOKMARK_SLOTS));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Midpoint sum over a fine grid, independent of the closed form
+    fn numeric_integral(ramp: &ScaleRamp, t: f64) -> f64 {
+        let steps = 200_000;
+        let h = t / steps as f64;
+        (0..steps).map(|i| ramp.scale_at((i as f64 + 0.5) * h) * h).sum()
+    }
+
+    fn close(a: f64, b: f64, tolerance: f64) -> bool {
+        (a - b).abs() <= tolerance * b.abs().max(1.0)
+    }
+
+    #[test]
+    fn exponential_ramp_matches_the_analytic_integral() {
+        let ramp = ScaleRamp::new(1.0, 86400.0, 2.0);
+        let k = 86400f64.ln();
+        let analytic = 2.0 / k * (86400.0 - 1.0);
+        assert!(close(ramp.integral_to(2.0), analytic, 1e-12));
+        assert!(close(numeric_integral(&ramp, 2.0), analytic, 1e-6));
+    }
+
+    #[test]
+    fn frames_add_up_to_the_integral_whatever_their_lengths() {
+        let mut ramp = ScaleRamp::new(1.0, 3600.0, 1.5);
+        let dts = [0.016, 0.017, 0.05, 0.3, 0.001, 0.4, 0.2, 0.6];
+        let covered: f64 = dts.iter().map(|&dt| ramp.advance(dt)).sum();
+        let total: f64 = dts.iter().sum();
+        assert!(close(covered, ramp.integral_to(total), 1e-12));
+        assert!(ramp.finished());
+        assert_eq!(ramp.current(), 3600.0);
+    }
+
+    // Scale times dt at the frame's end overshoots on the way up
+    #[test]
+    fn frame_covers_less_than_its_end_scale() {
+        let mut ramp = ScaleRamp::new(1.0, 1000.0, 1.0);
+        let covered = ramp.advance(0.5);
+        assert!(covered < ramp.current() as f64 * 0.5);
+        assert!(close(covered, numeric_integral(&ScaleRamp::new(1.0, 1000.0, 1.0), 0.5), 1e-6));
+    }
+
+    #[test]
+    fn ramp_through_zero_is_linear() {
+        let ramp = ScaleRamp::new(-10.0, 10.0, 1.0);
+        assert!(ramp.integral_to(1.0).abs() < 1e-12);
+        assert!(close(ramp.integral_to(0.5), -2.5, 1e-12));
+    }
+
+    #[test]
+    fn past_the_end_runs_at_the_target() {
+        let ramp = ScaleRamp::new(2.0, 20.0, 1.0);
+        assert!(close(ramp.integral_to(3.0) - ramp.integral_to(1.0), 40.0, 1e-12));
+    }
+}
@@ -0,0 +1,136 @@
+use serde::Deserialize;
+
+#[derive(Deserialize, Clone)]
+pub struct TourStop {
+    pub body: String,
+    #[serde(default = "default_dwell")]
+    pub dwell: f32, // real seconds spent at the stop, including the camera flight
+    #[serde(default = "default_time_scale")]
+    pub time_scale: f32,
+    #[serde(default)]
+    pub distance: Option<f32>, // camera distance in scene units, None frames the body itself
+}
+
+fn default_dwell() -> f32 {
+    8.0
+}
+
+fn default_time_scale() -> f32 {
+    1.0
+}
+
+fn stop(body: &str, time_scale: f32, distance: Option<f32>) -> TourStop {
+    TourStop { body: body.to_string(), dwell: default_dwell(), time_scale, distance }
+}
+
+// Sun, inner planets, then the moon systems; outer stops run faster so motion stays visible
+pub fn default_stops() -> Vec<TourStop> {
+    vec![
+        stop("Sun", 3600.0, None),
+        stop("Mercury", 172800.0, Some(0.05)),
+        stop("Earth", 86400.0, Some(0.8)),
+        stop("Mars", 86400.0, None),
+        stop("Jupiter", 43200.0, Some(3.0)),
+        stop("Saturn", 3600.0, Some(0.5)),
+        stop("Pluto", 43200.0, Some(0.05)),
+    ]
+}
+
+pub enum TourStep {
+    Stay,
+    Arrive(usize),
+    Finished,
+}
+
+// Stops are resolved to body indices up front, unknown bodies are dropped by the caller
+pub struct Tour {
+    pub stops: Vec<(usize, TourStop)>,
+    pub current: usize,
+    elapsed: f32,
+    pub previous_time_scale: f32,
+}
+
+impl Tour {
+    pub fn new(stops: Vec<(usize, TourStop)>, previous_time_scale: f32) -> Self {
+        Tour { stops, current: 0, elapsed: 0.0, previous_time_scale }
+    }
+
+    pub fn current_stop(&self) -> Option<&(usize, TourStop)> {
+        self.stops.get(self.current)
+    }
+
+    pub fn advance(&mut self, dt: f32) -> TourStep {
+        let dwell = match self.current_stop() {
+            Some((_, stop)) => stop.dwell,
+            None => return TourStep::Finished,
+        };
+        self.elapsed += dt;
+        if self.elapsed < dwell {
+            return TourStep::Stay;
+        }
+
+        self.elapsed = 0.0;
+        self.current += 1;
+        if self.current < self.stops.len() {
+            TourStep::Arrive(self.current)
+        } else {
+            TourStep::Finished
+        }
+    }
+
+    // Finished or cancelled, the time scale the tour replaced goes back
+    pub fn end(self) -> f32 {
+        self.previous_time_scale
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tour() -> Tour {
+        let stops = vec![(0, stop("Sun", 3600.0, None)), (3, stop("Earth", 86400.0, Some(0.8))), (5, stop("Mars", 86400.0, None))];
+        Tour::new(stops, 1.0)
+    }
+
+    #[test]
+    fn stops_follow_in_order_after_their_dwell() {
+        let mut tour = tour();
+        assert_eq!(tour.current_stop().map(|(index, _)| *index), Some(0));
+        // Seven and a half seconds of an eight second dwell
+        for _ in 0..15 {
+            assert!(matches!(tour.advance(0.5), TourStep::Stay));
+        }
+        assert!(matches!(tour.advance(0.5), TourStep::Arrive(1)));
+        assert_eq!(tour.current_stop().map(|(index, _)| *index), Some(3));
+
+        // A long frame moves on one stop at a time rather than skipping any
+        assert!(matches!(tour.advance(100.0), TourStep::Arrive(2)));
+        assert!(matches!(tour.advance(100.0), TourStep::Finished));
+        assert!(tour.current_stop().is_none());
+        assert!(matches!(tour.advance(1.0), TourStep::Finished));
+    }
+
+    #[test]
+    fn each_stop_dwells_from_its_own_arrival() {
+        let mut tour = tour();
+        tour.stops[1].1.dwell = 2.0;
+        assert!(matches!(tour.advance(8.0), TourStep::Arrive(1)));
+        assert!(matches!(tour.advance(1.5), TourStep::Stay));
+        assert!(matches!(tour.advance(0.5), TourStep::Arrive(2)));
+    }
+
+    #[test]
+    fn cancelling_mid_tour_restores_the_time_scale() {
+        let mut tour = Tour::new(tour().stops, 250.0);
+        assert!(matches!(tour.advance(8.0), TourStep::Arrive(1)));
+        assert_eq!(tour.end(), 250.0);
+    }
+
+    #[test]
+    fn stops_from_json_take_the_defaults() {
+        let stops: Vec<TourStop> = serde_json::from_str(r#"[{"body": "Io"}, {"body": "Titan", "dwell": 3, "time_scale": 60, "distance": 0.1}]"#).unwrap();
+        assert_eq!((stops[0].dwell, stops[0].time_scale, stops[0].distance), (8.0, 1.0, None));
+        assert_eq!((stops[1].dwell, stops[1].time_scale, stops[1].distance), (3.0, 60.0, Some(0.1)));
+    }
+}
@@ -0,0 +1,114 @@
+use crate::engine::audio::{Audio, SoundLoop};
+use web_sys::OscillatorType;
+
+// Orbital periods (days) mapped onto the pitch range, log scale, shorter is higher
+const SHORTEST_PERIOD_DAYS: f64 = 0.3;
+const LONGEST_PERIOD_DAYS: f64 = 100000.0;
+const HIGHEST_PITCH_HZ: f32 = 880.0;
+const LOWEST_PITCH_HZ: f32 = 110.0;
+const DRONE_VOLUME: f32 = 0.05;
+const PULSE_VOLUME: f32 = 0.3;
+const PULSE_SECONDS: f64 = 0.35;
+
+struct Voice {
+    body: usize,
+    pitch: f32,
+    // Held only so the tone keeps playing; dropping the voice stops it
+    _tone: SoundLoop,
+}
+
+// A soft held tone per voiced body plus a pulse each time it completes an orbit
+pub struct Sonification {
+    audio: Audio,
+    voices: Vec<Voice>,
+}
+
+impl Sonification {
+    // None when the browser refuses a context
+    pub fn new() -> Option<Self> {
+        Some(Sonification { audio: Audio::new()?, voices: Vec::new() })
+    }
+
+    // Replaces the voices with (body index, orbital period in days) pairs
+    pub fn retune(&mut self, bodies: &[(usize, f64)]) {
+        self.voices.clear();
+        for &(body, period_days) in bodies {
+            let pitch = orbit_pitch_hz(period_days);
+            if let Some(tone) = self.audio.tone_loop(OscillatorType::Sine, pitch) {
+                self.audio.set_loop_volume(&tone, DRONE_VOLUME);
+                self.voices.push(Voice { body, pitch, _tone: tone });
+            }
+        }
+    }
+
+    // completed[i] is how many orbits body i finished this frame
+    pub fn pulse(&self, completed: &[u32]) {
+        for voice in &self.voices {
+            if completed.get(voice.body).copied().unwrap_or(0) > 0 {
+                self.audio.blip(OscillatorType::Triangle, voice.pitch * 2.0, PULSE_SECONDS, PULSE_VOLUME);
+            }
+        }
+    }
+}
+
+pub fn orbit_pitch_hz(period_days: f64) -> f32 {
+    let span = (LONGEST_PERIOD_DAYS / SHORTEST_PERIOD_DAYS).ln();
+    let t = ((period_days.max(SHORTEST_PERIOD_DAYS) / SHORTEST_PERIOD_DAYS).ln() / span).min(1.0) as f32;
+    HIGHEST_PITCH_HZ * (LOWEST_PITCH_HZ / HIGHEST_PITCH_HZ).powf(t)
+}
+
+// Full turns crossed going from angle to angle + delta, in either direction.
+// Counted from the delta so a large step at high time scales still reports every orbit.
+pub fn completed_cycles(angle: f32, delta: f32) -> u32 {
+    let tau = std::f32::consts::TAU;
+    let start = (angle / tau).floor();
+    let end = ((angle + delta) / tau).floor();
+    (end - start).abs() as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::TAU;
+
+    #[test]
+    fn one_wrap_is_one_cycle() {
+        assert_eq!(completed_cycles(TAU - 0.1, 0.2), 1);
+        assert_eq!(completed_cycles(1.0, 0.2), 0);
+    }
+
+    #[test]
+    fn large_steps_count_every_orbit() {
+        assert_eq!(completed_cycles(0.5, 10.0 * TAU), 10);
+        assert_eq!(completed_cycles(TAU - 0.1, 10.0 * TAU + 0.2), 11);
+    }
+
+    #[test]
+    fn retrograde_steps_count_too() {
+        assert_eq!(completed_cycles(0.1, -0.2), 1);
+        assert_eq!(completed_cycles(-0.5, -3.0 * TAU), 3);
+    }
+
+    #[test]
+    fn stepping_with_the_wrapped_angle_adds_up() {
+        // The way the Solar update advances an orbit: count, add, then wrap
+        for delta in [0.3f32, 2.5, 17.0, -4.0] {
+            let mut angle = 0.0f32;
+            let mut total = 0;
+            for _ in 0..200 {
+                total += completed_cycles(angle, delta);
+                angle = (angle + delta) % TAU;
+            }
+            let expected = (200.0 * delta.abs() / TAU).floor() as u32;
+            assert!(total.abs_diff(expected) <= 1, "delta {} counted {} of {}", delta, total, expected);
+        }
+    }
+
+    #[test]
+    fn pitch_falls_with_longer_periods_inside_the_range() {
+        assert_eq!(orbit_pitch_hz(0.01), HIGHEST_PITCH_HZ);
+        assert!((orbit_pitch_hz(1e9) - LOWEST_PITCH_HZ).abs() < 1e-3);
+        let (mercury, earth, neptune) = (orbit_pitch_hz(88.0), orbit_pitch_hz(365.25), orbit_pitch_hz(60190.0));
+        assert!(mercury > earth && earth > neptune);
+    }
+}
@@ -0,0 +1,57 @@
+use crate::game::block_updates::BlockPos;
+use crate::game::minecraft::BlockType;
+use serde::Deserialize;
+
+// Prefabs placed by the world generator. Layers run bottom to top, each one rows along z
+// of cells along x; null leaves whatever the terrain has there.
+const TEMPLATE_SOURCES: [&str; 2] = [
+    include_str!("../../assets/structures/ruined_hut.json"),
+    include_str!("../../assets/structures/well.json"),
+];
+
+#[derive(Deserialize)]
+pub struct Template {
+    // Cell (x, y, z) that lands on the chosen ground column at surface height
+    anchor: [i32; 3],
+    layers: Vec<Vec<Vec<Option<BlockType>>>>,
+}
+
+pub fn builtin_templates() -> Vec<Template> {
+    TEMPLATE_SOURCES.iter()
+        .filter_map(|source| match serde_json::from_str(source) {
+            Ok(template) => Some(template),
+            Err(e) => {
+                web_sys::console::error_1(&format!("Bad structure template: {}", e).into());
+                None
+            }
+        })
+        .collect()
+}
+
+fn rotate(dx: i32, dz: i32, quarter_turns: u32) -> (i32, i32) {
+    match quarter_turns % 4 {
+        0 => (dx, dz),
+        1 => (-dz, dx),
+        2 => (-dx, -dz),
+        _ => (dz, -dx),
+    }
+}
+
+impl Template {
+    // Every filled cell as an offset from the anchor, turned about the vertical axis
+    pub fn cells(&self, quarter_turns: u32) -> Vec<(BlockPos, BlockType)> {
+        let [ax, ay, az] = self.anchor;
+        let mut cells = Vec::new();
+        for (y, layer) in self.layers.iter().enumerate() {
+            for (z, row) in layer.iter().enumerate() {
+                for (x, cell) in row.iter().enumerate() {
+                    if let Some(block) = cell {
+                        let (dx, dz) = rotate(x as i32 - ax, z as i32 - az, quarter_turns);
+                        cells.push(((dx, y as i32 - ay, dz), *block));
+                    }
+                }
+            }
+        }
+        cells
+    }
+}
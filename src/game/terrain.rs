@@ -0,0 +1,833 @@
+use crate::game::block_registry;
+use crate::game::block_shapes::{self, ShapePart};
+use crate::game::block_updates::BlockMeta;
+use crate::game::minecraft::BlockType;
+use crate::game::minecraft_graphics::{GraphicsProfile, ShadowMode};
+use crate::game::structures::{self, Template};
+use nalgebra::Vector3;
+use std::collections::{HashMap, HashSet};
+
+pub const CHUNK_SIZE: i32 = 16;
+pub const CHUNK_HEIGHT: i32 = 64;
+pub const VIEW_RADIUS: i32 = 2;
+pub const DEFAULT_BUDGET_MS: f64 = 3.0;
+
+// Sun movement (radians) after which a chunk's baked lighting is redone
+const RELIGHT_ANGLE: f32 = 0.03;
+// Seconds a sun relight takes to blend from the chunk's old light to the new one
+const LIGHT_FADE_SECONDS: f32 = 0.75;
+// Light lost per solid block crowding an exposed block's top, and the darkest it gets
+const AO_STEP: f32 = 0.07;
+const AO_MIN: f32 = 0.5;
+// Columns this low become sandy basins without trees
+const SAND_LEVEL: i32 = 4;
+// Chance a chunk hosts a structure, and how uneven its footprint may be
+const STRUCTURE_CHANCE: f32 = 0.08;
+const STRUCTURE_FLAT_TOLERANCE: i32 = 1;
+
+// Each stage is one resumable unit of work, a chunk advances one stage per step
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ChunkStage {
+    Heightmap,
+    Fill,
+    Decorate,
+    Light,
+    Mesh,
+}
+
+struct ChunkJob {
+    coord: (i32, i32),
+    stage: ChunkStage,
+    heights: Vec<i32>,
+    lit: Vec<((i32, i32, i32), BlockType, f32)>,
+    // Sun relights wait behind generation and edits, and fade in instead of popping
+    background: bool,
+    // Sun bucket the Light stage baked
+    sun_bucket: i32,
+}
+
+// Old and new light of every instance in a group, in instance order
+struct LightFade {
+    progress: f32,
+    instances: HashMap<BlockType, Vec<(f32, f32)>>,
+    parts: HashMap<(BlockType, ShapePart), Vec<(f32, f32)>>,
+}
+
+// Per block type instance data ready for draw_instanced_mesh (pos3, scale, color3, light)
+pub struct ChunkMesh {
+    pub instances: HashMap<BlockType, Vec<f32>>,
+    // Same layout for partial blocks, one group per shape part drawn with that part's mesh
+    pub parts: HashMap<(BlockType, ShapePart), Vec<f32>>,
+    sun_bucket: i32,
+    lighting_version: u32,
+    fade: Option<LightFade>,
+}
+
+impl ChunkMesh {
+    fn light_at(&self) -> HashMap<(i32, i32, i32), f32> {
+        self.instances.values().chain(self.parts.values())
+            .flat_map(|data| data.chunks(8))
+            .map(|i| ((i[0] as i32, i[1] as i32, i[2] as i32), i[7]))
+            .collect()
+    }
+
+    fn advance_fade(&mut self, dt: f32) {
+        let fade = match &mut self.fade {
+            Some(fade) => fade,
+            None => return,
+        };
+        fade.progress = (fade.progress + dt.max(0.0) / LIGHT_FADE_SECONDS).min(1.0);
+        let t = fade.progress;
+        for (key, lights) in &fade.instances {
+            if let Some(data) = self.instances.get_mut(key) {
+                blend_lights(data, lights, t);
+            }
+        }
+        for (key, lights) in &fade.parts {
+            if let Some(data) = self.parts.get_mut(key) {
+                blend_lights(data, lights, t);
+            }
+        }
+        if t >= 1.0 {
+            self.fade = None;
+        }
+    }
+}
+
+fn blend_lights(data: &mut [f32], lights: &[(f32, f32)], t: f32) {
+    for (instance, (from, to)) in data.chunks_mut(8).zip(lights) {
+        // The last step lands exactly on the new light rather than a rounding error short
+        instance[7] = if t >= 1.0 { *to } else { from + (to - from) * t };
+    }
+}
+
+// Starts every instance at the light the old mesh showed for its position
+fn fade_from(old: &HashMap<(i32, i32, i32), f32>, data: &mut [f32]) -> Vec<(f32, f32)> {
+    data.chunks_mut(8).map(|instance| {
+        let to = instance[7];
+        let from = old.get(&(instance[0] as i32, instance[1] as i32, instance[2] as i32)).copied().unwrap_or(to);
+        instance[7] = from;
+        (from, to)
+    }).collect()
+}
+
+// Blocks a structure puts down, in world positions
+pub type PlacedBlocks = Vec<((i32, i32, i32), BlockType)>;
+// A chunk's changes since generation, None where a block was removed
+type ChunkEdits = HashMap<(i32, i32, i32), Option<BlockType>>;
+
+pub struct ChunkQueue {
+    seed: u32,
+    pub budget_ms: f64,
+    jobs: Vec<ChunkJob>,
+    generated: HashSet<(i32, i32)>,
+    pub meshes: HashMap<(i32, i32), ChunkMesh>,
+    templates: Vec<Template>,
+    // Structure blocks that spilled into chunks not generated yet, applied when they decorate
+    pending: HashMap<(i32, i32), PlacedBlocks>,
+    // Every block changed since generation, by chunk. Decorating puts them back over whatever
+    // generation places, which is how saved worlds and peer edits to missing chunks come back.
+    edits: HashMap<(i32, i32), ChunkEdits>,
+    pub view_radius: i32,
+    ambient_occlusion: bool,
+    shadows: ShadowMode,
+    // Bumped when the baked lighting rules change so every mesh counts as stale
+    lighting_version: u32,
+}
+
+impl ChunkQueue {
+    pub fn new(seed: u32) -> Self {
+        ChunkQueue {
+            seed,
+            budget_ms: DEFAULT_BUDGET_MS,
+            jobs: Vec::new(),
+            generated: HashSet::new(),
+            meshes: HashMap::new(),
+            templates: structures::builtin_templates(),
+            pending: HashMap::new(),
+            edits: HashMap::new(),
+            view_radius: VIEW_RADIUS,
+            ambient_occlusion: false,
+            shadows: ShadowMode::Cached,
+            lighting_version: 0,
+        }
+    }
+
+    // Meshes baked under the old rules keep drawing until their relight comes through,
+    // so switching tiers never blanks the world
+    pub fn apply_graphics(&mut self, profile: &GraphicsProfile) {
+        self.view_radius = profile.view_radius;
+        if profile.ambient_occlusion == self.ambient_occlusion && profile.shadows == self.shadows {
+            return;
+        }
+        self.ambient_occlusion = profile.ambient_occlusion;
+        self.shadows = profile.shadows;
+        self.lighting_version += 1;
+        for job in &mut self.jobs {
+            if job.stage == ChunkStage::Mesh {
+                job.stage = ChunkStage::Light;
+            }
+        }
+    }
+
+    pub fn chunk_of(x: i32, z: i32) -> (i32, i32) {
+        (x.div_euclid(CHUNK_SIZE), z.div_euclid(CHUNK_SIZE))
+    }
+
+    pub fn surface_height(&self, x: i32, z: i32) -> i32 {
+        terrain_height(self.seed, x, z)
+    }
+
+    pub fn is_generated(&self, coord: (i32, i32)) -> bool {
+        self.generated.contains(&coord)
+    }
+
+    // The caller writes the block itself when the chunk is already generated
+    pub fn record_edit(&mut self, pos: (i32, i32, i32), block: Option<BlockType>) {
+        let owner = Self::chunk_of(pos.0, pos.2);
+        self.edits.entry(owner).or_default().insert(pos, block);
+    }
+
+    pub fn edits(&self) -> Vec<((i32, i32, i32), Option<BlockType>)> {
+        self.edits.values().flat_map(|edits| edits.iter().map(|(pos, block)| (*pos, *block))).collect()
+    }
+
+    // Starts over from the seed with a saved world's edits, every chunk generates again
+    // and picks its edits up as it decorates
+    pub fn restore(&mut self, edits: impl IntoIterator<Item = ((i32, i32, i32), Option<BlockType>)>) {
+        self.jobs.clear();
+        self.meshes.clear();
+        self.pending.clear();
+        self.generated.clear();
+        self.edits.clear();
+        for (pos, block) in edits {
+            self.record_edit(pos, block);
+        }
+    }
+
+    // What generation will put on top of a column, ignoring trees
+    pub fn surface_block(&self, x: i32, z: i32) -> BlockType {
+        if self.surface_height(x, z) <= SAND_LEVEL { BlockType::Sand } else { BlockType::Grass }
+    }
+
+    // Lighting is baked for the sun snapped to a bucket, so every chunk relit for the same
+    // bucket agrees exactly whenever it got its turn
+    fn sun_bucket(&self, sun_angle: f32) -> i32 {
+        if self.shadows.bakes_sun() { (sun_angle / RELIGHT_ANGLE).round() as i32 } else { 0 }
+    }
+
+    // Queues missing chunks around the player, drops far meshes and relights stale ones.
+    // A new sun bucket queues every loaded chunk at once, at background priority.
+    pub fn request_around(&mut self, player_pos: Vector3<f32>, sun_angle: f32) {
+        let (pcx, pcz) = Self::chunk_of(player_pos.x.round() as i32, player_pos.z.round() as i32);
+
+        let radius = self.view_radius;
+        let bucket = self.sun_bucket(sun_angle);
+        for cx in (pcx - radius)..=(pcx + radius) {
+            for cz in (pcz - radius)..=(pcz + radius) {
+                let coord = (cx, cz);
+                if self.is_queued(coord) {
+                    continue;
+                }
+                if !self.generated.contains(&coord) {
+                    self.push_job(coord, ChunkStage::Heightmap, false);
+                } else {
+                    // Some(background) when the chunk needs lighting
+                    let relight = match self.meshes.get(&coord) {
+                        Some(mesh) => (mesh.lighting_version != self.lighting_version || mesh.sun_bucket != bucket).then_some(true),
+                        None => Some(false),
+                    };
+                    if let Some(background) = relight {
+                        self.push_job(coord, ChunkStage::Light, background);
+                    }
+                }
+            }
+        }
+
+        // Blocks stay in the world so edits survive, only the render data is released
+        self.meshes.retain(|(cx, cz), _| {
+            (cx - pcx).abs() <= radius + 1 && (cz - pcz).abs() <= radius + 1
+        });
+    }
+
+    // A block edit changes visibility and shadows in its chunk and possibly the neighbour
+    pub fn mark_dirty(&mut self, x: i32, z: i32) {
+        let (cx, cz) = Self::chunk_of(x, z);
+        let lx = x.rem_euclid(CHUNK_SIZE);
+        let lz = z.rem_euclid(CHUNK_SIZE);
+
+        let mut coords = vec![(cx, cz)];
+        if lx == 0 { coords.push((cx - 1, cz)); }
+        if lx == CHUNK_SIZE - 1 { coords.push((cx + 1, cz)); }
+        if lz == 0 { coords.push((cx, cz - 1)); }
+        if lz == CHUNK_SIZE - 1 { coords.push((cx, cz + 1)); }
+
+        for coord in coords {
+            if !self.generated.contains(&coord) {
+                continue;
+            }
+            match self.jobs.iter_mut().find(|job| job.coord == coord) {
+                Some(job) => {
+                    if job.stage == ChunkStage::Mesh {
+                        job.stage = ChunkStage::Light;
+                    }
+                    job.background = false;
+                }
+                None => self.push_job(coord, ChunkStage::Light, false),
+            }
+        }
+    }
+
+    // Runs stages until the budget is spent. The clock is only checked between stages,
+    // so a call overshoots by at most one stage.
+    pub fn process(&mut self, blocks: &mut HashMap<(i32, i32, i32), BlockType>, meta: &BlockMeta, player_pos: Vector3<f32>, forward: Vector3<f32>, sun_angle: f32, now: &dyn Fn() -> f64) {
+        let start = now();
+        while let Some(index) = self.next_job(player_pos, forward) {
+            self.run_stage(index, blocks, meta, sun_angle);
+            if now() - start >= self.budget_ms {
+                break;
+            }
+        }
+    }
+
+    // Moves every relit chunk's light fade on by dt seconds
+    pub fn advance_fades(&mut self, dt: f32) {
+        for mesh in self.meshes.values_mut() {
+            mesh.advance_fade(dt);
+        }
+    }
+
+    // Used at load time where a stall is acceptable
+    pub fn run_all(&mut self, blocks: &mut HashMap<(i32, i32, i32), BlockType>, meta: &BlockMeta, player_pos: Vector3<f32>, sun_angle: f32) {
+        while let Some(index) = self.next_job(player_pos, Vector3::x()) {
+            self.run_stage(index, blocks, meta, sun_angle);
+        }
+    }
+
+    fn is_queued(&self, coord: (i32, i32)) -> bool {
+        self.jobs.iter().any(|job| job.coord == coord)
+    }
+
+    fn push_job(&mut self, coord: (i32, i32), stage: ChunkStage, background: bool) {
+        self.jobs.push(ChunkJob { coord, stage, heights: Vec::new(), lit: Vec::new(), background, sun_bucket: 0 });
+    }
+
+    // Closest chunks first, with chunks in front of the camera pulled ahead. Background
+    // relights only run once nothing else is waiting.
+    fn next_job(&self, player_pos: Vector3<f32>, forward: Vector3<f32>) -> Option<usize> {
+        let flat_forward = Vector3::new(forward.x, 0.0, forward.z);
+        let flat_forward = if flat_forward.norm() > 0.0 { flat_forward.normalize() } else { flat_forward };
+
+        let score = |job: &ChunkJob| {
+            let center = Vector3::new(
+                ((job.coord.0 * CHUNK_SIZE) as f32) + CHUNK_SIZE as f32 / 2.0,
+                0.0,
+                ((job.coord.1 * CHUNK_SIZE) as f32) + CHUNK_SIZE as f32 / 2.0,
+            );
+            let to_chunk = center - Vector3::new(player_pos.x, 0.0, player_pos.z);
+            let dist = to_chunk.norm() / CHUNK_SIZE as f32;
+            let facing = if dist > 0.0 { flat_forward.dot(&to_chunk.normalize()) } else { 1.0 };
+            dist - facing * 0.75
+        };
+
+        self.jobs.iter().enumerate()
+            .min_by(|(_, a), (_, b)| (a.background, score(a)).partial_cmp(&(b.background, score(b))).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(index, _)| index)
+    }
+
+    fn run_stage(&mut self, index: usize, blocks: &mut HashMap<(i32, i32, i32), BlockType>, meta: &BlockMeta, sun_angle: f32) {
+        let seed = self.seed;
+        let bucket = self.sun_bucket(sun_angle);
+        let bucket_angle = bucket as f32 * RELIGHT_ANGLE;
+        let job = &mut self.jobs[index];
+        let base_x = job.coord.0 * CHUNK_SIZE;
+        let base_z = job.coord.1 * CHUNK_SIZE;
+        let mut spilled = Vec::new();
+
+        match job.stage {
+            ChunkStage::Heightmap => {
+                job.heights = (0..CHUNK_SIZE * CHUNK_SIZE)
+                    .map(|i| terrain_height(seed, base_x + i / CHUNK_SIZE, base_z + i % CHUNK_SIZE))
+                    .collect();
+                job.stage = ChunkStage::Fill;
+            }
+            ChunkStage::Fill => {
+                for lx in 0..CHUNK_SIZE {
+                    for lz in 0..CHUNK_SIZE {
+                        let (x, z) = (base_x + lx, base_z + lz);
+                        let h = job.heights[(lx * CHUNK_SIZE + lz) as usize];
+                        blocks.insert((x, 0, z), BlockType::Bedrock);
+                        let low = h <= SAND_LEVEL;
+                        for y in 1..h {
+                            let block = if y < h - 2 {
+                                BlockType::Stone
+                            } else if low {
+                                BlockType::Sand
+                            } else {
+                                BlockType::Dirt
+                            };
+                            blocks.insert((x, y, z), block);
+                        }
+                        blocks.insert((x, h, z), if low { BlockType::Sand } else { BlockType::Grass });
+                    }
+                }
+                job.stage = ChunkStage::Decorate;
+            }
+            ChunkStage::Decorate => {
+                // Trees keep a margin so their leaves never spill into another chunk
+                for lx in 2..CHUNK_SIZE - 2 {
+                    for lz in 2..CHUNK_SIZE - 2 {
+                        let (x, z) = (base_x + lx, base_z + lz);
+                        if hash_noise(seed ^ 0x7ee5, x, z) > 0.015 {
+                            continue;
+                        }
+                        let h = job.heights[(lx * CHUNK_SIZE + lz) as usize];
+                        if h <= SAND_LEVEL {
+                            continue;
+                        }
+                        place_tree(blocks, x, h + 1, z);
+                    }
+                }
+
+                // Blocks from another chunk's structure always go in last, so the result
+                // is the same whichever of the two chunks generates first
+                for (pos, block) in plan_structure(seed, &self.templates, job.coord).unwrap_or_default() {
+                    let owner = Self::chunk_of(pos.0, pos.2);
+                    if owner == job.coord {
+                        blocks.insert(pos, block);
+                    } else if self.generated.contains(&owner) {
+                        if self.edits.get(&owner).is_some_and(|edits| edits.contains_key(&pos)) {
+                            continue;
+                        }
+                        blocks.insert(pos, block);
+                        spilled.push(pos);
+                    } else {
+                        self.pending.entry(owner).or_default().push((pos, block));
+                    }
+                }
+                for (pos, block) in self.pending.remove(&job.coord).unwrap_or_default() {
+                    blocks.insert(pos, block);
+                }
+                for (&pos, &block) in self.edits.get(&job.coord).into_iter().flatten() {
+                    match block {
+                        Some(block) => { blocks.insert(pos, block); }
+                        None => { blocks.remove(&pos); }
+                    }
+                }
+                job.heights = Vec::new();
+                self.generated.insert(job.coord);
+                job.stage = ChunkStage::Light;
+            }
+            ChunkStage::Light => {
+                let light_dir = sun_direction(bucket_angle);
+                job.lit.clear();
+                job.sun_bucket = bucket;
+                for lx in 0..CHUNK_SIZE {
+                    for lz in 0..CHUNK_SIZE {
+                        let (x, z) = (base_x + lx, base_z + lz);
+                        for y in 0..CHUNK_HEIGHT {
+                            if let Some(block) = blocks.get(&(x, y, z)) {
+                                if is_exposed(blocks, x, y, z) {
+                                    // Glowing blocks ignore the sun
+                                    let mut light = if block.light_emission() > 0 || !self.shadows.bakes_sun() {
+                                        1.0
+                                    } else {
+                                        shadow_level(blocks, x, y, z, light_dir)
+                                    };
+                                    if self.ambient_occlusion && block.light_emission() == 0 {
+                                        light *= ambient_occlusion(blocks, x, y, z);
+                                    }
+                                    job.lit.push(((x, y, z), *block, light));
+                                }
+                            }
+                        }
+                    }
+                }
+                job.stage = ChunkStage::Mesh;
+            }
+            ChunkStage::Mesh => {
+                let mut instances: HashMap<BlockType, Vec<f32>> = HashMap::new();
+                let mut parts: HashMap<(BlockType, ShapePart), Vec<f32>> = HashMap::new();
+                for ((x, y, z), block, light) in job.lit.drain(..) {
+                    let instance = [
+                        x as f32, y as f32, z as f32, // Position
+                        1.0, // Scale
+                        1.0, 1.0, 1.0, // All blocks are textured, keep the color white
+                        light,
+                    ];
+                    if block_shapes::is_full_cube(block) {
+                        instances.entry(block).or_default().extend_from_slice(&instance);
+                    } else {
+                        // Fence arms look at the neighbors, which the edit already re-meshed
+                        for part in block_shapes::shape_parts(blocks, meta, (x, y, z), block) {
+                            parts.entry((block, part)).or_default().extend_from_slice(&instance);
+                        }
+                    }
+                }
+                let (coord, sun_bucket, background) = (job.coord, job.sun_bucket, job.background);
+                self.jobs.swap_remove(index);
+                let fade = match self.meshes.get(&coord) {
+                    Some(old) if background => {
+                        let old = old.light_at();
+                        Some(LightFade {
+                            progress: 0.0,
+                            instances: instances.iter_mut().map(|(key, data)| (*key, fade_from(&old, data))).collect(),
+                            parts: parts.iter_mut().map(|(key, data)| (*key, fade_from(&old, data))).collect(),
+                        })
+                    }
+                    _ => None,
+                };
+                self.meshes.insert(coord, ChunkMesh { instances, parts, sun_bucket, lighting_version: self.lighting_version, fade });
+            }
+        }
+
+        for (x, _, z) in spilled {
+            self.mark_dirty(x, z);
+        }
+    }
+}
+
+pub fn sun_direction(sun_angle: f32) -> Vector3<f32> {
+    Vector3::new(sun_angle.cos(), sun_angle.sin(), 0.0).normalize()
+}
+
+// Raycast toward the sun, leaves only partially block the light
+pub fn shadow_level(blocks: &HashMap<(i32, i32, i32), BlockType>, x: i32, y: i32, z: i32, light_dir: Vector3<f32>) -> f32 {
+    // Start slightly above the top face center to avoid self-shadowing from the block itself
+    // and to avoid shadowing from neighbor ground blocks when sun is low.
+    let mut ray_pos = Vector3::new(x as f32, y as f32 + 0.6, z as f32);
+    let step_size = 0.2;
+    let max_y = (CHUNK_HEIGHT / 2) as f32;
+
+    for _ in 0..100 {
+        ray_pos += light_dir * step_size;
+
+        let check_x = ray_pos.x.round() as i32;
+        let check_y = ray_pos.y.round() as i32;
+        let check_z = ray_pos.z.round() as i32;
+
+        // Ignore blocks in the same vertical column to prevent ugly self-shadowing on trees/walls
+        if check_x == x && check_z == z {
+            continue;
+        }
+
+        if let Some(block) = blocks.get(&(check_x, check_y, check_z)) {
+            return block_registry::with_def(*block, |def| def.shadow);
+        }
+
+        if ray_pos.y > max_y { break; }
+    }
+
+    1.0
+}
+
+// Full cubes around the space above a block, the corners its top face would share
+pub fn ambient_occlusion(blocks: &HashMap<(i32, i32, i32), BlockType>, x: i32, y: i32, z: i32) -> f32 {
+    let mut crowded = 0;
+    for dx in -1..=1 {
+        for dz in -1..=1 {
+            if (dx, dz) == (0, 0) {
+                continue;
+            }
+            if matches!(blocks.get(&(x + dx, y + 1, z + dz)), Some(block) if block_shapes::is_full_cube(*block)) {
+                crowded += 1;
+            }
+        }
+    }
+    (1.0 - AO_STEP * crowded as f32).max(AO_MIN)
+}
+
+fn is_exposed(blocks: &HashMap<(i32, i32, i32), BlockType>, x: i32, y: i32, z: i32) -> bool {
+    let neighbours = [(1, 0, 0), (-1, 0, 0), (0, 1, 0), (0, -1, 0), (0, 0, 1), (0, 0, -1)];
+    neighbours.iter().any(|(dx, dy, dz)| {
+        // Partial blocks never hide the faces behind them
+        match blocks.get(&(x + dx, y + dy, z + dz)) {
+            Some(block) => block.is_transparent() || !block_shapes::is_full_cube(*block),
+            None => true,
+        }
+    })
+}
+
+// Whether a chunk hosts a structure and which blocks it puts down, a pure function of the
+// seed and chunk so both sides of a chunk border agree on it. Structures sit on the highest
+// column of their footprint and skip uneven or sandy ground.
+pub fn plan_structure(seed: u32, templates: &[Template], (cx, cz): (i32, i32)) -> Option<PlacedBlocks> {
+    if templates.is_empty() || hash_noise(seed ^ 0x57c7, cx, cz) > STRUCTURE_CHANCE {
+        return None;
+    }
+    let pick = |salt: u32, n: usize| ((hash_noise(seed ^ salt, cx, cz) * n as f32) as usize).min(n - 1);
+    let template = &templates[pick(0x57c8, templates.len())];
+    let cells = template.cells(pick(0x57c9, 4) as u32);
+    let origin_x = cx * CHUNK_SIZE + pick(0x57ca, CHUNK_SIZE as usize) as i32;
+    let origin_z = cz * CHUNK_SIZE + pick(0x57cb, CHUNK_SIZE as usize) as i32;
+
+    let columns: HashSet<(i32, i32)> = cells.iter().map(|((dx, _, dz), _)| (origin_x + dx, origin_z + dz)).collect();
+    let heights: Vec<i32> = columns.iter().map(|(x, z)| terrain_height(seed, *x, *z)).collect();
+    let low = *heights.iter().min()?;
+    let high = *heights.iter().max()?;
+    if high - low > STRUCTURE_FLAT_TOLERANCE || low <= SAND_LEVEL {
+        return None;
+    }
+    Some(cells.into_iter().map(|((dx, dy, dz), block)| ((origin_x + dx, high + dy, origin_z + dz), block)).collect())
+}
+
+fn place_tree(blocks: &mut HashMap<(i32, i32, i32), BlockType>, x: i32, base_y: i32, z: i32) {
+    for y in base_y..base_y + 3 {
+        blocks.insert((x, y, z), BlockType::Wood);
+    }
+    for dx in -1..=1 {
+        for dz in -1..=1 {
+            for y in base_y + 2..base_y + 4 {
+                if dx == 0 && dz == 0 && y < base_y + 3 { continue; }
+                blocks.insert((x + dx, y, z + dz), BlockType::Leaves);
+            }
+        }
+    }
+}
+
+// Two octaves of smoothed value noise, heights stay well under CHUNK_HEIGHT
+fn terrain_height(seed: u32, x: i32, z: i32) -> i32 {
+    let broad = value_noise(seed, x as f32 / 24.0, z as f32 / 24.0);
+    let detail = value_noise(seed.wrapping_add(1013), x as f32 / 8.0, z as f32 / 8.0);
+    3 + (broad * 7.0 + detail * 2.0) as i32
+}
+
+fn value_noise(seed: u32, x: f32, z: f32) -> f32 {
+    let x0 = x.floor() as i32;
+    let z0 = z.floor() as i32;
+    let tx = x - x0 as f32;
+    let tz = z - z0 as f32;
+    let sx = tx * tx * (3.0 - 2.0 * tx);
+    let sz = tz * tz * (3.0 - 2.0 * tz);
+
+    let a = hash_noise(seed, x0, z0);
+    let b = hash_noise(seed, x0 + 1, z0);
+    let c = hash_noise(seed, x0, z0 + 1);
+    let d = hash_noise(seed, x0 + 1, z0 + 1);
+
+    let top = a + (b - a) * sx;
+    let bottom = c + (d - c) * sx;
+    top + (bottom - top) * sz
+}
+
+fn hash_noise(seed: u32, x: i32, z: i32) -> f32 {
+    let n = seed.wrapping_add((x as u32).wrapping_mul(374761393))
+        .wrapping_add((z as u32).wrapping_mul(668265263));
+    let n = n ^ (n >> 13);
+    let n = n.wrapping_mul(1274126177);
+    let n = n ^ (n >> 16);
+    (n % 10000) as f32 / 10000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::time::Instant;
+
+    #[test]
+    fn long_walk_stays_within_one_stage_of_budget() {
+        let mut chunks = ChunkQueue::new(7);
+        let mut blocks = HashMap::new();
+        let meta = HashMap::new();
+        let forward = Vector3::x();
+        let origin = Instant::now();
+        // Every clock reading is logged so each call's stage boundaries can be checked
+        let readings = RefCell::new(Vec::new());
+        let now = || {
+            let t = origin.elapsed().as_secs_f64() * 1000.0;
+            readings.borrow_mut().push(t);
+            t
+        };
+
+        for step in 0..400 {
+            let pos = Vector3::new(step as f32 * 0.5, 20.0, 8.0);
+            chunks.request_around(pos, 0.0);
+            readings.borrow_mut().clear();
+            chunks.process(&mut blocks, &meta, pos, forward, 0.0, &now);
+
+            let times = readings.borrow();
+            let start = times[0];
+            // Only the final stage may end past the budget; every earlier one must have
+            // finished inside it, otherwise the loop kept going after running out.
+            if times.len() > 2 {
+                let before_last = times[times.len() - 2];
+                assert!(
+                    before_last - start < chunks.budget_ms,
+                    "step {} ran a stage after the budget was spent ({:.2}ms)",
+                    step,
+                    before_last - start
+                );
+            }
+        }
+
+        let end = ChunkQueue::chunk_of((399.0 * 0.5) as i32, 8);
+        assert!(chunks.is_generated(end), "the chunk under the player should keep up with the walk");
+    }
+
+    // Generates just the given chunks, in order
+    fn generate(chunks: &mut ChunkQueue, blocks: &mut HashMap<(i32, i32, i32), BlockType>, coords: &[(i32, i32)]) {
+        for &coord in coords {
+            chunks.push_job(coord, ChunkStage::Heightmap, false);
+            chunks.run_all(blocks, &HashMap::new(), Vector3::zeros(), 0.0);
+        }
+    }
+
+    #[test]
+    fn structure_across_a_chunk_border_completes_when_the_neighbor_generates() {
+        let seed = 7;
+        let templates = structures::builtin_templates();
+        // The first structure whose footprint reaches into another chunk
+        let (coord, neighbor, plan) = (-40..40).flat_map(|cx| (-40..40).map(move |cz| (cx, cz)))
+            .find_map(|coord| {
+                let plan = plan_structure(seed, &templates, coord)?;
+                let neighbor = plan.iter()
+                    .map(|(pos, _)| ChunkQueue::chunk_of(pos.0, pos.2))
+                    .find(|owner| *owner != coord)?;
+                Some((coord, neighbor, plan))
+            })
+            .expect("some structure should cross a border");
+        let spilled: Vec<_> = plan.iter().filter(|(pos, _)| ChunkQueue::chunk_of(pos.0, pos.2) == neighbor).copied().collect();
+
+        let mut chunks = ChunkQueue::new(seed);
+        let mut blocks = HashMap::new();
+        generate(&mut chunks, &mut blocks, &[coord]);
+        // The spilled part waits for its chunk rather than being overwritten by its generation
+        assert!(spilled.iter().all(|(pos, _)| !blocks.contains_key(pos)));
+        assert_eq!(chunks.pending[&neighbor].len(), spilled.len());
+
+        generate(&mut chunks, &mut blocks, &[neighbor]);
+        assert!(chunks.pending.is_empty());
+        for (pos, block) in &plan {
+            assert_eq!(blocks.get(pos), Some(block), "{:?}", pos);
+        }
+
+        // Generating the neighbor first ends up the same
+        let mut other = ChunkQueue::new(seed);
+        let mut other_blocks = HashMap::new();
+        generate(&mut other, &mut other_blocks, &[neighbor, coord]);
+        for (pos, block) in &plan {
+            assert_eq!(other_blocks.get(pos), Some(block), "{:?}", pos);
+        }
+    }
+
+    type LitWorld = (ChunkQueue, HashMap<(i32, i32, i32), BlockType>, BlockMeta, Vector3<f32>);
+
+    // A 3×3 patch of chunks around the origin, generated and lit for the given sun
+    fn lit_world(sun: f32) -> LitWorld {
+        let mut chunks = ChunkQueue::new(7);
+        chunks.view_radius = 1;
+        let mut blocks = HashMap::new();
+        let meta = HashMap::new();
+        let pos = Vector3::new(8.0, 20.0, 8.0);
+        chunks.request_around(pos, sun);
+        chunks.run_all(&mut blocks, &meta, pos, sun);
+        (chunks, blocks, meta, pos)
+    }
+
+    #[test]
+    fn sun_relights_yield_to_generation_and_edits() {
+        let (mut chunks, mut blocks, meta, pos) = lit_world(0.0);
+        let sun = 10.0 * RELIGHT_ANGLE;
+        chunks.request_around(pos, sun);
+        assert!(chunks.jobs.iter().all(|job| job.background));
+        // An edit promotes its chunk's relight, and a step east needs a new column generated
+        chunks.mark_dirty(3, 3);
+        let ahead = pos + Vector3::new(CHUNK_SIZE as f32, 0.0, 0.0);
+        chunks.request_around(ahead, sun);
+
+        // Every reading moves the clock a millisecond, so a call fits about three stages
+        let clock = std::cell::Cell::new(0.0);
+        let now = || {
+            clock.set(clock.get() + 1.0);
+            clock.get()
+        };
+        let mut calls = 0;
+        while chunks.jobs.iter().any(|job| !job.background) {
+            let relights = chunks.jobs.iter().filter(|job| job.background).count();
+            chunks.process(&mut blocks, &meta, ahead, Vector3::x(), sun, &now);
+            calls += 1;
+            if chunks.jobs.iter().any(|job| !job.background) {
+                assert!(chunks.jobs.iter().filter(|job| job.background).all(|job| job.stage == ChunkStage::Light));
+                assert_eq!(chunks.jobs.iter().filter(|job| job.background).count(), relights);
+            }
+        }
+        // Spread over several frames rather than done in one
+        assert!(calls > 3, "finished in {} calls", calls);
+        assert!(chunks.meshes[&(0, 0)].fade.is_none(), "an edited chunk swaps its light at once");
+        assert!(chunks.jobs.iter().any(|job| job.background));
+    }
+
+    // The light every relit instance is fading toward, by position
+    fn fade_targets(mesh: &ChunkMesh) -> HashMap<(i32, i32, i32), f32> {
+        let fade = mesh.fade.as_ref().unwrap();
+        let instances = fade.instances.iter().flat_map(|(key, lights)| mesh.instances[key].chunks(8).zip(lights));
+        let parts = fade.parts.iter().flat_map(|(key, lights)| mesh.parts[key].chunks(8).zip(lights));
+        instances.chain(parts)
+            .map(|(instance, (_, to))| ((instance[0] as i32, instance[1] as i32, instance[2] as i32), *to))
+            .collect()
+    }
+
+    #[test]
+    fn relit_chunks_fade_to_the_new_light_at_any_frame_rate() {
+        let sun = 10.0 * RELIGHT_ANGLE;
+        for hz in [30.0, 60.0, 144.0] {
+            let (mut chunks, mut blocks, meta, pos) = lit_world(0.0);
+            chunks.request_around(pos, sun);
+            chunks.run_all(&mut blocks, &meta, pos, sun);
+            let targets: HashMap<(i32, i32), _> = chunks.meshes.iter().map(|(coord, mesh)| (*coord, fade_targets(mesh))).collect();
+            assert!(chunks.meshes.iter().any(|(coord, mesh)| mesh.light_at() != targets[coord]), "the new sun should change something");
+
+            // Halfway through, every frame rate has come the same distance
+            let frames = (LIGHT_FADE_SECONDS * hz / 2.0).round() as usize;
+            for _ in 0..frames {
+                chunks.advance_fades(1.0 / hz);
+            }
+            let progress = chunks.meshes[&(0, 0)].fade.as_ref().unwrap().progress;
+            assert!((progress - 0.5).abs() < 0.05, "{} at {} Hz", progress, hz);
+
+            for _ in 0..frames + 1 {
+                chunks.advance_fades(1.0 / hz);
+            }
+            for (coord, mesh) in &chunks.meshes {
+                assert!(mesh.fade.is_none(), "chunk {:?} still fading at {} Hz", coord, hz);
+                assert!(mesh.light_at() == targets[coord], "chunk {:?} missed its new light at {} Hz", coord, hz);
+            }
+        }
+    }
+
+    // The whole patch relights for the new sun a few chunks a frame, no frame running a
+    // stage once its budget is spent, until every chunk has the new bucket
+    #[test]
+    fn sun_relight_stays_in_budget_and_converges() {
+        let (mut chunks, mut blocks, meta, pos) = lit_world(0.0);
+        let sun = 10.0 * RELIGHT_ANGLE;
+        chunks.request_around(pos, sun);
+        let relights = chunks.jobs.len();
+        assert!(relights > 1);
+
+        // Each stage costs a millisecond of the fake clock
+        let readings = RefCell::new(Vec::new());
+        let now = || {
+            let t = readings.borrow().len() as f64;
+            readings.borrow_mut().push(t);
+            t
+        };
+        let mut frames = 0;
+        while !chunks.jobs.is_empty() {
+            readings.borrow_mut().clear();
+            chunks.process(&mut blocks, &meta, pos, Vector3::x(), sun, &now);
+            frames += 1;
+            let times = readings.borrow();
+            let before_last = times[times.len() - 2];
+            assert!(before_last - times[0] < chunks.budget_ms, "frame {} ran a stage past its budget", frames);
+            assert!(frames < 1000, "the relight never finished");
+        }
+
+        assert!(frames > 1, "all {} relights ran in one frame", relights);
+        let bucket = chunks.sun_bucket(sun);
+        for (coord, mesh) in &chunks.meshes {
+            assert_eq!(mesh.sun_bucket, bucket, "chunk {:?} kept its old light", coord);
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use crate::engine::renderer::Renderer;
+
+// Smallest button edge in CSS pixels, the usual minimum comfortable touch target
+const MIN_TARGET_CSS: f32 = 48.0;
+const PRESS_FLASH_MS: f64 = 100.0;
+const BUTTON_ALPHA: f32 = 0.35;
+const PRESSED_ALPHA: f32 = 0.7;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TouchButton {
+    Left,
+    Right,
+    Forward,
+    Restart,
+}
+
+// Canvas pixels, origin top-left like the rest of the screen overlay
+#[derive(Clone, Copy, Debug)]
+pub struct ButtonRect {
+    pub button: TouchButton,
+    pub x: f32,
+    pub y: f32,
+    pub size: f32,
+}
+
+impl ButtonRect {
+    fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.x && x <= self.x + self.size && y >= self.y && y <= self.y + self.size
+    }
+}
+
+// Portrait puts the three arrows in a row along the bottom with forward raised in the
+// middle. Landscape splits them between the thumbs: left and right bottom-left,
+// forward bottom-right. Restart sits in the top-right corner either way.
+pub fn layout(width: f32, height: f32, dpr: f32) -> [ButtonRect; 4] {
+    let portrait = height >= width;
+    let size = (width.min(height) * if portrait { 0.2 } else { 0.16 }).max(MIN_TARGET_CSS * dpr);
+    let gap = size * 0.25;
+    let bottom = height - gap - size;
+    let rect = |button, x, y| ButtonRect { button, x, y, size };
+    let restart = rect(TouchButton::Restart, width - gap - size, gap);
+    if portrait {
+        let center = (width - size) / 2.0;
+        [
+            rect(TouchButton::Left, center - size - gap, bottom),
+            rect(TouchButton::Forward, center, bottom - size / 2.0),
+            rect(TouchButton::Right, center + size + gap, bottom),
+            restart,
+        ]
+    } else {
+        [
+            rect(TouchButton::Left, gap, bottom),
+            rect(TouchButton::Right, gap * 2.0 + size, bottom),
+            rect(TouchButton::Forward, width - gap - size, bottom),
+            restart,
+        ]
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Swipe {
+    Forward,
+    Back,
+    Left,
+    Right,
+}
+
+// Dominant direction of a finger's travel, screen y grows downward. Anything shorter than
+// the threshold is a tap, which hops forward like the space bar.
+pub fn classify_swipe(dx: f32, dy: f32, threshold: f32) -> Swipe {
+    if dx.hypot(dy) < threshold {
+        return Swipe::Forward;
+    }
+    if dx.abs() > dy.abs() {
+        if dx < 0.0 { Swipe::Left } else { Swipe::Right }
+    } else if dy < 0.0 {
+        Swipe::Forward
+    } else {
+        Swipe::Back
+    }
+}
+
+#[derive(Default)]
+pub struct TouchOverlay {
+    // Button lit up and when it was pressed, in performance.now() milliseconds
+    pressed: Option<(TouchButton, f64)>,
+}
+
+impl TouchOverlay {
+    // Coordinates in canvas pixels. A miss returns None so the touch can go elsewhere.
+    pub fn hit(&mut self, x: f32, y: f32, width: f32, height: f32, dpr: f32, now: f64) -> Option<TouchButton> {
+        let button = layout(width, height, dpr).into_iter().find(|rect| rect.contains(x, y))?.button;
+        self.pressed = Some((button, now));
+        Some(button)
+    }
+
+    pub fn draw(&self, renderer: &Renderer, width: f32, height: f32, dpr: f32, now: f64) {
+        let lit = self.pressed.filter(|(_, at)| now - at < PRESS_FLASH_MS).map(|(button, _)| button);
+        renderer.begin_screen_overlay();
+        renderer.enable_blend();
+        for rect in layout(width, height, dpr) {
+            let ButtonRect { button, x, y, size } = rect;
+            renderer.set_alpha(if lit == Some(button) { PRESSED_ALPHA } else { BUTTON_ALPHA });
+            renderer.draw_screen_rect(x, y, size, size, (0.1, 0.1, 0.1), (width, height));
+            renderer.set_alpha(0.9);
+            let (cx, cy, arm) = (x + size / 2.0, y + size / 2.0, size * 0.25);
+            let arrow = match button {
+                TouchButton::Left => Some([cx - arm, cy, cx + arm, cy - arm, cx + arm, cy + arm]),
+                TouchButton::Right => Some([cx + arm, cy, cx - arm, cy - arm, cx - arm, cy + arm]),
+                TouchButton::Forward => Some([cx, cy - arm, cx - arm, cy + arm, cx + arm, cy + arm]),
+                TouchButton::Restart => None,
+            };
+            match arrow {
+                Some(points) => renderer.draw_screen_polygon(&points, (1.0, 1.0, 1.0), (width, height)),
+                None => {
+                    let text_size = size * 0.4;
+                    let text_x = cx - Renderer::screen_text_width("GO", text_size) / 2.0;
+                    renderer.draw_screen_text("GO", text_x, cy - text_size / 2.0, text_size, (1.0, 1.0, 1.0), (width, height));
+                }
+            }
+        }
+        renderer.set_alpha(1.0);
+        renderer.end_screen_overlay();
+    }
+}
@@ -0,0 +1,194 @@
+use crate::engine::renderer::Renderer;
+use crate::game::minecraft::BlockType;
+use crate::game::terrain::{ChunkQueue, CHUNK_HEIGHT, CHUNK_SIZE, VIEW_RADIUS};
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use web_sys::WebGlTexture;
+
+// Chunks this close to the player are revealed for good
+const REVEAL_RADIUS: i32 = VIEW_RADIUS + 1;
+// The texture covers a window of chunks centered on the player's chunk
+const WINDOW_CHUNKS: i32 = 8;
+const WINDOW_PIXELS: i32 = WINDOW_CHUNKS * CHUNK_SIZE;
+const TILE_BYTES: usize = (CHUNK_SIZE * CHUNK_SIZE * 4) as usize;
+
+// Top-down map, one pixel per column. Only the revealed set is persisted,
+// tiles are rebuilt from the world on demand.
+#[derive(Serialize, Deserialize, Default)]
+pub struct WorldMap {
+    revealed: HashSet<(i32, i32)>,
+    #[serde(skip)]
+    tiles: HashMap<(i32, i32), Vec<u8>>,
+    // Tiles guessed from the heightmap, redone from real blocks once the chunk generates
+    #[serde(skip)]
+    estimated: HashSet<(i32, i32)>,
+    #[serde(skip)]
+    stale: HashSet<(i32, i32)>,
+    #[serde(skip)]
+    dirty: HashSet<(i32, i32)>,
+    #[serde(skip)]
+    texture: Option<WebGlTexture>,
+    #[serde(skip)]
+    window_origin: Option<(i32, i32)>,
+    #[serde(skip)]
+    pub visible: bool,
+}
+
+impl WorldMap {
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+        // Catch up on everything that changed while hidden
+        self.window_origin = None;
+    }
+
+    // The texture died with the GL context, the next update uploads the whole window again
+    pub fn forget_texture(&mut self) {
+        self.texture = None;
+        self.window_origin = None;
+    }
+
+    // A block edit can change the top of its column
+    pub fn mark_edited(&mut self, x: i32, z: i32) {
+        let coord = ChunkQueue::chunk_of(x, z);
+        if self.revealed.contains(&coord) {
+            self.stale.insert(coord);
+        }
+    }
+
+    pub fn update(&mut self, renderer: &Renderer, blocks: &HashMap<(i32, i32, i32), BlockType>, chunks: &ChunkQueue, player_pos: Vector3<f32>) {
+        let (pcx, pcz) = ChunkQueue::chunk_of(player_pos.x.round() as i32, player_pos.z.round() as i32);
+
+        for cx in (pcx - REVEAL_RADIUS)..=(pcx + REVEAL_RADIUS) {
+            for cz in (pcz - REVEAL_RADIUS)..=(pcz + REVEAL_RADIUS) {
+                let coord = (cx, cz);
+                let newly = self.revealed.insert(coord);
+                if newly || (self.estimated.contains(&coord) && chunks.is_generated(coord)) {
+                    self.stale.insert(coord);
+                }
+            }
+        }
+
+        for coord in std::mem::take(&mut self.stale) {
+            self.build_tile(coord, blocks, chunks);
+        }
+
+        if !self.visible {
+            return;
+        }
+
+        let origin = (pcx - WINDOW_CHUNKS / 2, pcz - WINDOW_CHUNKS / 2);
+        if self.texture.is_none() || self.window_origin != Some(origin) {
+            self.upload_window(renderer, origin, blocks, chunks);
+            return;
+        }
+
+        for coord in std::mem::take(&mut self.dirty) {
+            let (tx, tz) = (coord.0 - origin.0, coord.1 - origin.1);
+            if tx < 0 || tz < 0 || tx >= WINDOW_CHUNKS || tz >= WINDOW_CHUNKS {
+                continue;
+            }
+            if let (Some(texture), Some(tile)) = (&self.texture, self.tiles.get(&coord)) {
+                renderer.update_texture_region(texture, tx * CHUNK_SIZE, tz * CHUNK_SIZE, CHUNK_SIZE, CHUNK_SIZE, tile).ok();
+            }
+        }
+    }
+
+    fn upload_window(&mut self, renderer: &Renderer, origin: (i32, i32), blocks: &HashMap<(i32, i32, i32), BlockType>, chunks: &ChunkQueue) {
+        // Unrevealed pixels stay fully transparent, the shader discards them
+        let mut pixels = vec![0u8; (WINDOW_PIXELS * WINDOW_PIXELS * 4) as usize];
+        for tz in 0..WINDOW_CHUNKS {
+            for tx in 0..WINDOW_CHUNKS {
+                let coord = (origin.0 + tx, origin.1 + tz);
+                // Revealed in an earlier session but not generated in this one
+                if self.revealed.contains(&coord) && !self.tiles.contains_key(&coord) {
+                    self.build_tile(coord, blocks, chunks);
+                }
+                let tile = match self.tiles.get(&coord) {
+                    Some(tile) => tile,
+                    None => continue,
+                };
+                for row in 0..CHUNK_SIZE {
+                    let src = (row * CHUNK_SIZE * 4) as usize;
+                    let dst = (((tz * CHUNK_SIZE + row) * WINDOW_PIXELS + tx * CHUNK_SIZE) * 4) as usize;
+                    pixels[dst..dst + (CHUNK_SIZE * 4) as usize].copy_from_slice(&tile[src..src + (CHUNK_SIZE * 4) as usize]);
+                }
+            }
+        }
+
+        let uploaded = match &self.texture {
+            Some(texture) => renderer.update_texture_region(texture, 0, 0, WINDOW_PIXELS, WINDOW_PIXELS, &pixels).is_ok(),
+            None => {
+                self.texture = renderer.create_texture_from_rgba(WINDOW_PIXELS, WINDOW_PIXELS, &pixels).ok();
+                self.texture.is_some()
+            }
+        };
+        if uploaded {
+            self.window_origin = Some(origin);
+            self.dirty.clear();
+        }
+    }
+
+    // Rows run along +z, columns along +x
+    fn build_tile(&mut self, coord: (i32, i32), blocks: &HashMap<(i32, i32, i32), BlockType>, chunks: &ChunkQueue) {
+        let generated = chunks.is_generated(coord);
+        let mut tile = vec![0u8; TILE_BYTES];
+        for lz in 0..CHUNK_SIZE {
+            for lx in 0..CHUNK_SIZE {
+                let (x, z) = (coord.0 * CHUNK_SIZE + lx, coord.1 * CHUNK_SIZE + lz);
+                let top = if generated {
+                    (0..CHUNK_HEIGHT).rev().find_map(|y| blocks.get(&(x, y, z)).map(|block| (*block, y)))
+                } else {
+                    Some((chunks.surface_block(x, z), chunks.surface_height(x, z)))
+                };
+                if let Some((block, y)) = top {
+                    let shade = (0.55 + y as f32 / 40.0).clamp(0.5, 1.2);
+                    let (r, g, b) = block.color();
+                    let i = ((lz * CHUNK_SIZE + lx) * 4) as usize;
+                    tile[i] = ((r * shade).min(1.0) * 255.0) as u8;
+                    tile[i + 1] = ((g * shade).min(1.0) * 255.0) as u8;
+                    tile[i + 2] = ((b * shade).min(1.0) * 255.0) as u8;
+                    tile[i + 3] = 255;
+                }
+            }
+        }
+
+        if generated {
+            self.estimated.remove(&coord);
+        } else {
+            self.estimated.insert(coord);
+        }
+        self.tiles.insert(coord, tile);
+        self.dirty.insert(coord);
+    }
+
+    // Top-right corner panel with an arrow for the player, yaw 0 points along +x
+    pub fn draw(&self, renderer: &Renderer, player_pos: Vector3<f32>, yaw: f32, width: f32, height: f32) {
+        let (texture, origin) = match (&self.texture, self.window_origin) {
+            (Some(texture), Some(origin)) if self.visible => (texture, origin),
+            _ => return,
+        };
+
+        let dpr = web_sys::window().map(|w| w.device_pixel_ratio()).unwrap_or(1.0) as f32;
+        let scale = 1.5 * dpr;
+        let size = WINDOW_PIXELS as f32 * scale;
+        let margin = 12.0 * dpr;
+        let (left, top) = (width - size - margin, margin);
+
+        renderer.begin_screen_overlay();
+        renderer.draw_screen_rect(left - 2.0 * dpr, top - 2.0 * dpr, size + 4.0 * dpr, size + 4.0 * dpr, (0.08, 0.08, 0.1), (width, height));
+        renderer.draw_screen_texture(texture, left, top, size, size, (width, height));
+
+        let px = left + (player_pos.x + 0.5 - (origin.0 * CHUNK_SIZE) as f32) * scale;
+        let py = top + (player_pos.z + 0.5 - (origin.1 * CHUNK_SIZE) as f32) * scale;
+        let (dx, dy) = (yaw.cos(), yaw.sin());
+        let arrow = 7.0 * dpr;
+        renderer.draw_screen_polygon(&[
+            px + dx * arrow, py + dy * arrow,
+            px - dx * arrow * 0.6 - dy * arrow * 0.6, py - dy * arrow * 0.6 + dx * arrow * 0.6,
+            px - dx * arrow * 0.3, py - dy * arrow * 0.3,
+            px - dx * arrow * 0.6 + dy * arrow * 0.6, py - dy * arrow * 0.6 - dx * arrow * 0.6,
+        ], (1.0, 0.2, 0.2), (width, height));
+        renderer.end_screen_overlay();
+    }
+}
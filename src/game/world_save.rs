@@ -0,0 +1,230 @@
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::JsValue;
+
+// Each slot is two entries: a small metadata blob the world list reads, and the world
+// itself as base64 of DEFLATE-compressed JSON, only inflated when the world loads
+const META_PREFIX: &str = "minigame_world_meta:";
+const DATA_PREFIX: &str = "minigame_world_data:";
+const MAX_NAME_CHARS: usize = 48;
+const COMPRESSION_LEVEL: u8 = 6;
+// Smallest border half-width, anything tighter leaves no room around spawn
+const MIN_WORLD_BORDER: i32 = 16;
+
+// Flags a world carries with it. While it's loaded they replace whatever the global
+// setters last chose.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorldOptions {
+    pub creative: bool,
+    pub keep_inventory: bool,
+    // Half-width in blocks of the square the player is kept inside, centred on the origin
+    pub world_border: Option<i32>,
+    pub fluids_enabled: bool,
+}
+
+impl Default for WorldOptions {
+    fn default() -> Self {
+        WorldOptions { creative: false, keep_inventory: true, world_border: None, fluids_enabled: false }
+    }
+}
+
+impl WorldOptions {
+    pub fn parse(json: &str) -> Result<Self, String> {
+        if json.trim().is_empty() {
+            return Ok(Self::default());
+        }
+        let options: WorldOptions = serde_json::from_str(json).map_err(|e| format!("Invalid world options: {}", e))?;
+        if let Some(border) = options.world_border {
+            if border < MIN_WORLD_BORDER {
+                return Err(format!("Invalid world options: world_border must be at least {}", MIN_WORLD_BORDER));
+            }
+        }
+        Ok(options)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct WorldMeta {
+    pub name: String,
+    pub seed: u32,
+    // Milliseconds since the epoch
+    pub last_played: f64,
+    pub play_seconds: f64,
+    pub options: WorldOptions,
+}
+
+impl WorldMeta {
+    pub fn new(name: &str, seed: u32, options: WorldOptions) -> Self {
+        WorldMeta { name: name.to_string(), seed, last_played: js_sys::Date::now(), play_seconds: 0.0, options }
+    }
+}
+
+pub fn validate_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("World name can't be empty".to_string());
+    }
+    if name.chars().count() > MAX_NAME_CHARS {
+        return Err(format!("World name is longer than {} characters", MAX_NAME_CHARS));
+    }
+    Ok(())
+}
+
+// Most recently played first
+pub fn list() -> Vec<WorldMeta> {
+    let storage = match local_storage() {
+        Some(storage) => storage,
+        None => return Vec::new(),
+    };
+    let count = storage.length().unwrap_or(0);
+    let entries = (0..count)
+        .filter_map(|i| storage.key(i).ok().flatten())
+        .filter(|key| key.starts_with(META_PREFIX))
+        .filter_map(|key| storage.get_item(&key).ok().flatten().map(|json| (key, json)));
+    collect_worlds(entries)
+}
+
+// The metadata among storage entries, damaged ones skipped
+fn collect_worlds(entries: impl Iterator<Item = (String, String)>) -> Vec<WorldMeta> {
+    let mut worlds: Vec<WorldMeta> = entries
+        .filter(|(key, _)| key.starts_with(META_PREFIX))
+        .filter_map(|(_, json)| serde_json::from_str(&json).ok())
+        .collect();
+    worlds.sort_by(|a, b| b.last_played.total_cmp(&a.last_played));
+    worlds
+}
+
+pub fn exists(name: &str) -> bool {
+    local_storage()
+        .and_then(|storage| storage.get_item(&format!("{}{}", META_PREFIX, name)).ok().flatten())
+        .is_some()
+}
+
+pub fn encode(bytes: &[u8]) -> String {
+    BASE64.encode(miniz_oxide::deflate::compress_to_vec(bytes, COMPRESSION_LEVEL))
+}
+
+pub fn decode(blob: &str) -> Result<Vec<u8>, String> {
+    let compressed = BASE64.decode(blob.trim()).map_err(|e| format!("not base64: {}", e))?;
+    miniz_oxide::inflate::decompress_to_vec(&compressed).map_err(|e| format!("bad compressed data: {:?}", e))
+}
+
+// The data goes in first so a refused write never leaves metadata pointing at nothing
+pub fn write(meta: &WorldMeta, state: &[u8]) -> Result<(), String> {
+    let storage = local_storage().ok_or("Saving isn't available, localStorage is blocked")?;
+    let blob = encode(state);
+    let meta_json = serde_json::to_string(meta).map_err(|e| e.to_string())?;
+    storage.set_item(&format!("{}{}", DATA_PREFIX, meta.name), &blob)
+        .map_err(|e| storage_error(&meta.name, blob.len(), &e))?;
+    storage.set_item(&format!("{}{}", META_PREFIX, meta.name), &meta_json)
+        .map_err(|e| storage_error(&meta.name, blob.len() + meta_json.len(), &e))
+}
+
+pub fn read(name: &str) -> Result<(WorldMeta, Vec<u8>), String> {
+    let storage = local_storage().ok_or("Loading isn't available, localStorage is blocked")?;
+    let meta_json = storage.get_item(&format!("{}{}", META_PREFIX, name)).ok().flatten()
+        .ok_or_else(|| format!("No world named \"{}\"", name))?;
+    let meta: WorldMeta = serde_json::from_str(&meta_json)
+        .map_err(|e| format!("World \"{}\" has damaged metadata: {}", name, e))?;
+    let blob = storage.get_item(&format!("{}{}", DATA_PREFIX, name)).ok().flatten()
+        .ok_or_else(|| format!("World \"{}\" has no saved data", name))?;
+    let state = decode(&blob).map_err(|e| format!("World \"{}\" is damaged: {}", name, e))?;
+    Ok((meta, state))
+}
+
+pub fn delete(name: &str) -> Result<(), String> {
+    if !exists(name) {
+        return Err(format!("No world named \"{}\"", name));
+    }
+    let storage = local_storage().ok_or("localStorage is blocked")?;
+    storage.remove_item(&format!("{}{}", DATA_PREFIX, name)).ok();
+    storage.remove_item(&format!("{}{}", META_PREFIX, name)).ok();
+    Ok(())
+}
+
+fn storage_error(name: &str, bytes: usize, error: &JsValue) -> String {
+    let kind = js_sys::Reflect::get(error, &JsValue::from_str("name")).ok().and_then(|n| n.as_string());
+    match kind.as_deref() {
+        Some("QuotaExceededError") | Some("NS_ERROR_DOM_QUOTA_REACHED") => {
+            format!("Not enough storage to save world \"{}\" ({} bytes)", name, bytes)
+        }
+        _ => format!("Saving world \"{}\" ({} bytes) failed: {:?}", name, bytes, error),
+    }
+}
+
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok().flatten()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::minecraft::BlockType;
+    use crate::game::terrain::ChunkQueue;
+    use nalgebra::Vector3;
+    use std::collections::HashMap;
+
+    type Edits = Vec<((i32, i32, i32), Option<BlockType>)>;
+
+    fn generate(chunks: &mut ChunkQueue) -> HashMap<(i32, i32, i32), BlockType> {
+        let mut blocks = HashMap::new();
+        let pos = Vector3::new(0.0, 20.0, 0.0);
+        chunks.request_around(pos, 0.0);
+        chunks.run_all(&mut blocks, &HashMap::new(), pos, 0.0);
+        blocks
+    }
+
+    #[test]
+    fn edited_world_round_trips_through_its_save() {
+        let mut chunks = ChunkQueue::new(42);
+        let mut blocks = generate(&mut chunks);
+        let edits: Edits = vec![((2, 45, 2), Some(BlockType::Brick)), ((5, 0, 5), None), ((2, 45, 2), Some(BlockType::Glass))];
+        for (pos, block) in &edits {
+            match block {
+                Some(block) => { blocks.insert(*pos, *block); }
+                None => { blocks.remove(pos); }
+            }
+            chunks.record_edit(*pos, *block);
+        }
+
+        // Only the changes are stored, the same position twice keeps its last block
+        let saved = chunks.edits();
+        assert_eq!(saved.len(), 2);
+        let blob = encode(&serde_json::to_vec(&saved).unwrap());
+        let loaded: Edits = serde_json::from_slice(&decode(&blob).unwrap()).unwrap();
+
+        let mut restored = ChunkQueue::new(42);
+        restored.restore(loaded);
+        assert_eq!(generate(&mut restored), blocks);
+    }
+
+    #[test]
+    fn damaged_blob_is_an_error() {
+        assert!(decode("not a save").is_err());
+        assert!(decode(&BASE64.encode(b"plain bytes")).is_err());
+    }
+
+    fn meta(name: &str, seed: u32, last_played: f64) -> WorldMeta {
+        let options = WorldOptions { creative: true, world_border: Some(64), ..WorldOptions::default() };
+        WorldMeta { name: name.to_string(), seed, last_played, play_seconds: 12.5, options }
+    }
+
+    #[test]
+    fn listed_worlds_round_trip_their_metadata_newest_first() {
+        let saved = [meta("Old", 1, 1000.0), meta("New", 2, 3000.0), meta("Mid", 3, 2000.0)];
+        let mut entries: Vec<(String, String)> = saved.iter()
+            .map(|m| (format!("{}{}", META_PREFIX, m.name), serde_json::to_string(m).unwrap()))
+            .collect();
+        entries.push((format!("{}New", DATA_PREFIX), encode(b"{}")));
+        entries.push((format!("{}Broken", META_PREFIX), "{\"name\": ".to_string()));
+
+        let worlds = collect_worlds(entries.into_iter());
+        let names: Vec<&str> = worlds.iter().map(|w| w.name.as_str()).collect();
+        assert_eq!(names, ["New", "Mid", "Old"]);
+        let new = &worlds[0];
+        assert_eq!((new.seed, new.last_played, new.play_seconds), (2, 3000.0, 12.5));
+        assert!(new.options.creative && new.options.keep_inventory && !new.options.fluids_enabled);
+        assert_eq!(new.options.world_border, Some(64));
+    }
+}
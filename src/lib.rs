@@ -3,15 +3,28 @@ mod game;
 
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
-use wasm_bindgen_futures::JsFuture;
-use web_sys::{WebGlRenderingContext, HtmlCanvasElement, KeyboardEvent, MouseEvent, WheelEvent, Request, RequestInit, RequestMode, Response};
-use std::cell::RefCell;
+use web_sys::{WebGlRenderingContext, HtmlCanvasElement, KeyboardEvent, MouseEvent, WheelEvent, TouchEvent, Touch, TouchList};
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 use crate::engine::renderer::Renderer;
 use crate::engine::mesh::Mesh;
-use crate::game::{Game, AppConfig};
-use crate::game::solar_system::{SolarSystem, SystemType};
-use crate::game::minecraft::Minecraft;
+use crate::engine::camera::InputEvent;
+use crate::engine::assets;
+use crate::engine::audio;
+use crate::engine::events;
+use crate::engine::frame_stats;
+use crate::engine::input::{self, GameAction, GamepadFrame};
+use crate::engine::key_bindings::{self, BindingSet, GameBindings, KeyAction};
+use crate::engine::overlay;
+use crate::engine::settings::Settings;
+use crate::game::{Game, AppConfig, CrossySnapshot, PowerUpKind, CROSSY_KEYS};
+use crate::game::block_registry;
+use crate::game::crossy_stats::CrossyStats;
+use crate::game::crossy_level::CustomLevel;
+use crate::game::crossy_theme;
+use crate::game::solar_system::{SolarSystem, SolarSnapshot, SystemType};
+use crate::game::minecraft::{Minecraft, MinecraftSnapshot};
+use crate::game::world_save;
 
 enum ActiveGame {
     Crossy(Game),
@@ -19,36 +32,144 @@ enum ActiveGame {
     Minecraft(Minecraft),
 }
 
+// The running game with the canvas it draws on and takes pointer and touch input from
+struct CurrentGame {
+    game: ActiveGame,
+    canvas: HtmlCanvasElement,
+}
+
+// The frame callback is made once and reused by every game. Stopping cancels the frame
+// it has queued; starting again queues a new one.
+type FrameCallback = Rc<RefCell<Option<Closure<dyn FnMut()>>>>;
+
+struct GameLoop {
+    frame: FrameCallback,
+    running: Rc<Cell<bool>>,
+    pending: Rc<Cell<Option<i32>>>,
+}
+
+// Score, coins, best score, game over and the active power-up with its seconds left
+type ShownUi = (i32, i32, i32, bool, Option<(PowerUpKind, i32)>);
+// A touch's identifier, where it started and where it was last seen, in client pixels
+type TrackedTouch = (i32, (i32, i32), (i32, i32));
+
 thread_local! {
-    static CURRENT_GAME: RefCell<Option<ActiveGame>> = RefCell::new(None);
+    static CURRENT_GAME: RefCell<Option<CurrentGame>> = const { RefCell::new(None) };
+    static GAME_LOOP: RefCell<Option<GameLoop>> = const { RefCell::new(None) };
+    static LISTENERS_INSTALLED: Cell<bool> = const { Cell::new(false) };
+    // Paused by the page or hidden by the browser, either one holds the game. HELD is
+    // whether the last frame was skipped, so the first frame after knows to resume.
+    static PAUSED: Cell<bool> = const { Cell::new(false) };
+    static TAB_HIDDEN: Cell<bool> = const { Cell::new(false) };
+    // Between webglcontextlost and webglcontextrestored nothing can be drawn
+    static CONTEXT_LOST: Cell<bool> = const { Cell::new(false) };
+    // Read once, when the first game creates the context
+    static PRESERVE_DRAWING_BUFFER: Cell<bool> = const { Cell::new(false) };
+    // Chosen by set_canvas for the games started after it
+    static NEXT_CANVAS: RefCell<Option<HtmlCanvasElement>> = const { RefCell::new(None) };
+    static WIRED_CANVASES: RefCell<Vec<HtmlCanvasElement>> = const { RefCell::new(Vec::new()) };
+    // Crossy also writes #score and #gameover for pages that predate on_game_event.
+    // SHOWN_UI is what they show, so unchanged frames skip the DOM.
+    static DOM_UI: Cell<bool> = const { Cell::new(true) };
+    static SHOWN_UI: Cell<Option<ShownUi>> = const { Cell::new(None) };
+    // fps and the tenths of a millisecond #fps shows
+    static SHOWN_FPS: Cell<Option<(i32, i32, i32, i32)>> = const { Cell::new(None) };
+    static HELD: Cell<bool> = const { Cell::new(false) };
+    // The finger being followed
+    static TOUCH: Cell<Option<TrackedTouch>> = const { Cell::new(None) };
+    // performance.now() of the last frame that ran, for the real frame time
+    static LAST_FRAME_MS: Cell<Option<f64>> = const { Cell::new(None) };
 }
 
-fn get_gl() -> Result<WebGlRenderingContext, JsValue> {
-    let window = web_sys::window().ok_or("No window")?;
-    let document = window.document().ok_or("No document")?;
-    let canvas = document.get_element_by_id("canvas")
-        .ok_or("No canvas")?
-        .dyn_into::<HtmlCanvasElement>()?;
+// Frame time assumed for the first frame and the first after a pause
+const NOMINAL_FRAME_SECONDS: f64 = 1.0 / 60.0;
+
+#[wasm_bindgen(start)]
+pub fn init() {
+    // Panics reach the console with their message and the page's error callback
+    std::panic::set_hook(Box::new(|info| {
+        console_error_panic_hook::hook(info);
+        events::report_error(&info.to_string());
+    }));
+}
+
+// Hands a failed start to the page's error callback before returning it
+fn reported<T>(result: Result<T, JsValue>) -> Result<T, JsValue> {
+    if let Err(e) = &result {
+        events::report_error(&e.as_string().unwrap_or_else(|| format!("{:?}", e)));
+    }
+    result
+}
 
+fn get_gl(canvas: &HtmlCanvasElement) -> Result<WebGlRenderingContext, JsValue> {
+
+    // The browser hands back the existing context on later calls, whatever the attributes
+    let attributes = js_sys::Object::new();
+    js_sys::Reflect::set(&attributes, &"preserveDrawingBuffer".into(), &PRESERVE_DRAWING_BUFFER.with(Cell::get).into())?;
+    // WebGL2 where available. Everything the renderer calls outside instancing exists on both,
+    // so the context is used through the WebGL1 interface
+    if let Some(gl2) = canvas.get_context_with_context_options("webgl2", &attributes)? {
+        return Ok(gl2.unchecked_into::<WebGlRenderingContext>());
+    }
     let gl = canvas
-        .get_context("webgl")?
+        .get_context_with_context_options("webgl", &attributes)?
         .ok_or("No WebGL")?
         .dyn_into::<WebGlRenderingContext>()?;
     Ok(gl)
 }
 
-fn start_game_loop() -> Result<(), JsValue> {
+// Draws the following games on another canvas than #canvas. A game running elsewhere
+// is stopped, its context can't move, so start the next one after this.
+#[wasm_bindgen]
+pub fn set_canvas(canvas: HtmlCanvasElement) {
+    if NEXT_CANVAS.with(|c| c.borrow().as_ref() != Some(&canvas)) {
+        stop_game();
+        NEXT_CANVAS.with(|c| *c.borrow_mut() = Some(canvas));
+    }
+}
+
+// Keeps frames readable after compositing, for pages that read the canvas themselves.
+// Costs some speed, and only applies if called before the first game starts.
+#[wasm_bindgen]
+pub fn set_preserve_drawing_buffer(enabled: bool) {
+    PRESERVE_DRAWING_BUFFER.with(|p| p.set(enabled));
+}
+
+// Safe to call from every start: listeners go on once for the page and there is only ever
+// one frame loop, so switching games never doubles input handling or frames
+fn start_game_loop(canvas: &HtmlCanvasElement) -> Result<(), JsValue> {
+    // Only marked once every listener is on, so a start that fails partway retries them
+    if !LISTENERS_INSTALLED.with(Cell::get) {
+        install_listeners()?;
+        LISTENERS_INSTALLED.with(|installed| installed.set(true));
+    }
+    install_canvas_listeners(canvas)?;
+    assets::finish_loading();
+    if GAME_LOOP.with(|l| l.borrow().is_none()) {
+        let game_loop = create_game_loop();
+        GAME_LOOP.with(|l| *l.borrow_mut() = Some(game_loop));
+    }
+    GAME_LOOP.with(|l| {
+        if let Some(game_loop) = l.borrow().as_ref() {
+            if !game_loop.running.replace(true) {
+                game_loop.pending.set(Some(request_animation_frame(game_loop.frame.borrow().as_ref().unwrap())));
+            }
+        }
+    });
+    Ok(())
+}
+
+fn install_listeners() -> Result<(), JsValue> {
     let closure = Closure::wrap(Box::new(move |event: KeyboardEvent| {
+        audio::unlock();
         CURRENT_GAME.with(|g| {
-            if let Some(active_game) = g.borrow_mut().as_mut() {
+            if let Some(active_game) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
                 match active_game {
                     ActiveGame::Crossy(game) => {
-                        let handled = match event.key().as_str() {
-                            " " => { game.move_forward(); true },
-                            "ArrowLeft" | "d" | "D" => { game.move_left(); true },
-                            "ArrowRight" | "a" | "A" => { game.move_right(); true },
-                            "r" | "R" => { game.restart(); true },
-                            _ => false,
+                        let handled = match key_bindings::action(BindingSet::Crossy, &event.key(), &event.code(), CROSSY_KEYS) {
+                            Some(KeyAction::Restart) if event.shift_key() => { game.restart_same_world(); true },
+                            Some(action) => game.apply_action(action),
+                            None => false,
                         };
                         if handled {
                             event.prevent_default();
@@ -58,7 +179,7 @@ fn start_game_loop() -> Result<(), JsValue> {
                         game.handle_input(&event.key());
                     },
                     ActiveGame::Minecraft(game) => {
-                        game.handle_input(&event.key());
+                        game.handle_input(&event.key(), &event.code());
                     }
                 }
             }
@@ -70,71 +191,37 @@ fn start_game_loop() -> Result<(), JsValue> {
 
     let closure_keyup = Closure::wrap(Box::new(move |event: KeyboardEvent| {
         CURRENT_GAME.with(|g| {
-            if let Some(ActiveGame::Minecraft(game)) = g.borrow_mut().as_mut() {
-                game.handle_keyup(&event.key());
+            if let Some(ActiveGame::Minecraft(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+                game.handle_keyup(&event.key(), &event.code());
             }
         });
     }) as Box<dyn FnMut(_)>);
     web_sys::window().unwrap().add_event_listener_with_callback("keyup", closure_keyup.as_ref().unchecked_ref())?;
     closure_keyup.forget();
 
-    let closure_down = Closure::wrap(Box::new(move |event: MouseEvent| {
-        CURRENT_GAME.with(|g| {
-            if let Some(active_game) = g.borrow_mut().as_mut() {
-                match active_game {
-                    ActiveGame::Solar(game) => game.handle_mouse_down(event.client_x(), event.client_y()),
-                    ActiveGame::Minecraft(game) => game.handle_mouse_down(event.client_x(), event.client_y(), event.button() as i32),
-                    _ => {}
-                }
-            }
-        });
-    }) as Box<dyn FnMut(_)>);
-    web_sys::window().unwrap().document().unwrap().get_element_by_id("canvas").unwrap()
-        .add_event_listener_with_callback("mousedown", closure_down.as_ref().unchecked_ref())?;
-    closure_down.forget();
-
     let closure_up = Closure::wrap(Box::new(move |_event: MouseEvent| {
-        CURRENT_GAME.with(|g| {
-            if let Some(ActiveGame::Solar(game)) = g.borrow_mut().as_mut() {
-                game.handle_mouse_up();
-            }
-        });
+        dispatch_input(&InputEvent::PointerUp);
     }) as Box<dyn FnMut(_)>);
     web_sys::window().unwrap().add_event_listener_with_callback("mouseup", closure_up.as_ref().unchecked_ref())?;
     closure_up.forget();
 
     let closure_move = Closure::wrap(Box::new(move |event: MouseEvent| {
-        CURRENT_GAME.with(|g| {
-            if let Some(active_game) = g.borrow_mut().as_mut() {
-                match active_game {
-                    ActiveGame::Solar(game) => game.handle_mouse_move(event.client_x(), event.client_y()),
-                    ActiveGame::Minecraft(game) => game.handle_mouse_move(event.movement_x(), event.movement_y()),
-                    _ => {}
-                }
-            }
+        dispatch_input(&InputEvent::PointerMove {
+            x: event.client_x(),
+            y: event.client_y(),
+            dx: event.movement_x(),
+            dy: event.movement_y(),
         });
     }) as Box<dyn FnMut(_)>);
     web_sys::window().unwrap().add_event_listener_with_callback("mousemove", closure_move.as_ref().unchecked_ref())?;
     closure_move.forget();
 
-    let closure_wheel = Closure::wrap(Box::new(move |event: WheelEvent| {
-        CURRENT_GAME.with(|g| {
-            if let Some(ActiveGame::Solar(game)) = g.borrow_mut().as_mut() {
-                game.handle_wheel(event.delta_y() as f32);
-                event.prevent_default();
-            }
-        });
-    }) as Box<dyn FnMut(_)>);
-    web_sys::window().unwrap().document().unwrap().get_element_by_id("canvas").unwrap()
-        .add_event_listener_with_callback("wheel", closure_wheel.as_ref().unchecked_ref())?;
-    closure_wheel.forget();
-
     let closure_lock = Closure::wrap(Box::new(move || {
         let document = web_sys::window().unwrap().document().unwrap();
         let is_locked = document.pointer_lock_element().is_some();
         
         CURRENT_GAME.with(|g| {
-            if let Some(ActiveGame::Minecraft(game)) = g.borrow_mut().as_mut() {
+            if let Some(ActiveGame::Minecraft(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
                 game.set_locked(is_locked);
             }
         });
@@ -143,266 +230,1528 @@ fn start_game_loop() -> Result<(), JsValue> {
         .add_event_listener_with_callback("pointerlockchange", closure_lock.as_ref().unchecked_ref())?;
     closure_lock.forget();
 
-    let f = Rc::new(RefCell::new(None));
-    let g = f.clone();
+    let closure_visibility = Closure::wrap(Box::new(move || {
+        let hidden = web_sys::window().unwrap().document().unwrap().hidden();
+        TAB_HIDDEN.with(|h| h.set(hidden));
+    }) as Box<dyn FnMut()>);
+    web_sys::window().unwrap().document().unwrap()
+        .add_event_listener_with_callback("visibilitychange", closure_visibility.as_ref().unchecked_ref())?;
+    closure_visibility.forget();
+
+    Ok(())
+}
+
+// Canvas listeners go on once per canvas the page has used. Those left on a canvas no
+// game draws on anymore ignore its events.
+fn install_canvas_listeners(canvas: &HtmlCanvasElement) -> Result<(), JsValue> {
+    if WIRED_CANVASES.with(|w| w.borrow().contains(canvas)) {
+        return Ok(());
+    }
+    WIRED_CANVASES.with(|w| w.borrow_mut().push(canvas.clone()));
+
+    let closure_down = Closure::wrap(Box::new(move |event: MouseEvent| {
+        if !is_game_canvas(&event) {
+            return;
+        }
+        audio::unlock();
+        dispatch_input(&InputEvent::PointerDown {
+            x: event.client_x(),
+            y: event.client_y(),
+            button: event.button() as i32,
+        });
+    }) as Box<dyn FnMut(_)>);
+    canvas.add_event_listener_with_callback("mousedown", closure_down.as_ref().unchecked_ref())?;
+    closure_down.forget();
+
+    // A touch a game takes, a button or the start of a swipe or drag, skips the browser's
+    // scrolling and emulated mouse events. Extra fingers and touches with no game running
+    // keep their default handling.
+    let closure_touch = Closure::wrap(Box::new(move |event: TouchEvent| {
+        if !is_game_canvas(&event) || TOUCH.with(Cell::get).is_some() || CURRENT_GAME.with(|g| g.borrow().is_none()) {
+            return;
+        }
+        audio::unlock();
+        let touch = match event.changed_touches().get(0) {
+            Some(touch) => touch,
+            None => return,
+        };
+        let canvas = match event.target().and_then(|t| t.dyn_into::<HtmlCanvasElement>().ok()) {
+            Some(canvas) => canvas,
+            None => return,
+        };
+        let rect = canvas.get_bounding_client_rect();
+        if rect.width() <= 0.0 || rect.height() <= 0.0 {
+            return;
+        }
+        // Client coordinates to canvas pixels, whatever the canvas backing scale is
+        let x = (touch.client_x() as f64 - rect.left()) * canvas.width() as f64 / rect.width();
+        let y = (touch.client_y() as f64 - rect.top()) * canvas.height() as f64 / rect.height();
+        let on_button = CURRENT_GAME.with(|g| match g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            Some(ActiveGame::Crossy(game)) => game.touch_at(x as f32, y as f32),
+            _ => false,
+        });
+        event.prevent_default();
+        // A touch on a built-in button is done, anything else starts a swipe or a drag
+        if on_button {
+            return;
+        }
+        let start = (touch.client_x(), touch.client_y());
+        TOUCH.with(|t| t.set(Some((touch.identifier(), start, start))));
+        // Minecraft only looks, a press there would break blocks
+        if is_solar() {
+            dispatch_input(&InputEvent::PointerDown { x: start.0, y: start.1, button: 0 });
+        }
+    }) as Box<dyn FnMut(_)>);
+    canvas.add_event_listener_with_callback("touchstart", closure_touch.as_ref().unchecked_ref())?;
+    closure_touch.forget();
+
+    // Solar orbits and Minecraft looks around while a finger drags
+    let closure_touch_move = Closure::wrap(Box::new(move |event: TouchEvent| {
+        if !is_game_canvas(&event) {
+            return;
+        }
+        let (id, start, last) = match TOUCH.with(Cell::get) {
+            Some(touch) => touch,
+            None => return,
+        };
+        // Another finger moving says nothing about the followed one
+        let touch = match find_touch(&event.changed_touches(), id) {
+            Some(touch) => touch,
+            None => return,
+        };
+        event.prevent_default();
+        let (x, y) = (touch.client_x(), touch.client_y());
+        let (dx, dy) = (x - last.0, y - last.1);
+        TOUCH.with(|t| t.set(Some((id, start, (x, y)))));
+        let look = CURRENT_GAME.with(|g| match g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            Some(ActiveGame::Minecraft(game)) => {
+                game.touch_look(dx, dy);
+                true
+            }
+            _ => false,
+        });
+        if !look {
+            dispatch_input(&InputEvent::PointerMove { x, y, dx, dy });
+        }
+    }) as Box<dyn FnMut(_)>);
+    canvas.add_event_listener_with_callback("touchmove", closure_touch_move.as_ref().unchecked_ref())?;
+    closure_touch_move.forget();
+
+    // Crossy reads the whole gesture once the finger lifts
+    let closure_touch_end = Closure::wrap(Box::new(move |event: TouchEvent| {
+        if !is_game_canvas(&event) {
+            return;
+        }
+        let (id, start, _) = match TOUCH.with(Cell::get) {
+            Some(touch) => touch,
+            None => return,
+        };
+        let end = match find_touch(&event.changed_touches(), id) {
+            Some(touch) => (touch.client_x(), touch.client_y()),
+            None => return,
+        };
+        TOUCH.with(|t| t.set(None));
+        let cancelled = event.type_() == "touchcancel";
+        CURRENT_GAME.with(|g| {
+            if let Some(ActiveGame::Crossy(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+                if !cancelled {
+                    game.swipe((end.0 - start.0) as f32, (end.1 - start.1) as f32);
+                }
+            }
+        });
+        if is_solar() {
+            dispatch_input(&InputEvent::PointerUp);
+        }
+    }) as Box<dyn FnMut(_)>);
+    canvas.add_event_listener_with_callback("touchend", closure_touch_end.as_ref().unchecked_ref())?;
+    canvas.add_event_listener_with_callback("touchcancel", closure_touch_end.as_ref().unchecked_ref())?;
+    closure_touch_end.forget();
+
+    let closure_wheel = Closure::wrap(Box::new(move |event: WheelEvent| {
+        if !is_game_canvas(&event) {
+            return;
+        }
+        if dispatch_input(&InputEvent::Wheel { delta: event.delta_y() as f32 }) {
+            event.prevent_default();
+        }
+    }) as Box<dyn FnMut(_)>);
+    canvas.add_event_listener_with_callback("wheel", closure_wheel.as_ref().unchecked_ref())?;
+    closure_wheel.forget();
+
+    // Without preventDefault the browser never restores the context
+    let closure_context_lost = Closure::wrap(Box::new(move |event: web_sys::Event| {
+        if !is_game_canvas(&event) {
+            return;
+        }
+        event.prevent_default();
+        CONTEXT_LOST.with(|l| l.set(true));
+    }) as Box<dyn FnMut(_)>);
+    canvas.add_event_listener_with_callback("webglcontextlost", closure_context_lost.as_ref().unchecked_ref())?;
+    closure_context_lost.forget();
+
+    let closure_context_restored = Closure::wrap(Box::new(move |event: web_sys::Event| {
+        if !is_game_canvas(&event) {
+            return;
+        }
+        CONTEXT_LOST.with(|l| l.set(false));
+        CURRENT_GAME.with(|g| {
+            let restored = match g.borrow_mut().as_mut().map(|current| &mut current.game) {
+                Some(ActiveGame::Crossy(game)) => game.restore_context(),
+                Some(ActiveGame::Solar(game)) => game.restore_context(),
+                Some(ActiveGame::Minecraft(game)) => game.restore_context(),
+                None => Ok(()),
+            };
+            if let Err(e) = restored {
+                events::report_error(&format!("Failed to restore the WebGL context: {}", e.as_string().unwrap_or_else(|| format!("{:?}", e))));
+            }
+        });
+    }) as Box<dyn FnMut(_)>);
+    canvas.add_event_listener_with_callback("webglcontextrestored", closure_context_restored.as_ref().unchecked_ref())?;
+    closure_context_restored.forget();
+    Ok(())
+}
+
+fn is_game_canvas(event: &web_sys::Event) -> bool {
+    CURRENT_GAME.with(|g| match (g.borrow().as_ref(), event.current_target()) {
+        (Some(current), Some(target)) => &target == current.canvas.unchecked_ref::<web_sys::EventTarget>(),
+        _ => true,
+    })
+}
+
+// The canvas set by set_canvas, #canvas otherwise
+fn next_canvas() -> Result<HtmlCanvasElement, JsValue> {
+    if let Some(canvas) = NEXT_CANVAS.with(|c| c.borrow().clone()) {
+        return Ok(canvas);
+    }
+    let canvas = web_sys::window().ok_or("No window")?
+        .document().ok_or("No document")?
+        .get_element_by_id("canvas").ok_or("No canvas")?
+        .dyn_into::<HtmlCanvasElement>()?;
+    NEXT_CANVAS.with(|c| *c.borrow_mut() = Some(canvas.clone()));
+    Ok(canvas)
+}
+
+// Checked every frame rather than on resize, so phone rotation and moving the window to
+// a screen with another pixel ratio are caught too. Returns the CSS size.
+fn fit_canvas(canvas: &HtmlCanvasElement) -> Option<(i32, i32)> {
+    let window = web_sys::window()?;
+    let ratio = window.device_pixel_ratio();
+    let css_width = canvas.client_width().max(1);
+    let css_height = canvas.client_height().max(1);
+    let width = (css_width as f64 * ratio).round() as u32;
+    let height = (css_height as f64 * ratio).round() as u32;
+    // Setting the size clears the canvas even when it doesn't change
+    if canvas.width() != width {
+        canvas.set_width(width);
+    }
+    if canvas.height() != height {
+        canvas.set_height(height);
+    }
+    Some((css_width, css_height))
+}
+
+fn now_ms() -> f64 {
+    web_sys::window().and_then(|w| w.performance()).map(|p| p.now()).unwrap_or(0.0)
+}
+
+fn create_game_loop() -> GameLoop {
+    let frame: FrameCallback = Rc::new(RefCell::new(None));
+    let running = Rc::new(Cell::new(false));
+    let pending = Rc::new(Cell::new(None));
+    let (f, frame_running, frame_pending) = (frame.clone(), running.clone(), pending.clone());
 
-    *g.borrow_mut() = Some(Closure::wrap(Box::new(move || {
+    *frame.borrow_mut() = Some(Closure::wrap(Box::new(move || {
+        frame_pending.set(None);
+        let held = PAUSED.with(Cell::get) || TAB_HIDDEN.with(Cell::get) || CONTEXT_LOST.with(Cell::get);
+        let was_held = HELD.with(|h| h.replace(held));
+        let now = now_ms();
+        let last = LAST_FRAME_MS.with(|l| l.replace(Some(now)));
+        let interval_ms = last.filter(|_| !was_held).map(|last| now - last);
+        let dt = interval_ms.map_or(NOMINAL_FRAME_SECONDS, |ms| ms / 1000.0);
         CURRENT_GAME.with(|game| {
-            if let Some(active_game) = game.borrow_mut().as_mut() {
+            if held {
+                return;
+            }
+            if let Some(CurrentGame { game: active_game, canvas }) = game.borrow_mut().as_mut() {
+                // Clocks restart from now, the time spent held never reaches a simulation
+                if was_held {
+                    match active_game {
+                        ActiveGame::Crossy(_) => {},
+                        ActiveGame::Solar(game) => game.resume(),
+                        ActiveGame::Minecraft(game) => game.resume(),
+                    }
+                }
+                if let Some(pad) = input::poll() {
+                    dispatch_gamepad(active_game, &pad, dt as f32);
+                }
+                let (css_width, css_height) = fit_canvas(canvas).unwrap_or((1, 1));
+                let update_start = now_ms();
+                match active_game {
+                    ActiveGame::Crossy(game) => game.update(dt),
+                    ActiveGame::Solar(game) => game.update(),
+                    ActiveGame::Minecraft(game) => game.update(),
+                }
+                let render_start = now_ms();
                 match active_game {
                     ActiveGame::Crossy(game) => {
-                        game.update();
                         game.render();
-                        update_ui(game.score, game.coins, game.game_over);
+                        overlay::update_and_draw(&game.renderer);
+                        if DOM_UI.with(Cell::get) {
+                            update_ui(game.score, game.coins, game.high_score, game.game_over, game.power_up_hud());
+                        }
                     },
                     ActiveGame::Solar(game) => {
-                        game.update();
-                        let window = web_sys::window().unwrap();
-                        let width = window.inner_width().unwrap().as_f64().unwrap() as i32;
-                        let height = window.inner_height().unwrap().as_f64().unwrap() as i32;
-                        game.render(width, height);
+                        game.render(css_width, css_height);
+                        overlay::update_and_draw(game.renderer());
                     },
                     ActiveGame::Minecraft(game) => {
-                        game.update();
-                        let window = web_sys::window().unwrap();
-                        let width = window.inner_width().unwrap().as_f64().unwrap() as i32;
-                        let height = window.inner_height().unwrap().as_f64().unwrap() as i32;
-                        game.render(width, height);
+                        game.render(css_width, css_height);
+                        overlay::update_and_draw(game.renderer());
                     }
                 }
+                frame_stats::record(interval_ms, render_start - update_start, now_ms() - render_start);
+                update_fps_ui();
             }
         });
-        request_animation_frame(f.borrow().as_ref().unwrap());
+        events::flush();
+        // An event listener may have stopped the game during the flush, or started one and
+        // with it queued the next frame already
+        if frame_running.get() && frame_pending.get().is_none() {
+            frame_pending.set(Some(request_animation_frame(f.borrow().as_ref().unwrap())));
+        }
     }) as Box<dyn FnMut()>));
 
-    request_animation_frame(g.borrow().as_ref().unwrap());
-    Ok(())
+    GameLoop { frame, running, pending }
+}
+
+// Drops the running game and its frame loop. Each game removes the page elements it made
+// when dropped, so the next start begins from the same page a fresh load would.
+#[wasm_bindgen]
+pub fn stop_game() {
+    GAME_LOOP.with(|l| {
+        if let Some(game_loop) = l.borrow().as_ref() {
+            game_loop.running.set(false);
+            if let Some(id) = game_loop.pending.take() {
+                web_sys::window().unwrap().cancel_animation_frame(id).ok();
+            }
+        }
+    });
+    let game = CURRENT_GAME.with(|g| g.borrow_mut().take());
+    drop(game);
+    overlay::clear();
+    PAUSED.with(|p| p.set(false));
+    LAST_FRAME_MS.with(|l| l.set(None));
+    frame_stats::clear();
+}
+
+// Averages over the last 60 frames, all in milliseconds except fps
+#[wasm_bindgen]
+pub fn get_frame_stats() -> JsValue {
+    serde_wasm_bindgen::to_value(&frame_stats::current()).unwrap_or(JsValue::NULL)
+}
+
+// Stops the current game, which takes its labels and GL objects with it, then starts
+// another: "crossy", "solar" or "minecraft"
+#[wasm_bindgen]
+pub async fn switch_game(name: &str) -> Result<(), JsValue> {
+    if !matches!(name, "crossy" | "solar" | "minecraft") {
+        return Err(JsValue::from_str(&format!("Unknown game: {}", name)));
+    }
+    stop_game();
+    match name {
+        "crossy" => start_crossy_road().await,
+        "solar" => start_solar_system(),
+        _ => start_minecraft(),
+    }
+}
+
+// Holds updates and rendering until resume_game, the last frame stays on screen
+#[wasm_bindgen]
+pub fn pause_game() {
+    PAUSED.with(|p| p.set(true));
+}
+
+#[wasm_bindgen]
+pub fn resume_game() {
+    PAUSED.with(|p| p.set(false));
 }
 
 #[wasm_bindgen]
 pub async fn start_crossy_road() -> Result<(), JsValue> {
-    let gl = get_gl()?;
+    reported(load_crossy_road(None).await)
+}
+
+// Everyone starting with the same seed plays the same world
+#[wasm_bindgen]
+pub async fn start_crossy_road_with_seed(seed: u32) -> Result<(), JsValue> {
+    reported(load_crossy_road(Some(seed)).await)
+}
+
+async fn load_crossy_road(seed: Option<u32>) -> Result<(), JsValue> {
+    let canvas = next_canvas()?;
+    let gl = get_gl(&canvas)?;
     let renderer = Renderer::new(gl)?;
 
-    let window = web_sys::window().unwrap();
-    let mut config: Option<AppConfig> = None;
-    let opts = RequestInit::new();
-    opts.set_method("GET");
-    opts.set_mode(RequestMode::Cors);
-
-    let config_request = Request::new_with_str_and_init("/assets/config.json", &opts)?;
-    let config_resp_value = JsFuture::from(window.fetch_with_request(&config_request)).await;
-
-    if let Ok(resp_value) = config_resp_value {
-        let resp: Response = resp_value.dyn_into().unwrap();
-        if resp.ok() {
-            let json_promise = resp.json()?;
-            let json = JsFuture::from(json_promise).await?;
-            if let Ok(c) = serde_wasm_bindgen::from_value(json) {
-                config = Some(c);
-            }
+    // Both are optional, the game runs on defaults and box cars without them
+    let config: Option<AppConfig> = match assets::fetch_json("config", "/assets/config.json").await {
+        Ok(config) => Some(config),
+        Err(e) => {
+            web_sys::console::error_2(&"Crossy config not loaded, using defaults:".into(), &e);
+            None
         }
-    }
+    };
 
-    let mut car_mesh = None;
     let model_path = if let Some(ref c) = config {
         c.car_model.path.clone()
     } else {
         "/assets/models/grey_voxel_car.glb".to_string()
     };
-
-    let request = Request::new_with_str_and_init(&model_path, &opts)?;
-    let resp_value = JsFuture::from(window.fetch_with_request(&request)).await;
-    
-    if let Ok(resp_value) = resp_value {
-        let resp: Response = resp_value.dyn_into().unwrap();
-        if resp.ok() {
-            let buffer_promise = resp.array_buffer()?;
-            let buffer = JsFuture::from(buffer_promise).await?;
-            let array = js_sys::Uint8Array::new(&buffer);
-            let bytes = array.to_vec();
-            
-            if let Ok(mesh) = Mesh::from_gltf(&bytes) {
-                car_mesh = Some(mesh);
-            }
+    let car_mesh = match fetch_mesh("car_model", &model_path).await {
+        Ok(mesh) => Some(mesh),
+        Err(e) => {
+            web_sys::console::error_2(&"Car model not loaded, using boxes:".into(), &e);
+            None
         }
-    }
+    };
 
-    let game = Game::new(renderer, car_mesh, config);
-    CURRENT_GAME.with(|g| *g.borrow_mut() = Some(ActiveGame::Crossy(game)));
+    if let Some(c) = &config {
+        key_bindings::set(c.key_bindings.clone());
+    }
+    let game = Game::new(renderer, car_mesh, config, seed);
+    overlay::clear();
+    CURRENT_GAME.with(|g| *g.borrow_mut() = Some(CurrentGame { game: ActiveGame::Crossy(game), canvas: canvas.clone() }));
     
-    start_game_loop()?;
+    start_game_loop(&canvas)?;
     Ok(())
 }
 
+async fn fetch_mesh(stage: &str, path: &str) -> Result<Mesh, JsValue> {
+    let bytes = assets::fetch_bytes(stage, path).await?;
+    Mesh::from_gltf(&bytes).map_err(|e| JsValue::from_str(&format!("Model {} couldn't be read: {}", path, e)))
+}
+
+// Index into the config's characters, allowed mid-run
 #[wasm_bindgen]
-pub fn load_solar_system(sim_type: &str) -> Result<(), JsValue> {
-    let gl = get_gl()?;
-    let renderer = Renderer::new(gl)?;
-    
-    let system_type = match sim_type {
-        "black_hole" => SystemType::BlackHole,
-        "sirius" => SystemType::Sirius,
-        _ => SystemType::Solar,
+pub async fn select_character(index: usize) -> Result<(), JsValue> {
+    let model = CURRENT_GAME.with(|g| match g.borrow().as_ref().map(|current| &current.game) {
+        Some(ActiveGame::Crossy(game)) => game.character_model(index),
+        _ => None,
+    });
+    let Some(model) = model else {
+        return reported(Err(JsValue::from_str(&format!("No character {} in the config", index))));
     };
-    let game = SolarSystem::new(renderer, system_type);
-    
+    let mesh = reported(fetch_mesh("character_model", &model.path).await)?;
+    // The game may have been swapped out while the model loaded
     CURRENT_GAME.with(|g| {
-        *g.borrow_mut() = Some(ActiveGame::Solar(game));
+        if let Some(ActiveGame::Crossy(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.set_character(index, mesh);
+        }
     });
-    
-    // Ensure loop is running (idempotent)
-    start_game_loop()?;
-    
     Ok(())
 }
 
 #[wasm_bindgen]
-pub fn start_solar_system() -> Result<(), JsValue> {
-    load_solar_system("sun")
+pub async fn start_crossy_tournament(seed: u32, target_lane: i32) -> Result<(), JsValue> {
+    start_crossy_road().await?;
+    CURRENT_GAME.with(|g| {
+        if let Some(ActiveGame::Crossy(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.start_tournament(seed, target_lane);
+        }
+    });
+    Ok(())
 }
 
+// Level problems are reported before the game starts, with the line and column of the offending lane
 #[wasm_bindgen]
-pub fn start_minecraft() -> Result<(), JsValue> {
-    let gl = get_gl()?;
-    let renderer = Renderer::new(gl)?;
-    let game = Minecraft::new(renderer);
-    
+pub async fn start_crossy_custom_level(level_json: String) -> Result<(), JsValue> {
+    let level = reported(CustomLevel::parse(&level_json).map_err(|e| JsValue::from_str(&e)))?;
+    start_crossy_road().await?;
     CURRENT_GAME.with(|g| {
-        *g.borrow_mut() = Some(ActiveGame::Minecraft(game));
+        if let Some(ActiveGame::Crossy(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.load_custom_level(level);
+        }
     });
-    
-    start_game_loop()?;
     Ok(())
 }
 
-fn request_animation_frame(f: &Closure<dyn FnMut()>) {
-    web_sys::window()
-        .unwrap()
-        .request_animation_frame(f.as_ref().unchecked_ref())
-        .unwrap();
-}
-
-fn update_ui(score: i32, coins: i32, game_over: bool) {
-    if let Some(window) = web_sys::window() {
-        if let Some(document) = window.document() {
-            if let Some(score_el) = document.get_element_by_id("score") {
-                score_el.set_inner_html(&format!("Score: {} | Coins: {}", score, coins));
-            }
-            if let Some(gameover_el) = document.get_element_by_id("gameover") {
-                if game_over {
-                    gameover_el.set_attribute("style", "display: block;").ok();
-                } else {
-                    gameover_el.set_attribute("style", "display: none;").ok();
-                }
-            }
+// Unknown event keys are rejected before the game starts
+#[wasm_bindgen]
+pub async fn start_crossy_event(key: String) -> Result<(), JsValue> {
+    let theme = crossy_theme::theme(&key).ok_or_else(|| JsValue::from_str(&format!("Unknown event: {}", key)))?;
+    start_crossy_road().await?;
+    CURRENT_GAME.with(|g| {
+        if let Some(ActiveGame::Crossy(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.start_event(theme);
         }
-    }
+    });
+    Ok(())
 }
 
 #[wasm_bindgen]
-pub fn touch_left() {
+pub fn set_crossy_time_of_day(t: f32) {
     CURRENT_GAME.with(|g| {
-        if let Some(active_game) = g.borrow_mut().as_mut() {
-            match active_game {
-                ActiveGame::Crossy(game) => game.move_left(),
-                ActiveGame::Solar(game) => game.handle_input("ArrowLeft"),
-                ActiveGame::Minecraft(game) => game.handle_input("a"),
-            }
+        if let Some(ActiveGame::Crossy(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.set_time_of_day(t);
         }
     });
 }
 
 #[wasm_bindgen]
-pub fn touch_right() {
+pub fn set_crossy_photo_mode(enabled: bool) {
     CURRENT_GAME.with(|g| {
-        if let Some(active_game) = g.borrow_mut().as_mut() {
-            match active_game {
-                ActiveGame::Crossy(game) => game.move_right(),
-                ActiveGame::Solar(game) => game.handle_input("ArrowRight"),
-                ActiveGame::Minecraft(game) => game.handle_input("d"),
-            }
+        if let Some(ActiveGame::Crossy(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.set_photo_mode(enabled);
         }
     });
 }
 
 #[wasm_bindgen]
-pub fn touch_forward() {
+pub fn clear_crossy_time_override() {
     CURRENT_GAME.with(|g| {
-        if let Some(active_game) = g.borrow_mut().as_mut() {
-            match active_game {
-                ActiveGame::Crossy(game) => game.move_forward(),
-                ActiveGame::Solar(game) => game.handle_input("ArrowUp"),
-                ActiveGame::Minecraft(game) => game.handle_input("w"),
-            }
+        if let Some(ActiveGame::Crossy(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.clear_time_override();
         }
     });
 }
 
 #[wasm_bindgen]
-pub fn touch_restart() {
+pub async fn start_crossy_attract_mode() -> Result<(), JsValue> {
+    start_crossy_road().await?;
     CURRENT_GAME.with(|g| {
-        if let Some(active_game) = g.borrow_mut().as_mut() {
-            match active_game {
-                ActiveGame::Crossy(game) => game.restart(),
-                ActiveGame::Solar(game) => game.handle_input("ArrowDown"),
-                ActiveGame::Minecraft(game) => game.handle_input("s"),
-            }
+        if let Some(ActiveGame::Crossy(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.start_attract_mode();
         }
     });
+    Ok(())
 }
 
 #[wasm_bindgen]
-pub fn activate_god_mode() {
+pub fn get_last_run_summary() -> Option<String> {
     CURRENT_GAME.with(|g| {
-        if let Some(active_game) = g.borrow_mut().as_mut() {
-            if let ActiveGame::Crossy(game) = active_game {
-                game.debug_advance();
-            }
+        match g.borrow().as_ref().map(|current| &current.game) {
+            Some(ActiveGame::Crossy(game)) => game.last_run_summary.as_ref()
+                .and_then(|summary| serde_json::to_string(summary).ok()),
+            _ => None,
         }
-    });
+    })
 }
 
+// Lifetime stats live in storage, so they're readable from any game or the menu
 #[wasm_bindgen]
-pub fn set_solar_date(timestamp: f64) {
+pub fn get_crossy_stats() -> String {
+    CrossyStats::load().to_json()
+}
+
+#[wasm_bindgen]
+pub fn get_high_score() -> i32 {
+    CrossyStats::load().longest_run
+}
+
+#[wasm_bindgen]
+pub fn reset_high_score() {
+    CrossyStats::reset_high_score();
+    clear_shown_high_score();
+}
+
+#[wasm_bindgen]
+pub fn reset_stats() {
+    CrossyStats::from_value(serde_json::Value::Null).save();
+    clear_shown_high_score();
+}
+
+fn clear_shown_high_score() {
     CURRENT_GAME.with(|g| {
-        if let Some(active_game) = g.borrow_mut().as_mut() {
-            if let ActiveGame::Solar(game) = active_game {
-                game.set_date_from_timestamp(timestamp);
-            }
+        if let Some(ActiveGame::Crossy(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.high_score = 0;
         }
     });
 }
 
+// Tagged with the game so the page can tell the shapes apart
+#[derive(serde::Serialize)]
+#[serde(tag = "game", rename_all = "lowercase")]
+enum GameState {
+    Crossy(CrossySnapshot),
+    Solar(SolarSnapshot),
+    Minecraft(MinecraftSnapshot),
+}
+
+// null while no game is running
+#[wasm_bindgen]
+pub fn get_game_state() -> JsValue {
+    let state = CURRENT_GAME.with(|g| match g.borrow().as_ref().map(|current| &current.game) {
+        Some(ActiveGame::Crossy(game)) => Some(GameState::Crossy(game.snapshot())),
+        Some(ActiveGame::Solar(game)) => Some(GameState::Solar(game.snapshot())),
+        Some(ActiveGame::Minecraft(game)) => Some(GameState::Minecraft(game.snapshot())),
+        None => None,
+    });
+    state.and_then(|state| serde_wasm_bindgen::to_value(&state).ok()).unwrap_or(JsValue::NULL)
+}
+
 #[wasm_bindgen]
-pub fn set_solar_time_scale(scale: f32) {
+pub fn get_share_text() -> Option<String> {
     CURRENT_GAME.with(|g| {
-        if let Some(active_game) = g.borrow_mut().as_mut() {
-            if let ActiveGame::Solar(game) = active_game {
-                game.set_time_scale(scale);
-            }
+        match g.borrow().as_ref().map(|current| &current.game) {
+            Some(ActiveGame::Crossy(game)) => game.share_text(),
+            _ => None,
         }
-    });
+    })
 }
 
 #[wasm_bindgen]
-pub fn select_solar_body(index: usize) {
+pub fn set_crossy_playfield_width(half_width: f32) {
     CURRENT_GAME.with(|g| {
-        if let Some(active_game) = g.borrow_mut().as_mut() {
-            if let ActiveGame::Solar(game) = active_game {
-                game.select_body(index);
-            }
+        if let Some(ActiveGame::Crossy(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.set_half_width(half_width);
         }
     });
 }
 
 #[wasm_bindgen]
-pub fn toggle_solar_temperature_unit() {
+pub fn get_crossy_intensity() -> f32 {
     CURRENT_GAME.with(|g| {
-        if let Some(active_game) = g.borrow_mut().as_mut() {
-            if let ActiveGame::Solar(game) = active_game {
-                game.toggle_temperature_unit();
-            }
+        match g.borrow().as_ref().map(|current| &current.game) {
+            Some(ActiveGame::Crossy(game)) => game.intensity,
+            _ => 0.0,
+        }
+    })
+}
+
+#[wasm_bindgen]
+pub fn on_game_event(callback: js_sys::Function) {
+    events::set_callback(Some(callback));
+}
+
+// Pages drawing their own score from score, coins and game_over events can turn off
+// the #score and #gameover updates
+#[wasm_bindgen]
+pub fn set_dom_ui(enabled: bool) {
+    DOM_UI.with(|d| d.set(enabled));
+    SHOWN_UI.with(|s| s.set(None));
+}
+
+// Called with a message when a game fails to start, a lost context can't be restored or
+// the engine panics
+#[wasm_bindgen]
+pub fn set_error_callback(callback: js_sys::Function) {
+    events::set_error_callback(Some(callback));
+}
+
+// Called with (stage, loaded, total, url) as a game loads: "config", "car_model" and
+// sounds in bytes with the url being fetched, "texture" in images, then "ready" once
+// the game runs with all its textures in. Total is 0 while a download's size is unknown
+#[wasm_bindgen]
+pub fn set_loading_callback(callback: js_sys::Function) {
+    assets::set_loading_callback(Some(callback));
+}
+
+#[wasm_bindgen]
+pub fn load_solar_system(sim_type: &str) -> Result<(), JsValue> {
+    reported(create_solar_system(sim_type))
+}
+
+fn create_solar_system(sim_type: &str) -> Result<(), JsValue> {
+    let canvas = next_canvas()?;
+    let gl = get_gl(&canvas)?;
+    let renderer = Renderer::new(gl)?;
+    
+    let system_type = match sim_type {
+        "black_hole" => SystemType::BlackHole,
+        "sirius" => SystemType::Sirius,
+        _ => SystemType::Solar,
+    };
+    let game = SolarSystem::new(renderer, system_type);
+    
+    overlay::clear();
+    CURRENT_GAME.with(|g| {
+        *g.borrow_mut() = Some(CurrentGame { game: ActiveGame::Solar(game), canvas: canvas.clone() });
+    });
+    
+    // Ensure loop is running (idempotent)
+    start_game_loop(&canvas)?;
+    
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn start_solar_system() -> Result<(), JsValue> {
+    load_solar_system("sun")
+}
+
+#[wasm_bindgen]
+pub fn start_minecraft() -> Result<(), JsValue> {
+    reported(create_minecraft())
+}
+
+// Only the key_bindings section, Minecraft has no use for the rest of the config
+#[derive(serde::Deserialize)]
+struct BindingsConfig {
+    #[serde(default)]
+    key_bindings: GameBindings,
+}
+
+// Minecraft starts synchronously, its bindings arrive a moment later
+fn load_key_bindings() {
+    wasm_bindgen_futures::spawn_local(async {
+        match assets::fetch_json::<BindingsConfig>("config", "/assets/config.json").await {
+            Ok(config) => key_bindings::set(config.key_bindings),
+            Err(e) => web_sys::console::error_2(&"Key bindings not loaded, using defaults:".into(), &e),
+        }
+    });
+}
+
+fn create_minecraft() -> Result<(), JsValue> {
+    load_key_bindings();
+    let canvas = next_canvas()?;
+    let gl = get_gl(&canvas)?;
+    let renderer = Renderer::new(gl)?;
+    let game = Minecraft::new(renderer);
+    
+    overlay::clear();
+    CURRENT_GAME.with(|g| {
+        *g.borrow_mut() = Some(CurrentGame { game: ActiveGame::Minecraft(game), canvas: canvas.clone() });
+    });
+    
+    start_game_loop(&canvas)?;
+    Ok(())
+}
+
+// Metadata of every saved Minecraft world as a JSON array, most recently played first
+#[wasm_bindgen]
+pub fn list_worlds() -> String {
+    serde_json::to_string(&world_save::list()).unwrap_or_else(|_| "[]".to_string())
+}
+
+#[wasm_bindgen]
+pub fn create_world(name: String, seed: u32, options_json: String) -> Result<(), JsValue> {
+    load_key_bindings();
+    let canvas = next_canvas()?;
+    let gl = get_gl(&canvas)?;
+    let renderer = Renderer::new(gl)?;
+    let game = Minecraft::create_world(renderer, &name, seed, &options_json).map_err(|e| JsValue::from_str(&e))?;
+
+    overlay::clear();
+    CURRENT_GAME.with(|g| {
+        *g.borrow_mut() = Some(CurrentGame { game: ActiveGame::Minecraft(game), canvas: canvas.clone() });
+    });
+
+    start_game_loop(&canvas)?;
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn load_world(name: String) -> Result<(), JsValue> {
+    load_key_bindings();
+    let canvas = next_canvas()?;
+    let gl = get_gl(&canvas)?;
+    let renderer = Renderer::new(gl)?;
+    let game = Minecraft::load_world(renderer, &name).map_err(|e| JsValue::from_str(&e))?;
+
+    overlay::clear();
+    CURRENT_GAME.with(|g| {
+        *g.borrow_mut() = Some(CurrentGame { game: ActiveGame::Minecraft(game), canvas: canvas.clone() });
+    });
+
+    start_game_loop(&canvas)?;
+    Ok(())
+}
+
+#[wasm_bindgen]
+pub fn save_world() -> Result<(), JsValue> {
+    CURRENT_GAME.with(|g| {
+        match g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            Some(ActiveGame::Minecraft(game)) => game.save_world().map_err(|e| JsValue::from_str(&e)),
+            _ => Ok(()),
+        }
+    })
+}
+
+#[wasm_bindgen]
+pub fn delete_world(name: String) -> Result<(), JsValue> {
+    world_save::delete(&name).map_err(|e| JsValue::from_str(&e))?;
+    CURRENT_GAME.with(|g| {
+        if let Some(ActiveGame::Minecraft(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.forget_world(&name);
+        }
+    });
+    Ok(())
+}
+
+fn is_solar() -> bool {
+    CURRENT_GAME.with(|g| matches!(g.borrow().as_ref().map(|current| &current.game), Some(ActiveGame::Solar(_))))
+}
+
+fn find_touch(touches: &TouchList, id: i32) -> Option<Touch> {
+    (0..touches.length()).filter_map(|i| touches.get(i)).find(|touch| touch.identifier() == id)
+}
+
+// Forwards pointer input to the active game's camera controller, false if nothing took it
+fn dispatch_input(event: &InputEvent) -> bool {
+    CURRENT_GAME.with(|g| {
+        match g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            Some(ActiveGame::Solar(game)) => {
+                game.handle_pointer_event(event);
+                true
+            }
+            Some(ActiveGame::Minecraft(game)) => {
+                game.handle_pointer_event(event);
+                !matches!(event, InputEvent::Wheel { .. })
+            }
+            _ => false,
+        }
+    })
+}
+
+// Wheel delta per frame with a trigger fully down, about a 3x zoom each second
+const GAMEPAD_ZOOM_DELTA: f32 = 20.0;
+
+// Pad actions go through the same handlers as the keyboard and mouse
+fn dispatch_gamepad(active_game: &mut ActiveGame, pad: &GamepadFrame, dt: f32) {
+    match active_game {
+        ActiveGame::Crossy(game) => {
+            for action in pad.pressed.iter().filter_map(|action| key_action(*action)) {
+                game.apply_action(action);
+            }
+        }
+        ActiveGame::Solar(game) => {
+            if pad.zoom != 0.0 {
+                game.handle_pointer_event(&InputEvent::Wheel { delta: -pad.zoom * GAMEPAD_ZOOM_DELTA });
+            }
+        }
+        ActiveGame::Minecraft(game) => {
+            for action in &pad.pressed {
+                match action {
+                    GameAction::Primary => game.gamepad_use(0),
+                    GameAction::Secondary => game.gamepad_use(2),
+                    GameAction::Restart => {}
+                    action => {
+                        if let Some(action) = key_action(*action) {
+                            game.apply_action(action, true);
+                        }
+                    }
+                }
+            }
+            for action in pad.released.iter().filter_map(|action| key_action(*action)) {
+                game.apply_action(action, false);
+            }
+            game.gamepad_look(pad.look.0, pad.look.1, dt);
+        }
+    }
+}
+
+fn key_action(action: GameAction) -> Option<KeyAction> {
+    match action {
+        GameAction::Forward => Some(KeyAction::Forward),
+        GameAction::Back => Some(KeyAction::Back),
+        GameAction::Left => Some(KeyAction::Left),
+        GameAction::Right => Some(KeyAction::Right),
+        GameAction::Jump => Some(KeyAction::Jump),
+        GameAction::Restart => Some(KeyAction::Restart),
+        GameAction::Primary | GameAction::Secondary => None,
+    }
+}
+
+fn request_animation_frame(f: &Closure<dyn FnMut()>) -> i32 {
+    web_sys::window()
+        .unwrap()
+        .request_animation_frame(f.as_ref().unchecked_ref())
+        .unwrap()
+}
+
+fn update_ui(score: i32, coins: i32, high_score: i32, game_over: bool, power_up: Option<(PowerUpKind, i32)>) {
+    let shown = Some((score, coins, high_score, game_over, power_up));
+    if SHOWN_UI.with(|s| s.replace(shown)) == shown {
+        return;
+    }
+    if let Some(window) = web_sys::window() {
+        if let Some(document) = window.document() {
+            if let Some(score_el) = document.get_element_by_id("score") {
+                let power_up = match power_up {
+                    Some((PowerUpKind::Magnet, seconds)) => format!(" | Magnet {}s", seconds),
+                    Some((PowerUpKind::Shield, _)) => " | Shield".to_string(),
+                    None => String::new(),
+                };
+                score_el.set_inner_html(&format!("Score: {} | Coins: {} | Best: {}{}", score, coins, high_score.max(score), power_up));
+            }
+            if let Some(gameover_el) = document.get_element_by_id("gameover") {
+                if game_over {
+                    gameover_el.set_attribute("style", "display: block;").ok();
+                } else {
+                    gameover_el.set_attribute("style", "display: none;").ok();
+                }
+            }
+        }
+    }
+}
+
+// Only pages that have a #fps element show the counter, and only changes to what it
+// shows reach the DOM
+fn update_fps_ui() {
+    let Some(fps_el) = web_sys::window().and_then(|w| w.document()).and_then(|d| d.get_element_by_id("fps")) else {
+        return;
+    };
+    let stats = frame_stats::current();
+    // Rounded the way the text shows them
+    let shown = Some((
+        stats.fps.round() as i32,
+        (stats.frame_ms * 10.0).round() as i32,
+        (stats.update_ms * 10.0).round() as i32,
+        (stats.render_ms * 10.0).round() as i32,
+    ));
+    if SHOWN_FPS.with(|s| s.replace(shown)) == shown {
+        return;
+    }
+    fps_el.set_inner_html(&format!("{:.0} FPS | {:.1} ms (update {:.1}, render {:.1})", stats.fps, stats.frame_ms, stats.update_ms, stats.render_ms));
+}
+
+#[wasm_bindgen]
+pub fn set_touch_overlay(enabled: bool) {
+    CURRENT_GAME.with(|g| {
+        if let Some(ActiveGame::Crossy(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.set_touch_overlay(enabled);
+        }
+    });
+}
+
+// Resolves with a PNG Blob of a fresh frame of the current game, without the touch
+// buttons. The frame is read back right after it's drawn, so the default
+// cleared-after-compositing buffer is fine.
+#[wasm_bindgen]
+pub fn capture_screenshot() -> js_sys::Promise {
+    let canvas = draw_capture(true);
+    js_sys::Promise::new(&mut |resolve, reject| {
+        let Some(canvas) = &canvas else {
+            reject.call1(&JsValue::NULL, &JsValue::from_str("No game is running")).ok();
+            return;
+        };
+        let on_blob = Closure::once_into_js({
+            let reject = reject.clone();
+            move |blob: JsValue| {
+                // toBlob hands over null when the canvas can't be encoded
+                if blob.is_null() {
+                    reject.call1(&JsValue::NULL, &JsValue::from_str("The frame couldn't be encoded")).ok();
+                } else {
+                    resolve.call1(&JsValue::NULL, &blob).ok();
+                }
+            }
+        });
+        if let Err(e) = canvas.to_blob(on_blob.unchecked_ref()) {
+            reject.call1(&JsValue::NULL, &e).ok();
+        }
+    })
+}
+
+// PNG data URL of a freshly drawn frame, hide_ui leaves the touch buttons and overlay out
+#[wasm_bindgen]
+pub fn capture_frame(hide_ui: bool) -> Option<String> {
+    draw_capture(hide_ui)?.to_data_url().ok()
+}
+
+// Draws a fresh frame of the current game and hands back its canvas to read it from
+fn draw_capture(hide_ui: bool) -> Option<HtmlCanvasElement> {
+    CURRENT_GAME.with(|g| {
+        let mut current = g.borrow_mut();
+        let CurrentGame { game, canvas } = current.as_mut()?;
+        let (css_width, css_height) = fit_canvas(canvas).unwrap_or((1, 1));
+        match game {
+            ActiveGame::Crossy(game) => game.draw_capture(hide_ui),
+            ActiveGame::Solar(game) => {
+                game.render(css_width, css_height);
+                if !hide_ui {
+                    overlay::draw(game.renderer());
+                }
+            }
+            ActiveGame::Minecraft(game) => {
+                game.render(css_width, css_height);
+                if !hide_ui {
+                    overlay::draw(game.renderer());
+                }
+            }
+        }
+        Some(canvas.clone())
+    })
+}
+
+// Buttons press an action like its key would. Solar has no actions and gets the arrow key.
+fn touch_action(action: KeyAction, solar_key: &str) {
+    CURRENT_GAME.with(|g| {
+        if let Some(active_game) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            match active_game {
+                ActiveGame::Crossy(game) => { game.apply_action(action); },
+                ActiveGame::Solar(game) => game.handle_input(solar_key),
+                ActiveGame::Minecraft(game) => game.apply_action(action, true),
+            }
+        }
+    });
+}
+
+#[wasm_bindgen]
+pub fn touch_left() {
+    touch_action(KeyAction::Left, "ArrowLeft");
+}
+
+#[wasm_bindgen]
+pub fn touch_right() {
+    touch_action(KeyAction::Right, "ArrowRight");
+}
+
+#[wasm_bindgen]
+pub fn touch_backward() {
+    touch_action(KeyAction::Back, "ArrowDown");
+}
+
+#[wasm_bindgen]
+pub fn touch_forward() {
+    touch_action(KeyAction::Forward, "ArrowUp");
+}
+
+#[wasm_bindgen]
+pub fn touch_restart() {
+    touch_action(KeyAction::Restart, "ArrowDown");
+}
+
+#[wasm_bindgen]
+pub fn activate_god_mode() {
+    CURRENT_GAME.with(|g| {
+        if let Some(ActiveGame::Crossy(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.debug_advance();
+        }
+    });
+}
+
+#[wasm_bindgen]
+pub fn set_solar_date(timestamp: f64) {
+    CURRENT_GAME.with(|g| {
+        if let Some(ActiveGame::Solar(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.set_date_from_timestamp(timestamp);
+        }
+    });
+}
+
+#[wasm_bindgen]
+pub fn step_solar_simulation(seconds: f64) {
+    CURRENT_GAME.with(|g| {
+        if let Some(ActiveGame::Solar(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.step_simulation(seconds);
+        }
+    });
+}
+
+#[wasm_bindgen]
+pub fn get_body_position(index: usize) -> Option<String> {
+    CURRENT_GAME.with(|g| {
+        match g.borrow().as_ref().map(|current| &current.game) {
+            Some(ActiveGame::Solar(system)) => system.body_position_json(index),
+            _ => None,
+        }
+    })
+}
+
+#[wasm_bindgen]
+pub fn set_solar_time_scale(scale: f32) {
+    CURRENT_GAME.with(|g| {
+        if let Some(ActiveGame::Solar(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.set_time_scale(scale);
+        }
+    });
+}
+
+#[wasm_bindgen]
+pub fn set_solar_time_scale_smooth(target: f32, duration_s: f64) {
+    CURRENT_GAME.with(|g| {
+        if let Some(ActiveGame::Solar(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.set_time_scale_smooth(target, duration_s);
+        }
+    });
+}
+
+#[wasm_bindgen]
+pub fn save_time_bookmark(slot: usize) -> Result<(), JsValue> {
+    CURRENT_GAME.with(|g| {
+        match g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            Some(ActiveGame::Solar(game)) => game.save_time_bookmark(slot).map_err(|e| JsValue::from_str(&e)),
+            _ => Ok(()),
+        }
+    })
+}
+
+#[wasm_bindgen]
+pub fn restore_time_bookmark(slot: usize) -> Result<(), JsValue> {
+    CURRENT_GAME.with(|g| {
+        match g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            Some(ActiveGame::Solar(game)) => game.restore_time_bookmark(slot).map_err(|e| JsValue::from_str(&e)),
+            _ => Ok(()),
+        }
+    })
+}
+
+// JSON array of the five slots, null where nothing is saved
+#[wasm_bindgen]
+pub fn get_time_bookmarks() -> Option<String> {
+    CURRENT_GAME.with(|g| {
+        match g.borrow().as_ref().map(|current| &current.game) {
+            Some(ActiveGame::Solar(game)) => Some(game.time_bookmarks_json()),
+            _ => None,
+        }
+    })
+}
+
+#[wasm_bindgen]
+pub fn select_solar_body(index: usize) {
+    CURRENT_GAME.with(|g| {
+        if let Some(ActiveGame::Solar(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.select_body(index);
+        }
+    });
+}
+
+#[wasm_bindgen]
+pub fn toggle_solar_temperature_unit() {
+    CURRENT_GAME.with(|g| {
+        if let Some(ActiveGame::Solar(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.toggle_temperature_unit();
+        }
+    });
+}
+
+#[wasm_bindgen]
+pub fn set_accessibility_palette(name: &str) {
+    CURRENT_GAME.with(|g| {
+        if let Some(ActiveGame::Crossy(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.set_palette(name);
+        }
+    });
+}
+
+#[wasm_bindgen]
+pub fn set_crossy_rain(raining: bool) {
+    CURRENT_GAME.with(|g| {
+        if let Some(ActiveGame::Crossy(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.set_rain(raining);
+        }
+    });
+}
+
+#[wasm_bindgen]
+pub fn set_crossy_countdown(seconds: f32, allow_skip: bool) {
+    CURRENT_GAME.with(|g| {
+        if let Some(ActiveGame::Crossy(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.set_countdown(seconds, allow_skip);
+        }
+    });
+}
+
+#[wasm_bindgen]
+pub fn compare_solar_bodies(index_a: usize, index_b: usize) -> Option<String> {
+    CURRENT_GAME.with(|g| {
+        match g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            Some(ActiveGame::Solar(game)) => game.compare_bodies(index_a, index_b),
+            _ => None,
+        }
+    })
+}
+
+#[wasm_bindgen]
+pub fn exit_solar_comparison() {
+    CURRENT_GAME.with(|g| {
+        if let Some(ActiveGame::Solar(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.exit_comparison();
+        }
+    });
+}
+
+#[wasm_bindgen]
+pub fn set_crosshair_style(json: &str) -> Result<(), JsValue> {
+    CURRENT_GAME.with(|g| {
+        match g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            Some(ActiveGame::Minecraft(game)) => game.set_crosshair_style(json).map_err(|e| JsValue::from_str(&e)),
+            _ => Ok(()),
+        }
+    })
+}
+
+#[wasm_bindgen]
+pub fn get_minecraft_physics() -> Option<String> {
+    CURRENT_GAME.with(|g| {
+        match g.borrow().as_ref().map(|current| &current.game) {
+            Some(ActiveGame::Minecraft(game)) => Some(game.physics_json()),
+            _ => None,
+        }
+    })
+}
+
+#[wasm_bindgen]
+pub fn set_minecraft_physics(json: &str) -> Result<(), JsValue> {
+    CURRENT_GAME.with(|g| {
+        match g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            Some(ActiveGame::Minecraft(game)) => game.set_physics(json).map_err(|e| JsValue::from_str(&e)),
+            _ => Ok(()),
+        }
+    })
+}
+
+#[wasm_bindgen]
+pub fn get_minecraft_graphics() -> Option<String> {
+    CURRENT_GAME.with(|g| {
+        match g.borrow().as_ref().map(|current| &current.game) {
+            Some(ActiveGame::Minecraft(game)) => Some(game.graphics_tier()),
+            _ => None,
+        }
+    })
+}
+
+// "low", "medium" or "high"
+#[wasm_bindgen]
+pub fn set_minecraft_graphics(tier: &str) -> Result<(), JsValue> {
+    CURRENT_GAME.with(|g| {
+        match g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            Some(ActiveGame::Minecraft(game)) => game.set_graphics(tier).map_err(|e| JsValue::from_str(&e)),
+            _ => Ok(()),
+        }
+    })
+}
+
+// A JSON array of block defs, see assets/blocks.json. Call it before starting or loading a
+// world that uses them; blocks stay registered until the page reloads.
+#[wasm_bindgen]
+pub fn register_minecraft_blocks(json: &str) -> Result<(), JsValue> {
+    CURRENT_GAME.with(|g| {
+        match g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            Some(ActiveGame::Minecraft(game)) => game.register_blocks(json),
+            _ => block_registry::register(json),
+        }
+        .map_err(|e| JsValue::from_str(&e))
+    })
+}
+
+#[wasm_bindgen]
+pub fn reset_minecraft_physics() {
+    CURRENT_GAME.with(|g| {
+        if let Some(ActiveGame::Minecraft(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.reset_physics();
+        }
+    });
+}
+
+#[wasm_bindgen]
+pub fn set_body_orbit(index: usize, json: &str) -> Result<(), JsValue> {
+    CURRENT_GAME.with(|g| {
+        match g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            Some(ActiveGame::Solar(game)) => game.set_body_orbit(index, json).map_err(|e| JsValue::from_str(&e)),
+            _ => Ok(()),
+        }
+    })
+}
+
+#[wasm_bindgen]
+pub fn start_solar_tour(stops_json: Option<String>) -> Result<(), JsValue> {
+    CURRENT_GAME.with(|g| {
+        match g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            Some(ActiveGame::Solar(game)) => game.start_tour(stops_json.as_deref()).map_err(|e| JsValue::from_str(&e)),
+            _ => Ok(()),
+        }
+    })
+}
+
+#[wasm_bindgen]
+pub fn craft(recipe_id: &str) -> Result<(), JsValue> {
+    CURRENT_GAME.with(|g| {
+        match g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            Some(ActiveGame::Minecraft(game)) => game.craft(recipe_id).map_err(|e| JsValue::from_str(&e)),
+            _ => Ok(()),
+        }
+    })
+}
+
+#[wasm_bindgen]
+pub fn get_recipes() -> String {
+    serde_json::to_string(crate::game::crafting::RECIPES).unwrap_or_default()
+}
+
+#[wasm_bindgen]
+pub fn get_inventory() -> Option<String> {
+    CURRENT_GAME.with(|g| {
+        match g.borrow().as_ref().map(|current| &current.game) {
+            Some(ActiveGame::Minecraft(game)) => Some(game.inventory_json()),
+            _ => None,
+        }
+    })
+}
+
+#[wasm_bindgen]
+pub fn set_observer_location(latitude: f32, longitude: f32) {
+    CURRENT_GAME.with(|g| {
+        if let Some(ActiveGame::Solar(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.set_observer_location(latitude, longitude);
+        }
+    });
+}
+
+#[wasm_bindgen]
+pub fn view_from_surface(enabled: bool) -> Result<(), JsValue> {
+    CURRENT_GAME.with(|g| {
+        match g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            Some(ActiveGame::Solar(game)) => game.view_from_surface(enabled).map_err(|e| JsValue::from_str(&e)),
+            _ => Ok(()),
+        }
+    })
+}
+
+// Printable SVG of the current Solar view
+#[wasm_bindgen]
+pub fn export_solar_svg() -> Option<String> {
+    CURRENT_GAME.with(|g| {
+        match g.borrow().as_ref().map(|current| &current.game) {
+            Some(ActiveGame::Solar(game)) => game.export_svg(),
+            _ => None,
+        }
+    })
+}
+
+// JSON report of each planet's angular and radial error against the embedded reference
+// positions. Rejects when Solar isn't running the Solar System.
+#[wasm_bindgen]
+pub fn run_accuracy_check() -> Result<String, JsValue> {
+    CURRENT_GAME.with(|g| {
+        match g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            Some(ActiveGame::Solar(game)) => game.run_accuracy_check().map_err(|e| JsValue::from_str(&e)),
+            _ => Err(JsValue::from_str("The accuracy check needs the Solar System")),
+        }
+    })
+}
+
+#[wasm_bindgen]
+pub fn set_auto_slowmo(enabled: bool) {
+    CURRENT_GAME.with(|g| {
+        if let Some(ActiveGame::Solar(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.set_auto_slowmo(enabled);
+        }
+    });
+}
+
+#[wasm_bindgen]
+pub fn set_slowmo_config(json: &str) -> Result<(), JsValue> {
+    CURRENT_GAME.with(|g| {
+        match g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            Some(ActiveGame::Solar(game)) => game.set_slowmo_config(json).map_err(|e| JsValue::from_str(&e)),
+            _ => Ok(()),
+        }
+    })
+}
+
+#[wasm_bindgen]
+pub fn set_earth_markers(enabled: bool) {
+    CURRENT_GAME.with(|g| {
+        if let Some(ActiveGame::Solar(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.set_earth_markers(enabled);
+        }
+    });
+}
+
+#[wasm_bindgen]
+pub fn set_audio_settings(enabled: bool, volume: f32) {
+    Settings::update(|s| {
+        s.audio_enabled = enabled;
+        s.audio_volume = volume.clamp(0.0, 1.0);
+    });
+    audio::apply_settings();
+}
+
+// Silences every game until unmuted, without touching the saved settings
+#[wasm_bindgen]
+pub fn set_muted(muted: bool) {
+    audio::set_muted(muted);
+}
+
+// Replaces a built-in sound: "coin" and "death" in Crossy, "break" and "place" in Minecraft
+#[wasm_bindgen]
+pub async fn load_sound(name: String, url: String) -> Result<(), JsValue> {
+    audio::load_sound(&name, &url).await
+}
+
+#[wasm_bindgen]
+pub fn set_solar_validity_window(years: f32) {
+    CURRENT_GAME.with(|g| {
+        if let Some(ActiveGame::Solar(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.set_validity_window(years);
+        }
+    });
+}
+
+#[wasm_bindgen]
+pub fn solar_elements_stale() -> bool {
+    CURRENT_GAME.with(|g| {
+        match g.borrow().as_ref().map(|current| &current.game) {
+            Some(ActiveGame::Solar(game)) => game.elements_stale(),
+            _ => false,
+        }
+    })
+}
+
+#[wasm_bindgen]
+pub fn toggle_minecraft_map() {
+    CURRENT_GAME.with(|g| {
+        if let Some(ActiveGame::Minecraft(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.toggle_map();
+        }
+    });
+}
+
+#[wasm_bindgen]
+pub fn set_solar_ambient(enabled: bool) {
+    CURRENT_GAME.with(|g| {
+        if let Some(ActiveGame::Solar(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.set_ambient(enabled);
+        }
+    });
+}
+
+#[wasm_bindgen]
+pub fn set_solar_sonification(enabled: bool) {
+    CURRENT_GAME.with(|g| {
+        if let Some(ActiveGame::Solar(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.set_sonification(enabled);
+        }
+    });
+}
+
+#[wasm_bindgen]
+pub fn set_solar_trail_min_pixels(pixels: f32) {
+    CURRENT_GAME.with(|g| {
+        if let Some(ActiveGame::Solar(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.set_trail_min_pixels(pixels);
+        }
+    });
+}
+
+#[wasm_bindgen]
+pub fn set_stereo_mode(mode: &str) -> Result<(), JsValue> {
+    CURRENT_GAME.with(|g| {
+        match g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            Some(ActiveGame::Solar(game)) => game.set_stereo_mode(mode).map_err(|e| JsValue::from_str(&e)),
+            _ => Ok(()),
+        }
+    })
+}
+
+#[wasm_bindgen]
+pub fn set_stereo_eye_separation(fraction: f32) {
+    CURRENT_GAME.with(|g| {
+        if let Some(ActiveGame::Solar(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.set_eye_separation(fraction);
+        }
+    });
+}
+
+#[wasm_bindgen]
+pub fn set_spawn_here() {
+    CURRENT_GAME.with(|g| {
+        if let Some(ActiveGame::Minecraft(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.set_spawn_here();
+        }
+    });
+}
+
+#[wasm_bindgen]
+pub fn set_keep_inventory(keep: bool) {
+    CURRENT_GAME.with(|g| {
+        if let Some(ActiveGame::Minecraft(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.set_keep_inventory(keep);
+        }
+    });
+}
+
+#[wasm_bindgen]
+pub fn set_view_smoothing(enabled: bool) {
+    CURRENT_GAME.with(|g| {
+        if let Some(ActiveGame::Minecraft(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.set_view_smoothing(enabled);
+        }
+    });
+}
+
+#[wasm_bindgen]
+pub fn set_creative_mode(creative: bool) {
+    CURRENT_GAME.with(|g| {
+        if let Some(ActiveGame::Minecraft(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.set_creative(creative);
+        }
+    });
+}
+
+// Co-op hooks: the host page carries edits and player state between peers itself
+#[wasm_bindgen]
+pub fn apply_remote_edit(json: &str) -> Result<(), JsValue> {
+    CURRENT_GAME.with(|g| {
+        match g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            Some(ActiveGame::Minecraft(game)) => game.apply_remote_edits(json).map_err(|e| JsValue::from_str(&e)),
+            _ => Ok(()),
+        }
+    })
+}
+
+#[wasm_bindgen]
+pub fn get_pending_local_edits() -> Option<String> {
+    CURRENT_GAME.with(|g| {
+        match g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            Some(ActiveGame::Minecraft(game)) => Some(game.drain_local_edits()),
+            _ => None,
+        }
+    })
+}
+
+#[wasm_bindgen]
+pub fn set_remote_player_state(json: &str) -> Result<(), JsValue> {
+    CURRENT_GAME.with(|g| {
+        match g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            Some(ActiveGame::Minecraft(game)) => game.set_remote_player(json).map_err(|e| JsValue::from_str(&e)),
+            _ => Ok(()),
+        }
+    })
+}
+
+#[wasm_bindgen]
+pub fn set_fluids_enabled(enabled: bool) {
+    CURRENT_GAME.with(|g| {
+        if let Some(ActiveGame::Minecraft(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.set_fluids_enabled(enabled);
+        }
+    });
+}
+
+#[wasm_bindgen]
+pub fn get_solar_body_info(index: usize) -> Option<String> {
+    CURRENT_GAME.with(|g| {
+        match g.borrow().as_ref().map(|current| &current.game) {
+            Some(ActiveGame::Solar(system)) => system.body_info_json(index),
+            _ => None,
+        }
+    })
+}
+
+#[wasm_bindgen]
+pub fn set_chunk_budget_ms(budget_ms: f64) {
+    CURRENT_GAME.with(|g| {
+        if let Some(ActiveGame::Minecraft(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.set_chunk_budget(budget_ms);
+        }
+    });
+}
+
+#[wasm_bindgen]
+pub fn set_central_luminosity(solar_units: f64) {
+    CURRENT_GAME.with(|g| {
+        if let Some(ActiveGame::Solar(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.set_central_luminosity(solar_units);
+        }
+    });
+}
+
+#[wasm_bindgen]
+pub fn set_live_temperatures(enabled: bool) {
+    CURRENT_GAME.with(|g| {
+        if let Some(ActiveGame::Solar(game)) = g.borrow_mut().as_mut().map(|current| &mut current.game) {
+            game.set_live_temperatures(enabled);
         }
     });
 }
@@ -0,0 +1,30 @@
+// Runs in a browser: wasm-pack test --headless --chrome
+#![cfg(target_arch = "wasm32")]
+
+use wasm_bindgen::JsCast;
+use wasm_bindgen_test::*;
+use web_sys::HtmlCanvasElement;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+// Guards the Kepler solver, the epoch handling and the element set against the embedded
+// reference positions, through the same export the page calls. The limits are the ones
+// stored beside the table, measured from the current elements.
+#[wasm_bindgen_test]
+fn planets_stay_within_their_stored_tolerances() {
+    let document = web_sys::window().unwrap().document().unwrap();
+    // The game looks its canvas up by id
+    let canvas: HtmlCanvasElement = document.create_element("canvas").unwrap().dyn_into().unwrap();
+    canvas.set_id("canvas");
+    document.body().unwrap().append_child(&canvas).unwrap();
+    crossy_road_clone::start_solar_system().unwrap();
+
+    let report: serde_json::Value = serde_json::from_str(&crossy_road_clone::run_accuracy_check().unwrap()).unwrap();
+
+    let bodies = report["bodies"].as_array().unwrap();
+    assert_eq!(bodies.len(), 8, "missing: {}", report["missing"]);
+    for body in bodies {
+        assert!(body["passed"].as_bool().unwrap(), "{} regressed: {}", body["name"], body);
+    }
+    assert!(report["passed"].as_bool().unwrap());
+}
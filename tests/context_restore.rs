@@ -0,0 +1,65 @@
+// Runs in a browser: wasm-pack test --headless --chrome
+#![cfg(target_arch = "wasm32")]
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use wasm_bindgen_test::*;
+use web_sys::HtmlCanvasElement;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+// Resolves the next time the canvas fires the event
+fn next_event(canvas: &HtmlCanvasElement, name: &str) -> JsFuture {
+    let canvas = canvas.clone();
+    let name = name.to_string();
+    JsFuture::from(js_sys::Promise::new(&mut |resolve, _| {
+        canvas.add_event_listener_with_callback(&name, &resolve).unwrap();
+    }))
+}
+
+fn call(target: &JsValue, method: &str) {
+    let function: js_sys::Function = js_sys::Reflect::get(target, &method.into()).unwrap().dyn_into().unwrap();
+    function.call0(target).unwrap();
+}
+
+#[wasm_bindgen_test]
+async fn game_draws_again_after_the_context_is_restored() {
+    let document = web_sys::window().unwrap().document().unwrap();
+    let canvas: HtmlCanvasElement = document.create_element("canvas").unwrap().dyn_into().unwrap();
+    canvas.set_width(320);
+    canvas.set_height(240);
+    document.body().unwrap().append_child(&canvas).unwrap();
+
+    let errors = Rc::new(RefCell::new(Vec::new()));
+    let recorded = errors.clone();
+    let on_error = Closure::<dyn FnMut(String)>::new(move |message| recorded.borrow_mut().push(message));
+    crossy_road_clone::set_error_callback(on_error.as_ref().unchecked_ref::<js_sys::Function>().clone());
+
+    crossy_road_clone::set_canvas(canvas.clone());
+    crossy_road_clone::start_solar_system().unwrap();
+
+    // The game made a webgl2 or webgl context, asking again for either returns it
+    let gl = canvas.get_context("webgl2").unwrap().or_else(|| canvas.get_context("webgl").unwrap()).unwrap();
+    let get_extension: js_sys::Function = js_sys::Reflect::get(&gl, &"getExtension".into()).unwrap().dyn_into().unwrap();
+    let lose_context = get_extension.call1(&gl, &"WEBGL_lose_context".into()).unwrap();
+    assert!(!lose_context.is_null(), "WEBGL_lose_context is unavailable");
+
+    let lost = next_event(&canvas, "webglcontextlost");
+    call(&lose_context, "loseContext");
+    lost.await.unwrap();
+
+    let restored = next_event(&canvas, "webglcontextrestored");
+    call(&lose_context, "restoreContext");
+    restored.await.unwrap();
+
+    let blob = JsFuture::from(crossy_road_clone::capture_screenshot()).await.unwrap();
+    let size = js_sys::Reflect::get(&blob, &"size".into()).unwrap().as_f64().unwrap_or(0.0);
+    assert!(size > 0.0, "the restored context drew nothing");
+    assert!(errors.borrow().is_empty(), "{:?}", errors.borrow());
+
+    crossy_road_clone::stop_game();
+}